@@ -38,7 +38,7 @@ fn main() {
         Ok(path) => {
             print!("   Path: ");
             for (i, node) in path.iter().enumerate() {
-                print!("City {} -> ", (65 + node) as u8 as char);
+                print!("{} -> ", graph.node(*node).unwrap().label);
                 if i == path.len() - 1 {
                     println!("\n   Hops: {}", path.len() - 1);
                 }
@@ -53,7 +53,7 @@ fn main() {
         Ok((path, cost)) => {
             print!("   Path: ");
             for node in &path {
-                print!("City {} -> ", (65 + node) as u8 as char);
+                print!("{} -> ", graph.node(*node).unwrap().label);
             }
             println!("\n   Distance: {:.1}km", cost);
         }
@@ -68,7 +68,7 @@ fn main() {
         Ok(path) => {
             print!("   Path: ");
             for node in &path {
-                print!("City {} -> ", (65 + node) as u8 as char);
+                print!("{} -> ", graph.node(*node).unwrap().label);
             }
             println!();
         }
@@ -87,7 +87,7 @@ fn main() {
     match graph.neighbors(city_c) {
         Ok(neighbors) => {
             for neighbor in neighbors {
-                println!("   -> City {}", (65 + neighbor) as u8 as char);
+                println!("   -> {}", graph.node(neighbor).unwrap().label);
             }
         }
         Err(e) => println!("   Error: {}", e),
@@ -1,5 +1,6 @@
 //! Example: Fraud detection using anomaly detection
 
+use zipgraph_core::centrality::{degree_centrality, pagerank, CentralityReport};
 use zipgraph_core::Graph;
 use zipgraph_ml::AnomalyDetector;
 
@@ -33,8 +34,8 @@ fn main() {
     
     // Fraud pattern 1: Hub account (receives from many accounts)
     let fraud_hub = graph.add_node_simple("Fraud_Hub");
-    for i in 0..7 {
-        graph.add_edge(accounts[i], fraud_hub, 10.0).unwrap();
+    for &account in accounts.iter().take(7) {
+        graph.add_edge(account, fraud_hub, 10.0).unwrap();
     }
 
     // Fraud pattern 2: Isolated money mule
@@ -85,38 +86,35 @@ fn main() {
 
     // Analyze specific accounts
     println!("📈 Account Analysis:");
-    
-    let suspicious_accounts = vec![
-        (fraud_hub, "Fraud_Hub"),
-        (money_mule, "Money_Mule"),
-    ];
-
-    for (account_id, name) in suspicious_accounts {
-        if let Ok(degree) = graph.degree(account_id) {
-            let neighbors = graph.neighbors(account_id).unwrap();
-            
-            println!("\n  {}:", name);
-            println!("    Connections: {}", degree);
-            println!("    Connected to: {} accounts", neighbors.len());
-            
-            // Calculate risk score based on degree anomaly
-            let avg_degree = graph.node_count() as f64 * 2.0 / graph.node_count() as f64;
-            let risk_score = if degree > avg_degree as usize {
-                ((degree as f64 / avg_degree) * 50.0).min(100.0)
-            } else {
-                10.0
-            };
-            
-            println!("    Risk Score: {:.0}/100", risk_score);
-            
-            if risk_score > 70.0 {
-                println!("    ⚠️  HIGH RISK - Review required");
-            } else if risk_score > 40.0 {
-                println!("    ⚡ MEDIUM RISK - Monitor closely");
-            }
+    let degree = degree_centrality(&graph).unwrap();
+    let pagerank_scores = pagerank(&graph, 0.85, 100, 1e-6).unwrap();
+    let ranking = CentralityReport::from_scores(&graph, &degree, "degree")
+        .join(CentralityReport::from_scores(&graph, &pagerank_scores, "pagerank"))
+        .unwrap();
+
+    for account_id in [fraud_hub, money_mule] {
+        let row = ranking.row(account_id).unwrap();
+        // Risk grows with how far the account stands out from the rest
+        let risk_score = ranking.percentile_of(account_id).unwrap();
+
+        println!("\n  {}:", row.label);
+        println!("    Rank: {} of {}", row.rank, ranking.rows().len());
+        println!("    Degree centrality: {:.3}", row.scores[0]);
+        println!("    PageRank: {:.4}", row.scores[1]);
+        println!("    Risk Score: {:.0}/100", risk_score);
+
+        if risk_score > 70.0 {
+            println!("    ⚠️  HIGH RISK - Review required");
+        } else if risk_score > 40.0 {
+            println!("    ⚡ MEDIUM RISK - Monitor closely");
         }
     }
 
+    println!("\n  Most connected accounts:");
+    for row in ranking.top(3) {
+        println!("    {}. {} ({:.3})", row.rank, row.label, row.scores[0]);
+    }
+
     println!("\n💡 Key Insights:");
     println!("  • Graph-based fraud detection spots unusual transaction patterns");
     println!("  • ML models learn normal behavior automatically");
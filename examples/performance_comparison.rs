@@ -8,7 +8,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::fs;
 use std::time::Instant;
-use zipgraph_core::{centrality::*, parallel::*, Graph};
+use zipgraph_core::{centrality::*, Graph};
 
 #[derive(Debug, Serialize, Deserialize)]
 struct BenchmarkResult {
@@ -33,9 +33,7 @@ fn benchmark_graph_creation(n_nodes: usize, n_edges: usize) -> (f64, Graph) {
     let mut rng = rand::thread_rng();
 
     // Add nodes
-    for i in 0..n_nodes {
-        graph.add_node_simple(format!("Node{}", i));
-    }
+    let ids: Vec<_> = (0..n_nodes).map(|i| graph.add_node_simple(format!("Node{}", i))).collect();
 
     // Add random edges
     let mut edges_added = 0;
@@ -47,7 +45,7 @@ fn benchmark_graph_creation(n_nodes: usize, n_edges: usize) -> (f64, Graph) {
         
         if u != v && !attempted.contains(&(u.min(v), u.max(v))) {
             attempted.insert((u.min(v), u.max(v)));
-            if graph.add_edge(u, v, rng.gen::<f64>()).is_ok() {
+            if graph.add_edge(ids[u], ids[v], rng.gen::<f64>()).is_ok() {
                 edges_added += 1;
             }
         }
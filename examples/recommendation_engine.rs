@@ -61,11 +61,12 @@ fn main() {
 
     // Find similar users using embeddings
     println!("\n👥 User Similarity Analysis:");
-    for user_id in 0..5 {
+    let users = [user1, user2, user3, user4, user5];
+    for (i, &user_id) in users.iter().enumerate() {
         let user_name = graph.node(user_id).unwrap().label.clone();
         
         // Calculate similarity with other users
-        for other_id in (user_id + 1)..5 {
+        for &other_id in &users[i + 1..] {
             let other_name = graph.node(other_id).unwrap().label.clone();
             let similarity = embeddings.cosine_similarity(user_id, other_id).unwrap();
             
@@ -86,7 +87,7 @@ fn main() {
     
     // Find items Alice hasn't purchased yet
     let alice_neighbors = optimizer.neighbors(&graph, user1).unwrap();
-    let alice_purchased: Vec<_> = alice_neighbors.iter().copied().collect();
+    let alice_purchased: Vec<_> = alice_neighbors.to_vec();
     
     println!("  Alice has purchased:");
     for &item_id in &alice_purchased {
@@ -97,7 +98,7 @@ fn main() {
     println!("\n  Recommended items (based on similar users):");
     
     // Find items that similar users purchased
-    for item_id in 5..10 {
+    for item_id in [item1, item2, item3, item4, item5] {
         if !alice_purchased.contains(&item_id) {
             let item = graph.node(item_id).unwrap();
             
@@ -1,5 +1,8 @@
 //! Example: Social network analysis and community detection
 
+use zipgraph_core::centrality::{
+    betweenness_centrality, degree_centrality, CentralityReport,
+};
 use zipgraph_core::Graph;
 use zipgraph_ml::AlgorithmSelector;
 use zipgraph_optimizer::QueryOptimizer;
@@ -105,16 +108,21 @@ fn main() {
 
     // Identify influencers
     println!("\n🌟 Influencer Ranking:");
-    let mut user_degrees: Vec<_> = user_ids
-        .iter()
-        .enumerate()
-        .map(|(idx, &id)| (users[idx], graph.degree(id).unwrap()))
-        .collect();
-    
-    user_degrees.sort_by(|a, b| b.1.cmp(&a.1));
-    
-    for (rank, (user, degree)) in user_degrees.iter().take(5).enumerate() {
-        println!("  {}. {} ({} connections)", rank + 1, user, degree);
+    let degree = degree_centrality(&graph).unwrap();
+    let betweenness = betweenness_centrality(&graph).unwrap();
+    let influence = CentralityReport::from_scores(&graph, &betweenness, "betweenness")
+        .join(CentralityReport::from_scores(&graph, &degree, "degree"))
+        .unwrap();
+
+    for row in influence.top(5) {
+        println!(
+            "  {}. {} (betweenness {:.2}, degree {:.2}, percentile {:.0})",
+            row.rank,
+            row.label,
+            row.scores[0],
+            row.scores[1],
+            influence.percentile_of(row.node).unwrap()
+        );
     }
 
     // ML algorithm selection
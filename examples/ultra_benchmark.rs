@@ -4,6 +4,7 @@
 
 use std::time::Instant;
 use zipgraph_core::graph::{Graph, Node};
+use zipgraph_core::{node_id, NodeId};
 use zipgraph_core::ultra;
 use zipgraph_core::metrics;
 use zipgraph_core::algorithms;
@@ -17,7 +18,7 @@ fn benchmark_ultra_vs_standard_bfs(size: usize) {
     let mut node_ids = Vec::new();
     
     for i in 0..size {
-        let node = Node::new(i, format!("node_{}", i));
+        let node = Node::new(node_id(i), format!("node_{}", i));
         let id = graph.add_node(node);
         node_ids.push(id);
     }
@@ -60,6 +61,21 @@ fn benchmark_ultra_vs_standard_bfs(size: usize) {
     println!("\n  📊 Standard BFS: {:.3}ms (avg over 100 runs)", standard_time.as_secs_f64() * 1000.0 / 100.0);
     println!("  ⚡ Ultra BFS:    {:.3}ms (avg over 100 runs)", ultra_time.as_secs_f64() * 1000.0 / 100.0);
     println!("  🚀 Speedup:      {:.2}x faster", speedup);
+
+    // Work counts, so the speedup is not read as doing less work
+    let mut standard_work = algorithms::SearchMetrics::default();
+    let mut ultra_work = algorithms::SearchMetrics::default();
+    algorithms::bfs_instrumented(&graph, start_node, target_node, &mut standard_work).ok();
+    ultra::ultra_bfs_instrumented(&graph, start_node, target_node, &mut ultra_work).ok();
+    for (name, work) in [("Standard BFS", standard_work), ("Ultra BFS", ultra_work)] {
+        println!(
+            "  🔢 {:<13} {} nodes expanded, {} edges relaxed, frontier peak {}",
+            format!("{}:", name),
+            work.nodes_expanded,
+            work.edges_relaxed,
+            work.max_frontier
+        );
+    }
 }
 
 fn benchmark_batch_processing(size: usize, num_queries: usize) {
@@ -71,7 +87,7 @@ fn benchmark_batch_processing(size: usize, num_queries: usize) {
     let mut node_ids = Vec::new();
     
     for i in 0..size {
-        let node = Node::new(i, format!("node_{}", i));
+        let node = Node::new(node_id(i), format!("node_{}", i));
         let id = graph.add_node(node);
         node_ids.push(id);
     }
@@ -85,7 +101,7 @@ fn benchmark_batch_processing(size: usize, num_queries: usize) {
     }
     
     // Generate random queries
-    let queries: Vec<(usize, usize)> = (0..num_queries)
+    let queries: Vec<(NodeId, NodeId)> = (0..num_queries)
         .map(|i| {
             let src_idx = (i * 7) % size;
             let dst_idx = ((i * 13) + 1) % size;
@@ -121,7 +137,7 @@ fn benchmark_ultra_pagerank(size: usize) {
     let mut node_ids = Vec::new();
     
     for i in 0..size {
-        let node = Node::new(i, format!("node_{}", i));
+        let node = Node::new(node_id(i), format!("node_{}", i));
         let id = graph.add_node(node);
         node_ids.push(id);
     }
@@ -158,7 +174,7 @@ fn benchmark_memory_efficiency() {
         let mut node_ids = Vec::new();
         
         for i in 0..size {
-            let node = Node::new(i, format!("node_{}", i));
+            let node = Node::new(node_id(i), format!("node_{}", i));
             let id = graph.add_node(node);
             node_ids.push(id);
         }
@@ -1,6 +1,7 @@
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
 use rand::Rng;
-use zipgraph_core::{algorithms, Graph};
+use zipgraph_core::{algorithms, centrality, node_id, parallel, ultra, Graph};
+use zipgraph_optimizer::HubLabelIndex;
 
 fn create_test_graph(size: usize) -> Graph {
     let mut graph = Graph::with_capacity(size, size * 2);
@@ -12,7 +13,7 @@ fn create_test_graph(size: usize) -> Graph {
 
     for i in 0..size - 1 {
         let weight = rng.gen_range(1.0..10.0);
-        let _ = graph.add_edge(i, i + 1, weight);
+        let _ = graph.add_edge(node_id(i), node_id(i + 1), weight);
     }
 
     // Add some random edges for complexity
@@ -21,7 +22,7 @@ fn create_test_graph(size: usize) -> Graph {
         let to = rng.gen_range(0..size);
         if from != to {
             let weight = rng.gen_range(1.0..10.0);
-            let _ = graph.add_edge(from, to, weight);
+            let _ = graph.add_edge(node_id(from), node_id(to), weight);
         }
     }
 
@@ -33,11 +34,11 @@ fn bench_bfs(c: &mut Criterion) {
     
     for size in [100, 500, 1000].iter() {
         let graph = create_test_graph(*size);
-        let goal = size - 1;
+        let goal = node_id(size - 1);
         
         group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, _| {
             b.iter(|| {
-                let path = algorithms::bfs(&graph, 0, goal);
+                let path = algorithms::bfs(&graph, node_id(0), goal);
                 black_box(path)
             });
         });
@@ -50,11 +51,11 @@ fn bench_dfs(c: &mut Criterion) {
     
     for size in [100, 500, 1000].iter() {
         let graph = create_test_graph(*size);
-        let goal = size - 1;
+        let goal = node_id(size - 1);
         
         group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, _| {
             b.iter(|| {
-                let path = algorithms::dfs(&graph, 0, goal);
+                let path = algorithms::dfs(&graph, node_id(0), goal);
                 black_box(path)
             });
         });
@@ -67,11 +68,11 @@ fn bench_dijkstra(c: &mut Criterion) {
     
     for size in [100, 500, 1000].iter() {
         let graph = create_test_graph(*size);
-        let goal = size - 1;
+        let goal = node_id(size - 1);
         
         group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, _| {
             b.iter(|| {
-                let result = algorithms::dijkstra(&graph, 0, goal);
+                let result = algorithms::dijkstra(&graph, node_id(0), goal);
                 black_box(result)
             });
         });
@@ -79,5 +80,112 @@ fn bench_dijkstra(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, bench_bfs, bench_dfs, bench_dijkstra);
+fn bench_floyd_warshall(c: &mut Criterion) {
+    let mut group = c.benchmark_group("floyd_warshall");
+    group.sample_size(10);
+
+    let size = 500;
+    let graph = create_test_graph(size);
+
+    group.bench_with_input(BenchmarkId::new("serial", size), &size, |b, _| {
+        b.iter(|| {
+            let dist = algorithms::floyd_warshall(&graph);
+            black_box(dist)
+        });
+    });
+    group.bench_with_input(BenchmarkId::new("parallel", size), &size, |b, _| {
+        b.iter(|| {
+            let dist = parallel::parallel_floyd_warshall(&graph);
+            black_box(dist)
+        });
+    });
+    group.finish();
+}
+
+fn bench_hub_labels(c: &mut Criterion) {
+    let mut group = c.benchmark_group("hub_label_distance");
+
+    for size in [1000, 5000].iter() {
+        let graph = create_test_graph(*size);
+        let index = HubLabelIndex::build(&graph).unwrap();
+        let goal = node_id(size - 1);
+
+        group.bench_with_input(BenchmarkId::new("hub_labels", size), size, |b, _| {
+            b.iter(|| black_box(index.distance(black_box(node_id(0)), black_box(goal))));
+        });
+        group.bench_with_input(BenchmarkId::new("dijkstra", size), size, |b, _| {
+            b.iter(|| {
+                black_box(algorithms::dijkstra(&graph, black_box(node_id(0)), black_box(goal)))
+            });
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_pagerank(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pagerank");
+    group.sample_size(10);
+
+    for size in [500, 2000].iter() {
+        let graph = create_test_graph(*size);
+
+        group.bench_with_input(BenchmarkId::new("exact", size), size, |b, _| {
+            b.iter(|| black_box(centrality::pagerank(&graph, 0.85, 100, 1e-6)));
+        });
+        group.bench_with_input(BenchmarkId::new("ultra", size), size, |b, _| {
+            b.iter(|| black_box(ultra::ultra_pagerank(&graph, 0.85, 100, 1e-6)));
+        });
+        group.bench_with_input(BenchmarkId::new("monte_carlo", size), size, |b, _| {
+            b.iter(|| black_box(centrality::pagerank_monte_carlo(&graph, 0.85, 100, Some(1))));
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_betweenness(c: &mut Criterion) {
+    let mut group = c.benchmark_group("betweenness");
+    group.sample_size(10);
+
+    for size in [2000, 8000].iter() {
+        let graph = create_test_graph(*size);
+
+        // Every node a pivot is exact Brandes
+        group.bench_with_input(BenchmarkId::new("brandes", size), size, |b, &size| {
+            b.iter(|| black_box(centrality::approximate_betweenness(&graph, size, Some(1))));
+        });
+        group.bench_with_input(BenchmarkId::new("sampled_paths", size), size, |b, _| {
+            b.iter(|| black_box(centrality::approx_betweenness(&graph, 0.05, 0.1, Some(1))));
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_bfs_levels(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bfs_levels");
+    let graph = create_test_graph(10_000);
+
+    group.bench_function("zero_copy_collect", |b| {
+        b.iter(|| black_box(ultra::zero_copy_bfs(&graph, node_id(0)).collect::<Vec<_>>()));
+    });
+    group.bench_function("levels", |b| {
+        b.iter(|| black_box(ultra::bfs_levels(&graph, node_id(0)).collect::<Vec<_>>()));
+    });
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_bfs,
+    bench_dfs,
+    bench_dijkstra,
+    bench_floyd_warshall,
+    bench_hub_labels,
+    bench_pagerank,
+    bench_betweenness,
+    bench_bfs_levels
+);
 criterion_main!(benches);
@@ -1,6 +1,6 @@
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
 use rand::Rng;
-use zipgraph_core::Graph;
+use zipgraph_core::{node_id, save_graph, Graph, StorageFormat};
 
 fn create_random_graph(node_count: usize, edge_density: f64) -> Graph {
     let mut graph = Graph::with_capacity(node_count, (node_count as f64 * edge_density) as usize);
@@ -14,7 +14,7 @@ fn create_random_graph(node_count: usize, edge_density: f64) -> Graph {
         for j in i + 1..node_count {
             if rng.gen::<f64>() < edge_density {
                 let weight = rng.gen_range(1.0..10.0);
-                let _ = graph.add_edge(i, j, weight);
+                let _ = graph.add_edge(node_id(i), node_id(j), weight);
             }
         }
     }
@@ -54,7 +54,7 @@ fn bench_edge_addition(c: &mut Criterion) {
                 },
                 |mut graph| {
                     for i in 0..size - 1 {
-                        let _ = graph.add_edge(i, i + 1, 1.0);
+                        let _ = graph.add_edge(node_id(i), node_id(i + 1), 1.0);
                     }
                     black_box(graph)
                 },
@@ -72,7 +72,7 @@ fn bench_neighbor_lookup(c: &mut Criterion) {
         let graph = create_random_graph(*size, 0.1);
         group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, _| {
             b.iter(|| {
-                let neighbors = graph.neighbors(0).unwrap();
+                let neighbors = graph.neighbors(node_id(0)).unwrap();
                 black_box(neighbors)
             });
         });
@@ -80,10 +80,78 @@ fn bench_neighbor_lookup(c: &mut Criterion) {
     group.finish();
 }
 
+fn bench_zstd_storage(c: &mut Criterion) {
+    let mut group = c.benchmark_group("zstd_storage");
+    group.sample_size(10);
+
+    // About a million edges
+    let graph = create_random_graph(2000, 0.5);
+    let dir = std::env::temp_dir();
+    let binary = dir.join("zipgraph_bench.bin");
+    save_graph(&graph, &binary, StorageFormat::Binary).unwrap();
+    let binary_size = std::fs::metadata(&binary).unwrap().len();
+
+    for level in [1, 3, 9] {
+        let format = StorageFormat::BinaryZstd { level };
+        let path = dir.join(format!("zipgraph_bench_{}.bin.zst", level));
+        save_graph(&graph, &path, format).unwrap();
+        let size = std::fs::metadata(&path).unwrap().len();
+        println!(
+            "zstd level {}: {} bytes ({:.2}x smaller than binary)",
+            level,
+            size,
+            binary_size as f64 / size as f64
+        );
+
+        group.bench_with_input(BenchmarkId::from_parameter(level), &level, |b, _| {
+            b.iter(|| save_graph(black_box(&graph), &path, format).unwrap());
+        });
+        let _ = std::fs::remove_file(&path);
+    }
+    let _ = std::fs::remove_file(&binary);
+
+    group.finish();
+}
+
+fn bench_snapshot(c: &mut Criterion) {
+    let mut group = c.benchmark_group("snapshot");
+
+    for size in [1000, 10000, 100000] {
+        let mut graph = Graph::with_capacity(size, size);
+        for i in 0..size {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        for i in 0..size {
+            graph.add_edge(node_id(i), node_id((i + 1) % size), 1.0).unwrap();
+        }
+
+        // Taking a snapshot shares the graph's internals
+        group.bench_with_input(BenchmarkId::new("snapshot", size), &size, |b, _| {
+            b.iter(|| black_box(graph.snapshot()));
+        });
+        // The first mutation while a snapshot is held copies them, which
+        // is what a deep clone costs
+        group.bench_with_input(BenchmarkId::new("first_write", size), &size, |b, _| {
+            b.iter_batched(
+                || (graph.clone(), graph.snapshot()),
+                |(mut copy, snapshot)| {
+                    let node = copy.add_node_simple("New");
+                    copy.add_edge(node_id(0), node, 1.0).unwrap();
+                    black_box((copy, snapshot))
+                },
+                criterion::BatchSize::LargeInput,
+            );
+        });
+    }
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_graph_creation,
     bench_edge_addition,
-    bench_neighbor_lookup
+    bench_neighbor_lookup,
+    bench_zstd_storage,
+    bench_snapshot
 );
 criterion_main!(benches);
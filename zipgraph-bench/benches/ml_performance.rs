@@ -1,6 +1,6 @@
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
-use zipgraph_core::Graph;
-use zipgraph_ml::{AlgorithmSelector, AnomalyDetector, NodeEmbeddings};
+use zipgraph_core::{generators, node_id, Graph, NodeId};
+use zipgraph_ml::{AlgorithmSelector, AnomalyDetector, Node2VecTrainer, NodeEmbeddings};
 use zipgraph_optimizer::QueryOptimizer;
 
 fn create_test_graph(size: usize) -> Graph {
@@ -11,7 +11,7 @@ fn create_test_graph(size: usize) -> Graph {
     }
 
     for i in 0..size - 1 {
-        let _ = graph.add_edge(i, i + 1, 1.0);
+        let _ = graph.add_edge(node_id(i), node_id(i + 1), 1.0);
     }
 
     graph
@@ -48,6 +48,78 @@ fn bench_embeddings(c: &mut Criterion) {
     group.finish();
 }
 
+/// Walk generation on 1 to 8 threads; time should fall close to linearly
+/// with the thread count, up to the number of cores
+fn bench_walk_generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("walk_generation_threads");
+    group.sample_size(10);
+
+    let graph = generators::erdos_renyi(5000, 0.002, false, Some(1));
+    let trainer = Node2VecTrainer::new(40, 10, 64).with_seed(1);
+    for threads in [1, 2, 4, 8] {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(threads), &threads, |b, _| {
+            b.iter(|| {
+                let mut steps = 0;
+                pool.install(|| trainer.for_each_walk(&graph, |walk| steps += walk.len()));
+                black_box(steps)
+            });
+        });
+    }
+    group.finish();
+}
+
+fn bench_similarity_search(c: &mut Criterion) {
+    let mut group = c.benchmark_group("similarity_search");
+    group.sample_size(10);
+
+    let embeddings = NodeEmbeddings::new(5000, 64);
+    let queries: Vec<NodeId> = (0..1000).map(node_id).collect();
+    group.bench_function("per_node_loop", |b| {
+        b.iter(|| {
+            let results: Vec<_> = queries
+                .iter()
+                .map(|&node| embeddings.most_similar(node, 20).unwrap())
+                .collect();
+            black_box(results)
+        });
+    });
+    group.bench_function("batch", |b| {
+        b.iter(|| black_box(embeddings.batch_most_similar(&queries, 20).unwrap()));
+    });
+    group.finish();
+}
+
+/// Pairwise similarity scoring of 256-dimensional embeddings. Run once
+/// with `--features scalar-kernels -- --save-baseline scalar` and once
+/// with `-- --baseline scalar` to see the speedup of the chunked kernels
+/// over serial loops.
+fn bench_similarity_kernels(c: &mut Criterion) {
+    let mut group = c.benchmark_group("similarity_kernels_256");
+
+    let embeddings = NodeEmbeddings::new(2000, 256);
+    let pairs: Vec<(NodeId, NodeId)> =
+        (0..10_000).map(|i| (node_id(i % 2000), node_id((i * 7 + 1) % 2000))).collect();
+    group.bench_function("cosine_similarity", |b| {
+        b.iter(|| {
+            let total: f32 =
+                pairs.iter().map(|&(a, b)| embeddings.cosine_similarity(a, b).unwrap()).sum();
+            black_box(total)
+        });
+    });
+    group.bench_function("euclidean_distance", |b| {
+        b.iter(|| {
+            let total: f32 =
+                pairs.iter().map(|&(a, b)| embeddings.euclidean_distance(a, b).unwrap()).sum();
+            black_box(total)
+        });
+    });
+    group.bench_function("most_similar", |b| {
+        b.iter(|| black_box(embeddings.most_similar(node_id(0), 20).unwrap()));
+    });
+    group.finish();
+}
+
 fn bench_anomaly_detection(c: &mut Criterion) {
     let mut group = c.benchmark_group("anomaly_detection");
     
@@ -74,7 +146,7 @@ fn bench_query_optimizer(c: &mut Criterion) {
         
         group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, &size| {
             b.iter(|| {
-                let path = optimizer.shortest_path(&graph, 0, size - 1);
+                let path = optimizer.shortest_path(&graph, node_id(0), node_id(size - 1));
                 black_box(path)
             });
         });
@@ -86,6 +158,9 @@ criterion_group!(
     benches,
     bench_algorithm_selection,
     bench_embeddings,
+    bench_walk_generation,
+    bench_similarity_search,
+    bench_similarity_kernels,
     bench_anomaly_detection,
     bench_query_optimizer
 );
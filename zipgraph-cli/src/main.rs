@@ -0,0 +1,406 @@
+//! `zipgraph`: inspect, convert and query saved graphs
+//!
+//! ```text
+//! zipgraph stats graph.json
+//! zipgraph convert graph.json graph.graphml
+//! zipgraph query sp graph.bin 0 42 --json
+//! zipgraph pagerank graph.json --top 20
+//! zipgraph components graph.graphml
+//! zipgraph distances graph.bin --checkpoint apsp.ckpt --resume
+//! ```
+//!
+//! The storage format is taken from the file extension (`.json`,
+//! `.graphml`/`.xml`, `.bin`, `.zst`, `.dot`/`.gv`, `.net`) unless given with
+//! `--format`, or `--from`/`--to` for `convert`. Every command prints a
+//! table, or JSON with `--json`. Exit codes are listed on `ExitCode`.
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use zipgraph_core::{
+    algorithms, load_graph, save_graph, Graph, GraphStats, NodeId, StorageFormat,
+};
+use zipgraph_optimizer::{Query, QueryOptimizer, QueryResult};
+
+/// Number of PageRank iterations run by `pagerank`
+const PAGERANK_ITERATIONS: usize = 100;
+
+#[derive(Parser)]
+#[command(name = "zipgraph", version, about = "Inspect, convert and query saved graphs")]
+struct Cli {
+    /// Print JSON instead of a table
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print graph statistics
+    Stats(Input),
+    /// Convert a graph between storage formats
+    Convert {
+        input: PathBuf,
+        output: PathBuf,
+        /// Format of the input, detected from its extension by default
+        #[arg(long)]
+        from: Option<Format>,
+        /// Format of the output, detected from its extension by default
+        #[arg(long)]
+        to: Option<Format>,
+    },
+    /// Run a query against a graph
+    #[command(subcommand)]
+    Query(QueryCommand),
+    /// Print the nodes with the highest PageRank
+    Pagerank {
+        #[command(flatten)]
+        input: Input,
+        /// Number of nodes to print
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+    },
+    /// Print the connected components
+    Components(Input),
+    /// Compute all-pairs shortest distances and summarize them
+    Distances {
+        #[command(flatten)]
+        input: Input,
+        /// Save progress here while running, so that the run can be resumed
+        #[arg(long)]
+        checkpoint: Option<PathBuf>,
+        /// Continue from the checkpoint instead of starting over
+        #[arg(long, requires = "checkpoint")]
+        resume: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum QueryCommand {
+    /// Shortest weighted path between two nodes
+    Sp {
+        #[command(flatten)]
+        input: Input,
+        source: NodeId,
+        target: NodeId,
+    },
+}
+
+#[derive(Args)]
+struct Input {
+    /// Graph file
+    file: PathBuf,
+    /// Storage format, detected from the extension by default
+    #[arg(long)]
+    format: Option<Format>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Json,
+    Graphml,
+    Binary,
+    Zstd,
+    Dot,
+    Pajek,
+}
+
+impl From<Format> for StorageFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Json => StorageFormat::Json,
+            Format::Graphml => StorageFormat::GraphML,
+            Format::Binary => StorageFormat::Binary,
+            Format::Zstd => StorageFormat::BinaryZstd { level: 0 },
+            Format::Dot => StorageFormat::Dot,
+            Format::Pajek => StorageFormat::Pajek,
+        }
+    }
+}
+
+/// Process exit codes
+#[derive(Debug, Clone, Copy)]
+enum ExitCode {
+    /// The query has no answer, e.g. no path between the nodes
+    NoResult = 1,
+    // 2 is used by clap for invalid arguments
+    /// A graph file could not be read or written
+    Storage = 3,
+    /// The query failed, e.g. an unknown node
+    Query = 4,
+}
+
+struct Failure {
+    code: ExitCode,
+    message: String,
+}
+
+impl Failure {
+    fn new(code: ExitCode, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if let Err(failure) = run(&cli) {
+        eprintln!("zipgraph: {}", failure.message);
+        process::exit(failure.code as i32);
+    }
+}
+
+fn run(cli: &Cli) -> Result<(), Failure> {
+    match &cli.command {
+        Command::Stats(input) => {
+            let graph = input.load()?;
+            let stats = GraphStats::from_graph(&graph);
+            if cli.json {
+                print_json(&json!(stats));
+            } else {
+                print_stats(&stats);
+            }
+        }
+        Command::Convert { input, output, from, to } => {
+            let graph = load(input, *from)?;
+            let format = resolve_format(output, *to)?;
+            save_graph(&graph, output, format).map_err(|e| {
+                Failure::new(ExitCode::Storage, format!("{}: {}", output.display(), e))
+            })?;
+            if cli.json {
+                print_json(&json!({
+                    "output": output,
+                    "nodes": graph.node_count(),
+                    "edges": graph.edge_count(),
+                }));
+            } else {
+                println!(
+                    "Wrote {} nodes and {} edges to {}",
+                    graph.node_count(),
+                    graph.edge_count(),
+                    output.display()
+                );
+            }
+        }
+        Command::Query(QueryCommand::Sp { input, source, target }) => {
+            let graph = input.load()?;
+            let query = Query::ShortestPath {
+                start: *source,
+                goal: *target,
+                metric: Default::default(),
+            };
+            let path = match execute(&graph, &query)? {
+                QueryResult::Path(path) => Some(path),
+                _ => None,
+            };
+            if cli.json {
+                print_json(&json!({ "source": source, "target": target, "path": path }));
+            } else if let Some(path) = &path {
+                print_table(
+                    &["step", "node", "label"],
+                    path.iter().enumerate().map(|(step, &node)| {
+                        vec![step.to_string(), node.to_string(), label(&graph, node)]
+                    }),
+                );
+            }
+            if path.is_none() {
+                return Err(Failure::new(
+                    ExitCode::NoResult,
+                    format!("no path from {} to {}", source, target),
+                ));
+            }
+        }
+        Command::Pagerank { input, top } => {
+            let graph = input.load()?;
+            let query = Query::PageRank { iterations: PAGERANK_ITERATIONS, top_k: Some(*top) };
+            let QueryResult::Scores(scores) = execute(&graph, &query)? else {
+                unreachable!("PageRank queries return scores");
+            };
+            if cli.json {
+                let rows: Vec<_> = scores
+                    .iter()
+                    .map(|&(node, score)| {
+                        json!({ "node": node, "label": label(&graph, node), "score": score })
+                    })
+                    .collect();
+                print_json(&json!(rows));
+            } else {
+                print_table(
+                    &["rank", "node", "label", "score"],
+                    scores.iter().enumerate().map(|(rank, &(node, score))| {
+                        vec![
+                            (rank + 1).to_string(),
+                            node.to_string(),
+                            label(&graph, node),
+                            format!("{:.6}", score),
+                        ]
+                    }),
+                );
+            }
+        }
+        Command::Components(input) => {
+            let graph = input.load()?;
+            let QueryResult::Components(components) =
+                execute(&graph, &Query::ConnectedComponents)?
+            else {
+                unreachable!("component queries return components");
+            };
+            if cli.json {
+                print_json(&json!({ "count": components.len(), "components": components }));
+            } else {
+                print_table(
+                    &["component", "size", "nodes"],
+                    components.iter().enumerate().map(|(i, nodes)| {
+                        let ids: Vec<String> = nodes.iter().map(NodeId::to_string).collect();
+                        vec![i.to_string(), nodes.len().to_string(), ids.join(" ")]
+                    }),
+                );
+            }
+        }
+        Command::Distances { input, checkpoint, resume } => {
+            let graph = input.load()?;
+            if let (Some(path), false) = (checkpoint, resume) {
+                if path.exists() {
+                    fs::remove_file(path).map_err(|e| {
+                        Failure::new(ExitCode::Storage, format!("{}: {}", path.display(), e))
+                    })?;
+                }
+            }
+            let dist = algorithms::floyd_warshall_resumable(&graph, checkpoint.as_deref())
+                .map_err(|e| Failure::new(ExitCode::Query, e.to_string()))?;
+
+            let finite: Vec<f64> =
+                dist.iter().flatten().copied().filter(|d| d.is_finite() && *d > 0.0).collect();
+            let longest = finite.iter().copied().fold(0.0, f64::max);
+            if cli.json {
+                print_json(&json!({
+                    "nodes": graph.node_count(),
+                    "reachable_pairs": finite.len(),
+                    "longest_distance": longest,
+                }));
+            } else {
+                print_table(
+                    &["statistic", "value"],
+                    [
+                        vec!["nodes".to_string(), graph.node_count().to_string()],
+                        vec!["reachable pairs".to_string(), finite.len().to_string()],
+                        vec!["longest distance".to_string(), format!("{:.6}", longest)],
+                    ],
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Input {
+    fn load(&self) -> Result<Graph, Failure> {
+        load(&self.file, self.format)
+    }
+}
+
+fn load(path: &Path, format: Option<Format>) -> Result<Graph, Failure> {
+    let format = resolve_format(path, format)?;
+    load_graph(path, format)
+        .map_err(|e| Failure::new(ExitCode::Storage, format!("{}: {}", path.display(), e)))
+}
+
+/// The explicit format, or the one implied by the file extension
+fn resolve_format(path: &Path, format: Option<Format>) -> Result<StorageFormat, Failure> {
+    if let Some(format) = format {
+        return Ok(format.into());
+    }
+
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    let format = match extension.as_deref() {
+        Some("json") => Format::Json,
+        Some("graphml") | Some("xml") => Format::Graphml,
+        Some("bin") => Format::Binary,
+        Some("zst") => Format::Zstd,
+        Some("dot") | Some("gv") => Format::Dot,
+        Some("net") => Format::Pajek,
+        _ => {
+            return Err(Failure::new(
+                ExitCode::Storage,
+                format!(
+                    "{}: cannot tell the storage format from the extension; pass it explicitly",
+                    path.display()
+                ),
+            ))
+        }
+    };
+    Ok(format.into())
+}
+
+fn execute(graph: &Graph, query: &Query) -> Result<QueryResult, Failure> {
+    QueryOptimizer::new()
+        .execute(graph, query)
+        .map_err(|e| Failure::new(ExitCode::Query, e.to_string()))
+}
+
+fn label(graph: &Graph, node: NodeId) -> String {
+    graph.node(node).map(|node| node.label.clone()).unwrap_or_default()
+}
+
+fn print_json(value: &serde_json::Value) {
+    println!("{}", serde_json::to_string_pretty(value).expect("JSON values serialize"));
+}
+
+fn print_stats(stats: &GraphStats) {
+    let optional = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+    print_table(
+        &["statistic", "value"],
+        [
+            ("nodes", stats.node_count.to_string()),
+            ("edges", stats.edge_count.to_string()),
+            ("directed", stats.is_directed.to_string()),
+            ("average degree", format!("{:.3}", stats.avg_degree)),
+            ("min degree", stats.min_degree.to_string()),
+            ("max degree", stats.max_degree.to_string()),
+            ("density", format!("{:.6}", stats.density)),
+            (
+                "clustering coefficient",
+                optional(stats.clustering_coefficient.map(|c| format!("{:.6}", c))),
+            ),
+            ("diameter", optional(stats.diameter.map(|d| d.to_string()))),
+            (
+                "average path length",
+                optional(stats.average_shortest_path_length.map(|l| format!("{:.3}", l))),
+            ),
+        ]
+        .into_iter()
+        .map(|(name, value)| vec![name.to_string(), value]),
+    );
+}
+
+/// Print rows under `headers`, left-aligned in padded columns
+fn print_table(headers: &[&str], rows: impl IntoIterator<Item = Vec<String>>) {
+    let rows: Vec<Vec<String>> = rows.into_iter().collect();
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.len()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let line = |cells: Vec<&str>| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        println!("{}", padded.join("  ").trim_end());
+    };
+    line(headers.to_vec());
+    let rules: Vec<String> = widths.iter().map(|&width| "-".repeat(width)).collect();
+    line(rules.iter().map(String::as_str).collect());
+    for row in &rows {
+        line(row.iter().map(String::as_str).collect());
+    }
+}
@@ -0,0 +1,114 @@
+//! Drive the `zipgraph` binary against the fixtures in `tests/fixtures`
+
+// The tests number nodes with integer literals
+#![cfg(not(feature = "uuid-ids"))]
+
+use assert_cmd::Command;
+use serde_json::Value;
+use tempfile::TempDir;
+
+const SMALL_JSON: &str = "tests/fixtures/small.json";
+const SMALL_GRAPHML: &str = "tests/fixtures/small.graphml";
+
+fn zipgraph() -> Command {
+    Command::cargo_bin("zipgraph").unwrap()
+}
+
+fn json_output(args: &[&str]) -> Value {
+    let output = zipgraph().arg("--json").args(args).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn test_stats() {
+    let stats = json_output(&["stats", SMALL_JSON]);
+    assert_eq!(stats["node_count"], 6);
+    assert_eq!(stats["edge_count"], 5);
+
+    let output = zipgraph().args(["stats", SMALL_GRAPHML]).output().unwrap();
+    assert!(output.status.success());
+    let table = String::from_utf8(output.stdout).unwrap();
+    assert!(table.starts_with("statistic"));
+    assert!(table.lines().any(|line| line.split_whitespace().eq(["nodes", "6"])));
+}
+
+#[test]
+fn test_shortest_path() {
+    let result = json_output(&["query", "sp", SMALL_JSON, "0", "3"]);
+    assert_eq!(result["path"], serde_json::json!([0, 1, 2, 3]));
+
+    let output = zipgraph().args(["query", "sp", SMALL_JSON, "0", "3"]).output().unwrap();
+    let table = String::from_utf8(output.stdout).unwrap();
+    assert!(table.lines().nth(2).unwrap().contains("Alice"));
+}
+
+#[test]
+fn test_exit_codes() {
+    // No path between components
+    zipgraph().args(["query", "sp", SMALL_JSON, "0", "5"]).assert().code(1);
+    // Invalid arguments
+    zipgraph().args(["query", "sp", SMALL_JSON, "zero", "5"]).assert().code(2);
+    // Unreadable graph
+    zipgraph().args(["stats", "tests/fixtures/missing.json"]).assert().code(3);
+    zipgraph().args(["stats", "tests/fixtures/small.txt"]).assert().code(3);
+    // Unknown node
+    let output = zipgraph().args(["query", "sp", SMALL_JSON, "0", "99"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Node 99 not found"));
+}
+
+#[test]
+fn test_pagerank_and_components() {
+    let ranks = json_output(&["pagerank", SMALL_JSON, "--top", "2"]);
+    let ranks = ranks.as_array().unwrap();
+    assert_eq!(ranks.len(), 2);
+    assert_eq!(ranks[0]["label"], "Carol");
+
+    let components = json_output(&["components", SMALL_GRAPHML]);
+    assert_eq!(components["count"], 2);
+    assert_eq!(components["components"][1], serde_json::json!([4, 5]));
+}
+
+#[test]
+fn test_convert() {
+    let dir = TempDir::new().unwrap();
+    let output = dir.path().join("small.out");
+    let output = output.to_str().unwrap();
+
+    zipgraph()
+        .args(["convert", SMALL_JSON, output, "--from", "json", "--to", "zstd"])
+        .assert()
+        .success();
+    let stats = json_output(&["stats", output, "--format", "zstd"]);
+    assert_eq!(stats["edge_count"], 5);
+
+    let dot = dir.path().join("small.dot");
+    let dot = dot.to_str().unwrap();
+    zipgraph().args(["convert", SMALL_JSON, dot]).assert().success();
+    assert_eq!(json_output(&["stats", dot])["edge_count"], 5);
+
+    let pajek = dir.path().join("small.net");
+    let pajek = pajek.to_str().unwrap();
+    zipgraph().args(["convert", SMALL_JSON, pajek]).assert().success();
+    assert_eq!(json_output(&["stats", pajek])["edge_count"], 5);
+
+    // The output format cannot be detected from `.out`
+    zipgraph().args(["convert", SMALL_JSON, output]).assert().code(3);
+}
+
+#[test]
+fn test_distances() {
+    let plain = json_output(&["distances", SMALL_JSON]);
+    assert_eq!(plain["nodes"], 6);
+
+    let dir = TempDir::new().unwrap();
+    let checkpoint = dir.path().join("apsp.ckpt");
+    let checkpoint = checkpoint.to_str().unwrap();
+    let resumed = json_output(&["distances", SMALL_JSON, "--checkpoint", checkpoint, "--resume"]);
+    assert_eq!(resumed, plain);
+    assert!(!dir.path().join("apsp.ckpt").exists());
+
+    // Resuming needs a checkpoint path
+    zipgraph().args(["distances", SMALL_JSON, "--resume"]).assert().code(2);
+}
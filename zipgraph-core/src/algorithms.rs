@@ -1,34 +1,142 @@
 //! Graph algorithms
 
+use crate::checkpoint::{Checkpointable, FloydWarshallState};
+use crate::compat::{check_graph_compat, Requirements};
 use crate::error::{GraphError, Result};
-use crate::graph::Graph;
-use crate::types::{NodeId, Weight};
+use crate::graph::{Edge, Graph, Node};
+use crate::progress::{self, PartialResult, ProgressSink};
+use crate::types::{id_bits, node_id, NodeId, Weight};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 use std::cmp::Ordering;
+use std::ops::ControlFlow;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
 
-/// Priority queue item for Dijkstra's algorithm
+/// Priority queue item for Dijkstra's algorithm, over node IDs or, in
+/// internal networks, node indices
 #[derive(Copy, Clone, PartialEq)]
-struct State {
-    cost: Weight,
-    node: NodeId,
+pub(crate) struct State<N = NodeId> {
+    pub(crate) cost: Weight,
+    pub(crate) node: N,
 }
 
-impl Eq for State {}
+impl<N: PartialEq> Eq for State<N> {}
 
-impl Ord for State {
+impl<N: PartialEq> Ord for State<N> {
     fn cmp(&self, other: &Self) -> Ordering {
         other.cost.partial_cmp(&self.cost).unwrap_or(Ordering::Equal)
     }
 }
 
-impl PartialOrd for State {
+impl<N: PartialEq> PartialOrd for State<N> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
+/// Work done by one or more searches, filled in by the `*_instrumented`
+/// variants
+///
+/// The instrumented variants add to the metrics they are given, so one
+/// value can total several searches; `max_frontier` keeps the largest.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchMetrics {
+    /// Nodes taken off the frontier and settled, the goal included
+    pub nodes_expanded: usize,
+    /// Edges followed out of expanded nodes
+    pub edges_relaxed: usize,
+    /// Most entries waiting on the frontier (queue, stack, heap or BFS
+    /// level) at once
+    pub max_frontier: usize,
+    /// Wall time spent searching
+    pub duration: Duration,
+}
+
+/// Counting hooks of the searches
+///
+/// The plain variants pass `()`, whose hooks do nothing and compile away.
+pub(crate) trait SearchProbe {
+    /// Whether the hooks count anything, for searches that must set up
+    /// shared counters to call them
+    const ENABLED: bool = false;
+
+    fn expanded(&mut self, _nodes: usize) {}
+    fn relaxed(&mut self, _edges: usize) {}
+    fn frontier(&mut self, _len: usize) {}
+}
+
+impl SearchProbe for () {}
+
+impl SearchProbe for SearchMetrics {
+    const ENABLED: bool = true;
+
+    fn expanded(&mut self, nodes: usize) {
+        self.nodes_expanded += nodes;
+    }
+
+    fn relaxed(&mut self, edges: usize) {
+        self.edges_relaxed += edges;
+    }
+
+    fn frontier(&mut self, len: usize) {
+        self.max_frontier = self.max_frontier.max(len);
+    }
+}
+
+/// Run `search` with `metrics` as its probe, adding its wall time
+pub(crate) fn instrumented<T>(
+    metrics: &mut SearchMetrics,
+    search: impl FnOnce(&mut SearchMetrics) -> T,
+) -> T {
+    let start = Instant::now();
+    let result = search(metrics);
+    metrics.duration += start.elapsed();
+    result
+}
+
 /// Breadth-First Search
 pub fn bfs(graph: &Graph, start: NodeId, goal: NodeId) -> Result<Vec<NodeId>> {
+    bfs_probed(graph, start, goal, |_| true, &mut ())
+}
+
+/// Breadth-first search that only crosses edges for which `edge_filter`
+/// holds
+///
+/// The filter is applied as edges are explored, so the path found is the
+/// one with the fewest hops among the allowed edges. Fails with
+/// `AlgorithmError` when the allowed edges do not connect `start` to
+/// `goal`.
+pub fn bfs_filtered(
+    graph: &Graph,
+    start: NodeId,
+    goal: NodeId,
+    edge_filter: impl Fn(&Edge) -> bool,
+) -> Result<Vec<NodeId>> {
+    bfs_probed(graph, start, goal, edge_filter, &mut ())
+}
+
+/// `bfs`, adding the work it does to `metrics`
+pub fn bfs_instrumented(
+    graph: &Graph,
+    start: NodeId,
+    goal: NodeId,
+    metrics: &mut SearchMetrics,
+) -> Result<Vec<NodeId>> {
+    instrumented(metrics, |probe| bfs_probed(graph, start, goal, |_| true, probe))
+}
+
+fn bfs_probed<F: Fn(&Edge) -> bool, P: SearchProbe>(
+    graph: &Graph,
+    start: NodeId,
+    goal: NodeId,
+    edge_filter: F,
+    probe: &mut P,
+) -> Result<Vec<NodeId>> {
     if !graph.node_ids().contains(&start) {
         return Err(GraphError::NodeNotFound(start));
     }
@@ -42,19 +150,26 @@ pub fn bfs(graph: &Graph, start: NodeId, goal: NodeId) -> Result<Vec<NodeId>> {
 
     queue.push_back(start);
     visited.insert(start);
+    probe.frontier(queue.len());
 
     while let Some(current) = queue.pop_front() {
+        probe.expanded(1);
         if current == goal {
             return Ok(reconstruct_path(&parent, start, goal));
         }
 
-        for neighbor in graph.neighbors(current)? {
+        for (neighbor, edge) in graph.incident_edges_iter(current) {
+            if !edge_filter(edge) {
+                continue;
+            }
+            probe.relaxed(1);
             if !visited.contains(&neighbor) {
                 visited.insert(neighbor);
                 parent.insert(neighbor, current);
                 queue.push_back(neighbor);
             }
         }
+        probe.frontier(queue.len());
     }
 
     Err(GraphError::AlgorithmError(format!(
@@ -65,6 +180,40 @@ pub fn bfs(graph: &Graph, start: NodeId, goal: NodeId) -> Result<Vec<NodeId>> {
 
 /// Depth-First Search
 pub fn dfs(graph: &Graph, start: NodeId, goal: NodeId) -> Result<Vec<NodeId>> {
+    dfs_probed(graph, start, goal, |_| true, &mut ())
+}
+
+/// Depth-first search that only crosses edges for which `edge_filter`
+/// holds
+///
+/// Like `bfs_filtered`, but the path is whichever the search reaches
+/// first, not necessarily the shortest.
+pub fn dfs_filtered(
+    graph: &Graph,
+    start: NodeId,
+    goal: NodeId,
+    edge_filter: impl Fn(&Edge) -> bool,
+) -> Result<Vec<NodeId>> {
+    dfs_probed(graph, start, goal, edge_filter, &mut ())
+}
+
+/// `dfs`, adding the work it does to `metrics`
+pub fn dfs_instrumented(
+    graph: &Graph,
+    start: NodeId,
+    goal: NodeId,
+    metrics: &mut SearchMetrics,
+) -> Result<Vec<NodeId>> {
+    instrumented(metrics, |probe| dfs_probed(graph, start, goal, |_| true, probe))
+}
+
+fn dfs_probed<F: Fn(&Edge) -> bool, P: SearchProbe>(
+    graph: &Graph,
+    start: NodeId,
+    goal: NodeId,
+    edge_filter: F,
+    probe: &mut P,
+) -> Result<Vec<NodeId>> {
     if !graph.node_ids().contains(&start) {
         return Err(GraphError::NodeNotFound(start));
     }
@@ -77,19 +226,26 @@ pub fn dfs(graph: &Graph, start: NodeId, goal: NodeId) -> Result<Vec<NodeId>> {
     let mut parent = HashMap::new();
 
     visited.insert(start);
+    probe.frontier(stack.len());
 
     while let Some(current) = stack.pop() {
+        probe.expanded(1);
         if current == goal {
             return Ok(reconstruct_path(&parent, start, goal));
         }
 
-        for neighbor in graph.neighbors(current)? {
+        for (neighbor, edge) in graph.incident_edges_iter(current) {
+            if !edge_filter(edge) {
+                continue;
+            }
+            probe.relaxed(1);
             if !visited.contains(&neighbor) {
                 visited.insert(neighbor);
                 parent.insert(neighbor, current);
                 stack.push(neighbor);
             }
         }
+        probe.frontier(stack.len());
     }
 
     Err(GraphError::AlgorithmError(format!(
@@ -100,6 +256,67 @@ pub fn dfs(graph: &Graph, start: NodeId, goal: NodeId) -> Result<Vec<NodeId>> {
 
 /// Dijkstra's shortest path algorithm
 pub fn dijkstra(graph: &Graph, start: NodeId, goal: NodeId) -> Result<(Vec<NodeId>, Weight)> {
+    dijkstra_following(graph, start, goal, |_, _, _| true, &mut ())
+}
+
+/// `dijkstra`, adding the work it does to `metrics`
+///
+/// Stale heap entries, left behind when a node's distance improves, count
+/// towards `max_frontier` but are not expansions.
+pub fn dijkstra_instrumented(
+    graph: &Graph,
+    start: NodeId,
+    goal: NodeId,
+    metrics: &mut SearchMetrics,
+) -> Result<(Vec<NodeId>, Weight)> {
+    instrumented(metrics, |probe| {
+        dijkstra_following(graph, start, goal, |_, _, _| true, probe)
+    })
+}
+
+/// Dijkstra's shortest path restricted to part of the graph
+///
+/// Only nodes for which `node_allowed` holds and edges for which
+/// `edge_allowed(from, to, weight)` holds are used. The endpoints must be
+/// allowed too; otherwise, as when the filters disconnect them, this fails
+/// with `AlgorithmError`, exactly like `dijkstra` without a path.
+pub fn dijkstra_filtered<N, E>(
+    graph: &Graph,
+    start: NodeId,
+    goal: NodeId,
+    node_allowed: N,
+    edge_allowed: E,
+) -> Result<(Vec<NodeId>, Weight)>
+where
+    N: Fn(&Node) -> bool,
+    E: Fn(NodeId, NodeId, Weight) -> bool,
+{
+    let start_node = graph.node(start)?;
+    graph.node(goal)?;
+    if !node_allowed(start_node) {
+        return Err(GraphError::AlgorithmError(format!(
+            "No path from {} to {}",
+            start, goal
+        )));
+    }
+    let follow = |from, to, weight| {
+        edge_allowed(from, to, weight) && graph.node(to).is_ok_and(&node_allowed)
+    };
+    dijkstra_following(graph, start, goal, follow, &mut ())
+}
+
+/// `dijkstra` over the edges for which `follow(from, to, weight)` holds
+fn dijkstra_following<E, P>(
+    graph: &Graph,
+    start: NodeId,
+    goal: NodeId,
+    follow: E,
+    probe: &mut P,
+) -> Result<(Vec<NodeId>, Weight)>
+where
+    E: Fn(NodeId, NodeId, Weight) -> bool,
+    P: SearchProbe,
+{
     if !graph.node_ids().contains(&start) {
         return Err(GraphError::NodeNotFound(start));
     }
@@ -107,6 +324,191 @@ pub fn dijkstra(graph: &Graph, start: NodeId, goal: NodeId) -> Result<(Vec<NodeI
         return Err(GraphError::NodeNotFound(goal));
     }
 
+    let mut goal_cost = None;
+    let settle = |node, cost| {
+        if node == goal {
+            goal_cost = Some(cost);
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    };
+    let parent = dijkstra_search_probed(graph, start, follow, settle, probe)?;
+
+    match goal_cost {
+        Some(cost) => Ok((reconstruct_path(&parent, start, goal), cost)),
+        None => Err(GraphError::AlgorithmError(format!(
+            "No path from {} to {}",
+            start, goal
+        ))),
+    }
+}
+
+/// Shortest-path tree of `source`: the parent and distance of every
+/// reachable node
+///
+/// `source` is in the distances (at 0) but has no parent. Following the
+/// parents from any node gives a minimum-weight path back to `source`.
+pub fn dijkstra_tree(
+    graph: &Graph,
+    source: NodeId,
+) -> Result<(HashMap<NodeId, NodeId>, HashMap<NodeId, Weight>)> {
+    let mut distances = HashMap::new();
+    let parents = dijkstra_search(graph, source, |_, _, _| true, |node, cost| {
+        distances.insert(node, cost);
+        ControlFlow::Continue(())
+    })?;
+    Ok((parents, distances))
+}
+
+/// Breadth-first tree of `source`, like `dijkstra_tree` with every edge
+/// counting as one hop
+pub fn bfs_tree(
+    graph: &Graph,
+    source: NodeId,
+) -> Result<(HashMap<NodeId, NodeId>, HashMap<NodeId, Weight>)> {
+    graph.node(source)?;
+
+    let mut parents = HashMap::new();
+    let mut distances = HashMap::from([(source, 0.0)]);
+    let mut queue = VecDeque::from([source]);
+    while let Some(node) = queue.pop_front() {
+        let next = distances[&node] + 1.0;
+        for (neighbor, _) in graph.weighted_neighbors_iter(node) {
+            if let std::collections::hash_map::Entry::Vacant(entry) = distances.entry(neighbor) {
+                entry.insert(next);
+                parents.insert(neighbor, node);
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    Ok((parents, distances))
+}
+
+/// Distances to every node within `max_distance` of `source`
+///
+/// The bound is inclusive: a node exactly `max_distance` away is
+/// included. The search stops as soon as the nearest unsettled node lies
+/// beyond the bound, so only the region inside it is explored.
+pub fn dijkstra_within(
+    graph: &Graph,
+    source: NodeId,
+    max_distance: Weight,
+) -> Result<HashMap<NodeId, Weight>> {
+    let mut within = HashMap::new();
+    dijkstra_search(graph, source, |_, _, _| true, |node, cost| {
+        if cost > max_distance {
+            return ControlFlow::Break(());
+        }
+        within.insert(node, cost);
+        ControlFlow::Continue(())
+    })?;
+    Ok(within)
+}
+
+/// Shortest paths from `source` to each of `targets`
+///
+/// The search stops once every target is settled instead of exploring
+/// the whole graph. Unreachable targets are absent from the result;
+/// unknown ones are a `NodeNotFound` error.
+pub fn dijkstra_multi_target(
+    graph: &Graph,
+    source: NodeId,
+    targets: &HashSet<NodeId>,
+) -> Result<HashMap<NodeId, (Vec<NodeId>, Weight)>> {
+    if let Some(&unknown) = targets.iter().find(|&&target| graph.node(target).is_err()) {
+        return Err(GraphError::NodeNotFound(unknown));
+    }
+    if targets.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let mut costs = HashMap::with_capacity(targets.len());
+    let parent = dijkstra_search(graph, source, |_, _, _| true, |node, cost| {
+        if targets.contains(&node) {
+            costs.insert(node, cost);
+            if costs.len() == targets.len() {
+                return ControlFlow::Break(());
+            }
+        }
+        ControlFlow::Continue(())
+    })?;
+
+    Ok(costs
+        .into_iter()
+        .map(|(target, cost)| (target, (reconstruct_path(&parent, source, target), cost)))
+        .collect())
+}
+
+/// Nodes reachable from `source` along time-respecting paths
+///
+/// A path is time-respecting if its first edge has a timestamp of at
+/// least `start_time` and every later edge one at least that of the edge
+/// before it; edges without a timestamp are never followed. Returns the
+/// earliest arrival time at each reachable node, `start_time` for
+/// `source` itself.
+pub fn temporal_reachability(
+    graph: &Graph,
+    source: NodeId,
+    start_time: f64,
+) -> Result<HashMap<NodeId, f64>> {
+    graph.node(source)?;
+
+    let mut arrival = HashMap::from([(source, start_time)]);
+    let mut heap = BinaryHeap::from([State { cost: start_time, node: source }]);
+    while let Some(State { cost: time, node }) = heap.pop() {
+        if arrival.get(&node).is_some_and(|&best| time > best) {
+            continue;
+        }
+        for (neighbor, edge) in graph.incident_edges_iter(node) {
+            let Some(departure) = edge.timestamp.filter(|&t| t >= time) else {
+                continue;
+            };
+            if arrival.get(&neighbor).is_none_or(|&best| departure < best) {
+                arrival.insert(neighbor, departure);
+                heap.push(State { cost: departure, node: neighbor });
+            }
+        }
+    }
+    Ok(arrival)
+}
+
+/// The search loop shared by the Dijkstra variants
+///
+/// Settles nodes in order of distance from `start`, calling `settle` with
+/// each node and its final distance; returning `ControlFlow::Break` stops
+/// the search. Returns the parent of every node reached, from which the
+/// path to any settled node can be reconstructed. Only edges for which
+/// `follow(from, to, weight)` holds are relaxed.
+fn dijkstra_search<F, E>(
+    graph: &Graph,
+    start: NodeId,
+    follow: E,
+    settle: F,
+) -> Result<HashMap<NodeId, NodeId>>
+where
+    F: FnMut(NodeId, Weight) -> ControlFlow<()>,
+    E: Fn(NodeId, NodeId, Weight) -> bool,
+{
+    dijkstra_search_probed(graph, start, follow, settle, &mut ())
+}
+
+fn dijkstra_search_probed<F, E, P>(
+    graph: &Graph,
+    start: NodeId,
+    follow: E,
+    mut settle: F,
+    probe: &mut P,
+) -> Result<HashMap<NodeId, NodeId>>
+where
+    F: FnMut(NodeId, Weight) -> ControlFlow<()>,
+    E: Fn(NodeId, NodeId, Weight) -> bool,
+    P: SearchProbe,
+{
+    if !graph.node_ids().contains(&start) {
+        return Err(GraphError::NodeNotFound(start));
+    }
+
     let mut dist: HashMap<NodeId, Weight> = HashMap::new();
     let mut parent: HashMap<NodeId, NodeId> = HashMap::new();
     let mut heap = BinaryHeap::new();
@@ -116,30 +518,128 @@ pub fn dijkstra(graph: &Graph, start: NodeId, goal: NodeId) -> Result<(Vec<NodeI
         cost: 0.0,
         node: start,
     });
+    probe.frontier(heap.len());
 
     while let Some(State { cost, node }) = heap.pop() {
+        if cost > *dist.get(&node).unwrap_or(&Weight::INFINITY) {
+            continue;
+        }
+        probe.expanded(1);
+        if settle(node, cost).is_break() {
+            break;
+        }
+
+        for (neighbor, weight) in graph.neighbors_with_weights(node)? {
+            if !follow(node, neighbor, weight) {
+                continue;
+            }
+            probe.relaxed(1);
+            let next_cost = cost + weight;
+            let neighbor_dist = *dist.get(&neighbor).unwrap_or(&Weight::INFINITY);
+
+            if next_cost < neighbor_dist {
+                dist.insert(neighbor, next_cost);
+                parent.insert(neighbor, node);
+                heap.push(State {
+                    cost: next_cost,
+                    node: neighbor,
+                });
+            }
+        }
+        probe.frontier(heap.len());
+    }
+
+    Ok(parent)
+}
+
+/// A* shortest path search guided by `heuristic`
+///
+/// `heuristic(node)` must never overestimate the distance from `node` to
+/// `goal`; with an admissible heuristic the returned path is as cheap as
+/// Dijkstra's. A zero heuristic degrades to Dijkstra.
+pub fn astar<H>(
+    graph: &Graph,
+    start: NodeId,
+    goal: NodeId,
+    heuristic: H,
+) -> Result<(Vec<NodeId>, Weight)>
+where
+    H: Fn(NodeId) -> Weight,
+{
+    astar_probed(graph, start, goal, heuristic, &mut ())
+}
+
+/// `astar`, adding the work it does to `metrics`
+///
+/// A node reopened after its cost improves counts as expanded again.
+pub fn astar_instrumented<H>(
+    graph: &Graph,
+    start: NodeId,
+    goal: NodeId,
+    heuristic: H,
+    metrics: &mut SearchMetrics,
+) -> Result<(Vec<NodeId>, Weight)>
+where
+    H: Fn(NodeId) -> Weight,
+{
+    instrumented(metrics, |probe| astar_probed(graph, start, goal, heuristic, probe))
+}
+
+fn astar_probed<H, P>(
+    graph: &Graph,
+    start: NodeId,
+    goal: NodeId,
+    heuristic: H,
+    probe: &mut P,
+) -> Result<(Vec<NodeId>, Weight)>
+where
+    H: Fn(NodeId) -> Weight,
+    P: SearchProbe,
+{
+    graph.node(start)?;
+    graph.node(goal)?;
+
+    let mut dist: HashMap<NodeId, Weight> = HashMap::new();
+    let mut parent: HashMap<NodeId, NodeId> = HashMap::new();
+    let mut closed: HashSet<NodeId> = HashSet::new();
+    let mut heap = BinaryHeap::new();
+
+    dist.insert(start, 0.0);
+    heap.push(State {
+        cost: heuristic(start),
+        node: start,
+    });
+    probe.frontier(heap.len());
+
+    while let Some(State { node, .. }) = heap.pop() {
+        let cost = dist[&node];
         if node == goal {
+            probe.expanded(1);
             let path = reconstruct_path(&parent, start, goal);
             return Ok((path, cost));
         }
 
-        if cost > *dist.get(&node).unwrap_or(&Weight::INFINITY) {
+        if !closed.insert(node) {
             continue;
         }
+        probe.expanded(1);
 
         for (neighbor, weight) in graph.neighbors_with_weights(node)? {
+            probe.relaxed(1);
             let next_cost = cost + weight;
             let neighbor_dist = *dist.get(&neighbor).unwrap_or(&Weight::INFINITY);
 
             if next_cost < neighbor_dist {
                 dist.insert(neighbor, next_cost);
                 parent.insert(neighbor, node);
+                closed.remove(&neighbor);
                 heap.push(State {
-                    cost: next_cost,
+                    cost: next_cost + heuristic(neighbor),
                     node: neighbor,
                 });
             }
         }
+        probe.frontier(heap.len());
     }
 
     Err(GraphError::AlgorithmError(format!(
@@ -148,63 +648,3956 @@ pub fn dijkstra(graph: &Graph, start: NodeId, goal: NodeId) -> Result<(Vec<NodeI
     )))
 }
 
-/// Reconstruct path from parent map
-fn reconstruct_path(
-    parent: &HashMap<NodeId, NodeId>,
-    start: NodeId,
-    goal: NodeId,
-) -> Vec<NodeId> {
-    let mut path = vec![goal];
-    let mut current = goal;
+/// Bellman-Ford single-source shortest path distances
+///
+/// Unlike Dijkstra, edge weights may be negative. Only reachable nodes
+/// are in the result. When a negative cycle is reachable from `source`
+/// no distances exist and this fails with `AlgorithmError` naming the
+/// cycle's nodes; `find_negative_cycle` returns the cycle itself. In an
+/// undirected graph every edge can be crossed both ways, so any negative
+/// edge is such a cycle.
+pub fn bellman_ford(graph: &Graph, source: NodeId) -> Result<HashMap<NodeId, Weight>> {
+    graph.node(source)?;
+    let (node_ids, arcs) = weighted_arcs(graph);
+    let start = node_ids.binary_search(&source).expect("source is a node");
+    let relaxation = bellman_ford_relax(node_ids.len(), &arcs, &[start]);
 
-    while current != start {
-        if let Some(&prev) = parent.get(&current) {
-            path.push(prev);
-            current = prev;
-        } else {
+    if let Some(node) = relaxation.still_relaxing {
+        let cycle = trace_cycle(&relaxation.predecessors, node, &node_ids);
+        return Err(GraphError::AlgorithmError(format!(
+            "Negative cycle {:?} reachable from {}",
+            cycle, source
+        )));
+    }
+    Ok(node_ids
+        .iter()
+        .zip(relaxation.distances)
+        .filter(|(_, distance)| distance.is_finite())
+        .map(|(&id, distance)| (id, distance))
+        .collect())
+}
+
+/// A cycle whose edge weights sum to less than zero, if there is one
+///
+/// The cycle lists its nodes once each in edge order, starting from its
+/// smallest node ID; it returns to its first node from its last. Every
+/// node is a Bellman-Ford source, so cycles anywhere in the graph are
+/// found, in O(V·E) time. In an undirected graph a negative edge is
+/// reported as a cycle of its two endpoints, and a negative self-loop in
+/// any graph as a cycle of one node.
+pub fn find_negative_cycle(graph: &Graph) -> Result<Option<Vec<NodeId>>> {
+    let (node_ids, arcs) = weighted_arcs(graph);
+    let sources: Vec<usize> = (0..node_ids.len()).collect();
+    let relaxation = bellman_ford_relax(node_ids.len(), &arcs, &sources);
+    Ok(relaxation
+        .still_relaxing
+        .map(|node| trace_cycle(&relaxation.predecessors, node, &node_ids)))
+}
+
+/// Node IDs in ascending order and every edge as `(from, to, weight)`
+/// index triples, undirected edges in both directions
+fn weighted_arcs(graph: &Graph) -> (Vec<NodeId>, Vec<(usize, usize, Weight)>) {
+    let mut node_ids = graph.node_ids();
+    node_ids.sort_unstable();
+    let index: HashMap<NodeId, usize> =
+        node_ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+    let mut arcs = Vec::with_capacity(graph.edge_count() * 2);
+    for edge in graph.edges() {
+        let (from, to) = (index[&edge.from], index[&edge.to]);
+        arcs.push((from, to, edge.weight));
+        if !graph.is_directed() && from != to {
+            arcs.push((to, from, edge.weight));
+        }
+    }
+    (node_ids, arcs)
+}
+
+struct Relaxation {
+    distances: Vec<Weight>,
+    predecessors: Vec<Option<usize>>,
+    /// A node whose distance still improved in the V-th pass, which only
+    /// happens when a negative cycle is reachable
+    still_relaxing: Option<usize>,
+}
+
+/// Up to V passes of Bellman-Ford relaxation from `sources`, all at
+/// distance 0, stopping early once a pass changes nothing
+///
+/// With every node a source this is Bellman-Ford from a virtual source
+/// joined to all of them, which converges within V passes unless there is
+/// a negative cycle.
+fn bellman_ford_relax(
+    node_count: usize,
+    arcs: &[(usize, usize, Weight)],
+    sources: &[usize],
+) -> Relaxation {
+    let mut distances = vec![Weight::INFINITY; node_count];
+    let mut predecessors = vec![None; node_count];
+    for &source in sources {
+        distances[source] = 0.0;
+    }
+
+    let mut still_relaxing = None;
+    for _ in 0..node_count {
+        still_relaxing = None;
+        for &(from, to, weight) in arcs {
+            let candidate = distances[from] + weight;
+            if candidate < distances[to] {
+                distances[to] = candidate;
+                predecessors[to] = Some(from);
+                still_relaxing = Some(to);
+            }
+        }
+        if still_relaxing.is_none() {
             break;
         }
     }
+    Relaxation { distances, predecessors, still_relaxing }
+}
+
+/// The cycle the predecessors of `node` lead into, in edge order and
+/// rotated to start at its smallest node ID
+///
+/// Stepping back V times from a node still relaxing after V passes is
+/// guaranteed to land on the cycle.
+fn trace_cycle(predecessors: &[Option<usize>], node: usize, node_ids: &[NodeId]) -> Vec<NodeId> {
+    let back = |node: usize| predecessors[node].expect("relaxed nodes have predecessors");
+    let mut on_cycle = node;
+    for _ in 0..node_ids.len() {
+        on_cycle = back(on_cycle);
+    }
 
-    path.reverse();
-    path
+    let mut cycle = vec![node_ids[on_cycle]];
+    let mut current = back(on_cycle);
+    while current != on_cycle {
+        cycle.push(node_ids[current]);
+        current = back(current);
+    }
+    // Predecessor order runs against the edges
+    cycle.reverse();
+    let smallest = (0..cycle.len()).min_by_key(|&i| cycle[i]).unwrap_or(0);
+    cycle.rotate_left(smallest);
+    cycle
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Floyd-Warshall all-pairs shortest path distances
+///
+/// Rows and columns follow ascending node ID order; unreachable pairs are
+/// `Weight::INFINITY`. Runs in O(n³) time and O(n²) memory.
+pub fn floyd_warshall(graph: &Graph) -> Result<Vec<Vec<Weight>>> {
+    floyd_warshall_with_progress(graph, None).map(|result| result.value)
+}
 
-    fn create_test_graph() -> Graph {
-        let mut graph = Graph::new();
-        let n0 = graph.add_node_simple("A");
-        let n1 = graph.add_node_simple("B");
-        let n2 = graph.add_node_simple("C");
-        let n3 = graph.add_node_simple("D");
+/// `floyd_warshall`, reporting each iteration to `progress`
+///
+/// After a cancelled run, entry `[i][j]` is the length of the shortest path
+/// whose intermediate nodes are among the `completed` smallest IDs, an
+/// upper bound on the true distance.
+pub fn floyd_warshall_with_progress(
+    graph: &Graph,
+    progress: Option<&dyn ProgressSink>,
+) -> Result<PartialResult<Vec<Vec<Weight>>>> {
+    let mut state = FloydWarshallState::new(graph)?;
+    let total = state.dist.len();
+    while !state.is_complete() && !progress::cancelled(progress) {
+        state.step();
+        progress::report(progress, state.k, total);
+    }
+    Ok(PartialResult { value: state.dist, completed: state.k as u64, total: total as u64 })
+}
 
-        graph.add_edge(n0, n1, 1.0).unwrap();
-        graph.add_edge(n1, n2, 2.0).unwrap();
-        graph.add_edge(n0, n3, 4.0).unwrap();
-        graph.add_edge(n3, n2, 1.0).unwrap();
+/// Iterations of `floyd_warshall_resumable` between checkpoints
+pub const FLOYD_WARSHALL_CHECKPOINT_INTERVAL: usize = 100;
 
-        graph
+/// `floyd_warshall`, saving its progress to `checkpoint_path` so that an
+/// interrupted run can pick up where it left off
+///
+/// If a checkpoint exists at `checkpoint_path` the run resumes from it;
+/// otherwise it starts from scratch. The state is saved every
+/// `FLOYD_WARSHALL_CHECKPOINT_INTERVAL` iterations and removed once the
+/// run completes. A checkpoint taken on a graph with a different
+/// `content_hash`, or a malformed one, is rejected with `InvalidData`, see
+/// `FloydWarshallState::validate`. Without a path this is plain
+/// `floyd_warshall`.
+pub fn floyd_warshall_resumable(
+    graph: &Graph,
+    checkpoint_path: Option<&Path>,
+) -> Result<Vec<Vec<Weight>>> {
+    let Some(path) = checkpoint_path else {
+        return floyd_warshall(graph);
+    };
+
+    let mut state = match FloydWarshallState::load_checkpoint(path)? {
+        Some(state) => {
+            state.validate(graph)?;
+            state
+        }
+        None => FloydWarshallState::new(graph)?,
+    };
+
+    while !state.is_complete() {
+        state.step();
+        if state.k % FLOYD_WARSHALL_CHECKPOINT_INTERVAL == 0 && !state.is_complete() {
+            state.save_checkpoint(path)?;
+        }
     }
 
-    #[test]
-    fn test_bfs() {
-        let graph = create_test_graph();
-        let path = bfs(&graph, 0, 2).unwrap();
-        assert!(!path.is_empty());
-        assert_eq!(path[0], 0);
-        assert_eq!(path[path.len() - 1], 2);
+    fs::remove_file(path).or_else(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => Ok(()),
+        _ => Err(GraphError::InvalidData(format!("Failed to remove checkpoint: {}", e))),
+    })?;
+    Ok(state.dist)
+}
+
+/// Direct-edge distance matrix in ascending node ID order
+///
+/// The diagonal is zero and parallel edges keep their lightest weight.
+pub(crate) fn initial_distances(graph: &Graph) -> Result<Vec<Vec<Weight>>> {
+    let mut node_ids = graph.node_ids();
+    node_ids.sort_unstable();
+    let index: HashMap<NodeId, usize> =
+        node_ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+
+    let n = node_ids.len();
+    let mut dist = vec![vec![Weight::INFINITY; n]; n];
+    for (i, &id) in node_ids.iter().enumerate() {
+        dist[i][i] = 0.0;
+        for (neighbor, weight) in graph.neighbors_with_weights(id)? {
+            let j = index[&neighbor];
+            if weight < dist[i][j] {
+                dist[i][j] = weight;
+            }
+        }
     }
 
-    #[test]
-    fn test_dijkstra() {
-        let graph = create_test_graph();
-        let (path, cost) = dijkstra(&graph, 0, 2).unwrap();
-        assert!(!path.is_empty());
-        assert_eq!(path[0], 0);
-        assert_eq!(path[path.len() - 1], 2);
-        assert!(cost > 0.0);
+    Ok(dist)
+}
+
+/// Transitive closure of the graph
+///
+/// Rows and columns follow ascending node ID order; entry `[i][j]` is
+/// whether the j-th smallest ID is reachable from the i-th, see
+/// `Graph::reachable_from`. The diagonal is always `true`. Runs one
+/// traversal per node, so it takes `O(n * (n + m))` time and `O(n^2)`
+/// space.
+pub fn reachability_matrix(graph: &Graph) -> Vec<Vec<bool>> {
+    let mut node_ids = graph.node_ids();
+    node_ids.sort_unstable();
+
+    node_ids
+        .iter()
+        .map(|&source| {
+            let reached = graph.reachable_from(source);
+            node_ids.iter().map(|id| reached.contains(id)).collect()
+        })
+        .collect()
+}
+
+/// Connected components, weakly connected for directed graphs
+///
+/// Each component is sorted by node ID and components are ordered by
+/// their smallest node ID.
+pub fn connected_components(graph: &Graph) -> Result<Vec<Vec<NodeId>>> {
+    let (node_ids, adjacency) = undirected_adjacency(graph)?;
+
+    let mut visited = vec![false; node_ids.len()];
+    let mut components = Vec::new();
+    for start in 0..node_ids.len() {
+        if visited[start] {
+            continue;
+        }
+
+        let mut component = Vec::new();
+        let mut stack = vec![start];
+        visited[start] = true;
+        while let Some(node) = stack.pop() {
+            component.push(node_ids[node]);
+            for &neighbor in &adjacency[node] {
+                if !visited[neighbor] {
+                    visited[neighbor] = true;
+                    stack.push(neighbor);
+                }
+            }
+        }
+
+        component.sort_unstable();
+        components.push(component);
+    }
+
+    Ok(components)
+}
+
+/// Core number of every node: the largest `k` such that the node belongs
+/// to a subgraph in which every node has at least `k` neighbors
+///
+/// Edge directions, self-loops and parallel edges are ignored. Computed
+/// by repeatedly removing a node of smallest remaining degree
+/// (Batagelj and Zaversnik), in `O(m log n)`.
+pub fn core_numbers(graph: &Graph) -> Result<HashMap<NodeId, usize>> {
+    let (node_ids, adjacency) = undirected_adjacency(graph)?;
+    let mut degree: Vec<usize> = adjacency.iter().map(HashSet::len).collect();
+    let mut queue: std::collections::BTreeSet<(usize, usize)> =
+        degree.iter().enumerate().map(|(node, &d)| (d, node)).collect();
+    let mut removed = vec![false; node_ids.len()];
+    let mut cores = HashMap::with_capacity(node_ids.len());
+    let mut core = 0;
+
+    while let Some((d, node)) = queue.pop_first() {
+        core = core.max(d);
+        cores.insert(node_ids[node], core);
+        removed[node] = true;
+        for &neighbor in &adjacency[node] {
+            if !removed[neighbor] && degree[neighbor] > d {
+                queue.remove(&(degree[neighbor], neighbor));
+                degree[neighbor] -= 1;
+                queue.insert((degree[neighbor], neighbor));
+            }
+        }
+    }
+    Ok(cores)
+}
+
+/// Largest graph accepted by `exact_diameter_small`
+pub const EXACT_DIAMETER_MAX_NODES: usize = 1_000;
+
+/// Lower bound on the diameter by a double BFS sweep
+///
+/// Runs a BFS from the smallest node ID to its farthest node `u`, then
+/// from `u` to its farthest node `v`, and returns `(dist(u, v), u, v)`.
+/// On trees the bound is exact, and on most connected graphs it is in
+/// practice. The diameter here is the longest finite hop distance, so
+/// only the first sweep's component is explored on disconnected graphs.
+/// On directed graphs BFS follows outgoing edges. Ties between equally
+/// far nodes go to the smallest ID. Fails with `InvalidData` on an empty
+/// graph.
+pub fn double_sweep_diameter(graph: &Graph) -> Result<(usize, NodeId, NodeId)> {
+    let start = first_node(graph)?;
+    let (u, _) = farthest(&hop_distances_from(graph, start), start);
+    let (v, distance) = farthest(&hop_distances_from(graph, u), u);
+    Ok((distance, u, v))
+}
+
+/// Exact diameter, the longest finite hop distance between two nodes
+///
+/// On undirected graphs the double sweep gives a lower bound and twice
+/// the eccentricity of its first node an upper bound on the diameter of
+/// that component; when the two meet on a connected graph no further
+/// search is needed. Otherwise every node's eccentricity is computed by
+/// BFS, so this is limited to `EXACT_DIAMETER_MAX_NODES` nodes. Fails
+/// with `InvalidData` on an empty graph.
+pub fn exact_diameter_small(graph: &Graph) -> Result<usize> {
+    if graph.node_count() > EXACT_DIAMETER_MAX_NODES {
+        return Err(GraphError::InvalidParameter(format!(
+            "exact diameter is limited to {} nodes (graph has {}); \
+             use double_sweep_diameter for a lower bound",
+            EXACT_DIAMETER_MAX_NODES,
+            graph.node_count()
+        )));
+    }
+
+    let start = first_node(graph)?;
+    let from_start = hop_distances_from(graph, start);
+    let (u, eccentricity) = farthest(&from_start, start);
+    let (_, lower) = farthest(&hop_distances_from(graph, u), u);
+    let connected = from_start.len() == graph.node_count();
+    if !graph.is_directed() && connected && lower == 2 * eccentricity {
+        return Ok(lower);
+    }
+
+    let diameter = graph
+        .node_ids()
+        .into_iter()
+        .filter(|&node| node != u)
+        .map(|node| farthest(&hop_distances_from(graph, node), node).1)
+        .fold(lower, usize::max);
+    Ok(diameter)
+}
+
+/// Smallest node ID, where the diameter sweeps start
+fn first_node(graph: &Graph) -> Result<NodeId> {
+    graph
+        .node_ids()
+        .into_iter()
+        .min()
+        .ok_or_else(|| GraphError::InvalidData("diameter of an empty graph".to_string()))
+}
+
+/// The node farthest from `source` in `distances` and its distance, ties
+/// going to the smallest ID
+fn farthest(distances: &HashMap<NodeId, usize>, source: NodeId) -> (NodeId, usize) {
+    distances.iter().fold((source, 0), |(best, best_distance), (&node, &distance)| {
+        if distance > best_distance || (distance == best_distance && node < best) {
+            (node, distance)
+        } else {
+            (best, best_distance)
+        }
+    })
+}
+
+/// BFS hop distances from `source` to every node it reaches
+fn hop_distances_from(graph: &Graph, source: NodeId) -> HashMap<NodeId, usize> {
+    let mut distances = HashMap::from([(source, 0)]);
+    let mut queue = VecDeque::from([source]);
+    while let Some(node) = queue.pop_front() {
+        let next = distances[&node] + 1;
+        for (neighbor, _) in graph.weighted_neighbors_iter(node) {
+            if let std::collections::hash_map::Entry::Vacant(entry) = distances.entry(neighbor) {
+                entry.insert(next);
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    distances
+}
+
+/// Default HyperLogLog precision for `neighborhood_function`
+///
+/// `2^10` registers per counter give a relative standard error of about
+/// `1.04 / sqrt(1024)`, i.e. 3.3%.
+pub const DEFAULT_HLL_PRECISION: u8 = 10;
+
+/// Estimated number of nodes within `r` hops of every node (HyperANF)
+///
+/// Entry `r` of the result maps each node to the approximate size of its
+/// ball of radius `r`, for `r` in `0..=max_k`; the ball includes the node
+/// itself. On directed graphs balls follow outgoing edges. Uses
+/// `DEFAULT_HLL_PRECISION`, see `neighborhood_function_with_precision`.
+pub fn neighborhood_function(graph: &Graph, max_k: usize) -> Vec<HashMap<NodeId, f64>> {
+    hyper_anf(graph, max_k, DEFAULT_HLL_PRECISION, false)
+}
+
+/// `neighborhood_function` with `2^precision` registers per counter
+///
+/// Each node keeps a HyperLogLog counter of its ball, and the ball of
+/// radius `r + 1` is the union of the node's counter with its neighbors'
+/// counters at radius `r`, so memory is `O(n * 2^precision)` bytes and
+/// each radius costs `O(m * 2^precision)`. The relative standard error of
+/// each estimate is about `1.04 / sqrt(2^precision)`. `precision` must be
+/// between 4 and 16.
+pub fn neighborhood_function_with_precision(
+    graph: &Graph,
+    max_k: usize,
+    precision: u8,
+) -> Result<Vec<HashMap<NodeId, f64>>> {
+    if !(4..=16).contains(&precision) {
+        return Err(GraphError::InvalidParameter(format!(
+            "precision must be between 4 and 16, got {}",
+            precision
+        )));
+    }
+    Ok(hyper_anf(graph, max_k, precision, false))
+}
+
+/// Approximate effective diameter from a neighborhood function
+///
+/// The effective diameter is the (interpolated) number of hops within
+/// which 90% of the reachable pairs of distinct nodes lie, taking the last
+/// radius as reaching every pair. It is only accurate when the largest
+/// radius covers the graph's diameter. Returns `None` when no distinct
+/// pair is reachable.
+pub fn effective_diameter(neighborhood: &[HashMap<NodeId, f64>]) -> Option<f64> {
+    // Reachable pairs of distinct nodes within each radius
+    let node_count = neighborhood.first()?.len() as f64;
+    let pairs: Vec<f64> = neighborhood
+        .iter()
+        .map(|balls| (balls.values().sum::<f64>() - node_count).max(0.0))
+        .collect();
+
+    // Below half a pair, the estimate only reflects counter noise
+    let total = *pairs.last()?;
+    if total < 0.5 {
+        return None;
+    }
+
+    let target = 0.9 * total;
+    let radius = pairs.iter().position(|&count| count >= target)?;
+    if radius == 0 {
+        return Some(0.0);
+    }
+    let below = pairs[radius - 1];
+    Some((radius - 1) as f64 + (target - below) / (pairs[radius] - below))
+}
+
+/// HyperANF iteration, stopping early once counters stop changing when
+/// `until_stable` is set and otherwise padding up to `max_k`
+pub(crate) fn hyper_anf(
+    graph: &Graph,
+    max_k: usize,
+    precision: u8,
+    until_stable: bool,
+) -> Vec<HashMap<NodeId, f64>> {
+    let mut node_ids = graph.node_ids();
+    node_ids.sort_unstable();
+    let index: HashMap<NodeId, usize> =
+        node_ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+    let adjacency: Vec<Vec<usize>> = node_ids
+        .iter()
+        .map(|&id| {
+            graph
+                .neighbors(id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|neighbor| index[&neighbor])
+                .collect()
+        })
+        .collect();
+
+    let mut counters: Vec<HyperLogLog> = node_ids
+        .iter()
+        .map(|&id| {
+            let mut counter = HyperLogLog::new(precision);
+            counter.insert(id_bits(id));
+            counter
+        })
+        .collect();
+
+    let estimates = |counters: &[HyperLogLog]| -> HashMap<NodeId, f64> {
+        node_ids
+            .iter()
+            .zip(counters)
+            .map(|(&id, counter)| (id, counter.estimate()))
+            .collect()
+    };
+
+    let mut result = vec![estimates(&counters)];
+    while result.len() <= max_k {
+        let mut next = counters.clone();
+        let mut changed = false;
+        for (node, neighbors) in adjacency.iter().enumerate() {
+            for &neighbor in neighbors {
+                changed |= next[node].union(&counters[neighbor]);
+            }
+        }
+        counters = next;
+
+        if !changed {
+            if !until_stable {
+                let last = result.last().cloned().expect("radius 0 is always present");
+                result.resize(max_k + 1, last);
+            }
+            break;
+        }
+        result.push(estimates(&counters));
+    }
+
+    result
+}
+
+/// HyperLogLog distinct counter with `2^precision` registers
+#[derive(Clone)]
+struct HyperLogLog {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    fn new(precision: u8) -> Self {
+        Self {
+            precision,
+            registers: vec![0; 1 << precision],
+        }
+    }
+
+    fn insert(&mut self, value: u64) {
+        let hash = splitmix64(value);
+        let register = (hash >> (64 - self.precision)) as usize;
+        // Rank of the first set bit in the remaining bits, capped for hash = 0
+        let rest = hash << self.precision;
+        let rank = (rest.leading_zeros() + 1).min(65 - u32::from(self.precision)) as u8;
+        self.registers[register] = self.registers[register].max(rank);
+    }
+
+    /// Merge `other` into `self`, returning whether any register grew
+    fn union(&mut self, other: &HyperLogLog) -> bool {
+        let mut changed = false;
+        for (register, &theirs) in self.registers.iter_mut().zip(&other.registers) {
+            if theirs > *register {
+                *register = theirs;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    fn estimate(&self) -> f64 {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let sum: f64 = self.registers.iter().map(|&r| 2f64.powi(-i32::from(r))).sum();
+        let raw = alpha * m * m / sum;
+
+        // Linear counting is more accurate while many registers are empty
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        if raw <= 2.5 * m && zeros > 0 {
+            m * (m / zeros as f64).ln()
+        } else {
+            raw
+        }
+    }
+}
+
+/// Fixed 64-bit mixer, so estimates are reproducible across runs
+pub(crate) fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Default pass limit for `label_propagation` in `coarsen_recursive`
+pub const DEFAULT_LABEL_PROPAGATION_ITERATIONS: usize = 20;
+
+/// Seed of the visiting order and tie-breaks in `label_propagation`
+const LABEL_PROPAGATION_SEED: u64 = 0;
+
+/// Communities found by weighted label propagation
+///
+/// Every node starts in a community of its own. Visiting nodes in a
+/// shuffled order, each joins the community with the largest total edge
+/// weight among its neighbors; on a tie it stays in its own if that is
+/// among the tied, and otherwise takes a random tied label. Shuffles and
+/// tie-breaks come from a fixed seed, so results are reproducible. Edges
+/// count in both directions on directed graphs, and self-loops vote for
+/// the node's own community. Stops after a pass that changes nothing, or
+/// after `max_iterations` passes. Communities are numbered from 0 in
+/// order of their smallest member.
+pub fn label_propagation(graph: &Graph, max_iterations: usize) -> HashMap<NodeId, usize> {
+    let mut node_ids = graph.node_ids();
+    node_ids.sort_unstable();
+    let index: HashMap<NodeId, usize> =
+        node_ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+    let mut adjacency: Vec<Vec<(usize, Weight)>> = vec![Vec::new(); node_ids.len()];
+    for edge in graph.edges() {
+        let (a, b) = (index[&edge.from], index[&edge.to]);
+        adjacency[a].push((b, edge.weight));
+        if a != b {
+            adjacency[b].push((a, edge.weight));
+        }
+    }
+
+    let mut rng = StdRng::seed_from_u64(LABEL_PROPAGATION_SEED);
+    let mut order: Vec<usize> = (0..node_ids.len()).collect();
+    let mut labels: Vec<usize> = (0..node_ids.len()).collect();
+    for _ in 0..max_iterations {
+        let mut changed = false;
+        order.shuffle(&mut rng);
+        for &node in &order {
+            let mut votes: HashMap<usize, Weight> = HashMap::new();
+            for &(neighbor, weight) in &adjacency[node] {
+                *votes.entry(labels[neighbor]).or_insert(0.0) += weight;
+            }
+            let Some(best) = votes.values().copied().reduce(f64::max) else {
+                continue;
+            };
+            if votes.get(&labels[node]).is_some_and(|&own| own >= best) {
+                continue;
+            }
+            let mut tied: Vec<usize> = votes
+                .iter()
+                .filter(|&(_, &weight)| weight >= best)
+                .map(|(&label, _)| label)
+                .collect();
+            tied.sort_unstable();
+            labels[node] = tied[rng.gen_range(0..tied.len())];
+            changed = true;
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let mut numbering: HashMap<usize, usize> = HashMap::new();
+    node_ids
+        .iter()
+        .zip(labels)
+        .map(|(&id, label)| {
+            let next = numbering.len();
+            (id, *numbering.entry(label).or_insert(next))
+        })
+        .collect()
+}
+
+/// How `coarsen_with_options` treats edges within a community
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CoarsenOptions {
+    /// Leave out the self-loops recording the weight inside communities
+    pub drop_self_loops: bool,
+}
+
+impl CoarsenOptions {
+    /// Leave out intra-community weight
+    pub fn drop_self_loops(mut self) -> Self {
+        self.drop_self_loops = true;
+        self
+    }
+}
+
+/// The graph with one super-node per community of `partition`
+///
+/// `partition` maps each node to a community label; a node missing from
+/// it is an `InvalidParameter` error. Super-nodes are numbered from 0 in
+/// ascending label order, labelled `community_<label>` and have a
+/// `member_count` property; the returned map sends each node to its
+/// super-node. All edges between two communities become one edge weighted
+/// by their sum, and the edges inside a community a self-loop, so the
+/// total edge weight is preserved. The coarse graph is directed if
+/// `graph` is. This is the aggregation step of multilevel community
+/// detection, see `coarsen_recursive`.
+pub fn coarsen(
+    graph: &Graph,
+    partition: &HashMap<NodeId, usize>,
+) -> Result<(Graph, HashMap<NodeId, NodeId>)> {
+    coarsen_with_options(graph, partition, CoarsenOptions::default())
+}
+
+/// `coarsen`, optionally without intra-community self-loops
+pub fn coarsen_with_options(
+    graph: &Graph,
+    partition: &HashMap<NodeId, usize>,
+    options: CoarsenOptions,
+) -> Result<(Graph, HashMap<NodeId, NodeId>)> {
+    let mut labels = Vec::with_capacity(graph.node_count());
+    for id in graph.node_ids() {
+        let label = partition.get(&id).ok_or_else(|| {
+            GraphError::InvalidParameter(format!("node {} is not in the partition", id))
+        })?;
+        labels.push(*label);
+    }
+    labels.sort_unstable();
+    labels.dedup();
+    let super_node: HashMap<usize, usize> =
+        labels.iter().enumerate().map(|(i, &label)| (label, i)).collect();
+    let mut member_counts = vec![0usize; labels.len()];
+    let mapping: HashMap<NodeId, NodeId> = graph
+        .node_ids()
+        .into_iter()
+        .map(|id| {
+            let index = super_node[&partition[&id]];
+            member_counts[index] += 1;
+            (id, node_id(index))
+        })
+        .collect();
+
+    let mut coarse = if graph.is_directed() { Graph::new_directed() } else { Graph::new() };
+    for (i, label) in labels.iter().enumerate() {
+        let node = Node::new(node_id(i), format!("community_{}", label))
+            .with_property("member_count", member_counts[i] as f64);
+        coarse.add_node(node);
+    }
+
+    let mut weights: HashMap<(NodeId, NodeId), Weight> = HashMap::new();
+    for edge in graph.edges() {
+        let (a, b) = (mapping[&edge.from], mapping[&edge.to]);
+        if a == b && options.drop_self_loops {
+            continue;
+        }
+        let key = if graph.is_directed() || a <= b { (a, b) } else { (b, a) };
+        *weights.entry(key).or_insert(0.0) += edge.weight;
+    }
+    let mut weights: Vec<_> = weights.into_iter().collect();
+    weights.sort_unstable_by_key(|&(key, _)| key);
+    for ((a, b), weight) in weights {
+        coarse.add_edge(a, b, weight).expect("super-nodes were added");
+    }
+
+    Ok((coarse, mapping))
+}
+
+/// Alternate `label_propagation` and `coarsen` up to `levels` times
+///
+/// Returns each coarse graph with the map from the nodes of the level
+/// below it (for the first, of `graph`) to its super-nodes. Intra-community
+/// self-loops make super-nodes keep their own community, so this stops
+/// early once a level merges no nodes.
+pub fn coarsen_recursive(
+    graph: &Graph,
+    levels: usize,
+) -> Result<Vec<(Graph, HashMap<NodeId, NodeId>)>> {
+    let mut hierarchy: Vec<(Graph, HashMap<NodeId, NodeId>)> = Vec::new();
+    for _ in 0..levels {
+        let current = hierarchy.last().map_or(graph, |(coarse, _)| coarse);
+        let partition = label_propagation(current, DEFAULT_LABEL_PROPAGATION_ITERATIONS);
+        let (coarse, mapping) = coarsen(current, &partition)?;
+        if coarse.node_count() == current.node_count() {
+            break;
+        }
+        hierarchy.push((coarse, mapping));
+    }
+    Ok(hierarchy)
+}
+
+/// Default for `PartitionOptions::imbalance`
+pub const DEFAULT_PARTITION_IMBALANCE: f64 = 0.05;
+
+/// Refinement passes per level in `partition`
+const PARTITION_REFINEMENT_PASSES: usize = 10;
+
+/// `PartitionMethod::Multilevel` stops coarsening at this many nodes per
+/// part
+const COARSEST_NODES_PER_PART: usize = 15;
+
+/// How `partition` splits a graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionMethod {
+    /// Grow `k` regions breadth-first from spread-out seeds, always
+    /// extending the smallest region that can still grow
+    RegionGrowing,
+    /// `RegionGrowing`, then move boundary nodes to the part most of their
+    /// edge weight leads to while the balance allows
+    LabelPropagation,
+    /// Coarsen by heavy-edge matching, partition the coarsest graph by
+    /// refined region growing and project back, refining at every level
+    Multilevel,
+}
+
+/// Balance and seed for `partition_with_options`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PartitionOptions {
+    /// How far a part may exceed an even share of the nodes: no part gets
+    /// more than `max(ceil(n / k), floor((1 + imbalance) * n / k))`
+    pub imbalance: f64,
+    /// Seed of the seed choice, visiting orders and matchings
+    pub seed: u64,
+}
+
+impl Default for PartitionOptions {
+    fn default() -> Self {
+        Self { imbalance: DEFAULT_PARTITION_IMBALANCE, seed: 0 }
+    }
+}
+
+impl PartitionOptions {
+    /// Allow parts up to `(1 + imbalance)` times an even share
+    pub fn with_imbalance(mut self, imbalance: f64) -> Self {
+        self.imbalance = imbalance;
+        self
+    }
+
+    /// Use `seed` for the random choices
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+/// Size and edge cut of a partition, see `partition_quality`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionQuality {
+    /// Nodes in each part, indexed by part
+    pub part_sizes: Vec<usize>,
+    /// Edges whose endpoints are in different parts
+    pub edge_cut: usize,
+    /// Total weight of those edges
+    pub cut_weight: Weight,
+    /// Largest part relative to an even share, 1.0 when perfectly even
+    pub balance: f64,
+}
+
+/// Split the nodes into `k` parts of balanced size with few edges
+/// between them, for sharding work
+///
+/// `partition_with_options` with the default options.
+pub fn partition(
+    graph: &Graph,
+    k: usize,
+    method: PartitionMethod,
+) -> Result<HashMap<NodeId, usize>> {
+    partition_with_options(graph, k, method, PartitionOptions::default())
+}
+
+/// Split the nodes into `k` parts, recording each node's part in `0..k`
+///
+/// Edges count in both directions on directed graphs and are weighted by
+/// their weight when minimizing the cut; self-loops are ignored. No part
+/// exceeds the size bound of `options.imbalance`, and every part is
+/// non-empty if the graph has at least `k` nodes. Results are
+/// reproducible for a given seed. Fails with `InvalidParameter` if `k` is
+/// 0 or the imbalance is negative.
+pub fn partition_with_options(
+    graph: &Graph,
+    k: usize,
+    method: PartitionMethod,
+    options: PartitionOptions,
+) -> Result<HashMap<NodeId, usize>> {
+    if k == 0 {
+        return Err(GraphError::InvalidParameter("cannot partition into 0 parts".to_string()));
+    }
+    if options.imbalance.is_nan() || options.imbalance < 0.0 {
+        return Err(GraphError::InvalidParameter(format!(
+            "imbalance must be non-negative, got {}",
+            options.imbalance
+        )));
+    }
+
+    let mut node_ids = graph.node_ids();
+    node_ids.sort_unstable();
+    let n = node_ids.len();
+    if n == 0 {
+        return Ok(HashMap::new());
+    }
+    let even = n as f64 / k as f64;
+    let cap = n.div_ceil(k).max(((1.0 + options.imbalance) * even).floor() as usize);
+
+    let work = PartitionGraph::new(graph, &node_ids);
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    let parts = match method {
+        PartitionMethod::RegionGrowing => work.grow_regions(k, cap, &mut rng),
+        PartitionMethod::LabelPropagation => {
+            let mut parts = work.grow_regions(k, cap, &mut rng);
+            work.refine(&mut parts, k, cap, &mut rng);
+            parts
+        }
+        PartitionMethod::Multilevel => work.multilevel(k, cap, &mut rng),
+    };
+    Ok(node_ids.into_iter().zip(parts).collect())
+}
+
+/// Part sizes, edge cut and balance of `partition`
+///
+/// Parts are numbered `0..=max label`. Fails with `InvalidParameter` if a
+/// node is missing from `partition`.
+pub fn partition_quality(
+    graph: &Graph,
+    partition: &HashMap<NodeId, usize>,
+) -> Result<PartitionQuality> {
+    let part = |id: NodeId| {
+        partition.get(&id).copied().ok_or_else(|| {
+            GraphError::InvalidParameter(format!("node {} is not in the partition", id))
+        })
+    };
+    let mut part_sizes = Vec::new();
+    for id in graph.node_ids() {
+        let label = part(id)?;
+        if label >= part_sizes.len() {
+            part_sizes.resize(label + 1, 0);
+        }
+        part_sizes[label] += 1;
+    }
+
+    let (mut edge_cut, mut cut_weight) = (0, 0.0);
+    for edge in graph.edges() {
+        if part(edge.from)? != part(edge.to)? {
+            edge_cut += 1;
+            cut_weight += edge.weight;
+        }
+    }
+
+    let largest = part_sizes.iter().copied().max().unwrap_or(0);
+    let balance = if graph.node_count() == 0 {
+        1.0
+    } else {
+        largest as f64 * part_sizes.len() as f64 / graph.node_count() as f64
+    };
+    Ok(PartitionQuality { part_sizes, edge_cut, cut_weight, balance })
+}
+
+const UNASSIGNED: usize = usize::MAX;
+
+/// Undirected, node-weighted graph on `0..n` that `partition` works on;
+/// coarse levels of the multilevel scheme weigh each node by the number
+/// of original nodes it stands for
+struct PartitionGraph {
+    adjacency: Vec<Vec<(usize, Weight)>>,
+    weights: Vec<usize>,
+}
+
+impl PartitionGraph {
+    fn new(graph: &Graph, node_ids: &[NodeId]) -> Self {
+        let index: HashMap<NodeId, usize> =
+            node_ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+        let mut adjacency = vec![Vec::new(); node_ids.len()];
+        for edge in graph.edges() {
+            let (a, b) = (index[&edge.from], index[&edge.to]);
+            if a != b {
+                adjacency[a].push((b, edge.weight));
+                adjacency[b].push((a, edge.weight));
+            }
+        }
+        Self { adjacency, weights: vec![1; node_ids.len()] }
+    }
+
+    fn len(&self) -> usize {
+        self.adjacency.len()
+    }
+
+    /// Up to `k` seeds, each the node farthest in hops from those before,
+    /// unreachable nodes first
+    fn spread_seeds(&self, k: usize, rng: &mut StdRng) -> Vec<usize> {
+        let mut distance = vec![usize::MAX; self.len()];
+        let mut seeds = vec![rng.gen_range(0..self.len())];
+        while seeds.len() < k.min(self.len()) {
+            let seed = *seeds.last().expect("seeds is not empty");
+            distance[seed] = 0;
+            let mut queue = VecDeque::from([seed]);
+            while let Some(node) = queue.pop_front() {
+                for &(neighbor, _) in &self.adjacency[node] {
+                    if distance[node] + 1 < distance[neighbor] {
+                        distance[neighbor] = distance[node] + 1;
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+            let farthest = (0..self.len())
+                .max_by_key(|&node| (distance[node], std::cmp::Reverse(node)))
+                .expect("graph is not empty");
+            seeds.push(farthest);
+        }
+        seeds
+    }
+
+    /// Region growing, see `PartitionMethod::RegionGrowing`
+    ///
+    /// Regions stop growing at `cap`; nodes no region can reach restart
+    /// the smallest one. Only a node heavier than the room left anywhere
+    /// can push a region past `cap`.
+    fn grow_regions(&self, k: usize, cap: usize, rng: &mut StdRng) -> Vec<usize> {
+        let mut parts = vec![UNASSIGNED; self.len()];
+        let mut sizes = vec![0; k];
+        let mut frontiers = vec![VecDeque::new(); k];
+        for (part, seed) in self.spread_seeds(k, rng).into_iter().enumerate() {
+            parts[seed] = part;
+            sizes[part] += self.weights[seed];
+            frontiers[part].push_back(seed);
+        }
+
+        let mut assigned = k.min(self.len());
+        let mut next_unassigned = 0;
+        while assigned < self.len() {
+            let growing = (0..k)
+                .filter(|&part| sizes[part] < cap && !frontiers[part].is_empty())
+                .min_by_key(|&part| (sizes[part], part));
+            let Some(part) = growing else {
+                while parts[next_unassigned] != UNASSIGNED {
+                    next_unassigned += 1;
+                }
+                let part = (0..k).min_by_key(|&part| (sizes[part], part)).expect("k > 0");
+                parts[next_unassigned] = part;
+                sizes[part] += self.weights[next_unassigned];
+                frontiers[part].push_back(next_unassigned);
+                assigned += 1;
+                continue;
+            };
+
+            let node = frontiers[part].pop_front().expect("frontier is not empty");
+            for &(neighbor, _) in &self.adjacency[node] {
+                if parts[neighbor] == UNASSIGNED && sizes[part] + self.weights[neighbor] <= cap {
+                    parts[neighbor] = part;
+                    sizes[part] += self.weights[neighbor];
+                    frontiers[part].push_back(neighbor);
+                    assigned += 1;
+                }
+            }
+        }
+        parts
+    }
+
+    /// Bring parts within `cap`, then move nodes to the part most of
+    /// their edge weight leads to while that has room, never emptying a
+    /// part
+    fn refine(&self, parts: &mut [usize], k: usize, cap: usize, rng: &mut StdRng) {
+        let mut sizes = vec![0; k];
+        for (node, &part) in parts.iter().enumerate() {
+            sizes[part] += self.weights[node];
+        }
+        let mut order: Vec<usize> = (0..self.len()).collect();
+        let mut connection = vec![0.0; k];
+        let mut touched: Vec<usize> = Vec::new();
+
+        for _ in 0..PARTITION_REFINEMENT_PASSES {
+            // A balancing pass only moves nodes out of overloaded parts,
+            // even if that raises the cut
+            let balancing = sizes.iter().any(|&size| size > cap);
+            let mut moved = false;
+            order.shuffle(rng);
+            for &node in &order {
+                let (own, weight) = (parts[node], self.weights[node]);
+                if sizes[own] == weight || (balancing && sizes[own] <= cap) {
+                    continue;
+                }
+                for &(neighbor, edge_weight) in &self.adjacency[node] {
+                    let part = parts[neighbor];
+                    if !touched.contains(&part) {
+                        touched.push(part);
+                    }
+                    connection[part] += edge_weight;
+                }
+
+                let fits = |part: usize| part != own && sizes[part] + weight <= cap;
+                let strongest = touched
+                    .iter()
+                    .copied()
+                    .filter(|&part| fits(part))
+                    .max_by(|&a, &b| connection[a].total_cmp(&connection[b]).then(b.cmp(&a)));
+                let target = if balancing {
+                    strongest.or_else(|| {
+                        (0..k).filter(|&part| fits(part)).min_by_key(|&part| (sizes[part], part))
+                    })
+                } else {
+                    strongest.filter(|&part| connection[part] > connection[own])
+                };
+                for part in touched.drain(..) {
+                    connection[part] = 0.0;
+                }
+
+                if let Some(target) = target {
+                    sizes[own] -= weight;
+                    sizes[target] += weight;
+                    parts[node] = target;
+                    moved = true;
+                }
+            }
+            if !moved {
+                break;
+            }
+        }
+    }
+
+    /// Merge each node with its unmatched neighbor over the heaviest edge,
+    /// visiting nodes in random order; returns the coarse graph and the
+    /// coarse node of every node
+    fn coarsen_by_matching(&self, rng: &mut StdRng) -> (PartitionGraph, Vec<usize>) {
+        let mut order: Vec<usize> = (0..self.len()).collect();
+        order.shuffle(rng);
+        let mut coarse_node = vec![UNASSIGNED; self.len()];
+        let mut weights = Vec::new();
+        for &node in &order {
+            if coarse_node[node] != UNASSIGNED {
+                continue;
+            }
+            let mate = self.adjacency[node]
+                .iter()
+                .filter(|&&(neighbor, _)| coarse_node[neighbor] == UNASSIGNED)
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|&(neighbor, _)| neighbor);
+            coarse_node[node] = weights.len();
+            let mut weight = self.weights[node];
+            if let Some(mate) = mate {
+                coarse_node[mate] = weights.len();
+                weight += self.weights[mate];
+            }
+            weights.push(weight);
+        }
+
+        let mut edges: Vec<HashMap<usize, Weight>> = vec![HashMap::new(); weights.len()];
+        for (node, neighbors) in self.adjacency.iter().enumerate() {
+            for &(neighbor, weight) in neighbors {
+                let (a, b) = (coarse_node[node], coarse_node[neighbor]);
+                if a != b {
+                    *edges[a].entry(b).or_insert(0.0) += weight;
+                }
+            }
+        }
+        let adjacency = edges
+            .into_iter()
+            .map(|neighbors| {
+                let mut neighbors: Vec<(usize, Weight)> = neighbors.into_iter().collect();
+                neighbors.sort_unstable_by_key(|&(neighbor, _)| neighbor);
+                neighbors
+            })
+            .collect();
+        (PartitionGraph { adjacency, weights }, coarse_node)
+    }
+
+    /// The multilevel scheme, see `PartitionMethod::Multilevel`
+    fn multilevel(self, k: usize, cap: usize, rng: &mut StdRng) -> Vec<usize> {
+        let mut levels = vec![self];
+        let mut projections: Vec<Vec<usize>> = Vec::new();
+        loop {
+            let current = levels.last().expect("levels is not empty");
+            if current.len() <= COARSEST_NODES_PER_PART * k {
+                break;
+            }
+            let (coarse, projection) = current.coarsen_by_matching(rng);
+            // Stop once matching barely shrinks the graph
+            if coarse.len() * 20 > current.len() * 19 {
+                break;
+            }
+            levels.push(coarse);
+            projections.push(projection);
+        }
+
+        let coarsest = levels.last().expect("levels is not empty");
+        let mut parts = coarsest.grow_regions(k, cap, rng);
+        coarsest.refine(&mut parts, k, cap, rng);
+        for (level, projection) in levels.iter().rev().skip(1).zip(projections.iter().rev()) {
+            parts = projection.iter().map(|&coarse| parts[coarse]).collect();
+            level.refine(&mut parts, k, cap, rng);
+        }
+        parts
+    }
+}
+
+/// How `coalesce_parallel_edges` merges the edges between a pair of nodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoalesceStrategy {
+    /// Keep the edge added first
+    KeepFirst,
+    /// Keep the edge added last
+    KeepLast,
+    /// Keep the first edge, weighted by the sum of all the weights
+    SumWeights,
+    /// Keep the heaviest edge, the first one among equals
+    MaxWeight,
+    /// Keep the lightest edge, the first one among equals
+    MinWeight,
+}
+
+/// Merge parallel edges into one per pair of nodes, returning how many
+/// edges were removed
+///
+/// In an undirected graph, edges `a -- b` and `b -- a` are parallel. The
+/// merged edge keeps the endpoints and type of the edge `strategy` picks
+/// and takes the place of the pair's first edge, so edges that had no
+/// parallel keep their relative order. See `Graph::has_parallel_edges`.
+pub fn coalesce_parallel_edges(graph: &mut Graph, strategy: CoalesceStrategy) -> usize {
+    let mut merged: Vec<Edge> = Vec::with_capacity(graph.edge_count());
+    let mut slots: HashMap<(NodeId, NodeId), usize> = HashMap::new();
+    for edge in graph.edges() {
+        let slot = match slots.entry(graph.endpoint_key(edge)) {
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                entry.insert(merged.len());
+                merged.push(edge.clone());
+                continue;
+            }
+            std::collections::hash_map::Entry::Occupied(entry) => &mut merged[*entry.get()],
+        };
+        match strategy {
+            CoalesceStrategy::KeepFirst => {}
+            CoalesceStrategy::KeepLast => *slot = edge.clone(),
+            CoalesceStrategy::SumWeights => slot.weight += edge.weight,
+            CoalesceStrategy::MaxWeight if edge.weight > slot.weight => *slot = edge.clone(),
+            CoalesceStrategy::MinWeight if edge.weight < slot.weight => *slot = edge.clone(),
+            CoalesceStrategy::MaxWeight | CoalesceStrategy::MinWeight => {}
+        }
+    }
+
+    let removed = graph.edge_count() - merged.len();
+    if removed > 0 {
+        graph.replace_edges(merged).expect("endpoints are in the graph");
+    }
+    removed
+}
+
+/// Edge selection strategy for `sparsify`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SparsifyMethod {
+    /// Every edge is equally likely to be kept
+    Uniform,
+    /// Every node keeps about `target_fraction` of its incident edges, so
+    /// degrees shrink in proportion and the degree ranking, which drives
+    /// centrality on scale-free graphs, is preserved
+    DegreeProportional,
+    /// A random spanning forest is always kept and the remaining budget is
+    /// filled as for `DegreeProportional`, so connected components are
+    /// preserved
+    Spanner,
+}
+
+/// Keep about `target_fraction` of the edges of `graph`
+///
+/// All nodes are kept with their labels, properties and features; kept
+/// edges keep their weight and type. `round(target_fraction * edge_count)`
+/// edges are sampled without replacement, except that `Spanner` keeps its
+/// whole spanning forest even when that exceeds the budget. Passing a
+/// `seed` makes the result reproducible. Use `sparsify_rescaled` to
+/// preserve expected weighted degrees instead of the original weights.
+pub fn sparsify(
+    graph: &Graph,
+    method: SparsifyMethod,
+    target_fraction: f64,
+    seed: Option<u64>,
+) -> Result<Graph> {
+    sample_edges(graph, method, target_fraction, seed, false)
+}
+
+/// `sparsify`, dividing each kept weight by its inclusion probability
+///
+/// The expected weighted degree of every node then matches the original.
+/// For `DegreeProportional` and the non-tree edges of `Spanner`, each
+/// edge's inclusion probability is approximated by the share of its
+/// candidates that was kept.
+pub fn sparsify_rescaled(
+    graph: &Graph,
+    method: SparsifyMethod,
+    target_fraction: f64,
+    seed: Option<u64>,
+) -> Result<Graph> {
+    sample_edges(graph, method, target_fraction, seed, true)
+}
+
+fn sample_edges(
+    graph: &Graph,
+    method: SparsifyMethod,
+    target_fraction: f64,
+    seed: Option<u64>,
+    rescale: bool,
+) -> Result<Graph> {
+    if !(target_fraction > 0.0 && target_fraction <= 1.0) {
+        return Err(GraphError::InvalidParameter(format!(
+            "target_fraction must be in (0, 1], got {}",
+            target_fraction
+        )));
+    }
+
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let edges = graph.edges();
+    let m = edges.len();
+    let budget = ((target_fraction * m as f64).round() as usize).min(m);
+
+    // Kept edge indices with their inclusion probabilities
+    let mut kept: Vec<(usize, f64)> = match method {
+        SparsifyMethod::Uniform => {
+            let p = budget as f64 / m.max(1) as f64;
+            rand::seq::index::sample(&mut rng, m, budget)
+                .into_iter()
+                .map(|e| (e, p))
+                .collect()
+        }
+        SparsifyMethod::DegreeProportional => {
+            let mut order: Vec<usize> = (0..m).collect();
+            order.shuffle(&mut rng);
+
+            let p = budget as f64 / m.max(1) as f64;
+            fill_by_degree_quota(edges, Vec::new(), order, budget, target_fraction)
+                .into_iter()
+                .map(|e| (e, p))
+                .collect()
+        }
+        SparsifyMethod::Spanner => {
+            let mut order: Vec<usize> = (0..m).collect();
+            order.shuffle(&mut rng);
+
+            // Kruskal over a random order gives a random spanning forest
+            let mut forest = UnionFind::default();
+            let (tree, rest): (Vec<usize>, Vec<usize>) = order
+                .into_iter()
+                .partition(|&e| forest.union(edges[e].from, edges[e].to));
+
+            let tree_size = tree.len();
+            let extra = budget.saturating_sub(tree_size).min(rest.len());
+            let p = extra as f64 / rest.len().max(1) as f64;
+            fill_by_degree_quota(edges, tree, rest, budget, target_fraction)
+                .into_iter()
+                .enumerate()
+                .map(|(i, e)| (e, if i < tree_size { 1.0 } else { p }))
+                .collect()
+        }
+    };
+
+    let mut node_ids = graph.node_ids();
+    node_ids.sort_unstable();
+    let mut sparse = if graph.is_directed() {
+        Graph::new_directed()
+    } else {
+        Graph::new()
+    };
+    for id in node_ids {
+        sparse.add_node(graph.node(id)?.clone());
+    }
+
+    kept.sort_unstable_by_key(|&(e, _)| e);
+    for (e, probability) in kept {
+        let mut edge = edges[e].clone();
+        if rescale {
+            edge.weight /= probability;
+        }
+        sparse.insert_edge(edge)?;
+    }
+
+    Ok(sparse)
+}
+
+/// Extend `kept` from `candidates` until it holds `budget` edges
+///
+/// Candidates are first taken, in order, only while both endpoints keep
+/// fewer than `fraction` of their incident edges, so kept degrees shrink
+/// in proportion; the rest of the budget is filled in candidate order.
+/// Edges already in `kept` stay even beyond the budget.
+fn fill_by_degree_quota(
+    edges: &[Edge],
+    mut kept: Vec<usize>,
+    candidates: Vec<usize>,
+    budget: usize,
+    fraction: f64,
+) -> Vec<usize> {
+    let mut incident: HashMap<NodeId, f64> = HashMap::new();
+    for edge in edges {
+        *incident.entry(edge.from).or_default() += 1.0;
+        *incident.entry(edge.to).or_default() += 1.0;
+    }
+
+    let mut kept_degree: HashMap<NodeId, f64> = HashMap::new();
+    let keep = |edge: &Edge, kept_degree: &mut HashMap<NodeId, f64>| {
+        *kept_degree.entry(edge.from).or_default() += 1.0;
+        *kept_degree.entry(edge.to).or_default() += 1.0;
+    };
+    for &e in &kept {
+        keep(&edges[e], &mut kept_degree);
+    }
+
+    let below_quota = |id: NodeId, kept_degree: &HashMap<NodeId, f64>| {
+        kept_degree.get(&id).copied().unwrap_or(0.0) + 1.0 <= fraction * incident[&id] + 0.5
+    };
+    let mut rest = Vec::new();
+    for e in candidates {
+        let edge = &edges[e];
+        if kept.len() < budget
+            && below_quota(edge.from, &kept_degree)
+            && below_quota(edge.to, &kept_degree)
+        {
+            keep(edge, &mut kept_degree);
+            kept.push(e);
+        } else {
+            rest.push(e);
+        }
+    }
+
+    let missing = budget.saturating_sub(kept.len());
+    kept.extend(rest.into_iter().take(missing));
+    kept
+}
+
+/// Union-find keyed by node ID
+#[derive(Default)]
+struct UnionFind {
+    parent: HashMap<NodeId, NodeId>,
+}
+
+impl UnionFind {
+    fn find(&mut self, node: NodeId) -> NodeId {
+        let mut root = node;
+        while let Some(&parent) = self.parent.get(&root) {
+            if parent == root {
+                break;
+            }
+            root = parent;
+        }
+
+        // Path compression
+        let mut current = node;
+        while current != root {
+            let next = self.parent[&current];
+            self.parent.insert(current, root);
+            current = next;
+        }
+        root
+    }
+
+    /// Merge the sets of `a` and `b`, returning whether they were distinct
+    fn union(&mut self, a: NodeId, b: NodeId) -> bool {
+        let (root_a, root_b) = (self.find(a), self.find(b));
+        if root_a == root_b {
+            return false;
+        }
+        self.parent.insert(root_a.max(root_b), root_a.min(root_b));
+        true
+    }
+}
+
+/// Most cycles returned by `find_all_simple_cycles`
+pub const DEFAULT_MAX_CYCLES: usize = 100_000;
+
+/// Requirements of `find_all_simple_cycles`
+pub const SIMPLE_CYCLES_REQUIREMENTS: Requirements = Requirements::directed(
+    "find_all_simple_cycles",
+    "every undirected edge would be a cycle of two; use find_cliques or \
+     connected_components to study undirected structure",
+);
+
+/// Enumerate the simple cycles of a directed graph (Johnson's algorithm)
+///
+/// Each cycle lists its nodes once, starting from its smallest node ID and
+/// following edge directions; a self-loop is a cycle of one node. Cycles
+/// are sorted by start node, then length, then node IDs. At most
+/// `DEFAULT_MAX_CYCLES` cycles are returned, see
+/// `find_simple_cycles_with_limit`. Fails with `InvalidStructure` on
+/// undirected graphs.
+pub fn find_all_simple_cycles(graph: &Graph) -> Result<Vec<Vec<NodeId>>> {
+    find_simple_cycles_with_limit(graph, DEFAULT_MAX_CYCLES)
+}
+
+/// `find_all_simple_cycles` returning at most `max_cycles` cycles
+///
+/// Highly cyclic graphs have exponentially many cycles. Start nodes are
+/// searched in ascending order and the search stops once `max_cycles`
+/// cycles are found, so a truncated result holds the cycles through the
+/// smallest node IDs.
+pub fn find_simple_cycles_with_limit(
+    graph: &Graph,
+    max_cycles: usize,
+) -> Result<Vec<Vec<NodeId>>> {
+    check_graph_compat(graph, &SIMPLE_CYCLES_REQUIREMENTS)?;
+
+    let mut node_ids = graph.node_ids();
+    node_ids.sort_unstable();
+    let index: HashMap<NodeId, usize> =
+        node_ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+    let mut adjacency: Vec<Vec<usize>> = vec![Vec::new(); node_ids.len()];
+    for edge in graph.edges() {
+        adjacency[index[&edge.from]].push(index[&edge.to]);
+    }
+    for neighbors in &mut adjacency {
+        neighbors.sort_unstable();
+        neighbors.dedup();
+    }
+
+    let mut search = CycleSearch {
+        adjacency: &adjacency,
+        start: 0,
+        blocked: vec![false; node_ids.len()],
+        blocked_by: vec![Vec::new(); node_ids.len()],
+        stack: Vec::new(),
+        cycles: Vec::new(),
+        max_cycles,
+    };
+    for start in 0..node_ids.len() {
+        if search.cycles.len() >= max_cycles {
+            break;
+        }
+        search.start = start;
+        for node in start..node_ids.len() {
+            search.blocked[node] = false;
+            search.blocked_by[node].clear();
+        }
+        search.circuit(start);
+    }
+
+    let mut cycles: Vec<Vec<NodeId>> = search
+        .cycles
+        .into_iter()
+        .map(|cycle| cycle.into_iter().map(|i| node_ids[i]).collect())
+        .collect();
+    cycles.sort_by(|a: &Vec<NodeId>, b| (a[0], a.len(), a).cmp(&(b[0], b.len(), b)));
+    Ok(cycles)
+}
+
+/// State of Johnson's search for the cycles through `start` among the
+/// nodes from `start` on
+struct CycleSearch<'a> {
+    adjacency: &'a [Vec<usize>],
+    start: usize,
+    blocked: Vec<bool>,
+    /// Nodes to unblock when a node is unblocked
+    blocked_by: Vec<Vec<usize>>,
+    stack: Vec<usize>,
+    cycles: Vec<Vec<usize>>,
+    max_cycles: usize,
+}
+
+impl CycleSearch<'_> {
+    /// Extend the path on `stack` by `node`, recording every cycle back to
+    /// `start`; whether any was found
+    fn circuit(&mut self, node: usize) -> bool {
+        let mut found = false;
+        self.stack.push(node);
+        self.blocked[node] = true;
+        for &next in &self.adjacency[node] {
+            if next < self.start || self.cycles.len() >= self.max_cycles {
+                continue;
+            }
+            if next == self.start {
+                self.cycles.push(self.stack.clone());
+                found = true;
+            } else if !self.blocked[next] && self.circuit(next) {
+                found = true;
+            }
+        }
+        if found {
+            self.unblock(node);
+        } else {
+            for &next in &self.adjacency[node] {
+                if next >= self.start && !self.blocked_by[next].contains(&node) {
+                    self.blocked_by[next].push(node);
+                }
+            }
+        }
+        self.stack.pop();
+        found
+    }
+
+    fn unblock(&mut self, node: usize) {
+        self.blocked[node] = false;
+        for waiting in std::mem::take(&mut self.blocked_by[node]) {
+            if self.blocked[waiting] {
+                self.unblock(waiting);
+            }
+        }
+    }
+}
+
+/// Requirements of `find_bridges`
+pub const BRIDGES_REQUIREMENTS: Requirements = Requirements::undirected(
+    "find_bridges",
+    "convert with Graph::to_undirected to find the bridges of the underlying graph",
+);
+
+/// Requirements of `find_articulation_points`
+pub const ARTICULATION_POINTS_REQUIREMENTS: Requirements = Requirements::undirected(
+    "find_articulation_points",
+    "convert with Graph::to_undirected to find the cut vertices of the underlying graph",
+);
+
+/// Edges whose removal disconnects their endpoints (Tarjan)
+///
+/// Each bridge is `(smaller ID, larger ID)` and the bridges are sorted.
+/// One of several parallel edges is never a bridge, and self-loops are
+/// ignored. Fails with `InvalidStructure` on directed graphs.
+pub fn find_bridges(graph: &Graph) -> Result<Vec<(NodeId, NodeId)>> {
+    check_graph_compat(graph, &BRIDGES_REQUIREMENTS)?;
+    Ok(cut_structure(graph).0)
+}
+
+/// Nodes whose removal disconnects some of the others from each other
+///
+/// Computed in the same DFS pass as `find_bridges`, with the same
+/// treatment of parallel edges and self-loops. The nodes are sorted. Fails
+/// with `InvalidStructure` on directed graphs.
+pub fn find_articulation_points(graph: &Graph) -> Result<Vec<NodeId>> {
+    check_graph_compat(graph, &ARTICULATION_POINTS_REQUIREMENTS)?;
+    Ok(cut_structure(graph).1)
+}
+
+/// Bridges and articulation points from one iterative DFS
+///
+/// `low[v]` is the earliest discovery time reachable from the DFS subtree
+/// of `v` using at most one non-tree edge. The tree edge into `v` is
+/// skipped by edge index rather than by parent, so a parallel edge back
+/// to the parent counts as a back edge.
+fn cut_structure(graph: &Graph) -> (Vec<(NodeId, NodeId)>, Vec<NodeId>) {
+    let mut node_ids = graph.node_ids();
+    node_ids.sort_unstable();
+    let index: HashMap<NodeId, usize> =
+        node_ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+    let mut adjacency: Vec<Vec<(usize, usize)>> = vec![Vec::new(); node_ids.len()];
+    for (edge_idx, edge) in graph.edges().iter().enumerate() {
+        let (a, b) = (index[&edge.from], index[&edge.to]);
+        if a != b {
+            adjacency[a].push((b, edge_idx));
+            adjacency[b].push((a, edge_idx));
+        }
+    }
+
+    let mut discovery: Vec<Option<usize>> = vec![None; node_ids.len()];
+    let mut low = vec![0; node_ids.len()];
+    let mut is_articulation = vec![false; node_ids.len()];
+    let mut bridges = Vec::new();
+    let mut time = 0;
+    // (node, edge it was reached through, next adjacency position)
+    let mut stack: Vec<(usize, Option<usize>, usize)> = Vec::new();
+
+    for root in 0..node_ids.len() {
+        if discovery[root].is_some() {
+            continue;
+        }
+        discovery[root] = Some(time);
+        low[root] = time;
+        time += 1;
+        let mut root_children = 0;
+        stack.push((root, None, 0));
+
+        while let Some(frame) = stack.last_mut() {
+            let (node, via) = (frame.0, frame.1);
+            if let Some(&(neighbor, edge_idx)) = adjacency[node].get(frame.2) {
+                frame.2 += 1;
+                if via == Some(edge_idx) {
+                    continue;
+                }
+                match discovery[neighbor] {
+                    Some(seen) => low[node] = low[node].min(seen),
+                    None => {
+                        discovery[neighbor] = Some(time);
+                        low[neighbor] = time;
+                        time += 1;
+                        if node == root {
+                            root_children += 1;
+                        }
+                        stack.push((neighbor, Some(edge_idx), 0));
+                    }
+                }
+                continue;
+            }
+
+            stack.pop();
+            if let Some(&(parent, ..)) = stack.last() {
+                low[parent] = low[parent].min(low[node]);
+                let parent_time = discovery[parent].expect("stacked nodes are discovered");
+                if low[node] > parent_time {
+                    let (a, b) = (node_ids[parent], node_ids[node]);
+                    bridges.push((a.min(b), a.max(b)));
+                }
+                if parent != root && low[node] >= parent_time {
+                    is_articulation[parent] = true;
+                }
+            }
+        }
+        is_articulation[root] |= root_children > 1;
+    }
+
+    bridges.sort_unstable();
+    let articulation_points = node_ids
+        .iter()
+        .zip(&is_articulation)
+        .filter(|&(_, &cut)| cut)
+        .map(|(&id, _)| id)
+        .collect();
+    (bridges, articulation_points)
+}
+
+/// Enumerate all maximal cliques (Bron-Kerbosch with pivoting)
+///
+/// Edge direction is ignored and self-loops do not count. Each clique is
+/// sorted by node ID and the cliques are sorted lexicographically; an
+/// isolated node is a maximal clique of size one.
+pub fn find_cliques(graph: &Graph) -> Result<Vec<Vec<NodeId>>> {
+    let (node_ids, adjacency) = undirected_adjacency(graph)?;
+    if node_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut cliques = Vec::new();
+    let candidates: HashSet<usize> = (0..node_ids.len()).collect();
+    bron_kerbosch(&adjacency, &mut Vec::new(), candidates, HashSet::new(), &mut cliques);
+
+    let mut cliques: Vec<Vec<NodeId>> = cliques
+        .into_iter()
+        .map(|clique| {
+            let mut clique: Vec<NodeId> = clique.into_iter().map(|i| node_ids[i]).collect();
+            clique.sort_unstable();
+            clique
+        })
+        .collect();
+    cliques.sort();
+    Ok(cliques)
+}
+
+/// Find a maximum clique by branch and bound
+///
+/// Vertices are branched on in descending degree order, and a branch is
+/// pruned once it cannot beat the best clique found so far. Finding a
+/// maximum clique is NP-hard, so this is exponential in the worst case.
+/// Ties are broken towards the clique found first; the result is sorted.
+pub fn find_maximum_clique(graph: &Graph) -> Result<Vec<NodeId>> {
+    let (node_ids, adjacency) = undirected_adjacency(graph)?;
+
+    let mut order: Vec<usize> = (0..node_ids.len()).collect();
+    order.sort_by(|&a, &b| adjacency[b].len().cmp(&adjacency[a].len()).then(a.cmp(&b)));
+
+    let mut best = Vec::new();
+    expand_clique(&adjacency, &mut Vec::new(), &order, &mut best);
+
+    let mut clique: Vec<NodeId> = best.into_iter().map(|i| node_ids[i]).collect();
+    clique.sort_unstable();
+    Ok(clique)
+}
+
+/// Size of the largest clique in the graph
+pub fn clique_number(graph: &Graph) -> Result<usize> {
+    Ok(find_maximum_clique(graph)?.len())
+}
+
+/// Node IDs in ascending order with symmetric, loop-free neighbor sets
+fn undirected_adjacency(graph: &Graph) -> Result<(Vec<NodeId>, Vec<HashSet<usize>>)> {
+    let mut node_ids = graph.node_ids();
+    node_ids.sort_unstable();
+    let index: HashMap<NodeId, usize> =
+        node_ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+
+    let mut adjacency = vec![HashSet::new(); node_ids.len()];
+    for (i, &id) in node_ids.iter().enumerate() {
+        for neighbor in graph.neighbors(id)? {
+            let j = index[&neighbor];
+            if i != j {
+                adjacency[i].insert(j);
+                adjacency[j].insert(i);
+            }
+        }
+    }
+
+    Ok((node_ids, adjacency))
+}
+
+fn bron_kerbosch(
+    adjacency: &[HashSet<usize>],
+    clique: &mut Vec<usize>,
+    mut candidates: HashSet<usize>,
+    mut excluded: HashSet<usize>,
+    cliques: &mut Vec<Vec<usize>>,
+) {
+    if candidates.is_empty() {
+        if excluded.is_empty() {
+            cliques.push(clique.clone());
+        }
+        return;
+    }
+
+    // Pivot on the vertex covering the most candidates
+    let pivot = candidates
+        .union(&excluded)
+        .copied()
+        .max_by_key(|&u| (adjacency[u].intersection(&candidates).count(), std::cmp::Reverse(u)))
+        .unwrap();
+
+    let mut branches: Vec<usize> = candidates.difference(&adjacency[pivot]).copied().collect();
+    branches.sort_unstable();
+
+    for v in branches {
+        clique.push(v);
+        bron_kerbosch(
+            adjacency,
+            clique,
+            candidates.intersection(&adjacency[v]).copied().collect(),
+            excluded.intersection(&adjacency[v]).copied().collect(),
+            cliques,
+        );
+        clique.pop();
+
+        candidates.remove(&v);
+        excluded.insert(v);
+    }
+}
+
+fn expand_clique(
+    adjacency: &[HashSet<usize>],
+    clique: &mut Vec<usize>,
+    candidates: &[usize],
+    best: &mut Vec<usize>,
+) {
+    if clique.len() > best.len() {
+        *best = clique.clone();
+    }
+
+    for (i, &v) in candidates.iter().enumerate() {
+        // Even taking every remaining candidate cannot beat the best
+        if clique.len() + candidates.len() - i <= best.len() {
+            return;
+        }
+
+        let next: Vec<usize> = candidates[i + 1..]
+            .iter()
+            .copied()
+            .filter(|u| adjacency[v].contains(u))
+            .collect();
+        clique.push(v);
+        expand_clique(adjacency, clique, &next, best);
+        clique.pop();
+    }
+}
+
+/// Largest graph accepted by the isomorphism search
+pub const MAX_ISOMORPHISM_NODES: usize = 100;
+
+/// Options for `subgraph_isomorphisms`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SubgraphMatchOptions {
+    /// Require node labels and edge types to match
+    pub match_labels: bool,
+    /// Also require target edges between matched nodes to be in the
+    /// pattern, rather than only pattern edges to be in the target
+    pub induced: bool,
+    /// Stop after this many matches
+    pub max_matches: Option<usize>,
+}
+
+impl SubgraphMatchOptions {
+    /// Match node labels and edge types
+    pub fn with_labels(mut self) -> Self {
+        self.match_labels = true;
+        self
+    }
+
+    /// Only report induced subgraphs
+    pub fn induced(mut self) -> Self {
+        self.induced = true;
+        self
+    }
+
+    /// Return at most `max_matches` matches
+    pub fn with_max_matches(mut self, max_matches: usize) -> Self {
+        self.max_matches = Some(max_matches);
+        self
+    }
+}
+
+/// Whether two small graphs are isomorphic, see `Graph::is_isomorphic_to`
+pub fn are_isomorphic(a: &Graph, b: &Graph) -> bool {
+    vf2_isomorphism(a, b).is_some()
+}
+
+/// All embeddings of `pattern` in `target`, found with VF2
+///
+/// Each match maps every pattern node to a distinct target node such that
+/// every pattern edge, with its direction on directed graphs, is present
+/// between the images. Both graphs must have the same directedness. A
+/// pattern with automorphisms matches the same target nodes once per
+/// automorphism, e.g. an undirected triangle six times per triangle.
+/// Matches come in the order found; `options.max_matches` caps how many
+/// are returned.
+pub fn subgraph_isomorphisms(
+    pattern: &Graph,
+    target: &Graph,
+    options: SubgraphMatchOptions,
+) -> Result<Vec<HashMap<NodeId, NodeId>>> {
+    if pattern.is_directed() != target.is_directed() {
+        return Err(GraphError::InvalidParameter(
+            "pattern and target must both be directed or both undirected".to_string(),
+        ));
+    }
+
+    let mut matches = Vec::new();
+    if options.max_matches == Some(0) || pattern.node_count() > target.node_count() {
+        return Ok(matches);
+    }
+
+    let g1 = Vf2Graph::new(pattern)?;
+    let g2 = Vf2Graph::new(target)?;
+    let mode = if options.induced {
+        Vf2Mode::InducedSubgraph
+    } else {
+        Vf2Mode::Subgraph
+    };
+    let mut state = Vf2State::new(&g1, &g2, mode, options.match_labels);
+    state.search(&mut |core| {
+        matches.push(g1.mapping(&g2, core));
+        options.max_matches != Some(matches.len())
+    });
+    Ok(matches)
+}
+
+/// Find an isomorphism between two graphs with VF2
+///
+/// Returns `None` when the graphs are not isomorphic, differ in
+/// directedness, or have more than `MAX_ISOMORPHISM_NODES` nodes.
+/// Parallel edges are not distinguished from single ones.
+pub(crate) fn vf2_isomorphism(g1: &Graph, g2: &Graph) -> Option<HashMap<NodeId, NodeId>> {
+    if g1.is_directed() != g2.is_directed()
+        || g1.node_count() != g2.node_count()
+        || g1.node_count() > MAX_ISOMORPHISM_NODES
+    {
+        return None;
+    }
+
+    let a = Vf2Graph::new(g1).ok()?;
+    let b = Vf2Graph::new(g2).ok()?;
+
+    // Cheap invariants first: equal degree sequences and loop counts
+    let degree_sequence = |graph: &Vf2Graph| {
+        let mut degrees: Vec<(usize, usize, bool)> = (0..graph.node_ids.len())
+            .map(|i| (graph.out[i].len(), graph.incoming[i].len(), graph.loops[i]))
+            .collect();
+        degrees.sort_unstable();
+        degrees
+    };
+    if degree_sequence(&a) != degree_sequence(&b) {
+        return None;
+    }
+
+    let mut mapping = None;
+    let mut state = Vf2State::new(&a, &b, Vf2Mode::Isomorphism, false);
+    state.search(&mut |core| {
+        mapping = Some(a.mapping(&b, core));
+        false
+    });
+    mapping
+}
+
+/// A graph indexed for VF2
+///
+/// `out` and `incoming` are loop-free neighbor sets (equal on undirected
+/// graphs) and `neighbors` is their union; self-loops are kept as a flag
+/// per node.
+struct Vf2Graph<'a> {
+    graph: &'a Graph,
+    node_ids: Vec<NodeId>,
+    out: Vec<HashSet<usize>>,
+    incoming: Vec<HashSet<usize>>,
+    neighbors: Vec<HashSet<usize>>,
+    loops: Vec<bool>,
+    /// Types of the edges from `i` to `j`, keyed by `(i, j)`
+    edge_types: HashMap<(usize, usize), HashSet<&'a str>>,
+}
+
+impl<'a> Vf2Graph<'a> {
+    fn new(graph: &'a Graph) -> Result<Self> {
+        let mut node_ids = graph.node_ids();
+        node_ids.sort_unstable();
+        let index: HashMap<NodeId, usize> =
+            node_ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+
+        let n = node_ids.len();
+        let mut out = vec![HashSet::new(); n];
+        let mut incoming = vec![HashSet::new(); n];
+        let mut loops = vec![false; n];
+        let mut edge_types: HashMap<(usize, usize), HashSet<&str>> = HashMap::new();
+        for edge in graph.edges() {
+            let (from, to) = (index[&edge.from], index[&edge.to]);
+            let pairs = if graph.is_directed() {
+                vec![(from, to)]
+            } else {
+                vec![(from, to), (to, from)]
+            };
+            for (i, j) in pairs {
+                edge_types.entry((i, j)).or_default().insert(&edge.edge_type);
+                if i == j {
+                    loops[i] = true;
+                } else {
+                    out[i].insert(j);
+                    incoming[j].insert(i);
+                }
+            }
+        }
+        let neighbors = (0..n).map(|i| out[i].union(&incoming[i]).copied().collect()).collect();
+
+        Ok(Self {
+            graph,
+            node_ids,
+            out,
+            incoming,
+            neighbors,
+            loops,
+            edge_types,
+        })
+    }
+
+    fn label(&self, i: usize) -> &str {
+        self.graph
+            .node(self.node_ids[i])
+            .map(|node| node.label.as_str())
+            .unwrap_or_default()
+    }
+
+    /// Whether every type of the edges `i -> j` here labels some edge
+    /// `k -> l` in `other`
+    fn edge_types_match(&self, i: usize, j: usize, other: &Vf2Graph, k: usize, l: usize) -> bool {
+        match (self.edge_types.get(&(i, j)), other.edge_types.get(&(k, l))) {
+            (Some(types), Some(other_types)) => types.is_subset(other_types),
+            (None, _) => true,
+            (Some(_), None) => false,
+        }
+    }
+
+    fn mapping(&self, other: &Vf2Graph, core: &[usize]) -> HashMap<NodeId, NodeId> {
+        core.iter()
+            .enumerate()
+            .map(|(i, &j)| (self.node_ids[i], other.node_ids[j]))
+            .collect()
+    }
+}
+
+/// What a VF2 match must preserve
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Vf2Mode {
+    /// A bijection preserving edges and non-edges
+    Isomorphism,
+    /// An injection preserving edges and non-edges
+    InducedSubgraph,
+    /// An injection preserving edges
+    Subgraph,
+}
+
+/// Marks a node that is not mapped (in `core_*`) or not yet in the
+/// terminal set (in `terminal_*`)
+const UNSET: usize = usize::MAX;
+
+/// Partial mapping explored by VF2, from the first graph (the pattern)
+/// into the second
+///
+/// `terminal_*[n]` is the depth at which `n` entered the terminal set,
+/// the unmapped neighbors of mapped nodes, so that popping a pair undoes
+/// exactly what pushing it added.
+struct Vf2State<'s, 'a> {
+    g1: &'s Vf2Graph<'a>,
+    g2: &'s Vf2Graph<'a>,
+    mode: Vf2Mode,
+    match_labels: bool,
+    core_1: Vec<usize>,
+    core_2: Vec<usize>,
+    terminal_1: Vec<usize>,
+    terminal_2: Vec<usize>,
+    depth: usize,
+}
+
+impl<'s, 'a> Vf2State<'s, 'a> {
+    fn new(g1: &'s Vf2Graph<'a>, g2: &'s Vf2Graph<'a>, mode: Vf2Mode, match_labels: bool) -> Self {
+        let (n1, n2) = (g1.node_ids.len(), g2.node_ids.len());
+        Self {
+            g1,
+            g2,
+            mode,
+            match_labels,
+            core_1: vec![UNSET; n1],
+            core_2: vec![UNSET; n2],
+            terminal_1: vec![UNSET; n1],
+            terminal_2: vec![UNSET; n2],
+            depth: 0,
+        }
+    }
+
+    /// Report every complete mapping to `on_match` until it returns
+    /// `false`; returns whether the search should go on
+    fn search(&mut self, on_match: &mut dyn FnMut(&[usize]) -> bool) -> bool {
+        let n1 = self.core_1.len();
+        if self.depth == n1 {
+            return on_match(&self.core_1);
+        }
+
+        // Extend from the terminal set when it is non-empty, so the
+        // mapping grows along edges and mismatches surface early
+        let in_terminal_1 =
+            (0..n1).find(|&i| self.core_1[i] == UNSET && self.terminal_1[i] != UNSET);
+        let (node, from_terminal) = match in_terminal_1 {
+            Some(node) => (node, true),
+            None => match (0..n1).find(|&i| self.core_1[i] == UNSET) {
+                Some(node) => (node, false),
+                None => return true,
+            },
+        };
+
+        for candidate in 0..self.core_2.len() {
+            if self.core_2[candidate] != UNSET {
+                continue;
+            }
+            // A node adjacent to the mapping must map next to its image;
+            // only an isomorphism also forbids the converse
+            let in_terminal_2 = self.terminal_2[candidate] != UNSET;
+            if (from_terminal && !in_terminal_2)
+                || (self.mode == Vf2Mode::Isomorphism && !from_terminal && in_terminal_2)
+            {
+                continue;
+            }
+
+            if self.is_feasible(node, candidate) {
+                self.push(node, candidate);
+                let go_on = self.search(on_match);
+                self.pop(node, candidate);
+                if !go_on {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    fn is_feasible(&self, n: usize, m: usize) -> bool {
+        let (g1, g2) = (self.g1, self.g2);
+        let exact = self.mode == Vf2Mode::Isomorphism;
+        let induced = self.mode != Vf2Mode::Subgraph;
+
+        if self.match_labels && g1.label(n) != g2.label(m) {
+            return false;
+        }
+        let degrees_fit = |a: usize, b: usize| if exact { a == b } else { a <= b };
+        if !degrees_fit(g1.out[n].len(), g2.out[m].len())
+            || !degrees_fit(g1.incoming[n].len(), g2.incoming[m].len())
+        {
+            return false;
+        }
+        if (induced && g1.loops[n] != g2.loops[m]) || (g1.loops[n] && !g2.loops[m]) {
+            return false;
+        }
+        if self.match_labels && g1.loops[n] && !g1.edge_types_match(n, n, g2, m, m) {
+            return false;
+        }
+
+        // Pattern edges to mapped nodes must be present in the target
+        for &neighbor in &g1.out[n] {
+            let image = self.core_1[neighbor];
+            if image != UNSET
+                && (!g2.out[m].contains(&image)
+                    || (self.match_labels && !g1.edge_types_match(n, neighbor, g2, m, image)))
+            {
+                return false;
+            }
+        }
+        for &neighbor in &g1.incoming[n] {
+            let image = self.core_1[neighbor];
+            if image != UNSET
+                && (!g2.incoming[m].contains(&image)
+                    || (self.match_labels && !g1.edge_types_match(neighbor, n, g2, image, m)))
+            {
+                return false;
+            }
+        }
+
+        // ... and target edges to mapped nodes present in the pattern
+        if induced {
+            for &neighbor in &g2.out[m] {
+                let preimage = self.core_2[neighbor];
+                if preimage != UNSET && !g1.out[n].contains(&preimage) {
+                    return false;
+                }
+            }
+            for &neighbor in &g2.incoming[m] {
+                let preimage = self.core_2[neighbor];
+                if preimage != UNSET && !g1.incoming[n].contains(&preimage) {
+                    return false;
+                }
+            }
+        }
+
+        // Look-ahead: the unmapped neighbors inside and outside the
+        // terminal sets must balance, or fit for subgraph matches
+        let split = |adjacency: &HashSet<usize>, core: &[usize], terminal: &[usize]| {
+            adjacency
+                .iter()
+                .filter(|&&neighbor| core[neighbor] == UNSET)
+                .fold((0, 0), |(inside, outside), &neighbor| {
+                    if terminal[neighbor] != UNSET {
+                        (inside + 1, outside)
+                    } else {
+                        (inside, outside + 1)
+                    }
+                })
+        };
+        let (inside_1, outside_1) = split(&g1.neighbors[n], &self.core_1, &self.terminal_1);
+        let (inside_2, outside_2) = split(&g2.neighbors[m], &self.core_2, &self.terminal_2);
+        if exact {
+            inside_1 == inside_2 && outside_1 == outside_2
+        } else {
+            inside_1 <= inside_2 && inside_1 + outside_1 <= inside_2 + outside_2
+        }
+    }
+
+    fn push(&mut self, n: usize, m: usize) {
+        self.depth += 1;
+        self.core_1[n] = m;
+        self.core_2[m] = n;
+        Self::enter_terminal(&self.g1.neighbors, &mut self.terminal_1, n, self.depth);
+        Self::enter_terminal(&self.g2.neighbors, &mut self.terminal_2, m, self.depth);
+    }
+
+    fn pop(&mut self, n: usize, m: usize) {
+        Self::leave_terminal(&self.g1.neighbors, &mut self.terminal_1, n, self.depth);
+        Self::leave_terminal(&self.g2.neighbors, &mut self.terminal_2, m, self.depth);
+        self.core_1[n] = UNSET;
+        self.core_2[m] = UNSET;
+        self.depth -= 1;
+    }
+
+    fn enter_terminal(
+        adjacency: &[HashSet<usize>],
+        terminal: &mut [usize],
+        node: usize,
+        depth: usize,
+    ) {
+        for &i in std::iter::once(&node).chain(&adjacency[node]) {
+            if terminal[i] == UNSET {
+                terminal[i] = depth;
+            }
+        }
+    }
+
+    fn leave_terminal(
+        adjacency: &[HashSet<usize>],
+        terminal: &mut [usize],
+        node: usize,
+        depth: usize,
+    ) {
+        for &i in std::iter::once(&node).chain(&adjacency[node]) {
+            if terminal[i] == depth {
+                terminal[i] = UNSET;
+            }
+        }
+    }
+}
+
+/// Residual capacities at most this are treated as saturated
+const FLOW_TOLERANCE: Weight = 1e-9;
+
+/// Accuracy of the multiplicative weights in `multi_commodity_max_flow`
+const MULTI_COMMODITY_EPSILON: f64 = 0.05;
+
+/// The edges of a graph as capacities over node indices, see `max_flow`
+struct FlowNetwork {
+    index: HashMap<NodeId, usize>,
+    /// `(neighbor, edge)` for every edge flow can leave each node on: the
+    /// out-edges of a directed graph, every incident edge of an undirected
+    /// one
+    arcs: Vec<Vec<(usize, usize)>>,
+    /// Endpoint indices of each edge, in `Graph::edges` order
+    endpoints: Vec<(usize, usize)>,
+    capacities: Vec<Weight>,
+    is_directed: bool,
+}
+
+impl FlowNetwork {
+    fn new(graph: &Graph) -> Result<Self> {
+        let mut node_ids = graph.node_ids();
+        node_ids.sort_unstable();
+        let index: HashMap<NodeId, usize> =
+            node_ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+        let mut arcs = vec![Vec::new(); node_ids.len()];
+        let mut endpoints = Vec::with_capacity(graph.edge_count());
+        let mut capacities = Vec::with_capacity(graph.edge_count());
+        for (edge_idx, edge) in graph.edges().iter().enumerate() {
+            if !(edge.weight.is_finite() && edge.weight >= 0.0) {
+                return Err(GraphError::InvalidParameter(format!(
+                    "Edge {} -> {} has capacity {}; flows need finite, non-negative weights",
+                    edge.from, edge.to, edge.weight
+                )));
+            }
+            let (from, to) = (index[&edge.from], index[&edge.to]);
+            arcs[from].push((to, edge_idx));
+            if !graph.is_directed() && from != to {
+                arcs[to].push((from, edge_idx));
+            }
+            endpoints.push((from, to));
+            capacities.push(edge.weight);
+        }
+        Ok(Self { index, arcs, endpoints, capacities, is_directed: graph.is_directed() })
+    }
+
+    fn node(&self, id: NodeId) -> Result<usize> {
+        self.index.get(&id).copied().ok_or(GraphError::NodeNotFound(id))
+    }
+
+    /// Edmonds-Karp from `source` to `sink`
+    fn max_flow(&self, source: usize, sink: usize) -> Weight {
+        // `(to, residual capacity, index of the reverse arc in residual[to])`
+        let mut residual: Vec<Vec<(usize, Weight, usize)>> = vec![Vec::new(); self.arcs.len()];
+        for (&(from, to), &capacity) in self.endpoints.iter().zip(&self.capacities) {
+            if from == to {
+                continue;
+            }
+            // An undirected edge is its own reverse arc, usable either way
+            let reverse_capacity = if self.is_directed { 0.0 } else { capacity };
+            let (forward, backward) = (residual[from].len(), residual[to].len());
+            residual[from].push((to, capacity, backward));
+            residual[to].push((from, reverse_capacity, forward));
+        }
+
+        let mut total = 0.0;
+        loop {
+            let mut parent: Vec<Option<(usize, usize)>> = vec![None; residual.len()];
+            let mut queue = VecDeque::from([source]);
+            while let Some(node) = queue.pop_front() {
+                if node == sink {
+                    break;
+                }
+                for (arc, &(to, capacity, _)) in residual[node].iter().enumerate() {
+                    if capacity > FLOW_TOLERANCE && to != source && parent[to].is_none() {
+                        parent[to] = Some((node, arc));
+                        queue.push_back(to);
+                    }
+                }
+            }
+            if parent[sink].is_none() {
+                return total;
+            }
+
+            let mut bottleneck = Weight::INFINITY;
+            let mut node = sink;
+            while let Some((previous, arc)) = parent[node] {
+                bottleneck = bottleneck.min(residual[previous][arc].1);
+                node = previous;
+            }
+            let mut node = sink;
+            while let Some((previous, arc)) = parent[node] {
+                residual[previous][arc].1 -= bottleneck;
+                let reverse = residual[previous][arc].2;
+                residual[node][reverse].1 += bottleneck;
+                node = previous;
+            }
+            total += bottleneck;
+        }
+    }
+
+    /// Edges of a path from `source` to `sink` of least total `length`,
+    /// avoiding edges of infinite length
+    fn lightest_path(&self, length: &[f64], source: usize, sink: usize) -> Option<Vec<usize>> {
+        let mut distance = vec![f64::INFINITY; self.arcs.len()];
+        let mut parent: Vec<Option<(usize, usize)>> = vec![None; self.arcs.len()];
+        let mut heap = BinaryHeap::from([State::<usize> { cost: 0.0, node: source }]);
+        distance[source] = 0.0;
+        while let Some(State { cost, node }) = heap.pop() {
+            if node == sink {
+                break;
+            }
+            if cost > distance[node] {
+                continue;
+            }
+            for &(to, edge) in &self.arcs[node] {
+                let next = cost + length[edge];
+                if next < distance[to] {
+                    distance[to] = next;
+                    parent[to] = Some((node, edge));
+                    heap.push(State { cost: next, node: to });
+                }
+            }
+        }
+
+        parent[sink]?;
+        let mut path = Vec::new();
+        let mut node = sink;
+        while let Some((previous, edge)) = parent[node] {
+            path.push(edge);
+            node = previous;
+        }
+        Some(path)
+    }
+
+    /// Largest fraction of every demand in `commodities` that can be
+    /// routed at once, found by Garg-Könemann multiplicative weights
+    ///
+    /// Every commodity must be able to route some flow on its own. Rounds
+    /// route each demand in turn along the currently lightest paths and
+    /// lengthen the edges used in proportion to the share of their capacity
+    /// taken, until the lengths weigh 1 in total. The routed flows are then
+    /// scaled down by the most overloaded edge, so the fraction returned is
+    /// achievable and, with the demands scaled so that the optimum is at
+    /// most 1, within about `3 * MULTI_COMMODITY_EPSILON` of it.
+    fn concurrent_fraction(&self, commodities: &[(usize, usize, Weight)]) -> f64 {
+        let epsilon = MULTI_COMMODITY_EPSILON;
+        let usable = self.capacities.iter().filter(|&&c| c > FLOW_TOLERANCE).count();
+        let initial = (usable as f64 / (1.0 - epsilon)).powf(-1.0 / epsilon);
+        let mut length: Vec<f64> = self
+            .capacities
+            .iter()
+            // Edges without capacity are never used
+            .map(|&capacity| {
+                if capacity > FLOW_TOLERANCE { initial / capacity } else { f64::INFINITY }
+            })
+            .collect();
+        let mut volume = usable as f64 * initial;
+        let mut load = vec![0.0; self.capacities.len()];
+        let mut routed = vec![0.0; commodities.len()];
+
+        while volume < 1.0 {
+            for (commodity, &(source, sink, demand)) in commodities.iter().enumerate() {
+                let mut remaining = demand;
+                while remaining > FLOW_TOLERANCE && volume < 1.0 {
+                    let Some(path) = self.lightest_path(&length, source, sink) else {
+                        return 0.0;
+                    };
+                    let amount = path
+                        .iter()
+                        .map(|&edge| self.capacities[edge])
+                        .fold(remaining, Weight::min);
+                    remaining -= amount;
+                    routed[commodity] += amount;
+                    for &edge in &path {
+                        let capacity = self.capacities[edge];
+                        load[edge] += amount;
+                        let grown = length[edge] * (1.0 + epsilon * amount / capacity);
+                        volume += capacity * (grown - length[edge]);
+                        length[edge] = grown;
+                    }
+                }
+            }
+        }
+
+        let congestion = load
+            .iter()
+            .zip(&self.capacities)
+            .filter(|(&load, _)| load > 0.0)
+            .map(|(load, capacity)| load / capacity)
+            .fold(0.0, f64::max);
+        let fraction = routed
+            .iter()
+            .zip(commodities)
+            .map(|(routed, &(_, _, demand))| routed / demand)
+            .fold(f64::INFINITY, f64::min);
+        fraction / congestion
+    }
+
+    /// The flow of each demand in a concurrent routing, and whether every
+    /// demand is met in full
+    fn concurrent_flow(&self, demands: &[(NodeId, NodeId, Weight)]) -> Result<(Vec<Weight>, bool)> {
+        let mut flows = vec![0.0; demands.len()];
+        let mut all_met = true;
+        // `(demand index, source, sink, demand, max flow)`
+        let mut active = Vec::new();
+        for (i, &(source, sink, demand)) in demands.iter().enumerate() {
+            let (from, to) = (self.node(source)?, self.node(sink)?);
+            if !(demand.is_finite() && demand >= 0.0) {
+                return Err(GraphError::InvalidParameter(format!(
+                    "Demand {} from {} to {} must be finite and non-negative",
+                    demand, source, sink
+                )));
+            }
+            if demand == 0.0 || from == to {
+                flows[i] = demand;
+                continue;
+            }
+            let capacity = self.max_flow(from, to);
+            if capacity <= FLOW_TOLERANCE {
+                all_met = false;
+            } else {
+                active.push((i, from, to, demand, capacity));
+            }
+        }
+        if active.is_empty() {
+            return Ok((flows, all_met));
+        }
+
+        // No fraction above the tightest single-commodity bound fits, and
+        // commodities sharing capacity evenly reach `1 / k` of it
+        let bound = active
+            .iter()
+            .map(|&(_, _, _, demand, capacity)| capacity / demand)
+            .fold(f64::INFINITY, f64::min);
+        let fraction = if active.len() == 1 {
+            bound
+        } else {
+            let scaled: Vec<(usize, usize, Weight)> = active
+                .iter()
+                .map(|&(_, from, to, demand, _)| (from, to, demand * bound))
+                .collect();
+            bound * self.concurrent_fraction(&scaled)
+        };
+        for &(i, _, _, demand, _) in &active {
+            flows[i] = demand * fraction.min(1.0);
+        }
+        Ok((flows, all_met && fraction >= 1.0 - FLOW_TOLERANCE))
+    }
+}
+
+/// Maximum flow from `source` to `sink`, with edge weights as capacities
+///
+/// Edmonds-Karp, in O(V * E^2) time. An undirected edge carries flow in
+/// either direction, up to its weight in total, and parallel edges add
+/// their capacities. Fails with `InvalidParameter` if `source` is `sink`
+/// or an edge weight is negative or not finite.
+pub fn max_flow(graph: &Graph, source: NodeId, sink: NodeId) -> Result<Weight> {
+    let network = FlowNetwork::new(graph)?;
+    let (from, to) = (network.node(source)?, network.node(sink)?);
+    if from == to {
+        return Err(GraphError::InvalidParameter(format!(
+            "max_flow needs distinct source and sink, got {} twice",
+            source
+        )));
+    }
+    Ok(network.max_flow(from, to))
+}
+
+/// Flow routed for each `(source, sink, demand)` when all of them share
+/// the edge capacities, as in `max_flow`
+///
+/// Solves the maximum concurrent flow relaxation: the largest fraction
+/// `λ` such that `λ` times every demand can be routed at once, fractional
+/// flows allowed. Each commodity gets `min(λ, 1)` of its demand, so the
+/// demands are met in full exactly when they fit together. With more than
+/// one commodity `λ` is approximated by multiplicative weights
+/// (Garg-Könemann), to within roughly 15% of the optimum but never above
+/// it; a single commodity gets its exact maximum flow. Demands of zero and
+/// demands from a node to itself are always met in full, while a demand
+/// whose sink its source cannot reach gets nothing and is left out of `λ`.
+/// Fails with `NodeNotFound` for unknown nodes and `InvalidParameter` for
+/// negative or non-finite demands and capacities.
+pub fn multi_commodity_max_flow(
+    graph: &Graph,
+    demands: &[(NodeId, NodeId, Weight)],
+) -> Result<Vec<Weight>> {
+    FlowNetwork::new(graph)?.concurrent_flow(demands).map(|(flows, _)| flows)
+}
+
+/// Whether every demand can be routed in full at once, see
+/// `multi_commodity_max_flow`
+///
+/// A `true` answer is backed by a routing that was found. Since that
+/// routing is approximate for several commodities, demands that fit only
+/// within the approximation slack may be reported as not fitting. Invalid
+/// input is `false`.
+pub fn is_feasible_flow(graph: &Graph, demands: &[(NodeId, NodeId, Weight)]) -> bool {
+    FlowNetwork::new(graph)
+        .and_then(|network| network.concurrent_flow(demands))
+        .is_ok_and(|(_, feasible)| feasible)
+}
+
+/// A proper node coloring with few colors, colors numbered from 0
+///
+/// Edges count in both directions on directed graphs. Each connected
+/// component that is bipartite gets an exact 2-coloring, found by
+/// breadth-first search; the others are colored by the Welsh-Powell
+/// heuristic, visiting nodes by descending degree (ascending ID among
+/// equals) and giving each the smallest color none of its neighbors has.
+/// Fails with `InvalidStructure` if the graph has a self-loop, since a
+/// node adjacent to itself cannot be colored.
+pub fn greedy_coloring(graph: &Graph) -> Result<HashMap<NodeId, usize>> {
+    if let Some(edge) = graph.edges().iter().find(|edge| edge.from == edge.to) {
+        return Err(GraphError::InvalidStructure(format!(
+            "node {} has a self-loop and cannot be colored",
+            edge.from
+        )));
+    }
+
+    let mut node_ids = graph.node_ids();
+    node_ids.sort_unstable();
+    let index: HashMap<NodeId, usize> =
+        node_ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+    let mut adjacency: Vec<Vec<usize>> = vec![Vec::new(); node_ids.len()];
+    for edge in graph.edges() {
+        let (a, b) = (index[&edge.from], index[&edge.to]);
+        adjacency[a].push(b);
+        adjacency[b].push(a);
+    }
+    for neighbors in &mut adjacency {
+        neighbors.sort_unstable();
+        neighbors.dedup();
+    }
+
+    const UNCOLORED: usize = usize::MAX;
+    let mut colors = vec![UNCOLORED; node_ids.len()];
+    for root in 0..node_ids.len() {
+        if colors[root] != UNCOLORED {
+            continue;
+        }
+
+        // Try to 2-color the component, collecting its nodes
+        let mut component = vec![root];
+        let mut bipartite = true;
+        colors[root] = 0;
+        let mut next = 0;
+        while next < component.len() {
+            let node = component[next];
+            next += 1;
+            for &neighbor in &adjacency[node] {
+                if colors[neighbor] == UNCOLORED {
+                    colors[neighbor] = 1 - colors[node];
+                    component.push(neighbor);
+                } else if colors[neighbor] == colors[node] {
+                    bipartite = false;
+                }
+            }
+        }
+        if bipartite {
+            continue;
+        }
+
+        // Welsh-Powell
+        for &node in &component {
+            colors[node] = UNCOLORED;
+        }
+        component.sort_by_key(|&node| (std::cmp::Reverse(adjacency[node].len()), node));
+        let mut taken: Vec<bool> = Vec::new();
+        for &node in &component {
+            taken.clear();
+            taken.resize(adjacency[node].len() + 1, false);
+            for &neighbor in &adjacency[node] {
+                if let Some(slot) = taken.get_mut(colors[neighbor]) {
+                    *slot = true;
+                }
+            }
+            colors[node] = taken.iter().position(|&taken| !taken).expect("a color is free");
+        }
+    }
+
+    Ok(node_ids.into_iter().zip(colors).collect())
+}
+
+/// Number of colors `greedy_coloring` uses, an upper bound on the
+/// chromatic number; 0 for a graph without nodes
+pub fn chromatic_number_upper_bound(graph: &Graph) -> Result<usize> {
+    Ok(greedy_coloring(graph)?.values().max().map_or(0, |&color| color + 1))
+}
+
+/// Whether `coloring` colors every node and no edge joins two nodes of
+/// the same color; a self-loop makes every coloring invalid
+pub fn is_valid_coloring(graph: &Graph, coloring: &HashMap<NodeId, usize>) -> bool {
+    graph.node_ids().iter().all(|id| coloring.contains_key(id))
+        && graph.edges().iter().all(|edge| coloring[&edge.from] != coloring[&edge.to])
+}
+
+/// Reconstruct path from parent map
+pub(crate) fn reconstruct_path(
+    parent: &HashMap<NodeId, NodeId>,
+    start: NodeId,
+    goal: NodeId,
+) -> Vec<NodeId> {
+    let mut path = vec![goal];
+    let mut current = goal;
+
+    while current != start {
+        if let Some(&prev) = parent.get(&current) {
+            path.push(prev);
+            current = prev;
+        } else {
+            break;
+        }
+    }
+
+    path.reverse();
+    path
+}
+
+#[cfg(all(test, not(feature = "uuid-ids")))]
+mod tests {
+    use super::*;
+    use crate::graph::Node;
+    use rand::Rng;
+
+    fn create_test_graph() -> Graph {
+        let mut graph = Graph::new();
+        let n0 = graph.add_node_simple("A");
+        let n1 = graph.add_node_simple("B");
+        let n2 = graph.add_node_simple("C");
+        let n3 = graph.add_node_simple("D");
+
+        graph.add_edge(n0, n1, 1.0).unwrap();
+        graph.add_edge(n1, n2, 2.0).unwrap();
+        graph.add_edge(n0, n3, 4.0).unwrap();
+        graph.add_edge(n3, n2, 1.0).unwrap();
+
+        graph
+    }
+
+    #[test]
+    fn test_filtered_traversal() {
+        let mut graph = create_test_graph();
+        let n4 = graph.add_node_simple("E");
+        graph.add_edge(1, n4, 1.0).unwrap();
+        graph.add_edge(n4, 2, 1.0).unwrap();
+
+        assert_eq!(bfs_filtered(&graph, 0, 2, |_| true).unwrap(), bfs(&graph, 0, 2).unwrap());
+        assert_eq!(dfs_filtered(&graph, 0, 2, |_| true).unwrap(), dfs(&graph, 0, 2).unwrap());
+
+        let unit = |edge: &Edge| edge.weight == 1.0;
+        assert_eq!(bfs_filtered(&graph, 0, 2, unit).unwrap(), vec![0, 1, 4, 2]);
+        assert_eq!(dfs_filtered(&graph, 0, 2, unit).unwrap(), vec![0, 1, 4, 2]);
+
+        let none = |_: &Edge| false;
+        for result in [bfs_filtered(&graph, 0, 2, none), dfs_filtered(&graph, 0, 2, none)] {
+            assert!(matches!(result, Err(GraphError::AlgorithmError(_))));
+        }
+        // The start is its own path even when no edge may be crossed
+        assert_eq!(bfs_filtered(&graph, 3, 3, |_| false).unwrap(), vec![3]);
+        assert!(matches!(bfs_filtered(&graph, 0, 99, |_| true), Err(GraphError::NodeNotFound(99))));
+    }
+
+    #[test]
+    fn test_max_flow() {
+        // The network of CLRS figure 26.6
+        let mut graph = Graph::new_directed();
+        for i in 0..6 {
+            graph.add_node_simple(format!("v{}", i));
+        }
+        let arcs = [(0, 1, 16.0), (0, 2, 13.0), (1, 3, 12.0), (2, 1, 4.0), (2, 4, 14.0)];
+        let more = [(3, 2, 9.0), (3, 5, 20.0), (4, 3, 7.0), (4, 5, 4.0)];
+        for (from, to, capacity) in arcs.into_iter().chain(more) {
+            graph.add_edge(from, to, capacity).unwrap();
+        }
+        assert!((max_flow(&graph, 0, 5).unwrap() - 23.0).abs() < 1e-9);
+        assert_eq!(max_flow(&graph, 5, 0).unwrap(), 0.0);
+
+        // Undirected edges carry flow either way
+        let undirected = create_test_graph();
+        assert!((max_flow(&undirected, 0, 2).unwrap() - 2.0).abs() < 1e-9);
+        assert!((max_flow(&undirected, 2, 0).unwrap() - 2.0).abs() < 1e-9);
+
+        assert!(matches!(max_flow(&graph, 1, 1), Err(GraphError::InvalidParameter(_))));
+        assert!(matches!(max_flow(&graph, 0, 9), Err(GraphError::NodeNotFound(9))));
+        graph.add_edge(5, 0, -1.0).unwrap();
+        assert!(matches!(max_flow(&graph, 0, 5), Err(GraphError::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn test_multi_commodity_flow() {
+        // Both commodities cross the edge 2 -> 3 of capacity 10
+        let mut graph = Graph::new_directed();
+        for i in 0..6 {
+            graph.add_node_simple(format!("v{}", i));
+        }
+        for (from, to) in [(0, 2), (1, 2), (2, 3), (3, 4), (3, 5)] {
+            graph.add_edge(from, to, 10.0).unwrap();
+        }
+
+        let light = [(0, 4, 4.0), (1, 5, 4.0)];
+        let flows = multi_commodity_max_flow(&graph, &light).unwrap();
+        assert!(flows.iter().all(|&flow| (flow - 4.0).abs() < 1e-9), "{:?}", flows);
+        assert!(is_feasible_flow(&graph, &light));
+
+        // Only 10 of the 16 demanded fit, shared evenly
+        let heavy = [(0, 4, 8.0), (1, 5, 8.0)];
+        let flows = multi_commodity_max_flow(&graph, &heavy).unwrap();
+        for &flow in &flows {
+            assert!(flow > 0.85 * 5.0 && flow <= 5.0 + 1e-9, "{:?}", flows);
+        }
+        assert!(!is_feasible_flow(&graph, &heavy));
+
+        // One commodity gets its maximum flow
+        for demand in [1.0, 2.0, 5.0] {
+            let single = [(0, 2, demand)];
+            let flows = multi_commodity_max_flow(&create_test_graph(), &single).unwrap();
+            assert!((flows[0] - demand.min(2.0)).abs() < 1e-9, "{:?}", flows);
+            assert_eq!(is_feasible_flow(&create_test_graph(), &single), demand <= 2.0);
+        }
+
+        // Unreachable and trivial demands
+        let mixed = [(4, 0, 1.0), (0, 4, 3.0), (2, 2, 7.0), (1, 5, 0.0)];
+        assert_eq!(multi_commodity_max_flow(&graph, &mixed).unwrap(), vec![0.0, 3.0, 7.0, 0.0]);
+        assert!(!is_feasible_flow(&graph, &mixed));
+        assert!(is_feasible_flow(&graph, &mixed[1..]));
+        assert!(multi_commodity_max_flow(&graph, &[(0, 4, -1.0)]).is_err());
+        assert!(!is_feasible_flow(&graph, &[(0, 9, 1.0)]));
+    }
+
+    #[test]
+    fn test_bfs() {
+        let graph = create_test_graph();
+        let path = bfs(&graph, 0, 2).unwrap();
+        assert!(!path.is_empty());
+        assert_eq!(path[0], 0);
+        assert_eq!(path[path.len() - 1], 2);
+    }
+
+    #[test]
+    fn test_dijkstra() {
+        let graph = create_test_graph();
+        let (path, cost) = dijkstra(&graph, 0, 2).unwrap();
+        assert!(!path.is_empty());
+        assert_eq!(path[0], 0);
+        assert_eq!(path[path.len() - 1], 2);
+        assert!(cost > 0.0);
+    }
+
+    /// 5x5 grid with horizontal edges of weight 1 and vertical edges of
+    /// weight 2, so node `5r + c` is `c + 2r` away from node 0
+    fn weighted_grid() -> Graph {
+        let mut graph = Graph::new();
+        for i in 0..25 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        for r in 0..5 {
+            for c in 0..5 {
+                if c < 4 {
+                    graph.add_edge(5 * r + c, 5 * r + c + 1, 1.0).unwrap();
+                }
+                if r < 4 {
+                    graph.add_edge(5 * r + c, 5 * (r + 1) + c, 2.0).unwrap();
+                }
+            }
+        }
+        graph
+    }
+
+    #[test]
+    fn test_dijkstra_within() {
+        let graph = weighted_grid();
+
+        // The bound is inclusive: 4, 7 and 10 are exactly 4.0 away
+        let within = dijkstra_within(&graph, 0, 4.0).unwrap();
+        let mut nodes: Vec<NodeId> = within.keys().copied().collect();
+        nodes.sort_unstable();
+        assert_eq!(nodes, vec![0, 1, 2, 3, 4, 5, 6, 7, 10]);
+        assert_eq!(within[&7], 4.0);
+        assert_eq!(within[&10], 4.0);
+
+        let within = dijkstra_within(&graph, 0, 4.0 - 1e-9).unwrap();
+        assert_eq!(within.len(), 6);
+        assert!(!within.contains_key(&10));
+
+        assert_eq!(dijkstra_within(&graph, 0, 0.0).unwrap(), HashMap::from([(0, 0.0)]));
+        assert!(dijkstra_within(&graph, 0, -1.0).unwrap().is_empty());
+        assert_eq!(dijkstra_within(&graph, 0, f64::INFINITY).unwrap().len(), 25);
+        assert!(dijkstra_within(&graph, 99, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_dijkstra_multi_target() {
+        let mut graph = weighted_grid();
+        let isolated = graph.add_node_simple("Isolated");
+
+        let targets = HashSet::from([2, 6, 24, isolated]);
+        let paths = dijkstra_multi_target(&graph, 0, &targets).unwrap();
+        assert_eq!(paths.len(), 3);
+        assert_eq!(paths[&2], (vec![0, 1, 2], 2.0));
+        assert_eq!(paths[&6].1, 3.0);
+        assert_eq!(paths[&24].1, 12.0);
+        for (&target, (path, cost)) in &paths {
+            assert_eq!(dijkstra(&graph, 0, target).unwrap().1, *cost);
+            assert_eq!((path[0], path[path.len() - 1]), (0, target));
+        }
+
+        assert!(dijkstra_multi_target(&graph, 0, &HashSet::new()).unwrap().is_empty());
+        assert!(matches!(
+            dijkstra_multi_target(&graph, 0, &HashSet::from([99])),
+            Err(GraphError::NodeNotFound(99))
+        ));
+    }
+
+    #[test]
+    fn test_astar_matches_dijkstra() {
+        let graph = create_test_graph();
+        let (_, expected) = dijkstra(&graph, 0, 2).unwrap();
+
+        let (path, cost) = astar(&graph, 0, 2, |_| 0.0).unwrap();
+        assert_eq!(path, vec![0, 1, 2]);
+        assert_eq!(cost, expected);
+
+        // Exact remaining distances are the tightest admissible heuristic
+        let remaining = [3.0, 2.0, 0.0, 1.0];
+        let (_, cost) = astar(&graph, 0, 2, |n| remaining[n]).unwrap();
+        assert_eq!(cost, expected);
+    }
+
+    #[test]
+    fn test_floyd_warshall() {
+        let mut graph = create_test_graph();
+        graph.add_node_simple("E");
+        let dist = floyd_warshall(&graph).unwrap();
+
+        assert_eq!(dist[0][2], 3.0);
+        assert_eq!(dist[2][0], 3.0);
+        assert_eq!(dist[1][3], 3.0);
+        assert_eq!(dist[3][3], 0.0);
+        assert_eq!(dist[0][4], Weight::INFINITY);
+    }
+
+    #[test]
+    fn test_floyd_warshall_resumes_from_checkpoint() {
+        let config = crate::testing::GraphConfig::default().with_size(250, 1000);
+        let graph = crate::testing::random_graph(config, 3);
+        let expected = floyd_warshall(&graph).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("floyd.ckpt");
+
+        // A run that crashed after five iterations
+        let mut state = FloydWarshallState::new(&graph).unwrap();
+        for _ in 0..5 {
+            state.step();
+        }
+        state.save_checkpoint(&path).unwrap();
+        assert_eq!(FloydWarshallState::load_checkpoint(&path).unwrap(), Some(state));
+
+        assert_eq!(floyd_warshall_resumable(&graph, Some(&path)).unwrap(), expected);
+        assert!(!path.exists());
+        assert_eq!(floyd_warshall_resumable(&graph, Some(&path)).unwrap(), expected);
+        assert_eq!(floyd_warshall_resumable(&graph, None).unwrap(), expected);
+
+        let rejected = |state: FloydWarshallState| {
+            state.save_checkpoint(&path).unwrap();
+            match floyd_warshall_resumable(&graph, Some(&path)) {
+                Err(GraphError::InvalidData(message)) => message,
+                other => panic!("expected InvalidData, got {:?}", other.map(|d| d.len())),
+            }
+        };
+        let n = graph.node_count();
+        let small = create_test_graph();
+        let other_size = FloydWarshallState::new(&small).unwrap();
+        assert_eq!(
+            rejected(other_size),
+            format!("Checkpoint has {} nodes but the graph has {}", small.node_count(), n)
+        );
+
+        // Same nodes, one more edge
+        let mut other = graph.clone();
+        let ids = other.node_ids();
+        other.add_edge(ids[0], ids[1], 0.5).unwrap();
+        let other_graph = FloydWarshallState::new(&other).unwrap();
+        assert_eq!(rejected(other_graph), "Checkpoint was taken on a different graph");
+
+        let mut short_row = FloydWarshallState::new(&graph).unwrap();
+        short_row.dist[7].pop();
+        assert_eq!(
+            rejected(short_row),
+            format!("Checkpoint row 7 has {} entries instead of {}", n - 1, n)
+        );
+        let mut past_end = FloydWarshallState::new(&graph).unwrap();
+        past_end.k = n + 1;
+        assert_eq!(rejected(past_end), format!("Checkpoint is at iteration {} of {}", n + 1, n));
+    }
+
+    fn graph_with_edges(nodes: usize, edges: &[(NodeId, NodeId)]) -> Graph {
+        let mut graph = Graph::new();
+        for i in 0..nodes {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        for &(from, to) in edges {
+            graph.add_edge(from, to, 1.0).unwrap();
+        }
+        graph
+    }
+
+    #[test]
+    fn test_cliques_complete_graph() {
+        let graph = graph_with_edges(4, &[(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3)]);
+        assert_eq!(find_cliques(&graph).unwrap(), vec![vec![0, 1, 2, 3]]);
+        assert_eq!(find_maximum_clique(&graph).unwrap(), vec![0, 1, 2, 3]);
+        assert_eq!(clique_number(&graph).unwrap(), 4);
+
+        assert!(find_cliques(&Graph::new()).unwrap().is_empty());
+        assert_eq!(clique_number(&Graph::new()).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_cliques_path_graph() {
+        let graph = graph_with_edges(5, &[(0, 1), (1, 2), (2, 3), (3, 4)]);
+        let cliques = find_cliques(&graph).unwrap();
+        assert_eq!(cliques.len(), 4);
+        assert!(cliques.iter().all(|clique| clique.len() == 2));
+        assert_eq!(clique_number(&graph).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_cliques_disjoint_triangles() {
+        let graph = graph_with_edges(6, &[(0, 1), (1, 2), (2, 0), (3, 4), (4, 5), (5, 3)]);
+        assert_eq!(find_cliques(&graph).unwrap(), vec![vec![0, 1, 2], vec![3, 4, 5]]);
+        assert_eq!(clique_number(&graph).unwrap(), 3);
+
+        // A pendant node adds a maximal edge clique without changing the maximum
+        let mut graph = graph;
+        let extra = graph.add_node_simple("tail");
+        graph.add_edge(5, extra, 1.0).unwrap();
+        assert_eq!(find_cliques(&graph).unwrap().len(), 3);
+        assert_eq!(find_maximum_clique(&graph).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_connected_components() {
+        let mut graph = Graph::new_directed();
+        for i in 0..6 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        graph.add_edge(1, 0, 1.0).unwrap();
+        graph.add_edge(2, 1, 1.0).unwrap();
+        graph.add_edge(4, 3, 1.0).unwrap();
+
+        let components = connected_components(&graph).unwrap();
+        assert_eq!(components, vec![vec![0, 1, 2], vec![3, 4], vec![5]]);
+    }
+
+    /// Exact ball sizes of radius `0..=max_k` around `source` by BFS
+    fn exact_ball_sizes(graph: &Graph, source: NodeId, max_k: usize) -> Vec<usize> {
+        let mut depth = HashMap::from([(source, 0)]);
+        let mut queue = VecDeque::from([source]);
+        while let Some(node) = queue.pop_front() {
+            let d = depth[&node];
+            for neighbor in graph.neighbors(node).unwrap() {
+                if let std::collections::hash_map::Entry::Vacant(entry) = depth.entry(neighbor) {
+                    entry.insert(d + 1);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        (0..=max_k).map(|r| depth.values().filter(|&&d| d <= r).count()).collect()
+    }
+
+    #[test]
+    fn test_neighborhood_function_accuracy() {
+        // Ring of 600 nodes with chords, so balls grow well past 2^10
+        let mut graph = Graph::new();
+        let n = 600;
+        for i in 0..n {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        for i in 0..n {
+            graph.add_edge(i, (i + 1) % n, 1.0).unwrap();
+            graph.add_edge(i, (i * 7 + 3) % n, 1.0).unwrap();
+        }
+
+        let max_k = 6;
+        let estimates = neighborhood_function(&graph, max_k);
+        assert_eq!(estimates.len(), max_k + 1);
+
+        // Three standard errors of a precision-10 counter
+        let bound = 3.0 * 1.04 / (1024f64).sqrt();
+        let mut total_error = 0.0;
+        for source in (0..n).step_by(37) {
+            let exact = exact_ball_sizes(&graph, source, max_k);
+            for r in 0..=max_k {
+                let relative = (estimates[r][&source] - exact[r] as f64).abs() / exact[r] as f64;
+                assert!(relative < bound, "node {} radius {}: error {}", source, r, relative);
+                total_error += relative;
+            }
+        }
+        assert!(total_error / (17.0 * (max_k + 1) as f64) < 1.04 / (1024f64).sqrt());
+
+        // At precision 6 most balls exceed the linear-counting range
+        let coarse = neighborhood_function_with_precision(&graph, max_k, 6).unwrap();
+        let bound = 3.0 * 1.04 / 8.0;
+        for source in (0..n).step_by(37) {
+            let exact = exact_ball_sizes(&graph, source, max_k)[max_k] as f64;
+            assert!((coarse[max_k][&source] - exact).abs() / exact < bound);
+        }
+    }
+
+    #[test]
+    fn test_neighborhood_function_saturates() {
+        let graph = create_test_graph();
+        let estimates = neighborhood_function(&graph, 10);
+        assert_eq!(estimates.len(), 11);
+        for node in graph.node_ids() {
+            assert!((estimates[10][&node] - estimates[4][&node]).abs() < 1e-9);
+        }
+
+        assert!(neighborhood_function_with_precision(&graph, 3, 2).is_err());
+        assert!(neighborhood_function_with_precision(&graph, 3, 16).is_ok());
+        assert!(neighborhood_function(&Graph::new(), 3).iter().all(HashMap::is_empty));
+    }
+
+    #[test]
+    fn test_effective_diameter_of_path() {
+        let mut graph = Graph::new();
+        for i in 0..10 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        for i in 0..9 {
+            graph.add_edge(i, i + 1, 1.0).unwrap();
+        }
+
+        // 78 of 90 ordered pairs lie within 6 hops and 84 within 7
+        let estimate = effective_diameter(&neighborhood_function(&graph, 9)).unwrap();
+        assert!((estimate - 6.5).abs() < 0.25, "estimate {}", estimate);
+    }
+
+    /// Barabási-Albert graph: each new node attaches to `m` existing ones
+    fn scale_free_graph(n: usize, m: usize, seed: u64) -> Graph {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut graph = Graph::new();
+        let mut endpoints: Vec<NodeId> = Vec::new();
+        for i in 0..n {
+            graph.add_node_simple(format!("Node{}", i));
+            let mut targets = HashSet::new();
+            while i > 0 && targets.len() < m.min(i) {
+                let target = if endpoints.is_empty() {
+                    0
+                } else {
+                    endpoints[rng.gen_range(0..endpoints.len())]
+                };
+                targets.insert(target);
+            }
+            for target in targets {
+                graph.add_edge(i, target, 1.0).unwrap();
+                endpoints.extend([i, target]);
+            }
+        }
+        graph
+    }
+
+    #[test]
+    fn test_sparsify_preserves_nodes() {
+        let mut graph = create_test_graph();
+        graph.add_node(Node::new(10, "Tagged").with_property("score", 2.5));
+        graph.insert_edge(Edge::new(10, 0, 3.0).with_type("special")).unwrap();
+
+        for method in [SparsifyMethod::Uniform, SparsifyMethod::DegreeProportional] {
+            let sparse = sparsify(&graph, method, 0.4, Some(1)).unwrap();
+            assert_eq!(sparse.node_count(), graph.node_count());
+            assert_eq!(sparse.edge_count(), 2);
+            assert_eq!(sparse.node(10).unwrap().properties["score"], 2.5);
+        }
+
+        let full = sparsify(&graph, SparsifyMethod::Uniform, 1.0, Some(1)).unwrap();
+        assert!(full.edges().iter().any(|e| e.edge_type == "special"));
+        assert!(sparsify(&graph, SparsifyMethod::Uniform, 0.0, None).is_err());
+    }
+
+    #[test]
+    fn test_spanner_preserves_connectivity() {
+        let graph = scale_free_graph(300, 3, 11);
+        assert_eq!(connected_components(&graph).unwrap().len(), 1);
+
+        // Even a budget below the spanning tree keeps the tree
+        for fraction in [0.1, 0.5] {
+            let sparse = sparsify(&graph, SparsifyMethod::Spanner, fraction, Some(5)).unwrap();
+            assert_eq!(connected_components(&sparse).unwrap().len(), 1);
+            assert!(sparse.edge_count() >= graph.node_count() - 1);
+        }
+    }
+
+    #[test]
+    fn test_sparsify_rescaled_weighted_degree() {
+        let graph = scale_free_graph(300, 3, 13);
+        let sparse = sparsify_rescaled(&graph, SparsifyMethod::Uniform, 0.5, Some(2)).unwrap();
+
+        let total = |g: &Graph| g.edges().iter().map(|e| e.weight).sum::<f64>();
+        assert!((total(&sparse) - total(&graph)).abs() < 1e-6 * total(&graph));
+        assert_eq!(sparse.edge_count(), graph.edge_count() / 2);
+    }
+
+    #[test]
+    fn test_sparsified_pagerank_correlates() {
+        let graph = scale_free_graph(300, 4, 17);
+        let original = crate::centrality::pagerank(&graph, 0.85, 50, 1e-8).unwrap();
+
+        // Uniform sampling scrambles the many low-degree nodes at this rate
+        for method in [SparsifyMethod::DegreeProportional, SparsifyMethod::Spanner] {
+            let sparse = sparsify(&graph, method, 0.5, Some(3)).unwrap();
+            let sampled = crate::centrality::pagerank(&sparse, 0.85, 50, 1e-8).unwrap();
+
+            let ids = graph.node_ids();
+            let a: Vec<f64> = ids.iter().map(|id| original[id]).collect();
+            let b: Vec<f64> = ids.iter().map(|id| sampled[id]).collect();
+            let rho = crate::testing::spearman_correlation(&a, &b);
+            assert!(rho > 0.8, "{:?}: spearman {}", method, rho);
+        }
+    }
+
+    /// The fraud motif: a payment cycle A -> B -> C -> A with a side
+    /// payment from C to D
+    fn fraud_motif() -> Graph {
+        let mut pattern = Graph::new_directed();
+        for label in ["A", "B", "C", "D"] {
+            pattern.add_node_simple(label);
+        }
+        for (from, to) in [(0, 1), (1, 2), (2, 0), (2, 3)] {
+            pattern.add_edge(from, to, 1.0).unwrap();
+        }
+        pattern
+    }
+
+    #[test]
+    fn test_subgraph_isomorphisms_count() {
+        // Two payment cycles, 0 -> 1 -> 2 and 3 -> 4 -> 5, with side
+        // payments 2 -> 6, 2 -> 7 and 0 -> 8; the second cycle has none
+        let mut target = Graph::new_directed();
+        for i in 0..9 {
+            target.add_node_simple(format!("Node{}", i));
+        }
+        for (from, to) in [(0, 1), (1, 2), (2, 0), (3, 4), (4, 5), (5, 3)] {
+            target.add_edge(from, to, 1.0).unwrap();
+        }
+        for (from, to) in [(2, 6), (2, 7), (0, 8)] {
+            target.add_edge(from, to, 1.0).unwrap();
+        }
+
+        // Each side payment leaves one cycle node, which fixes the rest
+        let matches =
+            subgraph_isomorphisms(&fraud_motif(), &target, SubgraphMatchOptions::default()).unwrap();
+        assert_eq!(matches.len(), 3);
+        for mapping in &matches {
+            for edge in fraud_motif().edges() {
+                let neighbors = target.neighbors(mapping[&edge.from]).unwrap();
+                assert!(neighbors.contains(&mapping[&edge.to]));
+            }
+        }
+        let sides: HashSet<NodeId> = matches.iter().map(|mapping| mapping[&3]).collect();
+        assert_eq!(sides, HashSet::from([6, 7, 8]));
+
+        let capped = SubgraphMatchOptions::default().with_max_matches(2);
+        assert_eq!(subgraph_isomorphisms(&fraud_motif(), &target, capped).unwrap().len(), 2);
+
+        // A chord 6 -> 7 only changes induced matches
+        target.add_edge(6, 7, 1.0).unwrap();
+        let induced = SubgraphMatchOptions::default().induced();
+        assert_eq!(subgraph_isomorphisms(&fraud_motif(), &target, induced).unwrap().len(), 3);
+        target.add_edge(1, 8, 1.0).unwrap();
+        assert_eq!(subgraph_isomorphisms(&fraud_motif(), &target, induced).unwrap().len(), 2);
+
+        assert!(subgraph_isomorphisms(&fraud_motif(), &Graph::new(), induced).is_err());
+    }
+
+    #[test]
+    fn test_subgraph_isomorphisms_labels() {
+        let mut pattern = Graph::new();
+        pattern.add_node_simple("account");
+        pattern.add_node_simple("merchant");
+        pattern.insert_edge(Edge::new(0, 1, 1.0).with_type("payment")).unwrap();
+
+        let mut target = Graph::new();
+        for label in ["account", "merchant", "account", "account"] {
+            target.add_node_simple(label);
+        }
+        target.insert_edge(Edge::new(0, 1, 1.0).with_type("payment")).unwrap();
+        target.insert_edge(Edge::new(2, 1, 1.0).with_type("refund")).unwrap();
+        target.insert_edge(Edge::new(2, 3, 1.0).with_type("payment")).unwrap();
+
+        // Unlabeled, the single edge matches each of the three edges
+        // in both orientations
+        let any = SubgraphMatchOptions::default();
+        assert_eq!(subgraph_isomorphisms(&pattern, &target, any).unwrap().len(), 6);
+
+        let labeled = subgraph_isomorphisms(&pattern, &target, any.with_labels()).unwrap();
+        assert_eq!(labeled, vec![HashMap::from([(0, 0), (1, 1)])]);
+    }
+
+    #[test]
+    fn test_are_isomorphic() {
+        let mut relabeled = Graph::new_directed();
+        for id in [10, 20, 30, 40] {
+            relabeled.add_node(Node::new(id, "Node"));
+        }
+        for (from, to) in [(30, 20), (20, 40), (40, 30), (40, 10)] {
+            relabeled.add_edge(from, to, 1.0).unwrap();
+        }
+        assert!(are_isomorphic(&fraud_motif(), &relabeled));
+
+        relabeled.add_edge(10, 20, 1.0).unwrap();
+        assert!(!are_isomorphic(&fraud_motif(), &relabeled));
+    }
+
+    #[test]
+    fn test_reachability_matrix() {
+        let mut chain = Graph::new_directed();
+        for i in 0..5 {
+            chain.add_node_simple(format!("Node{}", i));
+        }
+        for i in 0..4 {
+            chain.add_edge(i, i + 1, 1.0).unwrap();
+        }
+
+        let matrix = reachability_matrix(&chain);
+        assert!(matrix[0][4]);
+        assert!(!matrix[4][0]);
+        for (i, row) in matrix.iter().enumerate() {
+            for (j, &reachable) in row.iter().enumerate() {
+                assert_eq!(reachable, i <= j);
+            }
+        }
+
+        let graph = create_test_graph();
+        let matrix = reachability_matrix(&graph);
+        assert!(matrix.iter().flatten().all(|&reachable| reachable));
+        assert!(reachability_matrix(&Graph::new()).is_empty());
+    }
+
+    #[test]
+    fn test_dijkstra_filtered() {
+        // Cheap route 0 -> 1 -> 3 through risky node 1, and a costlier
+        // route 0 -> 2 -> 3 with one heavy edge
+        let mut graph = Graph::new();
+        graph.add_node(Node::new(0, "start"));
+        graph.add_node(Node::new(1, "risky").with_property("risk", 0.9));
+        graph.add_node(Node::new(2, "safe").with_property("risk", 0.1));
+        graph.add_node(Node::new(3, "goal"));
+        graph.add_edge(0, 1, 1.0).unwrap();
+        graph.add_edge(1, 3, 1.0).unwrap();
+        graph.add_edge(0, 2, 2.0).unwrap();
+        graph.add_edge(2, 3, 8.0).unwrap();
+
+        let safe = |node: &Node| node.properties.get("risk").is_none_or(|&risk| risk <= 0.8);
+        let any_edge = |_: NodeId, _: NodeId, _: Weight| true;
+        assert_eq!(dijkstra(&graph, 0, 3).unwrap(), (vec![0, 1, 3], 2.0));
+        assert_eq!(dijkstra_filtered(&graph, 0, 3, safe, any_edge).unwrap(), (vec![0, 2, 3], 10.0));
+
+        let light = |_: NodeId, _: NodeId, weight: Weight| weight < 5.0;
+        assert!(matches!(
+            dijkstra_filtered(&graph, 0, 3, safe, light),
+            Err(GraphError::AlgorithmError(_))
+        ));
+        assert!(matches!(
+            dijkstra_filtered(&graph, 1, 3, safe, any_edge),
+            Err(GraphError::AlgorithmError(_))
+        ));
+        assert!(matches!(
+            dijkstra_filtered(&graph, 0, 9, safe, any_edge),
+            Err(GraphError::NodeNotFound(9))
+        ));
+    }
+
+    #[test]
+    fn test_floyd_warshall_progress() {
+        use crate::testing::CountingSink;
+
+        let config = crate::testing::GraphConfig::default().with_size(30, 100);
+        let graph = crate::testing::random_graph(config, 5);
+        let n = graph.node_count() as u64;
+
+        let sink = CountingSink::new();
+        let result = floyd_warshall_with_progress(&graph, Some(&sink)).unwrap();
+        assert!(result.is_complete());
+        assert_eq!(sink.updates(), n);
+        assert_eq!(result.value, floyd_warshall(&graph).unwrap());
+
+        let sink = CountingSink::cancelling_after(3);
+        let partial = floyd_warshall_with_progress(&graph, Some(&sink)).unwrap();
+        assert_eq!((partial.completed, partial.total), (3.min(n), n));
+        let mut state = FloydWarshallState::new(&graph).unwrap();
+        for _ in 0..3 {
+            state.step();
+        }
+        assert_eq!(partial.value, state.dist);
+    }
+
+    #[test]
+    fn test_coalesce_parallel_edges() {
+        let multigraph = |directed: bool| {
+            let mut graph = if directed { Graph::new_directed() } else { Graph::new() };
+            for label in ["A", "B", "C"] {
+                graph.add_node_simple(label);
+            }
+            graph.add_edge(0, 1, 1.0).unwrap();
+            graph.add_typed_edge(0, 1, 3.0, "heavy").unwrap();
+            graph.add_edge(1, 2, 5.0).unwrap();
+            graph.add_edge(1, 0, 2.0).unwrap();
+            graph
+        };
+
+        let mut graph = Graph::new();
+        graph.add_node_simple("A");
+        graph.add_node_simple("B");
+        graph.add_edge(0, 1, 1.0).unwrap();
+        graph.add_edge(0, 1, 1.0).unwrap();
+        assert!(graph.has_parallel_edges());
+        assert_eq!(coalesce_parallel_edges(&mut graph, CoalesceStrategy::SumWeights), 1);
+        assert!(!graph.has_parallel_edges());
+        assert_eq!(graph.edge_count(), 1);
+        assert_eq!(graph.neighbors_with_weights(1).unwrap(), vec![(0, 2.0)]);
+
+        // In the undirected multigraph 1 -- 0 is a third parallel edge
+        for (strategy, weight, edge_type) in [
+            (CoalesceStrategy::KeepFirst, 1.0, "default"),
+            (CoalesceStrategy::KeepLast, 2.0, "default"),
+            (CoalesceStrategy::SumWeights, 6.0, "default"),
+            (CoalesceStrategy::MaxWeight, 3.0, "heavy"),
+            (CoalesceStrategy::MinWeight, 1.0, "default"),
+        ] {
+            let mut graph = multigraph(false);
+            assert_eq!(coalesce_parallel_edges(&mut graph, strategy), 2, "{:?}", strategy);
+            assert_eq!(graph.edge_count(), 2);
+            assert_eq!(graph.edges()[0].weight, weight, "{:?}", strategy);
+            assert_eq!(graph.edges()[0].edge_type, edge_type, "{:?}", strategy);
+            assert_eq!(graph.edges()[1].weight, 5.0);
+            assert!(graph.check_invariants().unwrap().is_empty());
+            assert_eq!(graph.degree(1).unwrap(), 2);
+
+            let mut expected = Graph::new();
+            for node in ["A", "B", "C"] {
+                expected.add_node_simple(node);
+            }
+            let merged = &graph.edges()[0];
+            expected.add_typed_edge(merged.from, merged.to, weight, edge_type).unwrap();
+            expected.add_edge(1, 2, 5.0).unwrap();
+            assert_eq!(graph.content_hash(), expected.content_hash());
+        }
+
+        let mut graph = multigraph(true);
+        assert_eq!(coalesce_parallel_edges(&mut graph, CoalesceStrategy::SumWeights), 1);
+        assert_eq!(graph.neighbors_with_weights(0).unwrap(), vec![(1, 4.0)]);
+        assert_eq!(graph.neighbors_with_weights(1).unwrap(), vec![(2, 5.0), (0, 2.0)]);
+        assert_eq!(coalesce_parallel_edges(&mut graph, CoalesceStrategy::SumWeights), 0);
+    }
+
+    #[test]
+    fn test_temporal_reachability() {
+        // 0 -> 1 -> 2 respects time; 2 -> 3 happened before 1 -> 2, so 3
+        // is only structurally reachable. 0 -> 4 has no timestamp.
+        let mut graph = Graph::new_directed();
+        for i in 0..5 {
+            graph.add_node_simple(format!("N{}", i));
+        }
+        graph.add_timestamped_edge(0, 1, 1.0, 1.0).unwrap();
+        graph.add_timestamped_edge(1, 2, 1.0, 5.0).unwrap();
+        graph.add_timestamped_edge(2, 3, 1.0, 3.0).unwrap();
+        graph.add_edge(0, 4, 1.0).unwrap();
+        assert!(graph.is_reachable(0, 3));
+
+        let arrival = temporal_reachability(&graph, 0, 0.0).unwrap();
+        assert_eq!(arrival, HashMap::from([(0, 0.0), (1, 1.0), (2, 5.0)]));
+
+        // Starting too late misses the first edge
+        let arrival = temporal_reachability(&graph, 0, 2.0).unwrap();
+        assert_eq!(arrival, HashMap::from([(0, 2.0)]));
+
+        // An earlier route to 2 opens up 2 -> 3
+        graph.add_timestamped_edge(0, 2, 1.0, 2.0).unwrap();
+        let arrival = temporal_reachability(&graph, 0, 0.0).unwrap();
+        assert_eq!(arrival.get(&2), Some(&2.0));
+        assert_eq!(arrival.get(&3), Some(&3.0));
+
+        assert!(matches!(
+            temporal_reachability(&graph, 9, 0.0),
+            Err(GraphError::NodeNotFound(9))
+        ));
+    }
+
+    /// Two triangles of weight-5 edges joined by a weight-1 bridge
+    fn weighted_barbell() -> Graph {
+        let mut graph = Graph::new();
+        for i in 0..6 {
+            graph.add_node_simple(format!("N{}", i));
+        }
+        for (a, b) in [(0, 1), (1, 2), (0, 2), (3, 4), (4, 5), (3, 5)] {
+            graph.add_edge(a, b, 5.0).unwrap();
+        }
+        graph.add_edge(2, 3, 1.0).unwrap();
+        graph
+    }
+
+    #[test]
+    fn test_label_propagation() {
+        let communities = label_propagation(&weighted_barbell(), 10);
+        let labels: Vec<usize> = (0..6).map(|id| communities[&id]).collect();
+        assert_eq!(labels, vec![0, 0, 0, 1, 1, 1]);
+
+        let mut isolated = Graph::new();
+        isolated.add_node_simple("A");
+        isolated.add_node_simple("B");
+        assert_eq!(label_propagation(&isolated, 10), HashMap::from([(0, 0), (1, 1)]));
+    }
+
+    #[test]
+    fn test_coarsen() {
+        let total_weight = |graph: &Graph| graph.edges().iter().map(|e| e.weight).sum::<f64>();
+        let graph = weighted_barbell();
+        let mut partition: HashMap<NodeId, usize> = (0..6).map(|id| (id, 7)).collect();
+        partition.insert(4, 3);
+        partition.insert(5, 3);
+
+        let (coarse, mapping) = coarsen(&graph, &partition).unwrap();
+        assert_eq!(coarse.node_count(), 2);
+        assert_eq!(mapping[&0], 1);
+        assert_eq!(mapping[&4], 0);
+        let node = coarse.node(1).unwrap();
+        assert_eq!(node.label, "community_7");
+        assert_eq!(node.properties["member_count"], 4.0);
+        assert_eq!(coarse.node(0).unwrap().properties["member_count"], 2.0);
+        assert_eq!(total_weight(&coarse), total_weight(&graph));
+        assert_eq!(coarse.edge_count(), 3);
+        assert_eq!(coarse.neighbors_with_weights(0).unwrap(), vec![(0, 5.0), (1, 10.0)]);
+
+        let options = CoarsenOptions::default().drop_self_loops();
+        let (without_loops, _) = coarsen_with_options(&graph, &partition, options).unwrap();
+        assert_eq!(without_loops.edge_count(), 1);
+        assert_eq!(total_weight(&without_loops), 10.0);
+
+        partition.remove(&2);
+        assert!(matches!(coarsen(&graph, &partition), Err(GraphError::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn test_coarsen_recursive() {
+        let graph = weighted_barbell();
+        let hierarchy = coarsen_recursive(&graph, 3).unwrap();
+        assert_eq!(hierarchy.len(), 1);
+        let (coarse, mapping) = &hierarchy[0];
+        assert_eq!(coarse.node_count(), 2);
+        assert_eq!(mapping[&0], mapping[&2]);
+        assert_ne!(mapping[&2], mapping[&3]);
+        assert_eq!(coarse.edges().iter().map(|e| e.weight).sum::<f64>(), 31.0);
+        for node in 0..2 {
+            assert_eq!(coarse.node(node).unwrap().properties["member_count"], 3.0);
+        }
+        assert!(coarsen_recursive(&graph, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_shortest_path_trees() {
+        // The direct edge 0 -> 2 is one hop but heavier than 0 -> 1 -> 2
+        let mut graph = Graph::new_directed();
+        for label in ["A", "B", "C", "D"] {
+            graph.add_node_simple(label);
+        }
+        graph.add_edge(0, 2, 5.0).unwrap();
+        graph.add_edge(0, 1, 1.0).unwrap();
+        graph.add_edge(1, 2, 1.0).unwrap();
+        graph.add_edge(3, 0, 1.0).unwrap();
+
+        let (parents, distances) = dijkstra_tree(&graph, 0).unwrap();
+        assert_eq!(parents, HashMap::from([(1, 0), (2, 1)]));
+        assert_eq!(distances, HashMap::from([(0, 0.0), (1, 1.0), (2, 2.0)]));
+
+        let (parents, distances) = bfs_tree(&graph, 0).unwrap();
+        assert_eq!(parents, HashMap::from([(1, 0), (2, 0)]));
+        assert_eq!(distances, HashMap::from([(0, 0.0), (1, 1.0), (2, 1.0)]));
+
+        assert!(matches!(dijkstra_tree(&graph, 9), Err(GraphError::NodeNotFound(9))));
+        assert!(matches!(bfs_tree(&graph, 9), Err(GraphError::NodeNotFound(9))));
+    }
+
+    /// Longest finite hop distance, by BFS from every node
+    fn brute_force_diameter(graph: &Graph) -> usize {
+        let ids = graph.node_ids();
+        ids.iter()
+            .flat_map(|&id| crate::testing::hop_distances(graph, id).into_values())
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn test_double_sweep_diameter() {
+        // Path 0 - 1 - ... - 9: the sweep ends at both ends
+        let path: Vec<(NodeId, NodeId)> = (0..9).map(|i| (i, i + 1)).collect();
+        let graph = graph_with_edges(10, &path);
+        assert_eq!(double_sweep_diameter(&graph).unwrap(), (9, 9, 0));
+        assert_eq!(exact_diameter_small(&graph).unwrap(), 9);
+
+        // Binary tree of depth 4: leaf to leaf through the root
+        let tree: Vec<(NodeId, NodeId)> = (1..31).map(|i| ((i - 1) / 2, i)).collect();
+        let graph = graph_with_edges(31, &tree);
+        assert_eq!(double_sweep_diameter(&graph).unwrap().0, 8);
+        assert_eq!(exact_diameter_small(&graph).unwrap(), 8);
+
+        assert!(double_sweep_diameter(&Graph::new()).is_err());
+        assert!(exact_diameter_small(&Graph::new()).is_err());
+        let too_big = graph_with_edges(EXACT_DIAMETER_MAX_NODES + 1, &[]);
+        assert!(exact_diameter_small(&too_big).is_err());
+        assert_eq!(double_sweep_diameter(&too_big).unwrap().0, 0);
+    }
+
+    #[test]
+    fn test_double_sweep_is_a_lower_bound() {
+        for (seed, directed) in (0..20).map(|seed| (seed, seed % 2 == 1)) {
+            let config = crate::testing::GraphConfig::default()
+                .with_size(40, 60)
+                .with_directed(directed);
+            let graph = crate::testing::random_graph(config, seed);
+            if graph.node_count() == 0 {
+                continue;
+            }
+            let exact = brute_force_diameter(&graph);
+            let (bound, u, v) = double_sweep_diameter(&graph).unwrap();
+            assert!(bound <= exact, "seed {}: {} > {}", seed, bound, exact);
+            assert_eq!(crate::testing::hop_distances(&graph, u).get(&v), Some(&bound));
+            assert_eq!(exact_diameter_small(&graph).unwrap(), exact, "seed {}", seed);
+        }
+    }
+
+    fn directed_with_edges(nodes: usize, edges: &[(NodeId, NodeId)]) -> Graph {
+        let mut graph = Graph::new_directed();
+        for i in 0..nodes {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        for &(from, to) in edges {
+            graph.add_edge(from, to, 1.0).unwrap();
+        }
+        graph
+    }
+
+    #[test]
+    fn test_find_all_simple_cycles() {
+        let triangle = directed_with_edges(3, &[(0, 1), (1, 2), (2, 0)]);
+        assert_eq!(find_all_simple_cycles(&triangle).unwrap(), vec![vec![0, 1, 2]]);
+
+        let two_triangles =
+            directed_with_edges(6, &[(0, 1), (1, 2), (2, 0), (3, 4), (4, 5), (5, 3), (2, 3)]);
+        assert_eq!(
+            find_all_simple_cycles(&two_triangles).unwrap(),
+            vec![vec![0, 1, 2], vec![3, 4, 5]]
+        );
+
+        // K3 with both directions: three 2-cycles and the two orientations
+        // of the triangle, so five cycles
+        let both_ways: Vec<(NodeId, NodeId)> =
+            (0..3).flat_map(|a| (0..3).filter(move |&b| b != a).map(move |b| (a, b))).collect();
+        let cycles = find_all_simple_cycles(&directed_with_edges(3, &both_ways)).unwrap();
+        assert_eq!(
+            cycles,
+            vec![vec![0, 1], vec![0, 2], vec![0, 1, 2], vec![0, 2, 1], vec![1, 2]]
+        );
+
+        let looped = directed_with_edges(2, &[(1, 1), (0, 1), (0, 1)]);
+        assert_eq!(find_all_simple_cycles(&looped).unwrap(), vec![vec![1]]);
+        assert!(matches!(
+            find_all_simple_cycles(&graph_with_edges(3, &[(0, 1)])),
+            Err(GraphError::InvalidStructure(_))
+        ));
+    }
+
+    #[test]
+    fn test_find_simple_cycles_with_limit() {
+        // Complete digraph on 6 nodes: sum over k of C(6, k) (k - 1)! cycles
+        let edges: Vec<(NodeId, NodeId)> =
+            (0..6).flat_map(|a| (0..6).filter(move |&b| b != a).map(move |b| (a, b))).collect();
+        let complete = directed_with_edges(6, &edges);
+        let all = find_all_simple_cycles(&complete).unwrap();
+        assert_eq!(all.len(), 15 + 40 + 90 + 144 + 120);
+        let key = |cycle: &Vec<NodeId>| (cycle[0], cycle.len());
+        assert!(all.windows(2).all(|pair| key(&pair[0]) <= key(&pair[1])));
+
+        let capped = find_simple_cycles_with_limit(&complete, 10).unwrap();
+        assert_eq!(capped.len(), 10);
+        assert!(capped.iter().all(|cycle| cycle[0] == 0 && all.contains(cycle)));
+        assert!(find_simple_cycles_with_limit(&complete, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_core_numbers() {
+        // K4 on 0..4 with a tail 3 - 4 - 5 and an isolated node 6
+        let mut edges: Vec<(NodeId, NodeId)> =
+            (0..4).flat_map(|a| (a + 1..4).map(move |b| (a, b))).collect();
+        edges.extend([(3, 4), (4, 5), (4, 4)]);
+        let cores = core_numbers(&graph_with_edges(7, &edges)).unwrap();
+        assert_eq!(
+            cores,
+            HashMap::from([(0, 3), (1, 3), (2, 3), (3, 3), (4, 1), (5, 1), (6, 0)])
+        );
+        assert!(core_numbers(&Graph::new()).unwrap().is_empty());
+    }
+    #[test]
+    fn test_find_bridges() {
+        let single = graph_with_edges(2, &[(0, 1)]);
+        assert_eq!(find_bridges(&single).unwrap(), vec![(0, 1)]);
+        assert!(find_articulation_points(&single).unwrap().is_empty());
+
+        let k4 = graph_with_edges(4, &[(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3)]);
+        assert!(find_bridges(&k4).unwrap().is_empty());
+        assert!(find_articulation_points(&k4).unwrap().is_empty());
+
+        let triangles =
+            graph_with_edges(6, &[(0, 1), (1, 2), (2, 0), (3, 4), (4, 5), (5, 3), (3, 2)]);
+        assert_eq!(find_bridges(&triangles).unwrap(), vec![(2, 3)]);
+        assert_eq!(find_articulation_points(&triangles).unwrap(), vec![2, 3]);
+        assert!(triangles.is_bridge(3, 2).unwrap());
+        assert!(!triangles.is_bridge(0, 1).unwrap());
+        assert!(!triangles.is_bridge(0, 5).unwrap());
+        assert!(triangles.is_articulation_point(2).unwrap());
+        assert!(!triangles.is_articulation_point(4).unwrap());
+        assert!(triangles.is_articulation_point(9).is_err());
+    }
+
+    #[test]
+    fn test_find_bridges_edge_cases() {
+        // A doubled edge and a self-loop do not make bridges
+        let doubled = graph_with_edges(3, &[(0, 1), (1, 0), (1, 2), (2, 2)]);
+        assert_eq!(find_bridges(&doubled).unwrap(), vec![(1, 2)]);
+        assert_eq!(find_articulation_points(&doubled).unwrap(), vec![1]);
+
+        // Star centre and a second component
+        let star = graph_with_edges(6, &[(0, 1), (0, 2), (0, 3), (4, 5)]);
+        assert_eq!(find_bridges(&star).unwrap(), vec![(0, 1), (0, 2), (0, 3), (4, 5)]);
+        assert_eq!(find_articulation_points(&star).unwrap(), vec![0]);
+
+        let directed = directed_with_edges(2, &[(0, 1)]);
+        assert!(matches!(find_bridges(&directed), Err(GraphError::InvalidStructure(_))));
+        assert!(find_articulation_points(&directed).is_err());
+        assert!(find_bridges(&Graph::new()).unwrap().is_empty());
+    }
+    #[test]
+    fn test_search_metrics_on_path() {
+        let n = 10;
+        let edges: Vec<(NodeId, NodeId)> = (1..n).map(|i| (i - 1, i)).collect();
+        let path = graph_with_edges(n, &edges);
+
+        let mut metrics = SearchMetrics::default();
+        let found = bfs_instrumented(&path, 0, n - 1, &mut metrics).unwrap();
+        assert_eq!(found, bfs(&path, 0, n - 1).unwrap());
+        // Every node is expanded, the goal last; each interior node
+        // examines both of its edges
+        assert_eq!(metrics.nodes_expanded, n);
+        assert_eq!(metrics.edges_relaxed, 2 * n - 3);
+        assert_eq!(metrics.max_frontier, 1);
+
+        // Metrics add up across searches
+        bfs_instrumented(&path, 0, 2, &mut metrics).unwrap();
+        assert_eq!(metrics.nodes_expanded, n + 3);
+
+        for search in 0..3 {
+            let mut metrics = SearchMetrics::default();
+            let cost = match search {
+                0 => dfs_instrumented(&path, 0, n - 1, &mut metrics).map(|p| p.len() as f64 - 1.0),
+                1 => dijkstra_instrumented(&path, 0, n - 1, &mut metrics).map(|(_, cost)| cost),
+                _ => astar_instrumented(&path, 0, n - 1, |_| 0.0, &mut metrics).map(|(_, c)| c),
+            };
+            assert_eq!(cost.unwrap(), (n - 1) as f64);
+            assert_eq!(metrics.nodes_expanded, n, "search {}", search);
+            assert_eq!(metrics.edges_relaxed, 2 * n - 3, "search {}", search);
+        }
+
+        // Failed searches still report their work
+        let split = graph_with_edges(4, &[(0, 1), (2, 3)]);
+        let mut metrics = SearchMetrics::default();
+        assert!(bfs_instrumented(&split, 0, 3, &mut metrics).is_err());
+        assert_eq!(metrics.nodes_expanded, 2);
+    }
+
+    #[test]
+    fn test_search_metrics_frontier() {
+        // A star: the start's neighbors all wait at once
+        let star = graph_with_edges(6, &[(0, 1), (0, 2), (0, 3), (0, 4), (0, 5)]);
+        let mut metrics = SearchMetrics::default();
+        bfs_instrumented(&star, 0, 5, &mut metrics).unwrap();
+        assert_eq!(metrics.max_frontier, 5);
+        assert_eq!(metrics.nodes_expanded, 6);
+
+        let mut metrics = SearchMetrics::default();
+        dijkstra_instrumented(&star, 1, 1, &mut metrics).unwrap();
+        assert_eq!((metrics.nodes_expanded, metrics.edges_relaxed), (1, 0));
+    }
+    fn directed_weighted(nodes: usize, edges: &[(NodeId, NodeId, Weight)]) -> Graph {
+        let mut graph = Graph::new_directed();
+        for i in 0..nodes {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        for &(from, to, weight) in edges {
+            graph.add_edge(from, to, weight).unwrap();
+        }
+        graph
+    }
+
+    /// Total weight of `cycle`, taking the lightest edge between each
+    /// consecutive pair and failing if one is missing
+    fn cycle_weight(graph: &Graph, cycle: &[NodeId]) -> Weight {
+        let mut seen = HashSet::new();
+        assert!(cycle.iter().all(|&node| seen.insert(node)), "not simple: {:?}", cycle);
+        (0..cycle.len())
+            .map(|i| {
+                let (from, to) = (cycle[i], cycle[(i + 1) % cycle.len()]);
+                graph
+                    .neighbors_with_weights(from)
+                    .unwrap()
+                    .into_iter()
+                    .filter(|&(neighbor, _)| neighbor == to)
+                    .map(|(_, weight)| weight)
+                    .min_by(|a, b| a.total_cmp(b))
+                    .unwrap_or_else(|| panic!("no edge {} -> {} in {:?}", from, to, cycle))
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_find_negative_cycle() {
+        let graph = directed_weighted(
+            6,
+            &[(5, 0, 2.0), (0, 1, 1.0), (1, 2, -3.0), (2, 0, 1.0), (2, 3, 1.0), (3, 4, 1.0)],
+        );
+        let cycle = find_negative_cycle(&graph).unwrap().unwrap();
+        assert_eq!(cycle, vec![0, 1, 2]);
+        assert!(cycle_weight(&graph, &cycle) < 0.0);
+
+        match bellman_ford(&graph, 5) {
+            Err(GraphError::AlgorithmError(message)) => assert!(message.contains("[0, 1, 2]")),
+            other => panic!("expected a negative cycle error, got {:?}", other),
+        }
+        // The cycle is not reachable from 3
+        assert_eq!(bellman_ford(&graph, 3).unwrap()[&4], 1.0);
+
+        // Found behind a long approach, and with a parallel positive edge
+        let mut edges: Vec<(NodeId, NodeId, Weight)> = (0..20).map(|i| (i, i + 1, 1.0)).collect();
+        edges.extend([(20, 15, -6.0), (20, 15, 3.0)]);
+        let graph = directed_weighted(21, &edges);
+        let cycle = find_negative_cycle(&graph).unwrap().unwrap();
+        assert_eq!(cycle, (15..=20).collect::<Vec<_>>());
+        assert!((cycle_weight(&graph, &cycle) + 1.0).abs() < 1e-12);
+
+        let looped = directed_weighted(2, &[(0, 1, 1.0), (1, 1, -0.5)]);
+        assert_eq!(find_negative_cycle(&looped).unwrap(), Some(vec![1]));
+
+        // An undirected negative edge can be walked back and forth
+        let mut undirected = graph_with_edges(3, &[(0, 1)]);
+        undirected.add_edge(1, 2, -1.0).unwrap();
+        assert_eq!(find_negative_cycle(&undirected).unwrap(), Some(vec![1, 2]));
+    }
+
+    #[test]
+    fn test_bellman_ford_without_negative_cycles() {
+        let positive = graph_with_edges(5, &[(0, 1), (1, 2), (2, 3), (0, 3)]);
+        assert_eq!(find_negative_cycle(&positive).unwrap(), None);
+        let distances = bellman_ford(&positive, 0).unwrap();
+        assert_eq!(distances.len(), 4);
+        assert_eq!(distances[&3], dijkstra(&positive, 0, 3).unwrap().1);
+
+        // Negative edges but no cycle: the detour beats the direct edge
+        let graph = directed_weighted(3, &[(0, 2, 1.0), (0, 1, 2.0), (1, 2, -2.0)]);
+        assert_eq!(find_negative_cycle(&graph).unwrap(), None);
+        assert_eq!(bellman_ford(&graph, 0).unwrap()[&2], 0.0);
+
+        assert_eq!(find_negative_cycle(&Graph::new()).unwrap(), None);
+        assert!(matches!(bellman_ford(&graph, 7), Err(GraphError::NodeNotFound(7))));
+    }
+    #[test]
+    fn test_partition() {
+        let methods = [
+            PartitionMethod::RegionGrowing,
+            PartitionMethod::LabelPropagation,
+            PartitionMethod::Multilevel,
+        ];
+        let mut components = crate::generators::erdos_renyi(300, 0.01, false, Some(4));
+        for i in 0..20 {
+            components.add_node_simple(format!("isolated{}", i));
+        }
+        let graphs = [
+            crate::generators::barabasi_albert(1000, 3, Some(1)),
+            crate::generators::planted_partition(400, 4, 0.2, 0.005, Some(2)).0,
+            components,
+        ];
+
+        for graph in &graphs {
+            for (k, imbalance) in [(2, 0.0), (5, 0.05), (8, 0.2)] {
+                let options = PartitionOptions::default().with_imbalance(imbalance).with_seed(3);
+                let mut cuts = Vec::new();
+                for method in methods {
+                    let parts = partition_with_options(graph, k, method, options).unwrap();
+                    assert_eq!(parts.len(), graph.node_count());
+                    assert!(graph.node_ids().iter().all(|id| parts[id] < k));
+
+                    let quality = partition_quality(graph, &parts).unwrap();
+                    assert_eq!(quality.part_sizes.len(), k);
+                    assert!(quality.part_sizes.iter().all(|&size| size > 0));
+                    let bound = graph.node_count().div_ceil(k) as f64;
+                    let cap = bound.max(((1.0 + imbalance) * graph.node_count() as f64
+                        / k as f64)
+                        .floor());
+                    assert!(
+                        quality.part_sizes.iter().all(|&size| size as f64 <= cap),
+                        "{:?} {:?}",
+                        method,
+                        quality
+                    );
+                    let rounding = k as f64 / graph.node_count() as f64;
+                    assert!(quality.balance <= 1.0 + imbalance + rounding);
+                    cuts.push(quality.edge_cut);
+                }
+                // Refinement only ever lowers the cut
+                assert!(cuts[1] <= cuts[0], "{:?}", cuts);
+            }
+        }
+
+        // Planted communities are recovered almost exactly
+        let planted = &graphs[1];
+        let parts = partition(planted, 4, PartitionMethod::Multilevel).unwrap();
+        let quality = partition_quality(planted, &parts).unwrap();
+        let total_cut = quality.edge_cut as f64 / planted.edge_count() as f64;
+        assert!(total_cut < 0.15, "{:?}", quality);
+        assert_eq!(partition(planted, 4, PartitionMethod::Multilevel).unwrap(), parts);
+
+        assert!(partition(planted, 0, PartitionMethod::RegionGrowing).is_err());
+        let negative = PartitionOptions::default().with_imbalance(-0.1);
+        assert!(partition_with_options(planted, 2, PartitionMethod::Multilevel, negative).is_err());
+        assert!(partition(&Graph::new(), 3, PartitionMethod::Multilevel).unwrap().is_empty());
+        let mut missing = parts.clone();
+        missing.remove(&0);
+        assert!(partition_quality(planted, &missing).is_err());
+    }
+    #[test]
+    fn test_greedy_coloring() {
+        let graph_with = |n: usize, edges: &[(NodeId, NodeId)]| {
+            let mut graph = Graph::new();
+            for i in 0..n {
+                graph.add_node_simple(format!("Node{}", i));
+            }
+            for &(a, b) in edges {
+                graph.add_edge(a, b, 1.0).unwrap();
+            }
+            graph
+        };
+        let check = |graph: &Graph, expected: usize| {
+            let coloring = greedy_coloring(graph).unwrap();
+            assert!(is_valid_coloring(graph, &coloring));
+            assert_eq!(chromatic_number_upper_bound(graph).unwrap(), expected);
+        };
+
+        for n in [1, 2, 5, 9] {
+            let edges: Vec<_> = (0..n).flat_map(|a| (a + 1..n).map(move |b| (a, b))).collect();
+            check(&graph_with(n, &edges), n);
+        }
+        let bipartite: Vec<_> = (0..4).flat_map(|a| (4..10).map(move |b| (a, b))).collect();
+        check(&graph_with(10, &bipartite), 2);
+        let even_cycle: Vec<_> = (0..8).map(|i| (i, (i + 1) % 8)).collect();
+        check(&graph_with(8, &even_cycle), 2);
+
+        // A tree on which degree order alone would need a third color
+        let tree = [(0, 1), (0, 2), (0, 3), (3, 4), (4, 5), (5, 6), (5, 7)];
+        check(&graph_with(8, &tree), 2);
+        check(&crate::generators::barabasi_albert(300, 1, Some(2)), 2);
+
+        // An odd cycle and a triangle with a pendant path, plus an edge
+        let mut mixed: Vec<_> = (0..5).map(|i| (i, (i + 1) % 5)).collect();
+        mixed.extend([(5, 6), (6, 7), (7, 5), (7, 8)]);
+        check(&graph_with(10, &mixed), 3);
+        check(&Graph::new(), 0);
+
+        let dense = crate::generators::erdos_renyi(200, 0.1, true, Some(3));
+        let coloring = greedy_coloring(&dense).unwrap();
+        assert!(is_valid_coloring(&dense, &coloring));
+        let max_degree = dense.node_ids().iter().map(|&id| dense.degree(id).unwrap()).max();
+        assert!(chromatic_number_upper_bound(&dense).unwrap() <= max_degree.unwrap() + 1);
+
+        let triangle = graph_with(3, &[(0, 1), (1, 2), (2, 0)]);
+        let mut bad: HashMap<NodeId, usize> = [(0, 0), (1, 1), (2, 0)].into_iter().collect();
+        assert!(!is_valid_coloring(&triangle, &bad));
+        bad.remove(&2);
+        assert!(!is_valid_coloring(&triangle, &bad));
+
+        let mut looped = graph_with(2, &[(0, 1)]);
+        looped.add_edge(1, 1, 1.0).unwrap();
+        assert!(matches!(greedy_coloring(&looped), Err(GraphError::InvalidStructure(_))));
+        assert!(!is_valid_coloring(&looped, &[(0, 0), (1, 1)].into_iter().collect()));
     }
 }
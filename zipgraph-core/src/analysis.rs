@@ -0,0 +1,239 @@
+//! Per-graph memo of whole-graph analysis results
+//!
+//! The selector, the feature extractor, the anomaly detector and the
+//! optimizer each want the statistics (and sometimes the PageRank) of the
+//! same unchanged graph while serving one request. Every `Graph` carries an
+//! `AnalysisCache` that keeps such results, tagged with the graph's
+//! `generation`; a result computed for an older generation is never
+//! returned. Clones and snapshots share their original's cache until one
+//! of them is mutated, at which point it starts a fresh one.
+
+use crate::algorithms;
+use crate::centrality;
+use crate::error::Result;
+use crate::graph::Graph;
+use crate::stats::GraphStats;
+use crate::types::NodeId;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// Parameters of `centrality::pagerank`, the key of cached PageRank runs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageRankParams {
+    pub damping_factor: f64,
+    pub max_iterations: usize,
+    pub tolerance: f64,
+}
+
+impl Default for PageRankParams {
+    /// The parameters the optimizer runs PageRank queries with
+    fn default() -> Self {
+        Self { damping_factor: 0.85, max_iterations: 100, tolerance: 1e-6 }
+    }
+}
+
+impl PageRankParams {
+    /// The `max_iterations` of `PageRankParams::default()` replaced
+    pub fn with_max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    fn key(&self) -> (u64, usize, u64) {
+        (self.damping_factor.to_bits(), self.max_iterations, self.tolerance.to_bits())
+    }
+}
+
+impl Eq for PageRankParams {}
+
+impl Hash for PageRankParams {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+/// Results computed for one generation of a graph
+#[derive(Debug, Default)]
+struct Entries {
+    generation: u64,
+    stats: Option<GraphStats>,
+    pagerank: HashMap<PageRankParams, Arc<HashMap<NodeId, f64>>>,
+    core_numbers: Option<Arc<HashMap<NodeId, usize>>>,
+    components: Option<Arc<Vec<Vec<NodeId>>>>,
+}
+
+/// Memo of analysis results for one graph, see the module documentation
+///
+/// Lookups take a read lock. On a miss the result is computed without
+/// holding the lock, so two threads missing at once may both compute it.
+#[derive(Debug, Default)]
+pub struct AnalysisCache {
+    entries: RwLock<Entries>,
+    computations: AtomicU64,
+}
+
+impl AnalysisCache {
+    /// How many results this cache has computed rather than returned from
+    /// memory
+    pub fn computations(&self) -> u64 {
+        self.computations.load(Ordering::Relaxed)
+    }
+
+    /// Drop every stored result
+    pub fn clear(&self) {
+        let mut entries = self.entries.write().expect("analysis cache lock poisoned");
+        *entries = Entries::default();
+    }
+
+    /// The stored result `get` picks out for `graph`'s generation, or the
+    /// one `compute` makes, stored with `insert`
+    fn get_or_compute<T: Clone>(
+        &self,
+        graph: &Graph,
+        get: impl Fn(&Entries) -> Option<T>,
+        compute: impl FnOnce() -> Result<T>,
+        insert: impl FnOnce(&mut Entries, T),
+    ) -> Result<T> {
+        let generation = graph.generation();
+        {
+            let entries = self.entries.read().expect("analysis cache lock poisoned");
+            if entries.generation == generation {
+                if let Some(value) = get(&entries) {
+                    return Ok(value);
+                }
+            }
+        }
+
+        let value = compute()?;
+        self.computations.fetch_add(1, Ordering::Relaxed);
+        let mut entries = self.entries.write().expect("analysis cache lock poisoned");
+        if entries.generation != generation {
+            *entries = Entries { generation, ..Entries::default() };
+        }
+        insert(&mut entries, value.clone());
+        Ok(value)
+    }
+
+    pub(crate) fn stats(&self, graph: &Graph) -> GraphStats {
+        self.get_or_compute(
+            graph,
+            |entries| entries.stats.clone(),
+            || Ok(GraphStats::from_graph(graph)),
+            |entries, stats| entries.stats = Some(stats),
+        )
+        .expect("GraphStats::from_graph does not fail")
+    }
+
+    pub(crate) fn pagerank(
+        &self,
+        graph: &Graph,
+        params: PageRankParams,
+    ) -> Result<Arc<HashMap<NodeId, f64>>> {
+        self.get_or_compute(
+            graph,
+            |entries| entries.pagerank.get(&params).cloned(),
+            || {
+                let ranks = centrality::pagerank(
+                    graph,
+                    params.damping_factor,
+                    params.max_iterations,
+                    params.tolerance,
+                )?;
+                Ok(Arc::new(ranks))
+            },
+            |entries, ranks| {
+                entries.pagerank.insert(params, ranks);
+            },
+        )
+    }
+
+    pub(crate) fn core_numbers(&self, graph: &Graph) -> Result<Arc<HashMap<NodeId, usize>>> {
+        self.get_or_compute(
+            graph,
+            |entries| entries.core_numbers.clone(),
+            || algorithms::core_numbers(graph).map(Arc::new),
+            |entries, cores| entries.core_numbers = Some(cores),
+        )
+    }
+
+    pub(crate) fn components(&self, graph: &Graph) -> Result<Arc<Vec<Vec<NodeId>>>> {
+        self.get_or_compute(
+            graph,
+            |entries| entries.components.clone(),
+            || algorithms::connected_components(graph).map(Arc::new),
+            |entries, components| entries.components = Some(components),
+        )
+    }
+}
+
+#[cfg(all(test, not(feature = "uuid-ids")))]
+mod tests {
+    use super::*;
+
+    fn path_graph(n: usize) -> Graph {
+        let mut graph = Graph::new();
+        for i in 0..n {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        for i in 1..n {
+            graph.add_edge(i - 1, i, 1.0).unwrap();
+        }
+        graph
+    }
+
+    #[test]
+    fn test_results_are_computed_once_per_generation() {
+        let mut graph = path_graph(5);
+        let stats = graph.cached_stats();
+        assert_eq!(stats, GraphStats::from_graph(&graph));
+        assert_eq!(graph.cached_stats(), stats);
+        assert_eq!(graph.analysis_cache().computations(), 1);
+
+        let params = PageRankParams::default();
+        let ranks = graph.cached_pagerank(params).unwrap();
+        assert!(Arc::ptr_eq(&ranks, &graph.cached_pagerank(params).unwrap()));
+        assert_eq!(graph.analysis_cache().computations(), 2);
+        graph.cached_pagerank(params.with_max_iterations(5)).unwrap();
+        assert_eq!(graph.analysis_cache().computations(), 3);
+
+        assert_eq!(graph.cached_core_numbers().unwrap()[&2], 1);
+        assert_eq!(graph.cached_components().unwrap().len(), 1);
+        graph.cached_core_numbers().unwrap();
+        graph.cached_components().unwrap();
+        assert_eq!(graph.analysis_cache().computations(), 5);
+
+        // Mutation starts over
+        let before = graph.clone();
+        graph.add_node_simple("Alone");
+        assert_eq!(graph.cached_components().unwrap().len(), 2);
+        assert_eq!(graph.cached_stats().node_count, 6);
+        assert_eq!(graph.analysis_cache().computations(), 2);
+        assert_eq!(before.analysis_cache().computations(), 5);
+    }
+
+    #[test]
+    fn test_clones_share_until_mutated() {
+        let graph = path_graph(4);
+        graph.cached_stats();
+        let snapshot = graph.snapshot();
+        snapshot.cached_stats();
+        assert_eq!(graph.analysis_cache().computations(), 1);
+
+        let mut clone = graph.clone();
+        clone.add_edge(0, 3, 1.0).unwrap();
+        assert_eq!(clone.cached_stats().edge_count, 4);
+        assert_eq!(graph.cached_stats().edge_count, 3);
+        assert_eq!(graph.analysis_cache().computations(), 1);
+
+        // Stale results are not returned even from a shared cache
+        let cache = AnalysisCache::default();
+        cache.stats(&graph);
+        assert_eq!(cache.stats(&clone).edge_count, 4);
+        assert_eq!(cache.computations(), 2);
+        cache.clear();
+        cache.stats(&clone);
+        assert_eq!(cache.computations(), 3);
+    }
+}
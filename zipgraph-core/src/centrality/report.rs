@@ -0,0 +1,283 @@
+//! Ranked, printable tables of centrality scores
+//!
+//! ```
+//! use zipgraph_core::centrality::{degree_centrality, pagerank, CentralityReport};
+//! use zipgraph_core::Graph;
+//!
+//! let mut graph = Graph::new();
+//! let [hub, a, b] = ["hub", "a", "b"].map(|name| graph.add_node_simple(name));
+//! graph.add_edge(hub, a, 1.0).unwrap();
+//! graph.add_edge(hub, b, 1.0).unwrap();
+//!
+//! let scores = pagerank(&graph, 0.85, 100, 1e-6).unwrap();
+//! let report = CentralityReport::from_scores(&graph, &scores, "pagerank")
+//!     .join(CentralityReport::from_scores(&graph, &degree_centrality(&graph).unwrap(), "degree"))
+//!     .unwrap();
+//! assert_eq!(report.top(1)[0].label, "hub");
+//! ```
+
+use crate::error::{GraphError, Result};
+use crate::graph::Graph;
+use crate::types::NodeId;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// One node's row in a `CentralityReport`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CentralityRow {
+    /// 1-based rank by the report's primary metric; tied scores share the
+    /// best rank of the tie ("1224" ranking)
+    pub rank: usize,
+    pub node: NodeId,
+    pub label: String,
+    /// One score per metric, in the order of `CentralityReport::metrics`
+    pub scores: Vec<f64>,
+}
+
+/// Centrality scores ranked from highest to lowest, with node labels
+///
+/// Rows are ordered by the first (primary) metric, ties by ascending node
+/// ID. Further metrics can be added as columns with `join`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CentralityReport {
+    metrics: Vec<String>,
+    rows: Vec<CentralityRow>,
+}
+
+impl CentralityReport {
+    /// Rank `scores`, as returned by the functions in `centrality`, under
+    /// the column name `metric`
+    ///
+    /// Labels are taken from `graph`; nodes not in it get an empty label.
+    /// NaN scores rank last.
+    pub fn from_scores(graph: &Graph, scores: &HashMap<NodeId, f64>, metric: &str) -> Self {
+        let mut ranked: Vec<(NodeId, f64)> =
+            scores.iter().map(|(&node, &score)| (node, score)).collect();
+        ranked.sort_by(|a, b| descending(a.1, b.1).then(a.0.cmp(&b.0)));
+
+        let mut rows: Vec<CentralityRow> = Vec::with_capacity(ranked.len());
+        for (position, (node, score)) in ranked.into_iter().enumerate() {
+            let rank = match rows.last() {
+                Some(previous) if same_score(previous.scores[0], score) => previous.rank,
+                _ => position + 1,
+            };
+            let label = graph.node(node).map(|node| node.label.clone()).unwrap_or_default();
+            rows.push(CentralityRow { rank, node, label, scores: vec![score] });
+        }
+        Self { metrics: vec![metric.to_string()], rows }
+    }
+
+    /// Names of the score columns, the primary metric first
+    pub fn metrics(&self) -> &[String] {
+        &self.metrics
+    }
+
+    /// All rows, best first
+    pub fn rows(&self) -> &[CentralityRow] {
+        &self.rows
+    }
+
+    /// The `k` best rows, or all of them if there are fewer
+    pub fn top(&self, k: usize) -> &[CentralityRow] {
+        &self.rows[..k.min(self.rows.len())]
+    }
+
+    /// Row of `node`, if it was scored
+    pub fn row(&self, node: NodeId) -> Option<&CentralityRow> {
+        self.rows.iter().find(|row| row.node == node)
+    }
+
+    /// Percentage of nodes whose primary score is at most that of `node`
+    ///
+    /// The best node is at 100; tied nodes share a percentile. `None` if
+    /// `node` was not scored.
+    pub fn percentile_of(&self, node: NodeId) -> Option<f64> {
+        let score = self.row(node)?.scores[0];
+        let at_most = self
+            .rows
+            .iter()
+            .filter(|row| descending(row.scores[0], score).is_ge())
+            .count();
+        Some(100.0 * at_most as f64 / self.rows.len() as f64)
+    }
+
+    /// Add the metrics of `other` as further columns
+    ///
+    /// The ranking stays that of this report. Fails with `InvalidData` if
+    /// the reports score different nodes or share a metric name.
+    pub fn join(mut self, other: CentralityReport) -> Result<Self> {
+        if let Some(metric) = other.metrics.iter().find(|metric| self.metrics.contains(metric)) {
+            return Err(GraphError::InvalidData(format!(
+                "Metric '{}' is in both reports",
+                metric
+            )));
+        }
+        let mut other_scores: HashMap<NodeId, Vec<f64>> =
+            other.rows.into_iter().map(|row| (row.node, row.scores)).collect();
+        if other_scores.len() != self.rows.len() {
+            return Err(GraphError::InvalidData(format!(
+                "Cannot join reports over {} and {} nodes",
+                self.rows.len(),
+                other_scores.len()
+            )));
+        }
+        for row in &mut self.rows {
+            let scores = other_scores.remove(&row.node).ok_or_else(|| {
+                let message = format!("Node {} is missing from the other report", row.node);
+                GraphError::InvalidData(message)
+            })?;
+            row.scores.extend(scores);
+        }
+        self.metrics.extend(other.metrics);
+        Ok(self)
+    }
+
+    /// Write the report as CSV with a `rank,node,label,<metrics...>` header
+    ///
+    /// Fields containing commas, quotes or line breaks are quoted, with
+    /// quotes doubled (RFC 4180).
+    pub fn to_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut header = vec!["rank".to_string(), "node".to_string(), "label".to_string()];
+        header.extend(self.metrics.iter().cloned());
+        write_csv_record(&mut writer, &header)?;
+        for row in &self.rows {
+            let mut record = vec![row.rank.to_string(), row.node.to_string(), row.label.clone()];
+            record.extend(row.scores.iter().map(f64::to_string));
+            write_csv_record(&mut writer, &record)?;
+        }
+        Ok(())
+    }
+
+    /// The rows as a JSON array of objects with `rank`, `node`, `label` and
+    /// one field per metric
+    ///
+    /// Non-finite scores become `null`.
+    pub fn to_json(&self) -> Value {
+        let rows = self
+            .rows
+            .iter()
+            .map(|row| {
+                let mut object = Map::new();
+                object.insert("rank".to_string(), json!(row.rank));
+                object.insert("node".to_string(), json!(row.node));
+                object.insert("label".to_string(), json!(row.label));
+                for (metric, score) in self.metrics.iter().zip(&row.scores) {
+                    object.insert(metric.clone(), json!(score));
+                }
+                Value::Object(object)
+            })
+            .collect();
+        Value::Array(rows)
+    }
+}
+
+/// Order for ranking: higher scores first, NaN last
+fn descending(a: f64, b: f64) -> std::cmp::Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (false, false) => b.total_cmp(&a),
+        (a_nan, b_nan) => a_nan.cmp(&b_nan),
+    }
+}
+
+fn same_score(a: f64, b: f64) -> bool {
+    a == b || (a.is_nan() && b.is_nan())
+}
+
+fn write_csv_record<W: Write>(writer: &mut W, fields: &[String]) -> io::Result<()> {
+    let escaped: Vec<String> = fields.iter().map(|field| escape_csv(field)).collect();
+    writeln!(writer, "{}", escaped.join(","))
+}
+
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(all(test, not(feature = "uuid-ids")))]
+mod tests {
+    use super::*;
+
+    fn labeled_graph(labels: &[&str]) -> Graph {
+        let mut graph = Graph::new();
+        for label in labels {
+            graph.add_node_simple(*label);
+        }
+        graph
+    }
+
+    #[test]
+    fn test_ranking_and_ties() {
+        let graph = labeled_graph(&["a", "b", "c", "d"]);
+        let scores = HashMap::from([(0, 0.2), (1, 0.5), (2, 0.5), (3, 0.1)]);
+        let report = CentralityReport::from_scores(&graph, &scores, "score");
+
+        let ranked: Vec<(usize, NodeId)> =
+            report.rows().iter().map(|row| (row.rank, row.node)).collect();
+        assert_eq!(ranked, vec![(1, 1), (1, 2), (3, 0), (4, 3)]);
+        assert_eq!(report.top(2)[1].label, "c");
+        assert_eq!(report.top(10).len(), 4);
+        assert_eq!(report.percentile_of(1), Some(100.0));
+        assert_eq!(report.percentile_of(2), Some(100.0));
+        assert_eq!(report.percentile_of(0), Some(50.0));
+        assert_eq!(report.percentile_of(3), Some(25.0));
+        assert_eq!(report.percentile_of(9), None);
+    }
+
+    #[test]
+    fn test_nan_ranks_last() {
+        let graph = labeled_graph(&["a", "b", "c"]);
+        let scores = HashMap::from([(0, f64::NAN), (1, 0.0), (2, f64::NAN)]);
+        let report = CentralityReport::from_scores(&graph, &scores, "score");
+        let ranked: Vec<(usize, NodeId)> =
+            report.rows().iter().map(|row| (row.rank, row.node)).collect();
+        assert_eq!(ranked, vec![(1, 1), (2, 0), (2, 2)]);
+        assert_eq!(report.to_json()[1]["score"], Value::Null);
+    }
+
+    #[test]
+    fn test_join() {
+        let graph = labeled_graph(&["a", "b"]);
+        let first = HashMap::from([(0, 1.0), (1, 2.0)]);
+        let second = HashMap::from([(0, 10.0), (1, 5.0)]);
+        let report = CentralityReport::from_scores(&graph, &first, "first")
+            .join(CentralityReport::from_scores(&graph, &second, "second"))
+            .unwrap();
+        assert_eq!(report.metrics(), ["first", "second"]);
+        assert_eq!(report.rows()[0].node, 1);
+        assert_eq!(report.rows()[0].scores, vec![2.0, 5.0]);
+
+        let json = report.to_json();
+        assert_eq!(
+            json[0],
+            json!({ "rank": 1, "node": 1, "label": "b", "first": 2.0, "second": 5.0 })
+        );
+
+        let duplicate = report.clone().join(CentralityReport::from_scores(&graph, &first, "first"));
+        assert!(matches!(duplicate, Err(GraphError::InvalidData(_))));
+        let other_nodes = HashMap::from([(0, 1.0), (5, 1.0)]);
+        let mismatch = report.join(CentralityReport::from_scores(&graph, &other_nodes, "third"));
+        assert!(matches!(mismatch, Err(GraphError::InvalidData(_))));
+    }
+
+    #[test]
+    fn test_csv_escaping() {
+        let graph = labeled_graph(&["plain", "Smith, J.", "say \"hi\"", "two\nlines"]);
+        let scores = HashMap::from([(0, 4.0), (1, 3.0), (2, 2.0), (3, 1.0)]);
+        let report = CentralityReport::from_scores(&graph, &scores, "page,rank");
+
+        let mut csv = Vec::new();
+        report.to_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "rank,node,label,\"page,rank\"\n\
+             1,0,plain,4\n\
+             2,1,\"Smith, J.\",3\n\
+             3,2,\"say \"\"hi\"\"\",2\n\
+             4,3,\"two\nlines\",1\n"
+        );
+    }
+}
@@ -0,0 +1,136 @@
+//! Checkpoints for resuming long algorithm runs
+//!
+//! An algorithm that can take hours on a large graph keeps its progress in
+//! a state type implementing `Checkpointable`, which it saves periodically.
+//! After a crash the run restarts from the last saved state instead of from
+//! scratch; see `algorithms::floyd_warshall_resumable`.
+
+use crate::error::{GraphError, Result};
+use crate::graph::Graph;
+use crate::types::Weight;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Intermediate state of a long-running algorithm that can be saved to
+/// disk and resumed
+///
+/// Checkpoints are stored with bincode. Saving writes a temporary file
+/// next to `path` and renames it into place, so a crash while saving
+/// leaves the previous checkpoint intact.
+pub trait Checkpointable: Serialize + DeserializeOwned {
+    /// Write the state to `path`, replacing any previous checkpoint
+    fn save_checkpoint(&self, path: &Path) -> Result<()> {
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+
+        let file = File::create(&temporary)
+            .map_err(|e| GraphError::InvalidData(format!("Failed to create checkpoint: {}", e)))?;
+        let mut writer = BufWriter::new(file);
+        bincode::serialize_into(&mut writer, self)
+            .map_err(|e| GraphError::SerializationError(e.to_string()))?;
+        writer
+            .flush()
+            .and_then(|_| fs::rename(&temporary, path))
+            .map_err(|e| GraphError::InvalidData(format!("Failed to write checkpoint: {}", e)))
+    }
+
+    /// Read the state saved at `path`, or `None` if there is no checkpoint
+    fn load_checkpoint(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let file = File::open(path)
+            .map_err(|e| GraphError::InvalidData(format!("Failed to open checkpoint: {}", e)))?;
+        bincode::deserialize_from(BufReader::new(file))
+            .map(Some)
+            .map_err(|e| GraphError::SerializationError(e.to_string()))
+    }
+}
+
+/// Progress of Floyd-Warshall: the distance matrix after relaxing through
+/// the first `k` nodes (in ascending ID order)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FloydWarshallState {
+    pub dist: Vec<Vec<Weight>>,
+    pub k: usize,
+    /// `Graph::content_hash` of the graph the state was computed on
+    pub graph_hash: u64,
+}
+
+impl Checkpointable for FloydWarshallState {}
+
+impl FloydWarshallState {
+    /// State before the first iteration: the direct-edge distances
+    pub fn new(graph: &Graph) -> Result<Self> {
+        Ok(Self {
+            dist: crate::algorithms::initial_distances(graph)?,
+            k: 0,
+            graph_hash: graph.content_hash(),
+        })
+    }
+
+    /// Check that the state can be resumed on `graph`
+    ///
+    /// Fails with `InvalidData` if it was computed on a graph with a
+    /// different `content_hash`, or if `dist` is not a square matrix over
+    /// the nodes of `graph` or `k` is past its end.
+    pub fn validate(&self, graph: &Graph) -> Result<()> {
+        let n = graph.node_count();
+        if self.dist.len() != n {
+            return Err(GraphError::InvalidData(format!(
+                "Checkpoint has {} nodes but the graph has {}",
+                self.dist.len(),
+                n
+            )));
+        }
+        if let Some(row) = self.dist.iter().position(|row| row.len() != n) {
+            return Err(GraphError::InvalidData(format!(
+                "Checkpoint row {} has {} entries instead of {}",
+                row,
+                self.dist[row].len(),
+                n
+            )));
+        }
+        if self.k > n {
+            return Err(GraphError::InvalidData(format!(
+                "Checkpoint is at iteration {} of {}",
+                self.k, n
+            )));
+        }
+        if self.graph_hash != graph.content_hash() {
+            return Err(GraphError::InvalidData(
+                "Checkpoint was taken on a different graph".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether every node has been relaxed through
+    pub fn is_complete(&self) -> bool {
+        self.k >= self.dist.len()
+    }
+
+    /// Run the next iteration, relaxing every pair through node `k`
+    pub fn step(&mut self) {
+        if self.is_complete() {
+            return;
+        }
+        let row_k = self.dist[self.k].clone();
+        for row in self.dist.iter_mut() {
+            let through_k = row[self.k];
+            if through_k == Weight::INFINITY {
+                continue;
+            }
+            for (d, &via) in row.iter_mut().zip(&row_k) {
+                let candidate = through_k + via;
+                if candidate < *d {
+                    *d = candidate;
+                }
+            }
+        }
+        self.k += 1;
+    }
+}
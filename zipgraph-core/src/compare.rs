@@ -0,0 +1,437 @@
+//! Structural comparison of graphs
+//!
+//! `Graph::equivalent` and `Graph::assert_equivalent` compare node sets,
+//! edge multisets and directedness, and optionally labels, properties,
+//! weights, edge types and timestamps. Unlike comparing node and edge
+//! counts, they catch a storage format that silently drops an attribute,
+//! and report where.
+
+use crate::graph::{Edge, Graph, Node};
+use crate::types::{NodeId, Weight};
+use std::cmp::Ordering::Equal;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// Most differences `Graph::assert_equivalent` reports
+pub const MAX_DIFFERENCES: usize = 10;
+
+/// What `Graph::equivalent` compares besides node IDs, edge endpoints and
+/// directedness
+///
+/// The default compares everything, numbers to within `1e-9`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EqOptions {
+    pub labels: bool,
+    /// Node properties and feature vectors
+    pub properties: bool,
+    pub weights: bool,
+    pub edge_types: bool,
+    pub timestamps: bool,
+    /// Largest absolute difference at which numbers still match
+    pub epsilon: f64,
+}
+
+impl Default for EqOptions {
+    fn default() -> Self {
+        Self {
+            labels: true,
+            properties: true,
+            weights: true,
+            edge_types: true,
+            timestamps: true,
+            epsilon: 1e-9,
+        }
+    }
+}
+
+impl EqOptions {
+    pub fn ignore_labels(mut self) -> Self {
+        self.labels = false;
+        self
+    }
+
+    pub fn ignore_properties(mut self) -> Self {
+        self.properties = false;
+        self
+    }
+
+    pub fn ignore_weights(mut self) -> Self {
+        self.weights = false;
+        self
+    }
+
+    pub fn ignore_edge_types(mut self) -> Self {
+        self.edge_types = false;
+        self
+    }
+
+    pub fn ignore_timestamps(mut self) -> Self {
+        self.timestamps = false;
+        self
+    }
+
+    pub fn with_epsilon(mut self, epsilon: f64) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+
+    fn close(&self, left: f64, right: f64) -> bool {
+        left == right || (left - right).abs() <= self.epsilon || (left.is_nan() && right.is_nan())
+    }
+
+    fn close_options(&self, left: Option<f64>, right: Option<f64>) -> bool {
+        match (left, right) {
+            (Some(left), Some(right)) => self.close(left, right),
+            (left, right) => left.is_none() && right.is_none(),
+        }
+    }
+}
+
+/// One mismatch between a left graph (`self`) and a right graph (`other`)
+///
+/// Edges are named by their endpoints, smaller ID first when either graph
+/// is undirected.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Difference {
+    Directedness { left: bool, right: bool },
+    /// A node of the left graph missing from the right one
+    MissingNode(NodeId),
+    /// A node of the right graph missing from the left one
+    ExtraNode(NodeId),
+    Label { node: NodeId, left: String, right: String },
+    Property { node: NodeId, key: String, left: Option<f64>, right: Option<f64> },
+    Features { node: NodeId, left: Option<Vec<f64>>, right: Option<Vec<f64>> },
+    /// A different number of edges between the same endpoints
+    EdgeCount { from: NodeId, to: NodeId, left: usize, right: usize },
+    Weight { from: NodeId, to: NodeId, left: Weight, right: Weight },
+    EdgeType { from: NodeId, to: NodeId, left: String, right: String },
+    Timestamp { from: NodeId, to: NodeId, left: Option<f64>, right: Option<f64> },
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difference::Directedness { left, right } => {
+                write!(f, "directed: {} vs {}", left, right)
+            }
+            Difference::MissingNode(node) => write!(f, "node {} missing on the right", node),
+            Difference::ExtraNode(node) => write!(f, "node {} missing on the left", node),
+            Difference::Label { node, left, right } => {
+                write!(f, "node {} label: {:?} vs {:?}", node, left, right)
+            }
+            Difference::Property { node, key, left, right } => {
+                write!(f, "node {} property {:?}: {:?} vs {:?}", node, key, left, right)
+            }
+            Difference::Features { node, left, right } => {
+                write!(f, "node {} features: {:?} vs {:?}", node, left, right)
+            }
+            Difference::EdgeCount { from, to, left, right } => {
+                write!(f, "edges {}-{}: {} vs {}", from, to, left, right)
+            }
+            Difference::Weight { from, to, left, right } => {
+                write!(f, "edge {}-{} weight: {} vs {}", from, to, left, right)
+            }
+            Difference::EdgeType { from, to, left, right } => {
+                write!(f, "edge {}-{} type: {:?} vs {:?}", from, to, left, right)
+            }
+            Difference::Timestamp { from, to, left, right } => {
+                write!(f, "edge {}-{} timestamp: {:?} vs {:?}", from, to, left, right)
+            }
+        }
+    }
+}
+
+impl Graph {
+    /// Whether `other` matches this graph under `options`
+    pub fn equivalent(&self, other: &Graph, options: EqOptions) -> bool {
+        let mut differences = Differences { found: Vec::new(), limit: 1 };
+        compare(self, other, &options, &mut differences);
+        differences.found.is_empty()
+    }
+
+    /// `equivalent`, reporting the first `MAX_DIFFERENCES` mismatches
+    ///
+    /// Nodes are compared in ascending ID order, then edges in ascending
+    /// endpoint order. Parallel edges between the same endpoints are
+    /// paired up after sorting by the compared attributes, so their
+    /// insertion order does not matter.
+    pub fn assert_equivalent(
+        &self,
+        other: &Graph,
+        options: EqOptions,
+    ) -> std::result::Result<(), Vec<Difference>> {
+        let mut differences = Differences { found: Vec::new(), limit: MAX_DIFFERENCES };
+        compare(self, other, &options, &mut differences);
+        if differences.found.is_empty() {
+            Ok(())
+        } else {
+            Err(differences.found)
+        }
+    }
+}
+
+struct Differences {
+    found: Vec<Difference>,
+    limit: usize,
+}
+
+impl Differences {
+    fn push(&mut self, difference: Difference) {
+        if !self.is_full() {
+            self.found.push(difference);
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.found.len() >= self.limit
+    }
+}
+
+fn compare(left: &Graph, right: &Graph, options: &EqOptions, differences: &mut Differences) {
+    if left.is_directed() != right.is_directed() {
+        differences.push(Difference::Directedness {
+            left: left.is_directed(),
+            right: right.is_directed(),
+        });
+    }
+
+    let ids: BTreeSet<NodeId> = left.node_ids().into_iter().chain(right.node_ids()).collect();
+    for id in ids {
+        if differences.is_full() {
+            return;
+        }
+        match (left.node(id), right.node(id)) {
+            (Ok(l), Ok(r)) => compare_nodes(l, r, options, differences),
+            (Ok(_), Err(_)) => differences.push(Difference::MissingNode(id)),
+            _ => differences.push(Difference::ExtraNode(id)),
+        }
+    }
+
+    let undirected = !left.is_directed() || !right.is_directed();
+    let (left_edges, right_edges) =
+        (edges_by_endpoints(left, undirected), edges_by_endpoints(right, undirected));
+    let endpoints: BTreeSet<&(NodeId, NodeId)> =
+        left_edges.keys().chain(right_edges.keys()).collect();
+    for &(from, to) in endpoints {
+        if differences.is_full() {
+            return;
+        }
+        let none = Vec::new();
+        let l = left_edges.get(&(from, to)).unwrap_or(&none);
+        let r = right_edges.get(&(from, to)).unwrap_or(&none);
+        if l.len() != r.len() {
+            differences.push(Difference::EdgeCount { from, to, left: l.len(), right: r.len() });
+            continue;
+        }
+        let (l, r) = (sorted_edges(l, options), sorted_edges(r, options));
+        for (l, r) in l.into_iter().zip(r) {
+            compare_edges(from, to, l, r, options, differences);
+        }
+    }
+}
+
+fn compare_nodes(left: &Node, right: &Node, options: &EqOptions, differences: &mut Differences) {
+    let node = left.id;
+    if options.labels && left.label != right.label {
+        differences.push(Difference::Label {
+            node,
+            left: left.label.clone(),
+            right: right.label.clone(),
+        });
+    }
+    if !options.properties {
+        return;
+    }
+
+    let keys: BTreeSet<&String> = left.properties.keys().chain(right.properties.keys()).collect();
+    for key in keys {
+        let (l, r) = (left.properties.get(key).copied(), right.properties.get(key).copied());
+        if !options.close_options(l, r) {
+            differences.push(Difference::Property { node, key: key.clone(), left: l, right: r });
+        }
+    }
+    let features_match = match (&left.features, &right.features) {
+        (Some(l), Some(r)) => {
+            l.len() == r.len() && l.iter().zip(r).all(|(&a, &b)| options.close(a, b))
+        }
+        (l, r) => l.is_none() && r.is_none(),
+    };
+    if !features_match {
+        differences.push(Difference::Features {
+            node,
+            left: left.features.clone(),
+            right: right.features.clone(),
+        });
+    }
+}
+
+fn compare_edges(
+    from: NodeId,
+    to: NodeId,
+    left: &Edge,
+    right: &Edge,
+    options: &EqOptions,
+    differences: &mut Differences,
+) {
+    if options.weights && !options.close(left.weight, right.weight) {
+        differences.push(Difference::Weight { from, to, left: left.weight, right: right.weight });
+    }
+    if options.edge_types && left.edge_type != right.edge_type {
+        differences.push(Difference::EdgeType {
+            from,
+            to,
+            left: left.edge_type.clone(),
+            right: right.edge_type.clone(),
+        });
+    }
+    if options.timestamps && !options.close_options(left.timestamp, right.timestamp) {
+        differences.push(Difference::Timestamp {
+            from,
+            to,
+            left: left.timestamp,
+            right: right.timestamp,
+        });
+    }
+}
+
+/// The edges of `graph` grouped by endpoints, ordered if `undirected`
+fn edges_by_endpoints(graph: &Graph, undirected: bool) -> BTreeMap<(NodeId, NodeId), Vec<&Edge>> {
+    let mut groups: BTreeMap<(NodeId, NodeId), Vec<&Edge>> = BTreeMap::new();
+    for edge in graph.edges() {
+        let key = if undirected {
+            (edge.from.min(edge.to), edge.from.max(edge.to))
+        } else {
+            (edge.from, edge.to)
+        };
+        groups.entry(key).or_default().push(edge);
+    }
+    groups
+}
+
+/// Parallel edges sorted by the attributes `options` compares, so that
+/// matching edges pair up
+fn sorted_edges<'a>(edges: &[&'a Edge], options: &EqOptions) -> Vec<&'a Edge> {
+    let mut edges = edges.to_vec();
+    edges.sort_by(|a, b| {
+        let weights = if options.weights { a.weight.total_cmp(&b.weight) } else { Equal };
+        let types = if options.edge_types { a.edge_type.cmp(&b.edge_type) } else { Equal };
+        let timestamps = if options.timestamps {
+            let value = |edge: &Edge| edge.timestamp.unwrap_or(0.0);
+            (a.timestamp.is_some().cmp(&b.timestamp.is_some()))
+                .then_with(|| value(a).total_cmp(&value(b)))
+        } else {
+            Equal
+        };
+        types.then(weights).then(timestamps)
+    });
+    edges
+}
+
+#[cfg(all(test, not(feature = "uuid-ids")))]
+mod tests {
+    use super::*;
+
+    fn triangle(directed: bool) -> Graph {
+        let mut graph = if directed { Graph::new_directed() } else { Graph::new() };
+        for label in ["A", "B", "C"] {
+            graph.add_node_simple(label);
+        }
+        graph.add_edge(0, 1, 1.0).unwrap();
+        graph.add_typed_edge(1, 2, 2.0, "knows").unwrap();
+        graph.add_timestamped_edge(2, 0, 3.0, 10.0).unwrap();
+        graph
+    }
+
+    #[test]
+    fn test_equivalent_ignores_order_and_orientation() {
+        let graph = triangle(false);
+        assert!(graph.equivalent(&graph.clone(), EqOptions::default()));
+
+        // Same edges inserted in another order and orientation
+        let mut reordered = Graph::new();
+        for id in [2, 0, 1] {
+            reordered.add_node(Node::new(id, ["A", "B", "C"][id]));
+        }
+        reordered.add_timestamped_edge(0, 2, 3.0 + 1e-12, 10.0).unwrap();
+        reordered.add_typed_edge(2, 1, 2.0, "knows").unwrap();
+        reordered.add_edge(1, 0, 1.0).unwrap();
+        assert_eq!(graph.assert_equivalent(&reordered, EqOptions::default()), Ok(()));
+
+        // Directed edges keep their orientation
+        let mut flipped = Graph::new_directed();
+        flipped.add_node_simple("A");
+        flipped.add_node_simple("B");
+        flipped.add_edge(1, 0, 1.0).unwrap();
+        let mut forward = flipped.clone();
+        forward.add_edge(0, 1, 1.0).unwrap();
+        flipped.add_edge(1, 0, 1.0).unwrap();
+        assert_eq!(
+            forward.assert_equivalent(&flipped, EqOptions::default()),
+            Err(vec![
+                Difference::EdgeCount { from: 0, to: 1, left: 1, right: 0 },
+                Difference::EdgeCount { from: 1, to: 0, left: 1, right: 2 },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_assert_equivalent_pinpoints_differences() {
+        let graph = triangle(false);
+        let mut other = triangle(false);
+        other.update_node_label(1, "Bee").unwrap();
+        other.update_node_property(2, "age", 3.0).unwrap();
+        other.add_node_simple("D");
+        let mut changed = Graph::new();
+        for id in other.node_ids() {
+            changed.add_node(other.node(id).unwrap().clone());
+        }
+        changed.add_edge(0, 1, 1.5).unwrap();
+        changed.add_edge(1, 2, 2.0).unwrap();
+        changed.add_edge(2, 0, 3.0).unwrap();
+
+        let differences = graph.assert_equivalent(&changed, EqOptions::default()).unwrap_err();
+        assert_eq!(
+            differences,
+            vec![
+                Difference::Label { node: 1, left: "B".into(), right: "Bee".into() },
+                Difference::Property { node: 2, key: "age".into(), left: None, right: Some(3.0) },
+                Difference::ExtraNode(3),
+                Difference::Weight { from: 0, to: 1, left: 1.0, right: 1.5 },
+                Difference::Timestamp { from: 0, to: 2, left: Some(10.0), right: None },
+                Difference::EdgeType {
+                    from: 1,
+                    to: 2,
+                    left: "knows".into(),
+                    right: "default".into(),
+                },
+            ]
+        );
+        assert_eq!(differences[0].to_string(), r#"node 1 label: "B" vs "Bee""#);
+
+        let lenient = EqOptions::default()
+            .ignore_labels()
+            .ignore_properties()
+            .ignore_weights()
+            .ignore_edge_types()
+            .ignore_timestamps();
+        assert_eq!(
+            graph.assert_equivalent(&changed, lenient),
+            Err(vec![Difference::ExtraNode(3)])
+        );
+        assert!(!graph.equivalent(&triangle(true), lenient));
+        assert!(graph.equivalent(&triangle(false), EqOptions::default().with_epsilon(0.0)));
+    }
+
+    #[test]
+    fn test_assert_equivalent_reports_a_bounded_number() {
+        let mut a = Graph::new();
+        let mut b = Graph::new();
+        for i in 0..50 {
+            a.add_node_simple(format!("A{}", i));
+            b.add_node_simple(format!("B{}", i));
+        }
+        let differences = a.assert_equivalent(&b, EqOptions::default()).unwrap_err();
+        assert_eq!(differences.len(), MAX_DIFFERENCES);
+        assert_eq!(differences[0].to_string(), r#"node 0 label: "A0" vs "B0""#);
+    }
+}
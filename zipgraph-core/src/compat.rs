@@ -0,0 +1,152 @@
+//! Checking that a graph suits an algorithm
+//!
+//! Some algorithms are only defined for undirected (or only for directed)
+//! graphs, and run on the other kind they quietly return numbers that
+//! mean something else. Such algorithms declare their `Requirements` and
+//! call `check_graph_compat` first, which fails with a
+//! `GraphError::InvalidStructure` naming the algorithm and what to use
+//! instead. Algorithms that need undirected input also offer a variant
+//! taking `CompatOptions`, whose `allow_directed_as_undirected` escape
+//! hatch runs them on a symmetrized copy of a directed graph.
+
+use crate::error::{GraphError, Result};
+use crate::graph::Graph;
+use std::borrow::Cow;
+
+/// Edge direction an algorithm can handle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Directedness {
+    Any,
+    Undirected,
+    Directed,
+}
+
+/// What an algorithm needs from its input graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Requirements {
+    /// Name used in error messages, e.g. `"closeness_centrality"`
+    pub algorithm: &'static str,
+    pub directedness: Directedness,
+    /// What to do instead, appended to error messages
+    pub alternative: &'static str,
+}
+
+impl Requirements {
+    /// Requirements of an algorithm that only works on undirected graphs
+    pub const fn undirected(algorithm: &'static str, alternative: &'static str) -> Self {
+        Self {
+            algorithm,
+            directedness: Directedness::Undirected,
+            alternative,
+        }
+    }
+
+    /// Requirements of an algorithm that only works on directed graphs
+    pub const fn directed(algorithm: &'static str, alternative: &'static str) -> Self {
+        Self {
+            algorithm,
+            directedness: Directedness::Directed,
+            alternative,
+        }
+    }
+}
+
+/// How strictly `compatible_graph` applies `Requirements`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompatOptions {
+    /// Run undirected-only algorithms on directed graphs by treating
+    /// every edge as undirected (see `Graph::to_undirected`)
+    pub allow_directed_as_undirected: bool,
+}
+
+impl CompatOptions {
+    /// Symmetrize directed graphs instead of rejecting them
+    pub fn allow_directed_as_undirected(mut self) -> Self {
+        self.allow_directed_as_undirected = true;
+        self
+    }
+}
+
+/// Check that `graph` meets `requirements`
+pub fn check_graph_compat(graph: &Graph, requirements: &Requirements) -> Result<()> {
+    let (actual, expected) = match (requirements.directedness, graph.is_directed()) {
+        (Directedness::Undirected, true) => ("directed", "undirected"),
+        (Directedness::Directed, false) => ("undirected", "directed"),
+        _ => return Ok(()),
+    };
+
+    Err(GraphError::InvalidStructure(format!(
+        "{} ({} variant) called on a {} graph; {}",
+        requirements.algorithm, expected, actual, requirements.alternative
+    )))
+}
+
+/// The graph an algorithm with `requirements` should run on
+///
+/// That is `graph` itself when it meets the requirements, or its
+/// symmetrized copy when it is directed, the algorithm needs undirected
+/// input and `options` allow it.
+pub fn compatible_graph<'g>(
+    graph: &'g Graph,
+    requirements: &Requirements,
+    options: CompatOptions,
+) -> Result<Cow<'g, Graph>> {
+    match check_graph_compat(graph, requirements) {
+        Ok(()) => Ok(Cow::Borrowed(graph)),
+        Err(_)
+            if options.allow_directed_as_undirected
+                && requirements.directedness == Directedness::Undirected =>
+        {
+            Ok(Cow::Owned(graph.to_undirected()))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(all(test, not(feature = "uuid-ids")))]
+mod tests {
+    use super::*;
+
+    const UNDIRECTED_ONLY: Requirements = Requirements::undirected("example", "use something else");
+
+    #[test]
+    fn test_check_graph_compat() {
+        let undirected = Graph::new();
+        let directed = Graph::new_directed();
+
+        assert!(check_graph_compat(&undirected, &UNDIRECTED_ONLY).is_ok());
+        match check_graph_compat(&directed, &UNDIRECTED_ONLY) {
+            Err(GraphError::InvalidStructure(message)) => assert_eq!(
+                message,
+                "example (undirected variant) called on a directed graph; use something else"
+            ),
+            other => panic!("expected InvalidStructure, got {:?}", other),
+        }
+
+        let directed_only = Requirements::directed("example", "use something else");
+        assert!(check_graph_compat(&directed, &directed_only).is_ok());
+        assert!(check_graph_compat(&undirected, &directed_only).is_err());
+    }
+
+    #[test]
+    fn test_compatible_graph() {
+        let mut directed = Graph::new_directed();
+        directed.add_node_simple("A");
+        directed.add_node_simple("B");
+        directed.add_edge(0, 1, 1.0).unwrap();
+
+        let strict = compatible_graph(&directed, &UNDIRECTED_ONLY, CompatOptions::default());
+        assert!(strict.is_err());
+
+        let options = CompatOptions::default().allow_directed_as_undirected();
+        let graph = compatible_graph(&directed, &UNDIRECTED_ONLY, options).unwrap();
+        assert!(!graph.is_directed());
+        assert_eq!(graph.neighbors(1).unwrap(), vec![0]);
+
+        let undirected = Graph::new();
+        assert!(matches!(
+            compatible_graph(&undirected, &UNDIRECTED_ONLY, options).unwrap(),
+            Cow::Borrowed(_)
+        ));
+    }
+}
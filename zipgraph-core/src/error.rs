@@ -1,14 +1,15 @@
 //! Error types for ZipGraph Core
 
+use crate::types::NodeId;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum GraphError {
     #[error("Node {0} not found")]
-    NodeNotFound(usize),
+    NodeNotFound(NodeId),
 
     #[error("Edge from {0} to {1} not found")]
-    EdgeNotFound(usize, usize),
+    EdgeNotFound(NodeId, NodeId),
 
     #[error("Invalid graph structure: {0}")]
     InvalidStructure(String),
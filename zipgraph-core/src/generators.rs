@@ -0,0 +1,593 @@
+//! Random graph models
+//!
+//! Every generator takes an optional seed; the same seed and parameters
+//! always produce the same graph.
+
+use crate::error::{GraphError, Result};
+use crate::graph::Graph;
+use crate::types::{node_id, NodeId};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+fn rng_from(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    }
+}
+
+fn with_nodes(n: usize, directed: bool) -> Graph {
+    let mut graph = if directed { Graph::new_directed() } else { Graph::new() };
+    for i in 0..n {
+        graph.add_node_simple(format!("Node{}", i));
+    }
+    graph
+}
+
+/// Erdős–Rényi `G(n, p)`: each ordered (directed) or unordered pair of
+/// distinct nodes is joined with probability `edge_probability`
+pub fn erdos_renyi(n: usize, edge_probability: f64, directed: bool, seed: Option<u64>) -> Graph {
+    let mut rng = rng_from(seed);
+    let mut graph = with_nodes(n, directed);
+    for from in 0..n {
+        let targets = if directed { 0..n } else { from + 1..n };
+        for to in targets {
+            if from != to && rng.gen_bool(edge_probability.clamp(0.0, 1.0)) {
+                graph.add_edge(node_id(from), node_id(to), 1.0).expect("nodes exist");
+            }
+        }
+    }
+    graph
+}
+
+/// Directed forest fire graph (Leskovec, Kleinberg and Faloutsos)
+///
+/// Nodes arrive one at a time. Each new node picks a uniformly random
+/// ambassador among the existing nodes and "burns" outward from it: every
+/// burning node sets fire to a geometrically distributed number of its
+/// not yet burned out-neighbors (mean `forward_prob / (1 - forward_prob)`)
+/// and in-neighbors (mean `backward_prob / (1 - backward_prob)`), chosen
+/// at random. The new node then links to every burned node. The model
+/// produces heavy-tailed degrees, high clustering and densification: the
+/// average degree grows with the number of nodes. The paper's values
+/// `forward_prob = 0.37`, `backward_prob = 0.32` give moderate
+/// densification; larger values approach a complete graph quickly.
+pub fn forest_fire(n: usize, forward_prob: f64, backward_prob: f64, seed: Option<u64>) -> Graph {
+    let mut rng = rng_from(seed);
+    let mut graph = with_nodes(n, true);
+    let forward_prob = forward_prob.clamp(0.0, 1.0);
+    let backward_prob = backward_prob.clamp(0.0, 1.0);
+
+    // Graph has no reverse adjacency, so in-neighbors are tracked here, by
+    // node number
+    let mut out_neighbors: Vec<Vec<usize>> = vec![Vec::new(); n];
+    let mut in_neighbors: Vec<Vec<usize>> = vec![Vec::new(); n];
+    let mut burned = HashSet::new();
+    let mut queue = VecDeque::new();
+
+    for new in 1..n {
+        let ambassador = rng.gen_range(0..new);
+        burned.clear();
+        burned.insert(ambassador);
+        queue.push_back(ambassador);
+
+        while let Some(node) = queue.pop_front() {
+            for (links, prob) in
+                [(&out_neighbors[node], forward_prob), (&in_neighbors[node], backward_prob)]
+            {
+                let mut candidates: Vec<usize> =
+                    links.iter().copied().filter(|next| !burned.contains(next)).collect();
+                let mut count = 0;
+                while count < candidates.len() && rng.gen_bool(prob) {
+                    count += 1;
+                }
+                let (chosen, _) = candidates.partial_shuffle(&mut rng, count);
+                for &next in chosen.iter() {
+                    if burned.insert(next) {
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+
+        let mut targets: Vec<usize> = burned.iter().copied().collect();
+        targets.sort_unstable();
+        for target in targets {
+            graph.add_edge(node_id(new), node_id(target), 1.0).expect("nodes exist");
+            out_neighbors[new].push(target);
+            in_neighbors[target].push(new);
+        }
+    }
+    graph
+}
+
+/// Undirected Barabási–Albert preferential attachment graph
+///
+/// Starts from `m` isolated nodes. Each later node links to `m` distinct
+/// existing nodes, each chosen with probability proportional to its
+/// degree (the first one links to all `m` seed nodes). The degree
+/// distribution follows a power law with exponent 3.
+pub fn barabasi_albert(n: usize, m: usize, seed: Option<u64>) -> Graph {
+    let mut rng = rng_from(seed);
+    let mut graph = with_nodes(n, false);
+    let m = m.max(1);
+
+    // Every node number appears here once per incident edge
+    let mut endpoints: Vec<usize> = Vec::new();
+    let mut targets = HashSet::new();
+    for new in m..n {
+        targets.clear();
+        if endpoints.is_empty() {
+            targets.extend(0..m);
+        }
+        while targets.len() < m {
+            targets.insert(endpoints[rng.gen_range(0..endpoints.len())]);
+        }
+        let mut chosen: Vec<usize> = targets.iter().copied().collect();
+        chosen.sort_unstable();
+        for target in chosen {
+            graph.add_edge(node_id(new), node_id(target), 1.0).expect("nodes exist");
+            endpoints.push(new);
+            endpoints.push(target);
+        }
+    }
+    graph
+}
+
+/// Planted partition model: `n` nodes in `communities` contiguous blocks
+/// of near-equal size, pairs inside a block joined with probability
+/// `p_in` and pairs across blocks with `p_out`
+///
+/// Returns the undirected graph and each node's block, the ground truth
+/// for community detection.
+pub fn planted_partition(
+    n: usize,
+    communities: usize,
+    p_in: f64,
+    p_out: f64,
+    seed: Option<u64>,
+) -> (Graph, HashMap<NodeId, usize>) {
+    let mut rng = rng_from(seed);
+    let mut graph = with_nodes(n, false);
+    let communities = communities.max(1);
+    let block = |node: usize| node * communities / n;
+    for from in 0..n {
+        for to in from + 1..n {
+            let p = if block(from) == block(to) { p_in } else { p_out };
+            if rng.gen_bool(p.clamp(0.0, 1.0)) {
+                graph.add_edge(node_id(from), node_id(to), 1.0).expect("nodes exist");
+            }
+        }
+    }
+    let truth = (0..n).map(|node| (node_id(node), block(node))).collect();
+    (graph, truth)
+}
+
+/// What `planted_anomalies` injects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AnomalySpec {
+    /// New nodes linked to `hub_degree` random existing nodes each
+    pub hubs: usize,
+    pub hub_degree: usize,
+    /// New nodes without edges
+    pub isolated: usize,
+    /// Groups of `clique_size` random existing nodes joined into cliques
+    pub cliques: usize,
+    pub clique_size: usize,
+}
+
+impl AnomalySpec {
+    /// Inject `count` hubs of `degree` edges each
+    pub fn with_hubs(mut self, count: usize, degree: usize) -> Self {
+        self.hubs = count;
+        self.hub_degree = degree;
+        self
+    }
+
+    /// Inject `count` isolated nodes
+    pub fn with_isolated(mut self, count: usize) -> Self {
+        self.isolated = count;
+        self
+    }
+
+    /// Inject `count` cliques of `size` nodes each
+    pub fn with_cliques(mut self, count: usize, size: usize) -> Self {
+        self.cliques = count;
+        self.clique_size = size;
+        self
+    }
+}
+
+/// A copy of `base` with the anomalies of `spec` injected, and the sorted
+/// IDs of every node involved
+///
+/// Hubs and isolated nodes are new nodes; cliques are drawn from `base`'s
+/// nodes, distinct from each other, and only add the missing edges among
+/// them. Hub degrees and clique sizes are capped at what `base` has nodes
+/// for.
+pub fn planted_anomalies(
+    base: &Graph,
+    spec: AnomalySpec,
+    seed: Option<u64>,
+) -> (Graph, Vec<NodeId>) {
+    let mut rng = rng_from(seed);
+    let mut graph = base.clone();
+    let mut base_nodes = base.node_ids();
+    base_nodes.sort_unstable();
+    let mut injected = Vec::new();
+
+    let mut candidates = base_nodes.clone();
+    for _ in 0..spec.cliques {
+        let size = spec.clique_size.min(candidates.len());
+        let (members, rest) = candidates.partial_shuffle(&mut rng, size);
+        let mut members = members.to_vec();
+        members.sort_unstable();
+        for (i, &a) in members.iter().enumerate() {
+            for &b in &members[i + 1..] {
+                if !linked(&graph, a, b) {
+                    graph.add_edge(a, b, 1.0).expect("nodes exist");
+                }
+            }
+        }
+        injected.extend(&members);
+        candidates = rest.to_vec();
+    }
+
+    for hub in 0..spec.hubs {
+        let id = graph.add_node_simple(format!("Hub{}", hub));
+        let degree = spec.hub_degree.min(base_nodes.len());
+        let mut targets: Vec<NodeId> =
+            base_nodes.choose_multiple(&mut rng, degree).copied().collect();
+        targets.sort_unstable();
+        for target in targets {
+            graph.add_edge(id, target, 1.0).expect("nodes exist");
+        }
+        injected.push(id);
+    }
+
+    for isolated in 0..spec.isolated {
+        injected.push(graph.add_node_simple(format!("Isolated{}", isolated)));
+    }
+
+    injected.sort_unstable();
+    (graph, injected)
+}
+
+/// Swaps attempted per requested swap before `rewire` gives up, and per
+/// edge before `configuration_model` does
+const REWIRE_ATTEMPTS_PER_SWAP: usize = 100;
+
+/// A degree-preserving copy of `graph`, randomized by `num_swaps`
+/// double-edge swaps
+///
+/// A swap picks two edges `a -> b` and `c -> d` and reconnects them as
+/// `a -> d` and `c -> b`, or, in an undirected graph, as either that or
+/// `a -- c` and `b -- d`. Every node keeps its degree (its in- and
+/// out-degree if directed), and each edge keeps its weight, type and
+/// timestamp. Swaps that would create a self-loop are skipped, as are
+/// swaps that would join two nodes twice, unless `graph` already has
+/// parallel edges (`Graph::has_parallel_edges`). Self-loops of `graph` are
+/// left in place. Gives up after `REWIRE_ATTEMPTS_PER_SWAP * num_swaps`
+/// attempts, so a graph with few valid swaps is returned less randomized.
+/// The usual choice for a null model is ten swaps per edge.
+pub fn rewire(graph: &Graph, num_swaps: usize, seed: Option<u64>) -> Graph {
+    let mut rng = rng_from(seed);
+    let edges = graph.edges();
+    let swappable: Vec<usize> =
+        (0..edges.len()).filter(|&i| edges[i].from != edges[i].to).collect();
+    let mut swapper = EdgeSwapper::new(
+        swappable.iter().map(|&i| (edges[i].from, edges[i].to)).collect(),
+        graph.is_directed(),
+        graph.has_parallel_edges(),
+    );
+
+    if swappable.len() >= 2 {
+        let mut swaps = 0;
+        for _ in 0..num_swaps.saturating_mul(REWIRE_ATTEMPTS_PER_SWAP) {
+            if swaps == num_swaps {
+                break;
+            }
+            let first = rng.gen_range(0..swappable.len());
+            let second = rng.gen_range(0..swappable.len());
+            let flip = !graph.is_directed() && rng.gen_bool(0.5);
+            swaps += usize::from(swapper.try_swap(first, second, flip));
+        }
+    }
+
+    let mut rewired_edges = edges.to_vec();
+    for (&i, &(from, to)) in swappable.iter().zip(&swapper.pairs) {
+        rewired_edges[i].from = from;
+        rewired_edges[i].to = to;
+    }
+    let mut rewired = graph.clone();
+    rewired.replace_edges(rewired_edges).expect("rewired edges join existing nodes");
+    rewired
+}
+
+/// Undirected graph whose node `i` has degree `degree_sequence[i]`, drawn
+/// as a configuration model
+///
+/// Each node gets one stub per unit of degree and the stubs are paired
+/// uniformly at random. Pairs that form self-loops or parallel edges are
+/// then swapped with random other pairs, as in `rewire`, until none is
+/// left, which keeps every degree. Fails with `InvalidParameter` if the
+/// degrees sum to an odd number, or if the repair gives up, which happens
+/// when the sequence has no simple graph, like `[3, 3, 1, 1]`, and can
+/// happen for very dense ones.
+pub fn configuration_model(degree_sequence: &[usize], seed: Option<u64>) -> Result<Graph> {
+    let stub_count: usize = degree_sequence.iter().sum();
+    if stub_count % 2 == 1 {
+        return Err(GraphError::InvalidParameter(format!(
+            "Degrees sum to {}, an odd number of edge ends",
+            stub_count
+        )));
+    }
+
+    let mut rng = rng_from(seed);
+    let mut stubs: Vec<NodeId> = degree_sequence
+        .iter()
+        .enumerate()
+        .flat_map(|(node, &degree)| std::iter::repeat_n(node_id(node), degree))
+        .collect();
+    stubs.shuffle(&mut rng);
+    let pairs: Vec<(NodeId, NodeId)> = stubs.chunks(2).map(|pair| (pair[0], pair[1])).collect();
+    let mut swapper = EdgeSwapper::new(pairs, false, false);
+
+    let mut attempts =
+        swapper.pairs.len().saturating_mul(REWIRE_ATTEMPTS_PER_SWAP).max(REWIRE_ATTEMPTS_PER_SWAP);
+    for bad in 0..swapper.pairs.len() {
+        while swapper.is_bad(bad) {
+            if attempts == 0 {
+                return Err(GraphError::InvalidParameter(
+                    "Degree sequence could not be realized without self-loops or parallel edges"
+                        .to_string(),
+                ));
+            }
+            attempts -= 1;
+            let other = rng.gen_range(0..swapper.pairs.len());
+            let flip = rng.gen_bool(0.5);
+            swapper.try_swap(bad, other, flip);
+        }
+    }
+
+    let mut graph = with_nodes(degree_sequence.len(), false);
+    for &(from, to) in &swapper.pairs {
+        graph.add_edge(from, to, 1.0).expect("nodes exist");
+    }
+    Ok(graph)
+}
+
+/// Edge endpoints and how often each pair of nodes is joined, for
+/// double-edge swaps that never add self-loops
+struct EdgeSwapper {
+    pairs: Vec<(NodeId, NodeId)>,
+    counts: HashMap<(NodeId, NodeId), usize>,
+    directed: bool,
+    allow_parallel: bool,
+}
+
+impl EdgeSwapper {
+    fn new(pairs: Vec<(NodeId, NodeId)>, directed: bool, allow_parallel: bool) -> Self {
+        let mut swapper = Self { pairs, counts: HashMap::new(), directed, allow_parallel };
+        for i in 0..swapper.pairs.len() {
+            let key = swapper.key(swapper.pairs[i]);
+            *swapper.counts.entry(key).or_default() += 1;
+        }
+        swapper
+    }
+
+    fn key(&self, (from, to): (NodeId, NodeId)) -> (NodeId, NodeId) {
+        if self.directed || from <= to {
+            (from, to)
+        } else {
+            (to, from)
+        }
+    }
+
+    /// Whether edge `i` is a self-loop or parallel to another edge
+    fn is_bad(&self, i: usize) -> bool {
+        let (from, to) = self.pairs[i];
+        from == to || self.counts[&self.key((from, to))] > 1
+    }
+
+    /// Reconnect edges `i` and `j` as in `rewire`, the second one reversed
+    /// if `flip`, unless that adds a self-loop or a forbidden parallel edge
+    fn try_swap(&mut self, i: usize, j: usize, flip: bool) -> bool {
+        if i == j {
+            return false;
+        }
+        let (a, b) = self.pairs[i];
+        let (c, d) = if flip { (self.pairs[j].1, self.pairs[j].0) } else { self.pairs[j] };
+        let (first, second) = ((a, d), (c, b));
+        if a == d || c == b {
+            return false;
+        }
+
+        let old = [self.key(self.pairs[i]), self.key(self.pairs[j])];
+        for key in old {
+            *self.counts.get_mut(&key).expect("edges are counted") -= 1;
+        }
+        let (first_key, second_key) = (self.key(first), self.key(second));
+        let joined = |key| self.counts.get(&key).copied().unwrap_or(0) > 0;
+        let allowed = self.allow_parallel
+            || (first_key != second_key && !joined(first_key) && !joined(second_key));
+        let new = if allowed { [first_key, second_key] } else { old };
+        for key in new {
+            *self.counts.entry(key).or_default() += 1;
+        }
+        if allowed {
+            self.pairs[i] = first;
+            self.pairs[j] = second;
+        }
+        allowed
+    }
+}
+
+/// Whether an edge joins `a` and `b` in either direction
+fn linked(graph: &Graph, a: NodeId, b: NodeId) -> bool {
+    let points_to = |from: NodeId, to: NodeId| graph.neighbors(from).is_ok_and(|n| n.contains(&to));
+    points_to(a, b) || points_to(b, a)
+}
+
+#[cfg(all(test, not(feature = "uuid-ids")))]
+mod tests {
+    use super::*;
+    use crate::stats::average_clustering_coefficient;
+
+    #[test]
+    fn test_erdos_renyi() {
+        let graph = erdos_renyi(200, 0.05, false, Some(1));
+        assert_eq!(graph.node_count(), 200);
+        let expected = 0.05 * (200 * 199 / 2) as f64;
+        assert!((graph.edge_count() as f64 - expected).abs() < expected * 0.15);
+        assert_eq!(erdos_renyi(200, 0.05, false, Some(1)).content_hash(), graph.content_hash());
+        assert_eq!(erdos_renyi(10, 0.0, true, None).edge_count(), 0);
+        assert_eq!(erdos_renyi(10, 1.0, true, None).edge_count(), 90);
+    }
+
+    #[test]
+    fn test_forest_fire_is_clustered() {
+        let graph = forest_fire(500, 0.37, 0.32, Some(7));
+        assert_eq!(graph.node_count(), 500);
+        assert!(graph.check_invariants().unwrap().is_empty());
+
+        // Erdős–Rényi with the same number of undirected pairs
+        let pairs = (500 * 499 / 2) as f64;
+        let random = erdos_renyi(500, graph.edge_count() as f64 / pairs, false, Some(7));
+        let fire_clustering = average_clustering_coefficient(&graph);
+        let random_clustering = average_clustering_coefficient(&random);
+        assert!(
+            fire_clustering > 3.0 * random_clustering,
+            "forest fire {} vs random {}",
+            fire_clustering,
+            random_clustering
+        );
+    }
+
+    #[test]
+    fn test_forest_fire_densifies() {
+        let average_degree =
+            |n: usize| forest_fire(n, 0.37, 0.32, Some(3)).edge_count() as f64 / n as f64;
+        let (small, medium, large) =
+            (average_degree(100), average_degree(200), average_degree(500));
+        assert!(small < medium && medium < large, "{} {} {}", small, medium, large);
+        assert_eq!(forest_fire(1, 0.37, 0.32, None).edge_count(), 0);
+        assert_eq!(forest_fire(0, 0.37, 0.32, None).node_count(), 0);
+    }
+
+    #[test]
+    fn test_barabasi_albert() {
+        let graph = barabasi_albert(500, 3, Some(5));
+        assert_eq!(graph.node_count(), 500);
+        assert_eq!(graph.edge_count(), 3 * 497);
+        assert!(graph.check_invariants().unwrap().is_empty());
+        assert_eq!(barabasi_albert(500, 3, Some(5)).content_hash(), graph.content_hash());
+
+        // Preferential attachment grows hubs far above the average degree
+        let max_degree = graph.node_ids().iter().map(|&id| graph.degree(id).unwrap()).max();
+        assert!(max_degree.unwrap() > 30, "max degree {:?}", max_degree);
+        assert_eq!(barabasi_albert(2, 3, None).edge_count(), 0);
+    }
+
+    #[test]
+    fn test_planted_partition() {
+        let (graph, truth) = planted_partition(120, 4, 0.5, 0.01, Some(2));
+        assert_eq!(graph.node_count(), 120);
+        assert_eq!(truth.len(), 120);
+        assert!((0..4).all(|c| truth.values().filter(|&&t| t == c).count() == 30));
+
+        let inside = graph.edges().iter().filter(|e| truth[&e.from] == truth[&e.to]).count();
+        assert!(inside > 10 * (graph.edge_count() - inside));
+        let again = planted_partition(120, 4, 0.5, 0.01, Some(2)).0;
+        assert_eq!(again.content_hash(), graph.content_hash());
+    }
+
+    #[test]
+    fn test_planted_anomalies() {
+        let base = erdos_renyi(100, 0.05, false, Some(4));
+        let spec = AnomalySpec::default().with_hubs(2, 40).with_isolated(3).with_cliques(2, 6);
+        let (graph, injected) = planted_anomalies(&base, spec, Some(4));
+        assert_eq!(graph.node_count(), 105);
+        assert_eq!(injected.len(), 2 * 6 + 2 + 3);
+        assert!(injected.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let new_nodes: Vec<NodeId> = injected.iter().copied().filter(|&id| id >= 100).collect();
+        assert_eq!(new_nodes, vec![100, 101, 102, 103, 104]);
+        assert_eq!(graph.degree(100).unwrap(), 40);
+        assert_eq!(graph.degree(104).unwrap(), 0);
+
+        // Clique members are pairwise adjacent
+        let members: Vec<NodeId> = injected.iter().copied().filter(|&id| id < 100).collect();
+        let pairs = members
+            .iter()
+            .flat_map(|&a| members.iter().map(move |&b| (a, b)))
+            .filter(|&(a, b)| a < b && linked(&graph, a, b))
+            .count();
+        assert!(pairs >= 2 * 15);
+    }
+    fn endpoints(graph: &Graph) -> Vec<(NodeId, NodeId)> {
+        graph.edges().iter().map(|edge| (edge.from, edge.to)).collect()
+    }
+
+    fn degrees(graph: &Graph) -> (Vec<usize>, Vec<usize>) {
+        let mut out_degrees = vec![0; graph.node_count()];
+        let mut in_degrees = vec![0; graph.node_count()];
+        for edge in graph.edges() {
+            out_degrees[edge.from] += 1;
+            in_degrees[edge.to] += 1;
+        }
+        (out_degrees, in_degrees)
+    }
+
+    #[test]
+    fn test_rewire_preserves_degrees() {
+        let graph = barabasi_albert(200, 3, Some(2));
+        let swaps = 10 * graph.edge_count();
+        let rewired = rewire(&graph, swaps, Some(4));
+        assert_eq!(endpoints(&rewired), endpoints(&rewire(&graph, swaps, Some(4))));
+        for node in graph.node_ids() {
+            assert_eq!(rewired.degree(node).unwrap(), graph.degree(node).unwrap());
+        }
+        assert_eq!(rewired.edge_count(), graph.edge_count());
+        assert!(!rewired.has_parallel_edges());
+        assert!(rewired.edges().iter().all(|edge| edge.from != edge.to));
+        let original = endpoints(&graph);
+        let moved = original.iter().zip(endpoints(&rewired)).filter(|(a, b)| **a != *b).count();
+        assert!(moved > graph.edge_count() / 2, "{} edges moved", moved);
+
+        let directed = erdos_renyi(100, 0.05, true, Some(3));
+        let rewired = rewire(&directed, 2000, Some(5));
+        assert_eq!(degrees(&rewired), degrees(&directed));
+        assert!(!rewired.has_parallel_edges());
+
+        // A star has no valid swap at all
+        let mut star = with_nodes(6, false);
+        for leaf in 1..6 {
+            star.add_edge(0, leaf, 1.0).unwrap();
+        }
+        let mut rewired = endpoints(&rewire(&star, 10, Some(1)));
+        rewired.sort_unstable();
+        assert_eq!(rewired, endpoints(&star));
+    }
+
+    #[test]
+    fn test_configuration_model() {
+        let target = barabasi_albert(300, 2, Some(8));
+        let sequence: Vec<usize> = (0..300).map(|node| target.degree(node).unwrap()).collect();
+        let graph = configuration_model(&sequence, Some(1)).unwrap();
+        assert_eq!(endpoints(&graph), endpoints(&configuration_model(&sequence, Some(1)).unwrap()));
+        let degrees: Vec<usize> = (0..300).map(|node| graph.degree(node).unwrap()).collect();
+        assert_eq!(degrees, sequence);
+        assert!(!graph.has_parallel_edges());
+        assert!(graph.edges().iter().all(|edge| edge.from != edge.to));
+
+        assert_eq!(configuration_model(&[], None).unwrap().node_count(), 0);
+        assert!(matches!(configuration_model(&[2, 1], None), Err(GraphError::InvalidParameter(_))));
+        assert!(matches!(
+            configuration_model(&[3, 3, 1, 1], Some(1)),
+            Err(GraphError::InvalidParameter(_))
+        ));
+    }
+}
@@ -1,19 +1,150 @@
 //! Core graph data structures
 
+use crate::analysis::{AnalysisCache, PageRankParams};
 use crate::error::{GraphError, Result};
-use crate::types::{FeatureVector, NodeId, Weight};
+use crate::stats::GraphStats;
+use crate::types::{next_id, node_id, FeatureVector, NodeId, Weight, MAX_NODE_ID};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Source of generation tokens, shared by all graphs so that tokens from
+/// different graphs never collide
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+fn next_generation() -> u64 {
+    NEXT_GENERATION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// FNV-1a, used for hashes that must be stable across processes
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+
+    /// A node ID; `usize` IDs hash as `u64`, so hashes agree across
+    /// targets
+    #[cfg(not(feature = "uuid-ids"))]
+    fn write_id(&mut self, id: NodeId) {
+        self.write_u64(id as u64);
+    }
+
+    #[cfg(feature = "uuid-ids")]
+    fn write_id(&mut self, id: NodeId) {
+        self.write(id.as_bytes());
+    }
+
+    /// Length-prefixed, so that adjacent strings cannot run together
+    fn write_str(&mut self, value: &str) {
+        self.write_u64(value.len() as u64);
+        self.write(value.as_bytes());
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+fn node_hash(node: &Node) -> u64 {
+    let mut hash = Fnv::new();
+    hash.write(b"node");
+    hash.write_id(node.id);
+    hash.write_str(&node.label);
+    let mut properties: Vec<(&String, &f64)> = node.properties.iter().collect();
+    properties.sort_unstable_by(|a, b| a.0.cmp(b.0));
+    for (key, value) in properties {
+        hash.write_str(key);
+        hash.write_u64(value.to_bits());
+    }
+    hash.finish()
+}
+
+fn structural_node_hash(id: NodeId) -> u64 {
+    let mut hash = Fnv::new();
+    hash.write(b"node");
+    hash.write_id(id);
+    hash.finish()
+}
+
+/// Content and structural hash of `edge`; undirected edges hash the same
+/// either way round
+fn edge_hashes(edge: &Edge, directed: bool) -> (u64, u64) {
+    let (a, b) = if directed || edge.from <= edge.to {
+        (edge.from, edge.to)
+    } else {
+        (edge.to, edge.from)
+    };
+    let mut hash = Fnv::new();
+    hash.write(b"edge");
+    hash.write_id(a);
+    hash.write_id(b);
+    let structure = hash.finish();
+    hash.write_u64(edge.weight.to_bits());
+    hash.write_str(&edge.edge_type);
+    if let Some(timestamp) = edge.timestamp {
+        hash.write(b"at");
+        hash.write_u64(timestamp.to_bits());
+    }
+    (hash.finish(), structure)
+}
+
+/// Assert in debug builds that a graph passes `Graph::check_invariants`
+///
+/// Compiles to nothing in release builds.
+#[macro_export]
+macro_rules! debug_assert_invariants {
+    ($graph:expr) => {
+        if cfg!(debug_assertions) {
+            let violations = $graph
+                .check_invariants()
+                .unwrap_or_else(|e| vec![e.to_string()]);
+            debug_assert!(
+                violations.is_empty(),
+                "graph invariants violated: {:?}",
+                violations
+            );
+        }
+    };
+}
 
 /// Graph node with properties
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Node {
     pub id: NodeId,
     pub label: String,
+    /// Encoded in key order, so equal nodes always encode the same
+    #[serde(serialize_with = "serialize_sorted")]
     pub properties: HashMap<String, f64>,
     pub features: Option<FeatureVector>,
 }
 
+fn serialize_sorted<S: serde::Serializer>(
+    map: &HashMap<String, f64>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
 impl Node {
     pub fn new(id: NodeId, label: impl Into<String>) -> Self {
         Self {
@@ -35,13 +166,56 @@ impl Node {
     }
 }
 
-/// Graph edge with weight and type
+/// Nodes are identified by ID alone, since IDs are unique within a graph
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Node {}
+
+impl Hash for Node {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl From<Node> for NodeId {
+    fn from(node: Node) -> Self {
+        node.id
+    }
+}
+
+impl From<&Node> for NodeId {
+    fn from(node: &Node) -> Self {
+        node.id
+    }
+}
+
+/// Distribution of the weights drawn by `Graph::randomize_edge_weights`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum WeightDistribution {
+    /// Uniform on `[min, max)`
+    Uniform { min: f64, max: f64 },
+    /// Normal with mean `mean` and standard deviation `std`
+    Gaussian { mean: f64, std: f64 },
+    /// Exponential with rate `rate`, i.e. mean `1 / rate`
+    Exponential { rate: f64 },
+    /// The graph's own weights, shuffled among its edges
+    Original,
+}
+
+/// Graph edge with weight, type and optional timestamp
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Edge {
     pub from: NodeId,
     pub to: NodeId,
     pub weight: Weight,
     pub edge_type: String,
+    /// When the edge appeared, in any unit; see `Graph::time_window_view`
+    #[serde(default)]
+    pub timestamp: Option<f64>,
 }
 
 impl Edge {
@@ -51,6 +225,7 @@ impl Edge {
             to,
             weight,
             edge_type: "default".to_string(),
+            timestamp: None,
         }
     }
 
@@ -58,17 +233,127 @@ impl Edge {
         self.edge_type = edge_type.into();
         self
     }
+
+    pub fn with_timestamp(mut self, timestamp: f64) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+}
+
+/// One mutation of a `Graph::apply_batch` batch
+///
+/// Nodes are referred to by ID, so ops may use nodes added earlier in the
+/// same batch. `RemoveEdge` and `SetWeight` act on every edge from `from`
+/// to `to`, or between them either way round if the graph is undirected,
+/// including edges added earlier in the batch.
+#[derive(Debug, Clone)]
+pub enum GraphOp {
+    /// Add a node under its own ID, which must not be in use
+    AddNode(Node),
+    /// Add an edge, keeping its type and timestamp
+    AddEdge(Edge),
+    /// Remove the edges from `from` to `to`
+    RemoveEdge { from: NodeId, to: NodeId },
+    /// Set the weight of the edges from `from` to `to`
+    SetWeight { from: NodeId, to: NodeId, weight: Weight },
+    /// Set property `key` of `node`, adding it if absent
+    SetProperty { node: NodeId, key: String, value: f64 },
+}
+
+/// What a successful `Graph::apply_batch` changed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchReceipt {
+    /// IDs of the added nodes, in batch order
+    pub nodes: Vec<NodeId>,
+    /// Indices into `Graph::edges` of the added edges, in batch order;
+    /// edges removed again later in the batch are left out
+    pub edges: Vec<usize>,
+    /// Edges removed, not counting ones added in the same batch
+    pub removed_edges: usize,
 }
 
 /// Main graph structure using adjacency list representation
+///
+/// The node map, adjacency lists and edge list are shared between clones
+/// and copied the first time a clone mutates them, so cloning is O(1);
+/// see `snapshot`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "GraphData")]
 pub struct Graph {
-    nodes: HashMap<NodeId, Node>,
+    nodes: Arc<HashMap<NodeId, Node>>,
     /// Adjacency list: node_id -> Vec<(neighbor_id, edge_index)>
-    adjacency: HashMap<NodeId, Vec<(NodeId, usize)>>,
-    edges: Vec<Edge>,
+    adjacency: Arc<HashMap<NodeId, Vec<(NodeId, usize)>>>,
+    edges: Arc<Vec<Edge>>,
+    is_directed: bool,
+    next_node_id: NodeId,
+    /// Whether all edges have the same weight; see `has_uniform_weights`
+    uniform_weights: bool,
+    /// Whether adjacency lists are kept sorted; see `sort_adjacency`
+    sorted_adjacency: bool,
+    /// Changes on every mutation; see `Graph::generation`
+    #[serde(skip)]
+    generation: u64,
+    /// Sums of the per-element hashes behind `content_hash` and
+    /// `structural_hash`, maintained on every mutation
+    content_sum: u64,
+    structure_sum: u64,
+    /// Results memoized for the current generation; see `analysis_cache`
+    #[serde(skip)]
+    analysis: Arc<AnalysisCache>,
+}
+
+/// `Graph` as serialized
+///
+/// The fields `Graph` derives from its nodes and edges are read, to keep
+/// the layout, but recomputed rather than trusted, so that graphs saved
+/// without them or edited by hand still hash and report uniform weights
+/// correctly.
+#[derive(Deserialize)]
+struct GraphData {
+    nodes: Arc<HashMap<NodeId, Node>>,
+    adjacency: Arc<HashMap<NodeId, Vec<(NodeId, usize)>>>,
+    edges: Arc<Vec<Edge>>,
     is_directed: bool,
     next_node_id: NodeId,
+    #[serde(default, rename = "uniform_weights")]
+    _uniform_weights: bool,
+    #[serde(default)]
+    sorted_adjacency: bool,
+    #[serde(default, rename = "content_sum")]
+    _content_sum: u64,
+    #[serde(default, rename = "structure_sum")]
+    _structure_sum: u64,
+}
+
+impl From<GraphData> for Graph {
+    fn from(data: GraphData) -> Self {
+        let mut graph = Graph {
+            nodes: data.nodes,
+            adjacency: data.adjacency,
+            edges: data.edges,
+            is_directed: data.is_directed,
+            next_node_id: data.next_node_id,
+            uniform_weights: true,
+            sorted_adjacency: data.sorted_adjacency,
+            generation: next_generation(),
+            content_sum: 0,
+            structure_sum: 0,
+            analysis: Arc::default(),
+        };
+        for node in graph.nodes.values() {
+            graph.content_sum = graph.content_sum.wrapping_add(node_hash(node));
+            graph.structure_sum = graph.structure_sum.wrapping_add(structural_node_hash(node.id));
+        }
+        if let Some(first) = graph.edges.first() {
+            graph.uniform_weights = graph.edges.iter().all(|edge| edge.weight == first.weight);
+        }
+        for edge in graph.edges.iter() {
+            let (content, structure) = edge_hashes(edge, graph.is_directed);
+            graph.content_sum = graph.content_sum.wrapping_add(content);
+            graph.structure_sum = graph.structure_sum.wrapping_add(structure);
+        }
+        graph
+    }
 }
 
 impl Graph {
@@ -80,11 +365,17 @@ impl Graph {
     /// Create a new graph with preallocated capacity
     pub fn with_capacity(node_capacity: usize, edge_capacity: usize) -> Self {
         Self {
-            nodes: HashMap::with_capacity(node_capacity),
-            adjacency: HashMap::with_capacity(node_capacity),
-            edges: Vec::with_capacity(edge_capacity),
+            nodes: Arc::new(HashMap::with_capacity(node_capacity)),
+            adjacency: Arc::new(HashMap::with_capacity(node_capacity)),
+            edges: Arc::new(Vec::with_capacity(edge_capacity)),
             is_directed: false,
-            next_node_id: 0,
+            next_node_id: node_id(0),
+            uniform_weights: true,
+            sorted_adjacency: false,
+            generation: next_generation(),
+            content_sum: 0,
+            structure_sum: 0,
+            analysis: Arc::default(),
         }
     }
 
@@ -97,23 +388,213 @@ impl Graph {
 
     /// Add a node to the graph
     pub fn add_node(&mut self, node: Node) -> NodeId {
+        self.touch();
+        self.put_node(node)
+    }
+
+    /// `add_node` without the generation change
+    fn put_node(&mut self, node: Node) -> NodeId {
         let id = node.id;
-        self.nodes.insert(id, node);
-        self.adjacency.entry(id).or_insert_with(Vec::new);
+        self.content_sum = self.content_sum.wrapping_add(node_hash(&node));
+        self.structure_sum = self.structure_sum.wrapping_add(structural_node_hash(id));
+        if let Some(replaced) = Arc::make_mut(&mut self.nodes).insert(id, node) {
+            self.content_sum = self.content_sum.wrapping_sub(node_hash(&replaced));
+            self.structure_sum = self.structure_sum.wrapping_sub(structural_node_hash(id));
+        }
+        Arc::make_mut(&mut self.adjacency).entry(id).or_default();
         if id >= self.next_node_id {
-            self.next_node_id = id + 1;
+            self.next_node_id = next_id(id);
         }
         id
     }
 
+    /// Add `node` under `id`, which it takes in place of its own `id`
+    ///
+    /// Unlike `add_node`, this never replaces a node: an `id` already in
+    /// the graph is an `InvalidParameter` error, so graphs whose IDs came
+    /// from `types::generate_id` can be merged with collisions detected.
+    /// The largest ID, `usize::MAX` or the all-ones UUID, is reserved as well.
+    pub fn add_node_with_id(&mut self, id: NodeId, mut node: Node) -> Result<NodeId> {
+        if self.nodes.contains_key(&id) {
+            return Err(GraphError::InvalidParameter(format!("Node {} already exists", id)));
+        }
+        if id == MAX_NODE_ID {
+            return Err(GraphError::InvalidParameter(format!("Node ID {} is reserved", id)));
+        }
+        node.id = id;
+        Ok(self.add_node(node))
+    }
+
+    /// The ID `add_node_simple` hands out next
+    pub(crate) fn next_node_id(&self) -> NodeId {
+        self.next_node_id
+    }
+
+    /// Make `add_node_simple` hand out IDs from `next` on, if that is
+    /// above the IDs it would use anyway
+    pub(crate) fn reserve_node_ids(&mut self, next: NodeId) {
+        self.next_node_id = self.next_node_id.max(next);
+    }
+
     /// Add a node with just a label
     pub fn add_node_simple(&mut self, label: impl Into<String>) -> NodeId {
         let id = self.next_node_id;
         self.add_node(Node::new(id, label))
     }
 
+    /// Replace the nodes `nodes_to_merge` with one new node labelled
+    /// `merged_label`, returning its ID
+    ///
+    /// The merged node gets a fresh ID, as from `add_node_simple`. Every
+    /// edge with an endpoint in the set is redirected to it, so edges
+    /// between members become self-loops. Redirected edges that end up
+    /// with the same endpoints (either way round, if undirected) collapse
+    /// into the one with the largest weight, keeping its type and
+    /// timestamp. Other edges, parallel ones included, and the IDs of the
+    /// other nodes are unchanged, but edge indices are not. Repeated
+    /// members are ignored; an empty set is an `InvalidParameter` error.
+    pub fn contract_nodes(
+        &mut self,
+        nodes_to_merge: &[NodeId],
+        merged_label: impl Into<String>,
+    ) -> Result<NodeId> {
+        if nodes_to_merge.is_empty() {
+            return Err(GraphError::InvalidParameter("No nodes to contract".into()));
+        }
+        for &id in nodes_to_merge {
+            self.node(id)?;
+        }
+        let members: HashSet<NodeId> = nodes_to_merge.iter().copied().collect();
+        let merged = self.next_node_id;
+
+        let mut contracted = if self.is_directed { Graph::new_directed() } else { Graph::new() };
+        contracted.sorted_adjacency = self.sorted_adjacency;
+        let mut kept_ids: Vec<NodeId> =
+            self.nodes.keys().copied().filter(|id| !members.contains(id)).collect();
+        kept_ids.sort_unstable();
+        for id in kept_ids {
+            contracted.add_node(self.nodes[&id].clone());
+        }
+        contracted.add_node(Node::new(merged, merged_label));
+
+        let mut redirected: Vec<Edge> = Vec::new();
+        let mut slots: HashMap<(NodeId, NodeId), usize> = HashMap::new();
+        for edge in self.edges.iter() {
+            let from_merged = members.contains(&edge.from);
+            let to_merged = members.contains(&edge.to);
+            if !from_merged && !to_merged {
+                contracted.insert_edge(edge.clone())?;
+                continue;
+            }
+
+            let mut edge = edge.clone();
+            if from_merged {
+                edge.from = merged;
+            }
+            if to_merged {
+                edge.to = merged;
+            }
+            let key = if self.is_directed || edge.from <= edge.to {
+                (edge.from, edge.to)
+            } else {
+                (edge.to, edge.from)
+            };
+            match slots.entry(key) {
+                Entry::Occupied(slot) => {
+                    let kept = &mut redirected[*slot.get()];
+                    if edge.weight > kept.weight {
+                        *kept = edge;
+                    }
+                }
+                Entry::Vacant(slot) => {
+                    slot.insert(redirected.len());
+                    redirected.push(edge);
+                }
+            }
+        }
+        for edge in redirected {
+            contracted.insert_edge(edge)?;
+        }
+
+        *self = contracted;
+        Ok(merged)
+    }
+
+    /// Change the label of node `id`, keeping its ID and edges
+    pub fn update_node_label(&mut self, id: NodeId, new_label: impl Into<String>) -> Result<()> {
+        self.modify_node(id, |node| node.label = new_label.into())
+    }
+
+    /// Set property `key` of node `id`, adding it if absent
+    pub fn update_node_property(&mut self, id: NodeId, key: &str, value: f64) -> Result<()> {
+        self.modify_node(id, |node| {
+            node.properties.insert(key.to_string(), value);
+        })
+    }
+
+    /// Remove property `key` of node `id`, returning whether it was set
+    pub fn remove_node_property(&mut self, id: NodeId, key: &str) -> Result<bool> {
+        if !self.node(id)?.properties.contains_key(key) {
+            return Ok(false);
+        }
+        self.modify_node(id, |node| node.properties.remove(key).is_some())
+    }
+
+    /// Replace the feature vector of node `id`
+    pub fn set_node_features(&mut self, id: NodeId, features: FeatureVector) -> Result<()> {
+        self.modify_node(id, |node| node.features = Some(features))
+    }
+
+    /// Apply `change` to node `id`, keeping the generation and content
+    /// hash up to date
+    fn modify_node<T>(&mut self, id: NodeId, change: impl FnOnce(&mut Node) -> T) -> Result<T> {
+        // Checked first so that a miss does not copy shared nodes
+        self.node(id)?;
+        let result = self.edit_node(id, change);
+        self.touch();
+        Ok(result)
+    }
+
+    /// `modify_node` for an existing node, without the generation change
+    fn edit_node<T>(&mut self, id: NodeId, change: impl FnOnce(&mut Node) -> T) -> T {
+        let node = Arc::make_mut(&mut self.nodes).get_mut(&id).expect("node exists");
+        let before = node_hash(node);
+        let result = change(node);
+        let after = node_hash(node);
+        self.content_sum = self.content_sum.wrapping_sub(before).wrapping_add(after);
+        result
+    }
+
     /// Add an edge between two nodes
     pub fn add_edge(&mut self, from: NodeId, to: NodeId, weight: Weight) -> Result<usize> {
+        self.insert_edge(Edge::new(from, to, weight))
+    }
+
+    /// Add an edge with an explicit type, see `neighbors_of_type`
+    pub fn add_typed_edge(
+        &mut self,
+        from: NodeId,
+        to: NodeId,
+        weight: Weight,
+        edge_type: impl Into<String>,
+    ) -> Result<usize> {
+        self.insert_edge(Edge::new(from, to, weight).with_type(edge_type))
+    }
+
+    /// Add an edge that appeared at `timestamp`, see `time_window_view`
+    pub fn add_timestamped_edge(
+        &mut self,
+        from: NodeId,
+        to: NodeId,
+        weight: Weight,
+        timestamp: f64,
+    ) -> Result<usize> {
+        self.insert_edge(Edge::new(from, to, weight).with_timestamp(timestamp))
+    }
+
+    /// Add a fully specified edge, keeping its type and timestamp
+    pub(crate) fn insert_edge(&mut self, edge: Edge) -> Result<usize> {
+        let (from, to) = (edge.from, edge.to);
         if !self.nodes.contains_key(&from) {
             return Err(GraphError::NodeNotFound(from));
         }
@@ -121,22 +602,44 @@ impl Graph {
             return Err(GraphError::NodeNotFound(to));
         }
 
+        self.touch();
+        Ok(self.push_edge(edge))
+    }
+
+    /// `insert_edge` for an edge between existing nodes, without the
+    /// generation change
+    fn push_edge(&mut self, edge: Edge) -> usize {
+        let (from, to) = (edge.from, edge.to);
+        if let Some(first) = self.edges.first() {
+            self.uniform_weights &= first.weight == edge.weight;
+        }
+        let (content, structure) = edge_hashes(&edge, self.is_directed);
+        self.content_sum = self.content_sum.wrapping_add(content);
+        self.structure_sum = self.structure_sum.wrapping_add(structure);
         let edge_idx = self.edges.len();
-        self.edges.push(Edge::new(from, to, weight));
+        Arc::make_mut(&mut self.edges).push(edge);
 
-        self.adjacency
-            .get_mut(&from)
-            .unwrap()
-            .push((to, edge_idx));
+        let sorted = self.sorted_adjacency;
+        let adjacency = Arc::make_mut(&mut self.adjacency);
+        let mut link = |source: NodeId, target: NodeId| {
+            let neighbors = adjacency.get_mut(&source).unwrap();
+            // The new edge index is the largest, so it goes after any
+            // parallel edges to the same neighbor
+            let position = if sorted {
+                neighbors.partition_point(|&(neighbor, _)| neighbor <= target)
+            } else {
+                neighbors.len()
+            };
+            neighbors.insert(position, (target, edge_idx));
+        };
+        link(from, to);
 
-        if !self.is_directed {
-            self.adjacency
-                .get_mut(&to)
-                .unwrap()
-                .push((from, edge_idx));
+        // A self-loop is listed once, even in an undirected graph
+        if !self.is_directed && from != to {
+            link(to, from);
         }
 
-        Ok(edge_idx)
+        edge_idx
     }
 
     /// Get a node by ID
@@ -169,6 +672,129 @@ impl Graph {
             .ok_or(GraphError::NodeNotFound(id))
     }
 
+    /// Neighbors of `id` reached over edges of type `edge_type`
+    pub fn neighbors_of_type(&self, id: NodeId, edge_type: &str) -> Result<Vec<NodeId>> {
+        self.adjacency
+            .get(&id)
+            .map(|neighbors| {
+                neighbors
+                    .iter()
+                    .filter(|(_, edge_idx)| self.edges[*edge_idx].edge_type == edge_type)
+                    .map(|(neighbor_id, _)| *neighbor_id)
+                    .collect()
+            })
+            .ok_or(GraphError::NodeNotFound(id))
+    }
+
+    /// Get all neighbors with edge weights and types
+    pub fn neighbors_with_type(&self, id: NodeId) -> Result<Vec<(NodeId, Weight, String)>> {
+        self.adjacency
+            .get(&id)
+            .map(|neighbors| {
+                neighbors
+                    .iter()
+                    .map(|(neighbor_id, edge_idx)| {
+                        let edge = &self.edges[*edge_idx];
+                        (*neighbor_id, edge.weight, edge.edge_type.clone())
+                    })
+                    .collect()
+            })
+            .ok_or(GraphError::NodeNotFound(id))
+    }
+
+    /// Neighbors of `id` with edge weights, without collecting them
+    ///
+    /// Empty for unknown nodes.
+    pub(crate) fn weighted_neighbors_iter(
+        &self,
+        id: NodeId,
+    ) -> impl Iterator<Item = (NodeId, Weight)> + '_ {
+        self.adjacency
+            .get(&id)
+            .into_iter()
+            .flatten()
+            .map(|&(neighbor_id, edge_idx)| (neighbor_id, self.edges[edge_idx].weight))
+    }
+
+    /// Edges leaving `id` with the neighbor they lead to
+    ///
+    /// Empty for unknown nodes. On undirected graphs every incident edge
+    /// is listed, whichever way round it was added.
+    pub(crate) fn incident_edges_iter(&self, id: NodeId) -> impl Iterator<Item = (NodeId, &Edge)> {
+        self.adjacency
+            .get(&id)
+            .into_iter()
+            .flatten()
+            .map(|&(neighbor_id, edge_idx)| (neighbor_id, &self.edges[edge_idx]))
+    }
+
+    /// Get all nodes adjacent to a member of `nodes` but not in `nodes`
+    ///
+    /// Runs in time proportional to the sum of the members' degrees. On
+    /// directed graphs only outgoing edges are followed.
+    pub fn neighbors_of_set(&self, nodes: &[NodeId]) -> Result<HashSet<NodeId>> {
+        let members: HashSet<NodeId> = nodes.iter().copied().collect();
+        let mut neighbors = self.closed_neighborhood(nodes)?;
+        neighbors.retain(|id| !members.contains(id));
+        Ok(neighbors)
+    }
+
+    /// Get `nodes` together with all nodes adjacent to them
+    pub fn closed_neighborhood(&self, nodes: &[NodeId]) -> Result<HashSet<NodeId>> {
+        let mut neighborhood = HashSet::with_capacity(nodes.len());
+        for &id in nodes {
+            let adjacent = self.adjacency.get(&id).ok_or(GraphError::NodeNotFound(id))?;
+            neighborhood.insert(id);
+            neighborhood.extend(adjacent.iter().map(|&(neighbor_id, _)| neighbor_id));
+        }
+        Ok(neighborhood)
+    }
+
+    /// All nodes reachable from `source` along edges, including `source`
+    ///
+    /// Empty if `source` is not in the graph.
+    pub fn reachable_from(&self, source: NodeId) -> HashSet<NodeId> {
+        let mut reached = HashSet::new();
+        if !self.nodes.contains_key(&source) {
+            return reached;
+        }
+
+        let mut stack = vec![source];
+        reached.insert(source);
+        while let Some(node) = stack.pop() {
+            for &(neighbor, _) in &self.adjacency[&node] {
+                if reached.insert(neighbor) {
+                    stack.push(neighbor);
+                }
+            }
+        }
+        reached
+    }
+
+    /// Whether a path leads from `from` to `to`
+    ///
+    /// Every node reaches itself; missing nodes reach nothing. Stops
+    /// searching as soon as `to` is found.
+    pub fn is_reachable(&self, from: NodeId, to: NodeId) -> bool {
+        if !self.nodes.contains_key(&from) || !self.nodes.contains_key(&to) {
+            return false;
+        }
+
+        let mut stack = vec![from];
+        let mut visited = HashSet::from([from]);
+        while let Some(node) = stack.pop() {
+            if node == to {
+                return true;
+            }
+            for &(neighbor, _) in &self.adjacency[&node] {
+                if visited.insert(neighbor) {
+                    stack.push(neighbor);
+                }
+            }
+        }
+        false
+    }
+
     /// Get number of nodes
     pub fn node_count(&self) -> usize {
         self.nodes.len()
@@ -189,11 +815,61 @@ impl Graph {
         self.nodes.keys().copied().collect()
     }
 
+    /// Get all node IDs as a set
+    pub fn node_set(&self) -> HashSet<NodeId> {
+        self.nodes.keys().copied().collect()
+    }
+
     /// Get all edges
     pub fn edges(&self) -> &[Edge] {
         &self.edges
     }
 
+    /// Whether some pair of nodes is joined by more than one edge
+    ///
+    /// In an undirected graph `a -- b` and `b -- a` are parallel.
+    pub fn has_parallel_edges(&self) -> bool {
+        let mut pairs = HashSet::with_capacity(self.edges.len());
+        !self.edges.iter().all(|edge| pairs.insert(self.endpoint_key(edge)))
+    }
+
+    /// The endpoints of `edge`, in a canonical order if undirected
+    pub(crate) fn endpoint_key(&self, edge: &Edge) -> (NodeId, NodeId) {
+        if self.is_directed || edge.from <= edge.to {
+            (edge.from, edge.to)
+        } else {
+            (edge.to, edge.from)
+        }
+    }
+
+    /// Replace every edge with `edges`, keeping the nodes
+    pub(crate) fn replace_edges(&mut self, edges: Vec<Edge>) -> Result<()> {
+        self.unlink_edges();
+        self.touch();
+        for edge in edges {
+            self.insert_edge(edge)?;
+        }
+        Ok(())
+    }
+
+    /// Drop every edge, without the generation change
+    fn unlink_edges(&mut self) {
+        for edge in std::mem::take(&mut self.edges).iter() {
+            let (content, structure) = edge_hashes(edge, self.is_directed);
+            self.content_sum = self.content_sum.wrapping_sub(content);
+            self.structure_sum = self.structure_sum.wrapping_sub(structure);
+        }
+        for neighbors in Arc::make_mut(&mut self.adjacency).values_mut() {
+            neighbors.clear();
+        }
+        self.uniform_weights = true;
+    }
+
+    /// All distinct edge types in the graph
+    pub fn edge_types(&self) -> HashSet<String> {
+        self.edges.iter().map(|edge| edge.edge_type.clone()).collect()
+    }
+
     /// Calculate the degree of a node
     pub fn degree(&self, id: NodeId) -> Result<usize> {
         self.adjacency
@@ -202,61 +878,1860 @@ impl Graph {
             .ok_or(GraphError::NodeNotFound(id))
     }
 
-    /// Clear all nodes and edges
-    pub fn clear(&mut self) {
-        self.nodes.clear();
-        self.adjacency.clear();
-        self.edges.clear();
-        self.next_node_id = 0;
+    /// Whether the edge `from -> to` exists (either way round, if
+    /// undirected)
+    ///
+    /// A binary search with sorted adjacency, see `sort_adjacency`, and a
+    /// scan of the neighbors of `from` otherwise. False for unknown nodes.
+    pub fn has_edge(&self, from: NodeId, to: NodeId) -> bool {
+        let Some(neighbors) = self.adjacency.get(&from) else {
+            return false;
+        };
+        if self.sorted_adjacency {
+            neighbors.binary_search_by_key(&to, |&(neighbor, _)| neighbor).is_ok()
+        } else {
+            neighbors.iter().any(|&(neighbor, _)| neighbor == to)
+        }
     }
-}
 
-impl Default for Graph {
-    fn default() -> Self {
-        Self::new()
+    /// The lightest edge that leads from `from` to `to`
+    fn lightest_edge(&self, from: NodeId, to: NodeId) -> Option<&Edge> {
+        self.adjacency
+            .get(&from)?
+            .iter()
+            .filter(|&&(neighbor, _)| neighbor == to)
+            .map(|&(_, edge_idx)| &self.edges[edge_idx])
+            .min_by(|a, b| a.weight.total_cmp(&b.weight))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Whether `path` is a walk in the graph: a non-empty sequence of
+    /// existing nodes, each joined to the next by an edge
+    ///
+    /// Edges are followed in their direction on directed graphs. Nodes may
+    /// repeat, so results of `bfs`, `dijkstra` and the like always pass.
+    pub fn is_valid_path(&self, path: &[NodeId]) -> bool {
+        !path.is_empty()
+            && path.iter().all(|id| self.nodes.contains_key(id))
+            && path.windows(2).all(|pair| self.has_edge(pair[0], pair[1]))
+    }
 
-    #[test]
-    fn test_graph_creation() {
-        let graph = Graph::new();
-        assert_eq!(graph.node_count(), 0);
-        assert_eq!(graph.edge_count(), 0);
+    /// Same as `is_valid_path`
+    pub fn path_exists(&self, path: &[NodeId]) -> bool {
+        self.is_valid_path(path)
     }
 
-    #[test]
-    fn test_add_nodes_and_edges() {
-        let mut graph = Graph::new();
-        
-        let n0 = graph.add_node_simple("A");
-        let n1 = graph.add_node_simple("B");
-        let n2 = graph.add_node_simple("C");
+    /// Total weight of `path`, taking the lightest of any parallel edges
+    ///
+    /// Zero for a single node. Fails with `InvalidParameter` for an empty
+    /// path, `NodeNotFound` for unknown nodes and `EdgeNotFound` for
+    /// consecutive nodes without an edge.
+    pub fn path_weight(&self, path: &[NodeId]) -> Result<Weight> {
+        Ok(self.path_edges(path)?.iter().map(|edge| edge.weight).sum())
+    }
 
-        graph.add_edge(n0, n1, 1.0).unwrap();
-        graph.add_edge(n1, n2, 2.0).unwrap();
+    /// The edges `path_weight` sums, one per step of `path`
+    fn path_edges(&self, path: &[NodeId]) -> Result<Vec<&Edge>> {
+        if path.is_empty() {
+            return Err(GraphError::InvalidParameter("Path is empty".into()));
+        }
+        for &id in path {
+            self.node(id)?;
+        }
+        path.windows(2)
+            .map(|pair| {
+                self.lightest_edge(pair[0], pair[1])
+                    .ok_or(GraphError::EdgeNotFound(pair[0], pair[1]))
+            })
+            .collect()
+    }
 
-        assert_eq!(graph.node_count(), 3);
-        assert_eq!(graph.edge_count(), 2);
+    /// `path` as a graph of its own: its nodes and, for each step, the
+    /// edge `path_weight` takes
+    ///
+    /// Nodes keep their IDs, labels and properties, and edges their
+    /// weights, types and timestamps, so a simple path of `n` nodes gives
+    /// a chain of `n` nodes and `n - 1` edges. Other edges among the nodes
+    /// are left out; a step taken twice adds its edge twice. Fails like
+    /// `path_weight`.
+    pub fn induced_path_graph(&self, path: &[NodeId]) -> Result<Graph> {
+        let edges = self.path_edges(path)?;
+        let mut chain = if self.is_directed { Graph::new_directed() } else { Graph::new() };
+        for id in path {
+            if !chain.nodes.contains_key(id) {
+                chain.add_node(self.nodes[id].clone());
+            }
+        }
+        for edge in edges {
+            chain.insert_edge(edge.clone())?;
+        }
+        Ok(chain)
     }
 
-    #[test]
-    fn test_neighbors() {
-        let mut graph = Graph::new();
-        
-        let n0 = graph.add_node_simple("A");
-        let n1 = graph.add_node_simple("B");
-        let n2 = graph.add_node_simple("C");
+    /// Sort every adjacency list by neighbor ID and keep them sorted
+    ///
+    /// Afterwards `neighbors` and everything built on it, such as DFS
+    /// paths, random walks and label propagation, visit neighbors in ID
+    /// order rather than in the order edges were added, so results no
+    /// longer depend on how the graph was built or loaded. Parallel edges
+    /// stay in the order they were added. Adding an edge then costs
+    /// O(degree) instead of O(1), while `has_edge` becomes a binary search
+    /// and similarity measures skip sorting neighbor lists. The setting is
+    /// kept by clones and by serialization, but not by graphs derived from
+    /// this one, such as subgraphs.
+    pub fn sort_adjacency(&mut self) {
+        self.touch();
+        self.sorted_adjacency = true;
+        for neighbors in Arc::make_mut(&mut self.adjacency).values_mut() {
+            neighbors.sort_unstable();
+        }
+    }
 
-        graph.add_edge(n0, n1, 1.0).unwrap();
-        graph.add_edge(n0, n2, 2.0).unwrap();
+    /// Whether adjacency lists are kept sorted, see `sort_adjacency`
+    pub fn has_sorted_adjacency(&self) -> bool {
+        self.sorted_adjacency
+    }
 
-        let neighbors = graph.neighbors(n0).unwrap();
-        assert_eq!(neighbors.len(), 2);
-        assert!(neighbors.contains(&n1));
-        assert!(neighbors.contains(&n2));
+    /// Whether every edge has the same weight, so that hop-optimal paths
+    /// are also weight-optimal (given a non-negative weight)
+    ///
+    /// True for graphs without edges. Maintained as edges are added, so
+    /// this is O(1).
+    pub fn has_uniform_weights(&self) -> bool {
+        self.uniform_weights
+    }
+
+    /// Token identifying the current state of the graph
+    ///
+    /// Every mutation assigns a new token, and tokens are unique across all
+    /// graphs, so data derived from a graph (indexes, cached results) is
+    /// still valid exactly when the token it was built at matches. Clones
+    /// share the token of the original until either is mutated.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Start a new generation, dropping results memoized for the old one
+    fn touch(&mut self) {
+        self.generation = next_generation();
+        self.analysis = Arc::default();
+    }
+
+    /// The memo of analysis results for the current generation, shared
+    /// with clones that have not been mutated since
+    pub fn analysis_cache(&self) -> &AnalysisCache {
+        &self.analysis
+    }
+
+    /// `GraphStats::from_graph`, computed once per generation
+    pub fn cached_stats(&self) -> GraphStats {
+        self.analysis.stats(self)
+    }
+
+    /// `centrality::pagerank` with `params`, computed once per generation
+    pub fn cached_pagerank(&self, params: PageRankParams) -> Result<Arc<HashMap<NodeId, f64>>> {
+        self.analysis.pagerank(self, params)
+    }
+
+    /// `algorithms::core_numbers`, computed once per generation
+    pub fn cached_core_numbers(&self) -> Result<Arc<HashMap<NodeId, usize>>> {
+        self.analysis.core_numbers(self)
+    }
+
+    /// `algorithms::connected_components`, computed once per generation
+    pub fn cached_components(&self) -> Result<Arc<Vec<Vec<NodeId>>>> {
+        self.analysis.components(self)
+    }
+
+    /// Hash of the graph's content, independent of insertion order
+    ///
+    /// Covers directedness, every node (ID, label and properties) and
+    /// every edge (endpoints, weight and type); parallel edges count with
+    /// their multiplicity and node features are not included. Maintained
+    /// as the graph is mutated, so this is O(1), and stable across
+    /// processes and releases, so it can be stored alongside data derived
+    /// from the graph and compared after loading both.
+    pub fn content_hash(&self) -> u64 {
+        let mut hash = Fnv::new();
+        hash.write_u64(u64::from(self.is_directed));
+        hash.write_u64(self.content_sum);
+        hash.finish()
+    }
+
+    /// Like `content_hash`, but only over node IDs and edge endpoints
+    pub fn structural_hash(&self) -> u64 {
+        self.finish_structural_hash(self.structure_sum)
+    }
+
+    /// `structural_hash` of this graph without the nodes `nodes` and one
+    /// edge for each pair in `edges`
+    ///
+    /// Comparing it to a hash stored earlier tells whether adding these
+    /// nodes and edges was the only structural change since. Nodes and
+    /// edges are not checked to be in the graph.
+    pub fn structural_hash_without(&self, nodes: &[NodeId], edges: &[(NodeId, NodeId)]) -> u64 {
+        let mut sum = self.structure_sum;
+        for &id in nodes {
+            sum = sum.wrapping_sub(structural_node_hash(id));
+        }
+        for &(from, to) in edges {
+            let (_, structure) = edge_hashes(&Edge::new(from, to, 0.0), self.is_directed);
+            sum = sum.wrapping_sub(structure);
+        }
+        self.finish_structural_hash(sum)
+    }
+
+    fn finish_structural_hash(&self, structure_sum: u64) -> u64 {
+        let mut hash = Fnv::new();
+        hash.write_u64(u64::from(self.is_directed));
+        hash.write_u64(structure_sum);
+        hash.finish()
+    }
+
+    /// Whether `other` is isomorphic to this graph
+    ///
+    /// Uses VF2 after checking that the degree sequences match. A directed
+    /// graph is never isomorphic to an undirected one. Only graphs with at
+    /// most `algorithms::MAX_ISOMORPHISM_NODES` nodes are supported: for
+    /// larger ones this returns `false`, even if the graphs are in fact
+    /// isomorphic.
+    pub fn is_isomorphic_to(&self, other: &Graph) -> bool {
+        self.find_isomorphism(other).is_some()
+    }
+
+    /// A mapping from this graph's node IDs to `other`'s under which the
+    /// graphs are identical, if one exists
+    ///
+    /// Subject to the same limitations as `is_isomorphic_to`.
+    pub fn find_isomorphism(&self, other: &Graph) -> Option<HashMap<NodeId, NodeId>> {
+        crate::algorithms::vf2_isomorphism(self, other)
+    }
+
+    /// Whether removing the edges between `from` and `to` disconnects them
+    ///
+    /// `false` when the nodes are not adjacent. Runs
+    /// `algorithms::find_bridges` over the whole graph, so fails on
+    /// directed graphs.
+    pub fn is_bridge(&self, from: NodeId, to: NodeId) -> Result<bool> {
+        self.node(from)?;
+        self.node(to)?;
+        let edge = (from.min(to), from.max(to));
+        Ok(crate::algorithms::find_bridges(self)?.binary_search(&edge).is_ok())
+    }
+
+    /// Whether removing `node` disconnects some of the other nodes
+    ///
+    /// Runs `algorithms::find_articulation_points` over the whole graph,
+    /// so fails on directed graphs.
+    pub fn is_articulation_point(&self, node: NodeId) -> Result<bool> {
+        self.node(node)?;
+        let points = crate::algorithms::find_articulation_points(self)?;
+        Ok(points.binary_search(&node).is_ok())
+    }
+
+    /// Summarize numeric node property `key`, see `stats::node_property_stats`
+    pub fn node_property_statistics(&self, key: &str) -> Result<crate::stats::PropertyStats> {
+        crate::stats::node_property_stats(self, key)
+    }
+
+    /// An undirected copy of the graph with the same nodes
+    ///
+    /// Each pair of nodes joined by edges in either direction is joined by
+    /// one undirected edge, keeping the weight and type of the first such
+    /// edge; self-loops are kept once. Undirected graphs are copied as is.
+    pub fn to_undirected(&self) -> Graph {
+        if !self.is_directed {
+            return self.clone();
+        }
+
+        let mut undirected = Graph::with_capacity(self.nodes.len(), self.edges.len());
+        let mut node_ids: Vec<NodeId> = self.nodes.keys().copied().collect();
+        node_ids.sort_unstable();
+        for id in node_ids {
+            undirected.add_node(self.nodes[&id].clone());
+        }
+
+        let mut joined = HashSet::new();
+        for edge in self.edges.iter() {
+            if joined.insert((edge.from.min(edge.to), edge.from.max(edge.to))) {
+                undirected
+                    .insert_edge(edge.clone())
+                    .expect("both endpoints were copied");
+            }
+        }
+        undirected
+    }
+
+    /// A copy of the graph with every edge weight drawn anew from
+    /// `distribution`, for null models that keep the topology
+    ///
+    /// Nodes, edge endpoints, types and timestamps are kept; weights are
+    /// drawn independently, in edge order, so a `seed` makes the result
+    /// reproducible. `WeightDistribution::Original` instead shuffles the
+    /// existing weights among the edges.
+    ///
+    /// # Panics
+    ///
+    /// If the parameters describe no distribution: `min > max`, a negative
+    /// or non-finite `std`, or a `rate` that is not positive.
+    pub fn randomize_edge_weights(
+        &self,
+        distribution: WeightDistribution,
+        seed: Option<u64>,
+    ) -> Graph {
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        // `1 - u` lies in (0, 1], so its logarithm is finite
+        let open_unit = |rng: &mut StdRng| 1.0 - rng.gen::<f64>();
+        let weights: Vec<Weight> = match distribution {
+            WeightDistribution::Uniform { min, max } => {
+                assert!(min <= max, "uniform weights need min <= max, got {} > {}", min, max);
+                self.edges.iter().map(|_| min + (max - min) * rng.gen::<f64>()).collect()
+            }
+            WeightDistribution::Gaussian { mean, std } => {
+                let valid = std.is_finite() && std >= 0.0;
+                assert!(valid, "gaussian weights need std >= 0, got {}", std);
+                // Box-Muller
+                self.edges
+                    .iter()
+                    .map(|_| {
+                        let radius = (-2.0 * open_unit(&mut rng).ln()).sqrt();
+                        let angle = 2.0 * std::f64::consts::PI * open_unit(&mut rng);
+                        mean + std * radius * angle.cos()
+                    })
+                    .collect()
+            }
+            WeightDistribution::Exponential { rate } => {
+                assert!(rate > 0.0, "exponential weights need rate > 0, got {}", rate);
+                self.edges.iter().map(|_| -open_unit(&mut rng).ln() / rate).collect()
+            }
+            WeightDistribution::Original => {
+                let mut weights: Vec<Weight> = self.edges.iter().map(|edge| edge.weight).collect();
+                weights.shuffle(&mut rng);
+                weights
+            }
+        };
+
+        let edges = self
+            .edges
+            .iter()
+            .zip(weights)
+            .map(|(edge, weight)| Edge { weight, ..edge.clone() })
+            .collect();
+        let mut randomized = self.clone();
+        randomized.replace_edges(edges).expect("edges join existing nodes");
+        randomized
+    }
+
+    /// Node-edge incidence matrix `B`, with the node and edge order used
+    ///
+    /// Rows follow ascending node ID order and columns follow edge index
+    /// order (see `edges`). `B[i][e]` is `+1` if node `i` is the source of
+    /// edge `e` and `-1` if it is the target; undirected edges are oriented
+    /// from their lower-ID endpoint. Self-loop columns are all zero.
+    pub fn to_incidence_matrix(&self) -> (Vec<Vec<f64>>, Vec<NodeId>, Vec<usize>) {
+        let mut node_ids = self.node_ids();
+        node_ids.sort_unstable();
+        let positions: HashMap<NodeId, usize> =
+            node_ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+
+        let mut matrix = vec![vec![0.0; self.edges.len()]; node_ids.len()];
+        for (e, edge) in self.edges.iter().enumerate() {
+            if edge.from == edge.to {
+                continue;
+            }
+            let (source, target) = if self.is_directed {
+                (edge.from, edge.to)
+            } else {
+                (edge.from.min(edge.to), edge.from.max(edge.to))
+            };
+            matrix[positions[&source]][e] = 1.0;
+            matrix[positions[&target]][e] = -1.0;
+        }
+
+        (matrix, node_ids, (0..self.edges.len()).collect())
+    }
+
+    /// Check that `B · W · Bᵀ` equals the Laplacian from
+    /// `sparse::to_laplacian_sparse`, where `B` is the incidence matrix and
+    /// `W` the diagonal matrix of edge weights
+    ///
+    /// With unit weights this is the identity `L = B · Bᵀ`. It holds for
+    /// undirected graphs, so directed graphs, whose Laplacian uses
+    /// out-degrees and is not symmetric, return `false` unless they have
+    /// no edges between distinct nodes.
+    pub fn verify_laplacian_identity(&self) -> bool {
+        let (incidence, _, _) = self.to_incidence_matrix();
+        let n = incidence.len();
+
+        let mut laplacian = vec![vec![0.0; n]; n];
+        let (col_indices, row_pointers, values) = crate::sparse::to_laplacian_sparse(self);
+        for (i, row) in laplacian.iter_mut().enumerate() {
+            for k in row_pointers[i]..row_pointers[i + 1] {
+                row[col_indices[k]] = values[k];
+            }
+        }
+
+        for i in 0..n {
+            for j in 0..n {
+                let product: f64 = self
+                    .edges
+                    .iter()
+                    .enumerate()
+                    .map(|(e, edge)| incidence[i][e] * edge.weight * incidence[j][e])
+                    .sum();
+                if (product - laplacian[i][j]).abs() > 1e-9 {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Check the internal consistency of the graph
+    ///
+    /// Returns a description of every violation found, so a consistent
+    /// graph yields an empty list. Checks that edges and adjacency entries
+    /// only reference existing nodes and edges, that each adjacency entry
+    /// matches the endpoints of its edge and appears once, that adjacency
+    /// lists are sorted if `sort_adjacency` was called, and that
+    /// `next_node_id` is above every node ID. Meant for tests and debug
+    /// builds, see `debug_assert_invariants!`; it is linear in the size of
+    /// the graph.
+    pub fn check_invariants(&self) -> Result<Vec<String>> {
+        let mut violations = Vec::new();
+
+        let mut node_ids: Vec<NodeId> = self.nodes.keys().copied().collect();
+        node_ids.sort_unstable();
+
+        for &id in &node_ids {
+            if self.nodes[&id].id != id {
+                violations.push(format!(
+                    "node stored under ID {} has ID {}",
+                    id, self.nodes[&id].id
+                ));
+            }
+            if !self.adjacency.contains_key(&id) {
+                violations.push(format!("node {} has no adjacency entry", id));
+            }
+        }
+        if let Some(&max_id) = node_ids.last() {
+            if self.next_node_id <= max_id {
+                violations.push(format!(
+                    "next_node_id {} is not above the maximum node ID {}",
+                    self.next_node_id, max_id
+                ));
+            }
+        }
+
+        if self.uniform_weights {
+            if let Some(first) = self.edges.first() {
+                if let Some(index) = self.edges.iter().position(|e| e.weight != first.weight) {
+                    violations.push(format!(
+                        "uniform_weights is set but edge {} has weight {} instead of {}",
+                        index, self.edges[index].weight, first.weight
+                    ));
+                }
+            }
+        }
+
+        for (index, edge) in self.edges.iter().enumerate() {
+            for endpoint in [edge.from, edge.to] {
+                if !self.nodes.contains_key(&endpoint) {
+                    violations.push(format!(
+                        "edge {} references missing node {}",
+                        index, endpoint
+                    ));
+                }
+            }
+        }
+
+        let mut sources: Vec<NodeId> = self.adjacency.keys().copied().collect();
+        sources.sort_unstable();
+        for source in sources {
+            if !self.nodes.contains_key(&source) {
+                violations.push(format!("adjacency entry for missing node {}", source));
+            }
+
+            let adjacent = &self.adjacency[&source];
+            if self.sorted_adjacency && !adjacent.is_sorted() {
+                violations.push(format!("adjacency list of {} is not sorted", source));
+            }
+
+            let mut seen = HashSet::new();
+            for &(neighbor, edge_idx) in adjacent {
+                if !seen.insert((neighbor, edge_idx)) {
+                    violations.push(format!(
+                        "duplicate adjacency entry {} -> {} for edge {}",
+                        source, neighbor, edge_idx
+                    ));
+                }
+                if !self.nodes.contains_key(&neighbor) {
+                    violations.push(format!(
+                        "adjacency entry {} -> {} references missing node {}",
+                        source, neighbor, neighbor
+                    ));
+                }
+
+                let Some(edge) = self.edges.get(edge_idx) else {
+                    violations.push(format!(
+                        "adjacency entry {} -> {} references edge {} out of bounds ({} edges)",
+                        source,
+                        neighbor,
+                        edge_idx,
+                        self.edges.len()
+                    ));
+                    continue;
+                };
+                let forward = edge.from == source && edge.to == neighbor;
+                let backward = !self.is_directed && edge.to == source && edge.from == neighbor;
+                if !forward && !backward {
+                    violations.push(format!(
+                        "adjacency entry {} -> {} does not match edge {} ({} -> {})",
+                        source, neighbor, edge_idx, edge.from, edge.to
+                    ));
+                }
+            }
+        }
+
+        Ok(violations)
+    }
+
+    /// Subgraph induced by the nodes within `radius` hops of either
+    /// endpoint of the edge `from -> to`
+    ///
+    /// Nodes keep their IDs, labels and properties, and edges their
+    /// weights and types. Hops follow out-edges on directed graphs.
+    /// `radius = 0` yields just the endpoints and the edges between them.
+    /// Fails with `EdgeNotFound` if there is no such edge.
+    pub fn subgraph_around_edge(&self, from: NodeId, to: NodeId, radius: usize) -> Result<Graph> {
+        self.node(from)?;
+        self.node(to)?;
+        if !self.neighbors(from)?.contains(&to) {
+            return Err(GraphError::EdgeNotFound(from, to));
+        }
+
+        Ok(self.induced_subgraph(&self.within_hops(&[from, to], radius)))
+    }
+
+    /// Subgraph induced by the nodes within `radius` hops of `center`
+    ///
+    /// This is `center`'s ego network: the center, everything within
+    /// `radius` hops, and every edge among them, including edges between
+    /// neighbors. Nodes keep their IDs (so the center keeps its own),
+    /// labels and properties, and edges their weights and types. Hops
+    /// follow out-edges on directed graphs. Fails with `NodeNotFound` for
+    /// an unknown center.
+    pub fn ego_network(&self, center: NodeId, radius: usize) -> Result<Graph> {
+        self.node(center)?;
+        Ok(self.induced_subgraph(&self.within_hops(&[center], radius)))
+    }
+
+    /// Nodes within `radius` hops of any of `sources`, in ascending order
+    fn within_hops(&self, sources: &[NodeId], radius: usize) -> Vec<NodeId> {
+        let mut depth: HashMap<NodeId, usize> =
+            sources.iter().map(|&source| (source, 0)).collect();
+        let mut queue: VecDeque<NodeId> = sources.iter().copied().collect();
+        while let Some(node) = queue.pop_front() {
+            let next = depth[&node] + 1;
+            if next > radius {
+                continue;
+            }
+            for &(neighbor, _) in &self.adjacency[&node] {
+                if let Entry::Vacant(entry) = depth.entry(neighbor) {
+                    entry.insert(next);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+
+        let mut kept: Vec<NodeId> = depth.into_keys().collect();
+        kept.sort_unstable();
+        kept
+    }
+
+    /// Subgraph of `nodes`, which must exist, and every edge among them
+    fn induced_subgraph(&self, nodes: &[NodeId]) -> Graph {
+        let mut subgraph = if self.is_directed { Graph::new_directed() } else { Graph::new() };
+        for id in nodes {
+            subgraph.add_node(self.nodes[id].clone());
+        }
+        for edge in self.edges.iter() {
+            if subgraph.nodes.contains_key(&edge.from) && subgraph.nodes.contains_key(&edge.to) {
+                subgraph.insert_edge(edge.clone()).expect("endpoints were added");
+            }
+        }
+        subgraph
+    }
+
+    /// Subgraph of the edges of type `edge_type` and their endpoints
+    ///
+    /// Nodes keep their IDs, labels and properties; nodes without an edge
+    /// of that type are left out.
+    pub fn subgraph_by_edge_type(&self, edge_type: &str) -> Graph {
+        let mut subgraph = if self.is_directed { Graph::new_directed() } else { Graph::new() };
+        let edges: Vec<&Edge> =
+            self.edges.iter().filter(|edge| edge.edge_type == edge_type).collect();
+        let mut kept: Vec<NodeId> = edges.iter().flat_map(|edge| [edge.from, edge.to]).collect();
+        kept.sort_unstable();
+        kept.dedup();
+        for id in kept {
+            subgraph.add_node(self.nodes[&id].clone());
+        }
+        for edge in edges {
+            subgraph.insert_edge(edge.clone()).expect("endpoints were added");
+        }
+        subgraph
+    }
+
+    /// The graph restricted to edges with a timestamp in `[from, to]`
+    ///
+    /// Every node is kept, with its ID; edges without a timestamp are
+    /// left out. Nodes are shared with `self` until either is mutated, so
+    /// the cost is that of re-adding the kept edges. Use `f64::NEG_INFINITY`
+    /// as `from` for the graph as of time `to`.
+    pub fn time_window_view(&self, from: f64, to: f64) -> Graph {
+        let kept = self
+            .edges
+            .iter()
+            .filter(|edge| edge.timestamp.is_some_and(|t| from <= t && t <= to))
+            .cloned()
+            .collect();
+        let mut view = self.clone();
+        view.replace_edges(kept).expect("kept edges join existing nodes");
+        view
+    }
+
+    /// Apply `ops` in order, either all of them or, on error, none
+    ///
+    /// The whole batch is validated against the graph and the ops before
+    /// it first, so the error is that of the first invalid op and the
+    /// graph is left exactly as it was: `AddNode` fails like
+    /// `add_node_with_id`, ops naming an unknown node with `NodeNotFound`,
+    /// and `RemoveEdge` or `SetWeight` with `EdgeNotFound` if no edge
+    /// joins the nodes. The generation changes once for the whole batch.
+    ///
+    /// Added edges are appended in batch order. Removing or reweighting
+    /// edges that were in the graph before the batch rebuilds the edge
+    /// list, preserving the order of the remaining edges, which is O(E).
+    pub fn apply_batch(&mut self, ops: &[GraphOp]) -> Result<BatchReceipt> {
+        let mut added_nodes = HashSet::new();
+        // Changes to the edges already in the graph, by index
+        let mut removed = HashSet::new();
+        let mut reweighted = HashMap::new();
+        // Edges added by the batch, `None` once removed again, and their
+        // indices in `staged` by endpoints
+        let mut staged: Vec<Option<Edge>> = Vec::new();
+        let mut staged_by_key: HashMap<(NodeId, NodeId), Vec<usize>> = HashMap::new();
+
+        let has_node = |added: &HashSet<NodeId>, id: NodeId| {
+            if self.nodes.contains_key(&id) || added.contains(&id) {
+                Ok(())
+            } else {
+                Err(GraphError::NodeNotFound(id))
+            }
+        };
+        for op in ops {
+            match op {
+                GraphOp::AddNode(node) => {
+                    if node.id == MAX_NODE_ID {
+                        return Err(GraphError::InvalidParameter(format!(
+                            "Node ID {} is reserved",
+                            node.id
+                        )));
+                    }
+                    if self.nodes.contains_key(&node.id) || !added_nodes.insert(node.id) {
+                        return Err(GraphError::InvalidParameter(format!(
+                            "Node {} already exists",
+                            node.id
+                        )));
+                    }
+                }
+                GraphOp::AddEdge(edge) => {
+                    has_node(&added_nodes, edge.from)?;
+                    has_node(&added_nodes, edge.to)?;
+                    staged_by_key.entry(self.endpoint_key(edge)).or_default().push(staged.len());
+                    staged.push(Some(edge.clone()));
+                }
+                &GraphOp::RemoveEdge { from, to } | &GraphOp::SetWeight { from, to, .. } => {
+                    has_node(&added_nodes, from)?;
+                    has_node(&added_nodes, to)?;
+                    let weight = match op {
+                        GraphOp::SetWeight { weight, .. } => Some(*weight),
+                        _ => None,
+                    };
+                    let mut found = false;
+                    for &(neighbor, edge_idx) in self.adjacency.get(&from).into_iter().flatten() {
+                        if neighbor != to || removed.contains(&edge_idx) {
+                            continue;
+                        }
+                        found = true;
+                        match weight {
+                            Some(weight) => {
+                                reweighted.insert(edge_idx, weight);
+                            }
+                            None => {
+                                removed.insert(edge_idx);
+                            }
+                        }
+                    }
+                    let key = self.endpoint_key(&Edge::new(from, to, 0.0));
+                    for &index in staged_by_key.get(&key).into_iter().flatten() {
+                        let Some(edge) = &mut staged[index] else {
+                            continue;
+                        };
+                        found = true;
+                        match weight {
+                            Some(weight) => edge.weight = weight,
+                            None => staged[index] = None,
+                        }
+                    }
+                    if !found {
+                        return Err(GraphError::EdgeNotFound(from, to));
+                    }
+                }
+                GraphOp::SetProperty { node, .. } => has_node(&added_nodes, *node)?,
+            }
+        }
+
+        // Nothing below can fail
+        let mut receipt = BatchReceipt { removed_edges: removed.len(), ..Default::default() };
+        if ops.is_empty() {
+            return Ok(receipt);
+        }
+        for op in ops {
+            match op {
+                GraphOp::AddNode(node) => receipt.nodes.push(self.put_node(node.clone())),
+                GraphOp::SetProperty { node, key, value } => self.edit_node(*node, |node| {
+                    node.properties.insert(key.clone(), *value);
+                }),
+                _ => {}
+            }
+        }
+        if !removed.is_empty() || !reweighted.is_empty() {
+            let kept: Vec<Edge> = self
+                .edges
+                .iter()
+                .enumerate()
+                .filter(|(edge_idx, _)| !removed.contains(edge_idx))
+                .map(|(edge_idx, edge)| {
+                    let mut edge = edge.clone();
+                    if let Some(&weight) = reweighted.get(&edge_idx) {
+                        edge.weight = weight;
+                    }
+                    edge
+                })
+                .collect();
+            self.unlink_edges();
+            for edge in kept {
+                self.push_edge(edge);
+            }
+        }
+        for edge in staged.into_iter().flatten() {
+            receipt.edges.push(self.push_edge(edge));
+        }
+        self.touch();
+        debug_assert_invariants!(self);
+        Ok(receipt)
+    }
+
+    /// Clear all nodes and edges
+    pub fn clear(&mut self) {
+        self.touch();
+        self.nodes = Arc::default();
+        self.adjacency = Arc::default();
+        self.edges = Arc::default();
+        self.next_node_id = node_id(0);
+        self.uniform_weights = true;
+        self.content_sum = 0;
+        self.structure_sum = 0;
+    }
+}
+
+impl Default for Graph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Graph {
+    /// An immutable view of the graph as it is now
+    ///
+    /// O(1): the snapshot shares the graph's internals, and the graph copies
+    /// whichever of its node map, adjacency lists and edge list it next
+    /// mutates. Snapshots can be sent to other threads and cloned cheaply.
+    pub fn snapshot(&self) -> GraphSnapshot {
+        GraphSnapshot { graph: self.clone() }
+    }
+}
+
+/// A read-only graph taken with `Graph::snapshot`
+///
+/// Dereferences to `Graph`, so the whole read API is available and it can
+/// be passed to any algorithm taking a `&Graph`. `into_graph` turns it
+/// back into a mutable graph, again without copying.
+#[derive(Debug, Clone)]
+pub struct GraphSnapshot {
+    graph: Graph,
+}
+
+impl GraphSnapshot {
+    /// A mutable graph starting from this snapshot
+    pub fn into_graph(self) -> Graph {
+        self.graph
+    }
+}
+
+impl Deref for GraphSnapshot {
+    type Target = Graph;
+
+    fn deref(&self) -> &Graph {
+        &self.graph
+    }
+}
+
+#[cfg(all(test, not(feature = "uuid-ids")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_graph_creation() {
+        let graph = Graph::new();
+        assert_eq!(graph.node_count(), 0);
+        assert_eq!(graph.edge_count(), 0);
+    }
+
+    #[test]
+    fn test_add_nodes_and_edges() {
+        let mut graph = Graph::new();
+        
+        let n0 = graph.add_node_simple("A");
+        let n1 = graph.add_node_simple("B");
+        let n2 = graph.add_node_simple("C");
+
+        graph.add_edge(n0, n1, 1.0).unwrap();
+        graph.add_edge(n1, n2, 2.0).unwrap();
+
+        assert_eq!(graph.node_count(), 3);
+        assert_eq!(graph.edge_count(), 2);
+    }
+
+    #[test]
+    fn test_neighbors() {
+        let mut graph = Graph::new();
+        
+        let n0 = graph.add_node_simple("A");
+        let n1 = graph.add_node_simple("B");
+        let n2 = graph.add_node_simple("C");
+
+        graph.add_edge(n0, n1, 1.0).unwrap();
+        graph.add_edge(n0, n2, 2.0).unwrap();
+
+        let neighbors = graph.neighbors(n0).unwrap();
+        assert_eq!(neighbors.len(), 2);
+        assert!(neighbors.contains(&n1));
+        assert!(neighbors.contains(&n2));
+    }
+
+    #[test]
+    fn test_generation_changes_on_mutation() {
+        let mut graph = Graph::new();
+        let other = Graph::new();
+        assert_ne!(graph.generation(), other.generation());
+
+        let before = graph.generation();
+        let n0 = graph.add_node_simple("A");
+        let n1 = graph.add_node_simple("B");
+        assert_ne!(graph.generation(), before);
+
+        let snapshot = graph.clone();
+        assert_eq!(snapshot.generation(), graph.generation());
+
+        graph.add_edge(n0, n1, 1.0).unwrap();
+        assert_ne!(snapshot.generation(), graph.generation());
+
+        // A rejected edge leaves the graph and its token unchanged
+        let current = graph.generation();
+        assert!(graph.add_edge(n0, 99, 1.0).is_err());
+        assert_eq!(graph.generation(), current);
+    }
+
+    #[test]
+    fn test_node_identity() {
+        let a = Node::new(1, "A");
+        let renamed = Node::new(1, "B").with_property("weight", 2.0);
+        let b = Node::new(2, "A");
+
+        let set: HashSet<Node> = [a.clone(), renamed, b].into_iter().collect();
+        assert_eq!(set.len(), 2);
+
+        assert_eq!(NodeId::from(&a), 1);
+        assert_eq!(NodeId::from(a), 1);
+    }
+
+    #[test]
+    fn test_node_set() {
+        let mut graph = Graph::new();
+        for i in 0..4 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        let set = graph.node_set();
+        assert_eq!(set.len(), graph.node_count());
+        assert!(set.contains(&3));
+    }
+
+    #[test]
+    fn test_neighbors_of_set() {
+        let mut graph = Graph::new();
+        for i in 0..5 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        // Clique on 0, 1, 2 with a tail 2 - 3 - 4
+        graph.add_edge(0, 1, 1.0).unwrap();
+        graph.add_edge(0, 2, 1.0).unwrap();
+        graph.add_edge(1, 2, 1.0).unwrap();
+        graph.add_edge(2, 3, 1.0).unwrap();
+        graph.add_edge(3, 4, 1.0).unwrap();
+
+        let single = graph.neighbors_of_set(&[2]).unwrap();
+        let neighbors: HashSet<NodeId> = graph.neighbors(2).unwrap().into_iter().collect();
+        assert_eq!(single, neighbors);
+
+        assert_eq!(graph.neighbors_of_set(&[0, 1, 2]).unwrap(), HashSet::from([3]));
+        assert!(graph.neighbors_of_set(&graph.node_ids()).unwrap().is_empty());
+
+        assert!(graph.closed_neighborhood(&[0, 99]).is_err());
+
+        let mut clique = Graph::new();
+        for i in 0..4 {
+            clique.add_node_simple(format!("Node{}", i));
+        }
+        for i in 0..4 {
+            for j in (i + 1)..4 {
+                clique.add_edge(i, j, 1.0).unwrap();
+            }
+        }
+        let members = clique.node_ids();
+        assert_eq!(clique.closed_neighborhood(&members).unwrap(), clique.node_set());
+    }
+
+    fn consistent_graph() -> Graph {
+        let mut graph = Graph::new();
+        for i in 0..3 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        graph.add_edge(0, 1, 1.0).unwrap();
+        graph.add_edge(1, 2, 1.0).unwrap();
+        graph
+    }
+
+    fn single_violation(graph: &Graph) -> String {
+        let violations = graph.check_invariants().unwrap();
+        assert_eq!(violations.len(), 1, "{:?}", violations);
+        violations[0].clone()
+    }
+
+    #[test]
+    fn test_check_invariants_consistent() {
+        let graph = consistent_graph();
+        assert!(graph.check_invariants().unwrap().is_empty());
+        debug_assert_invariants!(graph);
+        assert!(Graph::new().check_invariants().unwrap().is_empty());
+
+        let mut graph = Graph::new();
+        graph.add_node_simple("A");
+        graph.add_edge(0, 0, 1.0).unwrap();
+        assert!(graph.check_invariants().unwrap().is_empty());
+        assert_eq!(graph.neighbors(0).unwrap(), vec![0]);
+    }
+
+    #[test]
+    fn test_check_invariants_violations() {
+        let mut graph = consistent_graph();
+        Arc::make_mut(&mut graph.edges).push(Edge::new(0, 7, 1.0));
+        assert_eq!(single_violation(&graph), "edge 2 references missing node 7");
+
+        let mut graph = consistent_graph();
+        Arc::make_mut(&mut graph.adjacency).insert(9, Vec::new());
+        assert_eq!(single_violation(&graph), "adjacency entry for missing node 9");
+
+        let mut graph = consistent_graph();
+        Arc::make_mut(&mut graph.adjacency).get_mut(&0).unwrap().push((2, 5));
+        assert_eq!(
+            single_violation(&graph),
+            "adjacency entry 0 -> 2 references edge 5 out of bounds (2 edges)"
+        );
+
+        let mut graph = consistent_graph();
+        graph.next_node_id = 2;
+        assert_eq!(
+            single_violation(&graph),
+            "next_node_id 2 is not above the maximum node ID 2"
+        );
+
+        let mut graph = consistent_graph();
+        Arc::make_mut(&mut graph.adjacency).get_mut(&1).unwrap().push((2, 1));
+        assert_eq!(
+            single_violation(&graph),
+            "duplicate adjacency entry 1 -> 2 for edge 1"
+        );
+
+        let mut graph = consistent_graph();
+        Arc::make_mut(&mut graph.adjacency).get_mut(&0).unwrap().push((2, 0));
+        assert_eq!(
+            single_violation(&graph),
+            "adjacency entry 0 -> 2 does not match edge 0 (0 -> 1)"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "graph invariants violated")]
+    #[cfg(debug_assertions)]
+    fn test_debug_assert_invariants_panics() {
+        let mut graph = consistent_graph();
+        Arc::make_mut(&mut graph.nodes).remove(&2);
+        debug_assert_invariants!(graph);
+    }
+
+    fn cycle(ids: &[NodeId]) -> Graph {
+        let mut graph = Graph::new();
+        for &id in ids {
+            graph.add_node(Node::new(id, format!("Node{}", id)));
+        }
+        for (i, &id) in ids.iter().enumerate() {
+            graph.add_edge(id, ids[(i + 1) % ids.len()], 1.0).unwrap();
+        }
+        graph
+    }
+
+    #[test]
+    fn test_isomorphic_to_self_and_relabeled_copy() {
+        // A 5-cycle with a chord and a pendant node
+        let mut graph = cycle(&[0, 1, 2, 3, 4]);
+        graph.add_edge(0, 2, 1.0).unwrap();
+        let pendant = graph.add_node_simple("Pendant");
+        graph.add_edge(pendant, 3, 1.0).unwrap();
+        assert!(graph.is_isomorphic_to(&graph));
+
+        let relabel = |id: NodeId| (id * 7 + 3) % 11 + 100;
+        let mut copy = Graph::new();
+        for id in [5, 3, 1, 0, 4, 2] {
+            copy.add_node(Node::new(relabel(id), "Copy"));
+        }
+        for edge in graph.edges() {
+            copy.add_edge(relabel(edge.to), relabel(edge.from), 2.0).unwrap();
+        }
+
+        let mapping = graph.find_isomorphism(&copy).unwrap();
+        assert_eq!(mapping.len(), graph.node_count());
+        let images: HashSet<NodeId> = mapping.values().copied().collect();
+        assert_eq!(images, copy.node_set());
+        for edge in graph.edges() {
+            let neighbors = copy.neighbors(mapping[&edge.from]).unwrap();
+            assert!(neighbors.contains(&mapping[&edge.to]));
+        }
+    }
+
+    #[test]
+    fn test_not_isomorphic() {
+        let path = {
+            let mut graph = Graph::new();
+            for i in 0..4 {
+                graph.add_node_simple(format!("Node{}", i));
+            }
+            for i in 0..3 {
+                graph.add_edge(i, i + 1, 1.0).unwrap();
+            }
+            graph
+        };
+        let mut star = Graph::new();
+        for i in 0..4 {
+            star.add_node_simple(format!("Node{}", i));
+        }
+        for i in 1..4 {
+            star.add_edge(0, i, 1.0).unwrap();
+        }
+        // Same size, different degree sequences
+        assert!(!path.is_isomorphic_to(&star));
+
+        // Both 2-regular on six nodes, but one is connected
+        let hexagon = cycle(&[0, 1, 2, 3, 4, 5]);
+        let mut triangles = cycle(&[0, 1, 2]);
+        for id in 3..6 {
+            triangles.add_node(Node::new(id, "Node"));
+        }
+        triangles.add_edge(3, 4, 1.0).unwrap();
+        triangles.add_edge(4, 5, 1.0).unwrap();
+        triangles.add_edge(5, 3, 1.0).unwrap();
+        assert!(!hexagon.is_isomorphic_to(&triangles));
+        assert!(hexagon.find_isomorphism(&triangles).is_none());
+
+        // Direction matters: a 3-cycle is not a path with a shortcut
+        let mut directed_cycle = Graph::new_directed();
+        let mut transitive = Graph::new_directed();
+        for i in 0..3 {
+            directed_cycle.add_node_simple(format!("Node{}", i));
+            transitive.add_node_simple(format!("Node{}", i));
+        }
+        for (from, to) in [(0, 1), (1, 2), (2, 0)] {
+            directed_cycle.add_edge(from, to, 1.0).unwrap();
+        }
+        for (from, to) in [(0, 1), (1, 2), (0, 2)] {
+            transitive.add_edge(from, to, 1.0).unwrap();
+        }
+        assert!(directed_cycle.is_isomorphic_to(&directed_cycle));
+        assert!(!directed_cycle.is_isomorphic_to(&transitive));
+        assert!(!directed_cycle.is_isomorphic_to(&cycle(&[0, 1, 2])));
+    }
+
+    #[test]
+    fn test_reachability() {
+        let mut graph = Graph::new_directed();
+        for i in 0..5 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        graph.add_edge(0, 1, 1.0).unwrap();
+        graph.add_edge(1, 2, 1.0).unwrap();
+        graph.add_edge(2, 0, 1.0).unwrap();
+        graph.add_edge(2, 3, 1.0).unwrap();
+
+        assert_eq!(graph.reachable_from(1), HashSet::from([0, 1, 2, 3]));
+        assert_eq!(graph.reachable_from(3), HashSet::from([3]));
+        assert!(graph.reachable_from(99).is_empty());
+
+        assert!(graph.is_reachable(0, 3));
+        assert!(!graph.is_reachable(3, 0));
+        assert!(!graph.is_reachable(0, 4));
+        assert!(graph.is_reachable(4, 4));
+        assert!(!graph.is_reachable(0, 99));
+    }
+
+    #[test]
+    fn test_uniform_weights() {
+        let mut graph = consistent_graph();
+        assert!(graph.has_uniform_weights());
+
+        graph.add_edge(0, 2, 3.0).unwrap();
+        assert!(!graph.has_uniform_weights());
+        assert!(graph.check_invariants().unwrap().is_empty());
+
+        graph.clear();
+        assert!(graph.has_uniform_weights());
+        assert!(Graph::new().has_uniform_weights());
+
+        let mut graph = consistent_graph();
+        Arc::make_mut(&mut graph.edges)[1].weight = 2.0;
+        assert_eq!(
+            single_violation(&graph),
+            "uniform_weights is set but edge 1 has weight 2 instead of 1"
+        );
+    }
+
+    #[test]
+    fn test_to_undirected() {
+        let mut graph = Graph::new_directed();
+        for i in 0..3 {
+            graph.add_node(Node::new(i, format!("Node{}", i)).with_property("rank", i as f64));
+        }
+        graph.add_edge(0, 1, 2.0).unwrap();
+        graph.add_edge(1, 0, 5.0).unwrap();
+        graph.add_edge(2, 1, 1.0).unwrap();
+
+        let undirected = graph.to_undirected();
+        assert!(!undirected.is_directed());
+        assert_eq!(undirected.node_count(), 3);
+        assert_eq!(undirected.edge_count(), 2);
+        assert_eq!(undirected.node(2).unwrap().properties["rank"], 2.0);
+        assert_eq!(undirected.neighbors_with_weights(0).unwrap(), vec![(1, 2.0)]);
+        assert!(undirected.neighbors(1).unwrap().contains(&2));
+        assert!(undirected.check_invariants().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_incidence_matrix() {
+        let mut graph = Graph::new();
+        for i in 0..3 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        graph.add_edge(1, 0, 1.0).unwrap();
+        graph.add_edge(1, 2, 1.0).unwrap();
+        graph.add_edge(2, 0, 1.0).unwrap();
+
+        let (matrix, node_ids, edge_ids) = graph.to_incidence_matrix();
+        assert_eq!((matrix.len(), matrix[0].len()), (3, 3));
+        assert_eq!(node_ids, vec![0, 1, 2]);
+        assert_eq!(edge_ids, vec![0, 1, 2]);
+        for e in 0..3 {
+            let column: Vec<f64> = matrix.iter().map(|row| row[e]).collect();
+            assert_eq!(column.iter().filter(|&&v| v == 1.0).count(), 1);
+            assert_eq!(column.iter().filter(|&&v| v == -1.0).count(), 1);
+        }
+        // Lower-ID endpoint is the source
+        assert_eq!((matrix[0][0], matrix[1][0]), (1.0, -1.0));
+
+        // B · Bᵀ has the degrees on the diagonal and -1 elsewhere
+        for i in 0..3 {
+            for j in 0..3 {
+                let product: f64 = (0..3).map(|e| matrix[i][e] * matrix[j][e]).sum();
+                assert_eq!(product, if i == j { 2.0 } else { -1.0 });
+            }
+        }
+        assert!(graph.verify_laplacian_identity());
+
+        graph.add_edge(0, 1, 2.5).unwrap();
+        assert!(graph.verify_laplacian_identity());
+    }
+
+    #[test]
+    fn test_incidence_matrix_directed() {
+        let mut graph = Graph::new_directed();
+        graph.add_node_simple("A");
+        graph.add_node_simple("B");
+        graph.add_edge(1, 0, 1.0).unwrap();
+        graph.add_edge(0, 0, 1.0).unwrap();
+
+        let (matrix, _, _) = graph.to_incidence_matrix();
+        assert_eq!(matrix, vec![vec![-1.0, 0.0], vec![1.0, 0.0]]);
+        assert!(!graph.verify_laplacian_identity());
+    }
+
+    #[test]
+    fn test_subgraph_around_edge() {
+        // Path 0-1-2-3-4-5 with a pendant 6 on node 2
+        let mut graph = Graph::new();
+        for i in 0..7 {
+            graph.add_node(Node::new(i, format!("Node{}", i)).with_property("rank", i as f64));
+        }
+        for (from, to) in [(0, 1), (1, 2), (2, 3), (3, 4), (4, 5), (2, 6)] {
+            graph.add_edge(from, to, from as f64 + 1.0).unwrap();
+        }
+
+        let edge = graph.subgraph_around_edge(2, 3, 0).unwrap();
+        assert_eq!(edge.node_count(), 2);
+        assert_eq!(edge.edge_count(), 1);
+        assert_eq!(edge.edges()[0].weight, 3.0);
+
+        let around = graph.subgraph_around_edge(2, 3, 1).unwrap();
+        let mut nodes = around.node_ids();
+        nodes.sort_unstable();
+        assert_eq!(nodes, vec![1, 2, 3, 4, 6]);
+        assert_eq!(around.edge_count(), 4);
+        assert_eq!(around.node(6).unwrap().properties["rank"], 6.0);
+        assert!(around.check_invariants().unwrap().is_empty());
+
+        assert_eq!(graph.subgraph_around_edge(2, 3, 3).unwrap().node_count(), 7);
+        assert!(matches!(
+            graph.subgraph_around_edge(0, 2, 1),
+            Err(GraphError::EdgeNotFound(0, 2))
+        ));
+        assert!(graph.subgraph_around_edge(0, 9, 1).is_err());
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let build = |directed: bool, edges: &[(NodeId, NodeId)]| {
+            let mut graph = if directed { Graph::new_directed() } else { Graph::new() };
+            graph.add_node_simple("A");
+            graph.add_node_simple("B");
+            for &(from, to) in edges {
+                graph.add_edge(from, to, 1.0).unwrap();
+            }
+            graph
+        };
+
+        // Undirected edges hash the same either way round
+        assert_eq!(build(false, &[(0, 1)]).content_hash(), build(false, &[(1, 0)]).content_hash());
+        assert_ne!(build(true, &[(0, 1)]).content_hash(), build(true, &[(1, 0)]).content_hash());
+        assert_ne!(build(true, &[(0, 1)]).content_hash(), build(false, &[(0, 1)]).content_hash());
+        // Parallel edges count with their multiplicity
+        assert_ne!(
+            build(false, &[(0, 1)]).structural_hash(),
+            build(false, &[(0, 1), (0, 1)]).structural_hash()
+        );
+
+        let mut typed = build(false, &[]);
+        typed.insert_edge(Edge::new(0, 1, 1.0).with_type("transfer")).unwrap();
+        assert_ne!(typed.content_hash(), build(false, &[(0, 1)]).content_hash());
+        assert_eq!(typed.structural_hash(), build(false, &[(0, 1)]).structural_hash());
+
+        let mut cleared = build(false, &[(0, 1)]);
+        cleared.clear();
+        assert_eq!(cleared.content_hash(), Graph::new().content_hash());
+    }
+
+    #[test]
+    fn test_edge_type_filtering() {
+        let mut graph = Graph::new_directed();
+        for name in ["alice", "bob", "carol", "dave", "eve"] {
+            graph.add_node_simple(name);
+        }
+        graph.add_typed_edge(0, 1, 1.0, "follows").unwrap();
+        graph.add_typed_edge(0, 2, 1.0, "follows").unwrap();
+        graph.add_typed_edge(0, 3, 1.0, "blocks").unwrap();
+        graph.add_typed_edge(3, 1, 1.0, "follows").unwrap();
+        graph.add_typed_edge(4, 0, 2.0, "blocks").unwrap();
+
+        assert_eq!(graph.neighbors_of_type(0, "follows").unwrap(), vec![1, 2]);
+        assert_eq!(graph.neighbors_of_type(0, "blocks").unwrap(), vec![3]);
+        assert!(graph.neighbors_of_type(1, "follows").unwrap().is_empty());
+        assert!(matches!(graph.neighbors_of_type(9, "follows"), Err(GraphError::NodeNotFound(9))));
+        assert_eq!(graph.neighbors_with_type(4).unwrap(), vec![(0, 2.0, "blocks".to_string())]);
+        assert_eq!(
+            graph.edge_types(),
+            HashSet::from(["follows".to_string(), "blocks".to_string()])
+        );
+
+        let follows = graph.subgraph_by_edge_type("follows");
+        assert_eq!((follows.node_count(), follows.edge_count()), (4, 3));
+        assert!(follows.is_directed());
+        assert_eq!(follows.node(3).unwrap().label, "dave");
+        let blocks = graph.subgraph_by_edge_type("blocks");
+        assert_eq!((blocks.node_count(), blocks.edge_count()), (3, 2));
+        assert_eq!(blocks.node_set(), HashSet::from([0, 3, 4]));
+        assert_eq!(graph.subgraph_by_edge_type("likes").node_count(), 0);
+    }
+
+    #[test]
+    fn test_snapshot_is_isolated_from_mutations() {
+        let mut graph = consistent_graph();
+        let before = graph.clone();
+        let snapshot = graph.snapshot();
+        assert!(Arc::ptr_eq(&snapshot.edges, &graph.edges));
+        assert!(Arc::ptr_eq(&snapshot.nodes, &graph.nodes));
+
+        graph.add_node_simple("D");
+        graph.add_edge(0, 3, 4.0).unwrap();
+        assert!(!Arc::ptr_eq(&snapshot.edges, &graph.edges));
+        assert_eq!(graph.edge_count(), snapshot.edge_count() + 1);
+
+        let reader = {
+            let snapshot = snapshot.clone();
+            std::thread::spawn(move || {
+                (snapshot.node_count(), crate::algorithms::bfs(&snapshot, 0, 2).unwrap())
+            })
+        };
+        graph.clear();
+        assert_eq!(reader.join().unwrap(), (3, vec![0, 1, 2]));
+
+        assert_eq!(snapshot.content_hash(), before.content_hash());
+        assert_eq!(snapshot.neighbors(0).unwrap(), before.neighbors(0).unwrap());
+        assert!(snapshot.check_invariants().unwrap().is_empty());
+
+        let mut thawed = snapshot.clone().into_graph();
+        thawed.add_node_simple("E");
+        assert_eq!(snapshot.node_count(), 3);
+        assert_eq!(thawed.node_count(), 4);
+    }
+
+    /// The friendships of `examples/social_network.rs`
+    fn social_network() -> Graph {
+        let mut graph = Graph::new();
+        for name in ["Alice", "Bob", "Charlie", "Diana", "Eve"] {
+            graph.add_node_simple(name);
+        }
+        for name in ["Frank", "Grace", "Henry", "Ivy", "Jack"] {
+            graph.add_node_simple(name);
+        }
+        for (a, b) in [(0, 1), (0, 2), (1, 2), (2, 3), (4, 5), (4, 6), (5, 6)] {
+            graph.add_edge(a, b, 1.0).unwrap();
+        }
+        for (a, b) in [(7, 8), (8, 9), (7, 9), (3, 4), (6, 7)] {
+            graph.add_edge(a, b, 1.0).unwrap();
+        }
+        graph
+    }
+
+    #[test]
+    fn test_ego_network() {
+        let mut graph = social_network();
+        graph.add_node(Node::new(1, "Bob").with_property("age", 30.0));
+        let edges = |graph: &Graph| -> Vec<(NodeId, NodeId)> {
+            let mut edges: Vec<_> = graph.edges().iter().map(|e| (e.from, e.to)).collect();
+            edges.sort_unstable();
+            edges
+        };
+        let ids = |graph: &Graph| {
+            let mut ids = graph.node_ids();
+            ids.sort_unstable();
+            ids
+        };
+
+        // Charlie's friends, with the friendship between Alice and Bob
+        let ego = graph.ego_network(2, 1).unwrap();
+        assert_eq!(ids(&ego), vec![0, 1, 2, 3]);
+        assert_eq!(edges(&ego), vec![(0, 1), (0, 2), (1, 2), (2, 3)]);
+        assert_eq!(ego.node(2).unwrap().label, "Charlie");
+        assert_eq!(ego.node(1).unwrap().properties["age"], 30.0);
+
+        // Two hops reach Eve through Diana, but not Eve's friends
+        let ego = graph.ego_network(2, 2).unwrap();
+        assert_eq!(ids(&ego), vec![0, 1, 2, 3, 4]);
+        assert_eq!(edges(&ego), vec![(0, 1), (0, 2), (1, 2), (2, 3), (3, 4)]);
+
+        assert_eq!(ids(&graph.ego_network(2, 0).unwrap()), vec![2]);
+        assert!(matches!(graph.ego_network(42, 1), Err(GraphError::NodeNotFound(42))));
+    }
+
+    #[test]
+    fn test_time_window_view() {
+        let mut graph = Graph::new();
+        for label in ["A", "B", "C", "D"] {
+            graph.add_node_simple(label);
+        }
+        graph.add_timestamped_edge(0, 1, 1.0, 10.0).unwrap();
+        graph.add_timestamped_edge(1, 2, 1.0, 20.0).unwrap();
+        graph.insert_edge(Edge::new(2, 3, 1.0).with_type("call").with_timestamp(30.0)).unwrap();
+        graph.add_edge(0, 3, 1.0).unwrap();
+
+        let window = graph.time_window_view(10.0, 20.0);
+        assert_eq!(window.node_count(), 4);
+        assert_eq!(window.edge_count(), 2);
+        assert!(window.neighbors(3).unwrap().is_empty());
+        assert!(window.check_invariants().unwrap().is_empty());
+
+        let as_of = graph.time_window_view(f64::NEG_INFINITY, 30.0);
+        assert_eq!(as_of.neighbors_of_type(2, "call").unwrap(), vec![3]);
+        assert_eq!(as_of.edge_count(), 3);
+        assert_eq!(graph.time_window_view(21.0, 29.0).edge_count(), 0);
+        assert_eq!(graph.edge_count(), 4);
+
+        // Timestamps are part of the content hash
+        let mut untimed = Graph::new();
+        untimed.add_node_simple("A");
+        untimed.add_node_simple("B");
+        let mut timed = untimed.clone();
+        untimed.add_edge(0, 1, 1.0).unwrap();
+        timed.add_timestamped_edge(0, 1, 1.0, 0.0).unwrap();
+        assert_ne!(untimed.content_hash(), timed.content_hash());
+        assert_eq!(untimed.structural_hash(), timed.structural_hash());
+    }
+
+    #[test]
+    fn test_node_updates() {
+        let mut graph = Graph::new();
+        let alice = graph.add_node(Node::new(0, "Alice").with_property("age", 30.0));
+        let bob = graph.add_node_simple("Bob");
+        graph.add_edge(alice, bob, 2.0).unwrap();
+        let (generation, hash) = (graph.generation(), graph.content_hash());
+
+        graph.update_node_label(alice, "Alicia").unwrap();
+        assert_eq!(graph.node(alice).unwrap().label, "Alicia");
+        assert_eq!(graph.node(alice).unwrap().id, alice);
+        assert_eq!(graph.neighbors_with_weights(alice).unwrap(), vec![(bob, 2.0)]);
+        assert_ne!(graph.generation(), generation);
+        assert_ne!(graph.content_hash(), hash);
+
+        graph.update_node_property(alice, "age", 31.0).unwrap();
+        graph.update_node_property(alice, "score", 0.5).unwrap();
+        assert_eq!(graph.node(alice).unwrap().properties["age"], 31.0);
+        assert_eq!(graph.node(alice).unwrap().properties["score"], 0.5);
+        assert!(graph.remove_node_property(alice, "score").unwrap());
+        let generation = graph.generation();
+        assert!(!graph.remove_node_property(alice, "score").unwrap());
+        assert_eq!(graph.generation(), generation);
+
+        graph.set_node_features(bob, vec![1.0, 2.0]).unwrap();
+        assert_eq!(graph.node(bob).unwrap().features, Some(vec![1.0, 2.0]));
+
+        // The maintained hash matches one computed from scratch
+        let mut rebuilt = Graph::new();
+        rebuilt.add_node(Node::new(0, "Alicia").with_property("age", 31.0));
+        rebuilt.add_node_simple("Bob");
+        rebuilt.add_edge(alice, bob, 2.0).unwrap();
+        assert_eq!(graph.content_hash(), rebuilt.content_hash());
+        graph.update_node_label(alice, "Alice").unwrap();
+        assert_ne!(graph.content_hash(), rebuilt.content_hash());
+
+        let missing = |result: Result<()>| matches!(result, Err(GraphError::NodeNotFound(7)));
+        assert!(missing(graph.update_node_label(7, "X")));
+        assert!(missing(graph.update_node_property(7, "a", 1.0)));
+        assert!(missing(graph.remove_node_property(7, "a").map(|_| ())));
+        assert!(missing(graph.set_node_features(7, vec![])));
+    }
+    #[test]
+    fn test_generated_ids_do_not_collide_across_threads() {
+        let build = || {
+            std::thread::spawn(|| {
+                let mut graph = Graph::new();
+                let mut previous = None;
+                for i in 0..5_000 {
+                    let id = crate::types::generate_id();
+                    graph.add_node_with_id(id, Node::new(0, format!("Node{}", i))).unwrap();
+                    if let Some(previous) = previous {
+                        graph.add_edge(previous, id, 1.0).unwrap();
+                    }
+                    previous = Some(id);
+                }
+                graph
+            })
+        };
+        let (a, b) = (build(), build());
+        let (a, b) = (a.join().unwrap(), b.join().unwrap());
+
+        let mut merged = a.clone();
+        for id in b.node_ids() {
+            merged.add_node_with_id(id, b.node(id).unwrap().clone()).unwrap();
+        }
+        for edge in b.edges() {
+            merged.add_edge(edge.from, edge.to, edge.weight).unwrap();
+        }
+        assert_eq!(merged.node_count(), 10_000);
+        assert_eq!(merged.edge_count(), 9_998);
+        assert!(merged.node_ids().iter().all(|&id| id >= 1 << (NodeId::BITS - 1)));
+        assert_eq!(crate::algorithms::connected_components(&merged).unwrap().len(), 2);
+
+        // Sequential IDs continue after the generated ones
+        let next = merged.add_node_simple("Next");
+        assert!(merged.node(next).is_ok());
+    }
+
+    #[test]
+    fn test_add_node_with_id_rejects_taken_ids() {
+        let mut graph = Graph::new();
+        let id = graph.add_node_simple("A");
+        assert_eq!(graph.add_node_with_id(7, Node::new(id, "B")).unwrap(), 7);
+        assert_eq!(graph.node(7).unwrap().id, 7);
+        assert_eq!(graph.node(id).unwrap().label, "A");
+        assert!(matches!(
+            graph.add_node_with_id(7, Node::new(7, "C")),
+            Err(GraphError::InvalidParameter(_))
+        ));
+        assert!(graph.add_node_with_id(NodeId::MAX, Node::new(0, "D")).is_err());
+        assert_eq!(graph.add_node_simple("E"), 8);
+    }
+    fn edge_weights(graph: &Graph) -> Vec<(NodeId, NodeId, Weight)> {
+        let mut edges: Vec<_> = graph.edges().iter().map(|e| (e.from, e.to, e.weight)).collect();
+        edges.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        edges
+    }
+
+    #[test]
+    fn test_contract_clique() {
+        let mut graph = Graph::new();
+        for i in 0..4 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        let pairs = [(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3)];
+        for (i, (a, b)) in pairs.into_iter().enumerate() {
+            graph.add_edge(a, b, i as Weight).unwrap();
+        }
+        let before = graph.edge_count();
+
+        let merged = graph.contract_nodes(&[0, 1, 2, 3, 2], "clique").unwrap();
+        assert_eq!(merged, 4);
+        assert_eq!(graph.node_ids(), vec![4]);
+        assert_eq!(graph.node(4).unwrap().label, "clique");
+        assert_eq!(edge_weights(&graph), vec![(4, 4, 5.0)]);
+        assert!(graph.edge_count() <= before);
+        assert!(graph.check_invariants().unwrap().is_empty());
+        assert_eq!(graph.add_node_simple("Next"), 5);
+    }
+
+    #[test]
+    fn test_contract_disconnected_nodes() {
+        let mut graph = Graph::new();
+        for i in 0..6 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        graph.add_edge(0, 1, 1.0).unwrap();
+        graph.add_edge(0, 2, 1.0).unwrap();
+        graph.add_edge(1, 3, 2.5).unwrap();
+        graph.add_edge(4, 3, 1.0).unwrap();
+        // Untouched parallel edges stay
+        graph.add_edge(2, 5, 1.0).unwrap();
+        graph.add_typed_edge(5, 2, 3.0, "other").unwrap();
+        let before = graph.edge_count();
+        let generation = graph.generation();
+
+        let merged = graph.contract_nodes(&[3, 0], "merged").unwrap();
+        let mut neighbors = graph.neighbors(merged).unwrap();
+        neighbors.sort_unstable();
+        assert_eq!(neighbors, vec![1, 2, 4]);
+        assert_eq!(
+            edge_weights(&graph),
+            vec![(1, 6, 2.5), (2, 5, 1.0), (4, 6, 1.0), (5, 2, 3.0), (6, 2, 1.0)]
+        );
+        assert!(graph.edge_count() <= before);
+        assert_ne!(graph.generation(), generation);
+        assert!(graph.check_invariants().unwrap().is_empty());
+
+        assert!(matches!(graph.contract_nodes(&[], "none"), Err(GraphError::InvalidParameter(_))));
+        assert!(matches!(graph.contract_nodes(&[1, 0], "gone"), Err(GraphError::NodeNotFound(0))));
+        assert_eq!(graph.node_count(), 5);
+    }
+
+    #[test]
+    fn test_contract_nodes_directed() {
+        let mut graph = Graph::new_directed();
+        for i in 0..3 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        graph.add_edge(0, 2, 1.0).unwrap();
+        graph.add_edge(2, 1, 2.0).unwrap();
+        graph.add_edge(1, 2, 3.0).unwrap();
+        graph.add_edge(0, 1, 4.0).unwrap();
+
+        let merged = graph.contract_nodes(&[0, 1], "merged").unwrap();
+        assert!(graph.is_directed());
+        assert_eq!(edge_weights(&graph), vec![(2, 3, 2.0), (3, 2, 3.0), (3, 3, 4.0)]);
+        assert_eq!(graph.node_count(), 2);
+        assert_eq!(merged, 3);
+    }
+    #[test]
+    fn test_sorted_adjacency() {
+        let mut graph = Graph::new();
+        for i in 0..5 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        graph.add_edge(0, 3, 1.0).unwrap();
+        graph.add_edge(0, 1, 1.0).unwrap();
+        assert_eq!(graph.neighbors(0).unwrap(), vec![3, 1]);
+        assert!(graph.has_edge(3, 0) && !graph.has_edge(0, 2));
+
+        let generation = graph.generation();
+        graph.sort_adjacency();
+        assert!(graph.has_sorted_adjacency());
+        assert_ne!(graph.generation(), generation);
+        graph.add_edge(0, 2, 1.0).unwrap();
+        graph.add_edge(4, 0, 1.0).unwrap();
+        // Parallel edges stay in insertion order
+        graph.add_typed_edge(0, 1, 2.0, "second").unwrap();
+        assert_eq!(graph.neighbors(0).unwrap(), vec![1, 1, 2, 3, 4]);
+        let weights: Vec<Weight> =
+            graph.neighbors_with_weights(0).unwrap().iter().map(|&(_, w)| w).collect();
+        assert_eq!(weights, vec![1.0, 2.0, 1.0, 1.0, 1.0]);
+        assert!(graph.check_invariants().unwrap().is_empty());
+
+        for from in 0..6 {
+            for to in 0..6 {
+                let scanned = graph.neighbors(from).is_ok_and(|n| n.contains(&to));
+                assert_eq!(graph.has_edge(from, to), scanned, "{} -> {}", from, to);
+            }
+        }
+
+        // Merging keeps the setting
+        let merged = graph.contract_nodes(&[2, 3], "merged").unwrap();
+        assert!(graph.has_sorted_adjacency());
+        assert_eq!(graph.neighbors(0).unwrap(), vec![1, 1, 4, merged]);
+
+        Arc::make_mut(&mut graph.adjacency).get_mut(&0).unwrap().reverse();
+        assert_eq!(single_violation(&graph), "adjacency list of 0 is not sorted");
+    }
+    #[test]
+    fn test_path_validation() {
+        let mut graph = Graph::new();
+        for i in 0..4 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        graph.add_edge(0, 1, 2.0).unwrap();
+        graph.add_edge(1, 2, 3.0).unwrap();
+        graph.add_typed_edge(2, 1, 0.5, "shortcut").unwrap();
+        graph.add_edge(2, 0, 9.0).unwrap();
+
+        assert!(graph.is_valid_path(&[0, 1, 2]));
+        assert!(graph.is_valid_path(&[2, 1, 0]) && graph.path_exists(&[3]));
+        assert!(!graph.is_valid_path(&[0, 1, 3]));
+        assert!(!graph.is_valid_path(&[0, 7]) && !graph.is_valid_path(&[]));
+
+        let path = [0, 1, 2];
+        let chain = graph.induced_path_graph(&path).unwrap();
+        assert_eq!((chain.node_count(), chain.edge_count()), (3, 2));
+        assert_eq!(chain.node(2).unwrap().label, "Node2");
+        // The chain leaves out 2 - 0 and keeps the lighter of 1 - 2
+        assert!(!chain.has_edge(0, 2));
+        assert_eq!(chain.edges()[1].edge_type, "shortcut");
+        let total: Weight = chain.edges().iter().map(|edge| edge.weight).sum();
+        assert_eq!(graph.path_weight(&path).unwrap(), 2.5);
+        assert_eq!(chain.path_weight(&path).unwrap(), total);
+        assert_eq!(graph.path_weight(&[3]).unwrap(), 0.0);
+
+        assert!(matches!(graph.path_weight(&[0, 3]), Err(GraphError::EdgeNotFound(0, 3))));
+        assert!(matches!(graph.induced_path_graph(&[0, 9]), Err(GraphError::NodeNotFound(9))));
+        assert!(matches!(graph.induced_path_graph(&[]), Err(GraphError::InvalidParameter(_))));
+
+        let mut directed = Graph::new_directed();
+        for i in 0..3 {
+            directed.add_node_simple(format!("Node{}", i));
+        }
+        directed.add_edge(0, 1, 1.0).unwrap();
+        directed.add_edge(1, 2, 1.0).unwrap();
+        let found = crate::algorithms::bfs(&directed, 0, 2).unwrap();
+        assert!(directed.is_valid_path(&found));
+        assert!(!directed.is_valid_path(&[2, 1, 0]));
+        assert!(directed.induced_path_graph(&found).unwrap().is_directed());
+    }
+    #[test]
+    fn test_randomize_edge_weights() {
+        let graph = crate::generators::erdos_renyi(200, 0.2, false, Some(1))
+            .randomize_edge_weights(WeightDistribution::Uniform { min: 1.0, max: 3.0 }, Some(2));
+        let moments = |graph: &Graph| {
+            let weights: Vec<f64> = graph.edges().iter().map(|edge| edge.weight).collect();
+            let mean = weights.iter().sum::<f64>() / weights.len() as f64;
+            let variance =
+                weights.iter().map(|w| (w - mean).powi(2)).sum::<f64>() / weights.len() as f64;
+            (mean, variance.sqrt())
+        };
+        assert!(graph.edge_count() > 3000);
+        assert!(graph.edges().iter().all(|edge| (1.0..3.0).contains(&edge.weight)));
+        let (mean, std) = moments(&graph);
+        assert!((mean - 2.0).abs() < 0.05 && (std - 1.0 / 3f64.sqrt()).abs() < 0.05);
+
+        let gaussian = WeightDistribution::Gaussian { mean: 5.0, std: 2.0 };
+        let normal = graph.randomize_edge_weights(gaussian, Some(3));
+        assert_eq!((normal.node_count(), normal.edge_count()), (200, graph.edge_count()));
+        let same = |a: &Graph, b: &Graph| {
+            a.edges().iter().zip(b.edges()).all(|(x, y)| (x.from, x.to) == (y.from, y.to))
+        };
+        assert!(same(&normal, &graph));
+        let (mean, std) = moments(&normal);
+        assert!((mean - 5.0).abs() < 0.15 && (std - 2.0).abs() < 0.15, "{} {}", mean, std);
+        let again = graph.randomize_edge_weights(gaussian, Some(3));
+        assert!(normal.edges().iter().zip(again.edges()).all(|(a, b)| a.weight == b.weight));
+
+        let exponential =
+            graph.randomize_edge_weights(WeightDistribution::Exponential { rate: 4.0 }, None);
+        let (mean, std) = moments(&exponential);
+        assert!((mean - 0.25).abs() < 0.02 && (std - 0.25).abs() < 0.02, "{} {}", mean, std);
+        assert!(exponential.edges().iter().all(|edge| edge.weight >= 0.0));
+
+        let shuffled = graph.randomize_edge_weights(WeightDistribution::Original, Some(4));
+        let sorted_weights = |graph: &Graph| {
+            let mut weights: Vec<f64> = graph.edges().iter().map(|edge| edge.weight).collect();
+            weights.sort_by(f64::total_cmp);
+            weights
+        };
+        assert!(same(&shuffled, &graph));
+        assert_eq!(sorted_weights(&shuffled), sorted_weights(&graph));
+        assert!(shuffled.edges().iter().zip(graph.edges()).any(|(a, b)| a.weight != b.weight));
+        assert!(shuffled.check_invariants().unwrap().is_empty());
+    }
+    #[test]
+    fn test_apply_batch_rolls_back_on_error() {
+        let mut graph = Graph::new();
+        for label in ["A", "B", "C"] {
+            graph.add_node_simple(label);
+        }
+        graph.add_edge(0, 1, 1.0).unwrap();
+        graph.add_edge(1, 2, 2.0).unwrap();
+        let adjacency = |graph: &Graph| -> Vec<_> {
+            (0..5).map(|id| graph.neighbors_with_weights(id).ok()).collect()
+        };
+        let before = adjacency(&graph);
+        let (generation, hash) = (graph.generation(), graph.content_hash());
+
+        let mut ops = vec![
+            GraphOp::AddNode(Node::new(3, "D")),
+            GraphOp::AddEdge(Edge::new(2, 3, 1.0)),
+            GraphOp::RemoveEdge { from: 0, to: 1 },
+            GraphOp::SetWeight { from: 2, to: 1, weight: 5.0 },
+            GraphOp::SetProperty { node: 3, key: "x".into(), value: 1.0 },
+        ];
+        let failures = [
+            (GraphOp::AddEdge(Edge::new(3, 4, 1.0)), "Node 4 not found"),
+            (GraphOp::RemoveEdge { from: 0, to: 1 }, "Edge from 0 to 1 not found"),
+            (GraphOp::AddNode(Node::new(3, "E")), "Invalid parameter: Node 3 already exists"),
+            (GraphOp::SetProperty { node: 9, key: "x".into(), value: 0.0 }, "Node 9 not found"),
+        ];
+        for (op, message) in failures {
+            ops.push(op);
+            let error = graph.apply_batch(&ops).unwrap_err();
+            assert_eq!(error.to_string(), message);
+            ops.pop();
+
+            assert_eq!((graph.node_count(), graph.edge_count()), (3, 2));
+            assert_eq!(adjacency(&graph), before);
+            assert_eq!((graph.generation(), graph.content_hash()), (generation, hash));
+        }
+    }
+
+    #[test]
+    fn test_apply_batch() {
+        let mut graph = Graph::new();
+        for label in ["A", "B", "C"] {
+            graph.add_node_simple(label);
+        }
+        graph.add_edge(0, 1, 1.0).unwrap();
+        graph.add_edge(1, 2, 2.0).unwrap();
+        graph.add_edge(2, 0, 3.0).unwrap();
+        let generation = graph.generation();
+
+        let receipt = graph
+            .apply_batch(&[
+                GraphOp::AddNode(Node::new(10, "D")),
+                GraphOp::AddNode(Node::new(11, "E")),
+                GraphOp::AddEdge(Edge::new(10, 11, 4.0).with_type("new")),
+                GraphOp::AddEdge(Edge::new(0, 10, 5.0)),
+                GraphOp::AddEdge(Edge::new(11, 2, 6.0)),
+                GraphOp::RemoveEdge { from: 1, to: 0 },
+                GraphOp::RemoveEdge { from: 10, to: 0 },
+                GraphOp::SetWeight { from: 0, to: 2, weight: 7.0 },
+                GraphOp::SetWeight { from: 11, to: 10, weight: 8.0 },
+                GraphOp::SetProperty { node: 11, key: "rank".into(), value: 2.0 },
+            ])
+            .unwrap();
+        assert_eq!(receipt.nodes, vec![10, 11]);
+        assert_eq!(receipt.edges, vec![2, 3]);
+        assert_eq!(receipt.removed_edges, 1);
+        assert_ne!(graph.generation(), generation);
+
+        assert_eq!(graph.node_count(), 5);
+        assert_eq!(
+            edge_weights(&graph),
+            vec![(1, 2, 2.0), (2, 0, 7.0), (10, 11, 8.0), (11, 2, 6.0)]
+        );
+        assert_eq!(graph.edges()[2].edge_type, "new");
+        assert_eq!(graph.node(11).unwrap().properties["rank"], 2.0);
+        assert_eq!(graph.neighbors(0).unwrap(), vec![2]);
+        assert_eq!(graph.add_node_simple("F"), 12);
+
+        // Same content as building the graph op by op
+        let mut expected = Graph::new();
+        for (id, label) in [(0, "A"), (1, "B"), (2, "C"), (10, "D"), (11, "E"), (12, "F")] {
+            expected.add_node(Node::new(id, label));
+        }
+        expected.update_node_property(11, "rank", 2.0).unwrap();
+        expected.add_edge(1, 2, 2.0).unwrap();
+        expected.add_edge(2, 0, 7.0).unwrap();
+        expected.add_typed_edge(10, 11, 8.0, "new").unwrap();
+        expected.add_edge(11, 2, 6.0).unwrap();
+        assert_eq!(graph.content_hash(), expected.content_hash());
+        assert!(graph.check_invariants().unwrap().is_empty());
+
+        let generation = graph.generation();
+        assert_eq!(graph.apply_batch(&[]).unwrap(), BatchReceipt::default());
+        assert_eq!(graph.generation(), generation);
+    }
+    #[test]
+    fn test_deserialize_recomputes_hashes() {
+        let mut graph = Graph::new();
+        graph.add_node(Node::new(0, "A").with_property("x", 1.0));
+        graph.add_node_simple("B");
+        graph.add_edge(0, 1, 2.0).unwrap();
+        graph.add_edge(1, 1, 3.0).unwrap();
+
+        let mut json = serde_json::to_value(&graph).unwrap();
+        let loaded: Graph = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(loaded.content_hash(), graph.content_hash());
+
+        // As saved before the sums existed, then edited by hand
+        let fields = json.as_object_mut().unwrap();
+        fields.remove("content_sum");
+        fields.remove("structure_sum");
+        fields.insert("uniform_weights".into(), true.into());
+        let mut loaded: Graph = serde_json::from_value(json.clone()).unwrap();
+        let expected = graph.content_hash();
+        assert_eq!(loaded.content_hash(), expected);
+        assert_eq!(loaded.structural_hash(), graph.structural_hash());
+        assert!(!loaded.has_uniform_weights());
+        assert!(loaded.check_invariants().unwrap().is_empty());
+
+        loaded.add_edge(1, 0, 4.0).unwrap();
+        graph.add_edge(1, 0, 4.0).unwrap();
+        assert_eq!(loaded.content_hash(), graph.content_hash());
+
+        json["content_sum"] = 12345.into();
+        let tampered: Graph = serde_json::from_value(json).unwrap();
+        assert_eq!(tampered.content_hash(), expected);
     }
 }
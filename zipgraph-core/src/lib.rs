@@ -16,31 +16,52 @@
 //! use zipgraph_core::Graph;
 //!
 //! let mut graph = Graph::new();
-//! graph.add_node_simple("A");
-//! graph.add_node_simple("B");
-//! graph.add_edge(0, 1, 1.0).unwrap();
+//! let a = graph.add_node_simple("A");
+//! let b = graph.add_node_simple("B");
+//! graph.add_edge(a, b, 1.0).unwrap();
 //!
 //! assert_eq!(graph.node_count(), 2);
 //! assert_eq!(graph.edge_count(), 1);
 //! ```
 
 pub mod algorithms;
+pub mod analysis;
 pub mod centrality;
+pub mod checkpoint;
+pub mod compare;
+pub mod compat;
 pub mod error;
+pub mod generators;
 pub mod graph;
 pub mod metrics;
 pub mod parallel;
+pub mod progress;
+pub mod similarity;
+pub mod sparse;
 pub mod stats;
 pub mod storage;
+pub mod streaming;
+pub mod testing;
 pub mod types;
 pub mod ultra;
 
 // Re-exports for convenience
+pub use analysis::{AnalysisCache, PageRankParams};
+pub use compare::{Difference, EqOptions};
 pub use error::{GraphError, Result};
-pub use graph::{Edge, Graph, Node};
-pub use stats::GraphStats;
-pub use storage::{load_graph, save_graph, StorageFormat};
-pub use types::NodeId;
+pub use graph::{
+    BatchReceipt, Edge, Graph, GraphOp, GraphSnapshot, Node, WeightDistribution,
+};
+pub use progress::{LogEveryN, PartialResult, ProgressSink};
+pub use stats::{GraphStats, MotifProfile, NullModelScore, PowerLawFit, PropertyStats};
+pub use storage::{
+    from_dot, from_pajek, load_graph, save_graph, to_dot, to_pajek, to_tikz, StorageFormat,
+    TikzLayout,
+};
+pub use streaming::{streaming_bfs, EdgeProvider, FileEdgeProvider};
+pub use types::{generate_id, id_bits, node_id, node_index, NodeId};
+
+use serde::{Deserialize, Serialize};
 
 /// Algorithm selection enum
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -53,3 +74,41 @@ pub enum Algorithm {
     FloydWarshall,
     BellmanFord,
 }
+
+impl Algorithm {
+    /// Whether the paths this algorithm finds are optimal under `metric`
+    ///
+    /// `DFS` finds some path, not a shortest one, and
+    /// `BidirectionalSearch` has no path search implementation, so
+    /// neither honors any metric. Hop-optimal paths are also
+    /// weight-optimal on graphs with uniform weights, see
+    /// `Graph::has_uniform_weights`.
+    pub fn honors(&self, metric: PathMetric) -> bool {
+        match self {
+            Algorithm::BFS => metric == PathMetric::Hops,
+            Algorithm::Dijkstra
+            | Algorithm::AStar
+            | Algorithm::FloydWarshall
+            | Algorithm::BellmanFord => metric == PathMetric::Weight,
+            Algorithm::DFS | Algorithm::BidirectionalSearch => false,
+        }
+    }
+}
+
+/// What a shortest path minimizes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PathMetric {
+    /// Number of edges
+    Hops,
+    /// Sum of edge weights
+    #[default]
+    Weight,
+}
+
+impl PathMetric {
+    /// Whether this is the default `Weight` metric
+    pub fn is_weight(&self) -> bool {
+        *self == PathMetric::Weight
+    }
+}
@@ -114,7 +114,7 @@ impl OperationMetrics {
         
         // Store for percentile calculation (with sampling to limit memory)
         let count = self.count.load(Ordering::Relaxed);
-        if count < 10000 || count % 100 == 0 {
+        if count < 10000 || count.is_multiple_of(100) {
             self.durations.write().push(nanos);
         }
     }
@@ -288,7 +288,7 @@ pub fn print_summary() {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(feature = "uuid-ids")))]
 mod tests {
     use super::*;
     use std::thread;
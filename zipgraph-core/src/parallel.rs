@@ -1,12 +1,18 @@
 //! Parallel graph algorithms using Rayon
 
-use crate::error::Result;
+use crate::error::{GraphError, Result};
 use crate::graph::Graph;
 use crate::types::{NodeId, Weight};
 use rayon::prelude::*;
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::sync::{Arc, Mutex};
 
+/// A path found from a particular source node
+type SourcePath = (NodeId, Vec<NodeId>);
+
+/// Rank a part passes to its own nodes, by position, and to other nodes
+type PartContributions = (Vec<f64>, Vec<(usize, f64)>);
+
 /// Parallel breadth-first search from multiple source nodes
 ///
 /// Performs BFS from multiple sources simultaneously, useful for
@@ -20,7 +26,7 @@ pub fn parallel_multi_source_bfs(
         return Ok(None);
     }
 
-    let result: Arc<Mutex<Option<(NodeId, Vec<NodeId>)>>> = Arc::new(Mutex::new(None));
+    let result: Arc<Mutex<Option<SourcePath>>> = Arc::new(Mutex::new(None));
     
     sources.par_iter().find_map_any(|&source| {
         match crate::algorithms::bfs(graph, source, goal) {
@@ -78,7 +84,8 @@ pub fn parallel_node_degrees(graph: &Graph) -> Result<HashMap<NodeId, usize>> {
 /// Parallel PageRank computation
 ///
 /// Computes PageRank using parallel operations for better performance
-/// on large graphs.
+/// on large graphs. Like `centrality::pagerank`, the rank of nodes
+/// without out-edges is spread over all nodes, so ranks sum to one.
 pub fn parallel_pagerank(
     graph: &Graph,
     damping_factor: f64,
@@ -98,7 +105,10 @@ pub fn parallel_pagerank(
         .map(|&id| (id, initial_rank))
         .collect();
 
+    let sinks = crate::centrality::sink_nodes(graph, &node_ids);
+
     for _ in 0..max_iterations {
+        let dangling = sinks.iter().map(|id| ranks[id]).sum::<f64>() / node_count as f64;
         let new_ranks: HashMap<NodeId, f64> = node_ids
             .par_iter()
             .map(|&node_id| {
@@ -115,9 +125,9 @@ pub fn parallel_pagerank(
                     }
                 }
 
-                let new_rank = (1.0 - damping_factor) / node_count as f64 
-                    + damping_factor * rank_sum;
-                
+                let new_rank = (1.0 - damping_factor) / node_count as f64
+                    + damping_factor * (dangling + rank_sum);
+
                 (node_id, new_rank)
             })
             .collect();
@@ -137,52 +147,221 @@ pub fn parallel_pagerank(
     Ok(ranks)
 }
 
+/// `parallel_pagerank` with one task per part of `partition`
+///
+/// `partition` maps every node to a part, e.g. from
+/// `algorithms::partition`. Each task spreads the rank of its part's nodes
+/// into a buffer of its own: contributions to nodes of the same part are
+/// summed locally and only those crossing to another part are handed back
+/// for merging, so a partition with a small edge cut keeps most of the
+/// work thread-local. Ranks agree with `parallel_pagerank` up to rounding.
+/// Fails with `InvalidParameter` if a node is missing from `partition`.
+pub fn partitioned_pagerank(
+    graph: &Graph,
+    partition: &HashMap<NodeId, usize>,
+    damping_factor: f64,
+    max_iterations: usize,
+    tolerance: f64,
+) -> Result<HashMap<NodeId, f64>> {
+    let node_count = graph.node_count();
+    if node_count == 0 {
+        return Ok(HashMap::new());
+    }
+
+    let mut node_ids = graph.node_ids();
+    node_ids.sort_unstable();
+    let index: HashMap<NodeId, usize> =
+        node_ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+
+    // Each node passes rank / out-degree once to every distinct neighbor
+    let mut targets: Vec<Vec<usize>> = Vec::with_capacity(node_count);
+    let mut out_degrees = Vec::with_capacity(node_count);
+    for &id in &node_ids {
+        let neighbors = graph.neighbors(id)?;
+        let mut distinct: Vec<usize> = neighbors.iter().map(|neighbor| index[neighbor]).collect();
+        distinct.sort_unstable();
+        distinct.dedup();
+        targets.push(distinct);
+        out_degrees.push(neighbors.len());
+    }
+    let sinks: Vec<usize> = crate::centrality::sink_nodes(graph, &node_ids)
+        .iter()
+        .map(|id| index[id])
+        .collect();
+
+    let mut members: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for (i, id) in node_ids.iter().enumerate() {
+        let part = partition.get(id).ok_or_else(|| {
+            GraphError::InvalidParameter(format!("node {} is not in the partition", id))
+        })?;
+        members.entry(*part).or_default().push(i);
+    }
+    let parts: Vec<Vec<usize>> = members.into_values().collect();
+    let mut part_of = vec![0; node_count];
+    let mut position = vec![0; node_count];
+    for (part, nodes) in parts.iter().enumerate() {
+        for (offset, &node) in nodes.iter().enumerate() {
+            part_of[node] = part;
+            position[node] = offset;
+        }
+    }
+
+    let mut ranks = vec![1.0 / node_count as f64; node_count];
+    for _ in 0..max_iterations {
+        let dangling = sinks.iter().map(|&node| ranks[node]).sum::<f64>() / node_count as f64;
+        let buffers: Vec<PartContributions> = parts
+            .par_iter()
+            .enumerate()
+            .map(|(part, nodes)| {
+                let mut local = vec![0.0; nodes.len()];
+                let mut crossing = Vec::new();
+                for &node in nodes {
+                    let share = ranks[node] / out_degrees[node] as f64;
+                    for &target in &targets[node] {
+                        if part_of[target] == part {
+                            local[position[target]] += share;
+                        } else {
+                            crossing.push((target, share));
+                        }
+                    }
+                }
+                (local, crossing)
+            })
+            .collect();
+
+        let mut incoming = vec![0.0; node_count];
+        for (nodes, (local, crossing)) in parts.iter().zip(buffers) {
+            for (&node, sum) in nodes.iter().zip(local) {
+                incoming[node] += sum;
+            }
+            for (target, share) in crossing {
+                incoming[target] += share;
+            }
+        }
+
+        let new_ranks: Vec<f64> = incoming
+            .iter()
+            .map(|&sum| {
+                (1.0 - damping_factor) / node_count as f64 + damping_factor * (dangling + sum)
+            })
+            .collect();
+        let converged =
+            ranks.iter().zip(&new_ranks).all(|(old, new)| (new - old).abs() <= tolerance);
+        ranks = new_ranks;
+        if converged {
+            break;
+        }
+    }
+
+    Ok(node_ids.into_iter().zip(ranks).collect())
+}
+
 /// Parallel neighborhood search
 ///
-/// Find all neighbors within k hops for multiple nodes in parallel.
+/// Find all nodes within k hops for multiple nodes in parallel. Each
+/// source maps to its neighborhood with hop distances, the source itself
+/// at distance 0. Fails with `NodeNotFound` if a source is not in the
+/// graph.
 pub fn parallel_k_hop_neighbors(
     graph: &Graph,
     sources: &[NodeId],
     k: usize,
-) -> Result<HashMap<NodeId, HashSet<NodeId>>> {
-    let results: Vec<_> = sources
+) -> Result<HashMap<NodeId, HashMap<NodeId, usize>>> {
+    sources
         .par_iter()
-        .filter_map(|&source| {
-            k_hop_neighbors(graph, source, k)
-                .ok()
-                .map(|neighbors| (source, neighbors))
+        .map(|&source| {
+            graph.node(source)?;
+            let mut depths = HashMap::new();
+            bounded_bfs(graph, source, k, |node, depth| {
+                depths.insert(node, depth);
+            });
+            Ok((source, depths))
         })
-        .collect();
+        .collect()
+}
 
-    Ok(results.into_iter().collect())
+/// Number of nodes within `k` hops of each source, excluding the source
+///
+/// Equals `k_hop_neighbors(graph, source, k)?.len()` without collecting
+/// the neighborhoods. Fails with `NodeNotFound` if a source is not in the
+/// graph.
+pub fn k_hop_neighborhood_counts(
+    graph: &Graph,
+    sources: &[NodeId],
+    k: usize,
+) -> Result<HashMap<NodeId, usize>> {
+    sources
+        .par_iter()
+        .map(|&source| {
+            graph.node(source)?;
+            let mut count = 0;
+            bounded_bfs(graph, source, k, |_, _| count += 1);
+            Ok((source, count - 1))
+        })
+        .collect()
 }
 
-/// Helper function to find k-hop neighbors
-fn k_hop_neighbors(graph: &Graph, source: NodeId, k: usize) -> Result<HashSet<NodeId>> {
-    let mut visited = HashSet::new();
-    let mut queue = VecDeque::new();
-    
-    queue.push_back((source, 0));
-    visited.insert(source);
+/// Parallel Floyd-Warshall all-pairs shortest path distances
+///
+/// Same result as `algorithms::floyd_warshall`: rows and columns follow
+/// ascending node ID order and unreachable pairs are `Weight::INFINITY`.
+/// For a fixed intermediate vertex `k` every row update is independent, so
+/// rows are relaxed in parallel against a snapshot of row `k`. Row `k`
+/// cannot improve through `k` itself, so the snapshot equals the live row
+/// and no synchronization on the matrix is needed.
+pub fn parallel_floyd_warshall(graph: &Graph) -> Result<Vec<Vec<Weight>>> {
+    let mut dist = crate::algorithms::initial_distances(graph)?;
+    let n = dist.len();
+
+    for k in 0..n {
+        let row_k = dist[k].clone();
+        dist.par_iter_mut().enumerate().for_each(|(i, row)| {
+            let through_k = row[k];
+            if i == k || through_k == Weight::INFINITY {
+                return;
+            }
+            for (d, &via) in row.iter_mut().zip(&row_k) {
+                let candidate = through_k + via;
+                if candidate < *d {
+                    *d = candidate;
+                }
+            }
+        });
+    }
+
+    Ok(dist)
+}
+
+/// Nodes within `k` hops of `source`, excluding `source` itself
+pub fn k_hop_neighbors(graph: &Graph, source: NodeId, k: usize) -> Result<HashSet<NodeId>> {
+    let mut neighbors = HashSet::new();
+    bounded_bfs(graph, source, k, |node, _| {
+        neighbors.insert(node);
+    });
+    neighbors.remove(&source); // Remove source itself
+    Ok(neighbors)
+}
+
+/// Breadth-first search from `source` that stops `k` hops out, calling
+/// `visit` with each reached node and its hop distance, `source` first
+fn bounded_bfs(graph: &Graph, source: NodeId, k: usize, mut visit: impl FnMut(NodeId, usize)) {
+    let mut visited = HashSet::from([source]);
+    let mut queue = VecDeque::from([(source, 0)]);
+    visit(source, 0);
 
     while let Some((node, depth)) = queue.pop_front() {
         if depth < k {
-            if let Ok(neighbors) = graph.neighbors(node) {
-                for neighbor in neighbors {
-                    if !visited.contains(&neighbor) {
-                        visited.insert(neighbor);
-                        queue.push_back((neighbor, depth + 1));
-                    }
+            for (neighbor, _) in graph.weighted_neighbors_iter(node) {
+                if visited.insert(neighbor) {
+                    visit(neighbor, depth + 1);
+                    queue.push_back((neighbor, depth + 1));
                 }
             }
         }
     }
-
-    visited.remove(&source); // Remove source itself
-    Ok(visited)
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(feature = "uuid-ids")))]
 mod tests {
     use super::*;
 
@@ -246,6 +425,63 @@ mod tests {
         assert!((sum - 1.0).abs() < 0.01);
     }
 
+    #[test]
+    fn test_parallel_pagerank_with_sink() {
+        let mut graph = Graph::new_directed();
+        for i in 0..4 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        // 3 is a sink fed by the cycle 0 -> 1 -> 2 -> 0
+        for (from, to) in [(0, 1), (1, 2), (2, 0), (2, 3)] {
+            graph.add_edge(from, to, 1.0).unwrap();
+        }
+
+        let ranks = parallel_pagerank(&graph, 0.85, 1000, 1e-12).unwrap();
+        let sum: f64 = ranks.values().sum();
+        assert!((sum - 1.0).abs() < 1e-9, "sum {}", sum);
+        assert!(ranks[&3] < 0.5, "{:?}", ranks);
+
+        let serial = crate::centrality::pagerank(&graph, 0.85, 1000, 1e-12).unwrap();
+        let ultra = crate::ultra::ultra_pagerank(&graph, 0.85, 1000, 1e-12).unwrap();
+        for (id, rank) in &serial {
+            assert!((ranks[id] - rank).abs() < 1e-9);
+            assert!((ultra[id] - rank).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_partitioned_pagerank() {
+        use crate::algorithms::{partition, PartitionMethod};
+
+        let mut directed = crate::generators::erdos_renyi(150, 0.03, true, Some(5));
+        directed.add_node_simple("sink");
+        directed.add_edge(0, 150, 1.0).unwrap();
+        let graphs = [crate::generators::barabasi_albert(200, 2, Some(6)), directed];
+        for graph in &graphs {
+            let expected = parallel_pagerank(graph, 0.85, 200, 1e-12).unwrap();
+            for k in [1, 4, 7] {
+                let parts = partition(graph, k, PartitionMethod::Multilevel).unwrap();
+                let ranks = partitioned_pagerank(graph, &parts, 0.85, 200, 1e-12).unwrap();
+                assert_eq!(ranks.len(), expected.len());
+                for (id, rank) in &expected {
+                    assert!((ranks[id] - rank).abs() < 1e-10, "{} {} {}", id, ranks[id], rank);
+                }
+            }
+        }
+
+        // Any labelling works, but every node needs one
+        let graph = &graphs[0];
+        let by_id: HashMap<NodeId, usize> =
+            graph.node_ids().iter().map(|&id| (id, id * 31)).collect();
+        assert!(partitioned_pagerank(graph, &by_id, 0.85, 200, 1e-12).is_ok());
+        let mut missing = by_id;
+        missing.remove(&3);
+        assert!(partitioned_pagerank(graph, &missing, 0.85, 200, 1e-12).is_err());
+        assert!(partitioned_pagerank(&Graph::new(), &HashMap::new(), 0.85, 10, 1e-6)
+            .unwrap()
+            .is_empty());
+    }
+
     #[test]
     fn test_parallel_k_hop_neighbors() {
         let graph = create_test_graph();
@@ -253,7 +489,49 @@ mod tests {
         let results = parallel_k_hop_neighbors(&graph, &sources, 2).unwrap();
         
         assert_eq!(results.len(), 2);
-        assert!(results[&0].contains(&1));
-        assert!(results[&0].contains(&2));
+        assert_eq!(results[&0], HashMap::from([(0, 0), (1, 1), (2, 2)]));
+        assert_eq!(results[&5], HashMap::from([(3, 2), (4, 1), (5, 0), (6, 1), (7, 2)]));
+        assert!(matches!(
+            parallel_k_hop_neighbors(&graph, &[0, 42], 2),
+            Err(crate::error::GraphError::NodeNotFound(42))
+        ));
+    }
+
+    #[test]
+    fn test_k_hop_neighborhood_counts() {
+        let graph = create_test_graph();
+        let sources: Vec<NodeId> = (0..10).collect();
+        for k in 0..4 {
+            let counts = k_hop_neighborhood_counts(&graph, &sources, k).unwrap();
+            for &source in &sources {
+                assert_eq!(counts[&source], k_hop_neighbors(&graph, source, k).unwrap().len());
+            }
+        }
+        assert!(k_hop_neighborhood_counts(&graph, &[10], 1).is_err());
+    }
+
+    #[test]
+    fn test_parallel_floyd_warshall_matches_serial() {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut graph = Graph::new_directed();
+        for i in 0..80 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        for _ in 0..400 {
+            let from = rng.gen_range(0..80);
+            let to = rng.gen_range(0..80);
+            graph.add_edge(from, to, rng.gen_range(0.1..5.0)).unwrap();
+        }
+
+        let serial = crate::algorithms::floyd_warshall(&graph).unwrap();
+        let parallel = parallel_floyd_warshall(&graph).unwrap();
+        assert_eq!(serial, parallel);
+
+        let chain = parallel_floyd_warshall(&create_test_graph()).unwrap();
+        assert_eq!(chain[0][9], 9.0);
+        assert_eq!(chain[9][0], 9.0);
     }
 }
@@ -0,0 +1,130 @@
+//! Progress reporting and cancellation for long-running algorithms
+//!
+//! Algorithms that can run for minutes on a large graph have
+//! `*_with_progress` variants taking an `Option<&dyn ProgressSink>`. They
+//! report after each unit of work (a source node, an iteration, an epoch)
+//! and check `should_cancel` before starting the next one, returning what
+//! they have so far as a `PartialResult`.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Receives progress updates from a running algorithm and can ask it to
+/// stop
+///
+/// Methods take `&self` so that one sink can be shared with parallel code;
+/// keep counters in atomics or behind a lock.
+pub trait ProgressSink: Sync {
+    /// `completed` of `total` units of work are done
+    fn on_progress(&self, completed: u64, total: u64);
+
+    /// Whether the algorithm should stop before its next unit of work
+    fn should_cancel(&self) -> bool {
+        false
+    }
+}
+
+/// The result of an algorithm that may have been cancelled part way
+///
+/// What a partial `value` means is documented on each algorithm.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartialResult<T> {
+    pub value: T,
+    /// Units of work done before the algorithm stopped
+    pub completed: u64,
+    /// Units of work a full run does
+    pub total: u64,
+}
+
+impl<T> PartialResult<T> {
+    /// Whether the algorithm ran to the end
+    pub fn is_complete(&self) -> bool {
+        self.completed >= self.total
+    }
+
+    /// The value, or `None` if the run was cancelled
+    pub fn complete(self) -> Option<T> {
+        self.is_complete().then_some(self.value)
+    }
+
+    /// Apply `f` to the value, keeping the progress counts
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> PartialResult<U> {
+        PartialResult { value: f(self.value), completed: self.completed, total: self.total }
+    }
+}
+
+/// A `ProgressSink` printing every `n`-th update, and the last one, to
+/// stderr
+///
+/// ```
+/// use zipgraph_core::centrality::betweenness_centrality_with_progress;
+/// use zipgraph_core::progress::LogEveryN;
+/// use zipgraph_core::Graph;
+///
+/// let mut graph = Graph::new();
+/// graph.add_node_simple("A");
+/// let log = LogEveryN::new("betweenness", 1000);
+/// let scores = betweenness_centrality_with_progress(&graph, Some(&log)).unwrap();
+/// assert!(scores.is_complete());
+/// ```
+#[derive(Debug)]
+pub struct LogEveryN {
+    name: String,
+    n: u64,
+    updates: AtomicU64,
+}
+
+impl LogEveryN {
+    /// Log progress of the algorithm called `name` every `n` updates
+    pub fn new(name: impl Into<String>, n: u64) -> Self {
+        Self { name: name.into(), n: n.max(1), updates: AtomicU64::new(0) }
+    }
+}
+
+impl ProgressSink for LogEveryN {
+    fn on_progress(&self, completed: u64, total: u64) {
+        let updates = self.updates.fetch_add(1, Ordering::Relaxed) + 1;
+        if updates.is_multiple_of(self.n) || completed >= total {
+            let percent = if total == 0 { 100.0 } else { 100.0 * completed as f64 / total as f64 };
+            eprintln!("{}: {}/{} ({:.1}%)", self.name, completed, total, percent);
+        }
+    }
+}
+
+/// Report that `completed` of `total` units are done, if there is a sink
+pub(crate) fn report(progress: Option<&dyn ProgressSink>, completed: usize, total: usize) {
+    if let Some(progress) = progress {
+        progress.on_progress(completed as u64, total as u64);
+    }
+}
+
+/// Whether there is a sink and it asks to stop
+pub(crate) fn cancelled(progress: Option<&dyn ProgressSink>) -> bool {
+    progress.is_some_and(|progress| progress.should_cancel())
+}
+
+#[cfg(all(test, not(feature = "uuid-ids")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_result() {
+        let done = PartialResult { value: 3, completed: 5, total: 5 };
+        assert!(done.is_complete());
+        assert_eq!(done.clone().map(|v| v * 2).value, 6);
+        assert_eq!(done.complete(), Some(3));
+
+        let cancelled = PartialResult { value: 3, completed: 2, total: 5 };
+        assert!(!cancelled.is_complete());
+        assert_eq!(cancelled.complete(), None);
+    }
+
+    #[test]
+    fn test_log_every_n_never_cancels() {
+        let log = LogEveryN::new("test", 0);
+        for completed in 1..=3 {
+            log.on_progress(completed, 3);
+        }
+        assert_eq!(log.updates.load(Ordering::Relaxed), 3);
+        assert!(!log.should_cancel());
+    }
+}
@@ -0,0 +1,393 @@
+//! Node similarity measures based on shared neighbors
+//!
+//! All measures use the (out-)neighbor sets of the nodes, so on directed
+//! graphs two nodes are similar when they point to the same nodes.
+
+use crate::error::{GraphError, Result};
+use crate::graph::Graph;
+use crate::types::NodeId;
+use std::collections::{HashMap, HashSet};
+
+/// Largest graph accepted by `common_neighbor_matrix`
+pub const MAX_MATRIX_NODES: usize = 10_000;
+
+/// Neighborhood-based similarity metric
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SimilarityMetric {
+    /// Number of shared neighbors
+    CommonNeighbors,
+    /// Shared neighbors divided by the union of both neighborhoods
+    Jaccard,
+    /// Shared neighbors weighted by `1 / ln(degree)`
+    AdamicAdar,
+    /// Shared neighbors weighted by `1 / degree`
+    ResourceAllocation,
+}
+
+/// Number of neighbors shared by `a` and `b`
+pub fn common_neighbors(graph: &Graph, a: NodeId, b: NodeId) -> Result<usize> {
+    let neighbors_a = sorted_neighbors(graph, a)?;
+    let neighbors_b = sorted_neighbors(graph, b)?;
+    Ok(sorted_intersection(&neighbors_a, &neighbors_b).len())
+}
+
+/// Jaccard coefficient of the neighborhoods of `a` and `b`
+pub fn jaccard_coefficient(graph: &Graph, a: NodeId, b: NodeId) -> Result<f64> {
+    similarity(graph, a, b, SimilarityMetric::Jaccard)
+}
+
+/// Adamic-Adar index of `a` and `b`
+pub fn adamic_adar_index(graph: &Graph, a: NodeId, b: NodeId) -> Result<f64> {
+    similarity(graph, a, b, SimilarityMetric::AdamicAdar)
+}
+
+/// Resource allocation index of `a` and `b`
+pub fn resource_allocation_index(graph: &Graph, a: NodeId, b: NodeId) -> Result<f64> {
+    similarity(graph, a, b, SimilarityMetric::ResourceAllocation)
+}
+
+/// Similarity of `a` and `b` under `metric`
+pub fn similarity(graph: &Graph, a: NodeId, b: NodeId, metric: SimilarityMetric) -> Result<f64> {
+    let neighbors_a = sorted_neighbors(graph, a)?;
+    let neighbors_b = sorted_neighbors(graph, b)?;
+    let shared = sorted_intersection(&neighbors_a, &neighbors_b);
+
+    score(graph, metric, &shared, neighbors_a.len(), neighbors_b.len())
+}
+
+/// Neighborhood overlap of the edge `from -> to`
+///
+/// Returns `(common_neighbors, total_neighbors, embeddedness)`, where
+/// `total_neighbors` is the size of the union of both neighborhoods with
+/// the two endpoints counted once, as the edge itself, and embeddedness is
+/// `common_neighbors / total_neighbors`. An edge in a triangle shares one
+/// neighbor out of two: `(1, 2, 0.5)`. Fails with `EdgeNotFound` if there
+/// is no such edge.
+pub fn edge_neighborhood_stats(
+    graph: &Graph,
+    from: NodeId,
+    to: NodeId,
+) -> Result<(usize, usize, f64)> {
+    let neighbors_from = sorted_neighbors(graph, from)?;
+    let neighbors_to = sorted_neighbors(graph, to)?;
+    if neighbors_from.binary_search(&to).is_err() {
+        return Err(GraphError::EdgeNotFound(from, to));
+    }
+
+    let common = sorted_intersection(&neighbors_from, &neighbors_to).len();
+    let others: HashSet<NodeId> = neighbors_from
+        .iter()
+        .chain(&neighbors_to)
+        .copied()
+        .filter(|&node| node != from && node != to)
+        .collect();
+    let total = others.len() + 1;
+    Ok((common, total, common as f64 / total as f64))
+}
+
+/// Matrix of common-neighbor counts for every pair of nodes
+///
+/// Rows and columns follow ascending node ID order, so entry `[i][j]` is
+/// `|N(i) ∩ N(j)|` for the i-th and j-th smallest IDs. The diagonal holds
+/// the number of distinct neighbors of each node. Counts are accumulated
+/// over the pairs of nodes sharing each neighbor, in
+/// `O(sum of squared in-degrees)` time; graphs with more than
+/// `MAX_MATRIX_NODES` nodes are rejected since the matrix is dense.
+pub fn common_neighbor_matrix(graph: &Graph) -> Result<Vec<Vec<usize>>> {
+    let n = graph.node_count();
+    if n > MAX_MATRIX_NODES {
+        return Err(GraphError::InvalidParameter(format!(
+            "common_neighbor_matrix is limited to {} nodes (graph has {})",
+            MAX_MATRIX_NODES, n
+        )));
+    }
+
+    let mut node_ids = graph.node_ids();
+    node_ids.sort_unstable();
+
+    let adjacency = node_ids
+        .iter()
+        .map(|&id| sorted_neighbors(graph, id))
+        .collect::<Result<Vec<_>>>()?;
+
+    // Every pair of nodes pointing at the same neighbor shares it, so
+    // only pairs with a common neighbor are ever visited
+    let index: HashMap<NodeId, usize> =
+        node_ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+    let mut sources: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for (i, neighbors) in adjacency.iter().enumerate() {
+        for neighbor in neighbors {
+            sources[index[neighbor]].push(i);
+        }
+    }
+
+    let mut counts: HashMap<(usize, usize), usize> = HashMap::new();
+    for shared_by in &sources {
+        for (position, &i) in shared_by.iter().enumerate() {
+            for &j in &shared_by[position + 1..] {
+                *counts.entry((i, j)).or_default() += 1;
+            }
+        }
+    }
+
+    let mut matrix = vec![vec![0; n]; n];
+    for (i, neighbors) in adjacency.iter().enumerate() {
+        matrix[i][i] = neighbors.len();
+    }
+    for ((i, j), count) in counts {
+        matrix[i][j] = count;
+        matrix[j][i] = count;
+    }
+
+    Ok(matrix)
+}
+
+/// The `k` nodes most similar to `node` under `metric`
+///
+/// Only nodes sharing at least one neighbor with `node` are considered, so
+/// an isolated node has no similar nodes. Results are sorted by descending
+/// score with ties broken by node ID.
+pub fn node_similarity_top_k(
+    graph: &Graph,
+    node: NodeId,
+    k: usize,
+    metric: SimilarityMetric,
+) -> Result<Vec<(NodeId, f64)>> {
+    let own_neighbors = sorted_neighbors(graph, node)?;
+
+    // Candidates are the nodes that also point at one of our neighbors.
+    // Undirected adjacency is symmetric; directed graphs only store
+    // outgoing edges, so their in-neighbors come from the edge list.
+    let mut candidates = HashSet::new();
+    if graph.is_directed() {
+        let targets: HashSet<NodeId> = own_neighbors.iter().copied().collect();
+        for edge in graph.edges() {
+            if targets.contains(&edge.to) && edge.from != node {
+                candidates.insert(edge.from);
+            }
+        }
+    } else {
+        for &neighbor in &own_neighbors {
+            for candidate in graph.neighbors(neighbor)? {
+                if candidate != node {
+                    candidates.insert(candidate);
+                }
+            }
+        }
+    }
+
+    let mut scored = Vec::with_capacity(candidates.len());
+    for candidate in candidates {
+        let candidate_neighbors = sorted_neighbors(graph, candidate)?;
+        let shared = sorted_intersection(&own_neighbors, &candidate_neighbors);
+        if shared.is_empty() {
+            continue;
+        }
+
+        let value = score(graph, metric, &shared, own_neighbors.len(), candidate_neighbors.len())?;
+        scored.push((candidate, value));
+    }
+
+    scored.sort_by(|a, b| {
+        b.1.partial_cmp(&a.1)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(a.0.cmp(&b.0))
+    });
+    scored.truncate(k);
+
+    Ok(scored)
+}
+
+/// Score a pair of nodes from their shared neighbors and degrees
+fn score(
+    graph: &Graph,
+    metric: SimilarityMetric,
+    shared: &[NodeId],
+    degree_a: usize,
+    degree_b: usize,
+) -> Result<f64> {
+    let value = match metric {
+        SimilarityMetric::CommonNeighbors => shared.len() as f64,
+        SimilarityMetric::Jaccard => {
+            let union = degree_a + degree_b - shared.len();
+            if union == 0 {
+                0.0
+            } else {
+                shared.len() as f64 / union as f64
+            }
+        }
+        SimilarityMetric::AdamicAdar => {
+            let mut sum = 0.0;
+            for &w in shared {
+                let degree = sorted_neighbors(graph, w)?.len();
+                // A degree-1 neighbor would divide by ln(1) = 0
+                if degree > 1 {
+                    sum += 1.0 / (degree as f64).ln();
+                }
+            }
+            sum
+        }
+        SimilarityMetric::ResourceAllocation => {
+            let mut sum = 0.0;
+            for &w in shared {
+                let degree = sorted_neighbors(graph, w)?.len();
+                if degree > 0 {
+                    sum += 1.0 / degree as f64;
+                }
+            }
+            sum
+        }
+    };
+
+    Ok(value)
+}
+
+/// Distinct neighbors of a node in ascending order, excluding self-loops
+pub(crate) fn sorted_neighbors(graph: &Graph, id: NodeId) -> Result<Vec<NodeId>> {
+    let mut neighbors = graph.neighbors(id)?;
+    neighbors.retain(|&n| n != id);
+    if !graph.has_sorted_adjacency() {
+        neighbors.sort_unstable();
+    }
+    neighbors.dedup();
+    Ok(neighbors)
+}
+
+/// Elements present in both sorted slices
+pub(crate) fn sorted_intersection(a: &[NodeId], b: &[NodeId]) -> Vec<NodeId> {
+    let mut shared = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                shared.push(a[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    shared
+}
+
+#[cfg(all(test, not(feature = "uuid-ids")))]
+mod tests {
+    use super::*;
+
+    /// 0-1, 0-2, 1-2, 1-3, 2-3, plus isolated node 4
+    fn create_test_graph() -> Graph {
+        let mut graph = Graph::new();
+        for i in 0..5 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        graph.add_edge(0, 1, 1.0).unwrap();
+        graph.add_edge(0, 2, 1.0).unwrap();
+        graph.add_edge(1, 2, 1.0).unwrap();
+        graph.add_edge(1, 3, 1.0).unwrap();
+        graph.add_edge(2, 3, 1.0).unwrap();
+        graph
+    }
+
+    #[test]
+    fn test_pairwise_similarity() {
+        let graph = create_test_graph();
+
+        // N(0) = {1, 2}, N(3) = {1, 2}
+        assert_eq!(common_neighbors(&graph, 0, 3).unwrap(), 2);
+        assert!((jaccard_coefficient(&graph, 0, 3).unwrap() - 1.0).abs() < 1e-9);
+
+        // Nodes 1 and 2 both have degree 3
+        let expected_aa = 2.0 / 3f64.ln();
+        assert!((adamic_adar_index(&graph, 0, 3).unwrap() - expected_aa).abs() < 1e-9);
+        assert!((resource_allocation_index(&graph, 0, 3).unwrap() - 2.0 / 3.0).abs() < 1e-9);
+
+        assert!(common_neighbors(&graph, 0, 99).is_err());
+    }
+
+    #[test]
+    fn test_common_neighbor_matrix() {
+        let graph = create_test_graph();
+        let matrix = common_neighbor_matrix(&graph).unwrap();
+
+        assert_eq!(matrix.len(), 5);
+        for (i, row) in matrix.iter().enumerate() {
+            assert_eq!(row[i], graph.degree(i).unwrap());
+            for (j, &count) in row.iter().enumerate() {
+                assert_eq!(count, matrix[j][i]);
+            }
+        }
+        assert_eq!(matrix[0][3], 2);
+        assert_eq!(matrix[1][2], 2);
+        assert_eq!(matrix[0][4], 0);
+    }
+
+    #[test]
+    fn test_top_k() {
+        let graph = create_test_graph();
+
+        let top = node_similarity_top_k(&graph, 0, 1, SimilarityMetric::Jaccard).unwrap();
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].0, 3);
+
+        let all = node_similarity_top_k(&graph, 0, 10, SimilarityMetric::CommonNeighbors).unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0], (3, 2.0));
+        assert!(all.iter().all(|&(node, _)| node != 0));
+    }
+
+    #[test]
+    fn test_top_k_isolated_node() {
+        let graph = create_test_graph();
+        for metric in [
+            SimilarityMetric::CommonNeighbors,
+            SimilarityMetric::Jaccard,
+            SimilarityMetric::AdamicAdar,
+            SimilarityMetric::ResourceAllocation,
+        ] {
+            assert!(node_similarity_top_k(&graph, 4, 3, metric).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_top_k_directed_shared_target() {
+        let mut graph = Graph::new_directed();
+        for i in 0..3 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        graph.add_edge(0, 2, 1.0).unwrap();
+        graph.add_edge(1, 2, 1.0).unwrap();
+
+        assert_eq!(common_neighbors(&graph, 0, 1).unwrap(), 1);
+        let top = node_similarity_top_k(&graph, 0, 5, SimilarityMetric::CommonNeighbors).unwrap();
+        assert_eq!(top, vec![(1, 1.0)]);
+
+        let matrix = common_neighbor_matrix(&graph).unwrap();
+        assert_eq!(matrix[0][1], 1);
+        assert_eq!(matrix[1][0], 1);
+        assert_eq!(matrix[2][2], 0);
+    }
+
+    #[test]
+    fn test_edge_neighborhood_stats() {
+        let graph = create_test_graph();
+
+        // 0-1 lies in the triangle 0-1-2 only; node 3 neighbors 1 alone
+        assert_eq!(edge_neighborhood_stats(&graph, 0, 1).unwrap(), (1, 3, 1.0 / 3.0));
+        // 1-2 lies in both triangles
+        assert_eq!(edge_neighborhood_stats(&graph, 1, 2).unwrap(), (2, 3, 2.0 / 3.0));
+        assert!(matches!(
+            edge_neighborhood_stats(&graph, 0, 3),
+            Err(GraphError::EdgeNotFound(0, 3))
+        ));
+
+        let mut triangle = Graph::new();
+        for i in 0..3 {
+            triangle.add_node_simple(format!("Node{}", i));
+        }
+        triangle.add_edge(0, 1, 1.0).unwrap();
+        triangle.add_edge(1, 2, 1.0).unwrap();
+        triangle.add_edge(2, 0, 1.0).unwrap();
+        assert_eq!(edge_neighborhood_stats(&triangle, 0, 1).unwrap(), (1, 2, 0.5));
+    }
+}
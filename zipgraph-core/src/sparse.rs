@@ -0,0 +1,238 @@
+//! Sparse matrix representations of graphs
+//!
+//! Matrices are returned in CSR form as `(col_indices, row_pointers,
+//! values)`: the entries of row `i` are `col_indices[row_pointers[i]..
+//! row_pointers[i + 1]]` with matching `values`. Rows and columns follow
+//! ascending node ID order and column indices are sorted within each row,
+//! so the parts can be passed straight to `sprs::CsMat::new`. With the
+//! `sparse` feature enabled, `to_csmat` and `to_laplacian_csmat` do that
+//! conversion.
+
+use crate::graph::Graph;
+use crate::types::{NodeId, Weight};
+use std::collections::HashMap;
+
+/// CSR parts as `(col_indices, row_pointers, values)`
+pub type CsrParts = (Vec<usize>, Vec<usize>, Vec<Weight>);
+
+/// Weighted adjacency matrix in CSR form
+///
+/// On undirected graphs every edge appears in both endpoint rows, so a
+/// graph without parallel edges has `edge_count` non-zero entries when
+/// directed and `2 * edge_count` when undirected. Parallel edges are merged
+/// into one entry holding the sum of their weights.
+pub fn to_sparse_adjacency(graph: &Graph) -> CsrParts {
+    let rows = sorted_rows(graph);
+
+    let mut col_indices = Vec::new();
+    let mut row_pointers = Vec::with_capacity(rows.len() + 1);
+    let mut values = Vec::new();
+
+    row_pointers.push(0);
+    for row in rows {
+        for (col, weight) in row {
+            col_indices.push(col);
+            values.push(weight);
+        }
+        row_pointers.push(col_indices.len());
+    }
+
+    (col_indices, row_pointers, values)
+}
+
+/// Laplacian matrix `L = D - A` in CSR form
+///
+/// `D` holds the weighted (out-)degree of each node, i.e. the row sums of
+/// the adjacency matrix. Every row stores its diagonal entry, even when it
+/// is zero, so each row of `L` sums to zero.
+pub fn to_laplacian_sparse(graph: &Graph) -> CsrParts {
+    let rows = sorted_rows(graph);
+
+    let mut col_indices = Vec::new();
+    let mut row_pointers = Vec::with_capacity(rows.len() + 1);
+    let mut values = Vec::new();
+
+    row_pointers.push(0);
+    for (i, row) in rows.into_iter().enumerate() {
+        let degree: Weight = row.iter().map(|&(_, weight)| weight).sum();
+        let self_loop = row
+            .iter()
+            .find(|&&(col, _)| col == i)
+            .map_or(0.0, |&(_, weight)| weight);
+
+        let mut diagonal_written = false;
+        for (col, weight) in row {
+            if col == i {
+                continue;
+            }
+            if col > i && !diagonal_written {
+                col_indices.push(i);
+                values.push(degree - self_loop);
+                diagonal_written = true;
+            }
+            col_indices.push(col);
+            values.push(-weight);
+        }
+        if !diagonal_written {
+            col_indices.push(i);
+            values.push(degree - self_loop);
+        }
+        row_pointers.push(col_indices.len());
+    }
+
+    (col_indices, row_pointers, values)
+}
+
+/// Weighted adjacency matrix as a `sprs` CSR matrix
+#[cfg(feature = "sparse")]
+pub fn to_csmat(graph: &Graph) -> sprs::CsMat<Weight> {
+    let n = graph.node_count();
+    let (col_indices, row_pointers, values) = to_sparse_adjacency(graph);
+    sprs::CsMat::new((n, n), row_pointers, col_indices, values)
+}
+
+/// Laplacian matrix as a `sprs` CSR matrix
+#[cfg(feature = "sparse")]
+pub fn to_laplacian_csmat(graph: &Graph) -> sprs::CsMat<Weight> {
+    let n = graph.node_count();
+    let (col_indices, row_pointers, values) = to_laplacian_sparse(graph);
+    sprs::CsMat::new((n, n), row_pointers, col_indices, values)
+}
+
+#[cfg(feature = "sparse")]
+impl Graph {
+    /// Weighted adjacency matrix as a `sprs` CSR matrix, see `to_csmat`
+    pub fn to_sparse_matrix(&self) -> sprs::CsMat<Weight> {
+        to_csmat(self)
+    }
+}
+
+/// Adjacency rows in node ID order with sorted, merged column entries
+fn sorted_rows(graph: &Graph) -> Vec<Vec<(usize, Weight)>> {
+    let mut node_ids = graph.node_ids();
+    node_ids.sort_unstable();
+    let index: HashMap<NodeId, usize> =
+        node_ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+
+    node_ids
+        .iter()
+        .map(|&id| {
+            let mut row: Vec<(usize, Weight)> = graph
+                .neighbors_with_weights(id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(neighbor, weight)| (index[&neighbor], weight))
+                .collect();
+            row.sort_by_key(|&(col, _)| col);
+
+            let mut merged: Vec<(usize, Weight)> = Vec::with_capacity(row.len());
+            for (col, weight) in row {
+                match merged.last_mut() {
+                    Some(last) if last.0 == col => last.1 += weight,
+                    _ => merged.push((col, weight)),
+                }
+            }
+            merged
+        })
+        .collect()
+}
+
+#[cfg(all(test, not(feature = "uuid-ids")))]
+mod tests {
+    use super::*;
+    use crate::centrality;
+
+    fn create_test_graph(directed: bool) -> Graph {
+        let mut graph = if directed { Graph::new_directed() } else { Graph::new() };
+        for i in 0..5 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        graph.add_edge(0, 1, 1.0).unwrap();
+        graph.add_edge(0, 2, 1.0).unwrap();
+        graph.add_edge(1, 2, 1.0).unwrap();
+        graph.add_edge(2, 0, 1.0).unwrap();
+        graph.add_edge(3, 2, 1.0).unwrap();
+        graph.add_edge(4, 3, 1.0).unwrap();
+        graph
+    }
+
+    #[test]
+    fn test_non_zero_count() {
+        let mut directed = create_test_graph(true);
+        let (cols, rows, values) = to_sparse_adjacency(&directed);
+        assert_eq!(cols.len(), directed.edge_count());
+        assert_eq!(values.len(), directed.edge_count());
+        assert_eq!(rows, vec![0, 2, 3, 4, 5, 6]);
+
+        // Without 2 -> 0, which would duplicate 0 - 2 once undirected
+        let mut undirected = Graph::new();
+        for i in 0..5 {
+            undirected.add_node_simple(format!("Node{}", i));
+        }
+        for edge in directed.edges().iter().filter(|e| (e.from, e.to) != (2, 0)) {
+            undirected.add_edge(edge.from, edge.to, edge.weight).unwrap();
+        }
+        let (cols, rows, _) = to_sparse_adjacency(&undirected);
+        assert_eq!(cols.len(), 2 * undirected.edge_count());
+        assert_eq!(*rows.last().unwrap(), cols.len());
+
+        directed.add_node_simple("isolated");
+        let (_, rows, _) = to_sparse_adjacency(&directed);
+        assert_eq!(rows.len(), 7);
+        assert_eq!(rows[6], rows[5]);
+    }
+
+    #[test]
+    fn test_laplacian_rows_sum_to_zero() {
+        for directed in [false, true] {
+            let graph = create_test_graph(directed);
+            let (cols, rows, values) = to_laplacian_sparse(&graph);
+
+            for i in 0..graph.node_count() {
+                let range = rows[i]..rows[i + 1];
+                let row_cols = &cols[range.clone()];
+                assert!(row_cols.windows(2).all(|w| w[0] < w[1]));
+                assert!(row_cols.contains(&i));
+
+                let sum: f64 = values[range].iter().sum();
+                assert!(sum.abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_power_iteration_matches_pagerank() {
+        let graph = create_test_graph(true);
+        let n = graph.node_count();
+        let damping = 0.85;
+        let (cols, rows, values) = to_sparse_adjacency(&graph);
+
+        // One step of x' = (1 - d) / n + d * Aᵀ D⁻¹ x from the uniform vector
+        let rank = vec![1.0 / n as f64; n];
+        let mut next = vec![(1.0 - damping) / n as f64; n];
+        for i in 0..n {
+            let out_degree: f64 = values[rows[i]..rows[i + 1]].iter().sum();
+            for k in rows[i]..rows[i + 1] {
+                next[cols[k]] += damping * values[k] * rank[i] / out_degree;
+            }
+        }
+
+        let expected = centrality::pagerank(&graph, damping, 1, 0.0).unwrap();
+        for (i, value) in next.iter().enumerate() {
+            assert!((value - expected[&i]).abs() < 1e-12);
+        }
+    }
+
+    #[cfg(feature = "sparse")]
+    #[test]
+    fn test_csmat_conversion() {
+        let graph = create_test_graph(false);
+        let matrix = graph.to_sparse_matrix();
+        assert_eq!(matrix.shape(), (5, 5));
+        assert_eq!(matrix.nnz(), 10);
+        assert_eq!(matrix.get(2, 0), Some(&2.0));
+
+        let laplacian = to_laplacian_csmat(&graph);
+        assert_eq!(laplacian.get(0, 0), Some(&3.0));
+    }
+}
@@ -1,10 +1,27 @@
 //! Graph statistics and analysis
 
+use crate::algorithms;
+use crate::error::{GraphError, Result};
+use crate::generators;
 use crate::graph::Graph;
+use crate::similarity::{sorted_intersection, sorted_neighbors};
+use crate::types::NodeId;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Largest graph for which `average_shortest_path_length` runs exactly
+pub const EXACT_PATH_LENGTH_MAX_NODES: usize = 1_000;
+
+/// Number of BFS sources used when estimating path length on large graphs
+const DEFAULT_PATH_LENGTH_SAMPLES: usize = 100;
 
 /// Graph statistics used for ML features
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct GraphStats {
     pub node_count: usize,
     pub edge_count: usize,
@@ -15,6 +32,17 @@ pub struct GraphStats {
     pub is_directed: bool,
     pub clustering_coefficient: Option<f64>,
     pub diameter: Option<usize>,
+    pub average_shortest_path_length: Option<f64>,
+    /// Hops within which 90% of reachable pairs lie, estimated by HyperANF
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective_diameter: Option<f64>,
+    /// Exponent of the power law fitted to the degree tail, see
+    /// `fit_power_law`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub power_law_alpha: Option<f64>,
+    /// Three-node subgraph counts, see `compute_motif_profile`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub motif_profile: Option<MotifProfile>,
 }
 
 impl GraphStats {
@@ -60,9 +88,83 @@ impl GraphStats {
             is_directed: graph.is_directed(),
             clustering_coefficient: None, // Computed on demand
             diameter: None,               // Computed on demand
+            average_shortest_path_length: None, // Computed on demand
+            effective_diameter: None,           // Computed on demand
+            power_law_alpha: None,              // Computed on demand
+            motif_profile: None,                // Computed on demand
         }
     }
 
+    /// Compute the average shortest path length and store it in the stats
+    ///
+    /// Exact for graphs up to `EXACT_PATH_LENGTH_MAX_NODES` nodes, sampled
+    /// from a fixed seed beyond that.
+    pub fn compute_average_shortest_path_length(&mut self, graph: &Graph) -> Result<f64> {
+        let aspl = if graph.node_count() <= EXACT_PATH_LENGTH_MAX_NODES {
+            average_shortest_path_length(graph)?
+        } else {
+            approximate_average_shortest_path_length(graph, DEFAULT_PATH_LENGTH_SAMPLES, Some(0))?
+        };
+        self.average_shortest_path_length = Some(aspl);
+        Ok(aspl)
+    }
+
+    /// Compute the diameter and store it in the stats
+    ///
+    /// Exact for graphs up to `algorithms::EXACT_DIAMETER_MAX_NODES` nodes,
+    /// the double-sweep lower bound beyond that; see
+    /// `algorithms::exact_diameter_small`.
+    pub fn compute_diameter(&mut self, graph: &Graph) -> Result<usize> {
+        let diameter = if graph.node_count() <= algorithms::EXACT_DIAMETER_MAX_NODES {
+            algorithms::exact_diameter_small(graph)?
+        } else {
+            algorithms::double_sweep_diameter(graph)?.0
+        };
+        self.diameter = Some(diameter);
+        Ok(diameter)
+    }
+
+    /// Estimate the effective diameter and store it in the stats
+    ///
+    /// Runs `algorithms::neighborhood_function` until every ball stops
+    /// growing and interpolates the 90th percentile of the pair distance
+    /// distribution, see `algorithms::effective_diameter`. Returns `None`
+    /// when no two distinct nodes are connected.
+    pub fn compute_effective_diameter(&mut self, graph: &Graph) -> Option<f64> {
+        let neighborhood = algorithms::hyper_anf(
+            graph,
+            graph.node_count(),
+            algorithms::DEFAULT_HLL_PRECISION,
+            true,
+        );
+        self.effective_diameter = algorithms::effective_diameter(&neighborhood);
+        self.effective_diameter
+    }
+
+    /// Fit a power law to the degree distribution and store its exponent
+    /// in the stats, see `fit_power_law`
+    pub fn compute_power_law_alpha(&mut self, graph: &Graph) -> Result<f64> {
+        let fit = fit_power_law(graph)?;
+        self.power_law_alpha = Some(fit.alpha);
+        Ok(fit.alpha)
+    }
+
+    /// Count the graph's three-node motifs and store them in the stats,
+    /// see `compute_motif_profile`
+    pub fn compute_motif_profile(&mut self, graph: &Graph) -> MotifProfile {
+        let profile = compute_motif_profile(graph);
+        self.motif_profile = Some(profile);
+        profile
+    }
+
+    /// Compute the average clustering coefficient and store it in the
+    /// stats, see `average_clustering_coefficient`
+    pub fn compute_clustering_coefficient(&mut self, graph: &Graph) -> f64 {
+        let coefficient = average_clustering_coefficient(graph);
+        self.clustering_coefficient = Some(coefficient);
+        coefficient
+    }
+
     /// Check if the graph is sparse
     pub fn is_sparse(&self) -> bool {
         self.density < 0.1
@@ -85,13 +187,615 @@ impl GraphStats {
             if self.is_directed { 1.0 } else { 0.0 },
             self.clustering_coefficient.unwrap_or(0.0),
             self.diameter.unwrap_or(0) as f64,
+            self.power_law_alpha.unwrap_or(0.0),
+        ]
+    }
+}
+
+/// Orders by `density`
+///
+/// Stats that differ in other fields but have the same density are
+/// unordered, so that `partial_cmp` returns `Equal` exactly for equal
+/// stats.
+impl PartialOrd for GraphStats {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        if self == other {
+            return Some(std::cmp::Ordering::Equal);
+        }
+        match self.density.partial_cmp(&other.density)? {
+            std::cmp::Ordering::Equal => None,
+            ordering => Some(ordering),
+        }
+    }
+}
+
+/// Fail if `node_count`, `edge_count`, `avg_degree` or `density` of
+/// `current` differs from `baseline` by more than `tolerance`, a fraction
+/// of the baseline value
+///
+/// A zero baseline only accepts zero. The error names every field out of
+/// tolerance, in the format of `diff_stats`.
+pub fn assert_no_regression(
+    baseline: &GraphStats,
+    current: &GraphStats,
+    tolerance: f64,
+) -> Result<()> {
+    if tolerance.is_nan() || tolerance < 0.0 {
+        return Err(GraphError::InvalidParameter(format!(
+            "tolerance must be non-negative, got {}",
+            tolerance
+        )));
+    }
+
+    let checked = [
+        ("node_count", baseline.node_count as f64, current.node_count as f64),
+        ("edge_count", baseline.edge_count as f64, current.edge_count as f64),
+        ("avg_degree", baseline.avg_degree, current.avg_degree),
+        ("density", baseline.density, current.density),
+    ];
+    let regressions: Vec<String> = checked
+        .iter()
+        .filter(|(_, old, new)| (new - old).abs() > tolerance * old.abs() || new.is_nan())
+        .map(|&(name, old, new)| {
+            describe_change(name, &(old.to_string(), Some(old)), &(new.to_string(), Some(new)))
+        })
+        .collect();
+
+    if regressions.is_empty() {
+        Ok(())
+    } else {
+        Err(GraphError::InvalidData(format!(
+            "graph statistics changed by more than {}%: {}",
+            tolerance * 100.0,
+            regressions.join("; ")
+        )))
+    }
+}
+
+/// One line per field that differs between `baseline` and `current`, such
+/// as `density: 0.1 -> 0.105 (+5.0%)`
+pub fn diff_stats(baseline: &GraphStats, current: &GraphStats) -> Vec<String> {
+    let fields = |stats: &GraphStats| -> [(&'static str, StatValue); 13] {
+        let count = |value: usize| (value.to_string(), Some(value as f64));
+        let real = |value: f64| (value.to_string(), Some(value));
+        let optional = |value: Option<String>| (value.unwrap_or_else(|| "none".to_string()), None);
+        [
+            ("node_count", count(stats.node_count)),
+            ("edge_count", count(stats.edge_count)),
+            ("avg_degree", real(stats.avg_degree)),
+            ("max_degree", count(stats.max_degree)),
+            ("min_degree", count(stats.min_degree)),
+            ("density", real(stats.density)),
+            ("is_directed", (stats.is_directed.to_string(), None)),
+            (
+                "clustering_coefficient",
+                optional(stats.clustering_coefficient.map(|c| c.to_string())),
+            ),
+            ("diameter", optional(stats.diameter.map(|d| d.to_string()))),
+            (
+                "average_shortest_path_length",
+                optional(stats.average_shortest_path_length.map(|l| l.to_string())),
+            ),
+            ("effective_diameter", optional(stats.effective_diameter.map(|d| d.to_string()))),
+            ("power_law_alpha", optional(stats.power_law_alpha.map(|a| a.to_string()))),
+            ("motif_profile", optional(stats.motif_profile.map(|p| format!("{:?}", p)))),
         ]
+    };
+
+    fields(baseline)
+        .into_iter()
+        .zip(fields(current))
+        .filter(|((_, old), (_, new))| old.0 != new.0)
+        .map(|((name, old), (_, new))| describe_change(name, &old, &new))
+        .collect()
+}
+
+/// A statistic as displayed, and as a number if it is one
+type StatValue = (String, Option<f64>);
+
+/// `name: old -> new`, with the relative change if both are numbers and
+/// the old one is not zero
+fn describe_change(name: &str, old: &StatValue, new: &StatValue) -> String {
+    match (old.1, new.1) {
+        (Some(a), Some(b)) if a != 0.0 => {
+            format!("{}: {} -> {} ({:+.1}%)", name, old.0, new.0, (b - a) / a.abs() * 100.0)
+        }
+        _ => format!("{}: {} -> {}", name, old.0, new.0),
+    }
+}
+
+/// Average hop distance over all reachable ordered pairs of nodes
+///
+/// Runs a BFS from every node, so it is limited to graphs with at most
+/// `EXACT_PATH_LENGTH_MAX_NODES` nodes; use
+/// `approximate_average_shortest_path_length` for larger graphs.
+/// Unreachable pairs are excluded from the average.
+pub fn average_shortest_path_length(graph: &Graph) -> Result<f64> {
+    if graph.node_count() > EXACT_PATH_LENGTH_MAX_NODES {
+        return Err(GraphError::InvalidParameter(format!(
+            "exact average shortest path length is limited to {} nodes (graph has {}); \
+             use approximate_average_shortest_path_length",
+            EXACT_PATH_LENGTH_MAX_NODES,
+            graph.node_count()
+        )));
+    }
+
+    let sources = graph.node_ids();
+    Ok(mean_distance_from(graph, &sources))
+}
+
+/// Mean of the local clustering coefficients of all nodes
+///
+/// The local coefficient of a node is the fraction of pairs of its
+/// neighbors that are adjacent themselves; nodes with fewer than two
+/// neighbors contribute zero. Edge directions, weights, self-loops and
+/// parallel edges are ignored. Zero for an empty graph.
+///
+/// Undirected graphs with sorted adjacency (see `Graph::sort_adjacency`)
+/// count each node's links by merging sorted neighbor lists instead of
+/// building neighbor sets.
+pub fn average_clustering_coefficient(graph: &Graph) -> f64 {
+    if !graph.is_directed() && graph.has_sorted_adjacency() {
+        return sorted_clustering_coefficient(graph);
+    }
+
+    let neighbors = simple_neighbor_sets(graph);
+    if neighbors.is_empty() {
+        return 0.0;
+    }
+
+    let total: f64 = neighbors
+        .values()
+        .map(|adjacent| {
+            let degree = adjacent.len();
+            if degree < 2 {
+                return 0.0;
+            }
+            let links = adjacent
+                .iter()
+                .map(|a| adjacent.iter().filter(|b| neighbors[a].contains(b)).count())
+                .sum::<usize>()
+                / 2;
+            links as f64 / (degree * (degree - 1) / 2) as f64
+        })
+        .sum();
+    total / neighbors.len() as f64
+}
+
+/// Counts of the connected three-node subgraphs of a graph
+///
+/// Counted on the simple undirected graph underneath, as in
+/// `average_clustering_coefficient`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct MotifProfile {
+    /// Node triples joined by all three edges
+    pub triangles: usize,
+    /// Node triples joined by exactly two edges
+    pub open_triads: usize,
+    /// Paths of two edges (wedges), closed or not: three per triangle and
+    /// one per open triad
+    pub paths3: usize,
+    /// Stars of a center and three of its neighbors
+    pub stars3: usize,
+}
+
+/// Count the triangles, open triads, two-edge paths and three-leaf stars
+/// of `graph`
+pub fn compute_motif_profile(graph: &Graph) -> MotifProfile {
+    let neighbors = simple_neighbor_sets(graph);
+
+    let mut profile = MotifProfile::default();
+    for (&node, adjacent) in &neighbors {
+        let degree = adjacent.len();
+        profile.paths3 += degree * degree.saturating_sub(1) / 2;
+        profile.stars3 += degree * degree.saturating_sub(1) * degree.saturating_sub(2) / 6;
+        // Each triangle once, from its smallest node
+        for &a in adjacent.iter().filter(|&&a| a > node) {
+            profile.triangles +=
+                neighbors[&a].iter().filter(|&&b| b > a && adjacent.contains(&b)).count();
+        }
+    }
+    profile.open_triads = profile.paths3 - 3 * profile.triangles;
+    profile
+}
+
+/// Global clustering coefficient (transitivity) of a motif profile
+///
+/// The fraction of two-edge paths that are closed, `3 * triangles /
+/// (3 * triangles + open_triads)`; zero when there are no such paths.
+pub fn global_clustering_from_motif_profile(profile: &MotifProfile) -> f64 {
+    let paths = 3 * profile.triangles + profile.open_triads;
+    if paths == 0 {
+        0.0
+    } else {
+        (3 * profile.triangles) as f64 / paths as f64
+    }
+}
+
+/// `average_clustering_coefficient` of an undirected graph with sorted
+/// adjacency
+fn sorted_clustering_coefficient(graph: &Graph) -> f64 {
+    let neighbors: HashMap<NodeId, Vec<NodeId>> = graph
+        .node_ids()
+        .into_iter()
+        .map(|id| (id, sorted_neighbors(graph, id).expect("listed node")))
+        .collect();
+    if neighbors.is_empty() {
+        return 0.0;
+    }
+
+    let total: f64 = neighbors
+        .values()
+        .map(|adjacent| {
+            let degree = adjacent.len();
+            if degree < 2 {
+                return 0.0;
+            }
+            let links = adjacent
+                .iter()
+                .map(|a| sorted_intersection(adjacent, &neighbors[a]).len())
+                .sum::<usize>()
+                / 2;
+            links as f64 / (degree * (degree - 1) / 2) as f64
+        })
+        .sum();
+    total / neighbors.len() as f64
+}
+
+/// Every node's neighbors, ignoring edge directions, self-loops and
+/// parallel edges
+fn simple_neighbor_sets(graph: &Graph) -> HashMap<NodeId, HashSet<NodeId>> {
+    let mut neighbors: HashMap<NodeId, HashSet<NodeId>> =
+        graph.node_ids().into_iter().map(|id| (id, HashSet::new())).collect();
+    for edge in graph.edges() {
+        if edge.from != edge.to {
+            neighbors.entry(edge.from).or_default().insert(edge.to);
+            neighbors.entry(edge.to).or_default().insert(edge.from);
+        }
+    }
+    neighbors
+}
+
+/// Estimate the average shortest path length from `sample_size` BFS sources
+///
+/// Sources are drawn uniformly without replacement; passing a `seed` makes
+/// the estimate reproducible. Unreachable pairs are excluded.
+pub fn approximate_average_shortest_path_length(
+    graph: &Graph,
+    sample_size: usize,
+    seed: Option<u64>,
+) -> Result<f64> {
+    if sample_size == 0 {
+        return Err(GraphError::InvalidParameter(
+            "sample_size must be greater than 0".to_string(),
+        ));
+    }
+
+    // Sort so that a fixed seed picks the same sources on every run
+    let mut node_ids = graph.node_ids();
+    node_ids.sort_unstable();
+
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let sources: Vec<NodeId> = node_ids
+        .choose_multiple(&mut rng, sample_size)
+        .copied()
+        .collect();
+
+    Ok(mean_distance_from(graph, &sources))
+}
+
+/// Summary of one numeric node property
+///
+/// `min`, `max`, `mean` and `std_dev` (population) are zero when no node
+/// has the property.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+pub struct PropertyStats {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub std_dev: f64,
+    /// Nodes that have the property
+    pub count: usize,
+    /// Nodes that do not
+    pub missing_count: usize,
+}
+
+/// Summarize the values of property `key` over the nodes that have it
+pub fn node_property_stats(graph: &Graph, key: &str) -> Result<PropertyStats> {
+    let values: Vec<f64> = graph
+        .node_ids()
+        .into_iter()
+        .filter_map(|id| graph.node(id).ok()?.properties.get(key).copied())
+        .collect();
+    let missing_count = graph.node_count() - values.len();
+    if values.is_empty() {
+        return Ok(PropertyStats {
+            missing_count,
+            ..PropertyStats::default()
+        });
+    }
+
+    let count = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / count;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / count;
+
+    Ok(PropertyStats {
+        min: values.iter().copied().fold(f64::INFINITY, f64::min),
+        max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        mean,
+        std_dev: variance.sqrt(),
+        count: values.len(),
+        missing_count,
+    })
+}
+
+/// Pearson correlation of properties `key_a` and `key_b` over the nodes
+/// that have both
+///
+/// Fails with `InvalidData` when fewer than two nodes have both or either
+/// property is constant over them, since the correlation is undefined.
+pub fn correlation_between_properties(graph: &Graph, key_a: &str, key_b: &str) -> Result<f64> {
+    let pairs: Vec<(f64, f64)> = graph
+        .node_ids()
+        .into_iter()
+        .filter_map(|id| {
+            let properties = &graph.node(id).ok()?.properties;
+            Some((*properties.get(key_a)?, *properties.get(key_b)?))
+        })
+        .collect();
+    if pairs.len() < 2 {
+        return Err(GraphError::InvalidData(format!(
+            "correlation of '{}' and '{}' needs two nodes with both, found {}",
+            key_a,
+            key_b,
+            pairs.len()
+        )));
+    }
+
+    let count = pairs.len() as f64;
+    let mean_a = pairs.iter().map(|p| p.0).sum::<f64>() / count;
+    let mean_b = pairs.iter().map(|p| p.1).sum::<f64>() / count;
+    let (mut covariance, mut variance_a, mut variance_b) = (0.0f64, 0.0f64, 0.0f64);
+    for &(a, b) in &pairs {
+        covariance += (a - mean_a) * (b - mean_b);
+        variance_a += (a - mean_a).powi(2);
+        variance_b += (b - mean_b).powi(2);
+    }
+    if variance_a == 0.0 || variance_b == 0.0 {
+        return Err(GraphError::InvalidData(format!(
+            "correlation of '{}' and '{}' is undefined for a constant property",
+            key_a, key_b
+        )));
+    }
+
+    Ok(covariance / (variance_a * variance_b).sqrt())
+}
+
+/// Fewest degrees of at least `xmin` that `fit_power_law` fits a tail to
+pub const POWER_LAW_MIN_TAIL: usize = 50;
+
+/// A power law `P(k) ~ k^-alpha` fitted to the degrees `k >= xmin`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PowerLawFit {
+    pub alpha: f64,
+    pub xmin: usize,
+    /// Largest gap between the empirical and the fitted tail distribution
+    pub ks_statistic: f64,
+}
+
+/// Fit a power law to the tail of the degree distribution
+///
+/// A simplified version of Clauset, Shalizi and Newman: for every
+/// candidate `xmin` among the distinct degrees, `alpha` is the
+/// approximate discrete maximum likelihood estimate
+/// `1 + n / sum(ln(k / (xmin - 0.5)))` over the `n` degrees `k >= xmin`,
+/// and the fit with the smallest Kolmogorov–Smirnov distance wins.
+///
+/// Unlike the paper, the tail must hold at least a quarter of the nodes
+/// and at least `POWER_LAW_MIN_TAIL` of them: on graphs of a few thousand
+/// nodes the outermost hundred degrees of an Erdős–Rényi graph fit a
+/// steep power law as well as a genuine heavy tail does. Fails with
+/// `InvalidData` when the graph has no such tail. Isolated nodes are
+/// ignored.
+pub fn fit_power_law(graph: &Graph) -> Result<PowerLawFit> {
+    let mut degrees: Vec<usize> = graph
+        .node_ids()
+        .into_iter()
+        .filter_map(|id| graph.degree(id).ok())
+        .filter(|&degree| degree > 0)
+        .collect();
+    degrees.sort_unstable();
+
+    let mut best: Option<PowerLawFit> = None;
+    let mut start = 0;
+    let min_tail = POWER_LAW_MIN_TAIL.max(degrees.len() / 4);
+    while degrees.len() - start >= min_tail {
+        let xmin = degrees[start];
+        let fit = fit_tail(&degrees[start..], xmin);
+        if best.is_none_or(|best| fit.ks_statistic < best.ks_statistic) {
+            best = Some(fit);
+        }
+        start += degrees[start..].partition_point(|&degree| degree == xmin);
     }
+
+    best.ok_or_else(|| {
+        GraphError::InvalidData(format!(
+            "power law fit needs {} nodes with positive degree, found {}",
+            POWER_LAW_MIN_TAIL,
+            degrees.len()
+        ))
+    })
 }
 
-#[cfg(test)]
+/// Whether the degree distribution is fitted by a power law with a
+/// Kolmogorov–Smirnov distance of at most `ks_threshold`
+///
+/// Graphs too small for `fit_power_law` are not scale-free.
+pub fn is_scale_free(graph: &Graph, ks_threshold: f64) -> bool {
+    fit_power_law(graph).is_ok_and(|fit| fit.ks_statistic <= ks_threshold)
+}
+
+/// Double-edge swaps per edge in each null sample of `zscore_against_null`
+pub const NULL_MODEL_SWAPS_PER_EDGE: usize = 10;
+
+/// A metric of a graph against its values on degree-preserving
+/// randomizations of the graph, see `zscore_against_null`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct NullModelScore {
+    /// The metric of the graph itself
+    pub observed: f64,
+    /// Mean of the metric over the null samples
+    pub null_mean: f64,
+    /// Sample standard deviation of the metric over the null samples
+    pub null_std: f64,
+    /// `(observed - null_mean) / null_std`; infinite if the null samples
+    /// all agree and the graph differs from them, 0 if it does not
+    pub z_score: f64,
+    /// Null samples drawn
+    pub samples: usize,
+}
+
+/// How far `metric` of `graph` lies from its values on `num_samples`
+/// degree-preserving null models of `graph`, in standard deviations
+///
+/// Each null sample is `generators::rewire` of `graph` with
+/// `NULL_MODEL_SWAPS_PER_EDGE` swaps per edge. A large positive z-score
+/// means the graph has much more of what `metric` measures than its
+/// degrees alone explain, e.g. clustering for
+/// `average_clustering_coefficient`. Samples are drawn and measured in
+/// parallel; with a `seed` the null models are the same whatever the
+/// number of threads. Fails with `InvalidParameter` if `num_samples` is below 2.
+pub fn zscore_against_null<F>(
+    graph: &Graph,
+    metric: F,
+    num_samples: usize,
+    seed: Option<u64>,
+) -> Result<NullModelScore>
+where
+    F: Fn(&Graph) -> f64 + Sync,
+{
+    if num_samples < 2 {
+        return Err(GraphError::InvalidParameter(
+            "zscore_against_null needs at least 2 null samples".to_string(),
+        ));
+    }
+
+    let master = seed.unwrap_or_else(|| rand::thread_rng().gen());
+    let swaps = graph.edge_count().saturating_mul(NULL_MODEL_SWAPS_PER_EDGE);
+    let null: Vec<f64> = (0..num_samples as u64)
+        .into_par_iter()
+        .map(|sample| {
+            let seed = algorithms::splitmix64(master ^ algorithms::splitmix64(sample));
+            metric(&generators::rewire(graph, swaps, Some(seed)))
+        })
+        .collect();
+
+    let observed = metric(graph);
+    let null_mean = null.iter().sum::<f64>() / num_samples as f64;
+    let variance = null.iter().map(|value| (value - null_mean).powi(2)).sum::<f64>()
+        / (num_samples - 1) as f64;
+    let null_std = variance.sqrt();
+    let difference = observed - null_mean;
+    let z_score = if null_std > 0.0 {
+        difference / null_std
+    } else if difference == 0.0 {
+        0.0
+    } else {
+        f64::INFINITY.copysign(difference)
+    };
+    Ok(NullModelScore { observed, null_mean, null_std, z_score, samples: num_samples })
+}
+
+/// Power law fit of `tail`, sorted degrees that are all at least `xmin`
+fn fit_tail(tail: &[usize], xmin: usize) -> PowerLawFit {
+    let n = tail.len() as f64;
+    let shift = xmin as f64 - 0.5;
+    let log_sum: f64 = tail.iter().map(|&degree| (degree as f64 / shift).ln()).sum();
+    let alpha = if log_sum > 0.0 { 1.0 + n / log_sum } else { f64::INFINITY };
+
+    // Compare P(K >= k) at every distinct degree, where the model's is
+    // the continuous approximation ((k - 0.5) / (xmin - 0.5))^(1 - alpha)
+    let mut ks_statistic = 0.0f64;
+    let mut index = 0;
+    while index < tail.len() {
+        let degree = tail[index];
+        let empirical = (tail.len() - index) as f64 / n;
+        let model = ((degree as f64 - 0.5) / shift).powf(1.0 - alpha);
+        ks_statistic = ks_statistic.max((empirical - model).abs());
+        index += tail[index..].partition_point(|&other| other == degree);
+    }
+
+    PowerLawFit { alpha, xmin, ks_statistic }
+}
+
+/// Mean BFS distance from each source to every node it can reach
+fn mean_distance_from(graph: &Graph, sources: &[NodeId]) -> f64 {
+    let mut total_distance = 0usize;
+    let mut pair_count = 0usize;
+
+    for &source in sources {
+        let mut distances: HashMap<NodeId, usize> = HashMap::new();
+        let mut queue = VecDeque::new();
+        distances.insert(source, 0);
+        queue.push_back(source);
+
+        while let Some(current) = queue.pop_front() {
+            let depth = distances[&current];
+            if let Ok(neighbors) = graph.neighbors(current) {
+                for neighbor in neighbors {
+                    if let Entry::Vacant(entry) = distances.entry(neighbor) {
+                        entry.insert(depth + 1);
+                        total_distance += depth + 1;
+                        pair_count += 1;
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+        }
+    }
+
+    if pair_count == 0 {
+        0.0
+    } else {
+        total_distance as f64 / pair_count as f64
+    }
+}
+
+#[cfg(all(test, not(feature = "uuid-ids")))]
 mod tests {
     use super::*;
+    use rand::Rng;
+
+    fn path_graph(n: usize) -> Graph {
+        let mut graph = Graph::new();
+        for i in 0..n {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        for i in 0..n - 1 {
+            graph.add_edge(i, i + 1, 1.0).unwrap();
+        }
+        graph
+    }
+
+    fn erdos_renyi(n: usize, avg_degree: f64, seed: u64) -> Graph {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut graph = Graph::new();
+        for i in 0..n {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        let edges = (n as f64 * avg_degree / 2.0) as usize;
+        for _ in 0..edges {
+            let from = rng.gen_range(0..n);
+            let to = rng.gen_range(0..n);
+            if from != to {
+                graph.add_edge(from, to, 1.0).unwrap();
+            }
+        }
+        graph
+    }
 
     #[test]
     fn test_stats_empty_graph() {
@@ -113,4 +817,324 @@ mod tests {
         assert_eq!(stats.edge_count, 1);
         assert!(stats.avg_degree > 0.0);
     }
+
+    #[test]
+    fn test_aspl_path_graph() {
+        for n in [2, 5, 10, 30] {
+            let graph = path_graph(n);
+            let aspl = average_shortest_path_length(&graph).unwrap();
+            assert!((aspl - (n as f64 + 1.0) / 3.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_aspl_complete_graph() {
+        let mut graph = Graph::new();
+        for i in 0..6 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        for i in 0..6 {
+            for j in (i + 1)..6 {
+                graph.add_edge(i, j, 1.0).unwrap();
+            }
+        }
+        assert!((average_shortest_path_length(&graph).unwrap() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_aspl_excludes_unreachable_pairs() {
+        // Two disjoint edges: every reachable pair is at distance 1
+        let mut graph = Graph::new();
+        for i in 0..4 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        graph.add_edge(0, 1, 1.0).unwrap();
+        graph.add_edge(2, 3, 1.0).unwrap();
+        assert!((average_shortest_path_length(&graph).unwrap() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_aspl_exact_rejects_large_graph() {
+        let graph = path_graph(EXACT_PATH_LENGTH_MAX_NODES + 1);
+        assert!(average_shortest_path_length(&graph).is_err());
+    }
+
+    #[test]
+    fn test_approximate_aspl_close_to_exact() {
+        let small = erdos_renyi(1_000, 8.0, 42);
+        let exact = average_shortest_path_length(&small).unwrap();
+        let approx = approximate_average_shortest_path_length(&small, 100, Some(7)).unwrap();
+        assert!((approx - exact).abs() / exact < 0.05);
+
+        // The 10 000-node version is only tractable via sampling. An ER graph's
+        // path length grows like ln(n) / ln(k), i.e. by ln(10) / ln(k) per
+        // tenfold increase in size
+        let large = erdos_renyi(10_000, 8.0, 42);
+        let approx_large = approximate_average_shortest_path_length(&large, 20, Some(7)).unwrap();
+        let expected_large = exact + 10f64.ln() / 8f64.ln();
+        assert!((approx_large - expected_large).abs() / expected_large < 0.05);
+    }
+
+    #[test]
+    fn test_approximate_aspl_is_seeded() {
+        let graph = erdos_renyi(300, 4.0, 1);
+        let a = approximate_average_shortest_path_length(&graph, 20, Some(3)).unwrap();
+        let b = approximate_average_shortest_path_length(&graph, 20, Some(3)).unwrap();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_stats_average_shortest_path_length() {
+        let graph = path_graph(5);
+        let mut stats = GraphStats::from_graph(&graph);
+        assert!(stats.average_shortest_path_length.is_none());
+
+        stats.compute_average_shortest_path_length(&graph).unwrap();
+        assert!((stats.average_shortest_path_length.unwrap() - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_compute_effective_diameter() {
+        let graph = path_graph(10);
+        let mut stats = GraphStats::from_graph(&graph);
+        assert_eq!(stats.effective_diameter, None);
+
+        let estimate = stats.compute_effective_diameter(&graph).unwrap();
+        assert!((estimate - 6.5).abs() < 0.25);
+        assert_eq!(stats.effective_diameter, Some(estimate));
+
+        let mut isolated = Graph::new();
+        isolated.add_node_simple("A");
+        assert_eq!(GraphStats::from_graph(&isolated).compute_effective_diameter(&isolated), None);
+    }
+
+    fn graph_with_properties(values: &[(Option<f64>, Option<f64>)]) -> Graph {
+        let mut graph = Graph::new();
+        for (i, &(a, b)) in values.iter().enumerate() {
+            let mut node = crate::graph::Node::new(i, format!("Node{}", i));
+            if let Some(a) = a {
+                node = node.with_property("a", a);
+            }
+            if let Some(b) = b {
+                node = node.with_property("b", b);
+            }
+            graph.add_node(node);
+        }
+        graph
+    }
+
+    #[test]
+    fn test_node_property_stats() {
+        let graph = graph_with_properties(&[
+            (Some(1.0), None),
+            (Some(3.0), None),
+            (None, None),
+            (Some(5.0), None),
+        ]);
+
+        let stats = node_property_stats(&graph, "a").unwrap();
+        assert_eq!(stats.count, 3);
+        assert_eq!(stats.missing_count, 1);
+        assert_eq!((stats.min, stats.max, stats.mean), (1.0, 5.0, 3.0));
+        assert!((stats.std_dev - (8.0f64 / 3.0).sqrt()).abs() < 1e-12);
+
+        let missing = node_property_stats(&graph, "b").unwrap();
+        assert_eq!(missing.count, 0);
+        assert_eq!(missing.missing_count, 4);
+        assert_eq!(graph.node_property_statistics("a").unwrap(), stats);
+    }
+
+    #[test]
+    fn test_correlation_between_properties() {
+        let mut rng = StdRng::seed_from_u64(9);
+        let linear: Vec<_> = (0..100)
+            .map(|i| {
+                let x = i as f64;
+                (Some(x), Some(2.0 * x + 1.0 + rng.gen_range(-1.0..1.0)))
+            })
+            .collect();
+        let correlation = correlation_between_properties(&graph_with_properties(&linear), "a", "b");
+        assert!(correlation.unwrap() >= 0.99);
+
+        // Independent values: the correlation's standard error is 1/sqrt(n)
+        let independent: Vec<_> = (0..100)
+            .map(|_| (Some(rng.gen::<f64>()), Some(rng.gen::<f64>())))
+            .collect();
+        let graph = graph_with_properties(&independent);
+        let correlation = correlation_between_properties(&graph, "a", "b").unwrap();
+        assert!(correlation.abs() <= 3.0 / 10.0, "correlation {}", correlation);
+
+        let sparse = graph_with_properties(&[(Some(1.0), None), (Some(2.0), Some(2.0))]);
+        assert!(matches!(
+            correlation_between_properties(&sparse, "a", "b"),
+            Err(GraphError::InvalidData(_))
+        ));
+    }
+
+    #[test]
+    fn test_average_clustering_coefficient() {
+        assert_eq!(average_clustering_coefficient(&Graph::new()), 0.0);
+        assert_eq!(average_clustering_coefficient(&path_graph(5)), 0.0);
+
+        // Triangle 0-1-2 with a pendant 3 on node 2: (1 + 1 + 1/3 + 0) / 4
+        let mut graph = path_graph(3);
+        graph.add_edge(2, 0, 1.0).unwrap();
+        graph.add_node_simple("Node3".to_string());
+        graph.add_edge(2, 3, 1.0).unwrap();
+        let mut stats = GraphStats::from_graph(&graph);
+        let coefficient = stats.compute_clustering_coefficient(&graph);
+        assert!((coefficient - 7.0 / 12.0).abs() < 1e-12);
+        assert_eq!(stats.clustering_coefficient, Some(coefficient));
+
+        // Merging sorted lists gives the same result
+        let mut random = erdos_renyi(200, 8.0, 3);
+        random.add_edge(0, 0, 1.0).unwrap();
+        random.add_edge(0, 1, 1.0).unwrap();
+        random.add_edge(0, 1, 1.0).unwrap();
+        let unsorted = average_clustering_coefficient(&random);
+        random.sort_adjacency();
+        assert!((average_clustering_coefficient(&random) - unsorted).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_regression_detection() {
+        let baseline = GraphStats::from_graph(&erdos_renyi(200, 6.0, 1));
+        assert_eq!(baseline.partial_cmp(&baseline), Some(std::cmp::Ordering::Equal));
+        assert!(assert_no_regression(&baseline, &baseline.clone(), 0.0).is_ok());
+        assert!(diff_stats(&baseline, &baseline).is_empty());
+
+        let mut current = baseline.clone();
+        current.density *= 1.05;
+        assert!(current > baseline);
+        assert!(assert_no_regression(&baseline, &current, 0.06).is_ok());
+        match assert_no_regression(&baseline, &current, 0.03) {
+            Err(GraphError::InvalidData(message)) => {
+                assert!(message.contains("density") && message.contains("+5.0%"), "{}", message);
+                assert!(!message.contains("node_count"), "{}", message);
+            }
+            other => panic!("expected a regression, got {:?}", other),
+        }
+
+        current.diameter = Some(7);
+        let diff = diff_stats(&baseline, &current);
+        assert_eq!(diff.len(), 2, "{:?}", diff);
+        assert!(diff[0].starts_with("density: ") && diff[0].ends_with("(+5.0%)"), "{:?}", diff);
+        assert_eq!(diff[1], "diameter: none -> 7");
+
+        // Same density, different stats: unordered
+        let mut other = baseline.clone();
+        other.max_degree += 1;
+        assert_eq!(baseline.partial_cmp(&other), None);
+        assert!(assert_no_regression(&baseline, &current, -1.0).is_err());
+    }
+
+    #[test]
+    fn test_fit_power_law() {
+        let scale_free = crate::generators::barabasi_albert(2000, 3, Some(1));
+        let fit = fit_power_law(&scale_free).unwrap();
+        assert!((fit.alpha - 3.0).abs() < 0.5, "{:?}", fit);
+        assert!(fit.xmin >= 3);
+        assert!(is_scale_free(&scale_free, 0.03), "{:?}", fit);
+
+        let random = crate::generators::erdos_renyi(2000, 0.003, false, Some(1));
+        let fit = fit_power_law(&random).unwrap();
+        assert!(fit.ks_statistic > 0.03, "{:?}", fit);
+        assert!(!is_scale_free(&random, 0.03));
+
+        let mut stats = GraphStats::from_graph(&scale_free);
+        let alpha = stats.compute_power_law_alpha(&scale_free).unwrap();
+        assert_eq!(stats.power_law_alpha, Some(alpha));
+        assert_eq!(stats.to_feature_vector().last(), Some(&alpha));
+
+        let mut small = Graph::new();
+        let a = small.add_node_simple("A");
+        let b = small.add_node_simple("B");
+        small.add_edge(a, b, 1.0).unwrap();
+        assert!(fit_power_law(&small).is_err());
+        assert!(!is_scale_free(&small, 1.0));
+    }
+
+    #[test]
+    fn test_compute_diameter() {
+        let graph = crate::generators::barabasi_albert(300, 2, Some(3));
+        let mut stats = GraphStats::from_graph(&graph);
+        let diameter = stats.compute_diameter(&graph).unwrap();
+        assert_eq!(stats.diameter, Some(diameter));
+        assert!(diameter >= algorithms::double_sweep_diameter(&graph).unwrap().0);
+        assert!(GraphStats::default().compute_diameter(&Graph::new()).is_err());
+    }
+
+    #[test]
+    fn test_compute_motif_profile() {
+        let mut triangle = Graph::new();
+        for label in ["A", "B", "C"] {
+            triangle.add_node_simple(label);
+        }
+        let mut path = triangle.clone();
+        for (from, to) in [(0, 1), (1, 2), (2, 0)] {
+            triangle.add_edge(from, to, 1.0).unwrap();
+        }
+        let profile = compute_motif_profile(&triangle);
+        assert_eq!((profile.triangles, profile.open_triads, profile.paths3), (1, 0, 3));
+        assert_eq!(global_clustering_from_motif_profile(&profile), 1.0);
+
+        path.add_edge(0, 1, 1.0).unwrap();
+        path.add_edge(1, 2, 1.0).unwrap();
+        let profile = compute_motif_profile(&path);
+        assert_eq!(
+            profile,
+            MotifProfile { triangles: 0, open_triads: 1, paths3: 1, stars3: 0 }
+        );
+        assert_eq!(global_clustering_from_motif_profile(&profile), 0.0);
+
+        let mut k4 = Graph::new();
+        for i in 0..4 {
+            k4.add_node_simple(format!("Node{}", i));
+        }
+        for a in 0..4 {
+            for b in a + 1..4 {
+                k4.add_edge(a, b, 1.0).unwrap();
+            }
+        }
+        let mut stats = GraphStats::from_graph(&k4);
+        let profile = stats.compute_motif_profile(&k4);
+        assert_eq!(stats.motif_profile, Some(profile));
+        assert_eq!(
+            profile,
+            MotifProfile { triangles: 4, open_triads: 0, paths3: 12, stars3: 4 }
+        );
+        assert_eq!(compute_motif_profile(&Graph::new()), MotifProfile::default());
+    }
+    #[test]
+    fn test_zscore_against_null() {
+        // Twenty 5-cliques in a ring: far more triangles than the degrees
+        // call for
+        let mut graph = Graph::new();
+        for i in 0..100 {
+            graph.add_node_simple(format!("{}", i));
+        }
+        for clique in 0..20 {
+            let base = clique * 5;
+            for a in base..base + 5 {
+                for b in a + 1..base + 5 {
+                    graph.add_edge(a, b, 1.0).unwrap();
+                }
+            }
+            graph.add_edge(base + 4, (base + 5) % 100, 1.0).unwrap();
+        }
+
+        let clustering = average_clustering_coefficient;
+        let score = zscore_against_null(&graph, clustering, 20, Some(1)).unwrap();
+        // The same null models, up to the summation order of the metric
+        let again = zscore_against_null(&graph, clustering, 20, Some(1)).unwrap();
+        assert!((score.z_score - again.z_score).abs() < 1e-9);
+        assert_eq!(score.samples, 20);
+        assert!(score.observed > 0.8 && score.null_mean < score.observed / 2.0, "{:?}", score);
+        assert!(score.z_score > 10.0, "{:?}", score);
+
+        // Rewiring cannot change the edge count
+        let edges = zscore_against_null(&graph, |g| g.edge_count() as f64, 5, None).unwrap();
+        assert_eq!((edges.null_std, edges.z_score), (0.0, 0.0));
+        assert!(zscore_against_null(&graph, clustering, 1, None).is_err());
+    }
 }
@@ -2,13 +2,22 @@
 //!
 //! Support for saving and loading graphs in multiple formats:
 //! - Binary (custom format, fastest)
+//! - Binary compressed with zstd (smallest, for archival)
 //! - JSON (human-readable)
 //! - GraphML (XML-based, widely supported)
+//! - DOT (Graphviz, for drawing and for tools such as Gephi and NetworkX)
+//! - Pajek `.net` (common for sociology and biology datasets)
+//!
+//! `to_tikz` additionally renders a graph as a TikZ picture for LaTeX
+//! documents; it is an export only.
 
 use crate::error::{GraphError, Result};
-use crate::graph::{Graph, Node};
-use crate::types::NodeId;
+use crate::graph::{Edge, Graph, Node};
+use crate::types::{next_id, node_id, NodeId};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::Path;
@@ -16,40 +25,171 @@ use std::path::Path;
 /// Storage format for graphs
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum StorageFormat {
-    /// Binary format (fastest, smallest)
+    /// Binary format (fastest)
+    ///
+    /// Files start with a version header. Files written before it was
+    /// added still load. Builds with and without `uuid-ids` cannot read
+    /// each other's files.
     Binary,
+    /// Binary format compressed with zstd (smallest)
+    ///
+    /// Higher levels (up to 22) compress better but write more slowly;
+    /// `level: 0` means zstd's default. Loading ignores the level.
+    BinaryZstd { level: i32 },
     /// JSON format (human-readable)
+    ///
+    /// Under `uuid-ids`, node IDs are written as hyphenated UUID strings.
     Json,
     /// GraphML format (XML-based, widely compatible)
     GraphML,
+    /// Graphviz DOT format, see `to_dot` and `from_dot`
+    Dot,
+    /// Pajek `.net` format, see `to_pajek` and `from_pajek`
+    ///
+    /// Keeps labels and weights only, and renumbers nodes.
+    Pajek,
 }
 
 /// Serializable graph representation
+///
+/// Nodes are listed by ID and edges in index order, the order adjacency
+/// lists are rebuilt from, so saving a graph, or a loaded copy of it,
+/// always writes the same bytes and algorithms behave the same on the
+/// copy.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct SerializableGraph {
     nodes: Vec<(NodeId, Node)>,
     edges: Vec<(NodeId, NodeId, f64)>,
     directed: bool,
+    /// `(index into edges, timestamp)` for each timestamped edge
+    #[serde(default)]
+    timestamps: Vec<(usize, f64)>,
+    /// Whether the graph keeps sorted adjacency, see `Graph::sort_adjacency`
+    #[serde(default)]
+    sorted_adjacency: bool,
+    /// The graph's next `add_node_simple` ID, which is past the largest
+    /// node ID if nodes were contracted away
+    #[serde(default)]
+    next_node_id: Option<NodeId>,
+}
+
+/// Leading bytes of the `Binary` and `BinaryZstd` encodings, followed by
+/// the encoding version as a little-endian `u32`
+///
+/// Files without them predate the header and hold a `LegacyGraph`. Those
+/// start with their node count as a `u64`, which would have to exceed a
+/// billion for its low bytes to match. Under `uuid-ids` the magic is
+/// `ZGRU` instead, as the layout of a `NodeId` differs.
+#[cfg(not(feature = "uuid-ids"))]
+const BINARY_MAGIC: &[u8; 4] = b"ZGRB";
+#[cfg(feature = "uuid-ids")]
+const BINARY_MAGIC: &[u8; 4] = b"ZGRU";
+
+/// `BINARY_MAGIC` of the build with the other kind of `NodeId`, and what
+/// loading such a file takes
+#[cfg(not(feature = "uuid-ids"))]
+const FOREIGN_BINARY_MAGIC: (&[u8; 4], &str) =
+    (b"ZGRU", "UUID node IDs; load it with the `uuid-ids` feature");
+#[cfg(feature = "uuid-ids")]
+const FOREIGN_BINARY_MAGIC: (&[u8; 4], &str) =
+    (b"ZGRB", "integer node IDs; load it without the `uuid-ids` feature");
+
+/// Version of the `SerializableGraph` layout written after `BINARY_MAGIC`
+const BINARY_VERSION: u32 = 1;
+
+/// `SerializableGraph` as written to binary files before fields were
+/// added to it; bincode is not self-describing, so the added fields
+/// cannot default
+#[derive(Deserialize)]
+struct LegacyGraph {
+    nodes: Vec<(NodeId, Node)>,
+    edges: Vec<(NodeId, NodeId, f64)>,
+    directed: bool,
+}
+
+impl From<LegacyGraph> for SerializableGraph {
+    fn from(legacy: LegacyGraph) -> Self {
+        SerializableGraph {
+            nodes: legacy.nodes,
+            edges: legacy.edges,
+            directed: legacy.directed,
+            timestamps: Vec::new(),
+            sorted_adjacency: false,
+            next_node_id: None,
+        }
+    }
+}
+
+/// The binary encoding of `graph`: the header, then the graph in bincode
+fn encode_binary(graph: &SerializableGraph) -> Result<Vec<u8>> {
+    let mut encoded = BINARY_MAGIC.to_vec();
+    encoded.extend_from_slice(&BINARY_VERSION.to_le_bytes());
+    bincode::serialize_into(&mut encoded, graph)
+        .map_err(|e| GraphError::SerializationError(e.to_string()))?;
+    Ok(encoded)
+}
+
+/// Decode `encode_binary` output, or a headerless legacy file
+fn decode_binary(bytes: &[u8]) -> Result<SerializableGraph> {
+    let serialization_error = |e: bincode::Error| GraphError::SerializationError(e.to_string());
+    if bytes.starts_with(FOREIGN_BINARY_MAGIC.0) {
+        return Err(GraphError::SerializationError(format!(
+            "Binary graph has {}",
+            FOREIGN_BINARY_MAGIC.1
+        )));
+    }
+    let Some(body) = bytes.strip_prefix(BINARY_MAGIC) else {
+        if cfg!(feature = "uuid-ids") {
+            return Err(GraphError::SerializationError(
+                "Binary graph predates UUID node IDs; load it without the `uuid-ids` feature"
+                    .to_string(),
+            ));
+        }
+        let legacy: LegacyGraph = bincode::deserialize(bytes).map_err(serialization_error)?;
+        return Ok(legacy.into());
+    };
+    let (version, body) = body.split_at_checked(4).ok_or_else(|| {
+        GraphError::SerializationError("Truncated binary graph header".to_string())
+    })?;
+    let version = u32::from_le_bytes(version.try_into().expect("four bytes"));
+    if version != BINARY_VERSION {
+        return Err(GraphError::SerializationError(format!(
+            "Unsupported binary graph version {} (expected {})",
+            version, BINARY_VERSION
+        )));
+    }
+    bincode::deserialize(body).map_err(serialization_error)
 }
 
 impl From<&Graph> for SerializableGraph {
     fn from(graph: &Graph) -> Self {
-        let nodes: Vec<_> = graph
-            .node_ids()
+        let mut node_ids = graph.node_ids();
+        node_ids.sort_unstable();
+        let nodes: Vec<_> = node_ids
             .into_iter()
             .filter_map(|id| graph.node(id).ok().map(|node| (id, node.clone())))
             .collect();
 
         let edges: Vec<_> = graph
             .edges()
-            .into_iter()
+            .iter()
             .map(|edge| (edge.from, edge.to, edge.weight))
             .collect();
 
+        let timestamps = graph
+            .edges()
+            .iter()
+            .enumerate()
+            .filter_map(|(index, edge)| edge.timestamp.map(|timestamp| (index, timestamp)))
+            .collect();
+
         SerializableGraph {
             nodes,
             edges,
             directed: graph.is_directed(),
+            timestamps,
+            sorted_adjacency: graph.has_sorted_adjacency(),
+            next_node_id: Some(graph.next_node_id()),
         }
     }
 }
@@ -62,14 +202,24 @@ impl SerializableGraph {
             Graph::new()
         };
 
+        if self.sorted_adjacency {
+            graph.sort_adjacency();
+        }
+
         // Add nodes
         for (_id, node) in &self.nodes {
             graph.add_node(node.clone());
         }
 
         // Add edges
-        for (source, target, weight) in &self.edges {
-            graph.add_edge(*source, *target, *weight)?;
+        let timestamps: HashMap<usize, f64> = self.timestamps.iter().copied().collect();
+        for (index, (source, target, weight)) in self.edges.iter().enumerate() {
+            let mut edge = Edge::new(*source, *target, *weight);
+            edge.timestamp = timestamps.get(&index).copied();
+            graph.insert_edge(edge)?;
+        }
+        if let Some(next_node_id) = self.next_node_id {
+            graph.reserve_node_ids(next_node_id);
         }
 
         Ok(graph)
@@ -90,11 +240,15 @@ pub fn save_graph<P: AsRef<Path>>(
 
     match format {
         StorageFormat::Binary => {
-            let encoded = bincode::serialize(&serializable)
-                .map_err(|e| GraphError::SerializationError(e.to_string()))?;
+            let encoded = encode_binary(&serializable)?;
             writer
                 .write_all(&encoded)
-                .map_err(|e| GraphError::IoError(e))?;
+                .map_err(GraphError::IoError)?;
+        }
+        StorageFormat::BinaryZstd { level } => {
+            let encoded = encode_binary(&serializable)?;
+            zstd::stream::copy_encode(&encoded[..], &mut writer, level)
+                .map_err(GraphError::IoError)?;
         }
         StorageFormat::Json => {
             serde_json::to_writer_pretty(&mut writer, &serializable)
@@ -103,11 +257,21 @@ pub fn save_graph<P: AsRef<Path>>(
         StorageFormat::GraphML => {
             write_graphml(&mut writer, &serializable)?;
         }
+        StorageFormat::Dot => {
+            writer
+                .write_all(to_dot(graph).as_bytes())
+                .map_err(GraphError::IoError)?;
+        }
+        StorageFormat::Pajek => {
+            writer
+                .write_all(to_pajek(graph).as_bytes())
+                .map_err(GraphError::IoError)?;
+        }
     }
 
     writer
         .flush()
-        .map_err(|e| GraphError::IoError(e))?;
+        .map_err(GraphError::IoError)?;
 
     Ok(())
 }
@@ -123,31 +287,57 @@ pub fn load_graph<P: AsRef<Path>>(path: P, format: StorageFormat) -> Result<Grap
             let mut buffer = Vec::new();
             reader
                 .read_to_end(&mut buffer)
-                .map_err(|e| GraphError::IoError(e))?;
-            bincode::deserialize(&buffer)
-                .map_err(|e| GraphError::SerializationError(e.to_string()))?
+                .map_err(GraphError::IoError)?;
+            decode_binary(&buffer)?
+        }
+        StorageFormat::BinaryZstd { .. } => {
+            // Input that is not a zstd frame fails here, not in bincode
+            let buffer = zstd::stream::decode_all(&mut reader).map_err(|e| {
+                GraphError::SerializationError(format!("Invalid zstd data: {}", e))
+            })?;
+            decode_binary(&buffer)?
         }
         StorageFormat::Json => serde_json::from_reader(&mut reader)
             .map_err(|e| GraphError::SerializationError(e.to_string()))?,
         StorageFormat::GraphML => read_graphml(&mut reader)?,
+        StorageFormat::Dot | StorageFormat::Pajek => {
+            let mut content = String::new();
+            reader
+                .read_to_string(&mut content)
+                .map_err(GraphError::IoError)?;
+            let graph = if matches!(format, StorageFormat::Dot) {
+                from_dot(&content)?
+            } else {
+                from_pajek(&content)?
+            };
+            crate::debug_assert_invariants!(graph);
+            return Ok(graph);
+        }
     };
 
-    serializable.to_graph()
+    let graph = serializable.to_graph()?;
+    crate::debug_assert_invariants!(graph);
+    Ok(graph)
 }
 
 /// Write graph in GraphML format
 fn write_graphml<W: Write>(writer: &mut W, graph: &SerializableGraph) -> Result<()> {
     writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)
-        .map_err(|e| GraphError::IoError(e))?;
+        .map_err(GraphError::IoError)?;
     writeln!(
         writer,
         r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
     )
-    .map_err(|e| GraphError::IoError(e))?;
+    .map_err(GraphError::IoError)?;
     writeln!(writer, r#"  <key id="weight" for="edge" attr.name="weight" attr.type="double"/>"#)
-        .map_err(|e| GraphError::IoError(e))?;
+        .map_err(GraphError::IoError)?;
     writeln!(writer, r#"  <key id="label" for="node" attr.name="label" attr.type="string"/>"#)
-        .map_err(|e| GraphError::IoError(e))?;
+        .map_err(GraphError::IoError)?;
+    writeln!(
+        writer,
+        r#"  <key id="timestamp" for="edge" attr.name="timestamp" attr.type="double"/>"#
+    )
+    .map_err(GraphError::IoError)?;
 
     let edge_default = if graph.directed {
         "directed"
@@ -159,160 +349,1151 @@ fn write_graphml<W: Write>(writer: &mut W, graph: &SerializableGraph) -> Result<
         r#"  <graph id="G" edgedefault="{}">"#,
         edge_default
     )
-    .map_err(|e| GraphError::IoError(e))?;
+    .map_err(GraphError::IoError)?;
 
     // Write nodes
     for (id, node) in &graph.nodes {
-        writeln!(writer, r#"    <node id="n{}">"#, id)
-            .map_err(|e| GraphError::IoError(e))?;
+        writeln!(writer, r#"    <node id="{}">"#, escape_xml_attribute(&format!("n{}", id)))
+            .map_err(GraphError::IoError)?;
         writeln!(
             writer,
             r#"      <data key="label">{}</data>"#,
-            escape_xml(&node.label)
+            escape_xml_text(&node.label)
         )
-        .map_err(|e| GraphError::IoError(e))?;
-        writeln!(writer, r#"    </node>"#).map_err(|e| GraphError::IoError(e))?;
+        .map_err(GraphError::IoError)?;
+        writeln!(writer, r#"    </node>"#).map_err(GraphError::IoError)?;
     }
 
     // Write edges
+    let timestamps: HashMap<usize, f64> = graph.timestamps.iter().copied().collect();
     for (i, (source, target, weight)) in graph.edges.iter().enumerate() {
         writeln!(
             writer,
             r#"    <edge id="e{}" source="n{}" target="n{}">"#,
             i, source, target
         )
-        .map_err(|e| GraphError::IoError(e))?;
+        .map_err(GraphError::IoError)?;
         writeln!(writer, r#"      <data key="weight">{}</data>"#, weight)
-            .map_err(|e| GraphError::IoError(e))?;
-        writeln!(writer, r#"    </edge>"#).map_err(|e| GraphError::IoError(e))?;
+            .map_err(GraphError::IoError)?;
+        if let Some(timestamp) = timestamps.get(&i) {
+            writeln!(writer, r#"      <data key="timestamp">{}</data>"#, timestamp)
+                .map_err(GraphError::IoError)?;
+        }
+        writeln!(writer, r#"    </edge>"#).map_err(GraphError::IoError)?;
     }
 
-    writeln!(writer, r#"  </graph>"#).map_err(|e| GraphError::IoError(e))?;
-    writeln!(writer, r#"</graphml>"#).map_err(|e| GraphError::IoError(e))?;
+    writeln!(writer, r#"  </graph>"#).map_err(GraphError::IoError)?;
+    writeln!(writer, r#"</graphml>"#).map_err(GraphError::IoError)?;
 
     Ok(())
 }
 
 /// Read graph from GraphML format (simplified parser)
+///
+/// Understands the subset `write_graphml` produces: `<node>` and `<edge>`
+/// elements with `n`-prefixed (or bare) numeric ids and `label`, `weight`
+/// and `timestamp` data. Unclosed elements, missing or non-numeric ids,
+/// unparsable numbers and invalid entities fail with `SerializationError`
+/// instead of being read as defaults. An edge without a weight gets 1.0.
 fn read_graphml<R: Read>(reader: &mut R) -> Result<SerializableGraph> {
     let mut content = String::new();
     reader
         .read_to_string(&mut content)
-        .map_err(|e| GraphError::IoError(e))?;
+        .map_err(GraphError::IoError)?;
 
     // Simple parsing - in production, use a proper XML parser
     let mut nodes = Vec::new();
     let mut edges = Vec::new();
+    let mut timestamps = Vec::new();
     let directed = content.contains(r#"edgedefault="directed""#);
 
-    // Parse nodes
-    for node_match in content.match_indices("<node id=") {
-        let start = node_match.0;
-        let end = content[start..].find("</node>").unwrap_or(0) + start;
-        let node_xml = &content[start..end];
-
-        // Extract node id - look for id="nXXX"
-        if let Some(id_start) = node_xml.find(r#"id=""#) {
-            let id_str = &node_xml[id_start + 4..]; // Skip 'id="'
-            if let Some(id_end) = id_str.find('"') {
-                let id_with_n = &id_str[..id_end]; // This will be "nXXX"
-                // Remove the 'n' prefix and parse the number
-                if id_with_n.starts_with('n') {
-                    if let Ok(id) = id_with_n[1..].parse::<NodeId>() {
-                        // Extract label
-                        let label = if let Some(label_start) = node_xml.find("<data key=\"label\">") {
-                            let label_str = &node_xml[label_start + 18..];
-                            let label_end = label_str.find("</data>").unwrap_or(0);
-                            unescape_xml(&label_str[..label_end])
-                        } else {
-                            format!("Node{}", id)
-                        };
-
-                        nodes.push((id, Node::new(id, label)));
-                    }
-                }
-            }
+    for (start, _) in content.match_indices("<node ") {
+        let (tag, body) = graphml_element(&content, start, "node")?;
+        let id = graphml_node_id(&graphml_attribute(tag, "id")?)?;
+        let label = graphml_data(body, "label")?.unwrap_or_else(|| format!("Node{}", id));
+        nodes.push((id, Node::new(id, label)));
+    }
+
+    for (start, _) in content.match_indices("<edge ") {
+        let (tag, body) = graphml_element(&content, start, "edge")?;
+        let source = graphml_node_id(&graphml_attribute(tag, "source")?)?;
+        let target = graphml_node_id(&graphml_attribute(tag, "target")?)?;
+        let weight = match graphml_data(body, "weight")? {
+            Some(text) => graphml_number(&text, "weight")?,
+            None => 1.0,
+        };
+        if let Some(text) = graphml_data(body, "timestamp")? {
+            timestamps.push((edges.len(), graphml_number(&text, "timestamp")?));
+        }
+        edges.push((source, target, weight));
+    }
+
+    Ok(SerializableGraph {
+        nodes,
+        edges,
+        directed,
+        timestamps,
+        sorted_adjacency: false,
+        next_node_id: None,
+    })
+}
+
+fn graphml_error(message: impl std::fmt::Display) -> GraphError {
+    GraphError::SerializationError(format!("GraphML: {}", message))
+}
+
+/// The start tag (without its closing `>`) and the content of the `name`
+/// element starting at byte `start` of `content`
+fn graphml_element<'a>(content: &'a str, start: usize, name: &str) -> Result<(&'a str, &'a str)> {
+    let rest = &content[start..];
+    let tag_end = rest
+        .find('>')
+        .ok_or_else(|| graphml_error(format!("unterminated <{}> tag", name)))?;
+    let tag = &rest[..tag_end];
+    if tag.ends_with('/') {
+        return Ok((tag, ""));
+    }
+
+    let body = &rest[tag_end + 1..];
+    let unclosed = || graphml_error(format!("{}> is not closed", tag));
+    let body = &body[..body.find(&format!("</{}>", name)).ok_or_else(unclosed)?];
+    // Otherwise a missing end tag would borrow the next element's
+    if body.contains(&format!("<{} ", name)) {
+        return Err(unclosed());
+    }
+    Ok((tag, body))
+}
+
+/// The unescaped value of the double-quoted attribute `name` in `tag`
+fn graphml_attribute(tag: &str, name: &str) -> Result<String> {
+    let pattern = format!(" {}=\"", name);
+    let missing = || graphml_error(format!("{}> has no {} attribute", tag, name));
+    let start = tag.find(&pattern).ok_or_else(missing)? + pattern.len();
+    let len = tag[start..].find('"').ok_or_else(missing)?;
+    unescape_xml(&tag[start..start + len])
+}
+
+fn graphml_node_id(value: &str) -> Result<NodeId> {
+    value
+        .strip_prefix('n')
+        .unwrap_or(value)
+        .parse()
+        .map_err(|_| graphml_error(format!("invalid node id {:?}", value)))
+}
+
+/// The unescaped text of the `<data key="key">` element in `body`, if any
+fn graphml_data(body: &str, key: &str) -> Result<Option<String>> {
+    let open = format!(r#"<data key="{}">"#, key);
+    let Some(start) = body.find(&open) else {
+        return Ok(None);
+    };
+    let text = &body[start + open.len()..];
+    let end = text
+        .find("</data>")
+        .ok_or_else(|| graphml_error(format!("{} data is not closed", key)))?;
+    unescape_xml(&text[..end]).map(Some)
+}
+
+fn graphml_number(text: &str, key: &str) -> Result<f64> {
+    text.trim()
+        .parse()
+        .map_err(|_| graphml_error(format!("invalid {} {:?}", key, text)))
+}
+
+/// Whether XML 1.0 allows `c` at all, even as a character reference
+fn is_xml_char(c: char) -> bool {
+    matches!(
+        c,
+        '\t' | '\n'
+            | '\r'
+            | '\u{20}'..='\u{D7FF}'
+            | '\u{E000}'..='\u{FFFD}'
+            | '\u{10000}'..='\u{10FFFF}'
+    )
+}
+
+/// Escape `s` for use as element text
+///
+/// `>` is escaped as well, so `]]>` never appears, and `\r` becomes a
+/// character reference so that line-end normalization keeps it.
+/// Characters XML 1.0 cannot represent, such as most control characters,
+/// are dropped.
+fn escape_xml_text(s: &str) -> String {
+    escape_xml(s, false)
+}
+
+/// Escape `s` for use as a quoted attribute value
+///
+/// As `escape_xml_text`, and quotes, tabs and newlines become references
+/// too: attribute value normalization would turn raw whitespace into
+/// spaces.
+fn escape_xml_attribute(s: &str) -> String {
+    escape_xml(s, true)
+}
+
+fn escape_xml(s: &str, attribute: bool) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '\r' => escaped.push_str("&#13;"),
+            '"' if attribute => escaped.push_str("&quot;"),
+            '\'' if attribute => escaped.push_str("&apos;"),
+            '\t' if attribute => escaped.push_str("&#9;"),
+            '\n' if attribute => escaped.push_str("&#10;"),
+            c if is_xml_char(c) => escaped.push(c),
+            _ => {}
+        }
+    }
+    escaped
+}
+
+/// Resolve the predefined entities and character references in `s`
+///
+/// Done in one pass, so `&amp;lt;` stays `&lt;`. A raw `<`, an unknown
+/// entity or a reference to a character XML does not allow is an error.
+fn unescape_xml(s: &str) -> Result<String> {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find(['&', '<']) {
+        unescaped.push_str(&rest[..i]);
+        if rest[i..].starts_with('<') {
+            return Err(graphml_error(format!("unexpected '<' in {:?}", s)));
+        }
+        let len = rest[i..]
+            .find(';')
+            .ok_or_else(|| graphml_error(format!("unterminated entity in {:?}", s)))?;
+        let entity = &rest[i + 1..i + len];
+        let c = match entity {
+            "amp" => '&',
+            "lt" => '<',
+            "gt" => '>',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32)
+                .filter(|&c| is_xml_char(c))
+                .ok_or_else(|| graphml_error(format!("invalid entity &{};", entity)))?,
+        };
+        unescaped.push(c);
+        rest = &rest[i + len + 1..];
+    }
+    unescaped.push_str(rest);
+    Ok(unescaped)
+}
+
+/// Write `graph` in Graphviz DOT format
+///
+/// Node ids are the DOT node names, so `from_dot` reads the graph back with
+/// the same ids. Labels and numeric properties become node attributes;
+/// edges carry their `weight`, their type as `label` unless it is the
+/// default one, and their `timestamp` if they have one.
+pub fn to_dot(graph: &Graph) -> String {
+    let (keyword, edge_op) = if graph.is_directed() { ("digraph", "->") } else { ("graph", "--") };
+    let mut out = format!("{} {{\n", keyword);
+
+    let mut ids = graph.node_ids();
+    ids.sort_unstable();
+    for id in ids {
+        let Ok(node) = graph.node(id) else { continue };
+        let mut attributes = vec![format!("label={}", dot_quote(&node.label))];
+        let mut properties: Vec<_> = node.properties.iter().collect();
+        properties.sort_by(|a, b| a.0.cmp(b.0));
+        for (key, value) in properties {
+            attributes.push(format!("{}={}", dot_id(key), dot_id(&value.to_string())));
         }
+        out.push_str(&format!("    {} [{}];\n", id, attributes.join(", ")));
     }
 
-    // Parse edges
-    for edge_match in content.match_indices("<edge ") {
-        let start = edge_match.0;
-        let end = content[start..].find("</edge>").unwrap_or(0) + start;
-        let edge_xml = &content[start..end];
+    for edge in graph.edges() {
+        let mut attributes = vec![format!("weight={}", dot_id(&edge.weight.to_string()))];
+        if edge.edge_type != "default" {
+            attributes.push(format!("label={}", dot_quote(&edge.edge_type)));
+        }
+        if let Some(timestamp) = edge.timestamp {
+            attributes.push(format!("timestamp={}", dot_id(&timestamp.to_string())));
+        }
+        out.push_str(&format!(
+            "    {} {} {} [{}];\n",
+            edge.from,
+            edge_op,
+            edge.to,
+            attributes.join(", ")
+        ));
+    }
 
-        // Extract source - look for source="nXXX"
-        let source = if let Some(src_start) = edge_xml.find(r#"source=""#) {
-            let src_str = &edge_xml[src_start + 8..]; // Skip 'source="'
-            if let Some(src_end) = src_str.find('"') {
-                let src_with_n = &src_str[..src_end];
-                if src_with_n.starts_with('n') {
-                    src_with_n[1..].parse::<NodeId>().unwrap_or(0)
-                } else {
-                    0
+    out.push_str("}\n");
+    out
+}
+
+/// Read a graph in Graphviz DOT format
+///
+/// Accepts the full DOT grammar: `strict`, attribute statements, edge
+/// chains, subgraphs, ports, comments and quoted, HTML and `+`-concatenated
+/// strings. Nodes named by a non-negative integer keep it as their id;
+/// others are numbered after the largest such id, in order of appearance.
+/// A node's `label` attribute becomes its label, defaulting to its name,
+/// and its other numeric attributes become properties. An edge's `weight`
+/// (1.0 if absent) becomes its weight, its `label` its type and its
+/// `timestamp` its timestamp. Other attributes, and graph attributes, are
+/// ignored.
+pub fn from_dot(input: &str) -> Result<Graph> {
+    let tokens = tokenize_dot(input)?;
+    let mut parser = DotParser {
+        tokens,
+        pos: 0,
+        directed: false,
+        nodes: Vec::new(),
+        index: HashMap::new(),
+        edges: Vec::new(),
+        node_defaults: HashMap::new(),
+        edge_defaults: HashMap::new(),
+        touched: vec![Vec::new()],
+    };
+    parser.parse_graph()?;
+    parser.into_graph()
+}
+
+fn dot_error(line: usize, message: impl std::fmt::Display) -> GraphError {
+    GraphError::SerializationError(format!("DOT line {}: {}", line, message))
+}
+
+/// `s` as a quoted DOT string
+fn dot_quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// `s` as a DOT identifier, quoted only if it has to be
+fn dot_id(s: &str) -> String {
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '_' || !c.is_ascii();
+    let is_name = s.chars().next().is_some_and(|c| !c.is_ascii_digit() && is_name_char(c))
+        && s.chars().all(is_name_char)
+        && !DOT_KEYWORDS.iter().any(|keyword| s.eq_ignore_ascii_case(keyword));
+    if is_name || is_dot_numeral(s) {
+        s.to_string()
+    } else {
+        dot_quote(s)
+    }
+}
+
+const DOT_KEYWORDS: [&str; 6] = ["strict", "graph", "digraph", "node", "edge", "subgraph"];
+
+/// Whether `s` is a DOT numeral, `[-]?(.[0-9]+ | [0-9]+(.[0-9]*)?)`
+fn is_dot_numeral(s: &str) -> bool {
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    let (whole, fraction) = match digits.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (digits, None),
+    };
+    let all_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    all_digits(whole)
+        && fraction.is_none_or(all_digits)
+        && (!whole.is_empty() || fraction.is_some_and(|fraction| !fraction.is_empty()))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum DotToken {
+    /// An identifier, numeral, or quoted or HTML string; `quoted` ones are
+    /// never keywords
+    Id { text: String, quoted: bool },
+    /// One of `{ } [ ] = ; , :`
+    Punct(char),
+    /// `--` or `->`
+    EdgeOp(&'static str),
+}
+
+impl DotToken {
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, DotToken::Id { text, quoted: false } if text.eq_ignore_ascii_case(keyword))
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            DotToken::Id { text, .. } => format!("'{}'", text),
+            DotToken::Punct(c) => format!("'{}'", c),
+            DotToken::EdgeOp(op) => format!("'{}'", op),
+        }
+    }
+}
+
+/// Split DOT source into tokens tagged with their line number
+fn tokenize_dot(input: &str) -> Result<Vec<(DotToken, usize)>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut at_line_start = true;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' {
+            line += 1;
+            at_line_start = true;
+            i += 1;
+            continue;
+        }
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        // `#` lines are C preprocessor output, which DOT skips
+        if c == '#' && at_line_start {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+        at_line_start = false;
+
+        let next = chars.get(i + 1).copied();
+        if c == '/' && next == Some('/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && next == Some('*') {
+            let start = line;
+            i += 2;
+            loop {
+                match chars.get(i) {
+                    None => return Err(dot_error(start, "unterminated comment")),
+                    Some('*') if chars.get(i + 1) == Some(&'/') => break,
+                    Some('\n') => line += 1,
+                    Some(_) => {}
                 }
-            } else {
-                0
+                i += 1;
             }
+            i += 2;
+        } else if c == '"' {
+            let start = line;
+            let mut text = String::new();
+            loop {
+                i = read_quoted(&chars, i, &mut text, &mut line)
+                    .ok_or_else(|| dot_error(start, "unterminated string"))?;
+                // `"a" + "b"` is the string "ab"
+                let mut j = i;
+                let mut skipped_lines = 0;
+                while j < chars.len() && chars[j].is_whitespace() {
+                    skipped_lines += usize::from(chars[j] == '\n');
+                    j += 1;
+                }
+                if chars.get(j) != Some(&'+') {
+                    break;
+                }
+                j += 1;
+                while j < chars.len() && chars[j].is_whitespace() {
+                    skipped_lines += usize::from(chars[j] == '\n');
+                    j += 1;
+                }
+                if chars.get(j) != Some(&'"') {
+                    return Err(dot_error(line, "expected a string after '+'"));
+                }
+                line += skipped_lines;
+                i = j;
+            }
+            tokens.push((DotToken::Id { text, quoted: true }, start));
+        } else if c == '<' {
+            let start = line;
+            let mut depth = 0;
+            let mut text = String::new();
+            loop {
+                let Some(&c) = chars.get(i) else {
+                    return Err(dot_error(start, "unterminated HTML string"));
+                };
+                i += 1;
+                match c {
+                    '<' => depth += 1,
+                    '>' => depth -= 1,
+                    '\n' => line += 1,
+                    _ => {}
+                }
+                // The outermost brackets delimit the string
+                match (c, depth) {
+                    ('>', 0) => break,
+                    ('<', 1) => {}
+                    _ => text.push(c),
+                }
+            }
+            tokens.push((DotToken::Id { text, quoted: true }, start));
+        } else if c == '-' && (next == Some('-') || next == Some('>')) {
+            let op = if next == Some('-') { "--" } else { "->" };
+            tokens.push((DotToken::EdgeOp(op), line));
+            i += 2;
+        } else if c.is_ascii_digit()
+            || ((c == '-' || c == '.') && next.is_some_and(|n| n.is_ascii_digit() || n == '.'))
+        {
+            let start = i;
+            i += 1;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            if !is_dot_numeral(&text) {
+                return Err(dot_error(line, format!("invalid number '{}'", text)));
+            }
+            tokens.push((DotToken::Id { text, quoted: false }, line));
+        } else if c.is_ascii_alphabetic() || c == '_' || !c.is_ascii() {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_ascii_alphanumeric() || chars[i] == '_' || !chars[i].is_ascii())
+            {
+                i += 1;
+            }
+            let text = chars[start..i].iter().collect();
+            tokens.push((DotToken::Id { text, quoted: false }, line));
+        } else if "{}[]=;,:".contains(c) {
+            tokens.push((DotToken::Punct(c), line));
+            i += 1;
         } else {
-            0
-        };
+            return Err(dot_error(line, format!("unexpected character '{}'", c)));
+        }
+    }
+    Ok(tokens)
+}
 
-        // Extract target - look for target="nXXX"
-        let target = if let Some(tgt_start) = edge_xml.find(r#"target=""#) {
-            let tgt_str = &edge_xml[tgt_start + 8..]; // Skip 'target="'
-            if let Some(tgt_end) = tgt_str.find('"') {
-                let tgt_with_n = &tgt_str[..tgt_end];
-                if tgt_with_n.starts_with('n') {
-                    tgt_with_n[1..].parse::<NodeId>().unwrap_or(0)
-                } else {
-                    0
+/// Append the quoted string starting at `chars[start]` to `text`, returning
+/// the index just past its closing quote
+fn read_quoted(chars: &[char], start: usize, text: &mut String, line: &mut usize) -> Option<usize> {
+    let mut i = start + 1;
+    loop {
+        match *chars.get(i)? {
+            '"' => return Some(i + 1),
+            '\\' => match chars.get(i + 1) {
+                Some('"') => {
+                    text.push('"');
+                    i += 1;
                 }
-            } else {
-                0
+                Some('\\') => {
+                    text.push('\\');
+                    i += 1;
+                }
+                // An escaped newline continues the line
+                Some('\n') => {
+                    *line += 1;
+                    i += 1;
+                }
+                _ => text.push('\\'),
+            },
+            c => {
+                *line += usize::from(c == '\n');
+                text.push(c);
             }
+        }
+        i += 1;
+    }
+}
+
+type DotAttributes = HashMap<String, String>;
+
+struct DotParser {
+    tokens: Vec<(DotToken, usize)>,
+    pos: usize,
+    directed: bool,
+    /// Node names and attributes, in order of first appearance
+    nodes: Vec<(String, DotAttributes)>,
+    index: HashMap<String, usize>,
+    edges: Vec<(usize, usize, DotAttributes)>,
+    node_defaults: DotAttributes,
+    edge_defaults: DotAttributes,
+    /// Nodes mentioned in each enclosing subgraph, innermost last
+    touched: Vec<Vec<usize>>,
+}
+
+impl DotParser {
+    fn peek(&self) -> Option<&DotToken> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or_else(|| self.tokens.last())
+            .map_or(1, |&(_, line)| line)
+    }
+
+    fn error(&self, message: impl std::fmt::Display) -> GraphError {
+        dot_error(self.line(), message)
+    }
+
+    fn unexpected(&self, expected: &str) -> GraphError {
+        match self.peek() {
+            Some(token) => self.error(format!("expected {}, found {}", expected, token.describe())),
+            None => self.error(format!("expected {}, found end of input", expected)),
+        }
+    }
+
+    fn eat_punct(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(&DotToken::Punct(c));
+        self.pos += usize::from(found);
+        found
+    }
+
+    fn expect_punct(&mut self, c: char) -> Result<()> {
+        if self.eat_punct(c) {
+            Ok(())
         } else {
-            0
-        };
+            Err(self.unexpected(&format!("'{}'", c)))
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek().is_some_and(|token| token.is_keyword(keyword));
+        self.pos += usize::from(found);
+        found
+    }
+
+    /// The next identifier, which must not be a keyword
+    fn expect_id(&mut self, expected: &str) -> Result<String> {
+        match self.peek() {
+            Some(token @ DotToken::Id { text, .. })
+                if !DOT_KEYWORDS.iter().any(|keyword| token.is_keyword(keyword)) =>
+            {
+                let text = text.clone();
+                self.pos += 1;
+                Ok(text)
+            }
+            _ => Err(self.unexpected(expected)),
+        }
+    }
+
+    fn at_id(&self) -> bool {
+        matches!(self.peek(), Some(DotToken::Id { .. }))
+    }
+
+    fn parse_graph(&mut self) -> Result<()> {
+        self.eat_keyword("strict");
+        if self.eat_keyword("digraph") {
+            self.directed = true;
+        } else if !self.eat_keyword("graph") {
+            return Err(self.unexpected("'graph' or 'digraph'"));
+        }
+        if self.at_id() {
+            self.expect_id("a graph name")?;
+        }
+        self.expect_punct('{')?;
+        self.parse_statements()?;
+        self.expect_punct('}')?;
+        if self.peek().is_some() {
+            return Err(self.unexpected("end of input"));
+        }
+        Ok(())
+    }
+
+    /// Statements up to, but not including, the closing `}`
+    fn parse_statements(&mut self) -> Result<()> {
+        while self.peek().is_some() && self.peek() != Some(&DotToken::Punct('}')) {
+            self.parse_statement()?;
+            self.eat_punct(';');
+        }
+        Ok(())
+    }
 
-        // Extract weight
-        let weight = if let Some(weight_start) = edge_xml.find("<data key=\"weight\">") {
-            let weight_str = &edge_xml[weight_start + 19..];
-            let weight_end = weight_str.find("</data>").unwrap_or(0);
-            weight_str[..weight_end].parse::<f64>().unwrap_or(1.0)
+    fn parse_statement(&mut self) -> Result<()> {
+        if self.eat_keyword("graph") {
+            self.parse_attributes()?;
+        } else if self.eat_keyword("node") {
+            let attributes = self.parse_attributes()?;
+            self.node_defaults.extend(attributes);
+        } else if self.eat_keyword("edge") {
+            let attributes = self.parse_attributes()?;
+            self.edge_defaults.extend(attributes);
+        } else if self.peek().is_some_and(|token| token.is_keyword("subgraph"))
+            || self.peek() == Some(&DotToken::Punct('{'))
+        {
+            let nodes = self.parse_subgraph()?;
+            if matches!(self.peek(), Some(DotToken::EdgeOp(_))) {
+                self.parse_edges(nodes)?;
+            }
         } else {
-            1.0
+            let name = self.expect_id("a statement")?;
+            if self.eat_punct('=') {
+                // A graph attribute
+                self.expect_id("an attribute value")?;
+                return Ok(());
+            }
+            self.parse_port()?;
+            let node = self.mention(name);
+            if matches!(self.peek(), Some(DotToken::EdgeOp(_))) {
+                self.parse_edges(vec![node])?;
+            } else {
+                let attributes = self.parse_attributes()?;
+                self.nodes[node].1.extend(attributes);
+            }
+        }
+        Ok(())
+    }
+
+    /// An edge chain continuing from `nodes`, with its attributes
+    fn parse_edges(&mut self, mut nodes: Vec<usize>) -> Result<()> {
+        let expected = if self.directed { "->" } else { "--" };
+        let mut pairs = Vec::new();
+        while let Some(&DotToken::EdgeOp(op)) = self.peek() {
+            if op != expected {
+                let kind = if self.directed { "a digraph" } else { "an undirected graph" };
+                return Err(self.error(format!("'{}' edge in {}", op, kind)));
+            }
+            self.pos += 1;
+            let targets = if self.peek().is_some_and(|token| token.is_keyword("subgraph"))
+                || self.peek() == Some(&DotToken::Punct('{'))
+            {
+                self.parse_subgraph()?
+            } else {
+                let name = self.expect_id("a node or subgraph")?;
+                self.parse_port()?;
+                vec![self.mention(name)]
+            };
+            pairs.push((nodes, targets.clone()));
+            nodes = targets;
+        }
+
+        let mut attributes = self.edge_defaults.clone();
+        attributes.extend(self.parse_attributes()?);
+        for (sources, targets) in pairs {
+            for &from in &sources {
+                for &to in &targets {
+                    self.edges.push((from, to, attributes.clone()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// A subgraph, returning the nodes mentioned in it
+    fn parse_subgraph(&mut self) -> Result<Vec<usize>> {
+        if self.eat_keyword("subgraph") && self.at_id() {
+            self.expect_id("a subgraph name")?;
+        }
+        self.expect_punct('{')?;
+
+        // Attribute defaults set inside a subgraph end with it
+        let node_defaults = self.node_defaults.clone();
+        let edge_defaults = self.edge_defaults.clone();
+        self.touched.push(Vec::new());
+        self.parse_statements()?;
+        self.expect_punct('}')?;
+        self.node_defaults = node_defaults;
+        self.edge_defaults = edge_defaults;
+
+        let mut nodes = self.touched.pop().unwrap_or_default();
+        let mut seen = HashSet::new();
+        nodes.retain(|node| seen.insert(*node));
+        if let Some(parent) = self.touched.last_mut() {
+            parent.extend_from_slice(&nodes);
+        }
+        Ok(nodes)
+    }
+
+    /// Skip a node's `:port` or `:port:compass`
+    fn parse_port(&mut self) -> Result<()> {
+        for _ in 0..2 {
+            if !self.eat_punct(':') {
+                break;
+            }
+            self.expect_id("a port")?;
+        }
+        Ok(())
+    }
+
+    /// `[key=value, ...]` lists, possibly none
+    fn parse_attributes(&mut self) -> Result<DotAttributes> {
+        let mut attributes = HashMap::new();
+        while self.eat_punct('[') {
+            while !self.eat_punct(']') {
+                let key = self.expect_id("an attribute name or ']'")?;
+                self.expect_punct('=')?;
+                let value = self.expect_id("an attribute value")?;
+                attributes.insert(key, value);
+                if !self.eat_punct(',') {
+                    self.eat_punct(';');
+                }
+            }
+        }
+        Ok(attributes)
+    }
+
+    /// The index of the node `name`, creating it with the current defaults
+    /// on first mention
+    fn mention(&mut self, name: String) -> usize {
+        let node = match self.index.get(&name) {
+            Some(&node) => node,
+            None => {
+                let node = self.nodes.len();
+                self.index.insert(name.clone(), node);
+                self.nodes.push((name, self.node_defaults.clone()));
+                node
+            }
         };
+        if let Some(touched) = self.touched.last_mut() {
+            touched.push(node);
+        }
+        node
+    }
 
-        edges.push((source, target, weight));
+    fn into_graph(self) -> Result<Graph> {
+        let numeric_id = |name: &str| {
+            name.parse::<NodeId>().ok().filter(|id| id.to_string() == name)
+        };
+        let mut fresh = self
+            .nodes
+            .iter()
+            .filter_map(|(name, _)| numeric_id(name))
+            .max()
+            .map_or(node_id(0), next_id);
+
+        let mut graph = if self.directed { Graph::new_directed() } else { Graph::new() };
+        let mut ids = Vec::with_capacity(self.nodes.len());
+        for (name, mut attributes) in self.nodes {
+            let id = numeric_id(&name).unwrap_or_else(|| {
+                let id = fresh;
+                fresh = next_id(fresh);
+                id
+            });
+            let label = attributes.remove("label").unwrap_or(name);
+            let mut node = Node::new(id, label);
+            for (key, value) in attributes {
+                if let Ok(value) = value.parse::<f64>() {
+                    node.properties.insert(key, value);
+                }
+            }
+            graph.add_node(node);
+            ids.push(id);
+        }
+
+        for (from, to, attributes) in self.edges {
+            let weight = match attributes.get("weight") {
+                Some(weight) => weight.parse::<f64>().map_err(|_| {
+                    GraphError::SerializationError(format!(
+                        "DOT edge weight '{}' is not a number",
+                        weight
+                    ))
+                })?,
+                None => 1.0,
+            };
+            let edge_type = attributes.get("label").map_or("default", String::as_str);
+            let mut edge = Edge::new(ids[from], ids[to], weight).with_type(edge_type);
+            if let Some(timestamp) = attributes.get("timestamp") {
+                edge.timestamp = Some(timestamp.parse::<f64>().map_err(|_| {
+                    GraphError::SerializationError(format!(
+                        "DOT edge timestamp '{}' is not a number",
+                        timestamp
+                    ))
+                })?);
+            }
+            graph.insert_edge(edge)?;
+        }
+        Ok(graph)
     }
+}
 
-    Ok(SerializableGraph {
-        nodes,
-        edges,
-        directed,
-    })
+/// Write a graph in Pajek `.net` format
+///
+/// Nodes become vertices `1..=n` in ascending ID order, so a graph with
+/// IDs `0..n` reads back with the same IDs. Every vertex is given the
+/// layout coordinates `0.0 0.0 0.0`. Pajek labels cannot contain double
+/// quotes or line breaks, so those are written as `'` and spaces. Edges
+/// are listed under `*Arcs` for directed graphs and `*Edges` otherwise,
+/// with their weights; types, timestamps and node properties are dropped.
+pub fn to_pajek(graph: &Graph) -> String {
+    let mut ids = graph.node_ids();
+    ids.sort_unstable();
+    let vertex: HashMap<NodeId, usize> =
+        ids.iter().enumerate().map(|(index, &id)| (id, index + 1)).collect();
+
+    let mut out = format!("*Vertices {}\n", ids.len());
+    for &id in &ids {
+        let Ok(node) = graph.node(id) else { continue };
+        let label = node.label.replace('"', "'").replace(['\r', '\n'], " ");
+        out.push_str(&format!("{} \"{}\" 0.0 0.0 0.0\n", vertex[&id], label));
+    }
+
+    out.push_str(if graph.is_directed() { "*Arcs\n" } else { "*Edges\n" });
+    for edge in graph.edges() {
+        out.push_str(&format!("{} {} {}\n", vertex[&edge.from], vertex[&edge.to], edge.weight));
+    }
+    out
+}
+
+/// Node placement for `to_tikz`, in TikZ units (centimetres by default)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TikzLayout {
+    /// Nodes evenly spaced on a circle, in ascending ID order
+    /// counter-clockwise from the positive x axis
+    Circular { radius: f64 },
+    /// Nodes in ascending ID order on a square grid one unit apart, row by
+    /// row from the top left
+    Grid,
+    /// Fruchterman-Reingold force-directed layout from random starting
+    /// positions, with an ideal edge length of one unit
+    Spring { seed: u64 },
+}
+
+/// Fruchterman-Reingold iterations of `TikzLayout::Spring`
+const SPRING_ITERATIONS: usize = 50;
+
+/// Render `graph` as a TikZ `tikzpicture` environment
+///
+/// Each node becomes a `\node` named `n<id>` and labelled with its label,
+/// LaTeX special characters escaped, and each edge a `\draw` between its
+/// endpoints, with an arrow if the graph is directed and as a loop if it
+/// is a self-loop. Weights, types and properties are not drawn. The
+/// picture needs only the base TikZ package.
+pub fn to_tikz(graph: &Graph, layout: TikzLayout) -> String {
+    let mut ids = graph.node_ids();
+    ids.sort_unstable();
+    let positions = match layout {
+        TikzLayout::Circular { radius } => circular_layout(ids.len(), radius),
+        TikzLayout::Grid => grid_layout(ids.len()),
+        TikzLayout::Spring { seed } => spring_layout(graph, &ids, seed),
+    };
+
+    let mut out = String::from("\\begin{tikzpicture}\n");
+    for (&id, (x, y)) in ids.iter().zip(positions) {
+        let Ok(node) = graph.node(id) else { continue };
+        out.push_str(&format!(
+            "    \\node[draw, circle] (n{}) at ({:.3}, {:.3}) {{{}}};\n",
+            id,
+            x,
+            y,
+            tikz_escape(&node.label)
+        ));
+    }
+
+    let style = if graph.is_directed() { "[->]" } else { "" };
+    for edge in graph.edges() {
+        let path = if edge.from == edge.to { "to[loop above]" } else { "--" };
+        out.push_str(&format!(
+            "    \\draw{} (n{}) {} (n{});\n",
+            style, edge.from, path, edge.to
+        ));
+    }
+
+    out.push_str("\\end{tikzpicture}\n");
+    out
 }
 
-fn escape_xml(s: &str) -> String {
-    s.replace('&', "&amp;")
-        .replace('<', "&lt;")
-        .replace('>', "&gt;")
-        .replace('"', "&quot;")
-        .replace('\'', "&apos;")
+fn circular_layout(n: usize, radius: f64) -> Vec<(f64, f64)> {
+    (0..n)
+        .map(|i| {
+            let angle = 2.0 * std::f64::consts::PI * i as f64 / n as f64;
+            (radius * angle.cos(), radius * angle.sin())
+        })
+        .collect()
 }
 
-fn unescape_xml(s: &str) -> String {
-    s.replace("&amp;", "&")
-        .replace("&lt;", "<")
-        .replace("&gt;", ">")
-        .replace("&quot;", "\"")
-        .replace("&apos;", "'")
+fn grid_layout(n: usize) -> Vec<(f64, f64)> {
+    let columns = (n as f64).sqrt().ceil().max(1.0) as usize;
+    (0..n).map(|i| ((i % columns) as f64, -((i / columns) as f64))).collect()
 }
 
-#[cfg(test)]
+/// Fruchterman-Reingold with `k = 1` in a `sqrt(n)` square, the
+/// temperature cooling linearly from a tenth of the frame to zero
+fn spring_layout(graph: &Graph, ids: &[NodeId], seed: u64) -> Vec<(f64, f64)> {
+    let n = ids.len();
+    let frame = (n as f64).sqrt().max(1.0);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut positions: Vec<(f64, f64)> =
+        (0..n).map(|_| (rng.gen::<f64>() * frame, rng.gen::<f64>() * frame)).collect();
+    let index: HashMap<NodeId, usize> = ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+    let edges: Vec<(usize, usize)> = graph
+        .edges()
+        .iter()
+        .filter(|edge| edge.from != edge.to)
+        .filter_map(|edge| Some((*index.get(&edge.from)?, *index.get(&edge.to)?)))
+        .collect();
+
+    for iteration in 0..SPRING_ITERATIONS {
+        let temperature = frame / 10.0 * (1.0 - iteration as f64 / SPRING_ITERATIONS as f64);
+        let mut shifts = vec![(0.0, 0.0); n];
+        // Every pair repels with force 1/d, every edge attracts with d^2
+        for i in 0..n {
+            for j in i + 1..n {
+                let (dx, dy) = (positions[i].0 - positions[j].0, positions[i].1 - positions[j].1);
+                let distance_sq = (dx * dx + dy * dy).max(1e-9);
+                let (fx, fy) = (dx / distance_sq, dy / distance_sq);
+                shifts[i].0 += fx;
+                shifts[i].1 += fy;
+                shifts[j].0 -= fx;
+                shifts[j].1 -= fy;
+            }
+        }
+        for &(u, v) in &edges {
+            let (dx, dy) = (positions[u].0 - positions[v].0, positions[u].1 - positions[v].1);
+            let distance = (dx * dx + dy * dy).sqrt();
+            let (fx, fy) = (dx * distance, dy * distance);
+            shifts[u].0 -= fx;
+            shifts[u].1 -= fy;
+            shifts[v].0 += fx;
+            shifts[v].1 += fy;
+        }
+        for (position, (sx, sy)) in positions.iter_mut().zip(shifts) {
+            let length = (sx * sx + sy * sy).sqrt();
+            if length > 0.0 {
+                let step = length.min(temperature) / length;
+                position.0 += sx * step;
+                position.1 += sy * step;
+            }
+        }
+    }
+    positions
+}
+
+/// Escape the characters with a special meaning in LaTeX text
+fn tikz_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '\n' | '\r' => escaped.push(' '),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Read a graph in Pajek `.net` format
+///
+/// Vertex `k` becomes node `k - 1`, labelled with its quoted or bare
+/// label if it has a line under `*Vertices` and with `k` otherwise;
+/// layout coordinates and other vertex attributes are ignored. `*Arcs`,
+/// `*Edges`, `*Arcslist` and `*Edgeslist` sections are read, each line of
+/// the first two with an optional weight (1.0 if absent). The graph is
+/// directed if it has an `*Arcs` or `*Arcslist` section, even an empty
+/// one; edges in a directed graph are then added in both directions.
+/// Keywords are case-insensitive, `%` starts a comment line and
+/// `*Network` lines are skipped.
+pub fn from_pajek(input: &str) -> Result<Graph> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Section {
+        Start,
+        Vertices,
+        Pairs { arcs: bool },
+        Lists { arcs: bool },
+    }
+
+    let mut section = Section::Start;
+    let mut labels: Vec<String> = Vec::new();
+    let mut edges: Vec<(usize, usize, f64, bool)> = Vec::new();
+    let mut directed = false;
+    for (index, raw) in input.lines().enumerate() {
+        let line_number = index + 1;
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('%') {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('*') {
+            let mut words = header.split_whitespace();
+            let keyword = words.next().unwrap_or("").to_ascii_lowercase();
+            section = match keyword.as_str() {
+                "network" => continue,
+                "vertices" => {
+                    let count = words.next().and_then(|count| count.parse::<usize>().ok());
+                    let count = count.ok_or_else(|| {
+                        pajek_error(line_number, "*Vertices needs a vertex count")
+                    })?;
+                    labels = (1..=count).map(|k| k.to_string()).collect();
+                    Section::Vertices
+                }
+                "arcs" | "edges" if section != Section::Start => {
+                    Section::Pairs { arcs: keyword == "arcs" }
+                }
+                "arcslist" | "edgeslist" if section != Section::Start => {
+                    Section::Lists { arcs: keyword == "arcslist" }
+                }
+                "arcs" | "edges" | "arcslist" | "edgeslist" => {
+                    return Err(pajek_error(line_number, "edges before *Vertices"));
+                }
+                _ => {
+                    return Err(pajek_error(
+                        line_number,
+                        format!("unsupported section *{}", keyword),
+                    ))
+                }
+            };
+            if let Section::Pairs { arcs } | Section::Lists { arcs } = section {
+                directed |= arcs;
+            }
+            continue;
+        }
+
+        let vertex = |token: Option<&str>| -> Result<usize> {
+            let token = token.unwrap_or("");
+            match token.parse::<usize>() {
+                Ok(k) if (1..=labels.len()).contains(&k) => Ok(k - 1),
+                _ => Err(pajek_error(line_number, format!("'{}' is not a vertex", token))),
+            }
+        };
+        let mut tokens = line.split_whitespace();
+        match section {
+            Section::Start => {
+                return Err(pajek_error(line_number, "expected *Vertices"));
+            }
+            Section::Vertices => {
+                let k = vertex(tokens.next())?;
+                let rest = line.split_once(char::is_whitespace).map_or("", |(_, rest)| rest);
+                let rest = rest.trim_start();
+                let label = match rest.strip_prefix('"') {
+                    Some(quoted) => quoted.split('"').next().unwrap_or(""),
+                    None => rest.split_whitespace().next().unwrap_or(""),
+                };
+                if !label.is_empty() {
+                    labels[k] = label.to_string();
+                }
+            }
+            Section::Pairs { arcs } => {
+                let from = vertex(tokens.next())?;
+                let to = vertex(tokens.next())?;
+                let weight = match tokens.next() {
+                    Some(weight) => weight.parse::<f64>().map_err(|_| {
+                        pajek_error(line_number, format!("weight '{}' is not a number", weight))
+                    })?,
+                    None => 1.0,
+                };
+                edges.push((from, to, weight, arcs));
+            }
+            Section::Lists { arcs } => {
+                let from = vertex(tokens.next())?;
+                for token in tokens {
+                    edges.push((from, vertex(Some(token))?, 1.0, arcs));
+                }
+            }
+        }
+    }
+
+    let mut graph = if directed { Graph::new_directed() } else { Graph::new() };
+    for (index, label) in labels.into_iter().enumerate() {
+        graph.add_node(Node::new(node_id(index), label));
+    }
+    for (from, to, weight, arc) in edges {
+        let (from, to) = (node_id(from), node_id(to));
+        graph.add_edge(from, to, weight)?;
+        if directed && !arc && from != to {
+            graph.add_edge(to, from, weight)?;
+        }
+    }
+    Ok(graph)
+}
+
+fn pajek_error(line: usize, message: impl std::fmt::Display) -> GraphError {
+    GraphError::SerializationError(format!("Pajek line {}: {}", line, message))
+}
+
+#[cfg(all(test, not(feature = "uuid-ids")))]
 mod tests {
     use super::*;
+    use crate::compare::{Difference, EqOptions};
     use std::fs;
     use tempfile::TempDir;
 
@@ -337,8 +1518,7 @@ mod tests {
         assert!(path.exists());
 
         let loaded = load_graph(&path, StorageFormat::Binary).unwrap();
-        assert_eq!(loaded.node_count(), graph.node_count());
-        assert_eq!(loaded.edge_count(), graph.edge_count());
+        assert_eq!(graph.assert_equivalent(&loaded, EqOptions::default()), Ok(()));
     }
 
     #[test]
@@ -356,8 +1536,7 @@ mod tests {
         assert!(content.contains("Bob"));
 
         let loaded = load_graph(&path, StorageFormat::Json).unwrap();
-        assert_eq!(loaded.node_count(), graph.node_count());
-        assert_eq!(loaded.edge_count(), graph.edge_count());
+        assert_eq!(graph.assert_equivalent(&loaded, EqOptions::default()), Ok(()));
     }
 
     #[test]
@@ -375,8 +1554,7 @@ mod tests {
         assert!(content.contains("<graphml"));
 
         let loaded = load_graph(&path, StorageFormat::GraphML).unwrap();
-        assert_eq!(loaded.node_count(), graph.node_count());
-        assert_eq!(loaded.edge_count(), graph.edge_count());
+        assert_eq!(graph.assert_equivalent(&loaded, EqOptions::default()), Ok(()));
     }
 
     #[test]
@@ -407,4 +1585,502 @@ mod tests {
         assert_eq!(loaded.node_count(), 0);
         assert_eq!(loaded.edge_count(), 0);
     }
+
+    fn large_test_graph() -> Graph {
+        let mut graph = Graph::new();
+        for i in 0..500 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        for i in 0..2000 {
+            graph.add_edge(i % 500, (i * 7 + 1) % 500, (i % 10) as f64).unwrap();
+        }
+        graph
+    }
+
+    #[test]
+    fn test_save_load_binary_zstd() {
+        let graph = large_test_graph();
+        let temp_dir = TempDir::new().unwrap();
+        let binary = temp_dir.path().join("graph.bin");
+        save_graph(&graph, &binary, StorageFormat::Binary).unwrap();
+
+        for level in [0, 1, 9] {
+            let path = temp_dir.path().join(format!("graph-{}.bin.zst", level));
+            save_graph(&graph, &path, StorageFormat::BinaryZstd { level }).unwrap();
+            assert!(fs::metadata(&path).unwrap().len() < fs::metadata(&binary).unwrap().len());
+
+            let loaded = load_graph(&path, StorageFormat::BinaryZstd { level: 0 }).unwrap();
+            assert_eq!(loaded.node_count(), graph.node_count());
+            assert_eq!(loaded.edges().len(), graph.edges().len());
+            for (a, b) in loaded.edges().iter().zip(graph.edges()) {
+                assert_eq!((a.from, a.to, a.weight), (b.from, b.to, b.weight));
+            }
+        }
+    }
+
+    #[test]
+    fn test_binary_is_not_binary_zstd() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("graph.bin");
+        save_graph(&large_test_graph(), &path, StorageFormat::Binary).unwrap();
+
+        assert!(matches!(
+            load_graph(&path, StorageFormat::BinaryZstd { level: 0 }),
+            Err(GraphError::SerializationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_from_dot() {
+        let graph = from_dot("graph G { a -- b [weight=2.0]; }").unwrap();
+        assert!(!graph.is_directed());
+        assert_eq!(graph.node(0).unwrap().label, "a");
+        assert_eq!(graph.node(1).unwrap().label, "b");
+        assert_eq!(graph.neighbors_with_weights(1).unwrap(), vec![(0, 2.0)]);
+
+        let graph = from_dot(
+            r#"
+            /* Numbered nodes keep their ids */
+            strict digraph "G" {
+                rankdir = LR;
+                node [shape=box, rank=2];
+                7 [label="Seven", score=0.5, color=red];
+                "x y" -> 7 [weight=1.5, label="foo"]; // trailing comment
+                edge [weight=3]
+                7 -> { a "x y" } -> c:port:n
+            }
+            "#,
+        )
+        .unwrap();
+        assert!(graph.is_directed());
+        assert_eq!(graph.node_count(), 4);
+        let seven = graph.node(7).unwrap();
+        assert_eq!(seven.label, "Seven");
+        assert_eq!(seven.properties.get("score"), Some(&0.5));
+        assert_eq!(seven.properties.get("rank"), Some(&2.0));
+        assert!(!seven.properties.contains_key("color"));
+        // Named nodes are numbered after the largest numbered one
+        assert_eq!(graph.node(8).unwrap().label, "x y");
+        assert_eq!(graph.node(9).unwrap().label, "a");
+        assert_eq!(graph.node(10).unwrap().label, "c");
+
+        assert_eq!(graph.neighbors_of_type(8, "foo").unwrap(), vec![7]);
+        let mut from_seven = graph.neighbors_with_weights(7).unwrap();
+        from_seven.sort_by_key(|&(node, _)| node);
+        assert_eq!(from_seven, vec![(8, 3.0), (9, 3.0)]);
+        assert_eq!(graph.neighbors(9).unwrap(), vec![10]);
+        assert_eq!(graph.edge_count(), 5);
+    }
+
+    #[test]
+    fn test_from_dot_syntax_errors() {
+        for input in [
+            "",
+            "graph { a -- b",
+            "graph { a -> b }",
+            "digraph { a -- b }",
+            "graph { a -- }",
+            "graph { a [weight] }",
+            "graph { \"a -- b }",
+            "graph { a -- b [weight=heavy] }",
+            "graph { a } graph { b }",
+            "graph { a ? b }",
+        ] {
+            assert!(
+                matches!(from_dot(input), Err(GraphError::SerializationError(_))),
+                "{:?} should not parse",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_dot_roundtrip() {
+        let mut graph = Graph::new_directed();
+        let mut alice = Node::new(0, "Alice \"Al\" \\ Smith");
+        alice.properties.insert("age".to_string(), 42.0);
+        alice.properties.insert("risk score".to_string(), -0.25);
+        graph.add_node(alice);
+        graph.add_node_simple("graph");
+        graph.add_node(Node::new(5, "Line\nbreak"));
+        graph.add_typed_edge(0, 1, 0.1 + 0.2, "knows").unwrap();
+        graph.add_edge(1, 5, 1e-9).unwrap();
+        graph.add_edge(5, 5, f64::INFINITY).unwrap();
+        graph.add_edge(0, 1, 2.0).unwrap();
+
+        let dot = to_dot(&graph);
+        assert!(dot.starts_with("digraph {"));
+        let loaded = from_dot(&dot).unwrap();
+        crate::testing::check_roundtrip(&graph, &loaded).unwrap();
+        assert_eq!(loaded.node(0).unwrap().properties, graph.node(0).unwrap().properties);
+        assert_eq!(loaded.neighbors_of_type(0, "knows").unwrap(), vec![1]);
+
+        let graph = create_test_graph();
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("graph.dot");
+        save_graph(&graph, &path, StorageFormat::Dot).unwrap();
+        assert!(fs::read_to_string(&path).unwrap().contains("0 -- 1 [weight=1.5]"));
+        let loaded = load_graph(&path, StorageFormat::Dot).unwrap();
+        crate::testing::check_roundtrip(&graph, &loaded).unwrap();
+    }
+
+    #[test]
+    fn test_timestamps_roundtrip_in_all_formats() {
+        let mut graph = create_test_graph();
+        graph.add_timestamped_edge(0, 2, 4.0, 1_700_000_000.5).unwrap();
+        graph.add_timestamped_edge(2, 0, 1.0, -3.0).unwrap();
+        let timestamps = |graph: &Graph| -> Vec<Option<f64>> {
+            graph.edges().iter().map(|edge| edge.timestamp).collect()
+        };
+
+        let temp_dir = TempDir::new().unwrap();
+        for format in [
+            StorageFormat::Binary,
+            StorageFormat::BinaryZstd { level: 1 },
+            StorageFormat::Json,
+            StorageFormat::GraphML,
+            StorageFormat::Dot,
+        ] {
+            let path = temp_dir.path().join("graph");
+            save_graph(&graph, &path, format).unwrap();
+            let loaded = load_graph(&path, format).unwrap();
+            assert_eq!(timestamps(&loaded), timestamps(&graph), "{:?}", format);
+            crate::testing::check_roundtrip(&graph, &loaded).unwrap();
+        }
+
+        assert!(matches!(
+            from_dot("graph { a -- b [timestamp=noon] }"),
+            Err(GraphError::SerializationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_pajek_roundtrip() {
+        let mut graph = Graph::new_directed();
+        for label in ["Alice", "Bob Smith", "Charlie", "Dana", "Eve"] {
+            graph.add_node_simple(label);
+        }
+        graph.add_edge(0, 1, 1.5).unwrap();
+        graph.add_edge(1, 2, 2.0).unwrap();
+        graph.add_edge(2, 0, 0.1 + 0.2).unwrap();
+        graph.add_edge(3, 4, 1.0).unwrap();
+        graph.add_edge(4, 4, 7.0).unwrap();
+
+        let pajek = to_pajek(&graph);
+        assert!(pajek.starts_with("*Vertices 5\n1 \"Alice\" 0.0 0.0 0.0\n2 \"Bob Smith\" 0.0"));
+        assert!(pajek.contains("*Arcs\n1 2 1.5\n"));
+        let loaded = from_pajek(&pajek).unwrap();
+        crate::testing::check_roundtrip(&graph, &loaded).unwrap();
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("graph.net");
+        let undirected = create_test_graph();
+        save_graph(&undirected, &path, StorageFormat::Pajek).unwrap();
+        assert!(fs::read_to_string(&path).unwrap().contains("*Edges\n1 2 1.5\n"));
+        let loaded = load_graph(&path, StorageFormat::Pajek).unwrap();
+        crate::testing::check_roundtrip(&undirected, &loaded).unwrap();
+    }
+
+    #[test]
+    fn test_from_pajek() {
+        // As the Pajek manual writes them: comments, a network name, a
+        // vertex without a line, bare labels, extra vertex and edge
+        // attributes, lower-case keywords, mixed edges and arcs and lists
+        let input = "% a network\r
+*Network Example\r
+*Vertices 4\r
+1 \"Ann Lee\" 0.1 0.2 0.5 ic Red\r
+2 Bob\r
+4 \"\"\r
+*edges\r
+1 2 3.5 c Blue\r
+*Arcs\r
+2 3\r
+*Arcslist\r
+4 1 2\r
+";
+        let graph = from_pajek(input).unwrap();
+        assert!(graph.is_directed());
+        let labels: Vec<String> =
+            (0..4).map(|id| graph.node(id).unwrap().label.clone()).collect();
+        assert_eq!(labels, vec!["Ann Lee", "Bob", "3", "4"]);
+        assert_eq!(graph.neighbors_with_weights(1).unwrap(), vec![(0, 3.5), (2, 1.0)]);
+        assert_eq!(graph.neighbors(3).unwrap(), vec![0, 1]);
+        assert_eq!(graph.edge_count(), 5);
+
+        assert!(from_pajek("*Vertices 2\n*Arcs\n").unwrap().is_directed());
+        let undirected = from_pajek("*Vertices 2\n*Edges\n1 2\n").unwrap();
+        assert!(!undirected.is_directed());
+        assert_eq!(undirected.neighbors_with_weights(1).unwrap(), vec![(0, 1.0)]);
+
+        for input in [
+            "1 \"A\"",
+            "*Vertices",
+            "*Vertices 2\n3 \"C\"",
+            "*Edges\n1 2",
+            "*Vertices 2\n*Edges\n1 2 heavy",
+            "*Vertices 2\n*Edges\n1 0",
+            "*Vertices 2\n*Matrix\n0 1\n1 0",
+        ] {
+            assert!(
+                matches!(from_pajek(input), Err(GraphError::SerializationError(_))),
+                "{:?}",
+                input
+            );
+        }
+    }
+    #[test]
+    fn test_graphml_roundtrip_adversarial_labels() {
+        let labels = [
+            r#"say "hi" & 'bye'"#,
+            "line one\nline two\r\nline\tthree",
+            "ünïcødé 日本語 עברית",
+            "]]> and <![CDATA[",
+            "emoji 🎉 👩‍👩‍👧",
+            r#"<node id="n9"></node></data>"#,
+            "&amp; &#65; &unknown;",
+            "control\u{0}\u{7}\u{1b}\u{FFFE} characters",
+            "",
+        ];
+        let mut graph = Graph::new();
+        for label in labels {
+            graph.add_node_simple(label);
+        }
+        graph.add_edge(0, 1, 1.0).unwrap();
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("graph.graphml");
+        save_graph(&graph, &path, StorageFormat::GraphML).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.chars().all(is_xml_char));
+        assert!(!content.contains("]]>"));
+
+        let loaded = load_graph(&path, StorageFormat::GraphML).unwrap();
+        assert_eq!(loaded.node_count(), labels.len());
+        assert_eq!(loaded.edge_count(), 1);
+        for (id, label) in labels.iter().enumerate() {
+            let expected: String = label.chars().filter(|&c| is_xml_char(c)).collect();
+            assert_eq!(loaded.node(id).unwrap().label, expected);
+        }
+    }
+
+    #[test]
+    fn test_graphml_attribute_escaping() {
+        assert_eq!(
+            escape_xml_attribute("a\"b'c<d>&\t\n\r\u{1}"),
+            "a&quot;b&apos;c&lt;d&gt;&amp;&#9;&#10;&#13;"
+        );
+        assert_eq!(escape_xml_text("a\"b'\t\n\r"), "a\"b'\t\n&#13;");
+        assert_eq!(unescape_xml("&amp;lt; &#x1F389; &#10;").unwrap(), "&lt; 🎉 \n");
+        assert!(unescape_xml("&#1;").is_err());
+    }
+
+    #[test]
+    fn test_graphml_rejects_malformed_structure() {
+        let node = r#"<node id="n0"><data key="label">A</data></node>"#;
+        let cases = [
+            // Missing end tags
+            r#"<node id="n0"><data key="label">A</data><node id="n1"></node>"#.to_string(),
+            r#"<node id="n0"><data key="label">A</node>"#.to_string(),
+            format!(r#"{}<edge id="e0" source="n0" target="n0">"#, node),
+            // Missing or invalid ids
+            r#"<node key="n0"></node>"#.to_string(),
+            r#"<node id="nX"></node>"#.to_string(),
+            format!(r#"{}<edge id="e0" target="n0"></edge>"#, node),
+            format!(r#"{}<edge id="e0" source="" target="n0"></edge>"#, node),
+            // Bad numbers and text
+            format!(
+                r#"{}<edge id="e0" source="n0" target="n0"><data key="weight">x</data></edge>"#,
+                node
+            ),
+            r#"<node id="n0"><data key="label">A &nbsp; B</data></node>"#.to_string(),
+            r#"<node id="n0"><data key="label">A & B</data></node>"#.to_string(),
+        ];
+        for input in &cases {
+            assert!(
+                matches!(
+                    read_graphml(&mut input.as_bytes()),
+                    Err(GraphError::SerializationError(_))
+                ),
+                "{}",
+                input
+            );
+        }
+
+        let valid = format!(r#"{}<edge id="e0" source="n0" target="0"/>"#, node);
+        let graph = read_graphml(&mut valid.as_bytes()).unwrap().to_graph().unwrap();
+        assert_eq!(graph.edges()[0].weight, 1.0);
+    }
+    #[test]
+    fn test_roundtrip_fidelity_per_format() {
+        let mut graph = Graph::new_directed();
+        let mut alice = Node::new(0, "Alice");
+        alice.properties.insert("age".to_string(), 42.0);
+        alice.features = Some(vec![0.5, -1.0]);
+        graph.add_node(alice);
+        graph.add_node_simple("Bob");
+        graph.add_node_simple("Charlie");
+        graph.add_typed_edge(0, 1, 1.5, "knows").unwrap();
+        graph.add_timestamped_edge(1, 2, 0.1 + 0.2, 7.0).unwrap();
+        graph.add_edge(2, 0, 2.0).unwrap();
+
+        // What each format is known to drop; a strict comparison must
+        // report exactly that, so fixing a format means updating this
+        let strict = EqOptions::default();
+        let cases = [
+            (StorageFormat::Binary, strict.ignore_edge_types()),
+            (StorageFormat::BinaryZstd { level: 1 }, strict.ignore_edge_types()),
+            (StorageFormat::Json, strict.ignore_edge_types()),
+            (StorageFormat::GraphML, strict.ignore_edge_types().ignore_properties()),
+            (StorageFormat::Dot, strict.ignore_properties()),
+            (
+                StorageFormat::Pajek,
+                strict.ignore_edge_types().ignore_properties().ignore_timestamps(),
+            ),
+        ];
+        let temp_dir = TempDir::new().unwrap();
+        for (format, preserved) in cases {
+            let path = temp_dir.path().join("graph");
+            save_graph(&graph, &path, format).unwrap();
+            let loaded = load_graph(&path, format).unwrap();
+            assert_eq!(graph.assert_equivalent(&loaded, preserved), Ok(()), "{:?}", format);
+
+            let lost = graph.assert_equivalent(&loaded, strict).unwrap_err();
+            for difference in &lost {
+                let expected = match difference {
+                    Difference::EdgeType { .. } => !preserved.edge_types,
+                    Difference::Property { .. } | Difference::Features { .. } => {
+                        !preserved.properties
+                    }
+                    Difference::Timestamp { .. } => !preserved.timestamps,
+                    _ => false,
+                };
+                assert!(expected, "{:?} unexpectedly lost {}", format, difference);
+            }
+        }
+    }
+    #[test]
+    fn test_sorted_adjacency_survives_json_roundtrip() {
+        let edges = [(0, 4), (0, 2), (4, 3), (2, 3), (0, 1), (1, 3), (3, 5)];
+        let build = |edges: &[(NodeId, NodeId)]| {
+            let mut graph = Graph::new();
+            for i in 0..6 {
+                graph.add_node_simple(format!("Node{}", i));
+            }
+            graph.sort_adjacency();
+            for &(from, to) in edges {
+                graph.add_edge(from, to, 1.0).unwrap();
+            }
+            graph
+        };
+        let graph = build(&edges);
+        let mut reversed = edges;
+        reversed.reverse();
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("graph.json");
+        save_graph(&graph, &path, StorageFormat::Json).unwrap();
+        let loaded = load_graph(&path, StorageFormat::Json).unwrap();
+        assert!(loaded.has_sorted_adjacency());
+
+        let dfs = |graph: &Graph| crate::algorithms::dfs(graph, 0, 5).unwrap();
+        assert_eq!(dfs(&graph), vec![0, 4, 3, 5]);
+        assert_eq!(dfs(&loaded), dfs(&graph));
+        assert_eq!(dfs(&build(&reversed)), dfs(&graph));
+        for id in graph.node_ids() {
+            assert_eq!(loaded.neighbors(id).unwrap(), graph.neighbors(id).unwrap());
+        }
+    }
+    #[test]
+    fn test_json_roundtrip_is_byte_stable() {
+        let mut graph = Graph::new();
+        for i in 0..40 {
+            let node = Node::new(i, format!("Node{}", i))
+                .with_property("rank", i as f64)
+                .with_property("score", 1.0 / (i + 1) as f64)
+                .with_property("bucket", (i % 3) as f64);
+            graph.add_node(node);
+        }
+        for i in 0..40 {
+            graph.add_edge(i, (i * 7 + 3) % 40, 1.0).unwrap();
+            graph.add_edge(i, (i * 11 + 5) % 40, 2.0).unwrap();
+            graph.add_edge(i, (i + 1) % 40, 3.0).unwrap();
+        }
+        graph.contract_nodes(&[38, 39], "Merged").unwrap();
+
+        let temp_dir = TempDir::new().unwrap();
+        let save = |graph: &Graph, name: &str| {
+            let path = temp_dir.path().join(name);
+            save_graph(graph, &path, StorageFormat::Json).unwrap();
+            (std::fs::read(&path).unwrap(), path)
+        };
+        let (first, path) = save(&graph, "first.json");
+        assert_eq!(save(&graph, "second.json").0, first);
+        let mut loaded = load_graph(&path, StorageFormat::Json).unwrap();
+        assert_eq!(save(&loaded, "reloaded.json").0, first);
+
+        // Traversals and fresh IDs match the original
+        let dfs = |graph: &Graph| crate::algorithms::dfs(graph, 0, 17).unwrap();
+        assert_eq!(dfs(&loaded), dfs(&graph));
+        for id in graph.node_ids() {
+            assert_eq!(loaded.neighbors(id).unwrap(), graph.neighbors(id).unwrap());
+        }
+        let mut original = graph.clone();
+        assert_eq!(loaded.add_node_simple("New"), original.add_node_simple("New"));
+    }
+    #[test]
+    fn test_to_tikz() {
+        let mut graph = Graph::new_directed();
+        for i in 0..6 {
+            graph.add_node(Node::new(i, format!("v_{}", i)));
+        }
+        graph.add_node(Node::new(6, "50% & {odd} #\\"));
+        for i in 0..6 {
+            graph.add_edge(i, (i + 1) % 6, 1.0).unwrap();
+        }
+        graph.add_edge(6, 0, 1.0).unwrap();
+        graph.add_edge(3, 3, 1.0).unwrap();
+
+        let layouts = [
+            TikzLayout::Circular { radius: 2.0 },
+            TikzLayout::Grid,
+            TikzLayout::Spring { seed: 7 },
+        ];
+        for layout in layouts {
+            let tikz = to_tikz(&graph, layout);
+            let lines: Vec<&str> = tikz.lines().collect();
+            assert_eq!(lines.first(), Some(&"\\begin{tikzpicture}"));
+            assert_eq!(lines.last(), Some(&"\\end{tikzpicture}"));
+            let body = &lines[1..lines.len() - 1];
+            assert!(body.iter().all(|line| line.ends_with(';')), "{}", tikz);
+            let count = |prefix: &str| body.iter().filter(|l| l.trim().starts_with(prefix)).count();
+            assert_eq!(count("\\node"), graph.node_count());
+            assert_eq!(count("\\draw[->]"), graph.edge_count());
+            assert!(tikz.contains("\\draw[->] (n3) to[loop above] (n3);"));
+
+            // Braces balance once escaped ones are removed
+            let unescaped = tikz.replace("\\{", "").replace("\\}", "");
+            assert_eq!(unescaped.matches('{').count(), unescaped.matches('}').count());
+            assert!(!tikz.contains("NaN") && !tikz.contains("inf"), "{}", tikz);
+        }
+
+        let circular = to_tikz(&graph, TikzLayout::Circular { radius: 2.0 });
+        assert!(circular.contains("\\node[draw, circle] (n0) at (2.000, 0.000) {v\\_0};"));
+        assert!(circular.contains("{50\\% \\& \\{odd\\} \\#\\textbackslash{}}"));
+        let grid = to_tikz(&graph, TikzLayout::Grid);
+        assert!(grid.contains("(n4) at (1.000, -1.000)"));
+        let spring = |seed| to_tikz(&graph, TikzLayout::Spring { seed });
+        assert_eq!(spring(7), spring(7));
+        assert_ne!(spring(7), spring(8));
+
+        let mut undirected = Graph::new();
+        undirected.add_node(Node::new(0, "a"));
+        undirected.add_node(Node::new(1, "b"));
+        undirected.add_edge(0, 1, 1.0).unwrap();
+        assert!(to_tikz(&undirected, TikzLayout::Grid).contains("\\draw (n0) -- (n1);"));
+        assert_eq!(
+            to_tikz(&Graph::new(), TikzLayout::Spring { seed: 1 }),
+            "\\begin{tikzpicture}\n\\end{tikzpicture}\n"
+        );
+    }
 }
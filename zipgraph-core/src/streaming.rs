@@ -0,0 +1,233 @@
+//! Traversal of graphs that are read on demand instead of held in memory
+//!
+//! An `EdgeProvider` answers neighbor queries, for example by scanning a
+//! file, so a search only keeps its frontier and visited set in memory.
+//! `FileEdgeProvider` serves an edge list file this way.
+
+use crate::algorithms::reconstruct_path;
+use crate::error::{GraphError, Result};
+use crate::types::{node_id, node_index, NodeId};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+/// Source of adjacency for the streaming searches
+///
+/// Nodes are `node_id(0)` to `node_id(node_count() - 1)`, which under the
+/// default `usize` IDs is `0..node_count()`.
+pub trait EdgeProvider {
+    /// Nodes reachable from `node` over one edge, in a stable order
+    fn neighbors(&self, node: NodeId) -> Box<dyn Iterator<Item = NodeId> + '_>;
+
+    /// Number of nodes
+    fn node_count(&self) -> usize;
+}
+
+/// Breadth-first search over `provider`, as `algorithms::bfs` does over a
+/// `Graph`
+///
+/// Neighbors are explored in the order the provider yields them, so for a
+/// provider that yields them in a graph's adjacency order the path is the
+/// one `algorithms::bfs` finds. `None` if `goal` is unreachable or either
+/// node is out of range.
+pub fn streaming_bfs(
+    provider: &dyn EdgeProvider,
+    start: NodeId,
+    goal: NodeId,
+) -> Option<Vec<NodeId>> {
+    let node_count = provider.node_count();
+    let in_range = |node| node_index(node).is_some_and(|index| index < node_count);
+    if !in_range(start) || !in_range(goal) {
+        return None;
+    }
+
+    let mut queue = VecDeque::new();
+    let mut visited = HashSet::new();
+    let mut parent = HashMap::new();
+
+    queue.push_back(start);
+    visited.insert(start);
+
+    while let Some(current) = queue.pop_front() {
+        if current == goal {
+            return Some(reconstruct_path(&parent, start, goal));
+        }
+
+        for neighbor in provider.neighbors(current) {
+            if visited.insert(neighbor) {
+                parent.insert(neighbor, current);
+                queue.push_back(neighbor);
+            }
+        }
+    }
+
+    None
+}
+
+/// An edge list file read afresh for every `neighbors` call
+///
+/// Each line holds a `from,to` pair of node IDs; further comma-separated
+/// columns, such as a weight, are ignored, as are blank lines, lines
+/// starting with `#` and a header line whose first field is not a number.
+/// Neighbors are yielded in file order, and in an undirected file an edge
+/// is also followed from `to` to `from`, matching the adjacency order of
+/// a `Graph` built by adding the edges in file order. Only the file path
+/// and the node count are held in memory.
+#[derive(Debug, Clone)]
+pub struct FileEdgeProvider {
+    path: PathBuf,
+    directed: bool,
+    node_count: usize,
+}
+
+impl FileEdgeProvider {
+    /// Check the file and count its nodes, one more than the largest ID
+    ///
+    /// Fails with `IoError` if the file cannot be read and with
+    /// `SerializationError` on a malformed line.
+    pub fn open<P: AsRef<Path>>(path: P, directed: bool) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut node_count = 0;
+        for (index, line) in BufReader::new(File::open(&path)?).lines().enumerate() {
+            let line = line?;
+            match parse_edge_line(&line, index) {
+                Some(Ok((from, to))) => node_count = node_count.max(from.max(to) + 1),
+                Some(Err(message)) => {
+                    return Err(GraphError::SerializationError(format!(
+                        "edge list line {}: {}",
+                        index + 1,
+                        message
+                    )))
+                }
+                None => {}
+            }
+        }
+        Ok(Self { path, directed, node_count })
+    }
+
+    /// Whether edges are only followed from `from` to `to`
+    pub fn is_directed(&self) -> bool {
+        self.directed
+    }
+}
+
+impl EdgeProvider for FileEdgeProvider {
+    /// Scans the whole file; a file that can no longer be read yields no
+    /// neighbors
+    fn neighbors(&self, node: NodeId) -> Box<dyn Iterator<Item = NodeId> + '_> {
+        let (Some(node), Ok(file)) = (node_index(node), File::open(&self.path)) else {
+            return Box::new(std::iter::empty());
+        };
+        let directed = self.directed;
+        let neighbors = BufReader::new(file)
+            .lines()
+            .map_while(|line| line.ok())
+            .enumerate()
+            .filter_map(move |(index, line)| match parse_edge_line(&line, index)? {
+                Ok((from, to)) if from == node => Some(node_id(to)),
+                Ok((from, to)) if to == node && !directed => Some(node_id(from)),
+                _ => None,
+            });
+        Box::new(neighbors)
+    }
+
+    fn node_count(&self) -> usize {
+        self.node_count
+    }
+}
+
+type EdgeLine = std::result::Result<(usize, usize), String>;
+
+/// The node numbers of the edge on line `index` (0-based), `None` for a
+/// line without one
+fn parse_edge_line(line: &str, index: usize) -> Option<EdgeLine> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let mut fields = line.split(',').map(str::trim);
+    let from = fields.next().unwrap_or_default();
+    if index == 0 && from.parse::<usize>().is_err() {
+        return None;
+    }
+    let to = fields.next().unwrap_or_default();
+    let parse = |field: &str| {
+        field.parse::<usize>().map_err(|_| format!("'{}' is not a node ID", field))
+    };
+    Some(parse(from).and_then(|from| Ok((from, parse(to)?))))
+}
+
+#[cfg(all(test, not(feature = "uuid-ids")))]
+mod tests {
+    use super::*;
+    use crate::algorithms;
+    use crate::Graph;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn write_edge_list(dir: &TempDir, graph: &Graph) -> PathBuf {
+        let path = dir.path().join("edges.csv");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "from,to,weight").unwrap();
+        for edge in graph.edges() {
+            writeln!(file, "{},{},{}", edge.from, edge.to, edge.weight).unwrap();
+        }
+        path
+    }
+
+    #[test]
+    fn test_streaming_bfs_matches_bfs() {
+        let temp_dir = TempDir::new().unwrap();
+        for directed in [false, true] {
+            let mut graph = if directed { Graph::new_directed() } else { Graph::new() };
+            for i in 0..400 {
+                graph.add_node_simple(format!("Node{}", i));
+            }
+            let mut rng = StdRng::seed_from_u64(3);
+            while graph.edge_count() < 1000 {
+                let (from, to) = (rng.gen_range(0..400), rng.gen_range(0..400));
+                if from != to {
+                    graph.add_edge(from, to, 1.0).unwrap();
+                }
+            }
+            let provider = FileEdgeProvider::open(write_edge_list(&temp_dir, &graph), directed)
+                .unwrap();
+
+            let mut compared = 0;
+            for goal in (0..400).step_by(31) {
+                let expected = algorithms::bfs(&graph, 0, goal).ok();
+                compared += usize::from(expected.is_some());
+                assert_eq!(streaming_bfs(&provider, 0, goal), expected, "goal {}", goal);
+            }
+            assert!(compared > 5);
+        }
+    }
+
+    #[test]
+    fn test_file_edge_provider_format() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("edges.csv");
+        std::fs::write(&path, "# comment\n0,1\n\n1, 2, 0.5\n3,1\n").unwrap();
+
+        let provider = FileEdgeProvider::open(&path, false).unwrap();
+        assert_eq!(provider.node_count(), 4);
+        assert_eq!(provider.neighbors(1).collect::<Vec<_>>(), vec![0, 2, 3]);
+        assert_eq!(streaming_bfs(&provider, 0, 3), Some(vec![0, 1, 3]));
+        assert_eq!(streaming_bfs(&provider, 0, 4), None);
+
+        let directed = FileEdgeProvider::open(&path, true).unwrap();
+        assert_eq!(directed.neighbors(1).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(streaming_bfs(&directed, 0, 3), None);
+
+        std::fs::write(&path, "0,1\n1,x\n").unwrap();
+        assert!(matches!(
+            FileEdgeProvider::open(&path, true),
+            Err(GraphError::SerializationError(_))
+        ));
+        assert!(FileEdgeProvider::open(temp_dir.path().join("missing.csv"), true).is_err());
+    }
+}
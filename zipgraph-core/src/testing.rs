@@ -0,0 +1,405 @@
+//! Graph generators and invariant checks for property-based tests
+//!
+//! `graphs` is a `proptest` strategy producing random graphs as configured
+//! by `GraphConfig`, and `random_graph` builds the same kind of graph from
+//! a seed for tests that are not property-based. The `check_*` functions
+//! verify invariants the algorithms must uphold on any input, returning a
+//! description of the first violation so that a failing property prints
+//! something more useful than `false`. They are public so that downstream
+//! crates can run the same properties against their own graphs.
+
+use crate::algorithms;
+use crate::error::Result;
+use crate::graph::Graph;
+use crate::progress::ProgressSink;
+use crate::types::{node_id, NodeId, Weight};
+use proptest::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Outcome of an invariant check: `Err` describes the violation
+pub type Check = std::result::Result<(), String>;
+
+/// Shape of the graphs produced by `graphs` and `random_graph`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GraphConfig {
+    /// Nodes are numbered `0..n` with `1 <= n <= max_nodes`
+    pub max_nodes: usize,
+    pub max_edges: usize,
+    /// `None` generates both directed and undirected graphs
+    pub directed: Option<bool>,
+    /// Draw weights from `[0.1, 10)`; otherwise every weight is 1
+    pub weighted: bool,
+    pub allow_self_loops: bool,
+}
+
+impl Default for GraphConfig {
+    fn default() -> Self {
+        Self {
+            max_nodes: 20,
+            max_edges: 40,
+            directed: None,
+            weighted: true,
+            allow_self_loops: false,
+        }
+    }
+}
+
+impl GraphConfig {
+    /// Generate graphs of at most `max_nodes` nodes and `max_edges` edges
+    pub fn with_size(mut self, max_nodes: usize, max_edges: usize) -> Self {
+        self.max_nodes = max_nodes.max(1);
+        self.max_edges = max_edges;
+        self
+    }
+
+    /// Only generate directed (`true`) or undirected (`false`) graphs
+    pub fn with_directed(mut self, directed: bool) -> Self {
+        self.directed = Some(directed);
+        self
+    }
+
+    /// Give every edge weight 1
+    pub fn unweighted(mut self) -> Self {
+        self.weighted = false;
+        self
+    }
+
+    /// Allow edges from a node to itself
+    pub fn with_self_loops(mut self) -> Self {
+        self.allow_self_loops = true;
+        self
+    }
+}
+
+/// Strategy generating graphs shaped by `config`
+///
+/// Shrinking removes edges and nodes, so failures are reported on small
+/// graphs.
+pub fn graphs(config: GraphConfig) -> impl Strategy<Value = Graph> {
+    let directed = match config.directed {
+        Some(directed) => Just(directed).boxed(),
+        None => any::<bool>().boxed(),
+    };
+    (1..=config.max_nodes, directed).prop_flat_map(move |(nodes, directed)| {
+        let weight = if config.weighted {
+            (0.1..10.0f64).boxed()
+        } else {
+            Just(1.0).boxed()
+        };
+        let edges = proptest::collection::vec((0..nodes, 0..nodes, weight), 0..=config.max_edges);
+        edges.prop_map(move |edges| {
+            build_graph(nodes, directed, config.allow_self_loops, edges)
+        })
+    })
+}
+
+/// A random graph shaped by `config`, reproducible from `seed`
+pub fn random_graph(config: GraphConfig, seed: u64) -> Graph {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let nodes = rng.gen_range(1..=config.max_nodes);
+    let directed = config.directed.unwrap_or_else(|| rng.gen());
+    let edge_count = rng.gen_range(0..=config.max_edges);
+    let edges = (0..edge_count)
+        .map(|_| {
+            let weight = if config.weighted { rng.gen_range(0.1..10.0) } else { 1.0 };
+            (rng.gen_range(0..nodes), rng.gen_range(0..nodes), weight)
+        })
+        .collect();
+    build_graph(nodes, directed, config.allow_self_loops, edges)
+}
+
+fn build_graph(
+    nodes: usize,
+    directed: bool,
+    allow_self_loops: bool,
+    edges: Vec<(usize, usize, Weight)>,
+) -> Graph {
+    let mut graph = if directed { Graph::new_directed() } else { Graph::new() };
+    for i in 0..nodes {
+        graph.add_node_simple(format!("Node{}", i));
+    }
+    for (from, to, weight) in edges {
+        if from != to || allow_self_loops {
+            graph.add_edge(node_id(from), node_id(to), weight).expect("endpoints exist");
+        }
+    }
+    graph
+}
+
+/// Total weight of `path`, taking the lightest of any parallel edges, or
+/// `None` if consecutive nodes are not joined by an edge
+pub fn path_cost(graph: &Graph, path: &[NodeId]) -> Option<Weight> {
+    path.windows(2)
+        .map(|pair| {
+            graph
+                .neighbors_with_weights(pair[0])
+                .ok()?
+                .into_iter()
+                .filter(|&(to, _)| to == pair[1])
+                .map(|(_, weight)| weight)
+                .reduce(Weight::min)
+        })
+        .sum()
+}
+
+/// Hop distances from `start` by plain BFS, independent of `algorithms`
+pub fn hop_distances(graph: &Graph, start: NodeId) -> HashMap<NodeId, usize> {
+    let mut distances = HashMap::from([(start, 0)]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(node) = queue.pop_front() {
+        let depth = distances[&node];
+        for neighbor in graph.neighbors(node).unwrap_or_default() {
+            distances.entry(neighbor).or_insert_with(|| {
+                queue.push_back(neighbor);
+                depth + 1
+            });
+        }
+    }
+    distances
+}
+
+/// Spearman rank correlation of two equally long samples
+///
+/// Tied values share their average rank. Used to compare approximate
+/// scores, such as sampled centralities, with exact ones.
+pub fn spearman_correlation(a: &[f64], b: &[f64]) -> f64 {
+    fn ranks(values: &[f64]) -> Vec<f64> {
+        let mut order: Vec<usize> = (0..values.len()).collect();
+        order.sort_by(|&x, &y| values[x].total_cmp(&values[y]));
+        let mut ranks = vec![0.0; values.len()];
+        let mut i = 0;
+        while i < order.len() {
+            // Ties share their average rank
+            let mut j = i;
+            while j + 1 < order.len() && values[order[j + 1]] == values[order[i]] {
+                j += 1;
+            }
+            for &index in &order[i..=j] {
+                ranks[index] = (i + j) as f64 / 2.0;
+            }
+            i = j + 1;
+        }
+        ranks
+    }
+
+    let (ra, rb) = (ranks(a), ranks(b));
+    let n = a.len() as f64;
+    let (mean_a, mean_b) = (ra.iter().sum::<f64>() / n, rb.iter().sum::<f64>() / n);
+    let cov: f64 = ra.iter().zip(&rb).map(|(x, y)| (x - mean_a) * (y - mean_b)).sum();
+    let var_a: f64 = ra.iter().map(|x| (x - mean_a).powi(2)).sum();
+    let var_b: f64 = rb.iter().map(|y| (y - mean_b).powi(2)).sum();
+    cov / (var_a * var_b).sqrt()
+}
+
+/// A `ProgressSink` that counts its updates, optionally asking to cancel
+/// once a number of them have arrived
+#[derive(Debug, Default)]
+pub struct CountingSink {
+    updates: AtomicU64,
+    last_completed: AtomicU64,
+    cancel_after: Option<u64>,
+}
+
+impl CountingSink {
+    /// A sink that never cancels
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A sink that cancels after `updates` updates
+    pub fn cancelling_after(updates: u64) -> Self {
+        Self { cancel_after: Some(updates), ..Self::default() }
+    }
+
+    /// Number of `on_progress` calls so far
+    pub fn updates(&self) -> u64 {
+        self.updates.load(Ordering::SeqCst)
+    }
+
+    /// `completed` in the latest update
+    pub fn last_completed(&self) -> u64 {
+        self.last_completed.load(Ordering::SeqCst)
+    }
+}
+
+impl ProgressSink for CountingSink {
+    fn on_progress(&self, completed: u64, _total: u64) {
+        self.updates.fetch_add(1, Ordering::SeqCst);
+        self.last_completed.store(completed, Ordering::SeqCst);
+    }
+
+    fn should_cancel(&self) -> bool {
+        self.cancel_after.is_some_and(|limit| self.updates() >= limit)
+    }
+}
+
+/// `path` (as returned by `bfs`) is a valid path from `start` to `goal`
+/// with the fewest hops, or an error exactly when `goal` is unreachable
+pub fn check_bfs_path(
+    graph: &Graph,
+    start: NodeId,
+    goal: NodeId,
+    path: &Result<Vec<NodeId>>,
+) -> Check {
+    let expected = hop_distances(graph, start).get(&goal).copied();
+    match (path, expected) {
+        (Ok(path), Some(hops)) => {
+            check_endpoints(path, start, goal)?;
+            if path_cost(graph, path).is_none() {
+                return Err(format!("{:?} is not a path", path));
+            }
+            if path.len() != hops + 1 {
+                return Err(format!("{:?} has {} hops, expected {}", path, path.len() - 1, hops));
+            }
+            Ok(())
+        }
+        (Err(_), None) => Ok(()),
+        (Ok(path), None) => Err(format!("found {:?} to unreachable {}", path, goal)),
+        (Err(e), Some(hops)) => {
+            Err(format!("no path found but {} is {} hops away: {}", goal, hops, e))
+        }
+    }
+}
+
+/// `result` (as returned by `dijkstra`) is a path whose `path_cost` is the
+/// reported cost and equals the shortest distance from Floyd-Warshall
+pub fn check_dijkstra_path(
+    graph: &Graph,
+    start: NodeId,
+    goal: NodeId,
+    result: &Result<(Vec<NodeId>, Weight)>,
+) -> Check {
+    let node_ids = {
+        let mut ids = graph.node_ids();
+        ids.sort_unstable();
+        ids
+    };
+    let position = |id: NodeId| node_ids.binary_search(&id).ok();
+    let (Some(i), Some(j)) = (position(start), position(goal)) else {
+        return match result {
+            Err(_) => Ok(()),
+            Ok(_) => Err(format!("found a path between unknown nodes {} and {}", start, goal)),
+        };
+    };
+    let distances = algorithms::floyd_warshall(graph).map_err(|e| e.to_string())?;
+    let shortest = distances[i][j];
+
+    match result {
+        Ok((path, cost)) => {
+            check_endpoints(path, start, goal)?;
+            let actual = path_cost(graph, path).ok_or_else(|| format!("{:?} is not a path", path))?;
+            if (actual - cost).abs() > 1e-9 * cost.abs().max(1.0) {
+                return Err(format!("{:?} costs {} but {} was reported", path, actual, cost));
+            }
+            if (cost - shortest).abs() > 1e-9 * shortest.abs().max(1.0) {
+                return Err(format!("cost {} but the shortest distance is {}", cost, shortest));
+            }
+            Ok(())
+        }
+        Err(_) if shortest.is_infinite() => Ok(()),
+        Err(e) => Err(format!("no path found but the distance is {}: {}", shortest, e)),
+    }
+}
+
+/// `ultra::ultra_bfs` finds paths exactly where `bfs` does, of the same
+/// length
+pub fn check_ultra_bfs(graph: &Graph, start: NodeId, goal: NodeId) -> Check {
+    let expected = algorithms::bfs(graph, start, goal);
+    let actual = crate::ultra::ultra_bfs(graph, start, goal);
+    match (&expected, &actual) {
+        (Ok(expected), Ok(actual)) if expected.len() == actual.len() => {
+            check_bfs_path(graph, start, goal, &Ok(actual.clone()))
+        }
+        (Err(_), Err(_)) => Ok(()),
+        _ => Err(format!("ultra_bfs gave {:?} but bfs gave {:?}", actual, expected)),
+    }
+}
+
+/// Ranks are non-negative and sum to one within `tolerance`
+///
+/// `centrality::pagerank` does not redistribute the rank of nodes without
+/// out-edges, so this only holds for graphs where every node has one.
+pub fn check_pagerank_sums_to_one(ranks: &HashMap<NodeId, f64>, tolerance: f64) -> Check {
+    if let Some((node, rank)) = ranks.iter().find(|(_, rank)| **rank < 0.0) {
+        return Err(format!("node {} has negative rank {}", node, rank));
+    }
+    let total: f64 = ranks.values().sum();
+    if (total - 1.0).abs() > tolerance {
+        return Err(format!("ranks sum to {}", total));
+    }
+    Ok(())
+}
+
+/// `components` partition the nodes of `graph` into non-empty sets with
+/// no edge between different sets
+pub fn check_components_partition(graph: &Graph, components: &[Vec<NodeId>]) -> Check {
+    let mut component_of = HashMap::new();
+    for (i, component) in components.iter().enumerate() {
+        if component.is_empty() {
+            return Err(format!("component {} is empty", i));
+        }
+        for &node in component {
+            if component_of.insert(node, i).is_some() {
+                return Err(format!("node {} is in more than one component", node));
+            }
+        }
+    }
+
+    let nodes: HashSet<NodeId> = graph.node_set();
+    if let Some(node) = nodes.iter().find(|node| !component_of.contains_key(node)) {
+        return Err(format!("node {} is in no component", node));
+    }
+    if let Some(node) = component_of.keys().find(|node| !nodes.contains(node)) {
+        return Err(format!("component holds unknown node {}", node));
+    }
+    for edge in graph.edges() {
+        if component_of[&edge.from] != component_of[&edge.to] {
+            return Err(format!("edge {} -> {} joins two components", edge.from, edge.to));
+        }
+    }
+    Ok(())
+}
+
+/// `loaded` has the nodes, labels, directedness, edge multiset and
+/// content hash of `original`
+pub fn check_roundtrip(original: &Graph, loaded: &Graph) -> Check {
+    if original.is_directed() != loaded.is_directed() {
+        return Err("directedness changed".to_string());
+    }
+
+    let labels = |graph: &Graph| -> HashMap<NodeId, String> {
+        graph
+            .node_ids()
+            .into_iter()
+            .map(|id| (id, graph.node(id).map(|node| node.label.clone()).unwrap_or_default()))
+            .collect()
+    };
+    if labels(original) != labels(loaded) {
+        return Err("nodes or labels changed".to_string());
+    }
+
+    let edges = |graph: &Graph| -> HashMap<(NodeId, NodeId, u64), usize> {
+        let mut counts = HashMap::new();
+        for edge in graph.edges() {
+            *counts.entry((edge.from, edge.to, edge.weight.to_bits())).or_insert(0) += 1;
+        }
+        counts
+    };
+    if edges(original) != edges(loaded) {
+        return Err("edge multiset changed".to_string());
+    }
+    if original.content_hash() != loaded.content_hash() {
+        return Err("content hash changed".to_string());
+    }
+    Ok(())
+}
+
+fn check_endpoints(path: &[NodeId], start: NodeId, goal: NodeId) -> Check {
+    if path.first() != Some(&start) || path.last() != Some(&goal) {
+        return Err(format!("{:?} does not lead from {} to {}", path, start, goal));
+    }
+    Ok(())
+}
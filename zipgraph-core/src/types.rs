@@ -1,7 +1,107 @@
 //! Type aliases and common types used throughout ZipGraph Core
 
 /// Node identifier type
+///
+/// A `usize` by default. With the `uuid-ids` feature it is a `uuid::Uuid`,
+/// so that processes can assign IDs without coordinating, see
+/// `generate_id`. Code meant for both builds converts between IDs and
+/// numbers with `node_id` and `node_index` rather than casting.
+#[cfg(not(feature = "uuid-ids"))]
 pub type NodeId = usize;
+#[cfg(feature = "uuid-ids")]
+pub type NodeId = uuid::Uuid;
+
+/// Largest node ID, reserved so that `next_node_id` cannot overflow
+#[cfg(not(feature = "uuid-ids"))]
+pub(crate) const MAX_NODE_ID: NodeId = NodeId::MAX;
+#[cfg(feature = "uuid-ids")]
+pub(crate) const MAX_NODE_ID: NodeId = uuid::Uuid::from_u128(u128::MAX);
+
+/// The node ID numbered `index`
+///
+/// `index` itself, or under `uuid-ids` the UUID whose 128 bits are
+/// `index`. `Graph::add_node_simple`, the generators and the formats that
+/// number their nodes, such as Pajek, assign IDs this way, so sequential
+/// IDs sort in the same order in both builds.
+#[cfg(not(feature = "uuid-ids"))]
+pub fn node_id(index: usize) -> NodeId {
+    index
+}
+
+#[cfg(feature = "uuid-ids")]
+pub fn node_id(index: usize) -> NodeId {
+    uuid::Uuid::from_u128(index as u128)
+}
+
+/// The number of `id` if it is a `node_id`, always under the default
+/// `usize` IDs
+#[cfg(not(feature = "uuid-ids"))]
+pub fn node_index(id: NodeId) -> Option<usize> {
+    Some(id)
+}
+
+#[cfg(feature = "uuid-ids")]
+pub fn node_index(id: NodeId) -> Option<usize> {
+    usize::try_from(id.as_u128()).ok()
+}
+
+/// The ID after `id` in the order `Graph::add_node_simple` counts in
+#[cfg(not(feature = "uuid-ids"))]
+pub(crate) fn next_id(id: NodeId) -> NodeId {
+    id + 1
+}
+
+#[cfg(feature = "uuid-ids")]
+pub(crate) fn next_id(id: NodeId) -> NodeId {
+    uuid::Uuid::from_u128(id.as_u128() + 1)
+}
+
+/// `id` as a `u64` for hashing; UUIDs fold their halves together
+#[cfg(not(feature = "uuid-ids"))]
+pub fn id_bits(id: NodeId) -> u64 {
+    id as u64
+}
+
+#[cfg(feature = "uuid-ids")]
+pub fn id_bits(id: NodeId) -> u64 {
+    let (high, low) = id.as_u64_pair();
+    high ^ low
+}
+
+/// A random node ID for use with `Graph::add_node_with_id`
+///
+/// Processes and threads can assign IDs with this without coordinating:
+/// the IDs are drawn from the thread's OS-seeded generator, so on 64-bit
+/// targets a collision among a billion IDs has a probability of about
+/// 3e-11 (far higher on 32-bit targets). The top bit is always set, so
+/// generated IDs do not collide with the small sequential IDs of
+/// `Graph::add_node_simple`. Note that `add_node_simple` continues after
+/// the largest ID in the graph, so once a generated ID is added it hands
+/// out the IDs following that one.
+#[cfg(not(feature = "uuid-ids"))]
+pub fn generate_id() -> NodeId {
+    const HIGH_BIT: NodeId = 1 << (NodeId::BITS - 1);
+    loop {
+        let id = rand::random::<NodeId>() | HIGH_BIT;
+        // Reserved so that `id + 1` cannot overflow
+        if id != MAX_NODE_ID {
+            return id;
+        }
+    }
+}
+
+/// A random (version 4) UUID for use with `Graph::add_node_with_id`
+///
+/// Processes and threads can assign IDs with this without coordinating;
+/// with 122 random bits, collisions are not a practical concern. The
+/// version bits keep generated IDs apart from the sequential `node_id`s.
+/// Note that `add_node_simple` continues after the largest ID in the
+/// graph, so once a generated ID is added it hands out the IDs following
+/// that one.
+#[cfg(feature = "uuid-ids")]
+pub fn generate_id() -> NodeId {
+    uuid::Uuid::new_v4()
+}
 
 /// Edge weight type
 pub type Weight = f64;
@@ -3,29 +3,69 @@
 //! These implementations use unsafe code, SIMD, and other optimizations
 //! to achieve 300-500x speedup over Python implementations.
 
-use crate::error::Result;
+use crate::algorithms::{instrumented, SearchMetrics, SearchProbe, State};
+use crate::error::{GraphError, Result};
 use crate::graph::Graph;
-use crate::types::NodeId;
+use crate::types::{NodeId, Weight};
 use rayon::prelude::*;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 
 /// Ultra-fast BFS using lock-free queues and SIMD operations
+///
+/// Like `algorithms::bfs`, fails with `NodeNotFound` for unknown endpoints
+/// and with `AlgorithmError` when `target` is unreachable.
 pub fn ultra_bfs(graph: &Graph, start: NodeId, target: NodeId) -> Result<Vec<NodeId>> {
-    let visited: Vec<AtomicBool> = (0..graph.node_count())
+    ultra_bfs_probed(graph, start, target, &mut ())
+}
+
+/// `ultra_bfs`, adding the work it does to `metrics`
+///
+/// The frontier is one BFS level. Levels are expanded in parallel and
+/// stop early once the target is seen, so the counts can differ slightly
+/// from run to run.
+pub fn ultra_bfs_instrumented(
+    graph: &Graph,
+    start: NodeId,
+    target: NodeId,
+    metrics: &mut SearchMetrics,
+) -> Result<Vec<NodeId>> {
+    instrumented(metrics, |probe| ultra_bfs_probed(graph, start, target, probe))
+}
+
+fn ultra_bfs_probed<P: SearchProbe>(
+    graph: &Graph,
+    start: NodeId,
+    target: NodeId,
+    probe: &mut P,
+) -> Result<Vec<NodeId>> {
+    graph.node(start)?;
+    graph.node(target)?;
+    let no_path = || GraphError::AlgorithmError(format!("No path from {} to {}", start, target));
+
+    // Flags and parents are kept by position in `ids`, so node IDs need
+    // not be contiguous
+    let ids = graph.node_ids();
+    let index: HashMap<NodeId, usize> = ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+    let visited: Vec<AtomicBool> = (0..ids.len())
         .map(|_| AtomicBool::new(false))
         .collect();
-    let parent: Vec<AtomicUsize> = (0..graph.node_count())
+    let parent: Vec<AtomicUsize> = (0..ids.len())
         .map(|_| AtomicUsize::new(usize::MAX))
         .collect();
 
     let mut current_level = vec![start];
-    visited[start].store(true, Ordering::Relaxed);
+    visited[index[&start]].store(true, Ordering::Relaxed);
+    // Only touched when `P::ENABLED`
+    let expanded = AtomicUsize::new(0);
+    let relaxed = AtomicUsize::new(0);
 
     while !current_level.is_empty() {
+        probe.frontier(current_level.len());
         // Check if we found target in current level
         if current_level.contains(&target) {
+            probe.expanded(1);
             break;
         }
 
@@ -40,10 +80,17 @@ pub fn ultra_bfs(graph: &Graph, start: NodeId, target: NodeId) -> Result<Vec<Nod
 
             if let Ok(neighbors) = graph.neighbors(node) {
                 let mut local_next = Vec::new();
+                if P::ENABLED {
+                    expanded.fetch_add(1, Ordering::Relaxed);
+                }
 
                 for neighbor in neighbors {
-                    if !visited[neighbor].swap(true, Ordering::Relaxed) {
-                        parent[neighbor].store(node, Ordering::Relaxed);
+                    if P::ENABLED {
+                        relaxed.fetch_add(1, Ordering::Relaxed);
+                    }
+                    let slot = index[&neighbor];
+                    if !visited[slot].swap(true, Ordering::Relaxed) {
+                        parent[slot].store(index[&node], Ordering::Relaxed);
                         local_next.push(neighbor);
 
                         if neighbor == target {
@@ -61,10 +108,12 @@ pub fn ultra_bfs(graph: &Graph, start: NodeId, target: NodeId) -> Result<Vec<Nod
 
         current_level = next_sync.lock().clone();
     }
+    probe.expanded(expanded.into_inner());
+    probe.relaxed(relaxed.into_inner());
 
     // Reconstruct path
-    if parent[target].load(Ordering::Relaxed) == usize::MAX && target != start {
-        return Ok(Vec::new());
+    if parent[index[&target]].load(Ordering::Relaxed) == usize::MAX && target != start {
+        return Err(no_path());
     }
 
     let mut path = Vec::new();
@@ -72,12 +121,12 @@ pub fn ultra_bfs(graph: &Graph, start: NodeId, target: NodeId) -> Result<Vec<Nod
     path.push(current);
 
     while current != start {
-        let p = parent[current].load(Ordering::Relaxed);
+        let p = parent[index[&current]].load(Ordering::Relaxed);
         if p == usize::MAX {
-            return Ok(Vec::new());
+            return Err(no_path());
         }
-        path.push(p);
-        current = p;
+        path.push(ids[p]);
+        current = ids[p];
     }
 
     path.reverse();
@@ -85,6 +134,9 @@ pub fn ultra_bfs(graph: &Graph, start: NodeId, target: NodeId) -> Result<Vec<Nod
 }
 
 /// Batch BFS - process multiple source-target pairs efficiently
+///
+/// Paths are hop-optimal: edge weights are ignored. Use
+/// `batch_shortest_paths` for weight-optimal paths.
 pub fn batch_bfs(
     graph: &Graph,
     queries: &[(NodeId, NodeId)],
@@ -96,6 +148,9 @@ pub fn batch_bfs(
 }
 
 /// Ultra-fast PageRank with vectorized operations
+///
+/// Like `centrality::pagerank`, the rank of nodes without out-edges is
+/// spread over all nodes, so ranks sum to one.
 pub fn ultra_pagerank(
     graph: &Graph,
     damping: f64,
@@ -122,6 +177,14 @@ pub fn ultra_pagerank(
     let base_rank = (1.0 - damping) / node_count as f64;
 
     for _ in 0..max_iter {
+        let dangling: f64 = out_degrees
+            .iter()
+            .zip(&ranks)
+            .filter(|(&degree, _)| degree == 0)
+            .map(|(_, rank)| rank)
+            .sum::<f64>()
+            / node_count as f64;
+
         // Parallel rank computation
         new_ranks.par_iter_mut().enumerate().for_each(|(i, rank)| {
             let node_id = node_ids[i];
@@ -136,7 +199,7 @@ pub fn ultra_pagerank(
                 }
             }
 
-            *rank = base_rank + damping * sum;
+            *rank = base_rank + damping * (dangling + sum);
         });
 
         // Check convergence
@@ -161,6 +224,9 @@ pub fn ultra_pagerank(
 }
 
 /// Batch shortest path queries using shared data structures
+///
+/// Runs Dijkstra once from `source` over the actual edge weights, so the
+/// returned paths are weight-optimal rather than hop-optimal.
 pub fn batch_shortest_paths(
     graph: &Graph,
     source: NodeId,
@@ -173,25 +239,30 @@ pub fn batch_shortest_paths(
     let mut queue = std::collections::BinaryHeap::new();
 
     distances.insert(source, 0.0);
-    queue.push((std::cmp::Reverse(0.0 as i64), source));
+    queue.push(State {
+        cost: 0.0,
+        node: source,
+    });
 
-    while let Some((std::cmp::Reverse(_), current)) = queue.pop() {
+    while let Some(State { node: current, .. }) = queue.pop() {
         if visited.contains(&current) {
             continue;
         }
         visited.insert(current);
 
-        if let Ok(neighbors) = graph.neighbors(current) {
+        if let Ok(neighbors) = graph.neighbors_with_weights(current) {
             let current_dist = *distances.get(&current).unwrap_or(&f64::MAX);
 
-            for neighbor in neighbors {
-                let edge_weight = 1.0; // Can be customized
+            for (neighbor, edge_weight) in neighbors {
                 let new_dist = current_dist + edge_weight;
 
                 if new_dist < *distances.get(&neighbor).unwrap_or(&f64::MAX) {
                     distances.insert(neighbor, new_dist);
                     parents.insert(neighbor, current);
-                    queue.push((std::cmp::Reverse((new_dist * 1000.0) as i64), neighbor));
+                    queue.push(State {
+                        cost: new_dist,
+                        node: neighbor,
+                    });
                 }
             }
         }
@@ -228,7 +299,7 @@ pub fn batch_shortest_paths(
 pub struct ZeroCopyBfsIterator<'a> {
     graph: &'a Graph,
     queue: VecDeque<NodeId>,
-    visited: Vec<bool>,
+    visited: HashSet<NodeId>,
 }
 
 /// Create a zero-copy BFS iterator
@@ -238,10 +309,13 @@ pub fn zero_copy_bfs(graph: &Graph, start: NodeId) -> ZeroCopyBfsIterator<'_> {
 
 impl<'a> ZeroCopyBfsIterator<'a> {
     pub fn new(graph: &'a Graph, start: NodeId) -> Self {
-        let mut visited = vec![false; graph.node_count()];
-        visited[start] = true;
+        // A set, since node IDs need not be contiguous
+        let mut visited = HashSet::new();
         let mut queue = VecDeque::new();
-        queue.push_back(start);
+        if graph.node(start).is_ok() {
+            visited.insert(start);
+            queue.push_back(start);
+        }
 
         Self {
             graph,
@@ -258,8 +332,7 @@ impl<'a> Iterator for ZeroCopyBfsIterator<'a> {
         if let Some(current) = self.queue.pop_front() {
             if let Ok(neighbors) = self.graph.neighbors(current) {
                 for neighbor in neighbors {
-                    if !self.visited[neighbor] {
-                        self.visited[neighbor] = true;
+                    if self.visited.insert(neighbor) {
                         self.queue.push_back(neighbor);
                     }
                 }
@@ -271,7 +344,130 @@ impl<'a> Iterator for ZeroCopyBfsIterator<'a> {
     }
 }
 
-#[cfg(test)]
+/// BFS that yields one whole depth level at a time
+///
+/// The `n`-th item is the set of nodes at exactly `n` hops from the
+/// start, the first being `[start]`, so the nodes of one level can be
+/// processed in parallel before the next is expanded. Within a level,
+/// nodes are in the order BFS discovers them. Node IDs need not be
+/// contiguous; an unknown start yields nothing.
+pub struct BfsLevelIterator<'a> {
+    graph: &'a Graph,
+    frontier: Vec<NodeId>,
+    visited: HashSet<NodeId>,
+    depth: usize,
+}
+
+/// Create an iterator over the BFS levels around `start`
+pub fn bfs_levels(graph: &Graph, start: NodeId) -> BfsLevelIterator<'_> {
+    BfsLevelIterator::new(graph, start)
+}
+
+impl<'a> BfsLevelIterator<'a> {
+    pub fn new(graph: &'a Graph, start: NodeId) -> Self {
+        let frontier = if graph.node(start).is_ok() { vec![start] } else { Vec::new() };
+        Self {
+            graph,
+            visited: frontier.iter().copied().collect(),
+            frontier,
+            depth: 0,
+        }
+    }
+
+    /// Depth of the level the next call to `next_layer` returns
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The next level, or `None` once every reachable node was returned
+    pub fn next_layer(&mut self) -> Option<Vec<NodeId>> {
+        if self.frontier.is_empty() {
+            return None;
+        }
+
+        let mut next = Vec::new();
+        for &node in &self.frontier {
+            for (neighbor, _) in self.graph.weighted_neighbors_iter(node) {
+                if self.visited.insert(neighbor) {
+                    next.push(neighbor);
+                }
+            }
+        }
+        self.depth += 1;
+        Some(std::mem::replace(&mut self.frontier, next))
+    }
+}
+
+impl<'a> Iterator for BfsLevelIterator<'a> {
+    type Item = Vec<NodeId>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_layer()
+    }
+}
+
+/// Lazy Dijkstra: nodes in order of increasing distance from a source
+///
+/// Each call to `next` settles one node, so taking the first `k` items
+/// explores only as far as the `k`-th nearest node. The distance table and
+/// the heap are kept between calls and no other allocation happens per
+/// item. Node IDs need not be contiguous. Edge weights must be
+/// non-negative; an unknown start yields nothing.
+pub struct DijkstraIterator<'a> {
+    graph: &'a Graph,
+    heap: BinaryHeap<State>,
+    /// Best known distance of every reached node
+    dist: HashMap<NodeId, Weight>,
+    settled: HashSet<NodeId>,
+}
+
+/// Create a Dijkstra iterator yielding `(node, distance)` from `start`
+pub fn dijkstra_iter(graph: &Graph, start: NodeId) -> DijkstraIterator<'_> {
+    DijkstraIterator::new(graph, start)
+}
+
+impl<'a> DijkstraIterator<'a> {
+    pub fn new(graph: &'a Graph, start: NodeId) -> Self {
+        let mut heap = BinaryHeap::new();
+        let mut dist = HashMap::new();
+        if graph.node(start).is_ok() {
+            heap.push(State { cost: 0.0, node: start });
+            dist.insert(start, 0.0);
+        }
+
+        Self {
+            graph,
+            heap,
+            dist,
+            settled: HashSet::new(),
+        }
+    }
+}
+
+impl<'a> Iterator for DijkstraIterator<'a> {
+    type Item = (NodeId, Weight);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(State { cost, node }) = self.heap.pop() {
+            if !self.settled.insert(node) {
+                continue;
+            }
+
+            for (neighbor, weight) in self.graph.weighted_neighbors_iter(node) {
+                let next_cost = cost + weight;
+                let best = self.dist.entry(neighbor).or_insert(Weight::INFINITY);
+                if next_cost < *best && !self.settled.contains(&neighbor) {
+                    *best = next_cost;
+                    self.heap.push(State { cost: next_cost, node: neighbor });
+                }
+            }
+            return Some((node, cost));
+        }
+        None
+    }
+}
+
+#[cfg(all(test, not(feature = "uuid-ids")))]
 mod tests {
     use super::*;
 
@@ -296,6 +492,14 @@ mod tests {
         assert!(!path.is_empty());
         assert_eq!(path[0], 0);
         assert_eq!(path[path.len() - 1], 4);
+
+        // Edges only lead one way
+        let mut directed = Graph::new_directed();
+        directed.add_node_simple("A");
+        directed.add_node_simple("B");
+        directed.add_edge(0, 1, 1.0).unwrap();
+        assert!(ultra_bfs(&directed, 1, 0).is_err());
+        assert!(ultra_bfs(&graph, 0, 99).is_err());
     }
 
     #[test]
@@ -322,6 +526,27 @@ mod tests {
         assert!(!paths.is_empty());
     }
 
+    #[test]
+    fn test_batch_shortest_paths_uses_weights() {
+        // 0 -> 3 directly is one hop but costs 10; 0 -> 1 -> 2 -> 3 costs 3
+        let mut graph = Graph::new();
+        for i in 0..4 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        graph.add_edge(0, 3, 10.0).unwrap();
+        graph.add_edge(0, 1, 1.0).unwrap();
+        graph.add_edge(1, 2, 1.0).unwrap();
+        graph.add_edge(2, 3, 1.0).unwrap();
+
+        let paths = batch_shortest_paths(&graph, 0, &[2, 3]);
+        assert_eq!(paths[&3], vec![0, 1, 2, 3]);
+        assert_eq!(paths[&2], vec![0, 1, 2]);
+
+        // batch_bfs still finds the hop-optimal path
+        let hop_paths = batch_bfs(&graph, &[(0, 3)]);
+        assert_eq!(hop_paths[0], Some(vec![0, 3]));
+    }
+
     #[test]
     fn test_zero_copy_iterator() {
         let graph = create_test_graph();
@@ -330,4 +555,125 @@ mod tests {
         assert!(!nodes.is_empty());
         assert_eq!(nodes[0], 0);
     }
+
+    #[test]
+    fn test_zero_copy_iterator_sparse_ids() {
+        let mut graph = Graph::new();
+        let ids: Vec<NodeId> = (0..3).map(|_| crate::types::generate_id()).collect();
+        for &id in &ids {
+            graph.add_node_with_id(id, crate::graph::Node::new(id, "N")).unwrap();
+        }
+        graph.add_edge(ids[0], ids[1], 1.0).unwrap();
+
+        assert_eq!(zero_copy_bfs(&graph, ids[0]).collect::<Vec<_>>(), vec![ids[0], ids[1]]);
+        assert_eq!(zero_copy_bfs(&graph, 42).count(), 0);
+    }
+
+    #[test]
+    fn test_batch_shortest_paths_small_weights() {
+        let mut graph = Graph::new_directed();
+        for i in 0..4 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        graph.add_edge(0, 1, 0.0001).unwrap();
+        graph.add_edge(0, 2, 0.0009).unwrap();
+        graph.add_edge(1, 2, 0.0001).unwrap();
+        graph.add_edge(0, 3, 1.0005).unwrap();
+        graph.add_edge(2, 3, 1.0).unwrap();
+
+        let paths = batch_shortest_paths(&graph, 0, &[3]);
+        let path = &paths[&3];
+        assert_eq!(path, &vec![0, 1, 2, 3]);
+
+        let cost: f64 = path
+            .windows(2)
+            .map(|pair| {
+                graph
+                    .neighbors_with_weights(pair[0])
+                    .unwrap()
+                    .into_iter()
+                    .find(|&(node, _)| node == pair[1])
+                    .unwrap()
+                    .1
+            })
+            .sum();
+        assert!((cost - 1.0002).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_dijkstra_iter_order() {
+        // Sparse IDs: multiples of 7
+        let mut graph = Graph::new();
+        for i in 0..30 {
+            graph.add_node(crate::graph::Node::new(7 * i, format!("Node{}", i)));
+        }
+        for i in 0..30 {
+            for j in [1, 3, 8] {
+                let weight = ((i * 13 + j * 5) % 11) as f64 + 0.5;
+                graph.add_edge(7 * i, 7 * ((i + j) % 30), weight).unwrap();
+            }
+        }
+
+        let visited: Vec<(NodeId, Weight)> = dijkstra_iter(&graph, 14).collect();
+        assert_eq!(visited.len(), 30);
+        assert_eq!(visited[0], (14, 0.0));
+
+        let mut expected: Vec<(NodeId, Weight)> = graph
+            .node_ids()
+            .into_iter()
+            .map(|id| (id, crate::algorithms::dijkstra(&graph, 14, id).unwrap().1))
+            .collect();
+        expected.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        for (a, b) in visited.iter().zip(&expected) {
+            assert!((a.1 - b.1).abs() < 1e-9);
+        }
+        for &(node, distance) in &visited {
+            assert!((expected.iter().find(|e| e.0 == node).unwrap().1 - distance).abs() < 1e-9);
+        }
+
+        let closest: Vec<NodeId> = dijkstra_iter(&graph, 14).take(5).map(|(id, _)| id).collect();
+        assert_eq!(closest.len(), 5);
+        assert_eq!(dijkstra_iter(&graph, 3).count(), 0);
+    }
+
+    #[test]
+    fn test_bfs_levels() {
+        let graph = create_test_graph();
+        let mut levels = bfs_levels(&graph, 1);
+        assert_eq!(levels.depth(), 0);
+        assert_eq!(levels.next_layer(), Some(vec![1]));
+        let mut first = levels.next_layer().unwrap();
+        first.sort_unstable();
+        assert_eq!(first, vec![0, 2, 5]);
+        assert_eq!(levels.depth(), 2);
+
+        let mut seen = HashSet::new();
+        for (depth, level) in bfs_levels(&graph, 0).enumerate() {
+            for node in level {
+                assert!(seen.insert(node), "{} visited twice", node);
+                assert_eq!(crate::testing::hop_distances(&graph, 0)[&node], depth);
+            }
+        }
+        assert_eq!(seen, graph.reachable_from(0));
+        assert_eq!(bfs_levels(&graph, 0).count(), 5);
+        assert_eq!(bfs_levels(&graph, 42).next(), None);
+    }
+    #[test]
+    fn test_ultra_bfs_metrics() {
+        let mut path = Graph::new();
+        for i in 0..8 {
+            path.add_node_simple(format!("Node{}", i));
+        }
+        for i in 1..8 {
+            path.add_edge(i - 1, i, 1.0).unwrap();
+        }
+
+        let mut metrics = SearchMetrics::default();
+        let found = ultra_bfs_instrumented(&path, 0, 7, &mut metrics).unwrap();
+        assert_eq!(found, (0..8).collect::<Vec<_>>());
+        // One node per level, so no parallel early exit
+        assert_eq!(metrics.nodes_expanded, 8);
+        assert_eq!(metrics.edges_relaxed, 13);
+        assert_eq!(metrics.max_frontier, 1);
+    }
 }
@@ -1,3 +1,6 @@
+// The tests number nodes with integer literals
+#![cfg(not(feature = "uuid-ids"))]
+
 use zipgraph_core::{Graph, GraphStats};
 
 #[test]
@@ -233,3 +236,40 @@ fn test_stats_star_graph() {
     assert!(stats.avg_degree > 1.0);
     assert!(stats.avg_degree < 3.0);
 }
+
+/// Written by the first release with `StorageFormat::Binary`, before the
+/// encoding had a version header
+const BASELINE_BINARY: &str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/baseline_graph.bin");
+
+#[test]
+fn test_load_baseline_binary() {
+    use zipgraph_core::{load_graph, save_graph, StorageFormat};
+
+    let graph = load_graph(BASELINE_BINARY, StorageFormat::Binary).unwrap();
+    assert!(graph.is_directed());
+    assert_eq!((graph.node_count(), graph.edge_count()), (4, 4));
+    assert_eq!(graph.node(0).unwrap().properties["rank"], 1.5);
+    assert_eq!(graph.node(2).unwrap().features, Some(vec![0.25, -1.0]));
+    assert_eq!(graph.node(5).unwrap().label, "far");
+    let edges: Vec<_> = graph.edges().iter().map(|e| (e.from, e.to, e.weight)).collect();
+    assert_eq!(edges, vec![(0, 1, 1.0), (1, 2, 2.5), (2, 0, 0.5), (0, 5, 4.0)]);
+
+    // Saved again, it gains the header and still loads the same
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = temp_dir.path().join("graph.bin");
+    save_graph(&graph, &path, StorageFormat::Binary).unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    assert_eq!(&bytes[..8], b"ZGRB\x01\0\0\0");
+    let reloaded = load_graph(&path, StorageFormat::Binary).unwrap();
+    assert_eq!(reloaded.content_hash(), graph.content_hash());
+
+    let mut future = bytes;
+    future[4] = 2;
+    std::fs::write(&path, future).unwrap();
+    let error = load_graph(&path, StorageFormat::Binary).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Serialization error: Unsupported binary graph version 2 (expected 1)"
+    );
+}
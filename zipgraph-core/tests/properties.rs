@@ -0,0 +1,201 @@
+//! Property-based tests of algorithm invariants on random graphs
+//!
+//! The generators and checks live in `zipgraph_core::testing`.
+
+// The tests number nodes with integer literals
+#![cfg(not(feature = "uuid-ids"))]
+
+use proptest::prelude::*;
+use zipgraph_core::testing::{self, GraphConfig};
+use zipgraph_core::{
+    algorithms, centrality, load_graph, save_graph, Edge, Graph, Node, StorageFormat,
+};
+
+fn config() -> ProptestConfig {
+    ProptestConfig::with_cases(64)
+}
+
+/// A graph with node pair drawn from its nodes
+fn graph_with_pair(config: GraphConfig) -> impl Strategy<Value = (Graph, usize, usize)> {
+    testing::graphs(config).prop_flat_map(|graph| {
+        let n = graph.node_count();
+        (Just(graph), 0..n, 0..n)
+    })
+}
+
+/// Adds a ring through all nodes so that every node has an out-edge
+fn with_ring(mut graph: Graph) -> Graph {
+    let n = graph.node_count();
+    for i in 0..n {
+        graph.add_edge(i, (i + 1) % n, 1.0).unwrap();
+    }
+    graph
+}
+
+proptest! {
+    #![proptest_config(config())]
+
+    #[test]
+    fn prop_bfs_paths_are_minimal(
+        (graph, start, goal) in graph_with_pair(GraphConfig::default().unweighted())
+    ) {
+        let path = algorithms::bfs(&graph, start, goal);
+        testing::check_bfs_path(&graph, start, goal, &path).map_err(TestCaseError::fail)?;
+    }
+
+    #[test]
+    fn prop_dijkstra_cost_matches_path(
+        (graph, start, goal) in graph_with_pair(GraphConfig::default())
+    ) {
+        let result = algorithms::dijkstra(&graph, start, goal);
+        testing::check_dijkstra_path(&graph, start, goal, &result)
+            .map_err(TestCaseError::fail)?;
+    }
+
+    #[test]
+    fn prop_ultra_bfs_matches_bfs(
+        (graph, start, goal) in graph_with_pair(GraphConfig::default().unweighted())
+    ) {
+        testing::check_ultra_bfs(&graph, start, goal).map_err(TestCaseError::fail)?;
+    }
+
+    #[test]
+    fn prop_pagerank_sums_to_one(
+        graph in testing::graphs(GraphConfig::default()).prop_map(with_ring)
+    ) {
+        let ranks = centrality::pagerank(&graph, 0.85, 200, 1e-12).unwrap();
+        testing::check_pagerank_sums_to_one(&ranks, 1e-6).map_err(TestCaseError::fail)?;
+    }
+
+    #[test]
+    fn prop_components_partition_nodes(graph in testing::graphs(GraphConfig::default())) {
+        let components = algorithms::connected_components(&graph).unwrap();
+        testing::check_components_partition(&graph, &components).map_err(TestCaseError::fail)?;
+    }
+
+    #[test]
+    fn prop_storage_roundtrip(graph in testing::graphs(GraphConfig::default().with_self_loops())) {
+        let dir = tempfile::tempdir().unwrap();
+        for format in [
+            StorageFormat::Binary,
+            StorageFormat::BinaryZstd { level: 1 },
+            StorageFormat::Json,
+            StorageFormat::GraphML,
+            StorageFormat::Dot,
+            StorageFormat::Pajek,
+        ] {
+            let path = dir.path().join("graph");
+            save_graph(&graph, &path, format).unwrap();
+            let loaded = load_graph(&path, format).unwrap();
+            testing::check_roundtrip(&graph, &loaded)
+                .map_err(|e| TestCaseError::fail(format!("{:?}: {}", format, e)))?;
+        }
+    }
+}
+
+/// `graph` rebuilt with nodes and edges inserted in the order their keys
+/// sort them
+fn reinserted(graph: &Graph, node_keys: &[u64], edge_keys: &[u64]) -> Graph {
+    let mut copy = if graph.is_directed() { Graph::new_directed() } else { Graph::new() };
+    let mut nodes: Vec<&Node> =
+        graph.node_ids().into_iter().map(|id| graph.node(id).unwrap()).collect();
+    nodes.sort_by_key(|node| node_keys[node.id % node_keys.len()]);
+    for node in nodes {
+        copy.add_node(node.clone());
+    }
+    let mut edges: Vec<(usize, &Edge)> = graph.edges().iter().enumerate().collect();
+    edges.sort_by_key(|(i, _)| edge_keys[i % edge_keys.len()]);
+    for (_, edge) in edges {
+        copy.add_edge(edge.from, edge.to, edge.weight).unwrap();
+    }
+    copy
+}
+
+proptest! {
+    #![proptest_config(config())]
+
+    #[test]
+    fn prop_hashes_ignore_insertion_order(
+        graph in testing::graphs(GraphConfig::default()),
+        node_keys in proptest::collection::vec(any::<u64>(), 1..20),
+        edge_keys in proptest::collection::vec(any::<u64>(), 1..40),
+    ) {
+        let copy = reinserted(&graph, &node_keys, &edge_keys);
+        prop_assert_eq!(copy.content_hash(), graph.content_hash());
+        prop_assert_eq!(copy.structural_hash(), graph.structural_hash());
+    }
+
+    #[test]
+    fn prop_hashes_detect_changes(
+        (graph, a, b) in graph_with_pair(GraphConfig::default()),
+        weight in 0.1..10.0f64,
+    ) {
+        let mut edge = graph.clone();
+        edge.add_edge(a, b, weight).unwrap();
+        prop_assert_ne!(edge.content_hash(), graph.content_hash());
+        prop_assert_ne!(edge.structural_hash(), graph.structural_hash());
+
+        let mut node = graph.clone();
+        node.add_node_simple("Extra");
+        prop_assert_ne!(node.content_hash(), graph.content_hash());
+        prop_assert_ne!(node.structural_hash(), graph.structural_hash());
+
+        // Relabeling and new properties change only the content hash
+        let mut relabeled = graph.clone();
+        let mut replacement = graph.node(a).unwrap().clone();
+        replacement.label.push('!');
+        relabeled.add_node(replacement.clone());
+        prop_assert_ne!(relabeled.content_hash(), graph.content_hash());
+        prop_assert_eq!(relabeled.structural_hash(), graph.structural_hash());
+        relabeled.add_node(replacement.with_property("score", weight));
+        prop_assert_ne!(relabeled.content_hash(), graph.content_hash());
+
+        // Reweighting an edge changes only the content hash
+        if let Some(first) = graph.edges().first() {
+            let mut weights: Vec<f64> = graph.edges().iter().map(|e| e.weight).collect();
+            weights[0] = first.weight + 1.0;
+            let mut reweighted =
+                if graph.is_directed() { Graph::new_directed() } else { Graph::new() };
+            for id in graph.node_ids() {
+                reweighted.add_node(graph.node(id).unwrap().clone());
+            }
+            for (edge, weight) in graph.edges().iter().zip(weights) {
+                reweighted.add_edge(edge.from, edge.to, weight).unwrap();
+            }
+            prop_assert_ne!(reweighted.content_hash(), graph.content_hash());
+            prop_assert_eq!(reweighted.structural_hash(), graph.structural_hash());
+        }
+    }
+}
+
+#[test]
+fn test_random_graph_is_reproducible() {
+    let config = GraphConfig::default().with_directed(true);
+    let a = testing::random_graph(config, 7);
+    let b = testing::random_graph(config, 7);
+    assert!(a.is_directed());
+    assert!(testing::check_roundtrip(&a, &b).is_ok());
+}
+
+#[test]
+fn test_path_cost() {
+    let mut graph = Graph::new_directed();
+    for i in 0..3 {
+        graph.add_node_simple(format!("Node{}", i));
+    }
+    graph.add_edge(0, 1, 2.0).unwrap();
+    graph.add_edge(0, 1, 0.5).unwrap();
+    graph.add_edge(1, 2, 1.0).unwrap();
+
+    assert_eq!(testing::path_cost(&graph, &[0, 1, 2]), Some(1.5));
+    assert_eq!(testing::path_cost(&graph, &[0]), Some(0.0));
+    assert_eq!(testing::path_cost(&graph, &[2, 1]), None);
+}
+
+#[test]
+fn test_checks_report_violations() {
+    let graph = with_ring(testing::random_graph(GraphConfig::default().with_size(5, 0), 1));
+    assert!(testing::check_bfs_path(&graph, 0, 0, &Ok(vec![0, 1, 0])).is_err());
+    assert!(testing::check_components_partition(&graph, &[graph.node_ids()]).is_ok());
+    assert!(testing::check_components_partition(&graph, &[vec![0]]).is_err());
+}
@@ -0,0 +1,109 @@
+//! `NodeId`s as UUIDs, assigned by independent builders and merged
+
+#![cfg(feature = "uuid-ids")]
+
+use std::thread;
+use tempfile::TempDir;
+use zipgraph_core::algorithms::{bfs, connected_components, dijkstra};
+use zipgraph_core::{
+    generate_id, load_graph, node_id, save_graph, Graph, Node, NodeId, StorageFormat,
+};
+
+/// A path of `len` nodes with generated IDs, built on its own thread
+fn build_path(len: usize) -> thread::JoinHandle<Graph> {
+    thread::spawn(move || {
+        let mut graph = Graph::new();
+        let ids: Vec<NodeId> = (0..len)
+            .map(|i| {
+                let id = generate_id();
+                graph.add_node_with_id(id, Node::new(id, format!("n{}", i))).unwrap()
+            })
+            .collect();
+        for pair in ids.windows(2) {
+            graph.add_edge(pair[0], pair[1], 1.0).unwrap();
+        }
+        graph
+    })
+}
+
+/// Merge `parts` into one graph without renumbering any node
+fn merge(parts: &[Graph]) -> Graph {
+    let mut merged = Graph::new();
+    for part in parts {
+        for id in part.node_ids() {
+            merged.add_node_with_id(id, part.node(id).unwrap().clone()).unwrap();
+        }
+        for edge in part.edges() {
+            merged.add_edge(edge.from, edge.to, edge.weight).unwrap();
+        }
+    }
+    merged
+}
+
+#[test]
+fn test_merge_independently_built_graphs() {
+    let parts: Vec<Graph> =
+        [build_path(50), build_path(30)].into_iter().map(|h| h.join().unwrap()).collect();
+    let mut merged = merge(&parts);
+    assert_eq!(merged.node_count(), 80);
+    assert_eq!(connected_components(&merged).unwrap().len(), 2);
+
+    let ends = |graph: &Graph| {
+        let mut ids = graph.node_ids();
+        ids.retain(|&id| graph.degree(id).unwrap() == 1);
+        (ids[0], ids[1])
+    };
+    let (a, b) = ends(&parts[0]);
+    let (c, _) = ends(&parts[1]);
+    assert_eq!(bfs(&merged, a, b).unwrap().len(), 50);
+    assert!(bfs(&merged, a, c).is_err());
+
+    merged.add_edge(b, c, 2.5).unwrap();
+    assert_eq!(connected_components(&merged).unwrap().len(), 1);
+    let (path, cost) = dijkstra(&merged, a, c).unwrap();
+    assert_eq!((path.len(), cost), (51, 51.5));
+}
+
+#[test]
+fn test_sequential_ids() {
+    let mut graph = Graph::new();
+    let a = graph.add_node_simple("a");
+    let b = graph.add_node_simple("b");
+    assert_eq!((a, b), (node_id(0), node_id(1)));
+    assert_eq!(b.to_string(), "00000000-0000-0000-0000-000000000001");
+}
+
+#[test]
+fn test_storage_roundtrip() {
+    let graph = build_path(5).join().unwrap();
+    let dir = TempDir::new().unwrap();
+    for format in [
+        StorageFormat::Binary,
+        StorageFormat::BinaryZstd { level: 0 },
+        StorageFormat::Json,
+        StorageFormat::GraphML,
+    ] {
+        let path = dir.path().join("graph");
+        save_graph(&graph, &path, format).unwrap();
+        let loaded = load_graph(&path, format).unwrap();
+        let mut expected = graph.node_ids();
+        let mut actual = loaded.node_ids();
+        expected.sort_unstable();
+        actual.sort_unstable();
+        assert_eq!(actual, expected, "{:?}", format);
+        assert_eq!(loaded.content_hash(), graph.content_hash(), "{:?}", format);
+    }
+
+    let path = dir.path().join("graph.json");
+    save_graph(&graph, &path, StorageFormat::Json).unwrap();
+    let json = std::fs::read_to_string(&path).unwrap();
+    let id = graph.node_ids()[0];
+    assert!(json.contains(&format!("\"{}\"", id)));
+}
+
+#[test]
+fn test_rejects_integer_id_binary() {
+    let fixture = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/baseline_graph.bin");
+    let error = load_graph(fixture, StorageFormat::Binary).unwrap_err();
+    assert!(error.to_string().contains("uuid-ids"), "{}", error);
+}
@@ -1,7 +1,7 @@
 //! ML-based algorithm selection
 
 use crate::error::{MlError, Result};
-use zipgraph_core::{Algorithm, Graph, GraphStats};
+use zipgraph_core::{Algorithm, Graph, NodeId, PathMetric};
 
 /// ML model for selecting the best algorithm for a graph
 pub struct AlgorithmSelector {
@@ -16,7 +16,7 @@ impl AlgorithmSelector {
 
     /// Select the best algorithm based on graph properties
     pub fn select(&self, graph: &Graph) -> Algorithm {
-        let stats = GraphStats::from_graph(graph);
+        let stats = graph.cached_stats();
         
         // Simple heuristic-based selection (replace with ML model in production)
         if stats.node_count < 100 {
@@ -34,16 +34,33 @@ impl AlgorithmSelector {
         }
     }
 
-    /// Select algorithm for shortest path query
-    pub fn select_shortest_path(&self, graph: &Graph, _start: usize, _goal: usize) -> Algorithm {
-        let stats = GraphStats::from_graph(graph);
-        
-        // Heuristic: use BFS for unweighted, Dijkstra for weighted
-        if stats.node_count < 1000 {
-            Algorithm::Dijkstra
-        } else {
-            Algorithm::BidirectionalSearch
-        }
+    /// Select algorithm for a minimum-weight shortest path query
+    pub fn select_shortest_path(&self, graph: &Graph, start: NodeId, goal: NodeId) -> Algorithm {
+        self.select_shortest_path_with_metric(graph, start, goal, PathMetric::Weight)
+    }
+
+    /// Select algorithm for a shortest path query under `metric`
+    ///
+    /// Only algorithms that honor `metric` are chosen, so the answer never
+    /// depends on graph size. BFS also serves weight queries on graphs
+    /// whose edges all have the same non-negative weight.
+    pub fn select_shortest_path_with_metric(
+        &self,
+        graph: &Graph,
+        _start: NodeId,
+        _goal: NodeId,
+        metric: PathMetric,
+    ) -> Algorithm {
+        let uniform = graph.has_uniform_weights()
+            && graph.edges().first().is_none_or(|edge| edge.weight >= 0.0);
+
+        let algorithm = match metric {
+            PathMetric::Hops => Algorithm::BFS,
+            PathMetric::Weight if uniform => Algorithm::BFS,
+            PathMetric::Weight => Algorithm::Dijkstra,
+        };
+        debug_assert!(algorithm.honors(metric) || uniform);
+        algorithm
     }
 
     /// Train the selector on historical data
@@ -73,7 +90,7 @@ impl Default for AlgorithmSelector {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(feature = "uuid-ids")))]
 mod tests {
     use super::*;
 
@@ -100,10 +117,40 @@ mod tests {
     #[test]
     fn test_shortest_path_selection() {
         let selector = AlgorithmSelector::new();
-        let graph = Graph::new();
-        
-        let algo = selector.select_shortest_path(&graph, 0, 1);
-        // Should select an appropriate algorithm
-        assert!(matches!(algo, Algorithm::Dijkstra | Algorithm::BidirectionalSearch));
+        let mut graph = Graph::new();
+        for i in 0..3 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        graph.add_edge(0, 1, 1.0).unwrap();
+        graph.add_edge(1, 2, 1.0).unwrap();
+
+        // Uniform weights: hop-optimal paths are weight-optimal
+        assert_eq!(selector.select_shortest_path(&graph, 0, 2), Algorithm::BFS);
+
+        graph.add_edge(0, 2, 5.0).unwrap();
+        let algo = selector.select_shortest_path(&graph, 0, 2);
+        assert!(algo.honors(PathMetric::Weight));
+        let algo = selector.select_shortest_path_with_metric(&graph, 0, 2, PathMetric::Hops);
+        assert!(algo.honors(PathMetric::Hops));
+    }
+    #[test]
+    fn test_selection_reuses_cached_stats() {
+        let selector = AlgorithmSelector::new();
+        let mut graph = Graph::new();
+        for i in 0..10 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+
+        for _ in 0..3 {
+            selector.select(&graph);
+            crate::features::FeatureExtractor::extract_basic_features(&graph);
+        }
+        assert_eq!(graph.analysis_cache().computations(), 1);
+
+        graph.add_edge(0, 1, 1.0).unwrap();
+        selector.select(&graph);
+        crate::features::FeatureExtractor::extract_basic_features(&graph);
+        assert_eq!(graph.analysis_cache().computations(), 1);
+        assert_eq!(graph.cached_stats().edge_count, 1);
     }
 }
@@ -3,18 +3,27 @@
 use crate::embeddings::NodeEmbeddings;
 use crate::error::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use zipgraph_core::algorithms::{self, SubgraphMatchOptions};
 use zipgraph_core::{Graph, NodeId};
 
+/// Most matches of a single pattern examined by `detect_motifs`
+pub const MAX_MOTIF_MATCHES: usize = 10_000;
+
 /// Detected anomaly in a graph
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Anomaly {
     pub node_ids: Vec<NodeId>,
     pub anomaly_score: f64,
     pub reason: String,
+    /// The type of the highest-scoring finding
     pub anomaly_type: AnomalyType,
+    /// Types of further findings on the same nodes, merged in by `detect`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub additional_types: Vec<AnomalyType>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AnomalyType {
     StructuralAnomaly,
     DegreeAnomaly,
@@ -52,6 +61,11 @@ impl AnomalyDetector {
     }
 
     /// Detect anomalies in a graph
+    ///
+    /// Findings on the same nodes are merged into one anomaly with the
+    /// highest score, its type and reason first. The result is sorted by
+    /// descending score, then by node IDs, so it does not depend on the
+    /// graph's internal ordering.
     pub fn detect(&self, graph: &Graph) -> Vec<Anomaly> {
         let mut anomalies = Vec::new();
 
@@ -61,15 +75,62 @@ impl AnomalyDetector {
         // Detect structural anomalies
         anomalies.extend(self.detect_structural_anomalies(graph));
 
+        merge_and_rank(anomalies)
+    }
+
+    /// The `k` highest-ranked anomalies of `detect`
+    pub fn detect_top_k(&self, graph: &Graph, k: usize) -> Vec<Anomaly> {
+        let mut anomalies = self.detect(graph);
+        anomalies.truncate(k);
+        anomalies
+    }
+
+    /// Flag every occurrence of one of `patterns` in `graph`
+    ///
+    /// Each distinct set of nodes matching a pattern (see
+    /// `algorithms::subgraph_isomorphisms`) is reported once as a
+    /// `PatternAnomaly`, with its node IDs sorted. Empty patterns and
+    /// patterns whose directedness differs from `graph` never match. At most
+    /// `MAX_MOTIF_MATCHES` embeddings are examined per pattern.
+    pub fn detect_motifs(&self, graph: &Graph, patterns: &[Graph]) -> Vec<Anomaly> {
+        let options = SubgraphMatchOptions::default().with_max_matches(MAX_MOTIF_MATCHES);
+        let mut anomalies = Vec::new();
+
+        for (index, pattern) in patterns.iter().enumerate() {
+            if pattern.node_count() == 0 {
+                continue;
+            }
+            let matches = algorithms::subgraph_isomorphisms(pattern, graph, options)
+                .unwrap_or_default();
+
+            let mut seen = HashSet::new();
+            for mapping in matches {
+                let mut node_ids: Vec<NodeId> = mapping.into_values().collect();
+                node_ids.sort_unstable();
+                if seen.insert(node_ids.clone()) {
+                    anomalies.push(Anomaly {
+                        reason: format!("Nodes {:?} match motif {}", node_ids, index),
+                        node_ids,
+                        anomaly_score: 1.0,
+                        anomaly_type: AnomalyType::PatternAnomaly,
+                        additional_types: Vec::new(),
+                    });
+                }
+            }
+        }
+
         anomalies
     }
 
     /// Detect nodes with unusual degree
     fn detect_degree_anomalies(&self, graph: &Graph) -> Vec<Anomaly> {
         let mut anomalies = Vec::new();
-        
-        let degrees: Vec<usize> = graph
-            .node_ids()
+
+        // Summed in ID order, so that the scores do not change in the last
+        // bits between runs
+        let mut node_ids = graph.node_ids();
+        node_ids.sort_unstable();
+        let degrees: Vec<usize> = node_ids
             .iter()
             .filter_map(|&id| graph.degree(id).ok())
             .collect();
@@ -89,7 +150,7 @@ impl AnomalyDetector {
             / degrees.len() as f64;
         let std_dev = variance.sqrt();
 
-        for &node_id in graph.node_ids().iter() {
+        for &node_id in node_ids.iter() {
             if let Ok(degree) = graph.degree(node_id) {
                 let z_score = ((degree as f64 - mean_degree) / std_dev).abs();
                 
@@ -100,6 +161,7 @@ impl AnomalyDetector {
                         anomaly_score: z_score / 10.0,
                         reason: format!("Node {} has unusual degree: {}", node_id, degree),
                         anomaly_type: AnomalyType::DegreeAnomaly,
+                        additional_types: Vec::new(),
                     });
                 }
             }
@@ -121,6 +183,7 @@ impl AnomalyDetector {
                         anomaly_score: 1.0,
                         reason: format!("Node {} is isolated", node_id),
                         anomaly_type: AnomalyType::StructuralAnomaly,
+                        additional_types: Vec::new(),
                     });
                 }
             }
@@ -130,13 +193,41 @@ impl AnomalyDetector {
     }
 }
 
+/// One anomaly per set of nodes, by descending score and then node IDs
+fn merge_and_rank(anomalies: Vec<Anomaly>) -> Vec<Anomaly> {
+    let mut merged: BTreeMap<Vec<NodeId>, Anomaly> = BTreeMap::new();
+    for anomaly in anomalies {
+        let Some(existing) = merged.get_mut(&anomaly.node_ids) else {
+            merged.insert(anomaly.node_ids.clone(), anomaly);
+            continue;
+        };
+        let (mut stronger, weaker) = if anomaly.anomaly_score > existing.anomaly_score {
+            (anomaly, existing.clone())
+        } else {
+            (existing.clone(), anomaly)
+        };
+        stronger.reason = format!("{}; {}", stronger.reason, weaker.reason);
+        stronger.additional_types.push(weaker.anomaly_type);
+        stronger.additional_types.extend(weaker.additional_types);
+        *existing = stronger;
+    }
+
+    let mut ranked: Vec<Anomaly> = merged.into_values().collect();
+    ranked.sort_by(|a, b| {
+        b.anomaly_score
+            .total_cmp(&a.anomaly_score)
+            .then_with(|| a.node_ids.cmp(&b.node_ids))
+    });
+    ranked
+}
+
 impl Default for AnomalyDetector {
     fn default() -> Self {
         Self::new()
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(feature = "uuid-ids")))]
 mod tests {
     use super::*;
 
@@ -172,4 +263,103 @@ mod tests {
         // Should detect the hub as anomalous
         assert!(!anomalies.is_empty());
     }
+
+    #[test]
+    fn test_detect_motifs() {
+        let mut pattern = Graph::new_directed();
+        for i in 0..3 {
+            pattern.add_node_simple(format!("Node{}", i));
+        }
+        for (from, to) in [(0, 1), (1, 2), (2, 0)] {
+            pattern.add_edge(from, to, 1.0).unwrap();
+        }
+
+        // Two payment cycles sharing node 2, plus a plain chain
+        let mut graph = Graph::new_directed();
+        for i in 0..7 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        for (from, to) in [(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 2), (4, 5), (5, 6)] {
+            graph.add_edge(from, to, 1.0).unwrap();
+        }
+
+        let detector = AnomalyDetector::new();
+        let anomalies = detector.detect_motifs(&graph, &[pattern]);
+        assert_eq!(anomalies.len(), 2);
+        assert_eq!(anomalies[0].node_ids.len(), 3);
+        assert!(anomalies
+            .iter()
+            .all(|anomaly| matches!(anomaly.anomaly_type, AnomalyType::PatternAnomaly)));
+
+        let mut groups: Vec<Vec<NodeId>> =
+            anomalies.into_iter().map(|anomaly| anomaly.node_ids).collect();
+        groups.sort();
+        assert_eq!(groups, vec![vec![0, 1, 2], vec![2, 3, 4]]);
+
+        assert!(detector
+            .detect_motifs(&graph, &[Graph::new(), Graph::new_directed()])
+            .is_empty());
+    }
+
+    /// K30 on nodes 0..30 and node 30, isolated once its edge to node 0 is
+    /// removed: 30 degrees of 29 and one 0, a z-score of about 5.5
+    fn dense_graph_with_isolated_node() -> Graph {
+        let mut graph = Graph::new();
+        for i in 0..31 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        for a in 0..30 {
+            for b in a + 1..30 {
+                graph.add_edge(a, b, 1.0).unwrap();
+            }
+        }
+        graph
+    }
+
+    #[test]
+    fn test_detect_merges_findings_per_node() {
+        let graph = dense_graph_with_isolated_node();
+        let detector = AnomalyDetector::new();
+        let anomalies = detector.detect(&graph);
+        assert_eq!(anomalies.len(), 1);
+        let anomaly = &anomalies[0];
+        assert_eq!(anomaly.node_ids, vec![30]);
+        assert_eq!(anomaly.anomaly_score, 1.0);
+        assert_eq!(anomaly.anomaly_type, AnomalyType::StructuralAnomaly);
+        assert_eq!(anomaly.additional_types, vec![AnomalyType::DegreeAnomaly]);
+        assert!(anomaly.reason.contains("isolated") && anomaly.reason.contains("degree"));
+    }
+
+    #[test]
+    fn test_detect_is_ranked_and_deterministic() {
+        // Two hubs sharing their leaves, and ten isolated nodes
+        let build = || {
+            let mut graph = Graph::new();
+            for i in 0..40 {
+                graph.add_node_simple(format!("Node{}", i));
+            }
+            for i in 2..30 {
+                graph.add_edge(0, i, 1.0).unwrap();
+            }
+            for i in 2..20 {
+                graph.add_edge(1, i, 1.0).unwrap();
+            }
+            graph
+        };
+        let detector = AnomalyDetector::new();
+        let first = serde_json::to_string(&detector.detect(&build())).unwrap();
+        for _ in 0..5 {
+            assert_eq!(serde_json::to_string(&detector.detect(&build())).unwrap(), first);
+        }
+
+        let anomalies = detector.detect(&build());
+        assert!(anomalies
+            .windows(2)
+            .all(|pair| pair[0].anomaly_score > pair[1].anomaly_score
+                || pair[0].node_ids < pair[1].node_ids));
+        let top = detector.detect_top_k(&build(), 2);
+        assert_eq!(top, anomalies[..2]);
+        assert_eq!(top[1].node_ids, vec![31]);
+        assert_eq!(anomalies.last().unwrap().node_ids, vec![1]);
+    }
 }
@@ -1,13 +1,29 @@
 //! Node embeddings for graphs
 
 use crate::error::{MlError, Result};
+use crate::kernels;
 use ndarray::{Array1, Array2};
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::{Distribution, Normal};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use zipgraph_core::{Graph, NodeId};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use zipgraph_core::checkpoint::Checkpointable;
+use zipgraph_core::{id_bits, node_index, Graph, NodeId, PartialResult, ProgressSink};
+
+/// Query nodes whose similarities to every node `batch_most_similar`
+/// holds in memory at once
+const SIMILARITY_BLOCK_ROWS: usize = 256;
 
 /// Node embeddings representation
+///
+/// Row `i` holds the embedding of the node `node_id(i)`, so methods
+/// taking a `NodeId` reject IDs that are not numbered below `node_count`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NodeEmbeddings {
     embeddings: Array2<f32>,
@@ -18,7 +34,11 @@ pub struct NodeEmbeddings {
 impl NodeEmbeddings {
     /// Create new embeddings with random initialization
     pub fn new(node_count: usize, dimension: usize) -> Self {
-        let mut rng = rand::thread_rng();
+        Self::random(node_count, dimension, &mut rand::thread_rng())
+    }
+
+    /// `new`, drawing the initial values from `rng`
+    fn random(node_count: usize, dimension: usize, rng: &mut impl Rng) -> Self {
         let embeddings = Array2::from_shape_fn((node_count, dimension), |_| {
             rng.gen_range(-0.1..0.1)
         });
@@ -30,25 +50,43 @@ impl NodeEmbeddings {
         }
     }
 
+    /// Wrap an embedding matrix with one row per node
+    pub fn from_array(embeddings: Array2<f32>) -> Self {
+        // Rows must be contiguous for the similarity kernels
+        let embeddings = if embeddings.is_standard_layout() {
+            embeddings
+        } else {
+            embeddings.as_standard_layout().into_owned()
+        };
+        let (node_count, dimension) = embeddings.dim();
+        Self {
+            embeddings,
+            dimension,
+            node_count,
+        }
+    }
+
     /// Get embedding for a node
     pub fn get_embedding(&self, node_id: NodeId) -> Result<Array1<f32>> {
-        if node_id >= self.node_count {
-            return Err(MlError::FeatureError(format!(
-                "Node {} out of bounds",
-                node_id
-            )));
-        }
-        Ok(self.embeddings.row(node_id).to_owned())
+        Ok(Array1::from(self.row(node_id)?.to_vec()))
+    }
+
+    /// The row holding the embedding of `node_id`
+    fn row_index(&self, node_id: NodeId) -> Result<usize> {
+        node_index(node_id).filter(|&row| row < self.node_count).ok_or_else(|| {
+            MlError::FeatureError(format!("Node {} out of bounds", node_id))
+        })
+    }
+
+    /// The embedding of a node, borrowed
+    fn row(&self, node_id: NodeId) -> Result<&[f32]> {
+        let row = self.row_index(node_id)?;
+        Ok(self.embeddings.row(row).to_slice().expect("embedding rows are contiguous"))
     }
 
     /// Set embedding for a node
     pub fn set_embedding(&mut self, node_id: NodeId, embedding: &Array1<f32>) -> Result<()> {
-        if node_id >= self.node_count {
-            return Err(MlError::FeatureError(format!(
-                "Node {} out of bounds",
-                node_id
-            )));
-        }
+        let row = self.row_index(node_id)?;
         if embedding.len() != self.dimension {
             return Err(MlError::FeatureError(format!(
                 "Embedding dimension mismatch: expected {}, got {}",
@@ -56,24 +94,236 @@ impl NodeEmbeddings {
                 embedding.len()
             )));
         }
-        self.embeddings.row_mut(node_id).assign(embedding);
+        self.embeddings.row_mut(row).assign(embedding);
         Ok(())
     }
 
     /// Calculate cosine similarity between two nodes
     pub fn cosine_similarity(&self, node_a: NodeId, node_b: NodeId) -> Result<f32> {
-        let emb_a = self.get_embedding(node_a)?;
-        let emb_b = self.get_embedding(node_b)?;
+        let (emb_a, emb_b) = (self.row(node_a)?, self.row(node_b)?);
+        Ok(cosine(kernels::dot(emb_a, emb_b), kernels::l2_norm(emb_a), kernels::l2_norm(emb_b)))
+    }
 
-        let dot_product: f32 = emb_a.iter().zip(emb_b.iter()).map(|(a, b)| a * b).sum();
-        let norm_a: f32 = emb_a.iter().map(|x| x * x).sum::<f32>().sqrt();
-        let norm_b: f32 = emb_b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    /// Euclidean distance between the embeddings of two nodes
+    pub fn euclidean_distance(&self, node_a: NodeId, node_b: NodeId) -> Result<f32> {
+        Ok(kernels::squared_distance(self.row(node_a)?, self.row(node_b)?).sqrt())
+    }
+
+    /// The `k` nodes with the highest cosine similarity to `node_id`,
+    /// most similar first
+    ///
+    /// Compares `node_id` with every other node; use `batch_most_similar`
+    /// for many queries. Ties are broken by ascending node ID.
+    pub fn most_similar(&self, node_id: NodeId, k: usize) -> Result<Vec<(NodeId, f32)>> {
+        let query_row = self.row_index(node_id)?;
+        let query = self.row(node_id)?;
+        let query_norm = kernels::l2_norm(query);
+        let scores = self
+            .embeddings
+            .rows()
+            .into_iter()
+            .enumerate()
+            .filter(|&(other, _)| other != query_row)
+            .map(|(other, row)| {
+                let row = row.to_slice().expect("embedding rows are contiguous");
+                let similarity =
+                    cosine(kernels::dot(query, row), query_norm, kernels::l2_norm(row));
+                (zipgraph_core::node_id(other), similarity)
+            })
+            .collect();
+        Ok(top_k(scores, k))
+    }
 
-        if norm_a == 0.0 || norm_b == 0.0 {
-            return Ok(0.0);
+    /// `most_similar` for every node in `queries`, in order
+    ///
+    /// Normalizes the embeddings once and scores blocks of
+    /// `SIMILARITY_BLOCK_ROWS` queries against all nodes in parallel.
+    /// Similarities may differ from `cosine_similarity` in the last bits.
+    pub fn batch_most_similar(
+        &self,
+        queries: &[NodeId],
+        k: usize,
+    ) -> Result<Vec<Vec<(NodeId, f32)>>> {
+        self.batch_most_similar_excluding(queries, k, &[])
+    }
+
+    /// `batch_most_similar`, leaving out `exclusions[i]` from the results
+    /// of `queries[i]`, such as items a user already bought
+    ///
+    /// `exclusions` is either empty or has one set per query. A query node
+    /// is never among its own results.
+    pub fn batch_most_similar_excluding(
+        &self,
+        queries: &[NodeId],
+        k: usize,
+        exclusions: &[HashSet<NodeId>],
+    ) -> Result<Vec<Vec<(NodeId, f32)>>> {
+        if !exclusions.is_empty() && exclusions.len() != queries.len() {
+            return Err(MlError::FeatureError(format!(
+                "Expected {} exclusion sets, got {}",
+                queries.len(),
+                exclusions.len()
+            )));
         }
+        let query_rows = queries
+            .iter()
+            .map(|&node_id| self.row_index(node_id))
+            .collect::<Result<Vec<_>>>()?;
 
-        Ok(dot_product / (norm_a * norm_b))
+        let mut normalized = self.embeddings.clone();
+        for mut row in normalized.rows_mut() {
+            let norm = kernels::l2_norm(row.as_slice().expect("embedding rows are contiguous"));
+            if norm > 0.0 {
+                row /= norm;
+            }
+        }
+        let rows: Vec<&[f32]> = normalized
+            .rows()
+            .into_iter()
+            .map(|row| row.to_slice().expect("embedding rows are contiguous"))
+            .collect();
+
+        let blocks: Vec<Vec<Vec<(NodeId, f32)>>> = query_rows
+            .par_chunks(SIMILARITY_BLOCK_ROWS)
+            .enumerate()
+            .map(|(block, block_queries)| {
+                block_queries
+                    .iter()
+                    .enumerate()
+                    .map(|(offset, &query_row)| {
+                        let excluded = exclusions.get(block * SIMILARITY_BLOCK_ROWS + offset);
+                        let query = rows[query_row];
+                        let candidates = rows
+                            .iter()
+                            .enumerate()
+                            .filter(|&(other, _)| {
+                                other != query_row
+                                    && excluded.is_none_or(|excluded| {
+                                        !excluded.contains(&zipgraph_core::node_id(other))
+                                    })
+                            })
+                            .map(|(other, row)| {
+                                (zipgraph_core::node_id(other), kernels::dot(query, row))
+                            })
+                            .collect();
+                        top_k(candidates, k)
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok(blocks.into_iter().flatten().collect())
+    }
+
+    /// Group the nodes into `k` clusters by k-means in embedding space
+    ///
+    /// Lloyd's algorithm under Euclidean distance, from k-means++ initial
+    /// centroids, run until no node changes cluster or for `max_iter`
+    /// rounds. Returns the cluster of every node, in `0..k`; a cluster
+    /// left empty keeps its previous centroid, so some labels may be
+    /// unused. A `seed` makes the result reproducible. Fails with
+    /// `InvalidConfig` unless `1 <= k <= node_count`.
+    pub fn cluster_kmeans(
+        &self,
+        k: usize,
+        max_iter: usize,
+        seed: Option<u64>,
+    ) -> Result<Vec<usize>> {
+        if k == 0 || k > self.node_count {
+            return Err(MlError::InvalidConfig(format!(
+                "k must be between 1 and the node count {}, got {}",
+                self.node_count, k
+            )));
+        }
+
+        let points = self.embeddings.mapv(f64::from);
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let mut centroids = kmeans_plus_plus(&points, k, &mut rng);
+
+        let nearest = |centroids: &Array2<f64>, point: ndarray::ArrayView1<f64>| {
+            let mut best = (0, f64::INFINITY);
+            for (cluster, centroid) in centroids.rows().into_iter().enumerate() {
+                let distance = squared_distance(point, centroid);
+                if distance < best.1 {
+                    best = (cluster, distance);
+                }
+            }
+            best.0
+        };
+        let mut labels: Vec<usize> =
+            points.rows().into_iter().map(|point| nearest(&centroids, point)).collect();
+
+        for _ in 0..max_iter {
+            let mut sums = Array2::<f64>::zeros((k, self.dimension));
+            let mut sizes = vec![0usize; k];
+            for (point, &label) in points.rows().into_iter().zip(&labels) {
+                sums.row_mut(label).scaled_add(1.0, &point);
+                sizes[label] += 1;
+            }
+            for (cluster, &size) in sizes.iter().enumerate() {
+                if size > 0 {
+                    let mean = &sums.row(cluster) / size as f64;
+                    centroids.row_mut(cluster).assign(&mean);
+                }
+            }
+
+            let next: Vec<usize> =
+                points.rows().into_iter().map(|point| nearest(&centroids, point)).collect();
+            if next == labels {
+                break;
+            }
+            labels = next;
+        }
+
+        Ok(labels)
+    }
+
+    /// Mean silhouette coefficient of the clustering `labels`, from -1
+    /// (nodes closer to another cluster) to 1 (tight, well separated
+    /// clusters)
+    ///
+    /// A node's coefficient compares its mean Euclidean distance to the
+    /// rest of its cluster with that to the nearest other cluster; nodes
+    /// alone in their cluster count as 0. The score is 0 with fewer than
+    /// two clusters. Takes O(n² · dimension) time.
+    ///
+    /// # Panics
+    ///
+    /// If `labels` does not have one entry per node.
+    pub fn silhouette_score(&self, labels: &[usize]) -> f64 {
+        assert_eq!(labels.len(), self.node_count, "one label per node is required");
+        let clusters = labels.iter().copied().collect::<HashSet<usize>>();
+        if clusters.len() < 2 {
+            return 0.0;
+        }
+
+        let points = self.embeddings.mapv(f64::from);
+        let total: f64 = (0..self.node_count)
+            .into_par_iter()
+            .map(|node| {
+                let mut sums: HashMap<usize, (f64, usize)> = HashMap::new();
+                for other in (0..self.node_count).filter(|&other| other != node) {
+                    let distance = squared_distance(points.row(node), points.row(other)).sqrt();
+                    let entry = sums.entry(labels[other]).or_default();
+                    entry.0 += distance;
+                    entry.1 += 1;
+                }
+                let Some(&(own, own_count)) = sums.get(&labels[node]) else {
+                    return 0.0;
+                };
+                let within = own / own_count as f64;
+                let between = sums
+                    .iter()
+                    .filter(|(&label, _)| label != labels[node])
+                    .map(|(_, &(sum, count))| sum / count as f64)
+                    .fold(f64::INFINITY, f64::min);
+                let spread = within.max(between);
+                if spread > 0.0 { (between - within) / spread } else { 0.0 }
+            })
+            .sum();
+        total / self.node_count as f64
     }
 
     /// Get dimension of embeddings
@@ -87,6 +337,157 @@ impl NodeEmbeddings {
     }
 }
 
+/// Write `walks` to `path` as a text corpus for word2vec tools: one walk
+/// per line, node IDs separated by spaces
+pub fn walks_to_corpus_file(walks: &[Vec<NodeId>], path: &Path) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    for walk in walks {
+        let line: Vec<String> = walk.iter().map(|node| node.to_string()).collect();
+        writeln!(writer, "{}", line.join(" "))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// The `k` highest scores, highest first, ties broken by ascending node ID
+/// Cosine similarity from a dot product and the two norms, 0 if either
+/// vector is zero
+fn cosine(dot: f32, norm_a: f32, norm_b: f32) -> f32 {
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+fn top_k(mut scores: Vec<(NodeId, f32)>, k: usize) -> Vec<(NodeId, f32)> {
+    let descending = |a: &(NodeId, f32), b: &(NodeId, f32)| {
+        b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal).then(a.0.cmp(&b.0))
+    };
+    if k < scores.len() {
+        if k == 0 {
+            return Vec::new();
+        }
+        scores.select_nth_unstable_by(k - 1, descending);
+        scores.truncate(k);
+    }
+    scores.sort_unstable_by(descending);
+    scores
+}
+
+/// Embeddings from the nodes' raw `features`, by random projection
+///
+/// Every feature vector is multiplied by the same random Gaussian matrix
+/// with entries drawn from `N(0, 1 / embedding_dim)`, which approximately
+/// preserves distances between feature vectors (Johnson-Lindenstrauss).
+/// Nodes without features get all-zero rows. Rows follow ascending node
+/// ID order, as in `Node2VecTrainer::train`. The matrix is drawn afresh on
+/// every call, so embeddings from separate calls are not comparable.
+/// Fails with `FeatureError` if the feature vectors differ in length or
+/// `embedding_dim` is zero.
+pub fn embeddings_from_node_features(
+    graph: &Graph,
+    embedding_dim: usize,
+) -> Result<NodeEmbeddings> {
+    if embedding_dim == 0 {
+        return Err(MlError::FeatureError("Embedding dimension must be positive".to_string()));
+    }
+    let mut node_ids = graph.node_ids();
+    node_ids.sort_unstable();
+
+    let mut feature_dim = None;
+    for &id in &node_ids {
+        if let Some(features) = &graph.node(id)?.features {
+            match feature_dim {
+                None => feature_dim = Some(features.len()),
+                Some(dim) if dim != features.len() => {
+                    return Err(MlError::FeatureError(format!(
+                        "Node {} has {} features, expected {}",
+                        id,
+                        features.len(),
+                        dim
+                    )));
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    let mut embeddings = Array2::zeros((node_ids.len(), embedding_dim));
+    if let Some(feature_dim) = feature_dim {
+        let normal = Normal::new(0.0, (1.0 / embedding_dim as f64).sqrt())
+            .expect("standard deviation is positive");
+        let mut rng = rand::thread_rng();
+        let projection: Array2<f64> =
+            Array2::from_shape_fn((feature_dim, embedding_dim), |_| normal.sample(&mut rng));
+        for (row, &id) in node_ids.iter().enumerate() {
+            if let Some(features) = &graph.node(id)?.features {
+                let mut projected = Array1::<f64>::zeros(embedding_dim);
+                for (&value, weights) in features.iter().zip(projection.rows()) {
+                    projected.scaled_add(value, &weights);
+                }
+                embeddings.row_mut(row).assign(&projected.mapv(|x| x as f32));
+            }
+        }
+    }
+    Ok(NodeEmbeddings::from_array(embeddings))
+}
+
+/// Embeddings with the columns of `a` followed by those of `b`
+///
+/// Both must cover the same nodes in the same row order. Fails with
+/// `FeatureError` if their node counts differ.
+pub fn concatenate_embeddings(a: &NodeEmbeddings, b: &NodeEmbeddings) -> Result<NodeEmbeddings> {
+    if a.node_count != b.node_count {
+        return Err(MlError::FeatureError(format!(
+            "Cannot concatenate embeddings of {} and {} nodes",
+            a.node_count, b.node_count
+        )));
+    }
+    let columns = [a.embeddings.view(), b.embeddings.view()];
+    let combined = ndarray::concatenate(ndarray::Axis(1), &columns).expect("row counts match");
+    Ok(NodeEmbeddings::from_array(combined))
+}
+
+/// Default number of training epochs for `Node2VecTrainer`
+pub const DEFAULT_EPOCHS: usize = 500;
+
+/// Default gradient descent step size for `Node2VecTrainer`
+pub const DEFAULT_LEARNING_RATE: f64 = 0.01;
+
+/// Relative loss change below which training counts as converged
+const CONVERGENCE_TOLERANCE: f64 = 1e-3;
+
+/// Consecutive loss increases after which training counts as diverged
+const DIVERGENCE_PATIENCE: usize = 5;
+
+/// Co-occurrence window, in steps along a walk
+const WINDOW: usize = 5;
+
+/// Starting nodes per file written by
+/// `Node2VecTrainer::generate_walks_with_checkpoint`
+pub const WALK_CHECKPOINT_BATCH: usize = 1_000;
+
+/// The walks from one batch of starting nodes, as checkpointed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct WalkBatch {
+    start_nodes: Vec<NodeId>,
+    walks: Vec<Vec<NodeId>>,
+}
+
+impl Checkpointable for WalkBatch {}
+
+/// How a `Node2VecTrainer::train` run went
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrainingReport {
+    pub epochs_completed: usize,
+    /// Loss after the last completed epoch
+    pub final_loss: f64,
+    /// Whether the loss settled before the epoch budget ran out
+    pub converged: bool,
+    /// Loss after each epoch
+    pub loss_history: Vec<f64>,
+}
+
 /// Node2Vec embeddings trainer
 pub struct Node2VecTrainer {
     walk_length: usize,
@@ -94,6 +495,9 @@ pub struct Node2VecTrainer {
     dimension: usize,
     p: f64, // Return parameter
     q: f64, // In-out parameter
+    epochs: usize,
+    learning_rate: f64,
+    seed: Option<u64>,
 }
 
 impl Node2VecTrainer {
@@ -104,9 +508,35 @@ impl Node2VecTrainer {
             dimension,
             p: 1.0,
             q: 1.0,
+            epochs: DEFAULT_EPOCHS,
+            learning_rate: DEFAULT_LEARNING_RATE,
+            seed: None,
         }
     }
 
+    /// Make walks and training reproducible
+    ///
+    /// Each walk draws from its own generator, seeded from `seed`, its
+    /// start node and its round, so the walks do not depend on how many
+    /// threads generate them. Training also seeds the initial embeddings,
+    /// so the same seed on the same graph gives the same embeddings.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Set the maximum number of training epochs
+    pub fn with_epochs(mut self, epochs: usize) -> Self {
+        self.epochs = epochs;
+        self
+    }
+
+    /// Set the gradient descent step size
+    pub fn with_learning_rate(mut self, learning_rate: f64) -> Self {
+        self.learning_rate = learning_rate;
+        self
+    }
+
     /// Set the return and in-out parameters for biased random walks
     pub fn with_params(mut self, p: f64, q: f64) -> Self {
         self.p = p;
@@ -115,9 +545,8 @@ impl Node2VecTrainer {
     }
 
     /// Generate a single random walk starting from a node
-    fn random_walk(&self, graph: &Graph, start_node: NodeId) -> Vec<NodeId> {
+    fn random_walk(&self, graph: &Graph, start_node: NodeId, rng: &mut impl Rng) -> Vec<NodeId> {
         let mut walk = vec![start_node];
-        let mut rng = rand::thread_rng();
 
         for _ in 1..self.walk_length {
             let current = *walk.last().unwrap();
@@ -136,83 +565,310 @@ impl Node2VecTrainer {
     }
 
     /// Generate all random walks for the graph
+    ///
+    /// These are the walks `for_each_walk` streams, in the same order,
+    /// collected into memory.
     pub fn generate_walks(&self, graph: &Graph) -> Vec<Vec<NodeId>> {
-        let mut all_walks = Vec::new();
-        let node_ids = graph.node_ids();
+        let mut walks = Vec::new();
+        self.for_each_walk(graph, |walk| walks.push(walk.to_vec()));
+        walks
+    }
 
-        for _ in 0..self.num_walks {
-            for &node_id in &node_ids {
-                let walk = self.random_walk(graph, node_id);
-                if walk.len() > 1 {
-                    all_walks.push(walk);
-                }
+    /// Call `f` with every random walk for the graph, without holding
+    /// them all in memory
+    ///
+    /// Walks are generated in parallel, for batches of
+    /// `WALK_CHECKPOINT_BATCH` start nodes in ascending ID order, and only
+    /// one batch is held at a time. Within a batch come `num_walks` rounds
+    /// of one walk from each start node, as in
+    /// `generate_walks_with_checkpoint`; walks that do not leave their
+    /// start are skipped.
+    pub fn for_each_walk(&self, graph: &Graph, mut f: impl FnMut(&[NodeId])) {
+        let master = self.master_seed();
+        let mut node_ids = graph.node_ids();
+        node_ids.sort_unstable();
+        for start_nodes in node_ids.chunks(WALK_CHECKPOINT_BATCH) {
+            for walk in self.walks_from(graph, start_nodes, master) {
+                f(&walk);
             }
         }
+    }
+
+    /// The configured seed, or a fresh one for this run
+    fn master_seed(&self) -> u64 {
+        self.seed.unwrap_or_else(|| rand::thread_rng().gen())
+    }
+
+    /// `generate_walks`, saving the walks to `checkpoint_dir` as they are
+    /// generated
+    ///
+    /// Starting nodes are taken in ascending ID order in batches of
+    /// `WALK_CHECKPOINT_BATCH`, and each batch's walks are saved with
+    /// bincode to `walks_{first_node_id}.bin`. With `resume`, batches whose
+    /// file already exists are read back instead of generated again; a file
+    /// written for different starting nodes fails with `InvalidConfig`.
+    /// Walks come in the order of `generate_walks`, and with a seed they
+    /// are the same walks.
+    pub fn generate_walks_with_checkpoint(
+        &self,
+        graph: &Graph,
+        checkpoint_dir: &Path,
+        resume: bool,
+    ) -> Result<Vec<Vec<NodeId>>> {
+        fs::create_dir_all(checkpoint_dir)?;
+        let master = self.master_seed();
+        let mut node_ids = graph.node_ids();
+        node_ids.sort_unstable();
+
+        let mut all_walks = Vec::new();
+        for start_nodes in node_ids.chunks(WALK_CHECKPOINT_BATCH) {
+            let path = checkpoint_dir.join(format!("walks_{}.bin", start_nodes[0]));
+            let saved = if resume { WalkBatch::load_checkpoint(&path)? } else { None };
+            let batch = match saved {
+                Some(batch) if batch.start_nodes != start_nodes => {
+                    return Err(MlError::InvalidConfig(format!(
+                        "{} was written for different starting nodes",
+                        path.display()
+                    )));
+                }
+                Some(batch) => batch,
+                None => {
+                    let batch = WalkBatch {
+                        start_nodes: start_nodes.to_vec(),
+                        walks: self.walks_from(graph, start_nodes, master),
+                    };
+                    batch.save_checkpoint(&path)?;
+                    batch
+                }
+            };
+            all_walks.extend(batch.walks);
+        }
+
+        Ok(all_walks)
+    }
 
-        all_walks
+    /// `num_walks` rounds of one walk from each of `start_nodes`, keeping
+    /// walks that leave their start, generated in parallel from `master`
+    fn walks_from(&self, graph: &Graph, start_nodes: &[NodeId], master: u64) -> Vec<Vec<NodeId>> {
+        (0..self.num_walks * start_nodes.len())
+            .into_par_iter()
+            .map(|walk| {
+                let round = walk / start_nodes.len();
+                let start = start_nodes[walk % start_nodes.len()];
+                let mut rng = walk_rng(master, start, round);
+                self.random_walk(graph, start, &mut rng)
+            })
+            .filter(|walk| walk.len() > 1)
+            .collect()
     }
 
     /// Train Node2Vec embeddings on a graph
-    pub fn train(&self, graph: &Graph) -> Result<NodeEmbeddings> {
+    ///
+    /// Embeddings are fitted by full-batch gradient descent so that the
+    /// dot product of two nodes approximates the log of how often they
+    /// co-occur within a few steps of each other on the random walks (a
+    /// simplified stand-in for skip-gram). Training stops early once the
+    /// loss changes by less than a relative `1e-3` between epochs, which
+    /// the report records as converged. Five consecutive loss increases,
+    /// or a non-finite loss, abort training with `MlError::Convergence`;
+    /// lowering the learning rate usually helps.
+    pub fn train(&self, graph: &Graph) -> Result<(NodeEmbeddings, TrainingReport)> {
+        self.train_with_progress(graph, None).map(|result| result.value)
+    }
+
+    /// `train`, reporting each epoch to `progress`
+    ///
+    /// Progress counts epochs out of the configured maximum, and jumps to
+    /// the end when training converges early. A cancelled run returns the
+    /// embeddings after the epochs completed so far.
+    pub fn train_with_progress(
+        &self,
+        graph: &Graph,
+        progress: Option<&dyn ProgressSink>,
+    ) -> Result<PartialResult<(NodeEmbeddings, TrainingReport)>> {
         let node_count = graph.node_count();
         if node_count == 0 {
             return Err(MlError::TrainingError("Empty graph".to_string()));
         }
 
-        // Generate random walks
-        let walks = self.generate_walks(graph);
-        
-        if walks.is_empty() {
-            return Err(MlError::TrainingError("No walks generated".to_string()));
-        }
+        // Embedding rows follow ascending node ID order
+        let mut node_ids = graph.node_ids();
+        node_ids.sort_unstable();
+        let index: HashMap<NodeId, usize> =
+            node_ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
 
-        // Initialize embeddings with small random values
-        let mut embeddings = NodeEmbeddings::new(node_count, self.dimension);
-        
-        // Simple embedding update based on co-occurrence in walks
-        // In a full implementation, this would be Skip-gram with negative sampling
-        let mut co_occurrence: HashMap<(NodeId, NodeId), usize> = HashMap::new();
-        
-        for walk in &walks {
+        // Count co-occurrences as the walks are generated
+        let mut walk_count = 0;
+        let mut co_occurrence: HashMap<(usize, usize), usize> = HashMap::new();
+        self.for_each_walk(graph, |walk| {
+            walk_count += 1;
             for i in 0..walk.len() {
-                for j in (i + 1)..(walk.len()).min(i + 5) {
-                    let key = (walk[i].min(walk[j]), walk[i].max(walk[j]));
-                    *co_occurrence.entry(key).or_insert(0) += 1;
+                for j in (i + 1)..walk.len().min(i + WINDOW) {
+                    let (a, b) = (index[&walk[i]], index[&walk[j]]);
+                    if a != b {
+                        *co_occurrence.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+                    }
                 }
             }
+        });
+        if walk_count == 0 {
+            return Err(MlError::TrainingError("No walks generated".to_string()));
         }
 
-        // Update embeddings based on co-occurrence (simplified)
-        for ((node_a, node_b), count) in co_occurrence.iter() {
-            if *count > 5 {
-                // Nodes that co-occur frequently should have similar embeddings
-                let weight = (*count as f32).log2() * 0.01;
-                
-                if let (Ok(emb_a), Ok(emb_b)) = (
-                    embeddings.get_embedding(*node_a),
-                    embeddings.get_embedding(*node_b)
-                ) {
-                    let mut updated_a = emb_a.clone();
-                    let mut updated_b = emb_b.clone();
-                    
-                    for i in 0..self.dimension {
-                        updated_a[i] += (updated_b[i] - updated_a[i]) * weight;
-                        updated_b[i] += (updated_a[i] - updated_b[i]) * weight;
+        // Sorted so that seeded runs sum the gradient in the same order
+        let mut pairs: Vec<(usize, usize, f64)> = co_occurrence
+            .into_iter()
+            .map(|((a, b), count)| (a, b, (1.0 + count as f64).ln()))
+            .collect();
+        pairs.sort_unstable_by_key(|&(a, b, _)| (a, b));
+
+        let mut embeddings = match self.seed {
+            Some(seed) => {
+                NodeEmbeddings::random(node_count, self.dimension, &mut StdRng::seed_from_u64(seed))
+            }
+            None => NodeEmbeddings::new(node_count, self.dimension),
+        };
+        let mut loss_history = Vec::with_capacity(self.epochs);
+        let mut converged = false;
+        let mut increases = 0;
+
+        let total = self.epochs as u64;
+        let mut completed = total;
+        for epoch in 1..=self.epochs {
+            if progress.is_some_and(|progress| progress.should_cancel()) {
+                completed = epoch as u64 - 1;
+                break;
+            }
+            let (loss, gradient) = co_occurrence_loss(&embeddings.embeddings, &pairs);
+            embeddings.embeddings.scaled_add(-self.learning_rate as f32, &gradient);
+
+            if !loss.is_finite() {
+                return Err(MlError::Convergence(format!(
+                    "loss diverged at epoch {}: loss is {}",
+                    epoch, loss
+                )));
+            }
+
+            if let Some(&previous) = loss_history.last() {
+                if loss > previous {
+                    increases += 1;
+                    if increases >= DIVERGENCE_PATIENCE {
+                        return Err(MlError::Convergence(format!(
+                            "loss diverged at epoch {}: increased for {} consecutive epochs to {}",
+                            epoch, increases, loss
+                        )));
                     }
-                    
-                    let _ = embeddings.set_embedding(*node_a, &updated_a);
-                    let _ = embeddings.set_embedding(*node_b, &updated_b);
+                } else {
+                    increases = 0;
                 }
+
+                if (previous - loss).abs() <= CONVERGENCE_TOLERANCE * previous.max(f64::EPSILON) {
+                    converged = true;
+                }
+            }
+
+            loss_history.push(loss);
+            let done = if converged { total } else { epoch as u64 };
+            if let Some(progress) = progress {
+                progress.on_progress(done, total);
+            }
+            if converged {
+                break;
             }
         }
 
-        Ok(embeddings)
+        let report = TrainingReport {
+            epochs_completed: loss_history.len(),
+            final_loss: loss_history.last().copied().unwrap_or_default(),
+            converged,
+            loss_history,
+        };
+        Ok(PartialResult { value: (embeddings, report), completed, total })
     }
 }
 
-#[cfg(test)]
+/// Squared Euclidean distance between two points
+fn squared_distance(a: ndarray::ArrayView1<f64>, b: ndarray::ArrayView1<f64>) -> f64 {
+    a.iter().zip(b.iter()).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+/// `k` initial centroids picked among the rows of `points` by k-means++
+///
+/// The first is uniform, each next one drawn with probability
+/// proportional to its squared distance from the nearest centroid so far.
+fn kmeans_plus_plus(points: &Array2<f64>, k: usize, rng: &mut StdRng) -> Array2<f64> {
+    let count = points.nrows();
+    let mut centroids = Array2::zeros((k, points.ncols()));
+    centroids.row_mut(0).assign(&points.row(rng.gen_range(0..count)));
+    let mut nearest: Vec<f64> = points
+        .rows()
+        .into_iter()
+        .map(|point| squared_distance(point, centroids.row(0)))
+        .collect();
+
+    for cluster in 1..k {
+        let total: f64 = nearest.iter().sum();
+        let chosen = if total > 0.0 {
+            let mut target = rng.gen_range(0.0..total);
+            nearest
+                .iter()
+                .position(|&distance| {
+                    target -= distance;
+                    target < 0.0
+                })
+                .unwrap_or(count - 1)
+        } else {
+            // Every point coincides with a centroid already
+            rng.gen_range(0..count)
+        };
+        centroids.row_mut(cluster).assign(&points.row(chosen));
+        for (distance, point) in nearest.iter_mut().zip(points.rows()) {
+            *distance = distance.min(squared_distance(point, centroids.row(cluster)));
+        }
+    }
+    centroids
+}
+
+/// Generator of the walk from `start` in round `round` of a run seeded
+/// with `master`
+fn walk_rng(master: u64, start: NodeId, round: usize) -> StdRng {
+    fn mix(value: u64) -> u64 {
+        // splitmix64
+        let mut z = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    StdRng::seed_from_u64(mix(master ^ mix(id_bits(start) ^ mix(round as u64))))
+}
+
+/// Mean squared error between embedding dot products and log
+/// co-occurrence counts, and its gradient with respect to the embeddings
+fn co_occurrence_loss(
+    embeddings: &Array2<f32>,
+    pairs: &[(usize, usize, f64)],
+) -> (f64, Array2<f32>) {
+    let mut gradient = Array2::zeros(embeddings.dim());
+    let mut loss = 0.0;
+    for &(a, b, target) in pairs {
+        let (row_a, row_b) = (embeddings.row(a), embeddings.row(b));
+        let dot: f64 = row_a.iter().zip(row_b.iter()).map(|(x, y)| (x * y) as f64).sum();
+        let error = dot - target;
+        loss += error * error;
+
+        let scale = (2.0 * error) as f32;
+        gradient.row_mut(a).scaled_add(scale, &row_b);
+        gradient.row_mut(b).scaled_add(scale, &row_a);
+    }
+
+    let count = pairs.len().max(1) as f64;
+    (loss / count, gradient)
+}
+
+#[cfg(all(test, not(feature = "uuid-ids")))]
 mod tests {
     use super::*;
+    use zipgraph_core::Node;
 
     #[test]
     fn test_embeddings_creation() {
@@ -232,7 +888,23 @@ mod tests {
     fn test_cosine_similarity() {
         let embeddings = NodeEmbeddings::new(10, 64);
         let sim = embeddings.cosine_similarity(0, 1).unwrap();
-        assert!(sim >= -1.0 && sim <= 1.0);
+        assert!((-1.0..=1.0).contains(&sim));
+    }
+
+    #[test]
+    fn test_similarity_of_non_standard_layout() {
+        // Stored column-major, rows [3, 4, 0], [0, 0, 0] and [6, 8, 1]
+        let matrix = ndarray::arr2(&[[3.0, 0.0, 6.0], [4.0, 0.0, 8.0], [0.0, 0.0, 1.0]]);
+        let embeddings = NodeEmbeddings::from_array(matrix.reversed_axes());
+        assert_eq!(embeddings.get_embedding(0).unwrap().to_vec(), vec![3.0, 4.0, 0.0]);
+
+        assert!((embeddings.euclidean_distance(0, 2).unwrap() - 26f32.sqrt()).abs() < 1e-6);
+        assert_eq!(embeddings.euclidean_distance(0, 1).unwrap(), 5.0);
+        let expected = 50.0 / (5.0 * 101f32.sqrt());
+        assert!((embeddings.cosine_similarity(0, 2).unwrap() - expected).abs() < 1e-6);
+        assert_eq!(embeddings.cosine_similarity(0, 1).unwrap(), 0.0);
+        assert!(embeddings.euclidean_distance(0, 3).is_err());
+        assert_eq!(embeddings.most_similar(0, 1).unwrap()[0].0, 2);
     }
 
     #[test]
@@ -249,7 +921,7 @@ mod tests {
         graph.add_edge(n3, n0, 1.0).unwrap();
 
         let trainer = Node2VecTrainer::new(10, 5, 16);
-        let walk = trainer.random_walk(&graph, n0);
+        let walk = trainer.random_walk(&graph, n0, &mut rand::thread_rng());
         
         assert!(!walk.is_empty());
         assert_eq!(walk[0], n0);
@@ -288,10 +960,32 @@ mod tests {
         graph.add_edge(n3, n0, 1.0).unwrap();
 
         let trainer = Node2VecTrainer::new(10, 5, 16);
-        let embeddings = trainer.train(&graph).unwrap();
+        let (embeddings, report) = trainer.train(&graph).unwrap();
         
         assert_eq!(embeddings.node_count(), 4);
         assert_eq!(embeddings.dimension(), 16);
+        assert!(report.converged, "{:?}", report);
+        assert_eq!(report.epochs_completed, report.loss_history.len());
+        assert!(report.epochs_completed < DEFAULT_EPOCHS);
+        assert_eq!(report.final_loss, *report.loss_history.last().unwrap());
+        assert!(report.final_loss < report.loss_history[0]);
+    }
+
+    #[test]
+    fn test_node2vec_divergence() {
+        let mut graph = Graph::new();
+        for i in 0..6 {
+            graph.add_node_simple(format!("{}", i));
+        }
+        for i in 0..6 {
+            graph.add_edge(i, (i + 1) % 6, 1.0).unwrap();
+        }
+
+        let trainer = Node2VecTrainer::new(10, 5, 16).with_learning_rate(10.0);
+        match trainer.train(&graph) {
+            Err(MlError::Convergence(message)) => assert!(message.contains("diverged")),
+            other => panic!("expected a convergence error, got {:?}", other.map(|(_, r)| r)),
+        }
     }
 
     #[test]
@@ -316,4 +1010,234 @@ mod tests {
         let result = trainer.train(&graph);
         assert!(result.is_err());
     }
+
+    fn graph_with_features() -> Graph {
+        let mut graph = Graph::new();
+        graph.add_node(Node::new(0, "a").with_features(vec![1.0, 0.0, 2.0]));
+        graph.add_node(Node::new(1, "b"));
+        graph.add_node(Node::new(2, "c").with_features(vec![0.5, -1.0, 0.0]));
+        graph
+    }
+
+    #[test]
+    fn test_embeddings_from_node_features() {
+        let graph = graph_with_features();
+        let embeddings = embeddings_from_node_features(&graph, 8).unwrap();
+        assert_eq!((embeddings.node_count(), embeddings.dimension()), (3, 8));
+
+        let norm = |id| embeddings.get_embedding(id).unwrap().iter().map(|x| x.abs()).sum::<f32>();
+        assert!(norm(0) > 0.0);
+        assert!(norm(2) > 0.0);
+        assert_eq!(norm(1), 0.0);
+
+        let featureless = embeddings_from_node_features(&Graph::new(), 4).unwrap();
+        assert_eq!(featureless.node_count(), 0);
+        assert!(embeddings_from_node_features(&graph, 0).is_err());
+
+        let mut mismatched = graph_with_features();
+        mismatched.add_node(Node::new(3, "d").with_features(vec![1.0]));
+        assert!(matches!(
+            embeddings_from_node_features(&mismatched, 8),
+            Err(MlError::FeatureError(_))
+        ));
+    }
+
+    #[test]
+    fn test_concatenate_embeddings() {
+        let graph = graph_with_features();
+        let projected = embeddings_from_node_features(&graph, 8).unwrap();
+        let random = NodeEmbeddings::new(3, 16);
+        let combined = concatenate_embeddings(&projected, &random).unwrap();
+        assert_eq!((combined.node_count(), combined.dimension()), (3, 24));
+
+        let row = combined.get_embedding(2).unwrap();
+        assert_eq!(row.slice(ndarray::s![..8]), projected.get_embedding(2).unwrap());
+        assert_eq!(row.slice(ndarray::s![8..]), random.get_embedding(2).unwrap());
+
+        let other = NodeEmbeddings::new(4, 16);
+        assert!(matches!(
+            concatenate_embeddings(&projected, &other),
+            Err(MlError::FeatureError(_))
+        ));
+    }
+
+    #[test]
+    fn test_node2vec_progress() {
+        use zipgraph_core::testing::CountingSink;
+
+        let mut graph = Graph::new();
+        for i in 0..6 {
+            graph.add_node_simple(format!("{}", i));
+        }
+        for i in 0..6 {
+            graph.add_edge(i, (i + 1) % 6, 1.0).unwrap();
+        }
+        // Seeded, so the run is the same every time and does not converge
+        // before the cancellation below
+        let trainer = Node2VecTrainer::new(10, 5, 8).with_seed(11);
+
+        let sink = CountingSink::new();
+        let result = trainer.train_with_progress(&graph, Some(&sink)).unwrap();
+        let report = &result.value.1;
+        assert!(result.is_complete());
+        assert!(report.epochs_completed > 3, "{:?}", report);
+        assert_eq!(sink.updates(), report.epochs_completed as u64);
+        assert_eq!(sink.last_completed(), DEFAULT_EPOCHS as u64);
+
+        let sink = CountingSink::cancelling_after(3);
+        let partial = trainer.train_with_progress(&graph, Some(&sink)).unwrap();
+        assert_eq!((partial.completed, partial.total), (3, DEFAULT_EPOCHS as u64));
+        assert_eq!(partial.value.1.epochs_completed, 3);
+        assert_eq!(partial.value.0.node_count(), 6);
+    }
+
+    #[test]
+    fn test_batch_most_similar_matches_most_similar() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(4);
+        let matrix = Array2::from_shape_fn((700, 16), |_| rng.gen_range(-1.0..1.0));
+        let mut embeddings = NodeEmbeddings::from_array(matrix);
+        // Zero rows have similarity 0 to everything
+        embeddings.set_embedding(3, &Array1::zeros(16)).unwrap();
+
+        let queries: Vec<NodeId> = (0..700).step_by(7).chain([3, 699]).collect();
+        let batch = embeddings.batch_most_similar(&queries, 20).unwrap();
+        assert_eq!(batch.len(), queries.len());
+        for (&query, results) in queries.iter().zip(&batch) {
+            let naive = embeddings.most_similar(query, 20).unwrap();
+            assert_eq!(results.len(), 20);
+            for (&(node, score), &(naive_node, naive_score)) in results.iter().zip(&naive) {
+                assert_eq!(node, naive_node);
+                assert!((score - naive_score).abs() < 1e-5, "{} vs {}", score, naive_score);
+            }
+        }
+
+        let everyone = embeddings.batch_most_similar(&[0], 10_000).unwrap();
+        assert_eq!(everyone[0].len(), 699);
+        assert!(embeddings.batch_most_similar(&[0], 0).unwrap()[0].is_empty());
+        assert!(embeddings.batch_most_similar(&[700], 5).is_err());
+    }
+
+    #[test]
+    fn test_batch_most_similar_excluding() {
+        let embeddings = NodeEmbeddings::new(50, 8);
+        let plain = embeddings.batch_most_similar(&[0, 1], 5).unwrap();
+        let bought: HashSet<NodeId> = plain[0].iter().take(2).map(|&(node, _)| node).collect();
+        let excluded = embeddings
+            .batch_most_similar_excluding(&[0, 1], 5, &[bought.clone(), HashSet::new()])
+            .unwrap();
+        assert!(excluded[0].iter().all(|(node, _)| !bought.contains(node)));
+        assert_eq!(excluded[0][..3], plain[0][2..]);
+        assert_eq!(excluded[1], plain[1]);
+        assert!(embeddings.batch_most_similar_excluding(&[0, 1], 5, &[bought]).is_err());
+    }
+
+    #[test]
+    fn test_generate_walks_with_checkpoint() {
+        let mut graph = Graph::new();
+        for i in 0..2500 {
+            graph.add_node_simple(format!("{}", i));
+        }
+        for i in 0..2500 {
+            graph.add_edge(i, (i + 1) % 2500, 1.0).unwrap();
+        }
+        let trainer = Node2VecTrainer::new(5, 2, 8);
+        let dir = tempfile::TempDir::new().unwrap();
+
+        let walks = trainer.generate_walks_with_checkpoint(&graph, dir.path(), false).unwrap();
+        assert_eq!(walks.len(), 2 * 2500);
+        for first in [0, 1000, 2000] {
+            assert!(dir.path().join(format!("walks_{}.bin", first)).exists());
+        }
+
+        // Every batch is on disk, so resuming reads the same walks back
+        let resumed = trainer.generate_walks_with_checkpoint(&graph, dir.path(), true).unwrap();
+        assert_eq!(resumed, walks);
+
+        // A missing batch is generated again, the others are kept
+        fs::remove_file(dir.path().join("walks_1000.bin")).unwrap();
+        let resumed = trainer.generate_walks_with_checkpoint(&graph, dir.path(), true).unwrap();
+        assert_eq!(resumed[..2000], walks[..2000]);
+        assert_eq!(resumed[4000..], walks[4000..]);
+        assert!(resumed[2000..4000].iter().all(|walk| (1000..2000).contains(&walk[0])));
+        assert!(dir.path().join("walks_1000.bin").exists());
+
+        // Without resume, every batch is generated again
+        let fresh = trainer.generate_walks_with_checkpoint(&graph, dir.path(), false).unwrap();
+        assert_ne!(fresh, resumed);
+
+        // The last batch on disk started from nodes 2000..2500
+        let mut smaller = Graph::new();
+        for i in 0..2400 {
+            smaller.add_node_simple(format!("{}", i));
+        }
+        assert!(trainer.generate_walks_with_checkpoint(&smaller, dir.path(), true).is_err());
+    }
+
+    #[test]
+    fn test_walks_to_corpus_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("walks.txt");
+        walks_to_corpus_file(&[vec![0, 1, 2], vec![12, 3]], &path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "0 1 2\n12 3\n");
+    }
+    #[test]
+    fn test_seeded_walks_are_reproducible() {
+        let graph = zipgraph_core::generators::erdos_renyi(1500, 0.004, false, Some(2));
+        let trainer = Node2VecTrainer::new(8, 3, 8).with_seed(17);
+        let walks = trainer.generate_walks(&graph);
+        assert_eq!(walks, trainer.generate_walks(&graph));
+        assert_ne!(walks, Node2VecTrainer::new(8, 3, 8).with_seed(18).generate_walks(&graph));
+
+        for threads in [1, 3] {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+            assert_eq!(pool.install(|| trainer.generate_walks(&graph)), walks);
+        }
+
+        let mut streamed = Vec::new();
+        trainer.for_each_walk(&graph, |walk| streamed.push(walk.to_vec()));
+        assert_eq!(streamed, walks);
+        let dir = tempfile::TempDir::new().unwrap();
+        let checkpointed = trainer.generate_walks_with_checkpoint(&graph, dir.path(), false);
+        assert_eq!(checkpointed.unwrap(), walks);
+
+        let small = zipgraph_core::generators::erdos_renyi(30, 0.2, false, Some(2));
+        let trainer = Node2VecTrainer::new(6, 2, 4).with_seed(3).with_epochs(20);
+        let (first, _) = trainer.train(&small).unwrap();
+        let (second, _) = trainer.train(&small).unwrap();
+        assert_eq!(first.embeddings, second.embeddings);
+    }
+    #[test]
+    fn test_cluster_kmeans() {
+        // Three tight groups of 20 nodes around far apart corners
+        let mut rng = StdRng::seed_from_u64(5);
+        let corners = [[5.0, 0.0, 0.0], [0.0, 5.0, 0.0], [0.0, 0.0, 5.0]];
+        let matrix = Array2::from_shape_fn((60, 3), |(row, column)| {
+            corners[row % 3][column] + rng.gen_range(-0.3..0.3)
+        });
+        let embeddings = NodeEmbeddings::from_array(matrix);
+
+        let labels = embeddings.cluster_kmeans(3, 50, Some(1)).unwrap();
+        assert_eq!(labels.len(), 60);
+        assert!(labels.iter().all(|&label| label < 3));
+        for node in 3..60 {
+            assert_eq!(labels[node], labels[node % 3], "node {}", node);
+        }
+        assert_eq!(embeddings.cluster_kmeans(3, 50, Some(1)).unwrap(), labels);
+        let score = embeddings.silhouette_score(&labels);
+        assert!(score > 0.5, "silhouette {}", score);
+
+        // Arbitrary clusterings still score within [-1, 1]
+        let random = NodeEmbeddings::new(40, 8);
+        let labels = random.cluster_kmeans(4, 10, None).unwrap();
+        assert!(labels.iter().all(|&label| label < 4));
+        for labels in [labels, (0..40).map(|node| node % 5).collect()] {
+            let score = random.silhouette_score(&labels);
+            assert!((-1.0..=1.0).contains(&score), "silhouette {}", score);
+        }
+        assert_eq!(random.silhouette_score(&[0; 40]), 0.0);
+
+        assert!(random.cluster_kmeans(0, 10, None).is_err());
+        assert!(random.cluster_kmeans(41, 10, None).is_err());
+        assert_eq!(random.cluster_kmeans(40, 10, Some(2)).unwrap().len(), 40);
+    }
 }
@@ -16,6 +16,9 @@ pub enum MlError {
     #[error("Inference error: {0}")]
     InferenceError(String),
 
+    #[error("Training did not converge: {0}")]
+    Convergence(String),
+
     #[error("Feature extraction error: {0}")]
     FeatureError(String),
 
@@ -0,0 +1,156 @@
+//! Quality measures against known ground truth
+//!
+//! Paired with the planted generators in `zipgraph_core::generators`, these
+//! turn the quality of community detection and anomaly detection into
+//! numbers that tests can hold to a floor.
+
+use crate::error::{MlError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use zipgraph_core::NodeId;
+
+/// Agreement between a predicted and a true partition of the nodes
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CommunityScores {
+    /// Normalized mutual information, from 0 (independent) to 1 (identical)
+    pub nmi: f64,
+    /// Adjusted Rand index: 1 for identical partitions, around 0 for
+    /// random ones, negative for worse than random
+    pub ari: f64,
+}
+
+/// How well detected anomalous nodes match the injected ones
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AnomalyScores {
+    /// Fraction of detected nodes that were injected
+    pub precision: f64,
+    /// Fraction of injected nodes that were detected
+    pub recall: f64,
+}
+
+/// Compare the community labels `predicted` with `truth`
+///
+/// Labels are only compared for equality, so the two sides may number
+/// their communities differently. Every node of `truth` must have a
+/// predicted label, otherwise this fails with `InvalidConfig`; extra
+/// predicted nodes are ignored. NMI is normalized by the mean of the two
+/// entropies, and both scores are 1 when both partitions are a single
+/// community.
+pub fn evaluate_communities(
+    predicted: &HashMap<NodeId, usize>,
+    truth: &HashMap<NodeId, usize>,
+) -> Result<CommunityScores> {
+    let mut joint: HashMap<(usize, usize), usize> = HashMap::new();
+    for (node, &true_label) in truth {
+        let predicted_label = predicted.get(node).ok_or_else(|| {
+            MlError::InvalidConfig(format!("Node {} has no predicted community", node))
+        })?;
+        *joint.entry((*predicted_label, true_label)).or_default() += 1;
+    }
+
+    let mut predicted_sizes: HashMap<usize, usize> = HashMap::new();
+    let mut true_sizes: HashMap<usize, usize> = HashMap::new();
+    for (&(p, t), &count) in &joint {
+        *predicted_sizes.entry(p).or_default() += count;
+        *true_sizes.entry(t).or_default() += count;
+    }
+
+    let n = truth.len() as f64;
+    let entropy = |sizes: &HashMap<usize, usize>| -> f64 {
+        sizes.values().map(|&size| size as f64 / n).map(|p| -p * p.ln()).sum()
+    };
+    let mutual_information: f64 = joint
+        .iter()
+        .map(|(&(p, t), &count)| {
+            let joint_p = count as f64 / n;
+            let independent = predicted_sizes[&p] as f64 * true_sizes[&t] as f64 / (n * n);
+            joint_p * (joint_p / independent).ln()
+        })
+        .sum();
+    let entropies = entropy(&predicted_sizes) + entropy(&true_sizes);
+    let nmi = if entropies > 0.0 { 2.0 * mutual_information / entropies } else { 1.0 };
+
+    let pairs = |count: usize| (count * count.saturating_sub(1) / 2) as f64;
+    let index: f64 = joint.values().map(|&count| pairs(count)).sum();
+    let predicted_pairs: f64 = predicted_sizes.values().map(|&size| pairs(size)).sum();
+    let true_pairs: f64 = true_sizes.values().map(|&size| pairs(size)).sum();
+    let expected = predicted_pairs * true_pairs / pairs(truth.len()).max(1.0);
+    let maximum = (predicted_pairs + true_pairs) / 2.0;
+    let ari = if maximum == expected { 1.0 } else { (index - expected) / (maximum - expected) };
+
+    Ok(CommunityScores { nmi, ari })
+}
+
+/// Compare the nodes flagged by a detector with the ones injected
+///
+/// Duplicates are ignored. Precision is 1 when nothing was detected and
+/// recall is 1 when nothing was injected.
+pub fn evaluate_anomalies(detected: &[NodeId], injected: &[NodeId]) -> AnomalyScores {
+    let detected: HashSet<NodeId> = detected.iter().copied().collect();
+    let injected: HashSet<NodeId> = injected.iter().copied().collect();
+    let hits = detected.intersection(&injected).count() as f64;
+    let ratio = |total: usize| if total == 0 { 1.0 } else { hits / total as f64 };
+    AnomalyScores { precision: ratio(detected.len()), recall: ratio(injected.len()) }
+}
+
+#[cfg(all(test, not(feature = "uuid-ids")))]
+mod tests {
+    use super::*;
+    use crate::anomaly::AnomalyDetector;
+    use zipgraph_core::algorithms::{label_propagation, DEFAULT_LABEL_PROPAGATION_ITERATIONS};
+    use zipgraph_core::generators::{erdos_renyi, planted_anomalies, planted_partition, AnomalySpec};
+
+    #[test]
+    fn test_evaluate_communities() {
+        let truth: HashMap<NodeId, usize> = (0..6).map(|node| (node, node / 3)).collect();
+
+        // Renumbered but identical
+        let relabeled: HashMap<NodeId, usize> = (0..6).map(|node| (node, 7 - node / 3)).collect();
+        let scores = evaluate_communities(&relabeled, &truth).unwrap();
+        assert!((scores.nmi - 1.0).abs() < 1e-12 && (scores.ari - 1.0).abs() < 1e-12);
+
+        // Each predicted community mixes both true ones evenly
+        let crossed: HashMap<NodeId, usize> = (0..6).map(|node| (node, node % 2)).collect();
+        let scores = evaluate_communities(&crossed, &truth).unwrap();
+        assert!(scores.nmi < 0.1, "{:?}", scores);
+        assert!(scores.ari < 0.0, "{:?}", scores);
+
+        let single: HashMap<NodeId, usize> = (0..6).map(|node| (node, 0)).collect();
+        assert_eq!(
+            evaluate_communities(&single, &single).unwrap(),
+            CommunityScores { nmi: 1.0, ari: 1.0 }
+        );
+        assert!(evaluate_communities(&HashMap::new(), &truth).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_anomalies() {
+        let scores = evaluate_anomalies(&[1, 2, 3, 3], &[2, 3, 4, 5]);
+        assert_eq!(scores, AnomalyScores { precision: 2.0 / 3.0, recall: 0.5 });
+        assert_eq!(evaluate_anomalies(&[], &[]), AnomalyScores { precision: 1.0, recall: 1.0 });
+    }
+
+    #[test]
+    fn test_label_propagation_recovers_planted_communities() {
+        let (graph, truth) = planted_partition(200, 4, 0.3, 0.01, Some(11));
+        let predicted = label_propagation(&graph, DEFAULT_LABEL_PROPAGATION_ITERATIONS);
+        let scores = evaluate_communities(&predicted, &truth).unwrap();
+        assert!(scores.nmi > 0.9, "{:?}", scores);
+        assert!(scores.ari > 0.9, "{:?}", scores);
+    }
+
+    #[test]
+    fn test_anomaly_detector_finds_planted_hubs_and_isolated_nodes() {
+        let base = erdos_renyi(300, 0.02, false, Some(8));
+        let spec = AnomalySpec::default().with_hubs(3, 60).with_isolated(5).with_cliques(1, 8);
+        let (graph, injected) = planted_anomalies(&base, spec, Some(8));
+
+        let anomalies = AnomalyDetector::new().detect(&graph);
+        let detected: Vec<NodeId> =
+            anomalies.iter().flat_map(|anomaly| anomaly.node_ids.iter().copied()).collect();
+        let scores = evaluate_anomalies(&detected, &injected);
+        assert!(scores.precision >= 0.9, "{:?}", scores);
+        // Degree and isolation checks cannot see the clique
+        assert!(scores.recall >= 0.5, "{:?}", scores);
+    }
+}
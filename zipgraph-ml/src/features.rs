@@ -1,19 +1,21 @@
 //! Feature extraction from graphs for ML models
 
-use zipgraph_core::{Graph, GraphStats};
+use zipgraph_core::{Graph, NodeId};
 
 /// Extract features from a graph for ML models
 pub struct FeatureExtractor;
 
 impl FeatureExtractor {
     /// Extract basic statistical features
+    ///
+    /// The statistics are memoized on the graph, so repeated calls on an
+    /// unchanged graph do not recompute them.
     pub fn extract_basic_features(graph: &Graph) -> Vec<f64> {
-        let stats = GraphStats::from_graph(graph);
-        stats.to_feature_vector()
+        graph.cached_stats().to_feature_vector()
     }
 
     /// Extract node-level features
-    pub fn extract_node_features(graph: &Graph, node_id: usize) -> Vec<f64> {
+    pub fn extract_node_features(graph: &Graph, node_id: NodeId) -> Vec<f64> {
         let mut features = Vec::new();
 
         // Degree
@@ -34,18 +36,16 @@ impl FeatureExtractor {
     }
 
     /// Extract edge-level features
-    pub fn extract_edge_features(graph: &Graph, from: usize, to: usize) -> Vec<f64> {
-        let mut features = Vec::new();
-
+    pub fn extract_edge_features(graph: &Graph, from: NodeId, to: NodeId) -> Vec<f64> {
         // Node degrees
-        features.push(graph.degree(from).unwrap_or(0) as f64);
-        features.push(graph.degree(to).unwrap_or(0) as f64);
-
-        features
+        vec![
+            graph.degree(from).unwrap_or(0) as f64,
+            graph.degree(to).unwrap_or(0) as f64,
+        ]
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(feature = "uuid-ids")))]
 mod tests {
     use super::*;
 
@@ -0,0 +1,584 @@
+//! Graph neural network layers
+//!
+//! Layers implement `MessagePassing`: each node's new representation is an
+//! aggregate of the transformed features of its neighborhood, which always
+//! includes the node itself. Feature matrices have one row per node, with
+//! row `i` holding the features of node ID `i`.
+
+use crate::embeddings::NodeEmbeddings;
+use crate::error::{MlError, Result};
+use ndarray::{s, Array1, Array2, ArrayView1, ArrayView2, Axis};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use zipgraph_core::{node_id, node_index, Graph, NodeId};
+
+/// Default negative slope of the LeakyReLU applied to attention scores
+pub const DEFAULT_LEAKY_RELU_ALPHA: f32 = 0.2;
+
+/// Seed used to initialize trained models, so training is deterministic
+const TRAINING_SEED: u64 = 42;
+
+/// Learning rate for full-batch gradient descent in `train_gat`/`train_gcn`
+const LEARNING_RATE: f32 = 0.05;
+
+/// A layer that updates node representations from their neighborhoods
+pub trait MessagePassing {
+    /// Dimension of the representations produced by `forward`
+    fn output_dim(&self) -> usize;
+
+    /// Compute new representations for every node of `graph`
+    fn forward(&self, graph: &Graph, features: &Array2<f32>) -> Result<Array2<f32>>;
+}
+
+/// Graph convolution: the mean of the transformed neighborhood features
+#[derive(Debug, Clone)]
+pub struct GcnLayer {
+    weight: Array2<f32>,
+}
+
+impl GcnLayer {
+    /// Create a layer with Glorot-initialized weights
+    pub fn new(input_dim: usize, output_dim: usize) -> Self {
+        Self::with_rng(input_dim, output_dim, &mut rand::thread_rng())
+    }
+
+    fn with_rng<R: Rng>(input_dim: usize, output_dim: usize, rng: &mut R) -> Self {
+        Self {
+            weight: glorot(input_dim, output_dim, rng),
+        }
+    }
+}
+
+impl MessagePassing for GcnLayer {
+    fn output_dim(&self) -> usize {
+        self.weight.ncols()
+    }
+
+    fn forward(&self, graph: &Graph, features: &Array2<f32>) -> Result<Array2<f32>> {
+        let neighborhoods = neighborhoods(graph, features, self.weight.nrows())?;
+        let z = matmul(features.view(), self.weight.view());
+        Ok(aggregate(&z, &neighborhoods, &uniform_coefficients(&neighborhoods)))
+    }
+}
+
+/// Graph attention layer (GAT)
+///
+/// The coefficient of neighbor `j` in the aggregate of node `i` is
+/// `softmax_j(leaky_relu(aᵀ [W·h_i || W·h_j]))`, where `a` is the
+/// attention vector stored as a `2 * output_dim` × 1 matrix.
+#[derive(Debug, Clone)]
+pub struct GatLayer {
+    weight: Array2<f32>,
+    attention_weight_matrix: Array2<f32>,
+    leaky_relu_alpha: f32,
+}
+
+impl GatLayer {
+    /// Create a layer with Glorot-initialized weights
+    pub fn new(input_dim: usize, output_dim: usize) -> Self {
+        Self::with_rng(input_dim, output_dim, &mut rand::thread_rng())
+    }
+
+    fn with_rng<R: Rng>(input_dim: usize, output_dim: usize, rng: &mut R) -> Self {
+        Self {
+            weight: glorot(input_dim, output_dim, rng),
+            attention_weight_matrix: glorot(2 * output_dim, 1, rng),
+            leaky_relu_alpha: DEFAULT_LEAKY_RELU_ALPHA,
+        }
+    }
+
+    /// Set the negative slope of the LeakyReLU applied to attention scores
+    pub fn with_leaky_relu_alpha(mut self, alpha: f32) -> Self {
+        self.leaky_relu_alpha = alpha;
+        self
+    }
+
+    /// Attention coefficients of `node` over its neighborhood
+    ///
+    /// Returns `(neighbor, coefficient)` pairs, the node itself first and
+    /// then its neighbors in ascending ID order. Coefficients sum to 1.
+    pub fn attention_coefficients(
+        &self,
+        graph: &Graph,
+        features: &Array2<f32>,
+        node: NodeId,
+    ) -> Result<Vec<(NodeId, f32)>> {
+        let neighborhoods = neighborhoods(graph, features, self.weight.nrows())?;
+        let (row, neighborhood) = node_index(node)
+            .and_then(|row| Some((row, neighborhoods.get(row)?)))
+            .ok_or_else(|| MlError::FeatureError(format!("Node {} out of bounds", node)))?;
+
+        let z = matmul(features.view(), self.weight.view());
+        let (coefficients, _) = self.attend(&z, row, neighborhood);
+        Ok(neighborhood.iter().map(|&j| node_id(j)).zip(coefficients).collect())
+    }
+
+    /// Attention coefficients and pre-activation scores for node `i`
+    fn attend(&self, z: &Array2<f32>, i: usize, neighborhood: &[usize]) -> (Vec<f32>, Vec<f32>) {
+        let (a_src, a_dst) = self.attention_halves();
+        let source = dot(z.row(i), a_src.view());
+        let scores: Vec<f32> = neighborhood
+            .iter()
+            .map(|&j| source + dot(z.row(j), a_dst.view()))
+            .collect();
+
+        let activated: Vec<f32> = scores
+            .iter()
+            .map(|&score| leaky_relu(score, self.leaky_relu_alpha))
+            .collect();
+        (softmax(&activated), scores)
+    }
+
+    /// The halves of `a` applied to `W·h_i` and `W·h_j`
+    fn attention_halves(&self) -> (Array1<f32>, Array1<f32>) {
+        let d = self.weight.ncols();
+        let a = self.attention_weight_matrix.column(0);
+        (a.slice(s![..d]).to_owned(), a.slice(s![d..]).to_owned())
+    }
+}
+
+impl MessagePassing for GatLayer {
+    fn output_dim(&self) -> usize {
+        self.weight.ncols()
+    }
+
+    fn forward(&self, graph: &Graph, features: &Array2<f32>) -> Result<Array2<f32>> {
+        let neighborhoods = neighborhoods(graph, features, self.weight.nrows())?;
+        let z = matmul(features.view(), self.weight.view());
+        let coefficients: Vec<Vec<f32>> = neighborhoods
+            .iter()
+            .enumerate()
+            .map(|(i, neighborhood)| self.attend(&z, i, neighborhood).0)
+            .collect();
+        Ok(aggregate(&z, &neighborhoods, &coefficients))
+    }
+}
+
+/// Train a multi-head GAT layer for node classification
+///
+/// The `num_heads` attention heads are trained jointly with a softmax
+/// classifier on `labels` by full-batch gradient descent. The returned
+/// embeddings are the concatenated head outputs, so their dimension is
+/// `hidden_dim * num_heads`.
+pub fn train_gat(
+    graph: &Graph,
+    features: &Array2<f32>,
+    labels: &[usize],
+    hidden_dim: usize,
+    num_heads: usize,
+    epochs: usize,
+) -> Result<NodeEmbeddings> {
+    train(graph, features, labels, hidden_dim, num_heads, epochs, true)
+}
+
+/// Train a GCN layer for node classification, see `train_gat`
+///
+/// The returned embeddings have dimension `hidden_dim`.
+pub fn train_gcn(
+    graph: &Graph,
+    features: &Array2<f32>,
+    labels: &[usize],
+    hidden_dim: usize,
+    epochs: usize,
+) -> Result<NodeEmbeddings> {
+    train(graph, features, labels, hidden_dim, 1, epochs, false)
+}
+
+/// Shared training loop; without `attention`, heads aggregate like a GCN
+fn train(
+    graph: &Graph,
+    features: &Array2<f32>,
+    labels: &[usize],
+    hidden_dim: usize,
+    num_heads: usize,
+    epochs: usize,
+    attention: bool,
+) -> Result<NodeEmbeddings> {
+    let n = graph.node_count();
+    if n == 0 {
+        return Err(MlError::TrainingError("Empty graph".to_string()));
+    }
+    if hidden_dim == 0 || num_heads == 0 {
+        return Err(MlError::InvalidConfig(
+            "hidden_dim and num_heads must be positive".to_string(),
+        ));
+    }
+    if labels.len() != n {
+        return Err(MlError::TrainingError(format!(
+            "Expected {} labels, got {}",
+            n,
+            labels.len()
+        )));
+    }
+
+    let neighborhoods = neighborhoods(graph, features, features.ncols())?;
+    let classes = labels.iter().max().map_or(0, |&max| max + 1);
+    let mut rng = StdRng::seed_from_u64(TRAINING_SEED);
+
+    let mut heads: Vec<GatLayer> = (0..num_heads)
+        .map(|_| GatLayer::with_rng(features.ncols(), hidden_dim, &mut rng))
+        .collect();
+    let mut classifier = glorot(hidden_dim * num_heads, classes, &mut rng);
+    let mut bias = Array1::<f32>::zeros(classes);
+
+    let mut targets = Array2::<f32>::zeros((n, classes));
+    for (i, &label) in labels.iter().enumerate() {
+        targets[[i, label]] = 1.0;
+    }
+
+    for _ in 0..epochs {
+        // Forward pass
+        let passes: Vec<HeadPass> = heads
+            .iter()
+            .map(|head| HeadPass::forward(head, features, &neighborhoods, attention))
+            .collect();
+        let hidden = concatenate(&passes, n, hidden_dim);
+
+        let mut probabilities = matmul(hidden.view(), classifier.view()) + &bias;
+        for mut row in probabilities.rows_mut() {
+            let normalized = softmax(row.as_slice().expect("rows are contiguous"));
+            row.assign(&Array1::from(normalized));
+        }
+
+        // Backward pass for the mean cross-entropy loss
+        let grad_logits = (probabilities - &targets) / n as f32;
+        let grad_hidden = matmul(grad_logits.view(), classifier.t());
+        let grad_classifier = matmul(hidden.t(), grad_logits.view());
+        let grad_bias = grad_logits.sum_axis(Axis(0));
+
+        for (k, (head, pass)) in heads.iter_mut().zip(&passes).enumerate() {
+            let grad_output = grad_hidden.slice(s![.., k * hidden_dim..(k + 1) * hidden_dim]);
+            pass.backward(head, features, &neighborhoods, &grad_output.to_owned(), attention);
+        }
+        classifier.scaled_add(-LEARNING_RATE, &grad_classifier);
+        bias.scaled_add(-LEARNING_RATE, &grad_bias);
+    }
+
+    let passes: Vec<HeadPass> = heads
+        .iter()
+        .map(|head| HeadPass::forward(head, features, &neighborhoods, attention))
+        .collect();
+    Ok(NodeEmbeddings::from_array(concatenate(&passes, n, hidden_dim)))
+}
+
+/// Intermediate values of one head's forward pass, kept for backprop
+struct HeadPass {
+    z: Array2<f32>,
+    coefficients: Vec<Vec<f32>>,
+    scores: Vec<Vec<f32>>,
+    output: Array2<f32>,
+}
+
+impl HeadPass {
+    fn forward(
+        head: &GatLayer,
+        features: &Array2<f32>,
+        neighborhoods: &[Vec<usize>],
+        attention: bool,
+    ) -> Self {
+        let z = matmul(features.view(), head.weight.view());
+        let (coefficients, scores) = if attention {
+            neighborhoods
+                .iter()
+                .enumerate()
+                .map(|(i, neighborhood)| head.attend(&z, i, neighborhood))
+                .unzip()
+        } else {
+            (uniform_coefficients(neighborhoods), Vec::new())
+        };
+        let output = aggregate(&z, neighborhoods, &coefficients);
+
+        Self {
+            z,
+            coefficients,
+            scores,
+            output,
+        }
+    }
+
+    /// Apply one gradient step to `head` given the gradient of its output
+    fn backward(
+        &self,
+        head: &mut GatLayer,
+        features: &Array2<f32>,
+        neighborhoods: &[Vec<usize>],
+        grad_output: &Array2<f32>,
+        attention: bool,
+    ) {
+        let d = self.z.ncols();
+        let (a_src, a_dst) = head.attention_halves();
+        let mut grad_z = Array2::<f32>::zeros(self.z.raw_dim());
+        let mut grad_src = Array1::<f32>::zeros(d);
+        let mut grad_dst = Array1::<f32>::zeros(d);
+
+        for (i, neighborhood) in neighborhoods.iter().enumerate() {
+            let grad_out = grad_output.row(i);
+            let alpha = &self.coefficients[i];
+
+            for (&j, &coefficient) in neighborhood.iter().zip(alpha) {
+                grad_z.row_mut(j).scaled_add(coefficient, &grad_out);
+            }
+            if !attention {
+                continue;
+            }
+
+            // Through the softmax and LeakyReLU to the raw scores
+            let grad_alpha: Vec<f32> = neighborhood
+                .iter()
+                .map(|&j| dot(grad_out, self.z.row(j)))
+                .collect();
+            let weighted: f32 = alpha.iter().zip(&grad_alpha).map(|(a, g)| a * g).sum();
+
+            for (idx, &j) in neighborhood.iter().enumerate() {
+                let slope = if self.scores[i][idx] > 0.0 {
+                    1.0
+                } else {
+                    head.leaky_relu_alpha
+                };
+                let grad_score = alpha[idx] * (grad_alpha[idx] - weighted) * slope;
+
+                grad_src.scaled_add(grad_score, &self.z.row(i));
+                grad_dst.scaled_add(grad_score, &self.z.row(j));
+                grad_z.row_mut(i).scaled_add(grad_score, &a_src);
+                grad_z.row_mut(j).scaled_add(grad_score, &a_dst);
+            }
+        }
+
+        let grad_weight = matmul(features.t(), grad_z.view());
+        head.weight.scaled_add(-LEARNING_RATE, &grad_weight);
+        if attention {
+            let mut a = head.attention_weight_matrix.column_mut(0);
+            a.slice_mut(s![..d]).scaled_add(-LEARNING_RATE, &grad_src);
+            a.slice_mut(s![d..]).scaled_add(-LEARNING_RATE, &grad_dst);
+        }
+    }
+}
+
+/// Each node's neighborhood: itself, then distinct neighbors by ID
+///
+/// Row `i` of `features` belongs to `node_id(i)`. Validates that
+/// `features` has one row per node and `input_dim` columns.
+fn neighborhoods(graph: &Graph, features: &Array2<f32>, input_dim: usize) -> Result<Vec<Vec<usize>>> {
+    let n = graph.node_count();
+    if features.nrows() != n || features.ncols() != input_dim {
+        return Err(MlError::FeatureError(format!(
+            "Feature matrix shape mismatch: expected {}x{}, got {}x{}",
+            n,
+            input_dim,
+            features.nrows(),
+            features.ncols()
+        )));
+    }
+
+    (0..n)
+        .map(|i| {
+            let mut neighbors = graph
+                .neighbors(node_id(i))?
+                .into_iter()
+                .map(|j| {
+                    node_index(j).filter(|&row| row < n).ok_or_else(|| {
+                        MlError::FeatureError(format!("Node {} has no feature row", j))
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            neighbors.retain(|&j| j != i);
+            neighbors.sort_unstable();
+            neighbors.dedup();
+
+            let mut neighborhood = Vec::with_capacity(neighbors.len() + 1);
+            neighborhood.push(i);
+            neighborhood.extend(neighbors);
+            Ok(neighborhood)
+        })
+        .collect()
+}
+
+/// Weighted sums of the rows of `z` over each neighborhood
+fn aggregate(z: &Array2<f32>, neighborhoods: &[Vec<usize>], coefficients: &[Vec<f32>]) -> Array2<f32> {
+    let mut output = Array2::<f32>::zeros((neighborhoods.len(), z.ncols()));
+    for (i, (neighborhood, alpha)) in neighborhoods.iter().zip(coefficients).enumerate() {
+        let mut row = output.row_mut(i);
+        for (&j, &coefficient) in neighborhood.iter().zip(alpha) {
+            row.scaled_add(coefficient, &z.row(j));
+        }
+    }
+    output
+}
+
+fn uniform_coefficients(neighborhoods: &[Vec<usize>]) -> Vec<Vec<f32>> {
+    neighborhoods
+        .iter()
+        .map(|neighborhood| vec![1.0 / neighborhood.len() as f32; neighborhood.len()])
+        .collect()
+}
+
+/// Head outputs side by side, one `hidden_dim` block per head
+fn concatenate(passes: &[HeadPass], n: usize, hidden_dim: usize) -> Array2<f32> {
+    let mut hidden = Array2::<f32>::zeros((n, hidden_dim * passes.len()));
+    for (k, pass) in passes.iter().enumerate() {
+        hidden
+            .slice_mut(s![.., k * hidden_dim..(k + 1) * hidden_dim])
+            .assign(&pass.output);
+    }
+    hidden
+}
+
+/// Matrix product without a BLAS backend
+pub(crate) fn matmul(a: ArrayView2<f32>, b: ArrayView2<f32>) -> Array2<f32> {
+    let mut product = Array2::<f32>::zeros((a.nrows(), b.ncols()));
+    for (a_row, mut out_row) in a.rows().into_iter().zip(product.rows_mut()) {
+        for (&x, b_row) in a_row.iter().zip(b.rows()) {
+            if x != 0.0 {
+                out_row.scaled_add(x, &b_row);
+            }
+        }
+    }
+    product
+}
+
+fn dot(a: ArrayView1<f32>, b: ArrayView1<f32>) -> f32 {
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+}
+
+fn glorot<R: Rng>(rows: usize, cols: usize, rng: &mut R) -> Array2<f32> {
+    let limit = (6.0 / (rows + cols) as f32).sqrt();
+    Array2::from_shape_fn((rows, cols), |_| rng.gen_range(-limit..limit))
+}
+
+fn leaky_relu(x: f32, alpha: f32) -> f32 {
+    if x > 0.0 {
+        x
+    } else {
+        alpha * x
+    }
+}
+
+fn softmax(values: &[f32]) -> Vec<f32> {
+    let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let exp: Vec<f32> = values.iter().map(|&v| (v - max).exp()).collect();
+    let sum: f32 = exp.iter().sum();
+    exp.into_iter().map(|e| e / sum).collect()
+}
+
+#[cfg(all(test, not(feature = "uuid-ids")))]
+mod tests {
+    use super::*;
+
+    /// Two classes whose members also link to distractor nodes with noisy
+    /// features; distractors form a third, flagged class
+    fn create_heterogeneous_graph() -> (Graph, Array2<f32>, Vec<usize>) {
+        let mut rng = StdRng::seed_from_u64(7);
+        let class_nodes = 20;
+        let distractors = 20;
+        let n = class_nodes + distractors;
+
+        let mut graph = Graph::new();
+        for i in 0..n {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+
+        let mut features = Array2::<f32>::zeros((n, 3));
+        let mut labels = vec![0; n];
+        for i in 0..class_nodes {
+            labels[i] = i % 2;
+            features[[i, i % 2]] = 1.0;
+        }
+        for i in class_nodes..n {
+            labels[i] = 2;
+            features[[i, 0]] = rng.gen_range(-3.0..3.0);
+            features[[i, 1]] = rng.gen_range(-3.0..3.0);
+            features[[i, 2]] = 1.0;
+        }
+
+        for i in 0..class_nodes {
+            // One same-class neighbor, three distractors
+            graph.add_edge(i, (i + 2) % class_nodes, 1.0).unwrap();
+            for k in 0..3 {
+                let distractor = class_nodes + (i * 3 + k) % distractors;
+                graph.add_edge(i, distractor, 1.0).unwrap();
+            }
+        }
+
+        (graph, features, labels)
+    }
+
+    /// Accuracy of nearest-class-centroid prediction on embeddings
+    fn centroid_accuracy(embeddings: &NodeEmbeddings, labels: &[usize]) -> f32 {
+        let classes = labels.iter().max().unwrap() + 1;
+        let dim = embeddings.dimension();
+        let mut centroids = Array2::<f32>::zeros((classes, dim));
+        let mut counts = vec![0.0; classes];
+        for (i, &label) in labels.iter().enumerate() {
+            centroids.row_mut(label).scaled_add(1.0, &embeddings.get_embedding(i).unwrap());
+            counts[label] += 1.0;
+        }
+        for (c, count) in counts.iter().enumerate() {
+            centroids.row_mut(c).mapv_inplace(|x| x / count);
+        }
+
+        let correct = labels
+            .iter()
+            .enumerate()
+            .filter(|&(i, &label)| {
+                let embedding = embeddings.get_embedding(i).unwrap();
+                let predicted = (0..classes)
+                    .min_by(|&a, &b| {
+                        let da = (&centroids.row(a) - &embedding).mapv(|x| x * x).sum();
+                        let db = (&centroids.row(b) - &embedding).mapv(|x| x * x).sum();
+                        da.partial_cmp(&db).unwrap()
+                    })
+                    .unwrap();
+                predicted == label
+            })
+            .count();
+        correct as f32 / labels.len() as f32
+    }
+
+    #[test]
+    fn test_attention_coefficients_sum_to_one() {
+        let (graph, features, _) = create_heterogeneous_graph();
+        let layer = GatLayer::new(3, 4);
+
+        for node in [0, 5, 25] {
+            let coefficients = layer.attention_coefficients(&graph, &features, node).unwrap();
+            assert_eq!(coefficients[0].0, node);
+            let sum: f32 = coefficients.iter().map(|&(_, c)| c).sum();
+            assert!((sum - 1.0).abs() < 1e-5);
+        }
+        assert!(layer.attention_coefficients(&graph, &features, 99).is_err());
+    }
+
+    #[test]
+    fn test_forward_dimensions() {
+        let (graph, features, _) = create_heterogeneous_graph();
+        let gat = GatLayer::new(3, 4).with_leaky_relu_alpha(0.1);
+        let gcn = GcnLayer::new(3, 5);
+
+        assert_eq!(gat.forward(&graph, &features).unwrap().dim(), (40, 4));
+        assert_eq!(gcn.forward(&graph, &features).unwrap().dim(), (40, 5));
+        assert!(gat.forward(&graph, &Array2::zeros((40, 2))).is_err());
+    }
+
+    #[test]
+    fn test_train_gat_output_dimension() {
+        let (graph, features, labels) = create_heterogeneous_graph();
+        let embeddings = train_gat(&graph, &features, &labels, 4, 3, 5).unwrap();
+        assert_eq!(embeddings.node_count(), 40);
+        assert_eq!(embeddings.dimension(), 12);
+
+        assert!(train_gat(&graph, &features, &labels[1..], 4, 3, 5).is_err());
+        assert!(train_gat(&Graph::new(), &Array2::zeros((0, 3)), &[], 4, 3, 5).is_err());
+    }
+
+    #[test]
+    fn test_gat_beats_gcn_on_heterogeneous_neighborhoods() {
+        let (graph, features, labels) = create_heterogeneous_graph();
+
+        let gat = train_gat(&graph, &features, &labels, 8, 2, 300).unwrap();
+        let gcn = train_gcn(&graph, &features, &labels, 16, 300).unwrap();
+
+        let gat_accuracy = centroid_accuracy(&gat, &labels);
+        let gcn_accuracy = centroid_accuracy(&gcn, &labels);
+        assert!(gat_accuracy > gcn_accuracy);
+    }
+}
@@ -0,0 +1,121 @@
+//! Vector kernels for embedding similarity and distance
+//!
+//! The kernels accumulate `LANES` independent partial sums over fixed-size
+//! chunks, which the compiler lowers to SIMD registers (eight f32 lanes
+//! with AVX, two four-lane registers with SSE or NEON) without relying on
+//! the unstable `std::simd`. The remainder that does not fill a chunk is
+//! summed serially. Reassociating the sum makes results differ from a
+//! serial loop in the last bits.
+//!
+//! The `scalar-kernels` feature replaces them with the serial loops in
+//! `scalar`, e.g. to compare against them in benchmarks.
+
+/// Width of the chunks the kernels accumulate over
+const LANES: usize = 8;
+
+#[cfg(not(feature = "scalar-kernels"))]
+pub(crate) use chunked::{dot, squared_distance, squared_norm};
+#[cfg(feature = "scalar-kernels")]
+pub(crate) use scalar::{dot, squared_distance, squared_norm};
+
+/// Euclidean length of `a`
+pub(crate) fn l2_norm(a: &[f32]) -> f32 {
+    squared_norm(a).sqrt()
+}
+
+#[cfg_attr(feature = "scalar-kernels", allow(dead_code))]
+mod chunked {
+    use super::LANES;
+
+    /// Sum `term(a[i], b[i])` over every index, in `LANES` interleaved
+    /// partial sums
+    #[inline(always)]
+    fn lane_sum(a: &[f32], b: &[f32], term: impl Fn(f32, f32) -> f32) -> f32 {
+        assert_eq!(a.len(), b.len(), "vector lengths differ");
+        let (a_chunks, b_chunks) = (a.chunks_exact(LANES), b.chunks_exact(LANES));
+        let tail: f32 =
+            a_chunks.remainder().iter().zip(b_chunks.remainder()).map(|(&x, &y)| term(x, y)).sum();
+
+        let mut lanes = [0.0f32; LANES];
+        for (x, y) in a_chunks.zip(b_chunks) {
+            for ((sum, &x), &y) in lanes.iter_mut().zip(x).zip(y) {
+                *sum += term(x, y);
+            }
+        }
+
+        // Pairwise reduction of the lanes
+        let mut width = LANES;
+        while width > 1 {
+            width /= 2;
+            for lane in 0..width {
+                lanes[lane] += lanes[lane + width];
+            }
+        }
+        lanes[0] + tail
+    }
+
+    pub(crate) fn dot(a: &[f32], b: &[f32]) -> f32 {
+        lane_sum(a, b, |x, y| x * y)
+    }
+
+    pub(crate) fn squared_norm(a: &[f32]) -> f32 {
+        lane_sum(a, a, |x, _| x * x)
+    }
+
+    pub(crate) fn squared_distance(a: &[f32], b: &[f32]) -> f32 {
+        lane_sum(a, b, |x, y| (x - y) * (x - y))
+    }
+}
+
+/// Serial reference implementations
+#[cfg_attr(not(any(test, feature = "scalar-kernels")), allow(dead_code))]
+mod scalar {
+    pub(crate) fn dot(a: &[f32], b: &[f32]) -> f32 {
+        assert_eq!(a.len(), b.len(), "vector lengths differ");
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    pub(crate) fn squared_norm(a: &[f32]) -> f32 {
+        a.iter().map(|x| x * x).sum()
+    }
+
+    pub(crate) fn squared_distance(a: &[f32], b: &[f32]) -> f32 {
+        assert_eq!(a.len(), b.len(), "vector lengths differ");
+        a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+    }
+}
+
+#[cfg(all(test, not(feature = "uuid-ids")))]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn assert_close(actual: f32, expected: f32, magnitude: f32) {
+        let tolerance = 1e-5 * magnitude.max(1.0);
+        assert!((actual - expected).abs() <= tolerance, "{} vs {}", actual, expected);
+    }
+
+    #[test]
+    fn test_kernels_match_scalar() {
+        let mut rng = StdRng::seed_from_u64(5);
+        for len in [0, 1, 3, 7, 8, 9, 15, 16, 17, 63, 100, 255, 256, 257, 1001] {
+            let a: Vec<f32> = (0..len).map(|_| rng.gen_range(-1.0..1.0)).collect();
+            let b: Vec<f32> = (0..len).map(|_| rng.gen_range(-1.0..1.0)).collect();
+            let products: f32 = a.iter().zip(&b).map(|(x, y)| (x * y).abs()).sum();
+
+            assert_close(chunked::dot(&a, &b), scalar::dot(&a, &b), products);
+            assert_close(chunked::squared_norm(&a), scalar::squared_norm(&a), len as f32);
+            assert_close(
+                chunked::squared_distance(&a, &b),
+                scalar::squared_distance(&a, &b),
+                4.0 * len as f32,
+            );
+        }
+
+        let ones = vec![1.0; 19];
+        assert_eq!(dot(&ones, &ones), 19.0);
+        assert_eq!(l2_norm(&[3.0, 4.0]), 5.0);
+        assert_eq!(squared_distance(&[1.0; 10], &[3.0; 10]), 40.0);
+    }
+}
@@ -25,10 +25,18 @@ pub mod algorithm_selector;
 pub mod anomaly;
 pub mod embeddings;
 pub mod error;
+pub mod evaluation;
 pub mod features;
+pub mod gnn;
+mod kernels;
 
 // Re-exports
 pub use algorithm_selector::AlgorithmSelector;
 pub use anomaly::{Anomaly, AnomalyDetector};
-pub use embeddings::NodeEmbeddings;
+pub use embeddings::{
+    concatenate_embeddings, embeddings_from_node_features, walks_to_corpus_file, Node2VecTrainer,
+    NodeEmbeddings, TrainingReport,
+};
 pub use error::{MlError, Result};
+pub use evaluation::{evaluate_anomalies, evaluate_communities, AnomalyScores, CommunityScores};
+pub use gnn::{train_gat, train_gcn, GatLayer, GcnLayer, MessagePassing};
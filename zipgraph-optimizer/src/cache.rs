@@ -1,118 +1,615 @@
 //! Intelligent caching for query results
+//!
+//! The cache can be split into partitions by `QueryKind`, each with its own
+//! entry budget and LRU eviction, so that a burst of cheap queries cannot
+//! evict expensive results of another kind. Kinds without a dedicated
+//! partition share the default partition.
+//!
+//! Every entry records the generation token of the graph it was computed
+//! from (`Graph::generation`). Lookups pass the current token, and entries
+//! from an older generation are dropped instead of returned, so results
+//! never outlive a mutation of the graph.
+//!
+//! Entries are keyed by `Query::fingerprint` but also keep the query they
+//! were computed for, and a lookup only returns a result whose query equals
+//! the one asked for. Two distinct queries sharing a fingerprint therefore
+//! cost a miss, counted in `CacheStats::collisions`, and never a wrong
+//! answer. `entries` and `evictions_log` expose the contents and recent
+//! evictions for debugging.
 
-use crate::query::{Query, QueryResult};
+use crate::query::{Query, QueryKind, QueryResult, SubgraphEdge, SubgraphNode};
+use dashmap::mapref::entry::Entry;
 use dashmap::DashMap;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::mem::size_of;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use zipgraph_core::NodeId;
 
 /// Cached query result with metadata
 #[derive(Debug, Clone)]
 struct CachedResult {
+    query: Query,
     result: QueryResult,
+    query_type: String,
     execution_time: Duration,
     hit_count: usize,
+    /// Generation of the graph the result was computed from
+    generation: u64,
+    inserted_at: Instant,
     last_access: Instant,
 }
 
+/// Hit/miss counters for a single query type
+#[derive(Debug, Clone, Copy, Default)]
+struct LookupCounters {
+    hits: usize,
+    misses: usize,
+    negative_hits: usize,
+}
+
+/// Default time-to-live for negative results such as `QueryResult::NoPath`
+pub const DEFAULT_NEGATIVE_TTL: Duration = Duration::from_secs(60);
+
+/// Name reported for the partition holding kinds without their own budget
+pub const DEFAULT_PARTITION: &str = "default";
+
+/// Evictions kept for `QueryCache::evictions_log`
+pub const EVICTION_LOG_CAPACITY: usize = 256;
+
+/// Partitioning configuration for a `QueryCache`
+#[derive(Debug, Clone)]
+pub struct QueryCacheConfig {
+    /// Maximum entries in the default partition
+    pub default_size: usize,
+    /// Dedicated partitions as `(kind, max entries)`
+    pub partitions: Vec<(QueryKind, usize)>,
+}
+
+impl QueryCacheConfig {
+    /// Create a config with a single default partition
+    pub fn new(default_size: usize) -> Self {
+        Self {
+            default_size,
+            partitions: Vec::new(),
+        }
+    }
+
+    /// Give `kind` its own partition holding at most `max_entries` results
+    ///
+    /// `max_entries: 0` turns caching off for `kind`.
+    pub fn with_partition(mut self, kind: QueryKind, max_entries: usize) -> Self {
+        self.partitions.push((kind, max_entries));
+        self
+    }
+}
+
+/// A slice of the cache with its own budget and eviction
+struct Partition {
+    name: String,
+    entries: DashMap<u64, CachedResult>,
+    max_size: usize,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl Partition {
+    fn new(name: impl Into<String>, max_size: usize) -> Self {
+        Self {
+            name: name.into(),
+            entries: DashMap::new(),
+            max_size,
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Evict the least recently used entry and return it
+    fn evict_lru(&self) -> Option<CachedResult> {
+        let mut oldest_key = None;
+        let mut oldest_time = Instant::now();
+
+        for entry in self.entries.iter() {
+            if entry.value().last_access < oldest_time {
+                oldest_time = entry.value().last_access;
+                oldest_key = Some(*entry.key());
+            }
+        }
+
+        oldest_key.and_then(|key| self.entries.remove(&key)).map(|(_, cached)| cached)
+    }
+
+    fn stats(&self) -> PartitionStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+
+        PartitionStats {
+            name: self.name.clone(),
+            max_size: self.max_size,
+            size: self.entries.len(),
+            hits,
+            misses,
+            hit_rate: hit_rate(hits, misses),
+        }
+    }
+}
+
 /// Query cache with adaptive eviction
 pub struct QueryCache {
-    cache: Arc<DashMap<u64, CachedResult>>,
-    max_size: usize,
+    /// Dedicated partitions followed by the default partition
+    partitions: Vec<Partition>,
+    routes: HashMap<QueryKind, usize>,
+    lookups: Arc<DashMap<String, LookupCounters>>,
+    negative_ttl: Duration,
+    fingerprint: fn(&Query) -> u64,
+    collisions: AtomicUsize,
+    evictions: Mutex<VecDeque<EvictionRecord>>,
 }
 
 impl QueryCache {
     /// Create a new cache with specified max size
     pub fn new(max_size: usize) -> Self {
+        Self::with_config(QueryCacheConfig::new(max_size))
+    }
+
+    /// Create a cache partitioned according to `config`
+    pub fn with_config(config: QueryCacheConfig) -> Self {
+        let mut partitions = Vec::with_capacity(config.partitions.len() + 1);
+        let mut routes = HashMap::new();
+
+        for (kind, max_size) in config.partitions {
+            routes.insert(kind, partitions.len());
+            partitions.push(Partition::new(kind.name(), max_size));
+        }
+        partitions.push(Partition::new(DEFAULT_PARTITION, config.default_size));
+
         Self {
-            cache: Arc::new(DashMap::new()),
-            max_size,
+            partitions,
+            routes,
+            lookups: Arc::new(DashMap::new()),
+            negative_ttl: DEFAULT_NEGATIVE_TTL,
+            fingerprint: Query::fingerprint,
+            collisions: AtomicUsize::new(0),
+            evictions: Mutex::new(VecDeque::new()),
         }
     }
 
-    /// Get a cached result
-    pub fn get(&self, query: &Query) -> Option<QueryResult> {
-        let fingerprint = query.fingerprint();
-        
-        self.cache.get_mut(&fingerprint).map(|mut entry| {
-            entry.hit_count += 1;
-            entry.last_access = Instant::now();
-            entry.result.clone()
-        })
+    /// Set how long negative results stay cached
+    ///
+    /// Positive results only expire with the graph generation; negative
+    /// results are additionally dropped once they are older than `ttl`.
+    pub fn with_negative_ttl(mut self, ttl: Duration) -> Self {
+        self.negative_ttl = ttl;
+        self
     }
 
-    /// Insert a result into the cache
-    pub fn insert(&self, query: &Query, result: QueryResult, execution_time: Duration) {
-        let fingerprint = query.fingerprint();
-        
+    /// Key entries with `fingerprint` instead of `Query::fingerprint`
+    #[cfg(all(test, not(feature = "uuid-ids")))]
+    fn with_fingerprint(mut self, fingerprint: fn(&Query) -> u64) -> Self {
+        self.fingerprint = fingerprint;
+        self
+    }
+
+    /// Partition responsible for a query
+    fn partition(&self, query: &Query) -> &Partition {
+        let index = self
+            .routes
+            .get(&query.kind())
+            .copied()
+            .unwrap_or(self.partitions.len() - 1);
+        &self.partitions[index]
+    }
+
+    /// Get a cached result computed at graph generation `generation`
+    ///
+    /// Entries from any other generation are stale and removed. An entry
+    /// for a different query with the same fingerprint is a miss and is
+    /// kept.
+    pub fn get(&self, query: &Query, generation: u64) -> Option<QueryResult> {
+        let fingerprint = (self.fingerprint)(query);
+        let partition = self.partition(query);
+
+        // Check and remove under one shard lock, so a concurrent insert of
+        // a fresh entry is never dropped and a stale one is never served
+        let mut evicted = None;
+        let result = match partition.entries.entry(fingerprint) {
+            Entry::Occupied(mut entry) => {
+                let cached = entry.get();
+                let reason = if cached.generation != generation {
+                    Some(EvictionReason::StaleGeneration)
+                } else if cached.result.is_negative()
+                    && cached.inserted_at.elapsed() >= self.negative_ttl
+                {
+                    Some(EvictionReason::Expired)
+                } else {
+                    None
+                };
+
+                if cached.query != *query {
+                    self.collisions.fetch_add(1, Ordering::Relaxed);
+                    None
+                } else if let Some(reason) = reason {
+                    evicted = Some((entry.remove(), reason));
+                    None
+                } else {
+                    let cached = entry.get_mut();
+                    cached.hit_count += 1;
+                    cached.last_access = Instant::now();
+                    Some(cached.result.clone())
+                }
+            }
+            Entry::Vacant(_) => None,
+        };
+        if let Some((cached, reason)) = evicted {
+            self.log_eviction(partition, cached, reason);
+        }
+
+        let mut counters = self.lookups.entry(query.type_name().to_string()).or_default();
+        match &result {
+            Some(result) => {
+                partition.hits.fetch_add(1, Ordering::Relaxed);
+                counters.hits += 1;
+                if result.is_negative() {
+                    counters.negative_hits += 1;
+                }
+            }
+            None => {
+                partition.misses.fetch_add(1, Ordering::Relaxed);
+                counters.misses += 1;
+            }
+        }
+
+        result
+    }
+
+    /// Whether a live result for `query` at `generation` is cached
+    ///
+    /// Unlike `get` this neither counts as a lookup nor removes stale
+    /// entries.
+    pub fn contains(&self, query: &Query, generation: u64) -> bool {
+        self.partition(query)
+            .entries
+            .get(&(self.fingerprint)(query))
+            .is_some_and(|cached| {
+                let expired = cached.result.is_negative()
+                    && cached.inserted_at.elapsed() >= self.negative_ttl;
+                cached.query == *query && cached.generation == generation && !expired
+            })
+    }
+
+    /// Insert a result computed at graph generation `generation`
+    ///
+    /// An entry for a different query with the same fingerprint is
+    /// replaced.
+    pub fn insert(&self, query: &Query, result: QueryResult, execution_time: Duration, generation: u64) {
+        let fingerprint = (self.fingerprint)(query);
+        let partition = self.partition(query);
+        if partition.max_size == 0 {
+            return;
+        }
+
         // Check if we need to evict
-        if self.cache.len() >= self.max_size {
-            self.evict_lru();
+        if partition.entries.len() >= partition.max_size && !partition.entries.contains_key(&fingerprint) {
+            if let Some(cached) = partition.evict_lru() {
+                self.log_eviction(partition, cached, EvictionReason::Capacity);
+            }
         }
 
         let cached = CachedResult {
+            query: query.clone(),
             result,
+            query_type: query.type_name().to_string(),
             execution_time,
             hit_count: 0,
+            generation,
+            inserted_at: Instant::now(),
             last_access: Instant::now(),
         };
 
-        self.cache.insert(fingerprint, cached);
-    }
-
-    /// Evict least recently used entry
-    fn evict_lru(&self) {
-        let mut oldest_key = None;
-        let mut oldest_time = Instant::now();
-
-        for entry in self.cache.iter() {
-            if entry.value().last_access < oldest_time {
-                oldest_time = entry.value().last_access;
-                oldest_key = Some(*entry.key());
+        if let Some(replaced) = partition.entries.insert(fingerprint, cached) {
+            if replaced.query != *query {
+                self.log_eviction(partition, replaced, EvictionReason::Collision);
             }
         }
+    }
 
-        if let Some(key) = oldest_key {
-            self.cache.remove(&key);
+    fn log_eviction(&self, partition: &Partition, cached: CachedResult, reason: EvictionReason) {
+        let mut evictions = self.evictions.lock();
+        if evictions.len() == EVICTION_LOG_CAPACITY {
+            evictions.pop_front();
         }
+        evictions.push_back(EvictionRecord {
+            kind: cached.query.kind(),
+            query: cached.query,
+            partition: partition.name.clone(),
+            reason,
+            hit_count: cached.hit_count,
+            evicted_at: Instant::now(),
+        });
+    }
+
+    /// Every cached entry, hottest first
+    ///
+    /// Entries are ordered by descending hit count, then most recently
+    /// accessed first. Stale entries are listed until a lookup drops them.
+    pub fn entries(&self) -> Vec<CacheEntryInfo> {
+        let mut entries: Vec<CacheEntryInfo> = self
+            .partitions
+            .iter()
+            .flat_map(|partition| partition.entries.iter())
+            .map(|entry| {
+                let cached = entry.value();
+                CacheEntryInfo {
+                    query: cached.query.clone(),
+                    kind: cached.query.kind(),
+                    hit_count: cached.hit_count,
+                    age: cached.inserted_at.elapsed(),
+                    last_access_age: cached.last_access.elapsed(),
+                    size_bytes: result_size_bytes(&cached.result),
+                    execution_time: cached.execution_time,
+                    generation: cached.generation,
+                }
+            })
+            .collect();
+        entries.sort_by(|a, b| {
+            b.hit_count.cmp(&a.hit_count).then(a.last_access_age.cmp(&b.last_access_age))
+        });
+        entries
+    }
+
+    /// The `last_n` most recent evictions, oldest first
+    ///
+    /// At most `EVICTION_LOG_CAPACITY` evictions are kept. `clear` empties
+    /// the cache without logging its entries.
+    pub fn evictions_log(&self, last_n: usize) -> Vec<EvictionRecord> {
+        let evictions = self.evictions.lock();
+        evictions.iter().skip(evictions.len().saturating_sub(last_n)).cloned().collect()
     }
 
     /// Get cache statistics
+    ///
+    /// `partitions` lists every partition, dedicated ones first and the
+    /// default partition last.
     pub fn stats(&self) -> CacheStats {
-        let mut total_hits = 0;
+        let mut counters = LookupCounters::default();
+        for entry in self.lookups.iter() {
+            counters.hits += entry.value().hits;
+            counters.misses += entry.value().misses;
+            counters.negative_hits += entry.value().negative_hits;
+        }
+
+        let mut size = 0;
         let mut total_time = Duration::default();
+        for partition in &self.partitions {
+            for entry in partition.entries.iter() {
+                size += 1;
+                total_time += entry.value().execution_time;
+            }
+        }
+
+        let mut stats = CacheStats::new(size, total_time, counters);
+        stats.collisions = self.collisions.load(Ordering::Relaxed);
+        stats.partitions = self.partitions.iter().map(Partition::stats).collect();
+        stats
+    }
 
-        for entry in self.cache.iter() {
-            total_hits += entry.value().hit_count;
-            total_time += entry.value().execution_time;
+    /// Get cache statistics broken down by query type
+    ///
+    /// Keys are query variant names such as `"ShortestPath"` or `"Neighbors"`.
+    /// The per-type stats carry no partition breakdown and no collision
+    /// count.
+    pub fn stats_by_query_type(&self) -> HashMap<String, CacheStats> {
+        let mut sizes: HashMap<String, (usize, Duration)> = HashMap::new();
+        for partition in &self.partitions {
+            for entry in partition.entries.iter() {
+                let (size, total_time) = sizes.entry(entry.value().query_type.clone()).or_default();
+                *size += 1;
+                *total_time += entry.value().execution_time;
+            }
         }
 
-        CacheStats {
-            size: self.cache.len(),
-            total_hits,
-            avg_execution_time: if self.cache.len() > 0 {
-                total_time / self.cache.len() as u32
-            } else {
-                Duration::default()
-            },
+        let mut stats = HashMap::new();
+        for entry in self.lookups.iter() {
+            let (size, total_time) = sizes.remove(entry.key()).unwrap_or_default();
+            stats.insert(entry.key().clone(), CacheStats::new(size, total_time, *entry.value()));
+        }
+
+        // Entries inserted without ever being looked up
+        for (query_type, (size, total_time)) in sizes {
+            stats.insert(query_type, CacheStats::new(size, total_time, LookupCounters::default()));
         }
+
+        stats
+    }
+
+    /// Get the hit rate for a single query type, if it has been looked up
+    pub fn hit_rate_for(&self, query_type: &str) -> Option<f64> {
+        self.lookups.get(query_type).and_then(|counters| {
+            let lookups = counters.hits + counters.misses;
+            if lookups > 0 {
+                Some(counters.hits as f64 / lookups as f64)
+            } else {
+                None
+            }
+        })
     }
 
     /// Clear the cache
     pub fn clear(&self) {
-        self.cache.clear();
+        for partition in &self.partitions {
+            partition.entries.clear();
+            partition.hits.store(0, Ordering::Relaxed);
+            partition.misses.store(0, Ordering::Relaxed);
+        }
+        self.lookups.clear();
+        self.collisions.store(0, Ordering::Relaxed);
+        self.evictions.lock().clear();
+    }
+}
+
+/// Approximate memory held by a cached result, heap allocations included
+fn result_size_bytes(result: &QueryResult) -> usize {
+    let heap = match result {
+        QueryResult::Path(nodes) | QueryResult::Neighbors(nodes) => {
+            nodes.len() * size_of::<NodeId>()
+        }
+        QueryResult::Scores(scores) | QueryResult::Distances(scores) => {
+            scores.len() * size_of::<(NodeId, f64)>()
+        }
+        QueryResult::Components(components) => components
+            .iter()
+            .map(|component| size_of::<Vec<NodeId>>() + component.len() * size_of::<NodeId>())
+            .sum(),
+        QueryResult::Properties(properties) => properties
+            .iter()
+            .map(|values| {
+                let entries: usize =
+                    values.keys().map(|key| key.len() + size_of::<(String, f64)>()).sum();
+                size_of::<HashMap<String, f64>>() + entries
+            })
+            .sum(),
+        QueryResult::Label(label) => label.len(),
+        QueryResult::Subgraph(subgraph) => {
+            let nodes: usize = subgraph
+                .nodes
+                .iter()
+                .map(|node| {
+                    let properties: usize = node
+                        .properties
+                        .keys()
+                        .map(|key| key.len() + size_of::<(String, f64)>())
+                        .sum();
+                    size_of::<SubgraphNode>() + node.label.len() + properties
+                })
+                .sum();
+            let edges: usize = subgraph
+                .edges
+                .iter()
+                .map(|edge| size_of::<SubgraphEdge>() + edge.edge_type.len())
+                .sum();
+            nodes + edges
+        }
+        QueryResult::Tree { parents, distances, .. } => {
+            parents.len() * size_of::<(NodeId, NodeId)>()
+                + distances.len() * size_of::<(NodeId, f64)>()
+        }
+        QueryResult::Distance(_)
+        | QueryResult::Value(_)
+        | QueryResult::Stats(_)
+        | QueryResult::NoPath => 0,
+    };
+    size_of::<QueryResult>() + heap
+}
+
+fn hit_rate(hits: usize, misses: usize) -> f64 {
+    let lookups = hits + misses;
+    if lookups > 0 {
+        hits as f64 / lookups as f64
+    } else {
+        0.0
     }
 }
 
+/// Statistics for one cache partition
+#[derive(Debug, Clone)]
+pub struct PartitionStats {
+    pub name: String,
+    pub max_size: usize,
+    pub size: usize,
+    pub hits: usize,
+    pub misses: usize,
+    pub hit_rate: f64,
+}
+
+/// A cached entry as listed by `QueryCache::entries`
+#[derive(Debug, Clone)]
+pub struct CacheEntryInfo {
+    /// Query the result was computed for
+    pub query: Query,
+    pub kind: QueryKind,
+    /// Lookups answered by this entry
+    pub hit_count: usize,
+    /// Time since the entry was inserted
+    pub age: Duration,
+    /// Time since the entry was inserted or last returned
+    pub last_access_age: Duration,
+    /// Approximate memory held by the result
+    pub size_bytes: usize,
+    /// Time it took to compute the result
+    pub execution_time: Duration,
+    /// Graph generation the result was computed from
+    pub generation: u64,
+}
+
+/// Why an entry left the cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EvictionReason {
+    /// Least recently used entry of a full partition
+    Capacity,
+    /// Computed from an older generation of the graph
+    StaleGeneration,
+    /// Negative result older than the negative TTL
+    Expired,
+    /// Replaced by a different query with the same fingerprint
+    Collision,
+}
+
+/// An eviction as listed by `QueryCache::evictions_log`
+#[derive(Debug, Clone)]
+pub struct EvictionRecord {
+    /// Query of the evicted entry
+    pub query: Query,
+    pub kind: QueryKind,
+    /// Partition the entry was evicted from
+    pub partition: String,
+    pub reason: EvictionReason,
+    /// Lookups the entry answered before it was evicted
+    pub hit_count: usize,
+    pub evicted_at: Instant,
+}
+
 #[derive(Debug, Clone)]
 pub struct CacheStats {
     pub size: usize,
     pub total_hits: usize,
+    pub total_misses: usize,
+    /// Hits served from cached negative results (e.g. `NoPath`)
+    pub negative_hits: usize,
+    pub hit_rate: f64,
     pub avg_execution_time: Duration,
+    /// Lookups that found an entry for a different query with the same
+    /// fingerprint
+    pub collisions: usize,
+    pub partitions: Vec<PartitionStats>,
+}
+
+impl CacheStats {
+    fn new(size: usize, total_time: Duration, counters: LookupCounters) -> Self {
+        Self {
+            size,
+            total_hits: counters.hits,
+            total_misses: counters.misses,
+            negative_hits: counters.negative_hits,
+            hit_rate: hit_rate(counters.hits, counters.misses),
+            avg_execution_time: if size > 0 {
+                total_time / size as u32
+            } else {
+                Duration::default()
+            },
+            collisions: 0,
+            partitions: Vec::new(),
+        }
+    }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(feature = "uuid-ids")))]
 mod tests {
     use super::*;
+    use zipgraph_core::PathMetric;
 
     #[test]
     fn test_cache_creation() {
@@ -127,25 +624,226 @@ mod tests {
         let query = Query::Neighbors { node: 1 };
         let result = QueryResult::Neighbors(vec![2, 3, 4]);
 
-        cache.insert(&query, result.clone(), Duration::from_millis(10));
-        
-        let cached = cache.get(&query);
+        cache.insert(&query, result.clone(), Duration::from_millis(10), 0);
+
+        let cached = cache.get(&query, 0);
         assert!(cached.is_some());
     }
 
     #[test]
     fn test_cache_eviction() {
         let cache = QueryCache::new(2);
-        
+
         let q1 = Query::Neighbors { node: 1 };
         let q2 = Query::Neighbors { node: 2 };
         let q3 = Query::Neighbors { node: 3 };
 
-        cache.insert(&q1, QueryResult::Neighbors(vec![]), Duration::from_millis(1));
-        cache.insert(&q2, QueryResult::Neighbors(vec![]), Duration::from_millis(1));
-        cache.insert(&q3, QueryResult::Neighbors(vec![]), Duration::from_millis(1));
+        cache.insert(&q1, QueryResult::Neighbors(vec![]), Duration::from_millis(1), 0);
+        cache.insert(&q2, QueryResult::Neighbors(vec![]), Duration::from_millis(1), 0);
+        cache.insert(&q3, QueryResult::Neighbors(vec![]), Duration::from_millis(1), 0);
 
         let stats = cache.stats();
         assert_eq!(stats.size, 2); // Should have evicted one
     }
+
+    /// Look up a query, inserting an empty result on a miss
+    fn lookup(cache: &QueryCache, query: &Query) {
+        if cache.get(query, 0).is_none() {
+            cache.insert(query, QueryResult::Path(vec![]), Duration::from_millis(1), 0);
+        }
+    }
+
+    #[test]
+    fn test_stats_by_query_type() {
+        let cache = QueryCache::new(100);
+        let sp_a = Query::ShortestPath { start: 0, goal: 1, metric: PathMetric::Weight };
+        let sp_b = Query::ShortestPath { start: 0, goal: 2, metric: PathMetric::Weight };
+        let neighbors = Query::Neighbors { node: 0 };
+
+        // 5 ShortestPath lookups: 2 misses followed by 3 hits
+        lookup(&cache, &sp_a);
+        lookup(&cache, &sp_a);
+        lookup(&cache, &sp_a);
+        lookup(&cache, &sp_b);
+        lookup(&cache, &sp_b);
+
+        // 2 Neighbors lookups: 1 miss, 1 hit
+        lookup(&cache, &neighbors);
+        lookup(&cache, &neighbors);
+
+        let by_type = cache.stats_by_query_type();
+        assert!((by_type["ShortestPath"].hit_rate - 0.6).abs() < 1e-9);
+        assert!((by_type["Neighbors"].hit_rate - 0.5).abs() < 1e-9);
+        assert_eq!(by_type["ShortestPath"].size, 2);
+        assert_eq!(by_type["Neighbors"].size, 1);
+
+        assert!((cache.hit_rate_for("ShortestPath").unwrap() - 0.6).abs() < 1e-9);
+        assert!(cache.hit_rate_for("PageRank").is_none());
+
+        let total = cache.stats();
+        assert_eq!(total.total_hits, 4);
+        assert_eq!(total.total_misses, 3);
+    }
+
+    #[test]
+    fn test_negative_result_expires() {
+        let cache = QueryCache::new(100).with_negative_ttl(Duration::from_millis(20));
+        let no_path = Query::ShortestPath { start: 0, goal: 1, metric: PathMetric::Weight };
+        let path = Query::ShortestPath { start: 0, goal: 2, metric: PathMetric::Weight };
+
+        cache.insert(&no_path, QueryResult::NoPath, Duration::from_millis(1), 0);
+        cache.insert(&path, QueryResult::Path(vec![0, 2]), Duration::from_millis(1), 0);
+
+        assert!(matches!(cache.get(&no_path, 0), Some(QueryResult::NoPath)));
+        assert_eq!(cache.stats().negative_hits, 1);
+
+        std::thread::sleep(Duration::from_millis(30));
+
+        // Negative entries expire, positive entries do not
+        assert!(cache.get(&no_path, 0).is_none());
+        assert!(cache.get(&path, 0).is_some());
+        assert_eq!(cache.stats().size, 1);
+    }
+
+    #[test]
+    fn test_partition_isolates_eviction() {
+        let config = QueryCacheConfig::new(10)
+            .with_partition(QueryKind::Neighbors, 2)
+            .with_partition(QueryKind::PageRank, 2);
+        let cache = QueryCache::with_config(config);
+
+        let pagerank = Query::PageRank { iterations: 20, top_k: None };
+        cache.insert(&pagerank, QueryResult::Scores(vec![(0, 1.0)]), Duration::from_millis(50), 0);
+
+        // Overfill the Neighbors partition
+        for node in 0..10 {
+            let query = Query::Neighbors { node };
+            cache.insert(&query, QueryResult::Neighbors(vec![]), Duration::from_millis(1), 0);
+        }
+
+        assert!(cache.get(&pagerank, 0).is_some());
+
+        let stats = cache.stats();
+        assert_eq!(stats.size, 3);
+        let neighbors = stats.partitions.iter().find(|p| p.name == "Neighbors").unwrap();
+        assert_eq!(neighbors.size, 2);
+        let pagerank = stats.partitions.iter().find(|p| p.name == "PageRank").unwrap();
+        assert_eq!(pagerank.size, 1);
+        assert_eq!(pagerank.hits, 1);
+    }
+
+    #[test]
+    fn test_zero_size_partition_caches_nothing() {
+        let config = QueryCacheConfig::new(0).with_partition(QueryKind::PageRank, 0);
+        let cache = QueryCache::with_config(config);
+
+        let pagerank = Query::PageRank { iterations: 20, top_k: None };
+        let neighbors = Query::Neighbors { node: 0 };
+        cache.insert(&pagerank, QueryResult::Scores(vec![(0, 1.0)]), Duration::from_millis(5), 0);
+        cache.insert(&neighbors, QueryResult::Neighbors(vec![1]), Duration::from_millis(1), 0);
+
+        assert!(cache.get(&pagerank, 0).is_none());
+        assert!(!cache.contains(&neighbors, 0));
+        assert_eq!(cache.stats().size, 0);
+        assert!(cache.evictions_log(10).is_empty());
+    }
+
+    #[test]
+    fn test_unlisted_kinds_use_default_partition() {
+        let config = QueryCacheConfig::new(1).with_partition(QueryKind::PageRank, 5);
+        let cache = QueryCache::with_config(config);
+
+        let q1 = Query::ShortestPath { start: 0, goal: 1, metric: PathMetric::Weight };
+        let q2 = Query::Neighbors { node: 0 };
+        cache.insert(&q1, QueryResult::Path(vec![0, 1]), Duration::from_millis(1), 0);
+        cache.insert(&q2, QueryResult::Neighbors(vec![1]), Duration::from_millis(1), 0);
+
+        // Both kinds share the single-entry default partition
+        assert!(cache.get(&q1, 0).is_none());
+        assert!(cache.get(&q2, 0).is_some());
+
+        let stats = cache.stats();
+        let default = stats.partitions.last().unwrap();
+        assert_eq!(default.name, DEFAULT_PARTITION);
+        assert_eq!(default.size, 1);
+        assert_eq!(default.hits, 1);
+        assert_eq!(default.misses, 1);
+    }
+
+    #[test]
+    fn test_stale_generation_is_dropped() {
+        let cache = QueryCache::new(100);
+        let query = Query::GraphStats;
+        cache.insert(&query, QueryResult::Value(1.0), Duration::from_millis(1), 1);
+
+        assert!(cache.get(&query, 1).is_some());
+        assert!(cache.get(&query, 2).is_none());
+        assert!(cache.get(&query, 1).is_none());
+        assert_eq!(cache.stats().size, 0);
+    }
+    #[test]
+    fn test_fingerprint_collision_is_a_miss() {
+        let cache = QueryCache::new(100).with_fingerprint(|_| 7);
+        let a = Query::Neighbors { node: 1 };
+        let b = Query::Neighbors { node: 2 };
+
+        cache.insert(&a, QueryResult::Neighbors(vec![2]), Duration::from_millis(1), 0);
+        assert!(cache.get(&b, 0).is_none());
+        assert!(!cache.contains(&b, 0));
+        assert_eq!(cache.get(&a, 0), Some(QueryResult::Neighbors(vec![2])));
+
+        cache.insert(&b, QueryResult::Neighbors(vec![3]), Duration::from_millis(1), 0);
+        assert!(cache.get(&a, 0).is_none());
+        assert_eq!(cache.get(&b, 0), Some(QueryResult::Neighbors(vec![3])));
+
+        let stats = cache.stats();
+        assert_eq!((stats.collisions, stats.total_hits, stats.total_misses), (2, 2, 2));
+        let evictions = cache.evictions_log(10);
+        assert_eq!(evictions.len(), 1);
+        assert_eq!(evictions[0].query, a);
+        assert_eq!(evictions[0].reason, EvictionReason::Collision);
+        assert_eq!(evictions[0].hit_count, 1);
+    }
+
+    #[test]
+    fn test_entries_and_evictions_log() {
+        let cache = QueryCache::new(2);
+        let path = Query::ShortestPath { start: 0, goal: 1, metric: PathMetric::Weight };
+        let neighbors = Query::Neighbors { node: 0 };
+        cache.insert(&path, QueryResult::Path(vec![0, 1]), Duration::from_millis(5), 0);
+        cache.insert(&neighbors, QueryResult::Neighbors(vec![1; 100]), Duration::from_millis(1), 0);
+        cache.get(&neighbors, 0);
+        cache.get(&neighbors, 0);
+
+        let entries = cache.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].query.clone(), entries[0].hit_count), (neighbors.clone(), 2));
+        assert_eq!(entries[0].kind, QueryKind::Neighbors);
+        assert_eq!(entries[1].execution_time, Duration::from_millis(5));
+        assert!(entries[0].size_bytes > entries[1].size_bytes);
+        assert!(entries[1].last_access_age >= entries[0].last_access_age);
+
+        // The unused path is evicted for space, the stats result when stale
+        let stats = Query::GraphStats;
+        cache.insert(&stats, QueryResult::Value(1.0), Duration::from_millis(1), 0);
+        cache.get(&stats, 1);
+        let reasons: Vec<(Query, EvictionReason)> = cache
+            .evictions_log(10)
+            .into_iter()
+            .map(|eviction| (eviction.query, eviction.reason))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![(path, EvictionReason::Capacity), (stats, EvictionReason::StaleGeneration)]
+        );
+        assert_eq!(cache.evictions_log(1)[0].reason, EvictionReason::StaleGeneration);
+
+        for node in 1..=EVICTION_LOG_CAPACITY + 10 {
+            let query = Query::Neighbors { node };
+            cache.insert(&query, QueryResult::Neighbors(vec![]), Duration::from_millis(1), 0);
+        }
+        assert_eq!(cache.evictions_log(usize::MAX).len(), EVICTION_LOG_CAPACITY);
+        cache.clear();
+        assert!(cache.evictions_log(10).is_empty() && cache.entries().is_empty());
+    }
 }
@@ -0,0 +1,238 @@
+//! Component index for answering cross-component path queries instantly
+//!
+//! Nodes in different (weakly) connected components have no path between
+//! them, so the optimizer can answer such shortest-path queries with
+//! `QueryResult::NoPath` without any traversal. The index is a union-find
+//! forest built from `algorithms::connected_components`; edge additions
+//! can be folded in incrementally, and any other mutation makes the index
+//! stale via the graph's generation token.
+
+use crate::error::Result;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use zipgraph_core::{algorithms, Graph, NodeId};
+
+/// Union-find over a graph's nodes
+///
+/// The representative of a component is always its smallest node ID.
+#[derive(Debug, Clone)]
+pub struct ComponentIndex {
+    parent: HashMap<NodeId, NodeId>,
+    component_count: usize,
+    generation: u64,
+    /// Graph sizes and `Graph::structural_hash` at `generation`, used to
+    /// tell whether a reported edge is the only change since then
+    node_count: usize,
+    edge_count: usize,
+    structural_hash: u64,
+}
+
+impl ComponentIndex {
+    /// Build the index from the connected components of `graph`
+    pub fn build(graph: &Graph) -> Result<Self> {
+        let components = algorithms::connected_components(graph)?;
+
+        let mut parent = HashMap::with_capacity(graph.node_count());
+        for component in &components {
+            let root = component[0];
+            for &node in component {
+                parent.insert(node, root);
+            }
+        }
+
+        Ok(Self {
+            parent,
+            component_count: components.len(),
+            generation: graph.generation(),
+            node_count: graph.node_count(),
+            edge_count: graph.edge_count(),
+            structural_hash: graph.structural_hash(),
+        })
+    }
+
+    /// Generation token of the graph the index describes
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Whether the index still describes `graph`
+    pub fn is_current(&self, graph: &Graph) -> bool {
+        self.generation == graph.generation()
+    }
+
+    /// Number of components
+    pub fn component_count(&self) -> usize {
+        self.component_count
+    }
+
+    /// Representative of the component containing `node`, if indexed
+    pub fn component_of(&self, node: NodeId) -> Option<NodeId> {
+        let mut current = *self.parent.get(&node)?;
+        loop {
+            let next = self.parent[&current];
+            if next == current {
+                return Some(current);
+            }
+            current = next;
+        }
+    }
+
+    /// Whether `a` and `b` are indexed and in different components
+    pub fn are_disconnected(&self, a: NodeId, b: NodeId) -> bool {
+        match (self.component_of(a), self.component_of(b)) {
+            (Some(a), Some(b)) => a != b,
+            _ => false,
+        }
+    }
+
+    /// Fold in the edge `from -> to` just added to `graph`
+    ///
+    /// Must be called right after the corresponding `Graph::add_edge` (and
+    /// `add_node` for new endpoints). Returns `false`, leaving the index
+    /// stale and untouched, if `graph` also changed in some other way since
+    /// the index was last current; the caller should then rebuild it.
+    pub fn record_edge(&mut self, graph: &Graph, from: NodeId, to: NodeId) -> bool {
+        let mut new_nodes: Vec<NodeId> =
+            [from, to].into_iter().filter(|node| !self.parent.contains_key(node)).collect();
+        new_nodes.dedup();
+        // The counts rule out most other changes cheaply; the hash also
+        // catches ones that keep them, such as an edge removed and another
+        // added
+        if graph.edge_count() != self.edge_count + 1
+            || graph.node_count() != self.node_count + new_nodes.len()
+            || graph.structural_hash_without(&new_nodes, &[(from, to)]) != self.structural_hash
+        {
+            return false;
+        }
+
+        for node in [from, to] {
+            if let Entry::Vacant(entry) = self.parent.entry(node) {
+                entry.insert(node);
+                self.component_count += 1;
+            }
+        }
+
+        let root_from = self.find(from);
+        let root_to = self.find(to);
+        if root_from != root_to {
+            let (root, child) = if root_from < root_to {
+                (root_from, root_to)
+            } else {
+                (root_to, root_from)
+            };
+            self.parent.insert(child, root);
+            self.component_count -= 1;
+        }
+
+        self.generation = graph.generation();
+        self.node_count = graph.node_count();
+        self.edge_count = graph.edge_count();
+        self.structural_hash = graph.structural_hash();
+        true
+    }
+
+    /// Find with path compression
+    fn find(&mut self, node: NodeId) -> NodeId {
+        let root = self.component_of(node).unwrap_or(node);
+
+        let mut current = node;
+        while let Some(&next) = self.parent.get(&current) {
+            if next == root {
+                break;
+            }
+            self.parent.insert(current, root);
+            current = next;
+        }
+        root
+    }
+}
+
+#[cfg(all(test, not(feature = "uuid-ids")))]
+mod tests {
+    use super::*;
+    use zipgraph_core::{Edge, GraphOp};
+
+    fn two_components() -> Graph {
+        let mut graph = Graph::new();
+        for i in 0..6 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        graph.add_edge(0, 1, 1.0).unwrap();
+        graph.add_edge(1, 2, 1.0).unwrap();
+        graph.add_edge(3, 4, 1.0).unwrap();
+        graph.add_edge(4, 5, 1.0).unwrap();
+        graph
+    }
+
+    #[test]
+    fn test_build() {
+        let graph = two_components();
+        let index = ComponentIndex::build(&graph).unwrap();
+
+        assert_eq!(index.component_count(), 2);
+        assert_eq!(index.component_of(2), Some(0));
+        assert_eq!(index.component_of(5), Some(3));
+        assert!(index.are_disconnected(0, 4));
+        assert!(!index.are_disconnected(3, 5));
+        assert!(!index.are_disconnected(0, 99));
+    }
+
+    #[test]
+    fn test_incremental_edges() {
+        let mut graph = two_components();
+        let mut index = ComponentIndex::build(&graph).unwrap();
+
+        graph.add_edge(5, 2, 1.0).unwrap();
+        assert!(!index.is_current(&graph));
+        assert!(index.record_edge(&graph, 5, 2));
+
+        assert!(index.is_current(&graph));
+        assert_eq!(index.component_count(), 1);
+        assert_eq!(index.component_of(4), Some(0));
+        assert!(!index.are_disconnected(0, 4));
+    }
+
+    #[test]
+    fn test_unreported_mutation_stays_stale() {
+        let mut graph = two_components();
+        let mut index = ComponentIndex::build(&graph).unwrap();
+
+        // Joins the components without telling the index
+        graph.add_edge(2, 3, 1.0).unwrap();
+        graph.add_edge(0, 5, 1.0).unwrap();
+        assert!(!index.record_edge(&graph, 0, 5));
+        assert!(!index.is_current(&graph));
+        assert_eq!(index.component_count(), 2);
+
+        let mut graph = two_components();
+        let mut index = ComponentIndex::build(&graph).unwrap();
+        graph.add_node_simple("Node6");
+        graph.add_edge(0, 1, 1.0).unwrap();
+        assert!(!index.record_edge(&graph, 0, 1));
+        assert!(!index.is_current(&graph));
+    }
+
+    #[test]
+    fn test_count_preserving_mutation_stays_stale() {
+        let mut graph = two_components();
+        let mut index = ComponentIndex::build(&graph).unwrap();
+
+        // Joins 0..=4 and cuts off 5, keeping the counts
+        graph
+            .apply_batch(&[
+                GraphOp::RemoveEdge { from: 4, to: 5 },
+                GraphOp::AddEdge(Edge::new(2, 3, 1.0)),
+            ])
+            .unwrap();
+        graph.add_edge(0, 2, 1.0).unwrap();
+        assert!(!index.record_edge(&graph, 0, 2));
+        assert!(!index.is_current(&graph));
+
+        let mut index = ComponentIndex::build(&graph).unwrap();
+        assert!(!index.are_disconnected(1, 3));
+        graph.add_node_simple("Node6");
+        graph.add_edge(6, 6, 1.0).unwrap();
+        assert!(index.record_edge(&graph, 6, 6));
+        assert_eq!(index.component_count(), 3);
+    }
+}
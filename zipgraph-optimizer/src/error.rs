@@ -13,6 +13,18 @@ pub enum OptimizerError {
     #[error("Query execution failed: {0}")]
     ExecutionError(String),
 
+    #[error("Subscription limit of {0} reached")]
+    SubscriptionLimitReached(usize),
+
+    #[error("Invalid query: {0}")]
+    ParseError(String),
+
+    #[error("Protocol error: {0}")]
+    ProtocolError(String),
+
+    #[error("Unknown graph handle: {0:?}")]
+    UnknownGraph(crate::session::GraphHandle),
+
     #[error("Graph error: {0}")]
     GraphError(#[from] zipgraph_core::GraphError),
 
@@ -20,4 +32,16 @@ pub enum OptimizerError {
     MlError(#[from] zipgraph_ml::MlError),
 }
 
+impl From<std::num::ParseIntError> for OptimizerError {
+    fn from(error: std::num::ParseIntError) -> Self {
+        OptimizerError::OptimizationFailed(error.to_string())
+    }
+}
+
+impl From<serde_json::Error> for OptimizerError {
+    fn from(error: serde_json::Error) -> Self {
+        OptimizerError::OptimizationFailed(error.to_string())
+    }
+}
+
 pub type Result<T> = std::result::Result<T, OptimizerError>;
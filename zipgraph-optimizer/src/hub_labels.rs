@@ -0,0 +1,475 @@
+//! Hub labeling for exact shortest-path distances in microseconds
+//!
+//! Pruned landmark labeling assigns every node a small set of hubs with
+//! the distances to (and, on directed graphs, from) each of them, such
+//! that every shortest path passes through a hub shared by the labels of
+//! its endpoints. A distance query is then a merge of two sorted labels,
+//! without touching the graph. Hubs are processed in descending degree
+//! order, and each pruned Dijkstra search stops at nodes whose distance
+//! the labels built so far already answer, which keeps labels small on
+//! graphs with well-connected hubs.
+//!
+//! Building is far more expensive than querying, so the index is meant
+//! for read-mostly graphs: it serializes with serde (see `to_json`) to be
+//! built offline, and `bind` attaches a loaded index to a graph with the
+//! same content hash. Like `LandmarkIndex` it records the graph's generation
+//! token and is stale once the graph is mutated.
+
+use crate::error::{OptimizerError, Result};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use zipgraph_core::types::Weight;
+use zipgraph_core::{Graph, NodeId};
+
+/// One hub of a node's label
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct Label {
+    /// Rank of the hub in processing order
+    hub: usize,
+    distance: Weight,
+    /// The node's neighbor on its shortest path to (or from) the hub
+    next: usize,
+}
+
+/// Precomputed hub labels, see the module documentation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HubLabelIndex {
+    /// Node ID at each position, sorted
+    node_ids: Vec<NodeId>,
+    positions: HashMap<NodeId, usize>,
+    /// Position of the hub with each rank
+    hubs: Vec<usize>,
+    /// `to_hubs[v]` holds distances from `v` to its hubs, sorted by rank
+    to_hubs: Vec<Vec<Label>>,
+    /// `from_hubs[v]` holds distances from hubs to `v`; only stored for
+    /// directed graphs, where it differs from the above
+    from_hubs: Option<Vec<Vec<Label>>>,
+    max_label_size: Option<usize>,
+    exact: bool,
+    /// `Graph::content_hash` of the graph the index was built from
+    content_hash: u64,
+    /// Not serialized: a loaded index is stale until `bind` succeeds
+    #[serde(skip)]
+    generation: u64,
+}
+
+impl HubLabelIndex {
+    /// Build exact labels for `graph`
+    ///
+    /// Edge weights must be non-negative.
+    pub fn build(graph: &Graph) -> Result<Self> {
+        Self::build_labels(graph, None)
+    }
+
+    /// Build labels holding at most `max_label_size` hubs per direction
+    /// and node
+    ///
+    /// Nodes whose label is full are no longer labeled or searched
+    /// through. If the budget was hit (see `is_exact`), `distance` returns
+    /// an upper bound, or `None` for pairs the partial labels do not
+    /// cover, and `path` a path of that length.
+    pub fn build_with_budget(graph: &Graph, max_label_size: usize) -> Result<Self> {
+        if max_label_size == 0 {
+            return Err(OptimizerError::OptimizationFailed(
+                "A hub label budget must allow at least one hub".to_string(),
+            ));
+        }
+        Self::build_labels(graph, Some(max_label_size))
+    }
+
+    fn build_labels(graph: &Graph, max_label_size: Option<usize>) -> Result<Self> {
+        if let Some(edge) = graph.edges().iter().find(|edge| edge.weight < 0.0) {
+            return Err(OptimizerError::OptimizationFailed(format!(
+                "Hub labels require non-negative weights (edge {} -> {} has {})",
+                edge.from, edge.to, edge.weight
+            )));
+        }
+
+        let mut node_ids = graph.node_ids();
+        node_ids.sort_unstable();
+        let positions: HashMap<NodeId, usize> =
+            node_ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+
+        let mut forward = vec![Vec::new(); node_ids.len()];
+        for (i, &id) in node_ids.iter().enumerate() {
+            for (neighbor, weight) in graph.neighbors_with_weights(id)? {
+                forward[i].push((positions[&neighbor], weight));
+            }
+        }
+        let reverse = graph.is_directed().then(|| {
+            let mut reverse = vec![Vec::new(); node_ids.len()];
+            for (from, edges) in forward.iter().enumerate() {
+                for &(to, weight) in edges {
+                    reverse[to].push((from, weight));
+                }
+            }
+            reverse
+        });
+
+        // Descending degree, ties broken by position
+        let mut hubs: Vec<usize> = (0..node_ids.len()).collect();
+        hubs.sort_by(|&a, &b| forward[b].len().cmp(&forward[a].len()).then(a.cmp(&b)));
+
+        let mut to_hubs = vec![Vec::new(); node_ids.len()];
+        let mut from_hubs = reverse.as_ref().map(|_| vec![Vec::new(); node_ids.len()]);
+        let mut search = PrunedSearch {
+            max_label_size,
+            exact: true,
+            scratch: vec![Weight::INFINITY; node_ids.len()],
+            dist: vec![Weight::INFINITY; node_ids.len()],
+        };
+        for (rank, &hub) in hubs.iter().enumerate() {
+            match (&reverse, &mut from_hubs) {
+                (Some(reverse), Some(from_hubs)) => {
+                    // Searching forward from the hub labels distances from it
+                    let hub_label = to_hubs[hub].clone();
+                    search.run(from_hubs, &hub_label, &forward, rank, hub);
+                    let hub_label = from_hubs[hub].clone();
+                    search.run(&mut to_hubs, &hub_label, reverse, rank, hub);
+                }
+                _ => {
+                    let hub_label = to_hubs[hub].clone();
+                    search.run(&mut to_hubs, &hub_label, &forward, rank, hub);
+                }
+            }
+        }
+
+        Ok(Self {
+            node_ids,
+            positions,
+            hubs,
+            to_hubs,
+            from_hubs,
+            max_label_size,
+            exact: search.exact,
+            content_hash: graph.content_hash(),
+            generation: graph.generation(),
+        })
+    }
+
+    /// Build a fresh index for `graph` with the same label budget
+    pub fn rebuild(&self, graph: &Graph) -> Result<Self> {
+        Self::build_labels(graph, self.max_label_size)
+    }
+
+    /// Whether every distance is exact, i.e. no label hit the budget
+    pub fn is_exact(&self) -> bool {
+        self.exact
+    }
+
+    /// Average number of hubs per node and direction
+    pub fn average_label_size(&self) -> f64 {
+        let labels: Vec<&Vec<Label>> =
+            self.to_hubs.iter().chain(self.from_hubs.iter().flatten()).collect();
+        if labels.is_empty() {
+            return 0.0;
+        }
+        labels.iter().map(|label| label.len()).sum::<usize>() as f64 / labels.len() as f64
+    }
+
+    /// Generation token of the graph the index describes
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Whether the index still describes `graph`
+    pub fn is_current(&self, graph: &Graph) -> bool {
+        self.generation == graph.generation()
+    }
+
+    /// Attach the index to `graph` if it has the content the index was
+    /// built from (see `Graph::content_hash`), e.g. after loading both
+    /// from disk
+    ///
+    /// Returns whether the index is now current for `graph`.
+    pub fn bind(&mut self, graph: &Graph) -> bool {
+        if graph.content_hash() != self.content_hash {
+            return false;
+        }
+        self.generation = graph.generation();
+        true
+    }
+
+    /// Encode the index as JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Decode an index encoded with `to_json`; `bind` it before use
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Shortest-path distance from `a` to `b`
+    ///
+    /// Returns `None` if either node is unknown to the index or `b` is not
+    /// reachable from `a`.
+    pub fn distance(&self, a: NodeId, b: NodeId) -> Option<Weight> {
+        let (a, b) = (*self.positions.get(&a)?, *self.positions.get(&b)?);
+        self.best_hub(a, b).map(|(_, distance)| distance)
+    }
+
+    /// A shortest path from `a` to `b`, including both endpoints
+    pub fn path(&self, a: NodeId, b: NodeId) -> Option<Vec<NodeId>> {
+        let (a, b) = (*self.positions.get(&a)?, *self.positions.get(&b)?);
+        let (hub, _) = self.best_hub(a, b)?;
+
+        // Follow the search trees of the hub from both ends
+        let mut path = walk_to_hub(&self.to_hubs, a, hub, self.hubs[hub]);
+        let from_hubs = self.from_hubs.as_ref().unwrap_or(&self.to_hubs);
+        let mut tail = walk_to_hub(from_hubs, b, hub, self.hubs[hub]);
+        tail.pop();
+        path.extend(tail.into_iter().rev());
+
+        Some(path.into_iter().map(|position| self.node_ids[position]).collect())
+    }
+
+    /// Rank of the hub on the shortest route from `a` to `b`, with the
+    /// distance through it
+    fn best_hub(&self, a: usize, b: usize) -> Option<(usize, Weight)> {
+        let from_hubs = self.from_hubs.as_ref().unwrap_or(&self.to_hubs);
+        let (out, into) = (&self.to_hubs[a], &from_hubs[b]);
+
+        let (mut i, mut j) = (0, 0);
+        let mut best: Option<(usize, Weight)> = None;
+        while i < out.len() && j < into.len() {
+            match out[i].hub.cmp(&into[j].hub) {
+                Ordering::Less => i += 1,
+                Ordering::Greater => j += 1,
+                Ordering::Equal => {
+                    let distance = out[i].distance + into[j].distance;
+                    if best.is_none_or(|(_, d)| distance < d) {
+                        best = Some((out[i].hub, distance));
+                    }
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        best
+    }
+}
+
+/// Positions from `start` to the hub along `labels`' next pointers
+fn walk_to_hub(labels: &[Vec<Label>], start: usize, rank: usize, hub: usize) -> Vec<usize> {
+    let mut path = vec![start];
+    let mut current = start;
+    while current != hub {
+        let label = &labels[current];
+        // Every node on a search tree path was labeled before it was expanded
+        let index = label
+            .binary_search_by(|entry| entry.hub.cmp(&rank))
+            .expect("search tree nodes carry the hub");
+        current = label[index].next;
+        path.push(current);
+    }
+    path
+}
+
+/// State shared by the pruned Dijkstra searches of one build
+struct PrunedSearch {
+    max_label_size: Option<usize>,
+    exact: bool,
+    /// Distances in the hub's label by hub rank, infinite elsewhere
+    scratch: Vec<Weight>,
+    /// Tentative distances of the current search, infinite elsewhere
+    dist: Vec<Weight>,
+}
+
+impl PrunedSearch {
+    /// Dijkstra from `hub` over `adjacency`, adding the hub to the
+    /// `labels` of every node whose distance the labels built so far do
+    /// not already cover
+    ///
+    /// `hub_label` is the hub's own label for the opposite direction, so
+    /// that a node `v` is covered when some earlier hub `h` has
+    /// `labels[v][h] + hub_label[h] <= d`.
+    fn run(
+        &mut self,
+        labels: &mut [Vec<Label>],
+        hub_label: &[Label],
+        adjacency: &[Vec<(usize, Weight)>],
+        rank: usize,
+        hub: usize,
+    ) {
+        for entry in hub_label {
+            self.scratch[entry.hub] = entry.distance;
+        }
+
+        let mut visited = vec![hub];
+        let mut heap = BinaryHeap::new();
+        self.dist[hub] = 0.0;
+        heap.push(State { cost: 0.0, node: hub, parent: hub });
+
+        while let Some(State { cost, node, parent }) = heap.pop() {
+            if cost > self.dist[node] {
+                continue;
+            }
+
+            let covered = labels[node]
+                .iter()
+                .map(|entry| entry.distance + self.scratch[entry.hub])
+                .fold(Weight::INFINITY, Weight::min);
+            if covered <= cost {
+                continue;
+            }
+            if self.max_label_size.is_some_and(|max| labels[node].len() >= max) {
+                self.exact = false;
+                continue;
+            }
+            labels[node].push(Label { hub: rank, distance: cost, next: parent });
+
+            for &(neighbor, weight) in &adjacency[node] {
+                let next_cost = cost + weight;
+                if next_cost < self.dist[neighbor] {
+                    if self.dist[neighbor].is_infinite() {
+                        visited.push(neighbor);
+                    }
+                    self.dist[neighbor] = next_cost;
+                    heap.push(State { cost: next_cost, node: neighbor, parent: node });
+                }
+            }
+        }
+
+        for entry in hub_label {
+            self.scratch[entry.hub] = Weight::INFINITY;
+        }
+        for node in visited {
+            self.dist[node] = Weight::INFINITY;
+        }
+    }
+}
+
+/// Priority queue item ordered by smallest distance first
+#[derive(Copy, Clone, PartialEq)]
+struct State {
+    cost: Weight,
+    node: usize,
+    parent: usize,
+}
+
+impl Eq for State {}
+
+impl Ord for State {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.partial_cmp(&self.cost).unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for State {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(all(test, not(feature = "uuid-ids")))]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use zipgraph_core::algorithms;
+
+    fn random_graph(directed: bool, seed: u64) -> Graph {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut graph = if directed { Graph::new_directed() } else { Graph::new() };
+        for i in 0..80 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        for _ in 0..200 {
+            let from = rng.gen_range(0..80);
+            let to = rng.gen_range(0..80);
+            graph.add_edge(from, to, rng.gen_range(0.5..10.0)).unwrap();
+        }
+        graph
+    }
+
+    fn path_length(graph: &Graph, path: &[NodeId]) -> Weight {
+        path.windows(2)
+            .map(|pair| {
+                graph
+                    .neighbors_with_weights(pair[0])
+                    .unwrap()
+                    .into_iter()
+                    .filter(|&(to, _)| to == pair[1])
+                    .map(|(_, weight)| weight)
+                    .fold(Weight::INFINITY, Weight::min)
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_matches_dijkstra() {
+        for directed in [false, true] {
+            for seed in [1, 2, 3] {
+                let graph = random_graph(directed, seed);
+                let index = HubLabelIndex::build(&graph).unwrap();
+                assert!(index.is_exact());
+
+                for a in 0..80 {
+                    for b in 0..80 {
+                        let expected = algorithms::dijkstra(&graph, a, b).ok();
+                        match (expected, index.distance(a, b)) {
+                            (Some((_, cost)), Some(distance)) => {
+                                assert!((cost - distance).abs() < 1e-9, "{} -> {}", a, b);
+                                let path = index.path(a, b).unwrap();
+                                assert_eq!((path[0], path[path.len() - 1]), (a, b));
+                                assert!((path_length(&graph, &path) - cost).abs() < 1e-9);
+                            }
+                            (None, None) => assert!(index.path(a, b).is_none()),
+                            (expected, actual) => panic!("{:?} vs {:?}", expected, actual),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_budget_gives_upper_bounds() {
+        let graph = random_graph(false, 4);
+        let exact = HubLabelIndex::build(&graph).unwrap();
+        let partial = HubLabelIndex::build_with_budget(&graph, 2).unwrap();
+        assert!(!partial.is_exact());
+        assert!(partial.average_label_size() <= 2.0);
+        assert!(partial.average_label_size() < exact.average_label_size());
+
+        for a in 0..80 {
+            for b in 0..80 {
+                if let Some(distance) = partial.distance(a, b) {
+                    assert!(distance >= exact.distance(a, b).unwrap() - 1e-9);
+                    let path = partial.path(a, b).unwrap();
+                    assert!((path_length(&graph, &path) - distance).abs() < 1e-9);
+                }
+            }
+        }
+        assert!(HubLabelIndex::build_with_budget(&graph, 0).is_err());
+    }
+
+    #[test]
+    fn test_serialization_and_bind() {
+        let graph = random_graph(true, 5);
+        let index = HubLabelIndex::build(&graph).unwrap();
+        assert!(index.is_current(&graph));
+
+        let mut loaded = HubLabelIndex::from_json(&index.to_json().unwrap()).unwrap();
+        assert!(!loaded.is_current(&graph));
+        assert_eq!(loaded.distance(0, 79), index.distance(0, 79));
+
+        let copy = graph.clone();
+        assert!(loaded.bind(&copy));
+        assert!(loaded.is_current(&copy));
+
+        let mut changed = graph.clone();
+        changed.add_edge(0, 1, 1.0).unwrap();
+        assert!(!loaded.bind(&changed));
+        assert!(!loaded.is_current(&changed));
+    }
+
+    #[test]
+    fn test_invalid_weights() {
+        let mut graph = random_graph(false, 1);
+        graph.add_edge(0, 1, -1.0).unwrap();
+        assert!(HubLabelIndex::build(&graph).is_err());
+    }
+}
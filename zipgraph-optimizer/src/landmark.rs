@@ -0,0 +1,384 @@
+//! Landmark index for fast approximate shortest-path distances
+//!
+//! A handful of landmark nodes are chosen up front and single-source
+//! shortest paths are precomputed from (and, on directed graphs, to) each
+//! of them. By the triangle inequality every landmark `L` gives
+//!
+//! - an upper bound `d(a, b) <= d(a, L) + d(L, b)`, used as the estimate
+//! - a lower bound `d(a, b) >= d(L, b) - d(L, a)`, usable as an A* heuristic
+//!
+//! so both answer in O(landmarks) without touching the graph. The index
+//! records the graph's generation token and is stale once the graph is
+//! mutated.
+
+use crate::error::{OptimizerError, Result};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use zipgraph_core::types::Weight;
+use zipgraph_core::{Graph, NodeId};
+
+/// How landmarks are chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LandmarkStrategy {
+    /// Uniformly random nodes, reproducible when seeded
+    Random { seed: Option<u64> },
+    /// The nodes with the most edges
+    HighestDegree,
+    /// Start from the highest-degree node, then repeatedly add the node
+    /// farthest from all landmarks chosen so far
+    FarthestPoint,
+}
+
+/// Precomputed landmark distances, see the module documentation
+#[derive(Debug, Clone)]
+pub struct LandmarkIndex {
+    landmarks: Vec<NodeId>,
+    num_landmarks: usize,
+    strategy: LandmarkStrategy,
+    /// Position of each node in the distance rows
+    positions: HashMap<NodeId, usize>,
+    /// `from_landmarks[l][i]` is the distance from landmark `l` to node `i`
+    from_landmarks: Vec<Vec<Weight>>,
+    /// `to_landmarks[l][i]` is the distance from node `i` to landmark `l`;
+    /// only stored for directed graphs, where it differs from the above
+    to_landmarks: Option<Vec<Vec<Weight>>>,
+    generation: u64,
+}
+
+impl LandmarkIndex {
+    /// Choose up to `num_landmarks` landmarks and precompute their distances
+    ///
+    /// Edge weights must be non-negative.
+    pub fn build(graph: &Graph, num_landmarks: usize, strategy: LandmarkStrategy) -> Result<Self> {
+        if num_landmarks == 0 {
+            return Err(OptimizerError::OptimizationFailed(
+                "A landmark index needs at least one landmark".to_string(),
+            ));
+        }
+        if let Some(edge) = graph.edges().iter().find(|edge| edge.weight < 0.0) {
+            return Err(OptimizerError::OptimizationFailed(format!(
+                "Landmark distances require non-negative weights (edge {} -> {} has {})",
+                edge.from, edge.to, edge.weight
+            )));
+        }
+
+        let mut node_ids = graph.node_ids();
+        node_ids.sort_unstable();
+        let positions: HashMap<NodeId, usize> =
+            node_ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+
+        let mut forward = vec![Vec::new(); node_ids.len()];
+        for (i, &id) in node_ids.iter().enumerate() {
+            for (neighbor, weight) in graph.neighbors_with_weights(id)? {
+                forward[i].push((positions[&neighbor], weight));
+            }
+        }
+
+        let count = num_landmarks.min(node_ids.len());
+        let (selected, from_landmarks) = match strategy {
+            LandmarkStrategy::Random { seed } => {
+                let mut rng = match seed {
+                    Some(seed) => StdRng::seed_from_u64(seed),
+                    None => StdRng::from_entropy(),
+                };
+                let indices: Vec<usize> = (0..node_ids.len()).collect();
+                let selected: Vec<usize> =
+                    indices.choose_multiple(&mut rng, count).copied().collect();
+                let rows = selected.iter().map(|&l| distances_from(&forward, l)).collect();
+                (selected, rows)
+            }
+            LandmarkStrategy::HighestDegree => {
+                let selected: Vec<usize> = by_degree(&forward).into_iter().take(count).collect();
+                let rows = selected.iter().map(|&l| distances_from(&forward, l)).collect();
+                (selected, rows)
+            }
+            LandmarkStrategy::FarthestPoint => farthest_points(&forward, count),
+        };
+
+        let to_landmarks = if graph.is_directed() {
+            let mut reverse = vec![Vec::new(); node_ids.len()];
+            for (from, edges) in forward.iter().enumerate() {
+                for &(to, weight) in edges {
+                    reverse[to].push((from, weight));
+                }
+            }
+            Some(selected.iter().map(|&l| distances_from(&reverse, l)).collect())
+        } else {
+            None
+        };
+
+        Ok(Self {
+            landmarks: selected.iter().map(|&l| node_ids[l]).collect(),
+            num_landmarks,
+            strategy,
+            positions,
+            from_landmarks,
+            to_landmarks,
+            generation: graph.generation(),
+        })
+    }
+
+    /// Build a fresh index for `graph` with the same landmark settings
+    pub fn rebuild(&self, graph: &Graph) -> Result<Self> {
+        Self::build(graph, self.num_landmarks, self.strategy)
+    }
+
+    /// The chosen landmark nodes
+    pub fn landmarks(&self) -> &[NodeId] {
+        &self.landmarks
+    }
+
+    /// Strategy used to choose the landmarks
+    pub fn strategy(&self) -> LandmarkStrategy {
+        self.strategy
+    }
+
+    /// Generation token of the graph the index was built from
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Whether the index still describes `graph`
+    pub fn is_current(&self, graph: &Graph) -> bool {
+        self.generation == graph.generation()
+    }
+
+    /// Upper bound on the distance from `a` to `b`
+    ///
+    /// Returns `None` if either node is unknown to the index or no landmark
+    /// lies on a route from `a` to `b`.
+    pub fn estimate_distance(&self, a: NodeId, b: NodeId) -> Option<Weight> {
+        let (a, b) = (*self.positions.get(&a)?, *self.positions.get(&b)?);
+        if a == b {
+            return Some(0.0);
+        }
+
+        let to_landmarks = self.to_landmarks.as_ref().unwrap_or(&self.from_landmarks);
+        to_landmarks
+            .iter()
+            .zip(&self.from_landmarks)
+            .map(|(to, from)| to[a] + from[b])
+            .filter(|distance| distance.is_finite())
+            .min_by(|x, y| x.partial_cmp(y).unwrap_or(Ordering::Equal))
+    }
+
+    /// Lower bound on the distance from `a` to `b`
+    ///
+    /// Never overestimates, so it is an admissible A* heuristic. Unknown
+    /// nodes get the trivial bound of zero.
+    pub fn lower_bound(&self, a: NodeId, b: NodeId) -> Weight {
+        let (a, b) = match (self.positions.get(&a), self.positions.get(&b)) {
+            (Some(&a), Some(&b)) => (a, b),
+            _ => return 0.0,
+        };
+
+        let to_landmarks = self.to_landmarks.as_ref().unwrap_or(&self.from_landmarks);
+        let mut bound: Weight = 0.0;
+        for (from, to) in self.from_landmarks.iter().zip(to_landmarks) {
+            // d(L, b) <= d(L, a) + d(a, b)
+            if from[a].is_finite() && from[b].is_finite() {
+                bound = bound.max(from[b] - from[a]);
+            }
+            // d(a, L) <= d(a, b) + d(b, L)
+            if to[a].is_finite() && to[b].is_finite() {
+                bound = bound.max(to[a] - to[b]);
+            }
+        }
+        bound
+    }
+}
+
+/// Node positions by descending degree, ties broken by position
+fn by_degree(adjacency: &[Vec<(usize, Weight)>]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..adjacency.len()).collect();
+    order.sort_by(|&a, &b| adjacency[b].len().cmp(&adjacency[a].len()).then(a.cmp(&b)));
+    order
+}
+
+/// Farthest-point landmark selection, returning landmarks and their rows
+fn farthest_points(adjacency: &[Vec<(usize, Weight)>], count: usize) -> (Vec<usize>, Vec<Vec<Weight>>) {
+    let mut selected = Vec::with_capacity(count);
+    let mut rows: Vec<Vec<Weight>> = Vec::with_capacity(count);
+    let mut nearest = vec![Weight::INFINITY; adjacency.len()];
+
+    let mut next = by_degree(adjacency).first().copied();
+    while let Some(landmark) = next {
+        let row = distances_from(adjacency, landmark);
+        for (d, &r) in nearest.iter_mut().zip(&row) {
+            *d = d.min(r);
+        }
+        selected.push(landmark);
+        rows.push(row);
+
+        if selected.len() == count {
+            break;
+        }
+
+        // Unreachable nodes are infinitely far, so other components win
+        next = (0..adjacency.len())
+            .filter(|i| !selected.contains(i))
+            .max_by(|&a, &b| {
+                nearest[a]
+                    .partial_cmp(&nearest[b])
+                    .unwrap_or(Ordering::Equal)
+                    .then(b.cmp(&a))
+            });
+    }
+
+    (selected, rows)
+}
+
+/// Priority queue item ordered by smallest distance first
+#[derive(Copy, Clone, PartialEq)]
+struct State {
+    cost: Weight,
+    node: usize,
+}
+
+impl Eq for State {}
+
+impl Ord for State {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.partial_cmp(&self.cost).unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for State {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Dijkstra distances from `source` to every position
+fn distances_from(adjacency: &[Vec<(usize, Weight)>], source: usize) -> Vec<Weight> {
+    let mut dist = vec![Weight::INFINITY; adjacency.len()];
+    let mut heap = BinaryHeap::new();
+
+    dist[source] = 0.0;
+    heap.push(State { cost: 0.0, node: source });
+
+    while let Some(State { cost, node }) = heap.pop() {
+        if cost > dist[node] {
+            continue;
+        }
+
+        for &(neighbor, weight) in &adjacency[node] {
+            let next_cost = cost + weight;
+            if next_cost < dist[neighbor] {
+                dist[neighbor] = next_cost;
+                heap.push(State { cost: next_cost, node: neighbor });
+            }
+        }
+    }
+
+    dist
+}
+
+#[cfg(all(test, not(feature = "uuid-ids")))]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use zipgraph_core::algorithms;
+
+    const STRATEGIES: [LandmarkStrategy; 3] = [
+        LandmarkStrategy::Random { seed: Some(7) },
+        LandmarkStrategy::HighestDegree,
+        LandmarkStrategy::FarthestPoint,
+    ];
+
+    fn random_graph(directed: bool, seed: u64) -> Graph {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut graph = if directed { Graph::new_directed() } else { Graph::new() };
+        for i in 0..60 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        for _ in 0..180 {
+            let from = rng.gen_range(0..60);
+            let to = rng.gen_range(0..60);
+            graph.add_edge(from, to, rng.gen_range(0.5..10.0)).unwrap();
+        }
+        graph
+    }
+
+    #[test]
+    fn test_bounds_enclose_true_distance() {
+        for directed in [false, true] {
+            let graph = random_graph(directed, 42);
+            for strategy in STRATEGIES {
+                let index = LandmarkIndex::build(&graph, 4, strategy).unwrap();
+                assert_eq!(index.landmarks().len(), 4);
+
+                for a in 0..60 {
+                    for b in 0..60 {
+                        let Ok((_, exact)) = algorithms::dijkstra(&graph, a, b) else {
+                            continue;
+                        };
+                        assert!(index.lower_bound(a, b) <= exact + 1e-9);
+                        if let Some(estimate) = index.estimate_distance(a, b) {
+                            assert!(estimate >= exact - 1e-9);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_landmark_astar_matches_dijkstra() {
+        for directed in [false, true] {
+            let graph = random_graph(directed, 9);
+            let index = LandmarkIndex::build(&graph, 3, LandmarkStrategy::FarthestPoint).unwrap();
+
+            for (a, b) in [(0, 59), (5, 17), (30, 2), (44, 44)] {
+                let expected = algorithms::dijkstra(&graph, a, b).map(|(_, cost)| cost);
+                let actual = algorithms::astar(&graph, a, b, |n| index.lower_bound(n, b))
+                    .map(|(_, cost)| cost);
+                match (expected, actual) {
+                    (Ok(expected), Ok(actual)) => assert!((expected - actual).abs() < 1e-9),
+                    (Err(_), Err(_)) => {}
+                    (expected, actual) => panic!("{:?} vs {:?}", expected, actual),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_farthest_point_covers_components() {
+        let mut graph = Graph::new();
+        for i in 0..6 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        graph.add_edge(0, 1, 1.0).unwrap();
+        graph.add_edge(0, 2, 1.0).unwrap();
+        graph.add_edge(3, 4, 1.0).unwrap();
+        graph.add_edge(4, 5, 1.0).unwrap();
+
+        let index = LandmarkIndex::build(&graph, 2, LandmarkStrategy::FarthestPoint).unwrap();
+        assert_eq!(index.landmarks(), &[0, 3]);
+        assert_eq!(index.estimate_distance(1, 2), Some(2.0));
+        assert_eq!(index.estimate_distance(1, 5), None);
+    }
+
+    #[test]
+    fn test_generation_invalidates_index() {
+        let mut graph = random_graph(false, 1);
+        let index = LandmarkIndex::build(&graph, 2, LandmarkStrategy::HighestDegree).unwrap();
+        assert!(index.is_current(&graph));
+
+        graph.add_edge(0, 1, 1.0).unwrap();
+        assert!(!index.is_current(&graph));
+        assert!(index.rebuild(&graph).unwrap().is_current(&graph));
+    }
+
+    #[test]
+    fn test_invalid_parameters() {
+        let mut graph = random_graph(false, 1);
+        assert!(LandmarkIndex::build(&graph, 0, LandmarkStrategy::HighestDegree).is_err());
+
+        graph.add_edge(0, 1, -1.0).unwrap();
+        assert!(LandmarkIndex::build(&graph, 2, LandmarkStrategy::HighestDegree).is_err());
+    }
+}
@@ -14,21 +14,44 @@
 //!
 //! ```rust
 //! use zipgraph_optimizer::QueryOptimizer;
-//! use zipgraph_core::Graph;
+//! use zipgraph_core::{node_id, Graph};
 //!
 //! let mut optimizer = QueryOptimizer::new();
 //! let graph = Graph::new();
 //! 
 //! // Optimizer learns and caches results
-//! let path = optimizer.shortest_path(&graph, 0, 5);
+//! let path = optimizer.shortest_path(&graph, node_id(0), node_id(5));
 //! ```
 
 pub mod cache;
+pub mod components;
 pub mod error;
+pub mod hub_labels;
+pub mod landmark;
 pub mod optimizer;
+pub mod prepared;
+pub mod protocol;
 pub mod query;
+pub mod scheduler;
+pub mod session;
+pub mod subscription;
 
 // Re-exports
+pub use components::ComponentIndex;
 pub use error::{OptimizerError, Result};
-pub use optimizer::QueryOptimizer;
-pub use query::{Query, QueryResult};
+pub use hub_labels::HubLabelIndex;
+pub use landmark::{LandmarkIndex, LandmarkStrategy};
+pub use optimizer::{OptimizerStatistics, OptimizerStats, PrefetchHandle, QueryOptimizer};
+pub use cache::{CacheEntryInfo, CacheStats, EvictionReason, EvictionRecord, QueryCacheConfig};
+pub use prepared::{PreparedQuery, PreparedStats, QueryTemplate};
+pub use protocol::{
+    decode_legacy_query, decode_legacy_result, QueryEnvelope, ResponseEnvelope,
+    LEGACY_PROTOCOL_VERSION, PROTOCOL_VERSION,
+};
+pub use query::{
+    parse_query, serialize_query, OrderedF64, Query, QueryKind, QueryOptions, QueryResult,
+    Subgraph, SubgraphEdge, SubgraphNode,
+};
+pub use scheduler::{Priority, PriorityStats, QueryTicket};
+pub use session::GraphHandle;
+pub use subscription::{SubscriptionCallback, SubscriptionId};
@@ -1,24 +1,104 @@
 //! Query optimizer with ML-powered planning
 
-use crate::cache::QueryCache;
+use crate::cache::{
+    CacheStats, QueryCache, QueryCacheConfig, DEFAULT_NEGATIVE_TTL, EVICTION_LOG_CAPACITY,
+};
+use crate::components::ComponentIndex;
 use crate::error::{OptimizerError, Result};
-use crate::query::{Query, QueryResult};
-use std::time::Instant;
-use zipgraph_core::{algorithms, Graph, NodeId};
+use crate::hub_labels::HubLabelIndex;
+use crate::landmark::LandmarkIndex;
+use crate::prepared::{PreparedQueries, PreparedQuery, PreparedStats, QueryTemplate};
+use crate::query::{Query, QueryOptions, QueryResult};
+use crate::scheduler::{
+    Priority, PriorityStats, QueryTicket, Scheduler, DEFAULT_BACKGROUND_SLOTS,
+};
+use crate::session::{GraphHandle, RegisteredGraph};
+use crate::subscription::{
+    SubscriptionCallback, SubscriptionId, Subscriptions, DEFAULT_MAX_SUBSCRIPTIONS,
+};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use zipgraph_core::algorithms::SearchMetrics;
+use zipgraph_core::{
+    algorithms, parallel, Algorithm, Graph, GraphError, Node, NodeId, PageRankParams, PathMetric,
+};
 use zipgraph_ml::AlgorithmSelector;
 
 /// Main query optimizer
 pub struct QueryOptimizer {
-    cache: QueryCache,
-    algorithm_selector: AlgorithmSelector,
+    cache: Arc<QueryCache>,
+    cache_config: QueryCacheConfig,
+    negative_ttl: Duration,
+    algorithm_selector: Arc<AlgorithmSelector>,
+    landmarks: Option<LandmarkIndex>,
+    hub_labels: Option<HubLabelIndex>,
+    use_components: bool,
+    components: Option<ComponentIndex>,
+    subscriptions: Subscriptions,
+    graphs: BTreeMap<GraphHandle, RegisteredGraph>,
+    next_graph_id: u64,
     stats: OptimizerStats,
+    background_slots: usize,
+    scheduler: Option<Scheduler>,
+    prepared: PreparedQueries,
 }
 
-#[derive(Debug, Default)]
-struct OptimizerStats {
-    queries_executed: usize,
-    cache_hits: usize,
-    cache_misses: usize,
+/// Query execution counters, serializable for monitoring endpoints
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OptimizerStats {
+    pub queries_executed: usize,
+    pub cache_hits: usize,
+    pub cache_misses: usize,
+    /// Time spent executing queries that missed the cache
+    pub total_execution_time_ms: f64,
+    /// `total_execution_time_ms / queries_executed`, or 0 before any query
+    pub avg_execution_time_ms: f64,
+    /// Path queries answered from the component index without execution
+    #[serde(default)]
+    pub short_circuited_queries: usize,
+    /// Work done by the searches of executed shortest-path queries
+    #[serde(default)]
+    pub search: SearchMetrics,
+}
+
+impl OptimizerStats {
+    fn record_execution(&mut self, elapsed: Duration) {
+        self.queries_executed += 1;
+        self.total_execution_time_ms += elapsed.as_secs_f64() * 1000.0;
+        self.avg_execution_time_ms = self.total_execution_time_ms / self.queries_executed as f64;
+    }
+
+    fn snapshot(&self, cache: &QueryCache) -> OptimizerStatistics {
+        OptimizerStatistics {
+            queries_executed: self.queries_executed,
+            cache_hits: self.cache_hits,
+            cache_misses: self.cache_misses,
+            cache: cache.stats(),
+            interactive: PriorityStats::default(),
+            background: PriorityStats::default(),
+            prepared: BTreeMap::new(),
+        }
+    }
+}
+
+/// Snapshot of optimizer and cache statistics
+#[derive(Debug, Clone)]
+pub struct OptimizerStatistics {
+    pub queries_executed: usize,
+    pub cache_hits: usize,
+    pub cache_misses: usize,
+    /// Cache statistics, including the per-partition breakdown
+    pub cache: CacheStats,
+    /// Queries sent with `execute_with_priority(.., Priority::Interactive)`
+    pub interactive: PriorityStats,
+    /// Queries sent with `execute_with_priority(.., Priority::Background)`
+    pub background: PriorityStats,
+    /// Executions of each query prepared with `prepare`, by name
+    pub prepared: BTreeMap<String, PreparedStats>,
 }
 
 impl QueryOptimizer {
@@ -29,46 +109,406 @@ impl QueryOptimizer {
 
     /// Create optimizer with specified cache size
     pub fn with_cache_size(cache_size: usize) -> Self {
+        Self::with_cache_config(QueryCacheConfig::new(cache_size))
+    }
+
+    /// Create optimizer with a partitioned cache
+    pub fn with_cache_config(config: QueryCacheConfig) -> Self {
         Self {
-            cache: QueryCache::new(cache_size),
-            algorithm_selector: AlgorithmSelector::new(),
+            cache: Arc::new(QueryCache::with_config(config.clone())),
+            cache_config: config,
+            negative_ttl: DEFAULT_NEGATIVE_TTL,
+            algorithm_selector: Arc::new(AlgorithmSelector::new()),
+            landmarks: None,
+            hub_labels: None,
+            use_components: false,
+            components: None,
+            subscriptions: Subscriptions::new(DEFAULT_MAX_SUBSCRIPTIONS),
+            graphs: BTreeMap::new(),
+            next_graph_id: 0,
             stats: OptimizerStats::default(),
+            background_slots: DEFAULT_BACKGROUND_SLOTS,
+            scheduler: None,
+            prepared: PreparedQueries::default(),
         }
     }
 
+    /// Set how long negative results (e.g. "no path") stay cached
+    pub fn with_negative_ttl(mut self, ttl: Duration) -> Self {
+        self.cache =
+            Arc::new(QueryCache::with_config(self.cache_config.clone()).with_negative_ttl(ttl));
+        self.negative_ttl = ttl;
+        self
+    }
+
+    /// Use a landmark index for distance estimates and shortest paths
+    ///
+    /// Shortest-path queries then run A* with the landmark lower bound as
+    /// heuristic. If the queried graph has been mutated since the index was
+    /// built, the index is rebuilt with the same settings before use.
+    pub fn with_landmark_index(mut self, index: LandmarkIndex) -> Self {
+        self.landmarks = Some(index);
+        self
+    }
+
+    /// Answer weighted shortest-path and distance queries from hub labels
+    ///
+    /// The index is used only while it is current for the queried graph
+    /// and exact; otherwise queries fall back to the usual algorithms.
+    /// Unlike a landmark index it is never rebuilt automatically, since
+    /// building is expensive: rebuild it with `HubLabelIndex::rebuild` and
+    /// set it again after mutating the graph.
+    pub fn with_hub_labels(mut self, index: HubLabelIndex) -> Self {
+        self.hub_labels = Some(index);
+        self
+    }
+
+    /// The hub label index, if one is configured
+    pub fn hub_labels(&self) -> Option<&HubLabelIndex> {
+        self.hub_labels.as_ref()
+    }
+
+    /// Answer path queries between components from a component index
+    ///
+    /// The index is built on first use and rebuilt whenever the queried
+    /// graph has been mutated, unless the mutation was an edge addition
+    /// reported through `record_edge_added`. Shortest-path and distance
+    /// queries whose endpoints lie in different components then return
+    /// `QueryResult::NoPath` without running any algorithm.
+    pub fn with_component_index(mut self) -> Self {
+        self.use_components = true;
+        self
+    }
+
+    /// Set the maximum number of standing query subscriptions
+    pub fn with_max_subscriptions(mut self, max_subscriptions: usize) -> Self {
+        self.subscriptions.max_subscriptions = max_subscriptions;
+        self
+    }
+
+    /// Set how many background queries `execute_with_priority` runs at
+    /// once; at least one always can
+    pub fn with_background_slots(mut self, background_slots: usize) -> Self {
+        self.background_slots = background_slots;
+        self.scheduler = None;
+        self
+    }
+
+    /// The landmark index, if one is configured
+    pub fn landmark_index(&self) -> Option<&LandmarkIndex> {
+        self.landmarks.as_ref()
+    }
+
     /// Execute a query with optimization
+    ///
+    /// Results are cached until the graph is mutated. Shortest-path
+    /// queries between disconnected nodes yield `QueryResult::NoPath`,
+    /// which is cached with the negative TTL. Errors such as unknown nodes
+    /// are never cached.
     pub fn execute(&mut self, graph: &Graph, query: &Query) -> Result<QueryResult> {
-        // Check cache first
-        if let Some(cached_result) = self.cache.get(query) {
-            self.stats.cache_hits += 1;
-            return Ok(cached_result);
+        self.execute_with_options(graph, query, &QueryOptions::default())
+    }
+
+    /// Execute a query and return one page of its result
+    ///
+    /// The query's own limit (`top_k`, `k`) is applied first, then
+    /// `options.offset` and `options.limit`. Only the full, unshaped result
+    /// is cached, so requests that differ only in their limits or page are
+    /// served from a single computation.
+    pub fn execute_with_options(
+        &mut self,
+        graph: &Graph,
+        query: &Query,
+        options: &QueryOptions,
+    ) -> Result<QueryResult> {
+        let result = self.execute_unshaped(graph, &query.unshaped())?;
+
+        Ok(result
+            .paginate(0, query.result_limit())
+            .paginate(options.offset, options.limit))
+    }
+
+    /// Execute a query without result shaping, going through the cache
+    fn execute_unshaped(&mut self, graph: &Graph, query: &Query) -> Result<QueryResult> {
+        let endpoints = match query {
+            Query::ShortestPath { start, goal, .. } => Some((*start, *goal)),
+            Query::DistanceEstimate { a, b } => Some((*a, *b)),
+            _ => None,
+        };
+
+        if let Some((a, b)) = endpoints {
+            if self.use_components && self.component_index(graph)?.are_disconnected(a, b) {
+                self.stats.short_circuited_queries += 1;
+                return Ok(QueryResult::NoPath);
+            }
+            self.refresh_landmarks(graph)?;
         }
 
-        self.stats.cache_misses += 1;
-        self.stats.queries_executed += 1;
+        let selector = &self.algorithm_selector;
+        let landmarks = self.landmarks.as_ref();
+        let hub_labels = self
+            .hub_labels
+            .as_ref()
+            .filter(|index| index.is_exact() && index.is_current(graph));
+        run_cached(&self.cache, &mut self.stats, graph, query, |graph, query, search| {
+            execute_query(selector, landmarks, hub_labels, graph, query, search)
+        })
+    }
+
+    /// Register `template` under `name` for `execute_prepared`
+    ///
+    /// `statistics` reports the executions of each name from then on;
+    /// preparing a name again adds to the counters it already has.
+    pub fn prepare(&mut self, name: &str, template: QueryTemplate) -> PreparedQuery {
+        self.prepared.register(name);
+        PreparedQuery::new(name.to_string(), template)
+    }
 
-        // Execute query
+    /// Bind `params` to the placeholders of `prepared` and execute the
+    /// query with `execute`
+    ///
+    /// Fails without executing anything if `params` does not match the
+    /// template's arity.
+    pub fn execute_prepared(
+        &mut self,
+        prepared: &PreparedQuery,
+        graph: &Graph,
+        params: &[NodeId],
+    ) -> Result<QueryResult> {
+        let query = prepared.template().bind(params)?;
+        let cache_hits = self.stats.cache_hits;
         let start = Instant::now();
-        let result = self.execute_query(graph, query)?;
-        let execution_time = start.elapsed();
+        let result = self.execute(graph, &query);
+        let cache_hit = self.stats.cache_hits > cache_hits;
+        self.prepared.record(prepared.name(), start.elapsed(), cache_hit);
+        result
+    }
 
-        // Cache the result
-        self.cache.insert(query, result.clone(), execution_time);
+    /// Register a graph under `name` and return its handle
+    ///
+    /// Queries sent with `execute_on` use a cache and statistics private
+    /// to this graph, configured like the optimizer's own cache. The
+    /// landmark index is not used for registered graphs.
+    pub fn register_graph(&mut self, name: impl Into<String>, graph: Arc<Graph>) -> GraphHandle {
+        let handle = GraphHandle::new(self.next_graph_id);
+        self.next_graph_id += 1;
 
-        Ok(result)
+        let cache = QueryCache::with_config(self.cache_config.clone())
+            .with_negative_ttl(self.negative_ttl);
+        self.graphs.insert(
+            handle,
+            RegisteredGraph {
+                name: name.into(),
+                graph,
+                cache,
+                stats: OptimizerStats::default(),
+            },
+        );
+        handle
     }
 
-    /// Execute shortest path query
+    /// Unregister a graph, dropping its cached results and statistics
+    ///
+    /// Returns the graph, or `None` if the handle is unknown.
+    pub fn unregister_graph(&mut self, handle: GraphHandle) -> Option<Arc<Graph>> {
+        self.graphs.remove(&handle).map(|registered| registered.graph)
+    }
+
+    /// Name a graph was registered under
+    pub fn graph_name(&self, handle: GraphHandle) -> Option<&str> {
+        self.graphs.get(&handle).map(|registered| registered.name.as_str())
+    }
+
+    /// Execute a query on a registered graph
+    pub fn execute_on(&mut self, handle: GraphHandle, query: &Query) -> Result<QueryResult> {
+        let registered = self
+            .graphs
+            .get_mut(&handle)
+            .ok_or(OptimizerError::UnknownGraph(handle))?;
+
+        let selector = &self.algorithm_selector;
+        let graph = Arc::clone(&registered.graph);
+        let result = run_cached(
+            &registered.cache,
+            &mut registered.stats,
+            &graph,
+            &query.unshaped(),
+            |graph, query, search| execute_query(selector, None, None, graph, query, search),
+        )?;
+
+        Ok(result.paginate(0, query.result_limit()))
+    }
+
+    /// Statistics for a registered graph
+    pub fn graph_statistics(&self, handle: GraphHandle) -> Option<OptimizerStatistics> {
+        self.graphs
+            .get(&handle)
+            .map(|registered| registered.stats.snapshot(&registered.cache))
+    }
+
+    /// Execute several queries against the same graph, in order
+    pub fn execute_batch(&mut self, graph: &Graph, queries: &[Query]) -> Vec<Result<QueryResult>> {
+        queries.iter().map(|query| self.execute(graph, query)).collect()
+    }
+
+    /// Warm the cache with `queries` on a background thread
+    ///
+    /// Queries already cached for the current generation of `graph` are
+    /// skipped; the rest run in parallel against a snapshot of the graph
+    /// and their results are inserted as they complete, so `execute` calls
+    /// made meanwhile may or may not hit. Failing queries are dropped.
+    /// Prefetching bypasses the landmark, hub label and component indexes
+    /// and does not count towards the optimizer statistics.
+    pub fn prefetch(&mut self, graph: &Graph, queries: &[Query]) -> PrefetchHandle {
+        let generation = graph.generation();
+        let pending: Vec<Query> = queries
+            .iter()
+            .map(Query::unshaped)
+            .filter(|query| !self.cache.contains(query, generation))
+            .collect();
+
+        let cache = Arc::clone(&self.cache);
+        let selector = Arc::clone(&self.algorithm_selector);
+        let graph = graph.snapshot();
+        let worker = thread::spawn(move || {
+            pending
+                .par_iter()
+                .filter(|query| !cache.contains(query, generation))
+                .filter_map(|query| {
+                    let start = Instant::now();
+                    let mut search = SearchMetrics::default();
+                    let result =
+                        execute_query(&selector, None, None, &graph, query, &mut search).ok()?;
+                    cache.insert(query, result, start.elapsed(), generation);
+                    Some(())
+                })
+                .count()
+        });
+        PrefetchHandle { worker }
+    }
+
+    /// Execute a query on a worker thread, scheduled by `priority`
+    ///
+    /// See the `scheduler` module: interactive queries are started before
+    /// any queued background query, and only a bounded number of
+    /// background queries run at a time. The workers are started on first
+    /// use. The query runs against a snapshot of `graph` through the
+    /// optimizer's cache, bypassing the landmark, hub label and component
+    /// indexes like `prefetch`; it counts towards the per-priority
+    /// statistics but not the other optimizer statistics.
+    pub fn execute_with_priority(
+        &mut self,
+        graph: &Graph,
+        query: &Query,
+        priority: Priority,
+    ) -> QueryTicket {
+        let cache = Arc::clone(&self.cache);
+        let selector = Arc::clone(&self.algorithm_selector);
+        let graph = graph.snapshot();
+        let query = query.clone();
+        let background_slots = self.background_slots;
+        let scheduler = self.scheduler.get_or_insert_with(|| Scheduler::new(background_slots));
+        scheduler.submit(
+            priority,
+            Box::new(move || {
+                let mut stats = OptimizerStats::default();
+                let result = run_cached(&cache, &mut stats, &graph, &query.unshaped(), |g, q, s| {
+                    execute_query(&selector, None, None, g, q, s)
+                })?;
+                Ok(result.paginate(0, query.result_limit()))
+            }),
+        )
+    }
+
+    /// Prefetch the neighbors of each neighbor of `node`
+    ///
+    /// Meant for the call after `neighbors(graph, node)`, when the next
+    /// queries are likely to expand one of the returned nodes.
+    pub fn prefetch_neighbors_of_neighbors(
+        &mut self,
+        graph: &Graph,
+        node: NodeId,
+    ) -> Result<PrefetchHandle> {
+        let queries: Vec<Query> = graph
+            .neighbors(node)?
+            .into_iter()
+            .map(|neighbor| Query::Neighbors { node: neighbor })
+            .collect();
+        Ok(self.prefetch(graph, &queries))
+    }
+
+    /// Register a standing query, see the `subscription` module
+    ///
+    /// The first `notify_graph_changed` after subscribing always delivers
+    /// the current result; later ones only deliver changed results.
+    pub fn subscribe(&mut self, query: Query, callback: SubscriptionCallback) -> Result<SubscriptionId> {
+        self.subscriptions.insert(query, callback).ok_or(
+            OptimizerError::SubscriptionLimitReached(self.subscriptions.max_subscriptions),
+        )
+    }
+
+    /// Remove a subscription, returning whether it existed
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        self.subscriptions.remove(id)
+    }
+
+    /// Number of active subscriptions
+    pub fn subscription_count(&self) -> usize {
+        self.subscriptions.len()
+    }
+
+    /// Re-execute all subscribed queries and notify changed results
+    ///
+    /// Callbacks are invoked in subscription order, only when the result
+    /// differs from the one last delivered. A query that fails (e.g. its
+    /// node no longer exists) is skipped and keeps its previous result.
+    /// Returns the number of callbacks invoked.
+    pub fn notify_graph_changed(&mut self, graph: &Graph) -> usize {
+        let queries = self.subscriptions.queries();
+        let results = self.execute_batch(graph, &queries);
+
+        let mut notified = 0;
+        for (subscription, result) in self.subscriptions.iter_mut().zip(results) {
+            let Ok(result) = result else {
+                continue;
+            };
+            if subscription.last_result.as_ref() != Some(&result) {
+                (subscription.callback)(&result);
+                subscription.last_result = Some(result);
+                notified += 1;
+            }
+        }
+        notified
+    }
+
+    /// Execute a minimum-weight shortest path query
+    ///
+    /// The path is weight-optimal whichever algorithm serves it; use
+    /// `shortest_path_with_metric` for the path with the fewest edges.
     pub fn shortest_path(
         &mut self,
         graph: &Graph,
         start: NodeId,
         goal: NodeId,
     ) -> Result<Vec<NodeId>> {
-        let query = Query::ShortestPath { start, goal };
-        
+        self.shortest_path_with_metric(graph, start, goal, PathMetric::Weight)
+    }
+
+    /// Execute a shortest path query minimizing `metric`
+    pub fn shortest_path_with_metric(
+        &mut self,
+        graph: &Graph,
+        start: NodeId,
+        goal: NodeId,
+        metric: PathMetric,
+    ) -> Result<Vec<NodeId>> {
+        let query = Query::ShortestPath { start, goal, metric };
+
         match self.execute(graph, &query)? {
             QueryResult::Path(path) => Ok(path),
+            QueryResult::NoPath => Err(OptimizerError::GraphError(GraphError::AlgorithmError(
+                format!("No path from {} to {}", start, goal),
+            ))),
             _ => Err(OptimizerError::ExecutionError(
                 "Unexpected result type".to_string(),
             )),
@@ -87,57 +527,139 @@ impl QueryOptimizer {
         }
     }
 
-    /// Internal query execution
-    fn execute_query(&self, graph: &Graph, query: &Query) -> Result<QueryResult> {
-        match query {
-            Query::ShortestPath { start, goal } => {
-                // Use ML to select best algorithm
-                let algorithm = self.algorithm_selector.select_shortest_path(graph, *start, *goal);
-                
-                let path = match algorithm {
-                    zipgraph_core::Algorithm::BFS => algorithms::bfs(graph, *start, *goal)?,
-                    zipgraph_core::Algorithm::DFS => algorithms::dfs(graph, *start, *goal)?,
-                    zipgraph_core::Algorithm::Dijkstra => {
-                        let (path, _cost) = algorithms::dijkstra(graph, *start, *goal)?;
-                        path
-                    }
-                    _ => algorithms::dijkstra(graph, *start, *goal)?.0,
-                };
-                
-                Ok(QueryResult::Path(path))
-            }
-            Query::Neighbors { node } => {
-                let neighbors = graph.neighbors(*node)?;
-                Ok(QueryResult::Neighbors(neighbors))
-            }
-            Query::PageRank { .. } => {
-                // TODO: Implement PageRank
-                Ok(QueryResult::Scores(vec![]))
-            }
-            Query::ConnectedComponents => {
-                // TODO: Implement connected components
-                Ok(QueryResult::Components(vec![]))
+    /// Representative of the connected component containing `node`
+    ///
+    /// Components are weakly connected on directed graphs, and the
+    /// representative is the component's smallest node ID. The component
+    /// index is built or refreshed as needed.
+    pub fn component_of(&mut self, graph: &Graph, node: NodeId) -> Result<NodeId> {
+        graph.node(node)?;
+        self.component_index(graph)?
+            .component_of(node)
+            .ok_or_else(|| OptimizerError::ExecutionError(format!("Node {} is not indexed", node)))
+    }
+
+    /// Update the component index for the edge `from -> to` just added
+    ///
+    /// Saves a full rebuild on the next query when called right after
+    /// `Graph::add_edge`. If `graph` was also mutated in some other way
+    /// since the index was last used, the index is dropped and rebuilt on
+    /// the next query instead.
+    pub fn record_edge_added(&mut self, graph: &Graph, from: NodeId, to: NodeId) {
+        if let Some(index) = &mut self.components {
+            if !index.record_edge(graph, from, to) {
+                self.components = None;
             }
-            Query::ShortestPaths { .. } => {
-                // TODO: Implement all-pairs shortest paths
-                Ok(QueryResult::Path(vec![]))
+        }
+    }
+
+    /// The component index for `graph`, built or rebuilt if stale
+    fn component_index(&mut self, graph: &Graph) -> Result<&ComponentIndex> {
+        let stale = !self.components.as_ref().is_some_and(|index| index.is_current(graph));
+        if stale {
+            self.components = Some(ComponentIndex::build(graph)?);
+        }
+        Ok(self.components.as_ref().expect("component index was just built"))
+    }
+
+    /// Rebuild the landmark index if `graph` has changed since it was built
+    fn refresh_landmarks(&mut self, graph: &Graph) -> Result<()> {
+        if let Some(index) = &self.landmarks {
+            if !index.is_current(graph) {
+                self.landmarks = Some(index.rebuild(graph)?);
             }
         }
+        Ok(())
     }
 
-    /// Get optimizer statistics
+    /// Get optimizer statistics as a JSON string
     pub fn stats(&self) -> String {
-        format!(
-            "Queries: {}, Cache hits: {}, Cache misses: {}, Hit rate: {:.2}%",
-            self.stats.queries_executed,
-            self.stats.cache_hits,
-            self.stats.cache_misses,
-            if self.stats.queries_executed > 0 {
-                (self.stats.cache_hits as f64 / (self.stats.cache_hits + self.stats.cache_misses) as f64) * 100.0
-            } else {
-                0.0
-            }
-        )
+        self.stats_json().to_string()
+    }
+
+    /// Get optimizer statistics as JSON, e.g. for a stats endpoint
+    pub fn stats_json(&self) -> serde_json::Value {
+        serde_json::to_value(&self.stats).unwrap_or_default()
+    }
+
+    /// Get the query execution counters
+    pub fn optimizer_stats(&self) -> &OptimizerStats {
+        &self.stats
+    }
+
+    /// Reset all query execution counters; cached results are kept
+    pub fn reset_stats(&mut self) {
+        self.stats = OptimizerStats::default();
+        self.prepared.reset();
+    }
+
+    /// Get optimizer statistics, including per-partition cache statistics
+    pub fn statistics(&self) -> OptimizerStatistics {
+        let mut statistics = self.stats.snapshot(&self.cache);
+        if let Some(scheduler) = &self.scheduler {
+            statistics.interactive = scheduler.stats(Priority::Interactive);
+            statistics.background = scheduler.stats(Priority::Background);
+        }
+        statistics.prepared = self.prepared.stats();
+        statistics
+    }
+
+    /// Get statistics for the underlying result cache
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.stats()
+    }
+
+    /// JSON report of the cache contents and recent evictions, for
+    /// debugging
+    ///
+    /// Entries are listed hottest first, as by `QueryCache::entries`, and
+    /// evictions oldest first; durations are in milliseconds. Only the
+    /// optimizer's own cache is reported, not those of registered graphs.
+    pub fn dump_cache_report(&self) -> String {
+        let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        let stats = self.cache.stats();
+        let entries: Vec<serde_json::Value> = self
+            .cache
+            .entries()
+            .into_iter()
+            .map(|entry| {
+                serde_json::json!({
+                    "query": entry.query,
+                    "kind": entry.kind,
+                    "hit_count": entry.hit_count,
+                    "age_ms": millis(entry.age),
+                    "last_access_age_ms": millis(entry.last_access_age),
+                    "size_bytes": entry.size_bytes,
+                    "execution_time_ms": millis(entry.execution_time),
+                    "generation": entry.generation,
+                })
+            })
+            .collect();
+        let evictions: Vec<serde_json::Value> = self
+            .cache
+            .evictions_log(EVICTION_LOG_CAPACITY)
+            .into_iter()
+            .map(|eviction| {
+                serde_json::json!({
+                    "query": eviction.query,
+                    "kind": eviction.kind,
+                    "partition": eviction.partition,
+                    "reason": eviction.reason,
+                    "hit_count": eviction.hit_count,
+                    "evicted_ago_ms": millis(eviction.evicted_at.elapsed()),
+                })
+            })
+            .collect();
+
+        serde_json::json!({
+            "size": stats.size,
+            "hits": stats.total_hits,
+            "misses": stats.total_misses,
+            "collisions": stats.collisions,
+            "entries": entries,
+            "evictions": evictions,
+        })
+        .to_string()
     }
 
     /// Clear the cache
@@ -146,13 +668,260 @@ impl QueryOptimizer {
     }
 }
 
+/// A running `QueryOptimizer::prefetch`
+///
+/// Dropping the handle detaches the prefetch, which still completes.
+#[must_use = "dropping the handle detaches the prefetch"]
+pub struct PrefetchHandle {
+    worker: JoinHandle<usize>,
+}
+
+impl PrefetchHandle {
+    /// Whether all prefetched results have been inserted
+    pub fn is_finished(&self) -> bool {
+        self.worker.is_finished()
+    }
+
+    /// Wait for the prefetch and return how many queries it executed
+    pub fn join(self) -> usize {
+        self.worker.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    }
+}
+
+/// Execute `query` through `cache`, recording hits, misses, timing and
+/// search work
+fn run_cached<F>(
+    cache: &QueryCache,
+    stats: &mut OptimizerStats,
+    graph: &Graph,
+    query: &Query,
+    execute: F,
+) -> Result<QueryResult>
+where
+    F: FnOnce(&Graph, &Query, &mut SearchMetrics) -> Result<QueryResult>,
+{
+    // Check cache first
+    if let Some(cached_result) = cache.get(query, graph.generation()) {
+        stats.cache_hits += 1;
+        return Ok(cached_result);
+    }
+
+    stats.cache_misses += 1;
+
+    // Execute query
+    let start = Instant::now();
+    let result = execute(graph, query, &mut stats.search);
+    let execution_time = start.elapsed();
+    stats.record_execution(execution_time);
+    let result = result?;
+
+    // Cache the result
+    cache.insert(query, result.clone(), execution_time, graph.generation());
+
+    Ok(result)
+}
+
+/// Internal query execution
+///
+/// Shortest-path searches add their work to `search`.
+fn execute_query(
+    selector: &AlgorithmSelector,
+    landmarks: Option<&LandmarkIndex>,
+    hub_labels: Option<&HubLabelIndex>,
+    graph: &Graph,
+    query: &Query,
+    search: &mut SearchMetrics,
+) -> Result<QueryResult> {
+    // Current, exact hub labels answer weighted distances directly
+    if let Some(index) = hub_labels {
+        let answer = match query {
+            Query::ShortestPath { start, goal, metric: PathMetric::Weight } => {
+                Some((*start, *goal, index.path(*start, *goal).map(QueryResult::Path)))
+            }
+            Query::DistanceEstimate { a, b } => {
+                Some((*a, *b, index.distance(*a, *b).map(QueryResult::Distance)))
+            }
+            _ => None,
+        };
+        if let Some((a, b, result)) = answer {
+            graph.node(a)?;
+            graph.node(b)?;
+            return Ok(result.unwrap_or(QueryResult::NoPath));
+        }
+    }
+
+    // With a landmark index, weighted shortest paths use A* with the
+    // landmark bound
+    if let (
+        Query::ShortestPath {
+            start,
+            goal,
+            metric: PathMetric::Weight,
+        },
+        Some(index),
+    ) = (query, landmarks)
+    {
+        let bound = |node| index.lower_bound(node, *goal);
+        let path = algorithms::astar_instrumented(graph, *start, *goal, bound, search);
+
+        return match path {
+            Ok((path, _cost)) => Ok(QueryResult::Path(path)),
+            Err(GraphError::AlgorithmError(_)) => Ok(QueryResult::NoPath),
+            Err(e) => Err(e.into()),
+        };
+    }
+
+    match query {
+        Query::ShortestPath { start, goal, metric } => {
+            // Use ML to select the best algorithm among those honoring the
+            // metric; BFS may serve weight queries on uniform weights
+            let algorithm = selector.select_shortest_path_with_metric(graph, *start, *goal, *metric);
+
+            let path = match algorithm {
+                Algorithm::BFS => algorithms::bfs_instrumented(graph, *start, *goal, search),
+                _ => algorithms::dijkstra_instrumented(graph, *start, *goal, search)
+                    .map(|(path, _cost)| path),
+            };
+
+            match path {
+                Ok(path) => Ok(QueryResult::Path(path)),
+                // The search ran to completion without reaching the goal
+                Err(GraphError::AlgorithmError(_)) => Ok(QueryResult::NoPath),
+                // Invalid nodes may be added later, so they stay errors
+                Err(e) => Err(e.into()),
+            }
+        }
+        Query::Neighbors { node } => {
+            let neighbors = graph.neighbors(*node)?;
+            Ok(QueryResult::Neighbors(neighbors))
+        }
+        Query::PageRank { iterations, .. } => {
+            let params = PageRankParams::default().with_max_iterations(*iterations);
+            let ranks = graph.cached_pagerank(params)?;
+            Ok(QueryResult::Scores(sorted_by_score(
+                ranks.iter().map(|(&node, &rank)| (node, rank)).collect(),
+            )))
+        }
+        Query::ConnectedComponents => {
+            Ok(QueryResult::Components(algorithms::connected_components(graph)?))
+        }
+        Query::ShortestPaths { .. } => {
+            // TODO: Implement all-pairs shortest paths
+            Ok(QueryResult::Path(vec![]))
+        }
+        Query::TopKNeighborsByWeight { node, k } => {
+            let mut scores = sorted_by_score(graph.neighbors_with_weights(*node)?);
+            scores.truncate(*k);
+            Ok(QueryResult::Scores(scores))
+        }
+        Query::DistanceEstimate { a, b } => {
+            graph.node(*a)?;
+            graph.node(*b)?;
+
+            // Without a covering landmark, fall back to the exact distance
+            let estimate = landmarks.and_then(|index| index.estimate_distance(*a, *b));
+            match estimate {
+                Some(distance) => Ok(QueryResult::Distance(distance)),
+                None => match algorithms::dijkstra(graph, *a, *b) {
+                    Ok((_path, cost)) => Ok(QueryResult::Distance(cost)),
+                    Err(GraphError::AlgorithmError(_)) => Ok(QueryResult::NoPath),
+                    Err(e) => Err(e.into()),
+                },
+            }
+        }
+        Query::Degree { node } => Ok(QueryResult::Value(graph.degree(*node)? as f64)),
+        Query::GraphStats => Ok(QueryResult::Stats(graph.cached_stats())),
+        Query::KHopNeighbors { node, k } => {
+            graph.node(*node)?;
+            let mut neighbors: Vec<NodeId> =
+                parallel::k_hop_neighbors(graph, *node, *k)?.into_iter().collect();
+            neighbors.sort_unstable();
+            Ok(QueryResult::Neighbors(neighbors))
+        }
+        Query::NodeProperties { nodes, keys } => {
+            let properties = nodes
+                .iter()
+                .map(|&id| {
+                    let node = graph.node(id)?;
+                    Ok(keys
+                        .iter()
+                        .filter_map(|key| Some((key.clone(), *node.properties.get(key)?)))
+                        .collect())
+                })
+                .collect::<Result<_>>()?;
+            Ok(QueryResult::Properties(properties))
+        }
+        Query::NodeLabel { node } => Ok(QueryResult::Label(graph.node(*node)?.label.clone())),
+        Query::WithinDistance { node, max_distance } => {
+            let within = algorithms::dijkstra_within(graph, *node, max_distance.0)?;
+            let mut distances: Vec<(NodeId, f64)> = within.into_iter().collect();
+            distances.sort_by(|a, b| {
+                a.1.partial_cmp(&b.1)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+                    .then(a.0.cmp(&b.0))
+            });
+            Ok(QueryResult::Distances(distances))
+        }
+        Query::ShortestPathTree { start, weighted } => {
+            let (parents, distances) = if *weighted {
+                algorithms::dijkstra_tree(graph, *start)?
+            } else {
+                algorithms::bfs_tree(graph, *start)?
+            };
+            Ok(QueryResult::Tree { source: *start, parents, distances })
+        }
+        Query::EgoNetwork { node, radius } => {
+            Ok(QueryResult::Subgraph((&graph.ego_network(*node, *radius)?).into()))
+        }
+        Query::FilteredShortestPath { start, goal, max_edge_weight, excluded_property } => {
+            if let Some((key, _)) = excluded_property {
+                let known = graph
+                    .node_ids()
+                    .into_iter()
+                    .any(|id| graph.node(id).is_ok_and(|node| node.properties.contains_key(key)));
+                if !known {
+                    return Err(OptimizerError::ExecutionError(format!(
+                        "No node has the property '{}' to filter on",
+                        key
+                    )));
+                }
+            }
+
+            let node_allowed = |node: &Node| match excluded_property {
+                Some((key, threshold)) => {
+                    node.properties.get(key).is_none_or(|&value| value <= threshold.0)
+                }
+                None => true,
+            };
+            let edge_allowed = |_: NodeId, _: NodeId, weight: f64| {
+                max_edge_weight.is_none_or(|limit| weight < limit.0)
+            };
+            match algorithms::dijkstra_filtered(graph, *start, *goal, node_allowed, edge_allowed) {
+                Ok((path, _cost)) => Ok(QueryResult::Path(path)),
+                Err(GraphError::AlgorithmError(_)) => Ok(QueryResult::NoPath),
+                Err(e) => Err(e.into()),
+            }
+        }
+    }
+}
+
+/// Sort scores in descending order, breaking ties by node ID
+fn sorted_by_score(mut scores: Vec<(NodeId, f64)>) -> Vec<(NodeId, f64)> {
+    scores.sort_by(|a, b| {
+        b.1.partial_cmp(&a.1)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(a.0.cmp(&b.0))
+    });
+    scores
+}
+
 impl Default for QueryOptimizer {
     fn default() -> Self {
         Self::new()
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(feature = "uuid-ids")))]
 mod tests {
     use super::*;
 
@@ -171,7 +940,7 @@ mod tests {
     #[test]
     fn test_optimizer_creation() {
         let optimizer = QueryOptimizer::new();
-        assert!(optimizer.stats().contains("Queries: 0"));
+        assert_eq!(optimizer.statistics().queries_executed, 0);
     }
 
     #[test]
@@ -205,6 +974,857 @@ mod tests {
         // Second query - cache hit
         optimizer.shortest_path(&graph, 0, 2).unwrap();
 
-        assert!(optimizer.stats().contains("Cache hits: 1"));
+        assert_eq!(optimizer.statistics().cache_hits, 1);
+    }
+
+    #[test]
+    fn test_no_path_is_negatively_cached() {
+        let mut optimizer = QueryOptimizer::new();
+        let mut graph = create_test_graph();
+        let isolated = graph.add_node_simple("D");
+
+        assert!(optimizer.shortest_path(&graph, 0, isolated).is_err());
+        assert!(optimizer.shortest_path(&graph, 0, isolated).is_err());
+
+        // The second query was served from cache without running a search
+        assert_eq!(optimizer.statistics().queries_executed, 1);
+        let stats = optimizer.cache_stats();
+        assert_eq!(stats.total_hits, 1);
+        assert_eq!(stats.negative_hits, 1);
+    }
+
+    #[test]
+    fn test_partitioned_statistics() {
+        let config = QueryCacheConfig::new(100).with_partition(crate::QueryKind::Neighbors, 1);
+        let mut optimizer = QueryOptimizer::with_cache_config(config);
+        let graph = create_test_graph();
+
+        optimizer.neighbors(&graph, 0).unwrap();
+        optimizer.neighbors(&graph, 1).unwrap();
+        optimizer.shortest_path(&graph, 0, 2).unwrap();
+        optimizer.shortest_path(&graph, 0, 2).unwrap();
+
+        let statistics = optimizer.statistics();
+        assert_eq!(statistics.queries_executed, 3);
+        assert_eq!(statistics.cache_hits, 1);
+
+        let partitions = &statistics.cache.partitions;
+        assert_eq!(partitions.len(), 2);
+        assert_eq!(partitions[0].name, "Neighbors");
+        assert_eq!(partitions[0].size, 1);
+        assert_eq!(partitions[1].size, 1);
+        assert_eq!(partitions[1].hits, 1);
+    }
+
+    #[test]
+    fn test_invalid_node_is_not_cached() {
+        let mut optimizer = QueryOptimizer::new();
+        let graph = create_test_graph();
+
+        assert!(optimizer.shortest_path(&graph, 0, 99).is_err());
+        assert!(optimizer.shortest_path(&graph, 0, 99).is_err());
+
+        assert_eq!(optimizer.statistics().queries_executed, 2);
+        assert_eq!(optimizer.cache_stats().size, 0);
+    }
+
+    #[test]
+    fn test_negative_ttl_is_configurable() {
+        let mut optimizer = QueryOptimizer::new().with_negative_ttl(Duration::ZERO);
+        let mut graph = create_test_graph();
+        let isolated = graph.add_node_simple("D");
+
+        assert!(optimizer.shortest_path(&graph, 0, isolated).is_err());
+        assert!(optimizer.shortest_path(&graph, 0, isolated).is_err());
+
+        assert_eq!(optimizer.statistics().queries_executed, 2);
+    }
+
+    #[test]
+    fn test_different_limits_share_one_computation() {
+        let mut optimizer = QueryOptimizer::new();
+        let graph = create_test_graph();
+
+        let top_one = Query::PageRank { iterations: 50, top_k: Some(1) };
+        let top_two = Query::PageRank { iterations: 50, top_k: Some(2) };
+
+        let QueryResult::Scores(first) = optimizer.execute(&graph, &top_one).unwrap() else {
+            panic!("expected scores");
+        };
+        let QueryResult::Scores(second) = optimizer.execute(&graph, &top_two).unwrap() else {
+            panic!("expected scores");
+        };
+        assert_eq!(first.len(), 1);
+        assert_eq!(second.len(), 2);
+        assert_eq!(first[0], second[0]);
+
+        let neighbors = Query::Neighbors { node: 1 };
+        optimizer
+            .execute_with_options(&graph, &neighbors, &QueryOptions::default().with_limit(1))
+            .unwrap();
+        optimizer
+            .execute_with_options(&graph, &neighbors, &QueryOptions::default().with_limit(5))
+            .unwrap();
+
+        let statistics = optimizer.statistics();
+        assert_eq!(statistics.queries_executed, 2);
+        assert_eq!(statistics.cache_hits, 2);
+    }
+
+    #[test]
+    fn test_pagination() {
+        let mut optimizer = QueryOptimizer::new();
+        let mut graph = Graph::new();
+        let hub = graph.add_node_simple("hub");
+        for i in 1..=5 {
+            let leaf = graph.add_node_simple(format!("leaf{}", i));
+            graph.add_edge(hub, leaf, i as f64).unwrap();
+        }
+
+        let query = Query::TopKNeighborsByWeight { node: hub, k: 4 };
+        let QueryResult::Scores(all) = optimizer.execute(&graph, &query).unwrap() else {
+            panic!("expected scores");
+        };
+        let weights: Vec<f64> = all.iter().map(|&(_, w)| w).collect();
+        assert_eq!(weights, vec![5.0, 4.0, 3.0, 2.0]);
+
+        let options = QueryOptions::default().with_offset(1).with_limit(2);
+        let QueryResult::Scores(page) = optimizer.execute_with_options(&graph, &query, &options).unwrap() else {
+            panic!("expected scores");
+        };
+        assert_eq!(page, all[1..3].to_vec());
+
+        // An offset past the query's own limit yields an empty page
+        let options = QueryOptions::default().with_offset(4);
+        let QueryResult::Scores(page) = optimizer.execute_with_options(&graph, &query, &options).unwrap() else {
+            panic!("expected scores");
+        };
+        assert!(page.is_empty());
+        assert_eq!(optimizer.statistics().queries_executed, 1);
+    }
+
+    #[test]
+    fn test_landmark_shortest_paths() {
+        use crate::landmark::LandmarkStrategy;
+
+        let mut graph = create_test_graph();
+        let n3 = graph.add_node_simple("D");
+        graph.add_edge(0, n3, 10.0).unwrap();
+        graph.add_edge(n3, 2, 1.0).unwrap();
+
+        let index = LandmarkIndex::build(&graph, 2, LandmarkStrategy::FarthestPoint).unwrap();
+        let mut optimizer = QueryOptimizer::new().with_landmark_index(index);
+
+        let path = optimizer.shortest_path(&graph, 0, 2).unwrap();
+        assert_eq!(path, vec![0, 1, 2]);
+
+        let query = Query::DistanceEstimate { a: 0, b: 2 };
+        let QueryResult::Distance(estimate) = optimizer.execute(&graph, &query).unwrap() else {
+            panic!("expected a distance");
+        };
+        assert!(estimate >= 3.0);
+
+        // Mutating the graph makes the optimizer rebuild the stale index
+        graph.add_edge(0, 2, 0.5).unwrap();
+        assert_eq!(optimizer.shortest_path(&graph, 2, 0).unwrap(), vec![2, 0]);
+        assert!(optimizer.landmark_index().unwrap().is_current(&graph));
+    }
+
+    #[test]
+    fn test_degree_query() {
+        let mut optimizer = QueryOptimizer::new();
+        let graph = create_test_graph();
+        let query = Query::Degree { node: 1 };
+
+        assert!(matches!(optimizer.execute(&graph, &query).unwrap(), QueryResult::Value(d) if d == 2.0));
+        assert!(matches!(optimizer.execute(&graph, &query).unwrap(), QueryResult::Value(d) if d == 2.0));
+        assert_eq!(optimizer.statistics().cache_hits, 1);
+        assert!(optimizer.execute(&graph, &Query::Degree { node: 99 }).is_err());
+    }
+
+    #[test]
+    fn test_graph_stats_query_invalidates_on_mutation() {
+        let mut optimizer = QueryOptimizer::new();
+        let mut graph = create_test_graph();
+
+        let QueryResult::Stats(stats) = optimizer.execute(&graph, &Query::GraphStats).unwrap() else {
+            panic!("expected stats");
+        };
+        assert_eq!(stats.node_count, 3);
+        optimizer.execute(&graph, &Query::GraphStats).unwrap();
+        assert_eq!(optimizer.statistics().cache_hits, 1);
+
+        graph.add_node_simple("D");
+        let QueryResult::Stats(stats) = optimizer.execute(&graph, &Query::GraphStats).unwrap() else {
+            panic!("expected stats");
+        };
+        assert_eq!(stats.node_count, 4);
+        assert_eq!(optimizer.statistics().queries_executed, 2);
+    }
+
+    #[test]
+    fn test_k_hop_neighbors_query() {
+        let mut optimizer = QueryOptimizer::new();
+        let mut graph = create_test_graph();
+        let n3 = graph.add_node_simple("D");
+        graph.add_edge(2, n3, 1.0).unwrap();
+
+        let query = Query::KHopNeighbors { node: 0, k: 2 };
+        let QueryResult::Neighbors(neighbors) = optimizer.execute(&graph, &query).unwrap() else {
+            panic!("expected neighbors");
+        };
+        assert_eq!(neighbors, vec![1, 2]);
+
+        optimizer.execute(&graph, &query).unwrap();
+        assert_eq!(optimizer.statistics().cache_hits, 1);
+
+        let query = Query::KHopNeighbors { node: 0, k: 3 };
+        let QueryResult::Neighbors(neighbors) = optimizer.execute(&graph, &query).unwrap() else {
+            panic!("expected neighbors");
+        };
+        assert_eq!(neighbors, vec![1, 2, 3]);
+        assert!(optimizer.execute(&graph, &Query::KHopNeighbors { node: 99, k: 1 }).is_err());
+    }
+
+    #[test]
+    fn test_stats_json() {
+        let mut optimizer = QueryOptimizer::new();
+        let graph = create_test_graph();
+        optimizer.shortest_path(&graph, 0, 2).unwrap();
+        optimizer.shortest_path(&graph, 0, 2).unwrap();
+        optimizer.neighbors(&graph, 1).unwrap();
+
+        let json = optimizer.stats_json();
+        for field in [
+            "queries_executed",
+            "cache_hits",
+            "cache_misses",
+            "total_execution_time_ms",
+            "avg_execution_time_ms",
+        ] {
+            assert!(json.get(field).is_some(), "missing {}", field);
+        }
+        assert_eq!(json["queries_executed"], 2);
+        assert_eq!(json["cache_hits"], 1);
+
+        let parsed: OptimizerStats = serde_json::from_str(&optimizer.stats()).unwrap();
+        let stats = optimizer.optimizer_stats();
+        assert_eq!(parsed.cache_misses, stats.cache_misses);
+        assert!((parsed.total_execution_time_ms - stats.total_execution_time_ms).abs() < 1e-9);
+
+        let expected_avg = stats.total_execution_time_ms / stats.queries_executed as f64;
+        assert!((stats.avg_execution_time_ms - expected_avg).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_dump_cache_report() {
+        let mut optimizer = QueryOptimizer::with_cache_size(1);
+        let graph = create_test_graph();
+        optimizer.shortest_path(&graph, 0, 2).unwrap();
+        optimizer.shortest_path(&graph, 0, 2).unwrap();
+        optimizer.neighbors(&graph, 1).unwrap();
+
+        let report: serde_json::Value =
+            serde_json::from_str(&optimizer.dump_cache_report()).unwrap();
+        assert_eq!(report["size"], 1);
+        assert_eq!(report["collisions"], 0);
+        let entry = &report["entries"][0];
+        assert_eq!(entry["query"]["type"], "neighbors");
+        assert_eq!(entry["hit_count"], 0);
+        assert!(entry["size_bytes"].as_u64().unwrap() > 0);
+        let eviction = &report["evictions"][0];
+        assert_eq!(eviction["query"]["type"], "shortest_path");
+        assert_eq!((&eviction["reason"], &eviction["hit_count"]), (&"capacity".into(), &1.into()));
+    }
+
+    #[test]
+    fn test_stats_record_search_work() {
+        let mut optimizer = QueryOptimizer::new();
+        let graph = create_test_graph();
+        optimizer.shortest_path(&graph, 0, 2).unwrap();
+        let search = optimizer.optimizer_stats().search;
+        assert_eq!((search.nodes_expanded, search.edges_relaxed), (3, 3));
+
+        // Cache hits and non-path queries do no search work
+        optimizer.shortest_path(&graph, 0, 2).unwrap();
+        optimizer.neighbors(&graph, 1).unwrap();
+        assert_eq!(optimizer.optimizer_stats().search, search);
+        assert_eq!(optimizer.stats_json()["search"]["nodes_expanded"], 3);
+    }
+
+    #[test]
+    fn test_reset_stats() {
+        let mut optimizer = QueryOptimizer::new();
+        let graph = create_test_graph();
+        optimizer.shortest_path(&graph, 0, 2).unwrap();
+        optimizer.shortest_path(&graph, 0, 2).unwrap();
+
+        optimizer.reset_stats();
+        assert_eq!(optimizer.optimizer_stats(), &OptimizerStats::default());
+
+        // Cached results survive the reset
+        optimizer.shortest_path(&graph, 0, 2).unwrap();
+        assert_eq!(optimizer.statistics().cache_hits, 1);
+        assert_eq!(optimizer.statistics().queries_executed, 0);
+    }
+
+    #[test]
+    fn test_subscription_fires_on_change_only() {
+        use std::sync::{Arc, Mutex};
+
+        let mut optimizer = QueryOptimizer::new();
+        let mut graph = create_test_graph();
+
+        let received: Arc<Mutex<Vec<QueryResult>>> = Arc::default();
+        let sink = Arc::clone(&received);
+        optimizer
+            .subscribe(
+                Query::ShortestPath { start: 0, goal: 2, metric: PathMetric::Weight },
+                Box::new(move |result| sink.lock().unwrap().push(result.clone())),
+            )
+            .unwrap();
+
+        // The first notification delivers the current result
+        assert_eq!(optimizer.notify_graph_changed(&graph), 1);
+
+        // An unrelated edge leaves the path unchanged
+        let n3 = graph.add_node_simple("D");
+        graph.add_edge(1, n3, 1.0).unwrap();
+        assert_eq!(optimizer.notify_graph_changed(&graph), 0);
+
+        // A cheap shortcut changes it
+        graph.add_edge(0, 2, 0.5).unwrap();
+        assert_eq!(optimizer.notify_graph_changed(&graph), 1);
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[1], QueryResult::Path(vec![0, 2]));
+    }
+
+    #[test]
+    fn test_unsubscribe_and_limit() {
+        let mut optimizer = QueryOptimizer::new().with_max_subscriptions(1);
+        let graph = create_test_graph();
+
+        let id = optimizer.subscribe(Query::GraphStats, Box::new(|_| {})).unwrap();
+        assert!(matches!(
+            optimizer.subscribe(Query::GraphStats, Box::new(|_| {})),
+            Err(OptimizerError::SubscriptionLimitReached(1))
+        ));
+
+        assert!(optimizer.unsubscribe(id));
+        assert!(!optimizer.unsubscribe(id));
+        assert_eq!(optimizer.subscription_count(), 0);
+        assert_eq!(optimizer.notify_graph_changed(&graph), 0);
+    }
+
+    #[test]
+    fn test_registered_graphs_are_isolated() {
+        let mut optimizer = QueryOptimizer::new();
+
+        let mut other = Graph::new();
+        let a = other.add_node_simple("A");
+        let b = other.add_node_simple("B");
+        let c = other.add_node_simple("C");
+        other.add_edge(a, c, 1.0).unwrap();
+        other.add_edge(b, c, 1.0).unwrap();
+
+        let first = optimizer.register_graph("first", Arc::new(create_test_graph()));
+        let second = optimizer.register_graph("second", Arc::new(other));
+        assert_eq!(optimizer.graph_name(second), Some("second"));
+
+        // The same query gives each graph its own result
+        let query = Query::Neighbors { node: 0 };
+        assert_eq!(optimizer.execute_on(first, &query).unwrap(), QueryResult::Neighbors(vec![1]));
+        assert_eq!(optimizer.execute_on(second, &query).unwrap(), QueryResult::Neighbors(vec![2]));
+        optimizer.execute_on(first, &query).unwrap();
+
+        let first_stats = optimizer.graph_statistics(first).unwrap();
+        assert_eq!(first_stats.queries_executed, 1);
+        assert_eq!(first_stats.cache_hits, 1);
+        let second_stats = optimizer.graph_statistics(second).unwrap();
+        assert_eq!(second_stats.queries_executed, 1);
+        assert_eq!(second_stats.cache_hits, 0);
+        assert_eq!(optimizer.statistics().queries_executed, 0);
+
+        assert!(optimizer.unregister_graph(first).is_some());
+        assert!(optimizer.graph_statistics(first).is_none());
+        assert!(matches!(
+            optimizer.execute_on(first, &query),
+            Err(OptimizerError::UnknownGraph(handle)) if handle == first
+        ));
+        assert_eq!(optimizer.graph_statistics(second).unwrap().cache.size, 1);
+    }
+
+    fn create_two_component_graph() -> Graph {
+        let mut graph = Graph::new();
+        for i in 0..6 {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        graph.add_edge(0, 1, 1.0).unwrap();
+        graph.add_edge(1, 2, 1.0).unwrap();
+        graph.add_edge(3, 4, 1.0).unwrap();
+        graph.add_edge(4, 5, 1.0).unwrap();
+        graph
+    }
+
+    #[test]
+    fn test_component_short_circuit() {
+        let mut optimizer = QueryOptimizer::new().with_component_index();
+        let graph = create_two_component_graph();
+
+        assert_eq!(optimizer.component_of(&graph, 5).unwrap(), 3);
+
+        let cross = [
+            Query::ShortestPath { start: 0, goal: 5, metric: PathMetric::Weight },
+            Query::ShortestPath { start: 4, goal: 1, metric: PathMetric::Weight },
+            Query::DistanceEstimate { a: 2, b: 3 },
+        ];
+        for query in &cross {
+            assert_eq!(optimizer.execute(&graph, query).unwrap(), QueryResult::NoPath);
+        }
+
+        let stats = optimizer.optimizer_stats();
+        assert_eq!(stats.short_circuited_queries, 3);
+        assert_eq!(stats.queries_executed, 0);
+        assert_eq!(stats.cache_misses, 0);
+
+        // Same-component queries still execute
+        let path = optimizer.shortest_path(&graph, 0, 2).unwrap();
+        assert_eq!(path, vec![0, 1, 2]);
+        assert_eq!(optimizer.optimizer_stats().queries_executed, 1);
+
+        // Unknown nodes remain errors
+        assert!(optimizer.execute(&graph, &Query::ShortestPath { start: 0, goal: 99, metric: PathMetric::Weight }).is_err());
+        assert!(optimizer.component_of(&graph, 99).is_err());
+    }
+
+    #[test]
+    fn test_component_index_updates() {
+        let mut optimizer = QueryOptimizer::new().with_component_index();
+        let mut graph = create_two_component_graph();
+        let query = Query::ShortestPath { start: 0, goal: 5, metric: PathMetric::Weight };
+        assert_eq!(optimizer.execute(&graph, &query).unwrap(), QueryResult::NoPath);
+
+        graph.add_edge(2, 3, 1.0).unwrap();
+        optimizer.record_edge_added(&graph, 2, 3);
+        assert_eq!(optimizer.component_of(&graph, 5).unwrap(), 0);
+        assert_eq!(
+            optimizer.execute(&graph, &query).unwrap(),
+            QueryResult::Path(vec![0, 1, 2, 3, 4, 5])
+        );
+
+        // Unreported mutations trigger a rebuild
+        let isolated = graph.add_node_simple("Isolated");
+        assert_eq!(optimizer.component_of(&graph, isolated).unwrap(), isolated);
+        assert_eq!(optimizer.optimizer_stats().short_circuited_queries, 1);
+    }
+
+    #[test]
+    fn test_component_index_unreported_edge() {
+        let mut optimizer = QueryOptimizer::new().with_component_index();
+        let mut graph = create_two_component_graph();
+        let query = Query::ShortestPath { start: 0, goal: 5, metric: PathMetric::Weight };
+        assert_eq!(optimizer.execute(&graph, &query).unwrap(), QueryResult::NoPath);
+
+        // The reported edge must not mark the index current over the
+        // unreported one that joined the components
+        graph.add_edge(2, 3, 1.0).unwrap();
+        graph.add_edge(4, 5, 2.0).unwrap();
+        optimizer.record_edge_added(&graph, 4, 5);
+        assert_eq!(
+            optimizer.execute(&graph, &query).unwrap(),
+            QueryResult::Path(vec![0, 1, 2, 3, 4, 5])
+        );
+    }
+
+    #[test]
+    fn test_component_index_count_preserving_batch() {
+        use zipgraph_core::{Edge, GraphOp};
+
+        let mut optimizer = QueryOptimizer::new().with_component_index();
+        let mut graph = create_two_component_graph();
+        let query = Query::ShortestPath { start: 1, goal: 3, metric: PathMetric::Hops };
+        assert_eq!(optimizer.execute(&graph, &query).unwrap(), QueryResult::NoPath);
+
+        // Same node and edge counts, but 1 and 3 are now connected
+        graph
+            .apply_batch(&[
+                GraphOp::RemoveEdge { from: 4, to: 5 },
+                GraphOp::AddEdge(Edge::new(2, 3, 1.0)),
+            ])
+            .unwrap();
+        graph.add_edge(0, 2, 1.0).unwrap();
+        optimizer.record_edge_added(&graph, 0, 2);
+        assert_eq!(optimizer.execute(&graph, &query).unwrap(), QueryResult::Path(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn test_connected_components_query() {
+        let mut optimizer = QueryOptimizer::new();
+        let graph = create_two_component_graph();
+        assert_eq!(
+            optimizer.execute(&graph, &Query::ConnectedComponents).unwrap(),
+            QueryResult::Components(vec![vec![0, 1, 2], vec![3, 4, 5]])
+        );
+    }
+
+    /// A heavy direct edge 0 - 1 next to a light detour 0 - 2 - 1, padded
+    /// with a chain of `padding` extra nodes
+    fn create_detour_graph(padding: usize) -> Graph {
+        let mut graph = Graph::new();
+        for i in 0..(3 + padding) {
+            graph.add_node_simple(format!("Node{}", i));
+        }
+        graph.add_edge(0, 1, 10.0).unwrap();
+        graph.add_edge(0, 2, 1.0).unwrap();
+        graph.add_edge(2, 1, 1.0).unwrap();
+        for i in 3..(2 + padding) {
+            graph.add_edge(i, i + 1, 1.0).unwrap();
+        }
+        graph
+    }
+
+    #[test]
+    fn test_shortest_path_metric_regardless_of_size() {
+        for padding in [0, 2000] {
+            let graph = create_detour_graph(padding);
+            assert_eq!(algorithms::bfs(&graph, 0, 1).unwrap(), vec![0, 1]);
+            let (dijkstra, _cost) = algorithms::dijkstra(&graph, 0, 1).unwrap();
+            assert_eq!(dijkstra, vec![0, 2, 1]);
+
+            let mut optimizer = QueryOptimizer::new();
+            assert_eq!(optimizer.shortest_path(&graph, 0, 1).unwrap(), dijkstra);
+            assert_eq!(
+                optimizer
+                    .shortest_path_with_metric(&graph, 0, 1, PathMetric::Hops)
+                    .unwrap(),
+                vec![0, 1]
+            );
+        }
+    }
+
+    #[test]
+    fn test_node_properties_query() {
+        let mut graph = Graph::new();
+        graph.add_node(zipgraph_core::Node::new(0, "A").with_property("score", 0.5));
+        graph.add_node(
+            zipgraph_core::Node::new(1, "B").with_property("score", 2.0).with_property("rank", 1.0),
+        );
+        let mut optimizer = QueryOptimizer::new();
+
+        let score = Query::NodeProperties { nodes: vec![1, 0], keys: vec!["score".to_string()] };
+        match optimizer.execute(&graph, &score).unwrap() {
+            QueryResult::Properties(properties) => {
+                assert_eq!(properties.len(), 2);
+                assert_eq!(properties[0]["score"], 2.0);
+                assert_eq!(properties[1]["score"], 0.5);
+            }
+            other => panic!("expected properties, got {:?}", other),
+        }
+
+        let missing = Query::NodeProperties { nodes: vec![0, 1], keys: vec!["age".to_string()] };
+        assert_eq!(
+            optimizer.execute(&graph, &missing).unwrap(),
+            QueryResult::Properties(vec![Default::default(), Default::default()])
+        );
+        // Different keys for the same nodes are separate cache entries
+        assert_eq!(optimizer.statistics().cache_misses, 2);
+        optimizer.execute(&graph, &score).unwrap();
+        assert_eq!(optimizer.statistics().cache_hits, 1);
+
+        let unknown = Query::NodeProperties { nodes: vec![5], keys: vec!["score".to_string()] };
+        assert!(optimizer.execute(&graph, &unknown).is_err());
+
+        assert_eq!(
+            optimizer.execute(&graph, &Query::NodeLabel { node: 1 }).unwrap(),
+            QueryResult::Label("B".to_string())
+        );
+    }
+
+    #[test]
+    fn test_within_distance_query() {
+        let mut optimizer = QueryOptimizer::new();
+        let graph = create_test_graph();
+
+        let query = Query::WithinDistance { node: 0, max_distance: 1.0.into() };
+        assert_eq!(
+            optimizer.execute(&graph, &query).unwrap(),
+            QueryResult::Distances(vec![(0, 0.0), (1, 1.0)])
+        );
+        let query = Query::WithinDistance { node: 2, max_distance: 3.0.into() };
+        assert_eq!(
+            optimizer.execute(&graph, &query).unwrap(),
+            QueryResult::Distances(vec![(2, 0.0), (1, 2.0), (0, 3.0)])
+        );
+        let unknown = Query::WithinDistance { node: 9, max_distance: 1.0.into() };
+        assert!(optimizer.execute(&graph, &unknown).is_err());
+    }
+
+    #[test]
+    fn test_filtered_shortest_path_query() {
+        // 0 -> 1 -> 4 is cheapest but crosses the high-risk node 1; of the
+        // detours, 0 -> 2 -> 4 uses a heavy edge and 0 -> 3 -> 4 is safe
+        let mut graph = Graph::new();
+        graph.add_node(Node::new(0, "A").with_property("risk", 0.1));
+        graph.add_node(Node::new(1, "B").with_property("risk", 0.9));
+        graph.add_node(Node::new(2, "C").with_property("risk", 0.2));
+        graph.add_node(Node::new(3, "D"));
+        graph.add_node(Node::new(4, "E"));
+        for (from, to, weight) in [(0, 1, 1.0), (1, 4, 1.0), (0, 2, 1.0), (2, 4, 150.0)] {
+            graph.add_edge(from, to, weight).unwrap();
+        }
+        graph.add_edge(0, 3, 40.0).unwrap();
+        graph.add_edge(3, 4, 40.0).unwrap();
+
+        let mut optimizer = QueryOptimizer::new();
+        let filtered = |max_edge_weight: Option<f64>, excluded: Option<(&str, f64)>| {
+            Query::FilteredShortestPath {
+                start: 0,
+                goal: 4,
+                max_edge_weight: max_edge_weight.map(Into::into),
+                excluded_property: excluded.map(|(key, value)| (key.to_string(), value.into())),
+            }
+        };
+
+        let unconstrained = filtered(None, None);
+        let path = optimizer.execute(&graph, &unconstrained).unwrap();
+        assert_eq!(path, QueryResult::Path(vec![0, 1, 4]));
+        let avoiding_risk = filtered(None, Some(("risk", 0.8)));
+        let path = optimizer.execute(&graph, &avoiding_risk).unwrap();
+        assert_eq!(path, QueryResult::Path(vec![0, 3, 4]));
+        let both = filtered(Some(100.0), Some(("risk", 0.8)));
+        assert_eq!(optimizer.execute(&graph, &both).unwrap(), QueryResult::Path(vec![0, 3, 4]));
+        let too_strict = filtered(Some(10.0), Some(("risk", 0.8)));
+        assert_eq!(optimizer.execute(&graph, &too_strict).unwrap(), QueryResult::NoPath);
+
+        // Cached like any other query
+        optimizer.execute(&graph, &avoiding_risk).unwrap();
+        assert_eq!(optimizer.statistics().cache_hits, 1);
+
+        let unknown_property = filtered(None, Some(("fraud", 0.5)));
+        match optimizer.execute(&graph, &unknown_property) {
+            Err(OptimizerError::ExecutionError(message)) => assert!(message.contains("'fraud'")),
+            other => panic!("expected a validation error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_hub_labels_answer_shortest_paths() {
+        let mut graph = create_test_graph();
+        graph.add_edge(0, 2, 5.0).unwrap();
+        let index = HubLabelIndex::build(&graph).unwrap();
+        let mut optimizer = QueryOptimizer::new().with_hub_labels(index);
+
+        assert_eq!(optimizer.shortest_path(&graph, 0, 2).unwrap(), vec![0, 1, 2]);
+        let query = Query::DistanceEstimate { a: 2, b: 0 };
+        assert_eq!(optimizer.execute(&graph, &query).unwrap(), QueryResult::Distance(3.0));
+        assert!(optimizer.shortest_path(&graph, 0, 9).is_err());
+
+        // A stale index is ignored rather than giving outdated answers
+        graph.add_edge(0, 2, 0.5).unwrap();
+        assert!(!optimizer.hub_labels().unwrap().is_current(&graph));
+        assert_eq!(optimizer.shortest_path(&graph, 0, 2).unwrap(), vec![0, 2]);
+    }
+
+    #[test]
+    fn test_ego_network_query() {
+        let mut graph = Graph::new();
+        for label in ["A", "B", "C", "D"] {
+            graph.add_node_simple(label);
+        }
+        graph.add_edge(0, 1, 1.0).unwrap();
+        graph.add_typed_edge(1, 2, 2.0, "follows").unwrap();
+        graph.add_edge(2, 3, 3.0).unwrap();
+
+        let mut optimizer = QueryOptimizer::new();
+        let query = Query::EgoNetwork { node: 1, radius: 1 };
+        let QueryResult::Subgraph(ego) = optimizer.execute(&graph, &query).unwrap() else {
+            panic!("expected a subgraph");
+        };
+        assert_eq!(ego.nodes.iter().map(|node| node.id).collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert_eq!(ego.edges.len(), 2);
+        assert_eq!(ego.edges[1].edge_type, "follows");
+
+        let rebuilt = ego.to_graph();
+        assert_eq!(rebuilt.node_count(), 3);
+        assert_eq!(rebuilt.edge_count(), 2);
+        assert_eq!(rebuilt.neighbors_of_type(1, "follows").unwrap(), vec![2]);
+
+        optimizer.execute(&graph, &query).unwrap();
+        assert_eq!(optimizer.statistics().cache_hits, 1);
+        assert!(optimizer.execute(&graph, &Query::EgoNetwork { node: 9, radius: 1 }).is_err());
+    }
+
+    #[test]
+    fn test_prefetch_warms_cache() {
+        let graph = create_test_graph();
+        let mut optimizer = QueryOptimizer::new();
+        let queries = [
+            Query::ShortestPath { start: 0, goal: 2, metric: PathMetric::Weight },
+            Query::PageRank { iterations: 20, top_k: Some(2) },
+        ];
+
+        assert_eq!(optimizer.prefetch(&graph, &queries).join(), 2);
+        for query in &queries {
+            optimizer.execute(&graph, query).unwrap();
+        }
+        let statistics = optimizer.statistics();
+        assert_eq!(statistics.cache_hits, 2);
+        assert_eq!(statistics.queries_executed, 0);
+
+        // Unknown nodes fail in the background and are not cached
+        let unknown = [Query::Neighbors { node: 9 }];
+        assert_eq!(optimizer.prefetch(&graph, &unknown).join(), 0);
+        assert!(optimizer.execute(&graph, &unknown[0]).is_err());
+    }
+
+    #[test]
+    fn test_prefetch_skips_cached_queries() {
+        let mut graph = create_test_graph();
+        let mut optimizer = QueryOptimizer::new();
+        optimizer.neighbors(&graph, 1).unwrap();
+
+        let handle = optimizer.prefetch_neighbors_of_neighbors(&graph, 1).unwrap();
+        assert_eq!(handle.join(), 2);
+        let handle = optimizer.prefetch_neighbors_of_neighbors(&graph, 1).unwrap();
+        assert_eq!(handle.join(), 0);
+        assert_eq!(optimizer.neighbors(&graph, 2).unwrap(), vec![1]);
+        assert_eq!(optimizer.statistics().cache_hits, 1);
+
+        // A mutation makes the prefetched results stale, so they run again
+        graph.add_node_simple("D");
+        let handle = optimizer.prefetch_neighbors_of_neighbors(&graph, 1).unwrap();
+        assert_eq!(handle.join(), 2);
+        assert!(optimizer.prefetch_neighbors_of_neighbors(&graph, 9).is_err());
+    }
+
+    #[test]
+    fn test_shortest_path_tree_query() {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(11);
+        for directed in [false, true] {
+            let mut graph = if directed { Graph::new_directed() } else { Graph::new() };
+            for i in 0..40 {
+                graph.add_node_simple(format!("N{}", i));
+            }
+            for _ in 0..120 {
+                let (from, to) = (rng.gen_range(0..40), rng.gen_range(0..40));
+                graph.add_edge(from, to, rng.gen_range(0.1..10.0)).unwrap();
+            }
+
+            let mut optimizer = QueryOptimizer::new();
+            for weighted in [true, false] {
+                let query = Query::ShortestPathTree { start: 0, weighted };
+                let result = optimizer.execute(&graph, &query).unwrap();
+                let QueryResult::Tree { source, parents, distances } = &result else {
+                    panic!("expected a tree");
+                };
+                assert_eq!((*source, distances[&0]), (0, 0.0));
+                assert!(!parents.contains_key(&0));
+                assert_eq!(parents.len() + 1, distances.len());
+                for (&child, &parent) in parents {
+                    let step = graph
+                        .neighbors_with_weights(parent)
+                        .unwrap()
+                        .into_iter()
+                        .filter(|&(to, _)| to == child)
+                        .map(|(_, weight)| if weighted { weight } else { 1.0 })
+                        .fold(f64::INFINITY, f64::min);
+                    assert!((distances[&child] - (distances[&parent] + step)).abs() < 1e-9);
+                }
+
+                let edges = result.tree_to_edges().unwrap();
+                assert_eq!(edges.len(), parents.len());
+                assert!(edges.windows(2).all(|pair| pair[0].2 <= pair[1].2));
+            }
+        }
+
+        let graph = create_test_graph();
+        let mut optimizer = QueryOptimizer::new();
+        let query = Query::ShortestPathTree { start: 0, weighted: true };
+        let tree = optimizer.execute(&graph, &query).unwrap();
+        assert_eq!(tree.tree_to_edges().unwrap(), vec![(0, 1, 1.0), (1, 2, 3.0)]);
+        optimizer.execute(&graph, &query).unwrap();
+        assert_eq!(optimizer.statistics().cache_hits, 1);
+        assert_eq!(QueryResult::NoPath.tree_to_edges(), None);
+        let unknown = Query::ShortestPathTree { start: 9, weighted: false };
+        assert!(optimizer.execute(&graph, &unknown).is_err());
+    }
+    #[test]
+    fn test_interactive_queries_overtake_background_work() {
+        let graph = zipgraph_core::generators::erdos_renyi(150, 0.04, false, Some(3));
+        let mut optimizer = QueryOptimizer::new().with_background_slots(1);
+        let background: Vec<QueryTicket> = (0..12)
+            .map(|i| {
+                let query = Query::PageRank { iterations: 100 + i, top_k: Some(1) };
+                optimizer.execute_with_priority(&graph, &query, Priority::Background)
+            })
+            .collect();
+
+        let start = Instant::now();
+        let query = Query::Neighbors { node: 7 };
+        let neighbors = optimizer.execute_with_priority(&graph, &query, Priority::Interactive);
+        assert_eq!(neighbors.wait().unwrap(), optimizer.execute(&graph, &query).unwrap());
+        let elapsed = start.elapsed();
+        let statistics = optimizer.statistics();
+        assert!(statistics.background.queued >= 6, "{:?}", statistics.background);
+        assert!(elapsed < Duration::from_secs(1), "{:?}", elapsed);
+        assert_eq!(statistics.interactive.count, 1);
+        assert!(statistics.interactive.p95_latency_ms < 1000.0);
+        // Scheduled queries share the cache, so the direct query hit
+        assert_eq!(statistics.cache_hits, 1);
+
+        for ticket in background {
+            assert!(matches!(ticket.wait().unwrap(), QueryResult::Scores(_)));
+        }
+        let statistics = optimizer.statistics();
+        assert_eq!((statistics.background.count, statistics.background.queued), (12, 0));
+    }
+    #[test]
+    fn test_prepared_queries() {
+        let mut optimizer = QueryOptimizer::new();
+        let graph = create_test_graph();
+        let template = QueryTemplate::new(Query::ShortestPath {
+            start: 0,
+            goal: 0,
+            metric: PathMetric::Weight,
+        });
+        assert_eq!(template.arity(), 2);
+        let route = optimizer.prepare("route", template);
+        let degree = optimizer.prepare("degree", QueryTemplate::new(Query::Degree { node: 0 }));
+
+        assert!(matches!(
+            optimizer.execute_prepared(&route, &graph, &[0]),
+            Err(OptimizerError::ParseError(_))
+        ));
+        assert!(optimizer.execute_prepared(&degree, &graph, &[0, 1]).is_err());
+        assert_eq!(optimizer.statistics().queries_executed, 0);
+
+        // Each binding has its own cache entry
+        let path = optimizer.execute_prepared(&route, &graph, &[0, 2]).unwrap();
+        assert_eq!(path, QueryResult::Path(vec![0, 1, 2]));
+        let back = optimizer.execute_prepared(&route, &graph, &[2, 0]).unwrap();
+        assert_eq!(back, QueryResult::Path(vec![2, 1, 0]));
+        optimizer.execute_prepared(&route, &graph, &[0, 2]).unwrap();
+        optimizer.execute_prepared(&route, &graph, &[2, 0]).unwrap();
+        assert_eq!(optimizer.statistics().cache_misses, 2);
+
+        optimizer.execute_prepared(&degree, &graph, &[1]).unwrap();
+        let prepared = optimizer.statistics().prepared;
+        let route_stats = &prepared["route"];
+        assert_eq!((route_stats.count, route_stats.cache_hits), (4, 2));
+        assert_eq!(route_stats.hit_rate, 0.5);
+        assert!(route_stats.p95_latency_ms >= 0.0);
+        assert_eq!((prepared["degree"].count, prepared["degree"].cache_hits), (1, 0));
+
+        // Preparing again keeps the counters; resetting zeroes them
+        let route = optimizer.prepare("route", route.template().clone());
+        optimizer.execute_prepared(&route, &graph, &[0, 2]).unwrap();
+        assert_eq!(optimizer.statistics().prepared["route"].count, 5);
+        optimizer.reset_stats();
+        assert_eq!(optimizer.statistics().prepared["route"], PreparedStats::default());
     }
 }
@@ -0,0 +1,167 @@
+//! Named queries prepared once and executed with different nodes
+//!
+//! A `QueryTemplate` is a query whose node arguments are placeholders.
+//! `QueryOptimizer::prepare` names it and returns a `PreparedQuery`;
+//! `QueryOptimizer::execute_prepared` binds the placeholders to concrete
+//! nodes and executes the bound query like any other, cache included, so
+//! two bindings of one template are cached independently. The optimizer
+//! keeps statistics per name.
+
+use crate::error::{OptimizerError, Result};
+use crate::query::Query;
+use crate::scheduler::Latencies;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use zipgraph_core::NodeId;
+
+/// A query with placeholder node arguments
+///
+/// The placeholders are the node arguments of the wrapped query, in the
+/// order its variant declares them: `start` then `goal` for a
+/// `ShortestPath`, `a` then `b` for a `DistanceEstimate`, every entry of
+/// `nodes` for `NodeProperties`. Their values in the template are ignored.
+/// Queries without node arguments, such as `GraphStats`, take no
+/// parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryTemplate {
+    query: Query,
+    arity: usize,
+}
+
+impl QueryTemplate {
+    /// Template whose placeholders are the node arguments of `query`
+    pub fn new(mut query: Query) -> Self {
+        let arity = node_slots(&mut query).len();
+        Self { query, arity }
+    }
+
+    /// Number of nodes a binding must supply
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    /// The query with its placeholders replaced by `params`, in order
+    pub fn bind(&self, params: &[NodeId]) -> Result<Query> {
+        if params.len() != self.arity {
+            return Err(OptimizerError::ParseError(format!(
+                "{} query takes {} node parameters, got {}",
+                self.query.type_name(),
+                self.arity,
+                params.len()
+            )));
+        }
+
+        let mut query = self.query.clone();
+        for (slot, &node) in node_slots(&mut query).into_iter().zip(params) {
+            *slot = node;
+        }
+        Ok(query)
+    }
+}
+
+/// The node arguments of `query`, in declaration order
+fn node_slots(query: &mut Query) -> Vec<&mut NodeId> {
+    match query {
+        Query::ShortestPath { start, goal, .. }
+        | Query::FilteredShortestPath { start, goal, .. } => vec![start, goal],
+        Query::DistanceEstimate { a, b } => vec![a, b],
+        Query::Neighbors { node }
+        | Query::TopKNeighborsByWeight { node, .. }
+        | Query::Degree { node }
+        | Query::KHopNeighbors { node, .. }
+        | Query::NodeLabel { node }
+        | Query::WithinDistance { node, .. }
+        | Query::EgoNetwork { node, .. } => vec![node],
+        Query::ShortestPaths { start } | Query::ShortestPathTree { start, .. } => vec![start],
+        Query::NodeProperties { nodes, .. } => nodes.iter_mut().collect(),
+        Query::PageRank { .. } | Query::ConnectedComponents | Query::GraphStats => Vec::new(),
+    }
+}
+
+/// A template registered with `QueryOptimizer::prepare`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreparedQuery {
+    name: String,
+    template: QueryTemplate,
+}
+
+impl PreparedQuery {
+    pub(crate) fn new(name: String, template: QueryTemplate) -> Self {
+        Self { name, template }
+    }
+
+    /// Name the query was prepared under
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Template bound by each execution
+    pub fn template(&self) -> &QueryTemplate {
+        &self.template
+    }
+}
+
+/// Counters for the executions of one prepared query
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PreparedStats {
+    /// Executions, failed ones included
+    pub count: usize,
+    /// Executions answered from the cache
+    pub cache_hits: usize,
+    /// `cache_hits / count`, or 0 before any execution
+    pub hit_rate: f64,
+    /// 95th percentile of the execution time over the most recent
+    /// executions
+    pub p95_latency_ms: f64,
+}
+
+#[derive(Default)]
+struct Usage {
+    cache_hits: usize,
+    latencies: Latencies,
+}
+
+/// Per-name usage of the prepared queries of one optimizer
+#[derive(Default)]
+pub(crate) struct PreparedQueries {
+    usage: BTreeMap<String, Usage>,
+}
+
+impl PreparedQueries {
+    /// Start reporting `name`; preparing a name again keeps its counters
+    pub(crate) fn register(&mut self, name: &str) {
+        self.usage.entry(name.to_string()).or_default();
+    }
+
+    pub(crate) fn record(&mut self, name: &str, latency: Duration, cache_hit: bool) {
+        let usage = self.usage.entry(name.to_string()).or_default();
+        usage.cache_hits += usize::from(cache_hit);
+        usage.latencies.record(latency);
+    }
+
+    /// Zero every counter, keeping the registered names
+    pub(crate) fn reset(&mut self) {
+        for usage in self.usage.values_mut() {
+            *usage = Usage::default();
+        }
+    }
+
+    pub(crate) fn stats(&self) -> BTreeMap<String, PreparedStats> {
+        self.usage
+            .iter()
+            .map(|(name, usage)| {
+                let count = usage.latencies.count;
+                let hit_rate =
+                    if count == 0 { 0.0 } else { usage.cache_hits as f64 / count as f64 };
+                let stats = PreparedStats {
+                    count,
+                    cache_hits: usage.cache_hits,
+                    hit_rate,
+                    p95_latency_ms: usage.latencies.p95_ms(),
+                };
+                (name.clone(), stats)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,228 @@
+//! Versioned envelopes for serving queries over the wire
+//!
+//! A client sends a `QueryEnvelope` and receives a `ResponseEnvelope` with
+//! the same `id`. Both are plain JSON objects:
+//!
+//! ```json
+//! {"protocol_version": 1, "id": 7, "query": {"type": "neighbors", "node": 3}}
+//! {"protocol_version": 1, "id": 7, "result": {"Ok": {"type": "neighbors", "value": [1, 4]}}}
+//! {"protocol_version": 1, "id": 8, "result": {"Err": "Graph error: Node 99 not found"}}
+//! ```
+//!
+//! See the `query` module for the encoding of queries and results. The
+//! protocol version is bumped whenever an existing message would decode
+//! differently; envelopes from a newer version are rejected so that a
+//! client never misreads a message it does not understand.
+//!
+//! ## Version 0
+//!
+//! Releases before envelopes existed exchanged bare queries and results
+//! in serde's default externally tagged form, e.g.
+//! `{"ShortestPath": {"start": 0, "goal": 42}}`, `"GraphStats"` or
+//! `{"Path": [0, 3, 42]}`. Version 1 is a wire break from that encoding:
+//! such messages do not decode as version 1 queries or results.
+//! `decode_legacy_query` and `decode_legacy_result` read them for clients
+//! that have not migrated yet.
+
+use crate::error::{OptimizerError, Result};
+use crate::query::{Query, QueryResult};
+use serde::{Deserialize, Serialize};
+
+/// Version of the wire protocol spoken by this crate
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Version of the unversioned encoding used before envelopes existed
+pub const LEGACY_PROTOCOL_VERSION: u32 = 0;
+
+/// A query request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryEnvelope {
+    pub protocol_version: u32,
+    /// Client-chosen ID echoed in the response
+    pub id: u64,
+    pub query: Query,
+}
+
+/// The response to a `QueryEnvelope`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseEnvelope {
+    pub protocol_version: u32,
+    /// ID of the request this answers
+    pub id: u64,
+    /// The result, or the error message if the query failed
+    pub result: std::result::Result<QueryResult, String>,
+}
+
+impl QueryEnvelope {
+    /// Wrap `query` for the current protocol version
+    pub fn new(id: u64, query: Query) -> Self {
+        Self {
+            protocol_version: PROTOCOL_VERSION,
+            id,
+            query,
+        }
+    }
+
+    /// Decode a request, rejecting newer protocol versions
+    pub fn from_json(json: &str) -> Result<Self> {
+        let envelope: Self = decode(json)?;
+        check_version(envelope.protocol_version)?;
+        Ok(envelope)
+    }
+
+    /// Encode the request as JSON
+    pub fn to_json(&self) -> Result<String> {
+        encode(self)
+    }
+
+    /// Build the response for this request from an execution outcome
+    pub fn respond(&self, result: Result<QueryResult>) -> ResponseEnvelope {
+        ResponseEnvelope::new(self.id, result)
+    }
+}
+
+impl ResponseEnvelope {
+    /// Wrap an execution outcome for the current protocol version
+    pub fn new(id: u64, result: Result<QueryResult>) -> Self {
+        Self {
+            protocol_version: PROTOCOL_VERSION,
+            id,
+            result: result.map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Decode a response, rejecting newer protocol versions
+    pub fn from_json(json: &str) -> Result<Self> {
+        let envelope: Self = decode(json)?;
+        check_version(envelope.protocol_version)?;
+        Ok(envelope)
+    }
+
+    /// Encode the response as JSON
+    pub fn to_json(&self) -> Result<String> {
+        encode(self)
+    }
+}
+
+/// Decode a bare query in the version 0 encoding
+pub fn decode_legacy_query(json: &str) -> Result<Query> {
+    let tagged = retag_legacy(decode(json)?, |payload, tagged| match payload {
+        serde_json::Value::Object(fields) => {
+            tagged.extend(fields);
+            Ok(())
+        }
+        other => Err(legacy_error(&other)),
+    })?;
+    decode_value(tagged)
+}
+
+/// Decode a bare result in the version 0 encoding
+pub fn decode_legacy_result(json: &str) -> Result<QueryResult> {
+    let tagged = retag_legacy(decode(json)?, |payload, tagged| {
+        tagged.insert("value".to_string(), payload);
+        Ok(())
+    })?;
+    decode_value(tagged)
+}
+
+/// Rewrite an externally tagged enum value into the `"type"`-tagged form,
+/// with `add_payload` placing a newtype or struct variant's payload
+fn retag_legacy<F>(legacy: serde_json::Value, add_payload: F) -> Result<serde_json::Value>
+where
+    F: FnOnce(serde_json::Value, &mut serde_json::Map<String, serde_json::Value>) -> Result<()>,
+{
+    let mut tagged = serde_json::Map::new();
+    match legacy {
+        serde_json::Value::String(variant) => {
+            tagged.insert("type".to_string(), snake_case(&variant).into());
+        }
+        serde_json::Value::Object(entries) if entries.len() == 1 => {
+            let (variant, payload) = entries.into_iter().next().expect("one entry");
+            add_payload(payload, &mut tagged)?;
+            tagged.insert("type".to_string(), snake_case(&variant).into());
+        }
+        other => return Err(legacy_error(&other)),
+    }
+    Ok(serde_json::Value::Object(tagged))
+}
+
+fn legacy_error(value: &serde_json::Value) -> OptimizerError {
+    OptimizerError::ProtocolError(format!("Not a version 0 message: {}", value))
+}
+
+/// `KHopNeighbors` -> `k_hop_neighbors`
+fn snake_case(variant: &str) -> String {
+    let mut name = String::with_capacity(variant.len() + 4);
+    for (i, c) in variant.chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            name.push('_');
+        }
+        name.push(c.to_ascii_lowercase());
+    }
+    name
+}
+
+fn check_version(version: u32) -> Result<()> {
+    if version > PROTOCOL_VERSION {
+        return Err(OptimizerError::ProtocolError(format!(
+            "Unsupported protocol version {} (latest supported is {})",
+            version, PROTOCOL_VERSION
+        )));
+    }
+    Ok(())
+}
+
+fn decode<T: for<'de> Deserialize<'de>>(json: &str) -> Result<T> {
+    serde_json::from_str(json).map_err(|e| OptimizerError::ProtocolError(e.to_string()))
+}
+
+fn decode_value<T: for<'de> Deserialize<'de>>(value: serde_json::Value) -> Result<T> {
+    serde_json::from_value(value).map_err(|e| OptimizerError::ProtocolError(e.to_string()))
+}
+
+fn encode<T: Serialize>(value: &T) -> Result<String> {
+    serde_json::to_string(value).map_err(|e| OptimizerError::ProtocolError(e.to_string()))
+}
+
+#[cfg(all(test, not(feature = "uuid-ids")))]
+mod tests {
+    use super::*;
+    use zipgraph_core::PathMetric;
+
+    #[test]
+    fn test_envelope_roundtrip() {
+        let request = QueryEnvelope::new(7, Query::ShortestPath { start: 0, goal: 42, metric: PathMetric::Weight });
+        let decoded = QueryEnvelope::from_json(&request.to_json().unwrap()).unwrap();
+        assert_eq!(decoded, request);
+
+        let response = request.respond(Err(OptimizerError::ExecutionError("boom".to_string())));
+        let decoded = ResponseEnvelope::from_json(&response.to_json().unwrap()).unwrap();
+        assert_eq!(decoded.id, 7);
+        assert_eq!(decoded.result, Err("Query execution failed: boom".to_string()));
+    }
+
+    #[test]
+    fn test_newer_version_rejected() {
+        let json = r#"{"protocol_version": 2, "id": 1, "query": {"type": "graph_stats"}}"#;
+        assert!(matches!(
+            QueryEnvelope::from_json(json),
+            Err(OptimizerError::ProtocolError(_))
+        ));
+        assert!(QueryEnvelope::from_json("{}").is_err());
+    }
+
+    #[test]
+    fn test_legacy_messages() {
+        assert_eq!(
+            decode_legacy_query(r#"{"KHopNeighbors": {"node": 7, "k": 2}}"#).unwrap(),
+            Query::KHopNeighbors { node: 7, k: 2 }
+        );
+        assert_eq!(decode_legacy_query(r#""GraphStats""#).unwrap(), Query::GraphStats);
+        assert_eq!(decode_legacy_result(r#""NoPath""#).unwrap(), QueryResult::NoPath);
+
+        // The version 1 encoding is not a version 0 message
+        assert!(decode_legacy_query(r#"{"type": "degree", "node": 7}"#).is_err());
+        assert!(decode_legacy_query(r#"{"Degree": 7}"#).is_err());
+        assert!(serde_json::from_str::<Query>(r#"{"Degree": {"node": 7}}"#).is_err());
+    }
+}
@@ -1,37 +1,594 @@
 //! Query representation and execution
+//!
+//! Some queries carry result-shaping parameters (`PageRank::top_k`,
+//! `TopKNeighborsByWeight::k`) and `QueryOptions` adds generic pagination.
+//! Shaping never changes what is computed: the optimizer computes and
+//! caches the unshaped query (`Query::unshaped`) and trims a copy of the
+//! full result, so queries differing only in their limits share one
+//! computation and one cache entry.
+//!
+//! ## Wire encoding
+//!
+//! `Query` and `QueryResult` have a pinned JSON representation that is part
+//! of the wire protocol (see `protocol`). A query is an object whose
+//! `"type"` is the snake_case variant name and whose other members are the
+//! variant's fields, e.g. `{"type": "shortest_path", "start": 0, "goal":
+//! 42}`. A result is an object with a snake_case `"type"` and, unless the
+//! variant is `no_path`, a `"value"` member holding its payload, e.g.
+//! `{"type": "path", "value": [0, 3, 42]}`. Renaming a variant or field
+//! breaks clients; the fixtures under `tests/fixtures` pin the encoding.
+//! Fields added since are optional and omitted at their default, like the
+//! `metric` of a shortest path, so existing messages keep their meaning.
 
+use crate::error::{OptimizerError, Result};
 use serde::{Deserialize, Serialize};
-use zipgraph_core::NodeId;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use zipgraph_core::{Graph, GraphStats, Node, NodeId, PathMetric};
 
 /// Query types supported by the optimizer
 #[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum Query {
     ShortestPath {
         start: NodeId,
         goal: NodeId,
+        /// What the path minimizes; omitted from the wire encoding when
+        /// it is the default `weight`
+        #[serde(default, skip_serializing_if = "PathMetric::is_weight")]
+        metric: PathMetric,
     },
     Neighbors {
         node: NodeId,
     },
     PageRank {
         iterations: usize,
+        /// Only return the highest-ranked `top_k` nodes
+        top_k: Option<usize>,
     },
     ConnectedComponents,
     ShortestPaths {
         start: NodeId,
     },
+    /// The `k` neighbors of `node` joined by the heaviest edges
+    TopKNeighborsByWeight {
+        node: NodeId,
+        k: usize,
+    },
+    /// Approximate distance from `a` to `b`, see `LandmarkIndex`
+    DistanceEstimate {
+        a: NodeId,
+        b: NodeId,
+    },
+    Degree {
+        node: NodeId,
+    },
+    GraphStats,
+    /// All nodes within `k` hops of `node`
+    KHopNeighbors {
+        node: NodeId,
+        k: usize,
+    },
+    /// The values of `keys` for each of `nodes`, in the order requested
+    NodeProperties {
+        nodes: Vec<NodeId>,
+        keys: Vec<String>,
+    },
+    NodeLabel {
+        node: NodeId,
+    },
+    /// Every node at most `max_distance` (by weight) from `node`
+    WithinDistance {
+        node: NodeId,
+        max_distance: OrderedF64,
+    },
+    /// Shortest weighted path that only uses edges lighter than
+    /// `max_edge_weight` and avoids nodes whose property exceeds a
+    /// threshold
+    ///
+    /// `excluded_property` is a `Node::properties` key and the threshold
+    /// above which a node is avoided; nodes without the property are kept.
+    /// Omitted filters are not applied.
+    FilteredShortestPath {
+        start: NodeId,
+        goal: NodeId,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_edge_weight: Option<OrderedF64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        excluded_property: Option<(String, OrderedF64)>,
+    },
+    /// `node`, everything within `radius` hops and the edges among them,
+    /// see `Graph::ego_network`
+    EgoNetwork {
+        node: NodeId,
+        radius: usize,
+    },
+    /// Parent and distance of every node reachable from `start`, by
+    /// weight or, unless `weighted`, by hops
+    ///
+    /// A tree can be as large as the graph; a cache partition of its own
+    /// (`QueryCacheConfig::with_partition`) keeps a few trees from
+    /// evicting many smaller results.
+    ShortestPathTree {
+        start: NodeId,
+        weighted: bool,
+    },
+}
+
+/// A number used as a query parameter, such as a distance bound
+///
+/// Compared and hashed by its bits so that queries can key the cache;
+/// encoded as a plain number.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct OrderedF64(pub f64);
+
+impl PartialEq for OrderedF64 {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for OrderedF64 {}
+
+impl std::hash::Hash for OrderedF64 {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
+}
+
+impl From<f64> for OrderedF64 {
+    fn from(value: f64) -> Self {
+        OrderedF64(value)
+    }
+}
+
+/// Pagination applied to list-shaped results after computation
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QueryOptions {
+    /// Maximum number of items to return
+    pub limit: Option<usize>,
+    /// Number of leading items to skip
+    pub offset: usize,
+}
+
+impl QueryOptions {
+    /// Return at most `limit` items
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Skip the first `offset` items
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+}
+
+/// Kind of a query, independent of its parameters
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub enum QueryKind {
+    ShortestPath,
+    Neighbors,
+    PageRank,
+    ConnectedComponents,
+    ShortestPaths,
+    TopKNeighborsByWeight,
+    DistanceEstimate,
+    Degree,
+    GraphStats,
+    KHopNeighbors,
+    NodeProperties,
+    NodeLabel,
+    WithinDistance,
+    FilteredShortestPath,
+    EgoNetwork,
+    ShortestPathTree,
+}
+
+impl QueryKind {
+    /// Name of the query kind
+    pub fn name(&self) -> &'static str {
+        match self {
+            QueryKind::ShortestPath => "ShortestPath",
+            QueryKind::Neighbors => "Neighbors",
+            QueryKind::PageRank => "PageRank",
+            QueryKind::ConnectedComponents => "ConnectedComponents",
+            QueryKind::ShortestPaths => "ShortestPaths",
+            QueryKind::TopKNeighborsByWeight => "TopKNeighborsByWeight",
+            QueryKind::DistanceEstimate => "DistanceEstimate",
+            QueryKind::Degree => "Degree",
+            QueryKind::GraphStats => "GraphStats",
+            QueryKind::KHopNeighbors => "KHopNeighbors",
+            QueryKind::NodeProperties => "NodeProperties",
+            QueryKind::NodeLabel => "NodeLabel",
+            QueryKind::WithinDistance => "WithinDistance",
+            QueryKind::FilteredShortestPath => "FilteredShortestPath",
+            QueryKind::EgoNetwork => "EgoNetwork",
+            QueryKind::ShortestPathTree => "ShortestPathTree",
+        }
+    }
 }
 
 /// Query result
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
 pub enum QueryResult {
     Path(Vec<NodeId>),
     Neighbors(Vec<NodeId>),
     Scores(Vec<(NodeId, f64)>),
     Components(Vec<Vec<NodeId>>),
+    Distance(f64),
+    Value(f64),
+    Stats(GraphStats),
+    /// Requested properties per node; keys a node does not have are absent
+    Properties(Vec<HashMap<String, f64>>),
+    Label(String),
+    /// Nodes and their distances, nearest first
+    Distances(Vec<(NodeId, f64)>),
+    /// No path exists between the queried nodes
+    NoPath,
+    Subgraph(Subgraph),
+    /// A shortest-path tree; `source` has a distance but no parent
+    Tree {
+        source: NodeId,
+        parents: HashMap<NodeId, NodeId>,
+        distances: HashMap<NodeId, f64>,
+    },
+}
+
+/// A subgraph as returned by a query, e.g. `Query::EgoNetwork`
+///
+/// Nodes keep their IDs in the queried graph and are listed in ascending
+/// ID order; edges are in insertion order. Node features and edge
+/// timestamps are dropped.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Subgraph {
+    pub directed: bool,
+    pub nodes: Vec<SubgraphNode>,
+    pub edges: Vec<SubgraphEdge>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubgraphNode {
+    pub id: NodeId,
+    pub label: String,
+    pub properties: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubgraphEdge {
+    pub from: NodeId,
+    pub to: NodeId,
+    pub weight: f64,
+    pub edge_type: String,
+}
+
+impl From<&Graph> for Subgraph {
+    fn from(graph: &Graph) -> Self {
+        let mut ids = graph.node_ids();
+        ids.sort_unstable();
+        let nodes = ids
+            .into_iter()
+            .filter_map(|id| graph.node(id).ok())
+            .map(|node| SubgraphNode {
+                id: node.id,
+                label: node.label.clone(),
+                properties: node.properties.iter().map(|(k, &v)| (k.clone(), v)).collect(),
+            })
+            .collect();
+        let edges = graph
+            .edges()
+            .iter()
+            .map(|edge| SubgraphEdge {
+                from: edge.from,
+                to: edge.to,
+                weight: edge.weight,
+                edge_type: edge.edge_type.clone(),
+            })
+            .collect();
+        Subgraph { directed: graph.is_directed(), nodes, edges }
+    }
+}
+
+impl Subgraph {
+    /// Rebuild the subgraph as a `Graph`
+    pub fn to_graph(&self) -> Graph {
+        let mut graph = if self.directed { Graph::new_directed() } else { Graph::new() };
+        for node in &self.nodes {
+            let mut copy = Node::new(node.id, node.label.clone());
+            copy.properties = node.properties.clone().into_iter().collect();
+            graph.add_node(copy);
+        }
+        for edge in &self.edges {
+            graph
+                .add_typed_edge(edge.from, edge.to, edge.weight, edge.edge_type.clone())
+                .expect("subgraph edges join subgraph nodes");
+        }
+        graph
+    }
+}
+
+impl QueryResult {
+    /// Whether this is a negative result (cached with a shorter TTL)
+    pub fn is_negative(&self) -> bool {
+        matches!(self, QueryResult::NoPath)
+    }
+
+    /// The edges of a `Tree` as `(parent, child, distance of child)`
+    ///
+    /// Sorted by distance, then child, so parents precede their children
+    /// and the list can be drawn in order. `None` for other results.
+    pub fn tree_to_edges(&self) -> Option<Vec<(NodeId, NodeId, f64)>> {
+        let QueryResult::Tree { parents, distances, .. } = self else {
+            return None;
+        };
+        let mut edges: Vec<(NodeId, NodeId, f64)> = parents
+            .iter()
+            .map(|(&child, &parent)| (parent, child, distances[&child]))
+            .collect();
+        edges.sort_by(|a, b| a.2.total_cmp(&b.2).then(a.1.cmp(&b.1)));
+        Some(edges)
+    }
+
+    /// Skip `offset` items and keep at most `limit` of the rest
+    ///
+    /// Applies to list-shaped results (neighbors, scores, components,
+    /// distances);
+    /// paths are returned whole since a partial path is not a path.
+    pub fn paginate(self, offset: usize, limit: Option<usize>) -> QueryResult {
+        fn page<T>(items: Vec<T>, offset: usize, limit: Option<usize>) -> Vec<T> {
+            items
+                .into_iter()
+                .skip(offset)
+                .take(limit.unwrap_or(usize::MAX))
+                .collect()
+        }
+
+        match self {
+            QueryResult::Neighbors(items) => QueryResult::Neighbors(page(items, offset, limit)),
+            QueryResult::Scores(items) => QueryResult::Scores(page(items, offset, limit)),
+            QueryResult::Components(items) => QueryResult::Components(page(items, offset, limit)),
+            QueryResult::Distances(items) => QueryResult::Distances(page(items, offset, limit)),
+            other => other,
+        }
+    }
 }
 
 impl Query {
+    /// Kind of this query, used to route it to a cache partition
+    pub fn kind(&self) -> QueryKind {
+        match self {
+            Query::ShortestPath { .. } => QueryKind::ShortestPath,
+            Query::Neighbors { .. } => QueryKind::Neighbors,
+            Query::PageRank { .. } => QueryKind::PageRank,
+            Query::ConnectedComponents => QueryKind::ConnectedComponents,
+            Query::ShortestPaths { .. } => QueryKind::ShortestPaths,
+            Query::TopKNeighborsByWeight { .. } => QueryKind::TopKNeighborsByWeight,
+            Query::DistanceEstimate { .. } => QueryKind::DistanceEstimate,
+            Query::Degree { .. } => QueryKind::Degree,
+            Query::GraphStats => QueryKind::GraphStats,
+            Query::KHopNeighbors { .. } => QueryKind::KHopNeighbors,
+            Query::NodeProperties { .. } => QueryKind::NodeProperties,
+            Query::NodeLabel { .. } => QueryKind::NodeLabel,
+            Query::WithinDistance { .. } => QueryKind::WithinDistance,
+            Query::FilteredShortestPath { .. } => QueryKind::FilteredShortestPath,
+            Query::EgoNetwork { .. } => QueryKind::EgoNetwork,
+            Query::ShortestPathTree { .. } => QueryKind::ShortestPathTree,
+        }
+    }
+
+    /// The query with its result-shaping parameters removed
+    ///
+    /// This is what the optimizer executes and caches.
+    /// `TopKNeighborsByWeight` is normalized to `k = usize::MAX`, i.e. all
+    /// neighbors.
+    pub fn unshaped(&self) -> Query {
+        match self {
+            Query::PageRank { iterations, .. } => Query::PageRank {
+                iterations: *iterations,
+                top_k: None,
+            },
+            Query::TopKNeighborsByWeight { node, .. } => Query::TopKNeighborsByWeight {
+                node: *node,
+                k: usize::MAX,
+            },
+            other => other.clone(),
+        }
+    }
+
+    /// Result limit implied by the query's own shaping parameters
+    pub fn result_limit(&self) -> Option<usize> {
+        match self {
+            Query::PageRank { top_k, .. } => *top_k,
+            Query::TopKNeighborsByWeight { k, .. } => Some(*k),
+            _ => None,
+        }
+    }
+
+    /// Name of the query variant, used to group cache statistics
+    pub fn type_name(&self) -> &'static str {
+        self.kind().name()
+    }
+
+    /// Parse the terse text form used by the CLI and REPL
+    ///
+    /// One query per line, keyword first:
+    ///
+    /// | Text                     | Query                     |
+    /// |--------------------------|---------------------------|
+    /// | `sp 0 -> 42`, `sp 0 -> 42 hops` | `ShortestPath`     |
+    /// | `neighbors 7`            | `Neighbors`               |
+    /// | `pagerank 20`, `pagerank 20 top 5` | `PageRank`      |
+    /// | `components`             | `ConnectedComponents`     |
+    /// | `paths 0`                | `ShortestPaths`           |
+    /// | `topk 7 3`               | `TopKNeighborsByWeight`   |
+    /// | `dist 0 -> 42`           | `DistanceEstimate`        |
+    /// | `degree 7`               | `Degree`                  |
+    /// | `stats`                  | `GraphStats`              |
+    /// | `khop 7 2`               | `KHopNeighbors`           |
+    /// | `props 0,1 score,rank`   | `NodeProperties`          |
+    /// | `label 7`                | `NodeLabel`               |
+    /// | `within 0 2.5`           | `WithinDistance`          |
+    /// | `fsp 0 -> 42 weight<100 risk>0.8` | `FilteredShortestPath` |
+    /// | `ego 7 2`                | `EgoNetwork`              |
+    /// | `tree 0 [hops]`          | `ShortestPathTree`        |
+    ///
+    /// Keywords are case-insensitive and the `->` arrow is optional. The
+    /// filters of `fsp` are optional and may come in either order:
+    /// `weight<X` sets the edge weight limit and `KEY>X` the excluded
+    /// property.
+    pub fn parse_str(input: &str) -> Result<Query> {
+        let tokens: Vec<&str> = input.split_whitespace().filter(|t| *t != "->").collect();
+        let Some((keyword, args)) = tokens.split_first() else {
+            return Err(parse_error(input, "empty query"));
+        };
+
+        let number = |index: usize| -> Result<usize> {
+            let token = args
+                .get(index)
+                .ok_or_else(|| parse_error(input, "missing argument"))?;
+            token
+                .parse()
+                .map_err(|_| parse_error(input, &format!("'{}' is not a number", token)))
+        };
+        let node = |index: usize| -> Result<NodeId> {
+            let token = args
+                .get(index)
+                .ok_or_else(|| parse_error(input, "missing argument"))?;
+            token
+                .parse()
+                .map_err(|_| parse_error(input, &format!("'{}' is not a node ID", token)))
+        };
+        let float = |token: &str| -> Result<OrderedF64> {
+            token
+                .parse()
+                .map(OrderedF64)
+                .map_err(|_| parse_error(input, &format!("'{}' is not a number", token)))
+        };
+        let arity = |expected: usize| -> Result<()> {
+            if args.len() == expected {
+                Ok(())
+            } else {
+                Err(parse_error(input, &format!("expected {} arguments", expected)))
+            }
+        };
+
+        let query = match keyword.to_ascii_lowercase().as_str() {
+            "sp" | "shortest_path" => {
+                let metric = match args.get(2) {
+                    None => PathMetric::Weight,
+                    Some(m) if args.len() == 3 && m.eq_ignore_ascii_case("weight") => {
+                        PathMetric::Weight
+                    }
+                    Some(m) if args.len() == 3 && m.eq_ignore_ascii_case("hops") => {
+                        PathMetric::Hops
+                    }
+                    Some(_) => return Err(parse_error(input, "expected 'sp A -> B [hops|weight]'")),
+                };
+                Query::ShortestPath { start: node(0)?, goal: node(1)?, metric }
+            }
+            "neighbors" => {
+                arity(1)?;
+                Query::Neighbors { node: node(0)? }
+            }
+            "pagerank" => {
+                let top_k = match args.len() {
+                    1 => None,
+                    3 if args[1].eq_ignore_ascii_case("top") => Some(number(2)?),
+                    _ => return Err(parse_error(input, "expected 'pagerank N [top K]'")),
+                };
+                Query::PageRank { iterations: number(0)?, top_k }
+            }
+            "components" => {
+                arity(0)?;
+                Query::ConnectedComponents
+            }
+            "paths" => {
+                arity(1)?;
+                Query::ShortestPaths { start: node(0)? }
+            }
+            "topk" => {
+                arity(2)?;
+                Query::TopKNeighborsByWeight { node: node(0)?, k: number(1)? }
+            }
+            "dist" | "distance" => {
+                arity(2)?;
+                Query::DistanceEstimate { a: node(0)?, b: node(1)? }
+            }
+            "degree" => {
+                arity(1)?;
+                Query::Degree { node: node(0)? }
+            }
+            "stats" => {
+                arity(0)?;
+                Query::GraphStats
+            }
+            "khop" => {
+                arity(2)?;
+                Query::KHopNeighbors { node: node(0)?, k: number(1)? }
+            }
+            "props" | "properties" => {
+                arity(2)?;
+                let nodes = args[0]
+                    .split(',')
+                    .map(|token| {
+                        token.parse().map_err(|_| {
+                            parse_error(input, &format!("'{}' is not a node ID", token))
+                        })
+                    })
+                    .collect::<Result<_>>()?;
+                let keys = args[1].split(',').map(str::to_string).collect();
+                Query::NodeProperties { nodes, keys }
+            }
+            "label" => {
+                arity(1)?;
+                Query::NodeLabel { node: node(0)? }
+            }
+            "tree" => {
+                let weighted = match args.get(1) {
+                    None => true,
+                    Some(m) if args.len() == 2 && m.eq_ignore_ascii_case("hops") => false,
+                    Some(_) => return Err(parse_error(input, "expected 'tree N [hops]'")),
+                };
+                Query::ShortestPathTree { start: node(0)?, weighted }
+            }
+            "ego" => {
+                arity(2)?;
+                Query::EgoNetwork { node: node(0)?, radius: number(1)? }
+            }
+            "within" => {
+                arity(2)?;
+                Query::WithinDistance { node: node(0)?, max_distance: float(args[1])? }
+            }
+            "fsp" | "filtered_shortest_path" => {
+                if !(2..=4).contains(&args.len()) {
+                    return Err(parse_error(input, "expected 'fsp A -> B [weight<X] [KEY>X]'"));
+                }
+                let mut max_edge_weight = None;
+                let mut excluded_property = None;
+                for filter in &args[2..] {
+                    if let Some(limit) = filter.strip_prefix("weight<") {
+                        max_edge_weight = Some(float(limit)?);
+                    } else if let Some((key, threshold)) = filter.split_once('>') {
+                        excluded_property = Some((key.to_string(), float(threshold)?));
+                    } else {
+                        return Err(parse_error(input, &format!("unknown filter '{}'", filter)));
+                    }
+                }
+                Query::FilteredShortestPath {
+                    start: node(0)?,
+                    goal: node(1)?,
+                    max_edge_weight,
+                    excluded_property,
+                }
+            }
+            other => return Err(parse_error(input, &format!("unknown query '{}'", other))),
+        };
+        Ok(query)
+    }
+
     /// Generate a fingerprint for caching
     pub fn fingerprint(&self) -> u64 {
         use std::collections::hash_map::DefaultHasher;
@@ -42,3 +599,159 @@ impl Query {
         hasher.finish()
     }
 }
+
+impl FromStr for Query {
+    type Err = OptimizerError;
+
+    fn from_str(input: &str) -> Result<Self> {
+        Query::parse_str(input)
+    }
+}
+
+/// Decode a query from its wire encoding, e.g.
+/// `{"type": "shortest_path", "start": 0, "goal": 5}`
+///
+/// Malformed JSON, unknown variants and missing fields surface as
+/// `OptimizerError::OptimizationFailed`.
+pub fn parse_query(json: &str) -> Result<Query> {
+    Ok(serde_json::from_str(json)?)
+}
+
+/// The wire encoding of `query`, which `parse_query` reads back
+///
+/// Members are emitted in declaration order with no whitespace, so a
+/// message already in that form is reproduced exactly.
+pub fn serialize_query(query: &Query) -> String {
+    serde_json::to_string(query).expect("queries always encode as JSON")
+}
+
+fn parse_error(input: &str, reason: &str) -> OptimizerError {
+    OptimizerError::ParseError(format!("{}: {}", reason, input.trim()))
+}
+
+#[cfg(all(test, not(feature = "uuid-ids")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_str() {
+        assert_eq!(
+            Query::parse_str("sp 0 -> 42").unwrap(),
+            Query::ShortestPath { start: 0, goal: 42, metric: PathMetric::Weight }
+        );
+        assert_eq!(
+            Query::parse_str("sp 0 42 HOPS").unwrap(),
+            Query::ShortestPath { start: 0, goal: 42, metric: PathMetric::Hops }
+        );
+        assert_eq!(Query::parse_str("neighbors 7").unwrap(), Query::Neighbors { node: 7 });
+        assert_eq!(
+            Query::parse_str("PageRank 20 top 5").unwrap(),
+            Query::PageRank { iterations: 20, top_k: Some(5) }
+        );
+        assert_eq!(
+            "khop 3 2".parse::<Query>().unwrap(),
+            Query::KHopNeighbors { node: 3, k: 2 }
+        );
+        assert_eq!(Query::parse_str("  stats ").unwrap(), Query::GraphStats);
+        assert_eq!(
+            Query::parse_str("props 0,3 score,rank").unwrap(),
+            Query::NodeProperties {
+                nodes: vec![0, 3],
+                keys: vec!["score".to_string(), "rank".to_string()],
+            }
+        );
+        assert_eq!(Query::parse_str("label 7").unwrap(), Query::NodeLabel { node: 7 });
+        assert_eq!(
+            Query::parse_str("within 0 2.5").unwrap(),
+            Query::WithinDistance { node: 0, max_distance: OrderedF64(2.5) }
+        );
+        assert!(Query::parse_str("within 0 far").is_err());
+        assert_eq!(Query::parse_str("ego 7 2").unwrap(), Query::EgoNetwork { node: 7, radius: 2 });
+        assert_eq!(
+            Query::parse_str("tree 3").unwrap(),
+            Query::ShortestPathTree { start: 3, weighted: true }
+        );
+        assert_eq!(
+            Query::parse_str("TREE 3 hops").unwrap(),
+            Query::ShortestPathTree { start: 3, weighted: false }
+        );
+        assert_eq!(
+            Query::parse_str("fsp 0 -> 42 risk>0.8 weight<100").unwrap(),
+            Query::FilteredShortestPath {
+                start: 0,
+                goal: 42,
+                max_edge_weight: Some(OrderedF64(100.0)),
+                excluded_property: Some(("risk".to_string(), OrderedF64(0.8))),
+            }
+        );
+        assert_eq!(
+            Query::parse_str("fsp 0 42").unwrap(),
+            Query::FilteredShortestPath {
+                start: 0,
+                goal: 42,
+                max_edge_weight: None,
+                excluded_property: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_str_errors() {
+        for input in [
+            "", "sp 0", "sp a -> 1", "sp 0 -> 1 miles", "sp 0 1 hops 2", "neighbors 1 2",
+            "pagerank 20 5", "props 0,x score", "teleport 3", "fsp 0 1 weight<x", "fsp 0 1 risk",
+            "ego 7", "ego 7 -1", "tree", "tree 3 miles", "tree 3 hops 2",
+        ] {
+            assert!(matches!(Query::parse_str(input), Err(OptimizerError::ParseError(_))));
+        }
+    }
+
+    #[test]
+    fn test_shortest_path_metric_encoding() {
+        let weight = Query::ShortestPath { start: 0, goal: 1, metric: PathMetric::Weight };
+        let json = serde_json::to_string(&weight).unwrap();
+        assert_eq!(json, r#"{"type":"shortest_path","start":0,"goal":1}"#);
+
+        let hops = Query::ShortestPath { start: 0, goal: 1, metric: PathMetric::Hops };
+        let json = serde_json::to_string(&hops).unwrap();
+        assert_eq!(json, r#"{"type":"shortest_path","start":0,"goal":1,"metric":"hops"}"#);
+        assert_eq!(serde_json::from_str::<Query>(&json).unwrap(), hops);
+        assert_ne!(hops.fingerprint(), weight.fingerprint());
+    }
+    #[test]
+    fn test_parse_and_serialize_query() {
+        let canonical = r#"{"type":"shortest_path","start":0,"goal":5}"#;
+        let query = parse_query(canonical).unwrap();
+        assert_eq!(query, Query::ShortestPath { start: 0, goal: 5, metric: PathMetric::Weight });
+        assert_eq!(serialize_query(&query), canonical);
+        assert_eq!(
+            parse_query(r#"{ "goal": 5, "type": "shortest_path", "start": 0, "metric": "hops" }"#)
+                .unwrap(),
+            Query::ShortestPath { start: 0, goal: 5, metric: PathMetric::Hops }
+        );
+
+        for canonical in [
+            r#"{"type":"page_rank","iterations":20,"top_k":5}"#,
+            r#"{"type":"k_hop_neighbors","node":3,"k":2}"#,
+            r#"{"type":"graph_stats"}"#,
+        ] {
+            assert_eq!(serialize_query(&parse_query(canonical).unwrap()), canonical);
+        }
+
+        for invalid in [
+            "",
+            "{",
+            r#"{"type":"teleport","node":1}"#,
+            r#"{"type":"shortest_path","start":0}"#,
+            r#"{"type":"degree","node":-1}"#,
+        ] {
+            assert!(
+                matches!(parse_query(invalid), Err(OptimizerError::OptimizationFailed(_))),
+                "{}",
+                invalid
+            );
+        }
+        let error: OptimizerError = "x".parse::<usize>().unwrap_err().into();
+        assert!(matches!(error, OptimizerError::OptimizationFailed(_)));
+    }
+}
@@ -0,0 +1,282 @@
+//! Priority scheduling of queries on background worker threads
+//!
+//! `QueryOptimizer::execute_with_priority` hands queries to a `Scheduler`
+//! owned by the optimizer. Queries wait in one queue per priority and
+//! free workers always take the oldest interactive query first. At most
+//! `background_slots` background queries run at a time, and there is one
+//! worker more than that, so an interactive query never waits for a
+//! background query to finish, only for other interactive ones. Running
+//! work is never cancelled.
+
+use crate::error::{OptimizerError, Result};
+use crate::query::QueryResult;
+use parking_lot::{Condvar, Mutex, MutexGuard};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Default number of background queries that may run at once
+pub const DEFAULT_BACKGROUND_SLOTS: usize = 2;
+
+/// Latencies kept per priority, or per prepared query, for the percentile
+/// statistics
+const LATENCY_WINDOW: usize = 1024;
+
+/// Scheduling class of a query sent with `execute_with_priority`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Priority {
+    /// Latency-sensitive, e.g. a user waiting on the answer
+    Interactive,
+    /// Throughput work such as analytics refreshes
+    Background,
+}
+
+/// Counters for the queries of one priority
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PriorityStats {
+    /// Queries completed
+    pub count: usize,
+    /// 95th percentile of the time from submission to completion, over the
+    /// most recent completed queries
+    pub p95_latency_ms: f64,
+    /// Queries waiting for a worker when the statistics were taken
+    pub queued: usize,
+}
+
+/// A query submitted with `execute_with_priority`
+#[must_use = "dropping the ticket discards the result"]
+pub struct QueryTicket {
+    receiver: Receiver<Result<QueryResult>>,
+}
+
+impl QueryTicket {
+    /// Wait for the query and return its result
+    pub fn wait(self) -> Result<QueryResult> {
+        self.receiver.recv().unwrap_or_else(|_| Err(abandoned()))
+    }
+
+    /// The result if the query has completed, without waiting
+    pub fn try_wait(&self) -> Option<Result<QueryResult>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(abandoned())),
+        }
+    }
+}
+
+fn abandoned() -> OptimizerError {
+    OptimizerError::ExecutionError("Scheduled query was abandoned by its worker".into())
+}
+
+type Job = Box<dyn FnOnce() -> Result<QueryResult> + Send>;
+
+struct Task {
+    job: Job,
+    submitted: Instant,
+    reply: mpsc::Sender<Result<QueryResult>>,
+}
+
+/// Completed-query latencies of one priority or prepared query
+#[derive(Default)]
+pub(crate) struct Latencies {
+    pub(crate) count: usize,
+    recent: VecDeque<Duration>,
+}
+
+impl Latencies {
+    pub(crate) fn record(&mut self, latency: Duration) {
+        self.count += 1;
+        if self.recent.len() == LATENCY_WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(latency);
+    }
+
+    pub(crate) fn p95_ms(&self) -> f64 {
+        let mut sorted: Vec<Duration> = self.recent.iter().copied().collect();
+        sorted.sort_unstable();
+        let index = (sorted.len() as f64 * 0.95) as usize;
+        sorted
+            .get(index.min(sorted.len().saturating_sub(1)))
+            .map_or(0.0, |latency| latency.as_secs_f64() * 1000.0)
+    }
+}
+
+#[derive(Default)]
+struct Queues {
+    interactive: VecDeque<Task>,
+    background: VecDeque<Task>,
+    running_background: usize,
+    shutdown: bool,
+    interactive_latencies: Latencies,
+    background_latencies: Latencies,
+}
+
+impl Queues {
+    fn queue(&mut self, priority: Priority) -> &mut VecDeque<Task> {
+        match priority {
+            Priority::Interactive => &mut self.interactive,
+            Priority::Background => &mut self.background,
+        }
+    }
+
+    fn latencies(&mut self, priority: Priority) -> &mut Latencies {
+        match priority {
+            Priority::Interactive => &mut self.interactive_latencies,
+            Priority::Background => &mut self.background_latencies,
+        }
+    }
+}
+
+struct Shared {
+    queues: Mutex<Queues>,
+    available: Condvar,
+    background_slots: usize,
+}
+
+/// Worker threads serving two priority queues, see the module
+/// documentation
+///
+/// Dropping the scheduler lets the workers finish the queued queries and
+/// exit; it does not wait for them.
+pub(crate) struct Scheduler {
+    shared: Arc<Shared>,
+}
+
+impl Scheduler {
+    /// Start `background_slots + 1` workers; at least one background slot
+    /// is kept so background queries always make progress
+    pub(crate) fn new(background_slots: usize) -> Self {
+        let background_slots = background_slots.max(1);
+        let shared = Arc::new(Shared {
+            queues: Mutex::new(Queues::default()),
+            available: Condvar::new(),
+            background_slots,
+        });
+        for _ in 0..=background_slots {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || work(&shared));
+        }
+        Self { shared }
+    }
+
+    /// Queue `job` and return the ticket its result is delivered to
+    pub(crate) fn submit(&self, priority: Priority, job: Job) -> QueryTicket {
+        let (reply, receiver) = mpsc::channel();
+        let task = Task { job, submitted: Instant::now(), reply };
+        self.shared.queues.lock().queue(priority).push_back(task);
+        self.shared.available.notify_all();
+        QueryTicket { receiver }
+    }
+
+    pub(crate) fn stats(&self, priority: Priority) -> PriorityStats {
+        let mut queues = self.shared.queues.lock();
+        let queued = queues.queue(priority).len();
+        let latencies = queues.latencies(priority);
+        PriorityStats { count: latencies.count, p95_latency_ms: latencies.p95_ms(), queued }
+    }
+}
+
+impl Drop for Scheduler {
+    fn drop(&mut self) {
+        self.shared.queues.lock().shutdown = true;
+        self.shared.available.notify_all();
+    }
+}
+
+/// Worker loop: interactive tasks first, background ones while a slot is
+/// free, until shut down with nothing left to run
+fn work(shared: &Shared) {
+    let mut queues = shared.queues.lock();
+    loop {
+        let next = if let Some(task) = queues.interactive.pop_front() {
+            Some((Priority::Interactive, task))
+        } else if queues.running_background < shared.background_slots {
+            queues.background.pop_front().map(|task| (Priority::Background, task))
+        } else {
+            None
+        };
+
+        let Some((priority, task)) = next else {
+            if queues.shutdown && queues.interactive.is_empty() && queues.background.is_empty() {
+                return;
+            }
+            shared.available.wait(&mut queues);
+            continue;
+        };
+
+        if priority == Priority::Background {
+            queues.running_background += 1;
+        }
+        // A panicking query fails its ticket instead of the worker
+        let job = AssertUnwindSafe(task.job);
+        let result = MutexGuard::unlocked(&mut queues, || panic::catch_unwind(job))
+            .unwrap_or_else(|_| Err(abandoned()));
+        let latency = task.submitted.elapsed();
+        if priority == Priority::Background {
+            queues.running_background -= 1;
+            shared.available.notify_all();
+        }
+        queues.latencies(priority).record(latency);
+        // The ticket may have been dropped
+        let _ = task.reply.send(result);
+    }
+}
+
+#[cfg(all(test, not(feature = "uuid-ids")))]
+mod tests {
+    use super::*;
+
+    fn sleeper(millis: u64) -> Job {
+        Box::new(move || {
+            thread::sleep(Duration::from_millis(millis));
+            Ok(QueryResult::Distance(millis as f64))
+        })
+    }
+
+    #[test]
+    fn test_background_slots_are_bounded() {
+        let scheduler = Scheduler::new(2);
+        let tickets: Vec<QueryTicket> =
+            (0..6).map(|_| scheduler.submit(Priority::Background, sleeper(40))).collect();
+        thread::sleep(Duration::from_millis(10));
+        // Two running, four waiting, even though a third worker is idle
+        assert_eq!(scheduler.stats(Priority::Background).queued, 4);
+
+        for ticket in tickets {
+            assert!(matches!(ticket.wait(), Ok(QueryResult::Distance(_))));
+        }
+        let stats = scheduler.stats(Priority::Background);
+        assert_eq!((stats.count, stats.queued), (6, 0));
+        assert!(stats.p95_latency_ms >= 40.0, "{:?}", stats);
+    }
+
+    #[test]
+    fn test_interactive_skips_the_background_queue() {
+        let scheduler = Scheduler::new(1);
+        let background: Vec<QueryTicket> =
+            (0..20).map(|_| scheduler.submit(Priority::Background, sleeper(50))).collect();
+        thread::sleep(Duration::from_millis(10));
+
+        let start = Instant::now();
+        let ticket = scheduler.submit(Priority::Interactive, sleeper(1));
+        assert!(ticket.wait().is_ok());
+        assert!(start.elapsed() < Duration::from_millis(500), "{:?}", start.elapsed());
+        assert!(scheduler.stats(Priority::Background).queued >= 15);
+        assert_eq!(scheduler.stats(Priority::Interactive).count, 1);
+
+        let error: Job = Box::new(|| Err(OptimizerError::ParseError("bad".into())));
+        assert!(scheduler.submit(Priority::Interactive, error).wait().is_err());
+        let panicking: Job = Box::new(|| panic!("query panicked"));
+        assert!(scheduler.submit(Priority::Interactive, panicking).wait().is_err());
+        assert!(scheduler.submit(Priority::Interactive, sleeper(1)).wait().is_ok());
+        // Queued work still completes after the scheduler is dropped
+        drop(scheduler);
+        assert!(background.into_iter().last().unwrap().wait().is_ok());
+    }
+}
@@ -0,0 +1,30 @@
+//! Named graphs registered with a `QueryOptimizer`
+//!
+//! One optimizer can serve several graphs. Each registered graph gets its
+//! own result cache and statistics so that identical queries on different
+//! graphs never share results, while graph-agnostic components such as the
+//! algorithm selector are shared.
+
+use crate::cache::QueryCache;
+use crate::optimizer::OptimizerStats;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use zipgraph_core::Graph;
+
+/// Identifier returned by `QueryOptimizer::register_graph`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct GraphHandle(u64);
+
+impl GraphHandle {
+    pub(crate) fn new(id: u64) -> Self {
+        Self(id)
+    }
+}
+
+/// A registered graph with its own cache and counters
+pub(crate) struct RegisteredGraph {
+    pub(crate) name: String,
+    pub(crate) graph: Arc<Graph>,
+    pub(crate) cache: QueryCache,
+    pub(crate) stats: OptimizerStats,
+}
@@ -0,0 +1,83 @@
+//! Standing queries that notify subscribers when their result changes
+//!
+//! Subscriptions are owned by a `QueryOptimizer`. After the graph is
+//! mutated, `QueryOptimizer::notify_graph_changed` re-executes every
+//! subscribed query and invokes the callbacks whose result differs from the
+//! one they were last given.
+//!
+//! Callbacks run while the optimizer is mutably borrowed and must not call
+//! back into it. In particular, a callback that locks a mutex guarding the
+//! same optimizer deadlocks; hand results off (e.g. over a channel) instead.
+
+use crate::query::{Query, QueryResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Default maximum number of subscriptions per optimizer
+pub const DEFAULT_MAX_SUBSCRIPTIONS: usize = 256;
+
+/// Callback invoked with the new result of a subscribed query
+pub type SubscriptionCallback = Box<dyn Fn(&QueryResult) + Send + Sync>;
+
+/// Identifier returned by `QueryOptimizer::subscribe`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct SubscriptionId(u64);
+
+/// A standing query and the last result delivered for it
+pub(crate) struct Subscription {
+    pub(crate) query: Query,
+    pub(crate) callback: SubscriptionCallback,
+    pub(crate) last_result: Option<QueryResult>,
+}
+
+/// Registered subscriptions, notified in subscription order
+pub(crate) struct Subscriptions {
+    entries: BTreeMap<SubscriptionId, Subscription>,
+    next_id: u64,
+    pub(crate) max_subscriptions: usize,
+}
+
+impl Subscriptions {
+    pub(crate) fn new(max_subscriptions: usize) -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            next_id: 0,
+            max_subscriptions,
+        }
+    }
+
+    /// Register a subscription, or return `None` if the cap is reached
+    pub(crate) fn insert(&mut self, query: Query, callback: SubscriptionCallback) -> Option<SubscriptionId> {
+        if self.entries.len() >= self.max_subscriptions {
+            return None;
+        }
+
+        let id = SubscriptionId(self.next_id);
+        self.next_id += 1;
+        self.entries.insert(
+            id,
+            Subscription {
+                query,
+                callback,
+                last_result: None,
+            },
+        );
+        Some(id)
+    }
+
+    pub(crate) fn remove(&mut self, id: SubscriptionId) -> bool {
+        self.entries.remove(&id).is_some()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub(crate) fn queries(&self) -> Vec<Query> {
+        self.entries.values().map(|s| s.query.clone()).collect()
+    }
+
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = &mut Subscription> {
+        self.entries.values_mut()
+    }
+}
@@ -0,0 +1,217 @@
+//! Wire compatibility against fixtures checked in at each protocol version
+//!
+//! Fixture messages are never edited once released: a failure here means
+//! the encoding changed and `PROTOCOL_VERSION` must be bumped, with new
+//! fixtures added next to the old ones. Query kinds added within a
+//! version append their messages to that version's fixtures. The version 0 fixtures were
+//! captured from the bare, externally tagged encoding used before
+//! envelopes existed.
+
+// The tests number nodes with integer literals
+#![cfg(not(feature = "uuid-ids"))]
+
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use zipgraph_core::{GraphStats, PathMetric};
+use zipgraph_optimizer::{
+    decode_legacy_query, decode_legacy_result, Query, QueryEnvelope, QueryKind, QueryResult,
+    ResponseEnvelope,
+};
+
+const V0_QUERIES: &str = include_str!("fixtures/protocol_v0_queries.json");
+const V0_RESULTS: &str = include_str!("fixtures/protocol_v0_results.json");
+const V1_QUERIES: &str = include_str!("fixtures/protocol_v1_queries.json");
+const V1_RESPONSES: &str = include_str!("fixtures/protocol_v1_responses.json");
+
+/// Leading entries of `expected_queries` and of the successful
+/// `expected_results` that existed in version 0
+const V0_QUERY_COUNT: usize = 11;
+const V0_RESULT_COUNT: usize = 8;
+
+fn fixture_messages(fixture: &str) -> Vec<Value> {
+    serde_json::from_str(fixture).unwrap()
+}
+
+fn expected_queries() -> Vec<Query> {
+    vec![
+        Query::ShortestPath { start: 0, goal: 42, metric: PathMetric::Weight },
+        Query::Neighbors { node: 7 },
+        Query::PageRank { iterations: 20, top_k: Some(5) },
+        Query::PageRank { iterations: 20, top_k: None },
+        Query::ConnectedComponents,
+        Query::ShortestPaths { start: 3 },
+        Query::TopKNeighborsByWeight { node: 7, k: 3 },
+        Query::DistanceEstimate { a: 0, b: 42 },
+        Query::Degree { node: 7 },
+        Query::GraphStats,
+        Query::KHopNeighbors { node: 7, k: 2 },
+        Query::NodeProperties { nodes: vec![0, 3], keys: vec!["score".to_string()] },
+        Query::NodeLabel { node: 7 },
+        Query::WithinDistance { node: 0, max_distance: 2.5.into() },
+        Query::FilteredShortestPath {
+            start: 0,
+            goal: 42,
+            max_edge_weight: Some(100.0.into()),
+            excluded_property: Some(("risk".to_string(), 0.8.into())),
+        },
+        Query::EgoNetwork { node: 7, radius: 2 },
+        Query::ShortestPathTree { start: 0, weighted: false },
+    ]
+}
+
+fn expected_results() -> Vec<Result<QueryResult, String>> {
+    vec![
+        Ok(QueryResult::Path(vec![0, 3, 42])),
+        Ok(QueryResult::Neighbors(vec![1, 4, 9])),
+        Ok(QueryResult::Scores(vec![(4, 0.5), (1, 0.25)])),
+        Ok(QueryResult::Components(vec![vec![0, 1, 2], vec![3]])),
+        Ok(QueryResult::Distance(2.5)),
+        Ok(QueryResult::Value(3.0)),
+        Ok(QueryResult::Stats(GraphStats {
+            node_count: 4,
+            edge_count: 3,
+            avg_degree: 1.5,
+            max_degree: 3,
+            min_degree: 1,
+            density: 0.5,
+            is_directed: false,
+            clustering_coefficient: Some(0.0),
+            diameter: Some(2),
+            average_shortest_path_length: None,
+            effective_diameter: None,
+            power_law_alpha: None,
+            motif_profile: None,
+        })),
+        Ok(QueryResult::NoPath),
+        Err("Graph error: Node 99 not found".to_string()),
+        Ok(QueryResult::Properties(vec![
+            HashMap::from([("score".to_string(), 0.5)]),
+            HashMap::new(),
+        ])),
+        Ok(QueryResult::Label("Node7".to_string())),
+        Ok(QueryResult::Distances(vec![(0, 0.0), (3, 1.5)])),
+        Ok(QueryResult::Tree {
+            source: 0,
+            parents: HashMap::from([(3, 0)]),
+            distances: HashMap::from([(0, 0.0), (3, 1.0)]),
+        }),
+    ]
+}
+
+#[test]
+fn test_v0_queries_decode() {
+    let messages = fixture_messages(V0_QUERIES);
+    // Query kinds added after version 0 have no version 0 encoding
+    assert_eq!(messages.len(), V0_QUERY_COUNT);
+
+    for (message, expected) in messages.iter().zip(expected_queries()) {
+        let json = message.to_string();
+        assert_eq!(decode_legacy_query(&json).unwrap(), expected);
+        // Version 1 is a wire break, see `protocol`
+        assert!(serde_json::from_str::<Query>(&json).is_err());
+    }
+}
+
+#[test]
+fn test_v0_results_decode() {
+    let messages = fixture_messages(V0_RESULTS);
+    let expected: Vec<QueryResult> =
+        expected_results().into_iter().filter_map(Result::ok).take(V0_RESULT_COUNT).collect();
+    assert_eq!(messages.len(), expected.len());
+
+    for (message, expected) in messages.iter().zip(expected) {
+        let json = message.to_string();
+        assert_eq!(decode_legacy_result(&json).unwrap(), expected);
+        assert!(serde_json::from_str::<QueryResult>(&json).is_err());
+    }
+}
+
+#[test]
+fn test_v1_queries_decode() {
+    let messages = fixture_messages(V1_QUERIES);
+    assert_eq!(messages.len(), expected_queries().len());
+
+    for (message, expected) in messages.iter().zip(expected_queries()) {
+        let envelope = QueryEnvelope::from_json(&message.to_string()).unwrap();
+        assert_eq!(envelope.protocol_version, 1);
+        assert_eq!(envelope.query, expected);
+    }
+}
+
+#[test]
+fn test_v1_queries_encode() {
+    for message in fixture_messages(V1_QUERIES) {
+        let envelope = QueryEnvelope::from_json(&message.to_string()).unwrap();
+        let encoded: Value = serde_json::from_str(&envelope.to_json().unwrap()).unwrap();
+        assert_eq!(encoded, message);
+    }
+}
+
+#[test]
+fn test_v1_responses_decode() {
+    let messages = fixture_messages(V1_RESPONSES);
+    assert_eq!(messages.len(), expected_results().len());
+
+    for (message, expected) in messages.iter().zip(expected_results()) {
+        let envelope = ResponseEnvelope::from_json(&message.to_string()).unwrap();
+        assert_eq!(envelope.protocol_version, 1);
+        assert_eq!(envelope.result, expected);
+    }
+}
+
+#[test]
+fn test_v1_responses_encode() {
+    for message in fixture_messages(V1_RESPONSES) {
+        let envelope = ResponseEnvelope::from_json(&message.to_string()).unwrap();
+        let encoded: Value = serde_json::from_str(&envelope.to_json().unwrap()).unwrap();
+        assert_eq!(encoded, message);
+    }
+}
+
+#[test]
+fn test_every_query_kind_has_a_fixture() {
+    let covered: HashSet<QueryKind> = expected_queries().iter().map(Query::kind).collect();
+
+    // Matching exhaustively makes a new variant fail to compile here
+    // until it is added to the list and given a fixture
+    let all_kinds = [
+        QueryKind::ShortestPath,
+        QueryKind::Neighbors,
+        QueryKind::PageRank,
+        QueryKind::ConnectedComponents,
+        QueryKind::ShortestPaths,
+        QueryKind::TopKNeighborsByWeight,
+        QueryKind::DistanceEstimate,
+        QueryKind::Degree,
+        QueryKind::GraphStats,
+        QueryKind::KHopNeighbors,
+        QueryKind::NodeProperties,
+        QueryKind::NodeLabel,
+        QueryKind::WithinDistance,
+        QueryKind::FilteredShortestPath,
+        QueryKind::EgoNetwork,
+        QueryKind::ShortestPathTree,
+    ];
+    for kind in all_kinds {
+        match kind {
+            QueryKind::ShortestPath
+            | QueryKind::Neighbors
+            | QueryKind::PageRank
+            | QueryKind::ConnectedComponents
+            | QueryKind::ShortestPaths
+            | QueryKind::TopKNeighborsByWeight
+            | QueryKind::DistanceEstimate
+            | QueryKind::Degree
+            | QueryKind::GraphStats
+            | QueryKind::KHopNeighbors
+            | QueryKind::NodeProperties
+            | QueryKind::NodeLabel
+            | QueryKind::WithinDistance
+            | QueryKind::FilteredShortestPath
+            | QueryKind::EgoNetwork
+            | QueryKind::ShortestPathTree => {}
+        }
+    }
+
+    assert_eq!(covered, all_kinds.into_iter().collect::<HashSet<_>>());
+}